@@ -0,0 +1,183 @@
+//! Album view for studio libraries.
+//!
+//! Studio recordings have no show date to hang off, so `show`, `shows` and
+//! `compare` have little to say about them. Albums take the show's place: a
+//! studio track's album comes from its album tag, albums are ranked against
+//! each other by a duration-weighted mean score (as chains are), and an
+//! album's own tracks can be ranked within it.
+
+use crate::db::Database;
+use crate::db::columns::{BAND_EXPR, NOT_GARBAGE, TRACK_SCORE_SELECT, map_track_score};
+use crate::db::models::TrackScore;
+use rusqlite::params;
+
+/// One studio album and its analyzed tracks, in running order.
+#[derive(Debug, Clone)]
+pub struct Album {
+    pub band: Option<String>,
+    pub title: String,
+    /// Earliest year among the tracks' dates, if they carry one.
+    pub year: Option<String>,
+    pub tracks: Vec<TrackScore>,
+}
+
+impl Album {
+    pub fn minutes(&self) -> f64 {
+        self.tracks.iter().map(|t| t.duration_min).sum()
+    }
+
+    /// Duration-weighted mean of a score over the album's tracks.
+    pub fn score(&self, column: &str) -> Option<f64> {
+        let (sum, weight) = self
+            .tracks
+            .iter()
+            .filter_map(|t| Some((t.score(column)?, t.duration_min.max(0.0))))
+            .fold((0.0, 0.0), |(s, w), (v, d)| (s + v * d, w + d));
+        (weight > 0.0).then(|| sum / weight)
+    }
+
+    /// The album's tracks, best first on `column`.
+    pub fn ranked_tracks(&self, column: &str) -> Vec<&TrackScore> {
+        let mut tracks: Vec<&TrackScore> = self.tracks.iter().collect();
+        tracks.sort_by(|a, b| {
+            let v = |t: &TrackScore| t.score(column).unwrap_or(f64::MIN);
+            v(b).total_cmp(&v(a))
+        });
+        tracks
+    }
+}
+
+/// Group tracks (in running order) into albums by band and album title.
+/// Tracks without an album are skipped.
+pub fn group_albums(tracks: Vec<TrackScore>) -> Vec<Album> {
+    let mut albums: Vec<Album> = Vec::new();
+    for track in tracks {
+        let Some(title) = track.album.clone() else {
+            continue;
+        };
+        let year = year_of(&track.date);
+        match albums
+            .iter_mut()
+            .find(|a| a.title == title && a.band == track.band)
+        {
+            Some(album) => {
+                if year.is_some() && (album.year.is_none() || year < album.year) {
+                    album.year = year;
+                }
+                album.tracks.push(track);
+            }
+            None => albums.push(Album {
+                band: track.band.clone(),
+                title,
+                year,
+                tracks: vec![track],
+            }),
+        }
+    }
+    albums
+}
+
+/// Leading four-digit year of a date ("1973-03-01", "1973"), if any.
+fn year_of(date: &str) -> Option<String> {
+    let year = date.get(..4)?;
+    year.bytes()
+        .all(|b| b.is_ascii_digit())
+        .then(|| year.to_string())
+}
+
+/// Studio albums, optionally for one band or matching an album title
+/// substring, ranked by the mean of `column`, best first.
+pub fn ranked_albums(
+    db: &Database,
+    column: &str,
+    band: Option<&str>,
+    album: Option<&str>,
+) -> crate::db::Result<Vec<Album>> {
+    let mut albums = group_albums(db.studio_tracks(band, album)?);
+    albums.sort_by(|a, b| {
+        let v = |x: &Album| x.score(column).unwrap_or(f64::MIN);
+        v(b).total_cmp(&v(a))
+    });
+    Ok(albums)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Analyzed studio tracks with an album tag, in album running order.
+    fn studio_tracks(
+        &self,
+        band: Option<&str>,
+        album: Option<&str>,
+    ) -> crate::db::Result<Vec<TrackScore>> {
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE t.recording_type = 'studio'
+               AND t.album IS NOT NULL AND t.album != ''
+               AND {NOT_GARBAGE}
+               AND (?1 IS NULL OR {BAND_EXPR} = ?1)
+               AND (?2 IS NULL OR t.album LIKE '%' || ?2 || '%')
+             ORDER BY {BAND_EXPR}, t.album,
+                      COALESCE(t.parsed_disc, t.disc_number, 1),
+                      COALESCE(t.parsed_track, t.track_number, 999), t.file_path"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let tracks = stmt
+            .query_map(params![band, album], map_track_score)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranked_albums() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format, title,
+                                     album, date, artist, track_number, recording_type)
+                 VALUES
+                    (1, '/m/a1.flac', 1, '0', 'flac', 'Intro', 'Aoxomoxoa', '1969',
+                     'Grateful Dead', 1, 'studio'),
+                    (2, '/m/a2.flac', 1, '0', 'flac', 'St. Stephen', 'Aoxomoxoa', '1969',
+                     'Grateful Dead', 2, 'studio'),
+                    (3, '/m/b1.flac', 1, '0', 'flac', 'Uncle John', 'Workingman''s Dead',
+                     '1970-06-14', 'Grateful Dead', 1, 'studio'),
+                    (4, '/m/live.flac', 1, '0', 'flac', 'Dark Star', 'Live/Dead', '1969',
+                     'Grateful Dead', 1, 'live'),
+                    (5, '/m/c1.flac', 1, '0', 'flac', 'Untagged', NULL, NULL,
+                     'Grateful Dead', 1, 'studio');
+                 INSERT INTO analysis_results (track_id, duration, groove_score)
+                 VALUES (1, 60, 20), (2, 240, 80), (3, 200, 60), (4, 1400, 99), (5, 100, 99);",
+            )
+            .unwrap();
+
+        let albums = ranked_albums(&db, "groove_score", None, None).unwrap();
+        let titles: Vec<&str> = albums.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["Aoxomoxoa", "Workingman's Dead"]);
+        // (20 × 1 + 80 × 4) / 5 minutes
+        assert!((albums[0].score("groove_score").unwrap() - 68.0).abs() < 1e-9);
+        assert_eq!(albums[0].minutes(), 5.0);
+        assert_eq!(albums[0].year.as_deref(), Some("1969"));
+        assert_eq!(albums[0].tracks[0].when(), "Aoxomoxoa");
+        assert_eq!(
+            albums[0].ranked_tracks("groove_score")[0].title,
+            "St. Stephen"
+        );
+
+        let albums = ranked_albums(&db, "groove_score", None, Some("working")).unwrap();
+        assert_eq!(albums.len(), 1);
+        assert_eq!(albums[0].year.as_deref(), Some("1970"));
+        assert!(
+            ranked_albums(&db, "groove_score", Some("Phish"), None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! What analysis costs, for `stats --performance`.
+//!
+//! Every track `analyze` stores also records its decode and analysis wall time,
+//! the length of audio it covered, the setbreak version, and how many workers
+//! ran alongside it, in `analysis_cost`. The realtime factor is seconds of audio
+//! per second of one worker's time; with the unanalyzed audio left in the
+//! library, it gives an estimate of what the next `analyze` run will take.
+
+use rusqlite::{OptionalExtension, params};
+
+use crate::db::Database;
+
+/// Wall time spent on one track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackCost {
+    pub track_id: i64,
+    /// Length of the audio covered (the whole track, for previews too).
+    pub audio_secs: Option<f64>,
+    pub decode_secs: f64,
+    /// Engine setup and run, boundaries, features and scores.
+    pub analysis_secs: f64,
+    pub preview: bool,
+    /// Analysis workers in the run.
+    pub jobs: usize,
+}
+
+/// Totals for one setbreak version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionCost {
+    pub version: String,
+    pub tracks: i64,
+    pub previews: i64,
+    pub audio_secs: f64,
+    pub decode_secs: f64,
+    pub analysis_secs: f64,
+    /// When the first and last of these tracks were analyzed.
+    pub first_at: String,
+    pub last_at: String,
+}
+
+impl VersionCost {
+    pub fn compute_secs(&self) -> f64 {
+        self.decode_secs + self.analysis_secs
+    }
+
+    pub fn realtime_factor(&self) -> Option<f64> {
+        realtime_factor(self.audio_secs, self.compute_secs())
+    }
+}
+
+/// One of the slowest tracks to analyze.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowTrack {
+    pub file_path: String,
+    pub audio_secs: Option<f64>,
+    pub decode_secs: f64,
+    pub analysis_secs: f64,
+    pub version: String,
+}
+
+impl SlowTrack {
+    pub fn compute_secs(&self) -> f64 {
+        self.decode_secs + self.analysis_secs
+    }
+}
+
+/// Audio seconds processed per second of one worker's time.
+pub fn realtime_factor(audio_secs: f64, compute_secs: f64) -> Option<f64> {
+    (audio_secs > 0.0 && compute_secs > 0.0).then(|| audio_secs / compute_secs)
+}
+
+/// Wall-clock hours to analyze `audio_secs` at `factor` with `jobs` workers.
+pub fn estimate_hours(audio_secs: f64, factor: f64, jobs: usize) -> f64 {
+    audio_secs / factor / jobs.max(1) as f64 / 3600.0
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Record what analyzing a track took, replacing any earlier record.
+    pub fn record_analysis_cost(&self, cost: &TrackCost) -> crate::db::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO analysis_cost
+                (track_id, version, preview, audio_secs, decode_secs, analysis_secs, jobs)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                cost.track_id,
+                env!("CARGO_PKG_VERSION"),
+                cost.preview,
+                cost.audio_secs,
+                cost.decode_secs,
+                cost.analysis_secs,
+                cost.jobs as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Totals per version, oldest version first.
+    pub fn analysis_cost_by_version(&self) -> crate::db::Result<Vec<VersionCost>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT version, COUNT(*), SUM(preview), COALESCE(SUM(audio_secs), 0.0),
+                    SUM(decode_secs), SUM(analysis_secs), MIN(recorded_at), MAX(recorded_at)
+             FROM analysis_cost
+             GROUP BY version
+             ORDER BY MIN(recorded_at), version",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(VersionCost {
+                    version: row.get(0)?,
+                    tracks: row.get(1)?,
+                    previews: row.get(2)?,
+                    audio_secs: row.get(3)?,
+                    decode_secs: row.get(4)?,
+                    analysis_secs: row.get(5)?,
+                    first_at: row.get(6)?,
+                    last_at: row.get(7)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// The `limit` tracks that took longest, slowest first.
+    pub fn slowest_analyses(&self, limit: usize) -> crate::db::Result<Vec<SlowTrack>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.file_path, c.audio_secs, c.decode_secs, c.analysis_secs, c.version
+             FROM analysis_cost c
+             JOIN tracks t ON t.id = c.track_id
+             ORDER BY c.decode_secs + c.analysis_secs DESC
+             LIMIT ?1",
+        )?;
+        let rows = stmt
+            .query_map([limit as i64], |row| {
+                Ok(SlowTrack {
+                    file_path: row.get(0)?,
+                    audio_secs: row.get(1)?,
+                    decode_secs: row.get(2)?,
+                    analysis_secs: row.get(3)?,
+                    version: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Tracks not analyzed yet and their length in seconds (where tags give one).
+    pub fn analysis_backlog(&self) -> crate::db::Result<(i64, f64)> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(t.duration_secs), 0.0)
+             FROM tracks t
+             WHERE NOT EXISTS (SELECT 1 FROM analysis_results a WHERE a.track_id = t.id)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?)
+    }
+
+    /// Workers used by the most recently recorded analysis, if any.
+    pub fn last_analysis_jobs(&self) -> crate::db::Result<Option<usize>> {
+        let jobs: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT jobs FROM analysis_cost ORDER BY recorded_at DESC, rowid DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(jobs.map(|j| j as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_summarize_costs() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format, duration_secs)
+                 VALUES (1, '/m/a.flac', 1, '0', 'flac', 600.0),
+                        (2, '/m/b.flac', 1, '0', 'flac', 1200.0),
+                        (3, '/m/c.flac', 1, '0', 'flac', 1800.0);
+                 INSERT INTO analysis_results (track_id) VALUES (1), (2);",
+            )
+            .unwrap();
+        let cost = |track_id, audio_secs, decode_secs, analysis_secs| TrackCost {
+            track_id,
+            audio_secs: Some(audio_secs),
+            decode_secs,
+            analysis_secs,
+            preview: false,
+            jobs: 4,
+        };
+        db.record_analysis_cost(&cost(1, 600.0, 5.0, 25.0)).unwrap();
+        db.record_analysis_cost(&cost(2, 1200.0, 10.0, 50.0))
+            .unwrap();
+        // A re-analysis replaces the track's record
+        db.record_analysis_cost(&cost(2, 1200.0, 10.0, 110.0))
+            .unwrap();
+
+        let versions = db.analysis_cost_by_version().unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(versions[0].tracks, 2);
+        assert_eq!(versions[0].compute_secs(), 150.0);
+        assert_eq!(versions[0].realtime_factor(), Some(12.0));
+
+        let slowest = db.slowest_analyses(1).unwrap();
+        assert_eq!(slowest[0].file_path, "/m/b.flac");
+        assert_eq!(slowest[0].compute_secs(), 120.0);
+
+        assert_eq!(db.analysis_backlog().unwrap(), (1, 1800.0));
+        assert_eq!(db.last_analysis_jobs().unwrap(), Some(4));
+        // 1800 s of audio at 12x realtime on 4 workers
+        assert!((estimate_hours(1800.0, 12.0, 4) - 37.5 / 3600.0).abs() < 1e-12);
+        assert_eq!(realtime_factor(0.0, 10.0), None);
+    }
+}
@@ -0,0 +1,143 @@
+//! Announcer detection: a voice talking over the music.
+//!
+//! Radio broadcasts and pre-FM tapes carry a DJ over intros, outros and the
+//! gaps between songs. Each second of audio is split into 20 ms frames and
+//! checked with two classic speech/music discriminators: the share of frames
+//! whose zero-crossing rate is well above the second's mean (unvoiced
+//! consonants between voiced syllables), and the share of frames well below
+//! its mean energy (the gaps between syllables). Sustained music keeps both
+//! low; speech on top of it raises both. Only runs of several talking seconds
+//! count, since a drum break or a tuning riff can pass for a second of speech.
+//!
+//! `announcer_ratio` is the share of the track's seconds that count, so a
+//! broadcast recording can be ranked below a clean one of the same night.
+
+/// Analysis frame, seconds.
+const FRAME_SECS: f32 = 0.02;
+/// Frames per decision window (one second).
+const WINDOW_FRAMES: usize = 50;
+/// A frame crosses zero often when its rate exceeds the window mean by this factor.
+const HIGH_ZCR_FACTOR: f32 = 1.5;
+/// A frame is quiet below this fraction of the window's mean energy.
+const LOW_ENERGY_FACTOR: f32 = 0.5;
+/// Share of high-ZCR frames from which a window sounds like speech.
+const MIN_HIGH_ZCR_RATIO: f64 = 0.1;
+/// Share of quiet frames from which a window sounds like speech.
+const MIN_LOW_ENERGY_RATIO: f64 = 0.15;
+/// Mean frame energy below which a window is silence, not talk (about -60 dBFS).
+const SILENCE_ENERGY: f32 = 1e-6;
+/// Talking windows only count in runs at least this long.
+const MIN_RUN_WINDOWS: usize = 3;
+
+/// Tracks talked over for at least this share of their length count as having
+/// an announcer (see `db::columns::ANNOUNCER_TALK`).
+pub const ANNOUNCER_RATIO: f64 = 0.1;
+
+/// Share (0-1) of the audio's seconds with a voice over the music, or `None`
+/// for audio shorter than one window.
+pub fn detect(mono: &[f32], sample_rate: f32) -> Option<f64> {
+    let frame_len = ((sample_rate * FRAME_SECS) as usize).max(1);
+    let frames: Vec<(f32, f32)> = mono.chunks_exact(frame_len).map(frame_stats).collect();
+    let talking: Vec<bool> = frames
+        .chunks_exact(WINDOW_FRAMES)
+        .map(sounds_like_speech)
+        .collect();
+    if talking.is_empty() {
+        return None;
+    }
+
+    let mut counted = 0;
+    let mut run = 0;
+    for &talk in talking.iter().chain([false].iter()) {
+        if talk {
+            run += 1;
+        } else {
+            if run >= MIN_RUN_WINDOWS {
+                counted += run;
+            }
+            run = 0;
+        }
+    }
+    Some(counted as f64 / talking.len() as f64)
+}
+
+/// (mean energy, zero-crossing rate) of one frame.
+fn frame_stats(frame: &[f32]) -> (f32, f32) {
+    let energy = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+    let crossings = frame
+        .windows(2)
+        .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+        .count();
+    (energy, crossings as f32 / frame.len() as f32)
+}
+
+fn sounds_like_speech(window: &[(f32, f32)]) -> bool {
+    let n = window.len() as f32;
+    let mean_energy = window.iter().map(|f| f.0).sum::<f32>() / n;
+    if mean_energy < SILENCE_ENERGY {
+        return false;
+    }
+    let mean_zcr = window.iter().map(|f| f.1).sum::<f32>() / n;
+    let share = |hit: &dyn Fn(&(f32, f32)) -> bool| {
+        window.iter().filter(|f| hit(f)).count() as f64 / window.len() as f64
+    };
+    share(&|f| f.1 > mean_zcr * HIGH_ZCR_FACTOR) >= MIN_HIGH_ZCR_RATIO
+        && share(&|f| f.0 < mean_energy * LOW_ENERGY_FACTOR) >= MIN_LOW_ENERGY_RATIO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: f32 = 8000.0;
+
+    /// A steady chord: no gaps, no bursts of crossings.
+    fn chord(secs: f32) -> Vec<f32> {
+        (0..(secs * SR) as usize)
+            .map(|i| {
+                let t = i as f32 / SR;
+                0.3 * (2.0 * std::f32::consts::PI * 220.0 * t).sin()
+                    + 0.2 * (2.0 * std::f32::consts::PI * 330.0 * t).sin()
+            })
+            .collect()
+    }
+
+    /// Four syllables a second: a voiced 150 Hz burst, then a quiet hiss.
+    fn talk(secs: f32) -> Vec<f32> {
+        let mut seed = 12345u32;
+        (0..(secs * SR) as usize)
+            .map(|i| {
+                let t = i as f32 / SR;
+                if t % 0.25 < 0.15 {
+                    0.5 * (2.0 * std::f32::consts::PI * 150.0 * t).sin()
+                } else {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    ((seed >> 16) as f32 / 32768.0 - 1.0) * 0.05
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_talk_over_music() {
+        assert_eq!(detect(&chord(20.0), SR), Some(0.0));
+        assert_eq!(detect(&vec![0.0; 20 * SR as usize], SR), Some(0.0));
+        assert_eq!(detect(&chord(0.5), SR), None);
+
+        // 5 seconds of DJ over the intro of a 20-second clip
+        let mut clip: Vec<f32> = talk(5.0)
+            .iter()
+            .zip(chord(5.0))
+            .map(|(v, m)| v + m * 0.1)
+            .collect();
+        clip.extend(chord(15.0));
+        let ratio = detect(&clip, SR).unwrap();
+        assert!((ratio - 0.25).abs() < 0.051, "ratio {ratio}");
+
+        // A lone talking second is not an announcement
+        let mut blip = chord(9.0);
+        blip.extend(talk(1.0));
+        blip.extend(chord(10.0));
+        assert_eq!(detect(&blip, SR), Some(0.0));
+    }
+}
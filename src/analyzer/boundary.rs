@@ -7,8 +7,6 @@
 //! The core signal is silence: a segued track has music sustaining through
 //! its tail, while a clean break fades to crowd noise or silence.
 
-use ferrous_waves::AudioFile;
-
 /// Duration of tail region to analyze (seconds).
 const TAIL_DURATION_SECS: f32 = 3.0;
 
@@ -41,13 +39,11 @@ pub struct BoundaryFeatures {
     pub head_silence_pct: f64,
 }
 
-/// Extract boundary features from a decoded audio file.
+/// Extract boundary features from mono samples.
 ///
-/// Converts to mono, slices the first/last N seconds, and computes
-/// RMS energy + silence percentage in short windows.
-pub fn extract_from_audio(audio: &AudioFile) -> BoundaryFeatures {
-    let mono = audio.buffer.to_mono();
-    let sr = audio.buffer.sample_rate as f32;
+/// Slices the first/last N seconds and computes RMS energy + silence
+/// percentage in short windows.
+pub fn extract(mono: &[f32], sr: f32) -> BoundaryFeatures {
     let window_samples = (sr * WINDOW_SECS) as usize;
 
     let tail_samples = (sr * TAIL_DURATION_SECS) as usize;
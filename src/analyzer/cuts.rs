@@ -0,0 +1,183 @@
+//! Tape flip / splice detection inside a track.
+//!
+//! Taper sources often hide discontinuities mid-track: a tape flip during a
+//! long jam, or a splice patching two sources together. These show up as an
+//! *instant* energy collapse — music at full level in one 20ms window, gone in
+//! the next — coinciding with a waveform discontinuity (a sample-to-sample jump
+//! far larger than the music's normal slope). A band stopping on a hit decays
+//! through room reverb and never jumps, so it passes both tests.
+//!
+//! The first and last few seconds are ignored: track boundaries are expected
+//! to start and stop abruptly.
+
+use ferrous_waves::AudioFile;
+
+/// Analysis window length (seconds).
+const WINDOW_SECS: f32 = 0.02;
+
+/// Windows averaged before a candidate boundary (200ms of "music playing").
+const PRE_WINDOWS: usize = 10;
+
+/// Windows averaged after a candidate boundary (100ms of "after the cut").
+const POST_WINDOWS: usize = 5;
+
+/// Pre-boundary level must be at least this loud to count as music (dBFS).
+const MUSIC_FLOOR_DB: f64 = -35.0;
+
+/// Minimum level drop across a single window boundary (dB).
+const COLLAPSE_DB: f64 = 20.0;
+
+/// Minimum ratio of the boundary's largest sample jump to the mean
+/// sample-to-sample change in the preceding music.
+const DISCONTINUITY_RATIO: f64 = 6.0;
+
+/// Region at each end of the track where cuts are not reported (seconds).
+const EDGE_SECS: f32 = 5.0;
+
+/// After a detection, skip this long before looking for the next one (seconds).
+const MIN_GAP_SECS: f32 = 2.0;
+
+/// A detected discontinuity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CutPoint {
+    /// Position of the cut in seconds.
+    pub time: f64,
+    /// Level drop across the boundary (dB).
+    pub drop_db: f64,
+    /// Boundary jump relative to the music's mean sample-to-sample change.
+    pub discontinuity: f64,
+}
+
+/// Detect tape flips and splices in a decoded audio file.
+pub fn detect_from_audio(audio: &AudioFile) -> Vec<CutPoint> {
+    let mono = audio.buffer.to_mono();
+    detect_cuts(&mono, audio.buffer.sample_rate as f32)
+}
+
+/// Detect tape flips and splices in mono samples.
+pub fn detect_cuts(samples: &[f32], sample_rate: f32) -> Vec<CutPoint> {
+    let window = (sample_rate * WINDOW_SECS) as usize;
+    if window == 0 {
+        return Vec::new();
+    }
+    let powers: Vec<f64> = samples.chunks_exact(window).map(mean_power).collect();
+
+    let edge = (EDGE_SECS / WINDOW_SECS) as usize;
+    let gap = (MIN_GAP_SECS / WINDOW_SECS) as usize;
+    let first = edge.max(PRE_WINDOWS);
+    let last = powers.len().saturating_sub(edge.max(POST_WINDOWS));
+
+    let mut cuts = Vec::new();
+    let mut w = first;
+    while w < last {
+        let pre_db = power_db(mean(&powers[w - PRE_WINDOWS..w]));
+        let post_db = power_db(mean(&powers[w..w + POST_WINDOWS]));
+        let drop_db = pre_db - post_db;
+
+        if pre_db >= MUSIC_FLOOR_DB && drop_db >= COLLAPSE_DB {
+            let pre = &samples[(w - PRE_WINDOWS) * window..w * window];
+            // The splice can land anywhere in the windows either side of the boundary
+            let around = &samples[(w - 1) * window..(w + 1) * window];
+            let discontinuity = max_abs_diff(around) / mean_abs_diff(pre).max(1e-9);
+            if discontinuity >= DISCONTINUITY_RATIO {
+                cuts.push(CutPoint {
+                    time: (w * window) as f64 / sample_rate as f64,
+                    drop_db,
+                    discontinuity,
+                });
+                w += gap;
+                continue;
+            }
+        }
+        w += 1;
+    }
+    cuts
+}
+
+fn mean_power(samples: &[f32]) -> f64 {
+    samples
+        .iter()
+        .map(|&s| (s as f64) * (s as f64))
+        .sum::<f64>()
+        / samples.len() as f64
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn power_db(power: f64) -> f64 {
+    if power < 1e-20 {
+        -100.0
+    } else {
+        10.0 * power.log10()
+    }
+}
+
+fn mean_abs_diff(samples: &[f32]) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let total: f64 = samples
+        .windows(2)
+        .map(|p| (p[1] as f64 - p[0] as f64).abs())
+        .sum();
+    total / (samples.len() - 1) as f64
+}
+
+fn max_abs_diff(samples: &[f32]) -> f64 {
+    samples
+        .windows(2)
+        .map(|p| (p[1] as f64 - p[0] as f64).abs())
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: f32 = 44100.0;
+
+    fn sine(secs: f32, freq: f32, amp: f32) -> Vec<f32> {
+        (0..(secs * SR) as usize)
+            .map(|i| amp * (2.0 * std::f32::consts::PI * freq * i as f32 / SR).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_hard_cut_to_silence() {
+        // 441Hz has an exact 100-sample period: stop the music on a crest
+        let mut samples = sine(10.0, 441.0, 0.5);
+        samples.truncate(samples.len() - 75);
+        let cut_at = samples.len() as f64 / SR as f64;
+        samples.extend(sine(10.0, 60.0, 0.001));
+
+        let cuts = detect_cuts(&samples, SR);
+        assert_eq!(cuts.len(), 1, "{cuts:?}");
+        assert!((cuts[0].time - cut_at).abs() < 0.05, "{cuts:?}");
+        assert!(cuts[0].drop_db > 40.0);
+    }
+
+    #[test]
+    fn test_ignores_fast_decay() {
+        // Collapses just as hard, but as a 2ms decay: the waveform never jumps
+        let mut samples = sine(10.0, 440.0, 0.5);
+        let start = samples.len();
+        samples.extend((0..(10.0 * SR) as usize).map(|i| {
+            let t = (start + i) as f32 / SR;
+            let env = (-(i as f32) / (0.002 * SR)).exp();
+            0.5 * env * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+        }));
+        assert!(detect_cuts(&samples, SR).is_empty());
+    }
+
+    #[test]
+    fn test_ignores_continuous_music_and_edges() {
+        assert!(detect_cuts(&sine(20.0, 440.0, 0.5), SR).is_empty());
+
+        // A hard stop in the last seconds is just the end of the track
+        let mut samples = sine(18.0, 440.0, 0.5);
+        samples.extend(vec![0.0; (2.0 * SR) as usize]);
+        assert!(detect_cuts(&samples, SR).is_empty());
+    }
+}
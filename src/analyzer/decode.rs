@@ -199,16 +199,10 @@ fn load_via_ffmpeg(path: &Path) -> Result<AudioFile, DecodeError> {
     let tmp_wav = tmp_dir.join(format!("setbreak_decode_{}_{}.wav", std::process::id(), id));
 
     let output = Command::new("ffmpeg")
-        .args([
-            "-i",
-            &path.to_string_lossy(),
-            "-f",
-            "wav",
-            "-acodec",
-            "pcm_s16le",
-            "-y",
-            &tmp_wav.to_string_lossy(),
-        ])
+        .arg("-i")
+        .arg(path)
+        .args(["-f", "wav", "-acodec", "pcm_s16le", "-y"])
+        .arg(&tmp_wav)
         .output()?;
 
     if !output.status.success() {
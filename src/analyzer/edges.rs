@@ -0,0 +1,134 @@
+//! Segue edges: what a track sounds like as it starts and as it ends.
+//!
+//! A segue works when the end of one song could run into the start of the
+//! next — the same key centre, a tempo the band can move between, a similar
+//! level and brightness. `extract` summarizes the first and last `EDGE_SECS`
+//! of a track from the per-second frames song splitting already computes
+//! (chroma, level, zero-crossing rate), plus a tempo from the onsets in each
+//! window when the analysis found them. `segue_match` compares one track's
+//! tail with other tracks' heads.
+
+use super::{songsplit, tempo};
+
+/// Length of each edge, seconds.
+pub const EDGE_SECS: f64 = 60.0;
+
+/// Tracks shorter than this have no separate head and tail.
+const MIN_TRACK_SECS: f64 = 20.0;
+
+/// Summary of one edge of a track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    /// Pitch-class profile, loudness-weighted over the edge, summing to 1.
+    pub chroma: [f64; 12],
+    /// Mean level, dBFS.
+    pub level_db: f64,
+    /// Mean zero-crossing rate of the decimated signal, a brightness proxy.
+    pub brightness: f64,
+    /// Tempo of the edge, BPM; None without onsets (boundary backfill) or
+    /// when they show no steady pulse.
+    pub tempo: Option<f64>,
+}
+
+/// A track's opening and closing edges. They overlap on tracks shorter than
+/// two edges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edges {
+    pub head: Edge,
+    pub tail: Edge,
+}
+
+/// Summarize the first and last `EDGE_SECS` of mono samples. `onsets` are
+/// onset times in seconds from the start of the track (empty if unknown).
+pub fn extract(mono: &[f32], sr: f32, onsets: &[f32]) -> Option<Edges> {
+    let duration = mono.len() as f64 / sr as f64;
+    if duration < MIN_TRACK_SECS {
+        return None;
+    }
+    let len = ((EDGE_SECS * sr as f64) as usize).min(mono.len());
+    let tail_start = duration - len as f64 / sr as f64;
+    Some(Edges {
+        head: edge(&mono[..len], sr, window(onsets, 0.0, EDGE_SECS))?,
+        tail: edge(
+            &mono[mono.len() - len..],
+            sr,
+            window(onsets, tail_start, duration),
+        )?,
+    })
+}
+
+fn edge(samples: &[f32], sr: f32, onsets: Vec<f32>) -> Option<Edge> {
+    let frames = songsplit::frames(samples, sr);
+    if frames.is_empty() {
+        return None;
+    }
+    let n = frames.len() as f64;
+    // Weight chroma by frame power, so a quiet count-in or fade-out doesn't
+    // pull the profile toward noise
+    let power: Vec<f64> = frames
+        .iter()
+        .map(|f| 10f64.powf(f.level_db / 10.0))
+        .collect();
+    let total: f64 = power.iter().sum();
+    let mut chroma = [0.0; 12];
+    for (f, p) in frames.iter().zip(&power) {
+        let weight = if total > 0.0 { p / total } else { 1.0 / n };
+        for (c, v) in chroma.iter_mut().zip(&f.chroma) {
+            *c += v * weight;
+        }
+    }
+    Some(Edge {
+        chroma,
+        level_db: frames.iter().map(|f| f.level_db).sum::<f64>() / n,
+        brightness: frames.iter().map(|f| f.zcr).sum::<f64>() / n,
+        tempo: tempo::estimate(&onsets),
+    })
+}
+
+/// Onsets between `start` and `end`, relative to `start`.
+fn window(onsets: &[f32], start: f64, end: f64) -> Vec<f32> {
+    onsets
+        .iter()
+        .filter(|&&t| (start..end).contains(&(t as f64)))
+        .map(|&t| (t as f64 - start) as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f32, secs: f32, sr: f32, amp: f32) -> Vec<f32> {
+        (0..(secs * sr) as usize)
+            .map(|i| amp * (std::f32::consts::TAU * freq * i as f32 / sr).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_head_and_tail_differ() {
+        let sr = 22_050.0;
+        // A (440 Hz) for two minutes, then E (329.6 Hz), quieter, for two
+        let mut mono = tone(440.0, 120.0, sr, 0.5);
+        mono.extend(tone(329.63, 120.0, sr, 0.1));
+        let beat = |bpm: f64, from: f64, to: f64| {
+            let period = 60.0 / bpm;
+            (0..((to - from) / period) as usize).map(move |i| (from + i as f64 * period) as f32)
+        };
+        let onsets: Vec<f32> = beat(120.0, 0.0, 120.0)
+            .chain(beat(90.0, 120.0, 240.0))
+            .collect();
+
+        let e = extract(&mono, sr, &onsets).unwrap();
+        let peak = |c: &[f64; 12]| (0..12).max_by(|&a, &b| c[a].total_cmp(&c[b])).unwrap();
+        assert_eq!(peak(&e.head.chroma), 9);
+        assert_eq!(peak(&e.tail.chroma), 4);
+        assert!((e.head.chroma.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(e.head.level_db > e.tail.level_db + 10.0);
+        assert!((e.head.tempo.unwrap() - 120.0).abs() < 1.5);
+        assert!((e.tail.tempo.unwrap() - 90.0).abs() < 1.5);
+
+        let unknown = extract(&mono, sr, &[]).unwrap();
+        assert_eq!(unknown.head.tempo, None);
+        assert!(extract(&mono[..sr as usize * 10], sr, &[]).is_none());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::db::models::{
-    ChordEvent, NewAnalysis, SegmentRecord, TensionPointRecord, TransitionRecord,
+    ChordEvent, CutPointRecord, NewAnalysis, SegmentRecord, TensionPointRecord, TransitionRecord,
 };
 use ferrous_waves::analysis::engine::AnalysisResult;
 use ferrous_waves::analysis::pitch::PitchFrame;
@@ -12,6 +12,8 @@ pub struct ExtractionResult {
     pub segments: Vec<SegmentRecord>,
     pub tension_points: Vec<TensionPointRecord>,
     pub transitions: Vec<TransitionRecord>,
+    /// Tape flip / splice points, detected from raw audio by the analyzer.
+    pub cuts: Vec<CutPointRecord>,
 }
 
 /// Extract all available features from a ferrous-waves AnalysisResult.
@@ -204,8 +206,29 @@ pub fn extract(track_id: i64, r: &AnalysisResult) -> ExtractionResult {
 
     // Classification
     let classification_music_score = Some(r.classification.scores.music as f64);
+    let classification_speech_score = Some(r.classification.scores.speech as f64);
     let hnr = Some(r.classification.features.hnr as f64);
 
+    // Loudness of the music alone, for calibrating single-file sets
+    let windowed_loudness = compute_windowed_loudness(&r.perceptual.short_term_loudness);
+
+    // Half/double-time correction of the beat tracker's tempo
+    let tempo = r
+        .temporal
+        .tempo
+        .and_then(|t| super::tempo::correct(t as f64, &r.temporal.onsets));
+
+    // Octave errors folded out of the confident pitch frames
+    let pitch = super::pitch::correct(
+        &r.pitch
+            .pitch_track
+            .frames
+            .iter()
+            .filter(|f| f.confidence > 0.5)
+            .filter_map(|f| f.frequency.map(|hz| hz as f64))
+            .collect::<Vec<_>>(),
+    );
+
     let analysis = NewAnalysis {
         track_id,
 
@@ -267,6 +290,8 @@ pub fn extract(track_id: i64, r: &AnalysisResult) -> ExtractionResult {
 
         // Temporal
         tempo_bpm: r.temporal.tempo.map(|t| t as f64),
+        tempo_bpm_corrected: tempo.map(|t| t.bpm),
+        tempo_confidence: tempo.map(|t| t.confidence),
         beat_count: Some(r.temporal.beats.len() as i32),
         onset_count: Some(r.temporal.onsets.len() as i32),
         tempo_stability: Some(r.temporal.tempo_stability as f64),
@@ -281,6 +306,10 @@ pub fn extract(track_id: i64, r: &AnalysisResult) -> ExtractionResult {
         vibrato_presence,
         vibrato_rate,
         pitch_confidence_mean,
+        mean_pitch_corrected: pitch.map(|p| p.mean_hz),
+        pitch_range_low_corrected: pitch.map(|p| p.low_hz),
+        pitch_range_high_corrected: pitch.map(|p| p.high_hz),
+        pitch_octave_errors: pitch.map(|p| p.octave_errors),
 
         // Perceptual
         lufs_integrated: Some(r.perceptual.loudness_lufs as f64),
@@ -306,6 +335,9 @@ pub fn extract(track_id: i64, r: &AnalysisResult) -> ExtractionResult {
                 Some(ml.iter().cloned().fold(f32::NEG_INFINITY, f32::max) as f64)
             }
         },
+        lufs_music: windowed_loudness.map(|w| w.music),
+        lufs_window_p10: windowed_loudness.map(|w| w.p10),
+        lufs_window_p90: windowed_loudness.map(|w| w.p90),
 
         // Per-frame derived features
         spectral_flux_skewness: compute_skewness(&r.spectral.spectral_flux),
@@ -517,6 +549,9 @@ pub fn extract(track_id: i64, r: &AnalysisResult) -> ExtractionResult {
 
         // Classification
         classification_music_score,
+        classification_speech_score,
+        // Set from raw audio in analyze_decoded_track
+        announcer_ratio: None,
         hnr,
 
         // Emotion scores — computed by jam_metrics
@@ -561,6 +596,7 @@ pub fn extract(track_id: i64, r: &AnalysisResult) -> ExtractionResult {
         spectral_novelty_std: Some(r.spectral.spectral_novelty_std as f64),
         groove_stability_mean: Some(r.spectral.groove_stability_mean as f64),
         groove_stability_std: Some(r.spectral.groove_stability_std as f64),
+        preview: false,
     };
 
     ExtractionResult {
@@ -569,6 +605,7 @@ pub fn extract(track_id: i64, r: &AnalysisResult) -> ExtractionResult {
         segments: segment_records,
         tension_points: tension_records,
         transitions: transition_records,
+        cuts: Vec::new(),
     }
 }
 
@@ -770,6 +807,45 @@ fn compute_linear_slope_f32(values: &[f32]) -> Option<f64> {
     Some((n * sum_xy - sum_x * sum_y) / denom)
 }
 
+/// Short-term windows this far below the track's loud end (its 90th
+/// percentile) are banter, tuning, or dead air rather than music.
+const MUSIC_GATE_LU: f64 = 12.0;
+
+/// Distribution of short-term loudness, and the loudness of the music alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WindowedLoudness {
+    /// Power-mean of the windows within `MUSIC_GATE_LU` of the 90th percentile.
+    music: f64,
+    p10: f64,
+    p90: f64,
+}
+
+/// Whole-file integrated LUFS of a single-file set is dragged down by the
+/// quiet stretches between songs; gating windows relative to the loud end
+/// keeps only the playing.
+fn compute_windowed_loudness(short_term: &[f32]) -> Option<WindowedLoudness> {
+    // Silence frames produce -inf; below -70 LUFS is the BS.1770 absolute gate
+    let mut windows: Vec<f64> = short_term
+        .iter()
+        .map(|&v| v as f64)
+        .filter(|v| v.is_finite() && *v > -70.0)
+        .collect();
+    if windows.len() < 3 {
+        return None;
+    }
+    windows.sort_by(f64::total_cmp);
+    let percentile = |p: f64| windows[((windows.len() - 1) as f64 * p).round() as usize];
+    let (p10, p90) = (percentile(0.1), percentile(0.9));
+    let gate = p90 - MUSIC_GATE_LU;
+    let powers: Vec<f64> = windows
+        .iter()
+        .filter(|&&v| v >= gate)
+        .map(|v| 10f64.powf(v / 10.0))
+        .collect();
+    let music = 10.0 * (powers.iter().sum::<f64>() / powers.len() as f64).log10();
+    Some(WindowedLoudness { music, p10, p90 })
+}
+
 /// Ratio of energy in the last third vs first third of the track.
 /// >1.0 = track builds in energy, <1.0 = track decays.
 fn compute_buildup_ratio(values: &[f32]) -> Option<f64> {
@@ -1166,3 +1242,24 @@ fn compute_section_diversity(
         Some(total_dist / count as f64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windowed_loudness_gates_banter() {
+        // Ten minutes of music at -18 LUFS, then four of banter at -40 and silence
+        let mut windows = vec![-18.0f32; 200];
+        windows.extend(std::iter::repeat_n(-40.0, 80));
+        windows.extend(std::iter::repeat_n(f32::NEG_INFINITY, 20));
+        let w = compute_windowed_loudness(&windows).unwrap();
+        assert!((w.music + 18.0).abs() < 1e-9);
+        assert_eq!((w.p10, w.p90), (-40.0, -18.0));
+
+        // A uniformly quiet tape is quiet music, not banter
+        let w = compute_windowed_loudness(&[-30.0; 50]).unwrap();
+        assert!((w.music + 30.0).abs() < 1e-9);
+        assert_eq!(compute_windowed_loudness(&[f32::NEG_INFINITY; 10]), None);
+    }
+}
@@ -1,10 +1,173 @@
 use crate::db::models::NewAnalysis;
+#[cfg(feature = "analysis")]
 use ferrous_waves::analysis::engine::AnalysisResult;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+/// Version of the score formulas. Bump on any change to how a score is
+/// computed from stored features, so `refresh` rescores the library.
+pub const FORMULA_VERSION: i64 = 2;
+
+/// The ten jam scores (0-100) for one track.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Scores {
+    pub energy: f64,
+    pub intensity: f64,
+    pub groove: f64,
+    pub improvisation: f64,
+    pub tightness: f64,
+    pub build_quality: f64,
+    pub exploratory: f64,
+    pub transcendence: f64,
+    pub valence: f64,
+    pub arousal: f64,
+}
+
+impl Scores {
+    /// Write the scores into an analysis row's `*_score` fields.
+    pub fn apply(&self, analysis: &mut NewAnalysis) {
+        analysis.energy_score = Some(self.energy);
+        analysis.intensity_score = Some(self.intensity);
+        analysis.groove_score = Some(self.groove);
+        analysis.improvisation_score = Some(self.improvisation);
+        analysis.tightness_score = Some(self.tightness);
+        analysis.build_quality_score = Some(self.build_quality);
+        analysis.exploratory_score = Some(self.exploratory);
+        analysis.transcendence_score = Some(self.transcendence);
+        analysis.valence_score = Some(self.valence);
+        analysis.arousal_score = Some(self.arousal);
+    }
+
+    /// The score with this CLI name (`build_quality`, `groove`, ...).
+    fn get_mut(&mut self, name: &str) -> Option<&mut f64> {
+        Some(match name {
+            "energy" => &mut self.energy,
+            "intensity" => &mut self.intensity,
+            "groove" => &mut self.groove,
+            "improvisation" => &mut self.improvisation,
+            "tightness" => &mut self.tightness,
+            "build_quality" => &mut self.build_quality,
+            "exploratory" => &mut self.exploratory,
+            "transcendence" => &mut self.transcendence,
+            "valence" => &mut self.valence,
+            "arousal" => &mut self.arousal,
+            _ => return None,
+        })
+    }
+
+    fn from_analysis(a: &NewAnalysis) -> Self {
+        Self {
+            energy: a.energy_score.unwrap_or(0.0),
+            intensity: a.intensity_score.unwrap_or(0.0),
+            groove: a.groove_score.unwrap_or(0.0),
+            improvisation: a.improvisation_score.unwrap_or(0.0),
+            tightness: a.tightness_score.unwrap_or(0.0),
+            build_quality: a.build_quality_score.unwrap_or(0.0),
+            exploratory: a.exploratory_score.unwrap_or(0.0),
+            transcendence: a.transcendence_score.unwrap_or(0.0),
+            valence: a.valence_score.unwrap_or(0.0),
+            arousal: a.arousal_score.unwrap_or(0.0),
+        }
+    }
+}
+
+// ── Per-band adjustments ──────────────────────────────────────────────
+// The formulas' normalization ranges were fit to Grateful Dead tapes. A band
+// whose dynamics sit elsewhere (Built to Spill's dense guitar wall reads as
+// low groove) can declare where its own scores typically fall, p5..p95, and
+// that range is stretched linearly onto the score's library-wide healthy
+// range. Scores that a gate forced to 0 (no onsets, too short) stay 0.
+
+/// Raw p5..p95 ranges by score name, for one band.
+pub type BandRanges = BTreeMap<String, [f64; 2]>;
+
+/// Per-band score remaps from the `[band_scoring.<band>]` config sections.
+#[derive(Debug, Clone, Default)]
+pub struct BandAdjustments {
+    /// Lowercased canonical band name → score name → raw range.
+    bands: HashMap<String, HashMap<&'static str, (f64, f64)>>,
+}
+
+static BAND_ADJUSTMENTS: OnceLock<BandAdjustments> = OnceLock::new();
+
+/// Install the per-band adjustments used by every scoring path. Call once at
+/// startup, after `bands::init` (band keys resolve through the registry).
+pub fn init_band_adjustments(adjustments: BandAdjustments) {
+    let _ = BAND_ADJUSTMENTS.set(adjustments);
+}
+
+/// The installed per-band adjustments, if any were configured.
+pub fn band_adjustments() -> Option<&'static BandAdjustments> {
+    BAND_ADJUSTMENTS.get().filter(|a| !a.is_empty())
+}
+
+impl BandAdjustments {
+    /// Build from config, resolving band codes to canonical names and
+    /// rejecting unknown scores or empty/out-of-range intervals.
+    pub fn from_config(config: &BTreeMap<String, BandRanges>) -> Result<Self, String> {
+        let mut bands = HashMap::new();
+        for (band, ranges) in config {
+            let mut remaps = HashMap::new();
+            for (score, &[lo, hi]) in ranges {
+                let meta = crate::scores::get(score)
+                    .ok_or_else(|| format!("band_scoring.{band}: unknown score '{score}'"))?;
+                if !(0.0..=100.0).contains(&lo) || !(0.0..=100.0).contains(&hi) || lo >= hi {
+                    return Err(format!(
+                        "band_scoring.{band}.{score}: need 0 <= low < high <= 100, got [{lo}, {hi}]"
+                    ));
+                }
+                remaps.insert(meta.name, (lo, hi));
+            }
+            let name = crate::bands::registry().resolve_canonical_name(band);
+            bands.insert(name.to_lowercase(), remaps);
+        }
+        Ok(Self { bands })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bands.is_empty()
+    }
+
+    /// The configured raw range for a band's score, if any.
+    pub fn range(&self, band: &str, score: &str) -> Option<(f64, f64)> {
+        self.bands.get(&band.to_lowercase())?.get(score).copied()
+    }
+
+    /// Remap `scores` for a track by `band` (its `parsed_band`).
+    pub fn apply(&self, band: Option<&str>, scores: &mut Scores) {
+        let Some(remaps) = band.and_then(|b| self.bands.get(&b.to_lowercase())) else {
+            return;
+        };
+        for (&name, &(lo, hi)) in remaps {
+            let (Some(value), Some(meta)) = (scores.get_mut(name), crate::scores::get(name)) else {
+                continue;
+            };
+            if *value <= 0.0 {
+                continue;
+            }
+            let (to_lo, to_hi) = meta.healthy_range;
+            *value = (to_lo + (*value - lo) / (hi - lo) * (to_hi - to_lo)).clamp(0.0, 100.0);
+        }
+    }
+}
+
+/// Apply the installed per-band adjustments to an analysis row's scores.
+/// A no-op when none are configured (or `init_band_adjustments` wasn't called).
+pub fn adjust_for_band(analysis: &mut NewAnalysis, band: Option<&str>) {
+    let Some(adjustments) = band_adjustments() else {
+        return;
+    };
+    let mut scores = Scores::from_analysis(analysis);
+    adjustments.apply(band, &mut scores);
+    scores.apply(analysis);
+}
 
 /// Compute all jam-specific derived scores (0-100) and attach them to the analysis.
 ///
 /// During initial analysis, extracts segment energies directly from the AnalysisResult
 /// so the build quality score uses segment data even before segments are stored in DB.
+#[cfg(feature = "analysis")]
 pub fn compute_jam_scores(analysis: &mut NewAnalysis, result: &AnalysisResult) {
     // Extract (start_time, energy) pairs from raw analysis segments
     let segment_energies: Vec<(f64, f64)> = result
@@ -27,16 +190,32 @@ pub fn compute_jam_scores_from_scalars(
     analysis: &mut NewAnalysis,
     segment_energies: Option<&[(f64, f64)]>,
 ) {
-    analysis.energy_score = Some(energy_score(analysis));
-    analysis.intensity_score = Some(intensity_score(analysis));
-    analysis.groove_score = Some(groove_score(analysis));
-    analysis.improvisation_score = Some(improvisation_score(analysis));
-    analysis.tightness_score = Some(tightness_score(analysis));
-    analysis.build_quality_score = Some(build_quality_score(analysis, segment_energies));
-    analysis.exploratory_score = Some(exploratory_score(analysis));
-    analysis.transcendence_score = Some(transcendence_score(analysis));
-    analysis.valence_score = Some(valence_score(analysis));
-    analysis.arousal_score = Some(arousal_score(analysis));
+    score(analysis, segment_energies).apply(analysis);
+}
+
+/// Compute the jam scores for features extracted elsewhere, without touching
+/// the analysis or the database. Only the score inputs need to be set (see
+/// [`NewAnalysis::builder`]); missing features fall back to neutral defaults.
+pub fn score(analysis: &NewAnalysis, segment_energies: Option<&[(f64, f64)]>) -> Scores {
+    let build_quality = build_quality_score(analysis, segment_energies);
+    Scores {
+        energy: energy_score(analysis),
+        intensity: intensity_score(analysis),
+        groove: groove_score(analysis),
+        improvisation: improvisation_score(analysis),
+        tightness: tightness_score(analysis),
+        build_quality,
+        exploratory: exploratory_score(analysis),
+        transcendence: transcendence_score(analysis, build_quality),
+        valence: valence_score(analysis),
+        arousal: arousal_score(analysis),
+    }
+}
+
+/// Score many feature sets in parallel, in input order. Build quality uses the
+/// scalar fallback, since there's no segment data.
+pub fn score_batch(analyses: &[NewAnalysis]) -> Vec<Scores> {
+    analyses.par_iter().map(|a| score(a, None)).collect()
 }
 
 // ── Energy Score (0-100) ──────────────────────────────────────────────
@@ -586,6 +765,11 @@ fn build_quality_from_segments(energies: &[(f64, f64)], duration: f64) -> f64 {
 // harmonic non-repetition, tonal ambiguity. NO key_change_count (temporal
 // feature → improvisation). NO dynamics_entropy (shared signal).
 // Duration gate only — breadth doesn't inherently require length.
+//
+// v7: Added melodic range (10 pts, from key alternatives and section
+// diversity). The raw pitch_range_high follows harmonics the tracker jumped
+// to, so it measured distortion more than melody; the octave-corrected range
+// (see `analyzer::pitch`) is usable.
 fn exploratory_score(a: &NewAnalysis) -> f64 {
     let duration = a.duration.unwrap_or(0.0);
 
@@ -632,22 +816,37 @@ fn exploratory_score(a: &NewAnalysis) -> f64 {
     let css_norm = ((0.50 - css_bw) / 0.35).clamp(0.0, 1.0);
     let css_contrib = css_norm * 15.0;
 
-    // 5. Key alternatives count (15 pts): tonal ambiguity / polytonality
+    // 5. Key alternatives count (10 pts): tonal ambiguity / polytonality
     // r=0.34 for exp, near 0 for imp. More plausible keys = more tonally ambiguous.
     // Library: 0-12+, avg ~4. Map: 2 → 0.0, 10 → 1.0
     let key_alts = a.key_alternatives_count.unwrap_or(0) as f64;
     let ka_norm = ((key_alts - 2.0) / 8.0).clamp(0.0, 1.0);
-    let ka_contrib = ka_norm * 15.0;
+    let ka_contrib = ka_norm * 10.0;
 
-    // 6. Section diversity (15 pts): structural variety in the track
+    // 6. Section diversity (10 pts): structural variety in the track
     // r=0.38 for exp, present in v5. Library: 0-1.0, avg ~0.4.
     let section_div = a.section_diversity_score.unwrap_or(0.35);
     let section_norm = section_div.clamp(0.0, 1.0);
-    let section_contrib = section_norm * 15.0;
-
-    let raw =
-        (chord_contrib + chroma_contrib + oi_contrib + css_contrib + ka_contrib + section_contrib)
-            .clamp(0.0, 100.0);
+    let section_contrib = section_norm * 10.0;
+
+    // 7. Melodic range (10 pts): octaves between the 5th and 95th percentile
+    // of the octave-corrected pitch. Rows analyzed before the correction get
+    // the midpoint. Map: 1 octave → 0.0, 3 octaves → 1.0
+    let melodic_octaves = match (a.pitch_range_low_corrected, a.pitch_range_high_corrected) {
+        (Some(low), Some(high)) if low > 0.0 && high >= low => (high / low).log2(),
+        _ => 2.0,
+    };
+    let melodic_norm = ((melodic_octaves - 1.0) / 2.0).clamp(0.0, 1.0);
+    let melodic_contrib = melodic_norm * 10.0;
+
+    let raw = (chord_contrib
+        + chroma_contrib
+        + oi_contrib
+        + css_contrib
+        + ka_contrib
+        + section_contrib
+        + melodic_contrib)
+        .clamp(0.0, 100.0);
     (raw * duration_gate).clamp(0.0, 100.0)
 }
 
@@ -659,7 +858,7 @@ fn exploratory_score(a: &NewAnalysis) -> f64 {
 // Star 98th pctl, Portland Dark Star 94th, Cornell Morning Dew 92nd). Combined
 // with groove×energy synergy and dynamic arc features.
 // Duration gate raised to 120s with ramp to 360s.
-fn transcendence_score(a: &NewAnalysis) -> f64 {
+fn transcendence_score(a: &NewAnalysis, build: f64) -> f64 {
     let duration = a.duration.unwrap_or(0.0);
     if duration < 120.0 {
         return 0.0;
@@ -674,7 +873,6 @@ fn transcendence_score(a: &NewAnalysis) -> f64 {
     // 1. Build quality (25 pts): the proven indicator of legendary jams
     // Uses segment-level arc detection which correctly identifies canonical jams.
     // build_quality_score is already 0-100, normalize.
-    let build = a.build_quality_score.unwrap_or(0.0);
     let build_norm = (build / 85.0).clamp(0.0, 1.0);
     let build_contrib = build_norm * 25.0;
 
@@ -788,7 +986,8 @@ fn arousal_score(a: &NewAnalysis) -> f64 {
 
     // Tempo component (15 pts): faster tempo = more aroused
     // Library: 30-313, avg 124, p50≈115. Map: 60 → 0.0, 180 → 1.0
-    let bpm = a.tempo_bpm.unwrap_or(100.0);
+    // Octave-corrected when available: a 95 BPM jam misread as 190 isn't frantic.
+    let bpm = a.tempo_bpm_corrected.or(a.tempo_bpm).unwrap_or(100.0);
     let tempo_norm = ((bpm - 60.0) / 120.0).clamp(0.0, 1.0);
     let tempo_contrib = tempo_norm * 15.0;
 
@@ -868,6 +1067,8 @@ mod tests {
             mfcc_12_mean: None,
             mfcc_12_std: None,
             tempo_bpm: Some(120.0),
+            tempo_bpm_corrected: None,
+            tempo_confidence: None,
             beat_count: Some(600),
             onset_count: Some(1200),
             tempo_stability: Some(0.8),
@@ -880,6 +1081,10 @@ mod tests {
             vibrato_presence: None,
             vibrato_rate: None,
             pitch_confidence_mean: Some(0.7),
+            mean_pitch_corrected: None,
+            pitch_range_low_corrected: None,
+            pitch_range_high_corrected: None,
+            pitch_octave_errors: None,
             lufs_integrated: Some(-14.0),
             loudness_range: Some(10.0),
             true_peak_dbfs: Some(-1.0),
@@ -916,9 +1121,14 @@ mod tests {
             solo_section_ratio: Some(0.3),
             transition_count: Some(4),
             classification_music_score: Some(0.95),
+            classification_speech_score: None,
+            announcer_ratio: None,
             hnr: Some(12.0),
             loudness_std: None,
             peak_loudness: None,
+            lufs_music: None,
+            lufs_window_p10: None,
+            lufs_window_p90: None,
             spectral_flux_skewness: None,
             spectral_centroid_slope: None,
             energy_buildup_ratio: None,
@@ -1027,6 +1237,7 @@ mod tests {
             spectral_novelty_std: None,
             groove_stability_mean: None,
             groove_stability_std: None,
+            preview: false,
         }
     }
 
@@ -1052,6 +1263,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_score_batch_matches_stored_scores() {
+        let built = NewAnalysis::builder()
+            .duration(900.0)
+            .rms_level(0.3)
+            .lufs_integrated(-18.0)
+            .tempo_bpm(118.0)
+            .beat_count(1700)
+            .spectral_flux_mean(10.0)
+            .build();
+        let batch = score_batch(&[base_analysis(), built]);
+        assert_eq!(batch.len(), 2);
+
+        let mut a = base_analysis();
+        compute_jam_scores_from_scalars(&mut a, None);
+        assert_eq!(Some(batch[0].transcendence), a.transcendence_score);
+        assert_eq!(Some(batch[0].groove), a.groove_score);
+        assert!((0.0..=100.0).contains(&batch[1].energy));
+    }
+
     #[test]
     fn test_silence_scores_low() {
         let mut a = base_analysis();
@@ -1248,4 +1479,50 @@ mod tests {
             "middle should be blended"
         );
     }
+
+    #[test]
+    fn test_band_adjustments() {
+        crate::bands::init_default();
+        let config: BTreeMap<String, BandRanges> = [(
+            "bts".to_string(),
+            [("groove".to_string(), [10.0, 60.0])].into(),
+        )]
+        .into();
+        let adj = BandAdjustments::from_config(&config).unwrap();
+        assert_eq!(adj.range("Built to Spill", "groove"), Some((10.0, 60.0)));
+
+        // Groove's healthy range is 15-85: the band's 10-60 stretches onto it
+        let mut s = Scores {
+            groove: 35.0,
+            energy: 40.0,
+            ..Default::default()
+        };
+        adj.apply(Some("Built to Spill"), &mut s);
+        assert!((s.groove - 50.0).abs() < 1e-9, "{s:?}");
+        assert_eq!(s.energy, 40.0);
+
+        // Other bands and gated zeros are untouched
+        let mut gd = Scores {
+            groove: 35.0,
+            ..Default::default()
+        };
+        adj.apply(Some("Grateful Dead"), &mut gd);
+        assert_eq!(gd.groove, 35.0);
+        let mut silent = Scores::default();
+        adj.apply(Some("Built to Spill"), &mut silent);
+        assert_eq!(silent.groove, 0.0);
+
+        let bad: BTreeMap<String, BandRanges> = [(
+            "bts".to_string(),
+            [("vibes".to_string(), [0.0, 50.0])].into(),
+        )]
+        .into();
+        assert!(BandAdjustments::from_config(&bad).is_err());
+        let empty: BTreeMap<String, BandRanges> = [(
+            "bts".to_string(),
+            [("groove".to_string(), [60.0, 10.0])].into(),
+        )]
+        .into();
+        assert!(BandAdjustments::from_config(&empty).is_err());
+    }
 }
@@ -1,29 +1,96 @@
+pub mod announcer;
 pub mod boundary;
+#[cfg(feature = "analysis")]
+pub mod cuts;
+#[cfg(feature = "analysis")]
 pub mod decode;
+pub mod edges;
+#[cfg(feature = "analysis")]
 pub mod features;
 pub mod jam_metrics;
+// Only the config half is used without the engine
+#[cfg_attr(not(feature = "analysis"), allow(dead_code))]
+pub mod pipeline;
+pub mod pitch;
+#[cfg(feature = "analysis")]
+pub mod preview;
+pub mod priority;
+pub mod songsplit;
+pub mod tempo;
+pub mod throttle;
+#[cfg(feature = "analysis")]
+pub mod trace;
 
 use crate::db::Database;
-use crate::db::models::Track;
-use features::ExtractionResult;
-use ferrous_waves::analysis::engine::{AnalysisConfig, AnalysisResult};
-use indicatif::{ProgressBar, ProgressStyle};
-use std::path::Path;
+use crate::progress::Progress;
 use thiserror::Error;
+#[cfg(feature = "analysis")]
+use {
+    crate::artifacts::{Artifact, ArtifactKind},
+    crate::config::{AnalysisTuning, TrackTuning},
+    crate::db::models::{CutPointRecord, Track},
+    features::ExtractionResult,
+    ferrous_waves::analysis::engine::{AnalysisConfig, AnalysisResult},
+    pipeline::PipelineConfig,
+    std::cell::RefCell,
+    std::path::Path,
+    std::time::{Duration, Instant},
+};
 
 #[derive(Error, Debug)]
 pub enum AnalyzeError {
+    #[cfg(feature = "analysis")]
     #[error("Decode error: {0}")]
     Decode(#[from] decode::DecodeError),
     #[error("Analysis engine error: {0}")]
     Engine(String),
     #[error("Database error: {0}")]
     Db(#[from] crate::db::DbError),
+    #[error("Remote source error: {0}")]
+    Remote(#[from] crate::remote::RemoteError),
 }
 
+/// Which tracks `analyze_tracks` picks and how much of each it analyzes.
+#[cfg(feature = "analysis")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalyzeMode {
+    /// Tracks without a full, current analysis (previews included).
+    Missing,
+    /// Every track, re-analyzed in full.
+    Force,
+    /// Tracks without any current analysis, scored from excerpts (see [`preview`]).
+    Preview,
+}
+
+#[cfg(feature = "analysis")]
 pub struct AnalyzeResult {
     pub analyzed: u64,
     pub failed: u64,
+    pub engine_setup: EngineSetup,
+}
+
+/// How often the analysis workers built an engine during a run, and what that
+/// cost. Each worker builds one for its first track and reuses it after that.
+#[cfg(feature = "analysis")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EngineSetup {
+    pub builds: u64,
+    pub reuses: u64,
+    /// Total time spent building engines.
+    pub build_secs: f64,
+}
+
+#[cfg(feature = "analysis")]
+impl EngineSetup {
+    /// Mean time to build one engine.
+    pub fn mean_build_secs(&self) -> Option<f64> {
+        (self.builds > 0).then(|| self.build_secs / self.builds as f64)
+    }
+
+    /// Time the reused engines would have cost to build, at the mean build time.
+    pub fn saved_secs(&self) -> f64 {
+        self.mean_build_secs().unwrap_or(0.0) * self.reuses as f64
+    }
 }
 
 pub struct RescoreResult {
@@ -31,21 +98,19 @@ pub struct RescoreResult {
 }
 
 /// Recompute all jam scores from stored feature data (no audio re-analysis).
-pub fn rescore_tracks(db: &Database) -> Result<RescoreResult, AnalyzeError> {
+pub fn rescore_tracks(
+    db: &Database,
+    progress: &dyn Progress,
+) -> Result<RescoreResult, AnalyzeError> {
     let mut analyses = db.get_analyses_for_rescore()?;
+    let bands = db.track_bands()?;
     let total = analyses.len();
 
     if total == 0 {
         return Ok(RescoreResult { rescored: 0 });
     }
 
-    let pb = ProgressBar::new(total as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} tracks ({eta} remaining)")
-            .unwrap()
-            .progress_chars("=>-"),
-    );
+    progress.start(total as u64, "tracks");
 
     let tx = db
         .conn
@@ -53,6 +118,15 @@ pub fn rescore_tracks(db: &Database) -> Result<RescoreResult, AnalyzeError> {
         .map_err(|e| AnalyzeError::Db(e.into()))?;
 
     for a in &mut analyses {
+        // Rows analyzed before tempo correction existed: onsets aren't stored,
+        // so correct from the tempo prior alone
+        if a.tempo_bpm_corrected.is_none() {
+            if let Some(est) = a.tempo_bpm.and_then(|bpm| tempo::correct(bpm, &[])) {
+                a.tempo_bpm_corrected = Some(est.bpm);
+                a.tempo_confidence = Some(est.confidence);
+                db.update_tempo_correction(a.track_id, est.bpm, est.confidence)?;
+            }
+        }
         // Load segment energies for segment-level build quality scoring
         let segment_energies = db.get_segment_energies(a.track_id).unwrap_or_default();
         let segments = if segment_energies.is_empty() {
@@ -61,12 +135,13 @@ pub fn rescore_tracks(db: &Database) -> Result<RescoreResult, AnalyzeError> {
             Some(segment_energies.as_slice())
         };
         jam_metrics::compute_jam_scores_from_scalars(a, segments);
+        jam_metrics::adjust_for_band(a, bands.get(&a.track_id).map(String::as_str));
         db.update_jam_scores(a)?;
-        pb.inc(1);
+        progress.inc(1);
     }
 
     tx.commit().map_err(|e| AnalyzeError::Db(e.into()))?;
-    pb.finish_with_message("done");
+    progress.finish("done");
 
     Ok(RescoreResult { rescored: total })
 }
@@ -97,33 +172,50 @@ pub fn classify_data_quality(
 }
 
 /// Full result from analyzing a single track (before DB write).
+#[cfg(feature = "analysis")]
 struct TrackAnalysis {
     #[allow(dead_code)]
     track_id: i64,
     extraction: ExtractionResult,
+    /// Raw series kept per `[analysis] keep_artifacts`.
+    artifacts: Vec<Artifact>,
+    /// First- and last-minute summaries for `segue-suggest` (full analyses only).
+    edges: Option<edges::Edges>,
+    /// Wall time per stage (setup, engine, boundaries, features, scores), for
+    /// `trace` and `stats --performance`.
+    timings: Vec<(&'static str, Duration)>,
+    /// Whether this track's worker had to build its engine first.
+    engine_built: bool,
 }
 
-/// Analyze tracks in parallel using rayon + tokio for the async engine.
+/// Analyze tracks with a decode/analysis pipeline (see [`pipeline`]).
 ///
-/// Processes tracks in chunks: analyze a chunk in parallel with rayon,
-/// write results to DB, then move to next chunk. This gives:
-/// - Incremental DB progress (resumable on crash)
-/// - Bounded memory (only one chunk of results in memory)
-/// - Visible progress in check_progress.sh
+/// A decode pool prefetches audio for the next tracks while `jobs` analysis
+/// workers (rayon + tokio for the async engine) process the current ones. Each
+/// result is written to the DB as soon as it's ready, so progress is resumable
+/// after a crash and only the prefetch budget's worth of audio is held at once.
+///
+/// Everything the workers need is read before the pipeline starts; during the
+/// run the connection belongs to the writer thread alone. Each track is analyzed
+/// with its collection's tuning; run-wide settings come from `tuning.global`.
+#[cfg(feature = "analysis")]
 pub fn analyze_tracks(
-    db: &Database,
-    force: bool,
-    jobs: usize,
+    db: &mut Database,
+    mode: AnalyzeMode,
+    config: &PipelineConfig,
+    tuning: &TrackTuning,
     filter: Option<&str>,
+    priority: Option<&priority::Priority>,
+    progress: &dyn Progress,
 ) -> std::result::Result<AnalyzeResult, AnalyzeError> {
-    let tracks = if force {
-        db.get_all_tracks()?
-    } else {
-        db.get_unanalyzed_tracks()?
+    let tracks = match mode {
+        AnalyzeMode::Missing => db.get_unanalyzed_tracks()?,
+        AnalyzeMode::Force => db.get_all_tracks()?,
+        AnalyzeMode::Preview => db.get_unpreviewed_tracks()?,
     };
 
     // Apply filter if provided
-    let tracks: Vec<Track> = if let Some(pattern) = filter {
+    let mut tracks: Vec<Track> = if let Some(pattern) = filter {
         let pattern_lower = pattern.to_lowercase();
         tracks
             .into_iter()
@@ -132,109 +224,195 @@ pub fn analyze_tracks(
     } else {
         tracks
     };
+    if let Some(priority) = priority {
+        priority.sort(&mut tracks, &db.priority_facts()?);
+    }
 
     if tracks.is_empty() {
         log::info!("No tracks to analyze");
         return Ok(AnalyzeResult {
             analyzed: 0,
             failed: 0,
+            engine_setup: EngineSetup::default(),
         });
     }
 
-    log::info!("Analyzing {} tracks with {} workers", tracks.len(), jobs);
-
-    let pb = ProgressBar::new(tracks.len() as u64);
-    pb.set_style(
-        ProgressStyle::with_template(
-            "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
-        )
-        .unwrap()
-        .progress_chars("#>-"),
+    log::info!(
+        "Analyzing {} tracks with {} workers ({} decoding, {} MB prefetch)",
+        tracks.len(),
+        config.jobs,
+        config.decode_jobs,
+        config.prefetch_bytes >> 20
     );
 
+    let keep_artifacts = !tuning.global.keep_artifacts.is_empty();
+    if keep_artifacts {
+        db.attach_artifacts()?;
+    }
+
+    progress.start(tracks.len() as u64, "tracks");
+
+    // Before any worker exists, so every pool thread inherits the lower priority
+    throttle::lower_priority(tuning.global.nice, tuning.global.ionice);
+    let throttle = throttle::Throttle::new(tuning.global.max_cpu_percent);
+
     // Configure rayon thread pool
     let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(jobs)
+        .num_threads(config.jobs)
         .build()
         .unwrap();
 
     let mut analyzed: u64 = 0;
     let mut failed: u64 = 0;
-
-    // Process in chunks: analyze chunk in parallel, write to DB, repeat.
-    // Chunk size = jobs so only `jobs` tracks are in memory simultaneously.
-    // (Previously jobs*2, but large FLAC files use 1-2 GB per track in ferrous-waves,
-    //  and holding extra results while analyzing the next batch caused OOM on long runs.)
-    let chunk_size = jobs;
-
-    for chunk in tracks.chunks(chunk_size) {
-        // Analyze this chunk in parallel
-        let results: Vec<_> = pool.install(|| {
-            use rayon::prelude::*;
-            chunk
-                .par_iter()
-                .map(|track| {
-                    let result = analyze_single_track(track);
-                    pb.inc(1);
-                    (track.file_path.clone(), result)
-                })
-                .collect()
-        });
-
-        // Write this chunk's results to DB immediately
-        for (file_path, result) in results {
+    let mut engine_setup = EngineSetup::default();
+    let writer: &mut Database = db;
+
+    pipeline::run(
+        &tracks,
+        config,
+        &pool,
+        |track| {
+            let started = Instant::now();
+            load_track_audio(track).map(|audio| (audio, started.elapsed()))
+        },
+        |(audio, _)| audio.buffer.samples.len() * std::mem::size_of::<f32>(),
+        |track, (audio, decode_time)| {
+            let tuning = tuning.for_track(&track.file_path);
+            throttle
+                .run(|| analyze_decoded_track(track, audio, tuning, mode == AnalyzeMode::Preview))
+                .map(|ta| (ta, decode_time))
+        },
+        |track, result| {
+            // Reborrow mutably so the closure holds `&mut Database`, which is `Send`
+            let db = &mut *writer;
+            progress.inc(1);
             match result {
-                Ok(ta) => {
+                Ok((ta, decode_time)) => {
+                    if ta.engine_built {
+                        engine_setup.builds += 1;
+                        engine_setup.build_secs += stage_secs(&ta.timings, "setup");
+                    } else {
+                        engine_setup.reuses += 1;
+                    }
                     match db.store_full_analysis(
                         &ta.extraction.analysis,
                         &ta.extraction.chords,
                         &ta.extraction.segments,
                         &ta.extraction.tension_points,
                         &ta.extraction.transitions,
+                        &ta.extraction.cuts,
                     ) {
                         Ok(()) => analyzed += 1,
                         Err(e) => {
-                            log::error!("DB error storing analysis for {}: {}", file_path, e);
+                            log::error!("DB error storing analysis for {}: {}", track.file_path, e);
                             failed += 1;
                         }
                     }
+                    let cost = crate::analysis_cost::TrackCost {
+                        track_id: track.id,
+                        audio_secs: ta.extraction.analysis.duration,
+                        decode_secs: decode_time.as_secs_f64(),
+                        analysis_secs: ta.timings.iter().map(|(_, d)| d.as_secs_f64()).sum(),
+                        preview: ta.extraction.analysis.preview,
+                        jobs: config.jobs,
+                    };
+                    if let Err(e) = db.record_analysis_cost(&cost) {
+                        log::warn!(
+                            "Failed to record analysis time for {}: {}",
+                            track.file_path,
+                            e
+                        );
+                    }
+                    if let Err(e) = db.store_artifacts(track.id, &ta.artifacts) {
+                        log::warn!("Failed to store artifacts for {}: {}", track.file_path, e);
+                    }
+                    if let Some(edges) = &ta.edges {
+                        if let Err(e) = db.store_segue_edges(track.id, edges) {
+                            log::warn!(
+                                "Failed to store segue edges for {}: {}",
+                                track.file_path,
+                                e
+                            );
+                        }
+                    }
                 }
                 Err(e) => {
-                    log::warn!("Analysis failed for {}: {}", file_path, e);
+                    log::warn!("Analysis failed for {}: {}", track.file_path, e);
                     failed += 1;
                 }
             }
-        }
 
-        // Return freed memory to the OS. Without this, the system allocator holds onto
-        // large freed audio buffers (hundreds of MB per FLAC track), causing RSS to grow
-        // linearly across chunks until OOM on long overnight runs.
-        #[cfg(target_os = "linux")]
-        unsafe {
-            libc::malloc_trim(0);
+            let done = analyzed + failed;
+            if done % config.jobs.max(1) as u64 == 0 {
+                // Return freed memory to the OS. Without this, the system allocator holds
+                // onto large freed audio buffers (hundreds of MB per FLAC track), causing
+                // RSS to grow linearly until OOM on long overnight runs.
+                #[cfg(target_os = "linux")]
+                unsafe {
+                    libc::malloc_trim(0);
+                }
+                log::info!(
+                    "Progress: {}/{} analyzed, {} failed",
+                    analyzed,
+                    tracks.len(),
+                    failed
+                );
+            }
+            progress.set_message(&format!("{} stored, {} failed", analyzed, failed));
+        },
+    );
+
+    progress.finish(&format!("Done: {} analyzed, {} failed", analyzed, failed));
+
+    if keep_artifacts && tuning.global.artifact_budget_mb > 0 {
+        let pruned = db.prune_artifacts(Some(tuning.global.artifact_budget_mb << 20), None)?;
+        if pruned.evicted > 0 {
+            log::info!(
+                "Artifact budget: evicted {} artifacts ({} MB)",
+                pruned.evicted,
+                pruned.freed_bytes >> 20
+            );
         }
+    }
 
-        pb.set_message(format!("{} stored, {} failed", analyzed, failed));
-        log::info!(
-            "Chunk complete: {}/{} analyzed, {} failed",
-            analyzed,
-            tracks.len(),
-            failed
-        );
+    match db.backfill_durations() {
+        Ok(0) => {}
+        Ok(n) => log::info!("Filled in the length of {n} tracks whose tags had none"),
+        Err(e) => log::warn!("Failed to backfill track durations: {e}"),
     }
 
-    pb.finish_with_message(format!("Done: {} analyzed, {} failed", analyzed, failed));
+    if let Err(e) = db.record_snapshot("analyze", analyzed as i64) {
+        log::warn!("Failed to record library snapshot: {e}");
+    }
 
-    Ok(AnalyzeResult { analyzed, failed })
+    Ok(AnalyzeResult {
+        analyzed,
+        failed,
+        engine_setup,
+    })
 }
 
-/// Extract boundary features for tracks that don't have them yet.
+/// Total time recorded for one stage of a track's analysis.
+#[cfg(feature = "analysis")]
+fn stage_secs(timings: &[(&'static str, Duration)], stage: &str) -> f64 {
+    timings
+        .iter()
+        .filter(|(name, _)| *name == stage)
+        .map(|(_, d)| d.as_secs_f64())
+        .sum()
+}
+
+/// Extract boundary features and segue edges for tracks that don't have them yet.
 ///
 /// This is a lightweight decode-only pass — no FFT, no ferrous-waves analysis.
-/// Just loads the audio and computes RMS/silence stats on head and tail regions.
+/// Just loads the audio and computes RMS/silence stats on head and tail regions,
+/// and the first- and last-minute summaries `segue-suggest` compares.
+#[cfg(feature = "analysis")]
 pub fn extract_boundaries(
     db: &Database,
     jobs: usize,
+    chunk_size: usize,
+    progress: &dyn Progress,
 ) -> std::result::Result<AnalyzeResult, AnalyzeError> {
     let tracks = db.get_tracks_missing_boundaries()?;
 
@@ -243,6 +421,7 @@ pub fn extract_boundaries(
         return Ok(AnalyzeResult {
             analyzed: 0,
             failed: 0,
+            engine_setup: EngineSetup::default(),
         });
     }
 
@@ -252,14 +431,7 @@ pub fn extract_boundaries(
         jobs
     );
 
-    let pb = ProgressBar::new(tracks.len() as u64);
-    pb.set_style(
-        ProgressStyle::with_template(
-            "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
-        )
-        .unwrap()
-        .progress_chars("#>-"),
-    );
+    progress.start(tracks.len() as u64, "tracks");
 
     let pool = rayon::ThreadPoolBuilder::new()
         .num_threads(jobs)
@@ -268,7 +440,6 @@ pub fn extract_boundaries(
 
     let mut extracted: u64 = 0;
     let mut failed: u64 = 0;
-    let chunk_size = jobs * 4; // Larger chunks since boundary extraction is fast
 
     for chunk in tracks.chunks(chunk_size) {
         let results: Vec<_> = pool.install(|| {
@@ -277,7 +448,7 @@ pub fn extract_boundaries(
                 .par_iter()
                 .map(|track| {
                     let result = extract_boundary_single(track);
-                    pb.inc(1);
+                    progress.inc(1);
                     (track.id, track.file_path.clone(), result)
                 })
                 .collect()
@@ -285,7 +456,12 @@ pub fn extract_boundaries(
 
         for (track_id, file_path, result) in results {
             match result {
-                Ok(bf) => {
+                Ok((bf, edges)) => {
+                    if let Some(edges) = &edges {
+                        if let Err(e) = db.store_segue_edges(track_id, edges) {
+                            log::warn!("Failed to store segue edges for {}: {}", file_path, e);
+                        }
+                    }
                     match db.update_boundary_features(
                         track_id,
                         bf.tail_rms_db,
@@ -307,32 +483,82 @@ pub fn extract_boundaries(
             }
         }
 
-        pb.set_message(format!("{} stored, {} failed", extracted, failed));
+        progress.set_message(&format!("{} stored, {} failed", extracted, failed));
     }
 
-    pb.finish_with_message(format!("Done: {} extracted, {} failed", extracted, failed));
+    progress.finish(&format!("Done: {} extracted, {} failed", extracted, failed));
 
     Ok(AnalyzeResult {
         analyzed: extracted,
         failed,
+        engine_setup: EngineSetup::default(),
     })
 }
 
-/// Decode a single track and extract boundary features.
+/// Decode a single track and extract boundary features and segue edges
+/// (without edge tempos: there are no onsets without the full analysis).
+#[cfg(feature = "analysis")]
 fn extract_boundary_single(
     track: &Track,
-) -> std::result::Result<boundary::BoundaryFeatures, AnalyzeError> {
+) -> std::result::Result<(boundary::BoundaryFeatures, Option<edges::Edges>), AnalyzeError> {
     let path = Path::new(&track.file_path);
     log::debug!(
         "Boundary: {}",
         path.file_name().and_then(|f| f.to_str()).unwrap_or("?")
     );
-    let audio = decode::load_audio(path)?;
-    Ok(boundary::extract_from_audio(&audio))
+    let audio = load_track_audio(track)?;
+    let mono = audio.buffer.to_mono();
+    let sample_rate = audio.buffer.sample_rate as f32;
+    Ok((
+        boundary::extract(&mono, sample_rate),
+        edges::extract(&mono, sample_rate, &[]),
+    ))
+}
+
+/// Decode a long recording and propose song boundaries inside it. Returns the
+/// boundaries and the decoded length in seconds.
+#[cfg(feature = "analysis")]
+pub fn split_recording(
+    track: &Track,
+    params: &songsplit::SplitParams,
+) -> std::result::Result<(Vec<songsplit::SongBoundary>, f64), AnalyzeError> {
+    let audio = load_track_audio(track)?;
+    let mono = audio.buffer.to_mono();
+    let sample_rate = audio.buffer.sample_rate as f32;
+    drop(audio);
+    let duration = mono.len() as f64 / sample_rate as f64;
+    Ok((
+        songsplit::detect_songs(&mono, sample_rate, params),
+        duration,
+    ))
+}
+
+/// Decode a track and reduce it to song-boundary frames (one per second), for
+/// searching a show's tracks as one recording.
+#[cfg(feature = "analysis")]
+pub fn song_frames(track: &Track) -> std::result::Result<Vec<songsplit::Frame>, AnalyzeError> {
+    let audio = load_track_audio(track)?;
+    let mono = audio.buffer.to_mono();
+    let sample_rate = audio.buffer.sample_rate as f32;
+    drop(audio);
+    Ok(songsplit::frames(&mono, sample_rate))
+}
+
+/// Decode a track's audio. Files on a remote share are downloaded to the cache
+/// first and the cached copy is deleted once decoded.
+#[cfg(feature = "analysis")]
+fn load_track_audio(track: &Track) -> std::result::Result<ferrous_waves::AudioFile, AnalyzeError> {
+    if crate::remote::is_remote(&track.file_path) {
+        let cached = crate::remote::fetch(&track.file_path)?;
+        Ok(decode::load_audio(cached.path())?)
+    } else {
+        Ok(decode::load_audio(&crate::paths::to_fs(&track.file_path))?)
+    }
 }
 
 // Thread-local tokio runtime — reused across tracks on the same rayon thread
 // to avoid the overhead of creating a runtime per-track.
+#[cfg(feature = "analysis")]
 thread_local! {
     static THREAD_RT: tokio::runtime::Runtime = tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -340,46 +566,117 @@ thread_local! {
         .expect("tokio runtime");
 }
 
+/// The tuning an engine was built with. A worker keeps one engine per tuning
+/// it has been asked for, so collections with different profiles can interleave.
+#[cfg(feature = "analysis")]
+type EngineKey = (bool, usize, usize);
+
+// Thread-local analysis engines — built on a worker's first track with a given
+// tuning (FFT plans, pitch and classifier tables) and reused for the rest.
+#[cfg(feature = "analysis")]
+thread_local! {
+    static THREAD_ENGINE: RefCell<Vec<(EngineKey, ferrous_waves::AnalysisEngine)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(feature = "analysis")]
+fn engine_key(tuning: &AnalysisTuning) -> EngineKey {
+    (
+        tuning.skip_classification_segments,
+        tuning.pyin_threshold_count,
+        tuning.pyin_hop_multiplier,
+    )
+}
+
 /// Analysis config optimized for setbreak's batch processing:
 /// - Skip PNG visualization (we never display it)
 /// - Skip audio fingerprinting (not used yet, future Phase 4)
 /// - Skip per-segment content classification (we use overall classification only)
 /// - Reduce PYIN thresholds from 100 to 25 (4x faster pitch detection)
 /// - Double PYIN hop size (analyze every 2nd frame)
-fn fast_analysis_config() -> AnalysisConfig {
+///
+/// The last three come from `[analysis]` in the config, defaulting to the above.
+#[cfg(feature = "analysis")]
+fn fast_analysis_config(tuning: &AnalysisTuning) -> AnalysisConfig {
     AnalysisConfig {
         skip_visualization: true,
         skip_fingerprinting: true,
-        skip_classification_segments: true,
-        pyin_threshold_count: 25,
-        pyin_hop_multiplier: 2,
+        skip_classification_segments: tuning.skip_classification_segments,
+        pyin_threshold_count: tuning.pyin_threshold_count,
+        pyin_hop_multiplier: tuning.pyin_hop_multiplier,
     }
 }
 
-/// Analyze a single track: decode -> ferrous-waves analyze -> extract features -> compute scores.
-fn analyze_single_track(track: &Track) -> std::result::Result<TrackAnalysis, AnalyzeError> {
-    let path = Path::new(&track.file_path);
-
-    log::debug!(
-        "Analyzing: {}",
-        path.file_name().and_then(|f| f.to_str()).unwrap_or("?")
-    );
+/// Analyze a decoded track: ferrous-waves analyze -> extract features -> compute scores.
+/// With `preview`, long tracks are cut to their excerpts first.
+#[cfg(feature = "analysis")]
+fn analyze_decoded_track(
+    track: &Track,
+    mut audio: ferrous_waves::AudioFile,
+    tuning: &AnalysisTuning,
+    preview: bool,
+) -> std::result::Result<TrackAnalysis, AnalyzeError> {
+    log::debug!("Analyzing: {}", crate::paths::file_name(&track.file_path));
+    let full_secs = if preview {
+        preview::excerpt(&mut audio)
+    } else {
+        None
+    };
 
-    // Decode audio
-    let audio = decode::load_audio(path)?;
+    let mut timings = Vec::new();
+    let mut started = Instant::now();
+    let mut lap = |stage: &'static str| {
+        timings.push((stage, started.elapsed()));
+        started = Instant::now();
+    };
 
-    // Run ferrous-waves analysis with optimized config
-    let engine = ferrous_waves::AnalysisEngine::new()
-        .without_cache()
-        .with_analysis_config(fast_analysis_config());
-    let analysis_result: AnalysisResult = THREAD_RT
-        .with(|rt| rt.block_on(engine.analyze(&audio)))
+    // Run ferrous-waves analysis with optimized config, on this thread's engine
+    let key = engine_key(tuning);
+    let mut engine_built = false;
+    let analysis_result: AnalysisResult = THREAD_ENGINE
+        .with(|engines| {
+            let mut engines = engines.borrow_mut();
+            let at = match engines.iter().position(|(k, _)| *k == key) {
+                Some(at) => at,
+                None => {
+                    engines.push((
+                        key,
+                        ferrous_waves::AnalysisEngine::new()
+                            .without_cache()
+                            .with_analysis_config(fast_analysis_config(tuning)),
+                    ));
+                    engine_built = true;
+                    engines.len() - 1
+                }
+            };
+            lap("setup");
+            let (_, engine) = &engines[at];
+            THREAD_RT.with(|rt| rt.block_on(engine.analyze(&audio)))
+        })
         .map_err(|e| AnalyzeError::Engine(e.to_string()))?;
-
-    // Extract boundary features from raw audio (for segue detection)
-    let bf = boundary::extract_from_audio(&audio);
+    lap("engine");
+
+    // Boundary features and segue edges from raw audio (for segue detection).
+    // Edges need the real first and last minute, not the preview's excerpts
+    let mono = audio.buffer.to_mono();
+    let sample_rate = audio.buffer.sample_rate as f32;
+    let bf = boundary::extract(&mono, sample_rate);
+    let announcer_ratio = announcer::detect(&mono, sample_rate);
+    let edges = if full_secs.is_some() {
+        None
+    } else {
+        edges::extract(&mono, sample_rate, &analysis_result.temporal.onsets)
+    };
+    drop(mono);
+    // Tape flips / splices, also from raw audio (not across excerpt joins)
+    let cut_points = if full_secs.is_some() {
+        Vec::new()
+    } else {
+        cuts::detect_from_audio(&audio)
+    };
     // Drop raw audio ASAP — large FLAC tracks can use 500+ MB
     drop(audio);
+    lap("boundaries");
 
     // Extract all features into DB schema + detail records
     let mut extraction = features::extract(track.id, &analysis_result);
@@ -387,9 +684,35 @@ fn analyze_single_track(track: &Track) -> std::result::Result<TrackAnalysis, Ana
     extraction.analysis.tail_silence_pct = Some(bf.tail_silence_pct);
     extraction.analysis.head_rms_db = Some(bf.head_rms_db);
     extraction.analysis.head_silence_pct = Some(bf.head_silence_pct);
-
-    // Compute jam-specific derived scores using the full analysis result
-    jam_metrics::compute_jam_scores(&mut extraction.analysis, &analysis_result);
+    extraction.analysis.announcer_ratio = announcer_ratio;
+    extraction.cuts = cut_points
+        .into_iter()
+        .map(|c| CutPointRecord {
+            track_id: track.id,
+            time: c.time,
+            drop_db: c.drop_db,
+            discontinuity: c.discontinuity,
+        })
+        .collect();
+    lap("features");
+
+    let artifacts = if let Some(full_secs) = full_secs {
+        // Excerpt timings mean nothing on the full track: keep only scalars,
+        // scaled up, and score them like `rescore` does
+        preview::extrapolate(&mut extraction.analysis, full_secs);
+        extraction.chords.clear();
+        extraction.segments.clear();
+        extraction.tension_points.clear();
+        extraction.transitions.clear();
+        jam_metrics::compute_jam_scores_from_scalars(&mut extraction.analysis, None);
+        Vec::new()
+    } else {
+        // Compute jam-specific derived scores using the full analysis result
+        jam_metrics::compute_jam_scores(&mut extraction.analysis, &analysis_result);
+        capture_artifacts(&analysis_result, &tuning.keep_artifacts)
+    };
+    jam_metrics::adjust_for_band(&mut extraction.analysis, track.parsed_band.as_deref());
+    lap("scores");
     // Drop the full AnalysisResult — ferrous-waves retains spectrograms, pitch tracks,
     // and per-frame features that can be 1-2 GB for long concert recordings.
     drop(analysis_result);
@@ -397,5 +720,43 @@ fn analyze_single_track(track: &Track) -> std::result::Result<TrackAnalysis, Ana
     Ok(TrackAnalysis {
         track_id: track.id,
         extraction,
+        artifacts,
+        edges,
+        timings,
+        engine_built,
     })
 }
+
+/// Copy the requested per-frame series out of the analysis before it's dropped.
+#[cfg(feature = "analysis")]
+fn capture_artifacts(r: &AnalysisResult, kinds: &[ArtifactKind]) -> Vec<Artifact> {
+    kinds
+        .iter()
+        .filter_map(|&kind| {
+            let (channels, values): (usize, Vec<f32>) = match kind {
+                ArtifactKind::Chromagram => {
+                    let frames = r.spectral.chroma.iter().map(Vec::len).min().unwrap_or(0);
+                    let values = r
+                        .spectral
+                        .chroma
+                        .iter()
+                        .flat_map(|ch| ch[..frames].iter().copied())
+                        .collect();
+                    (r.spectral.chroma.len(), values)
+                }
+                ArtifactKind::OnsetEnvelope => (1, r.spectral.spectral_flux.clone()),
+                ArtifactKind::Loudness => (1, r.perceptual.short_term_loudness.clone()),
+            };
+            let frames = values.len() / channels.max(1);
+            if frames == 0 {
+                return None;
+            }
+            Some(Artifact {
+                kind,
+                channels,
+                hop_secs: r.summary.duration as f64 / frames as f64,
+                values,
+            })
+        })
+        .collect()
+}
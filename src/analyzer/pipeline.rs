@@ -0,0 +1,259 @@
+//! Decode/analysis pipeline for `analyze`.
+//!
+//! Decoding (FLAC especially) runs single-threaded per file and dominates wall
+//! time on some libraries, while ferrous-waves analysis is CPU-heavy in bursts.
+//! Running them as separate stages lets a small decode pool prefetch the next
+//! tracks while the analysis workers crunch the current ones, instead of every
+//! worker idling its FFT code while it waits on its own decode.
+//!
+//! Decoded audio is large (a long FLAC set is several hundred MB of f32 samples),
+//! so the audio queued for or inside analysis is capped by a byte budget. Each
+//! decode worker may additionally hold the one track it just decoded while it
+//! waits for room.
+//!
+//! Results go to a single writer thread, which is the only stage that touches
+//! the database: workers get the pre-fetched track list and decoded audio, and
+//! the writer takes the connection by `&mut` for the whole run. A
+//! `rusqlite::Connection` isn't `Sync`, so a worker closure that reached for the
+//! database wouldn't compile.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Condvar, Mutex};
+
+use rayon::iter::{ParallelBridge, ParallelIterator};
+
+/// Name of the thread that runs the sink.
+pub const WRITER_THREAD: &str = "setbreak-writer";
+
+/// Default budget for decoded audio in flight, in MB.
+pub const DEFAULT_PREFETCH_MB: usize = 2048;
+
+/// How `analyze` splits work between decoding and analysis.
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineConfig {
+    /// Analysis workers (ferrous-waves and feature extraction).
+    pub jobs: usize,
+    /// Decode workers prefetching ahead of analysis.
+    pub decode_jobs: usize,
+    /// Cap on decoded audio queued for or inside analysis, in bytes.
+    pub prefetch_bytes: usize,
+    /// Decoded items that may wait for an analysis worker (still subject to the budget).
+    pub queue_depth: usize,
+}
+
+/// Byte counter that blocks producers while the budget is spent.
+struct MemoryBudget {
+    cap: usize,
+    used: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl MemoryBudget {
+    fn new(cap: usize) -> Self {
+        Self {
+            cap,
+            used: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Block until `bytes` fit. An item larger than the whole budget is let
+    /// through once nothing else is held, so an oversized track can't stall the run.
+    /// The bytes are held until the returned reservation is dropped.
+    fn acquire(&self, bytes: usize) -> Reservation<'_> {
+        let mut used = self.used.lock().unwrap();
+        while *used > 0 && *used + bytes > self.cap {
+            used = self.freed.wait(used).unwrap();
+        }
+        *used += bytes;
+        Reservation {
+            budget: self,
+            bytes,
+        }
+    }
+
+    fn release(&self, bytes: usize) {
+        let mut used = self.used.lock().unwrap();
+        *used -= bytes;
+        self.freed.notify_all();
+    }
+}
+
+/// Bytes held against a `MemoryBudget`, released on drop — including while
+/// unwinding from a panicking `analyze`, so the decoders waiting on the budget
+/// aren't left blocked forever.
+struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: usize,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.budget.release(self.bytes);
+    }
+}
+
+/// Run `decode` on a dedicated pool and `analyze` on `pool`, handing each
+/// item's result to `sink` on the writer thread as soon as it's ready
+/// (completion order, not input order). `size_of` reports a decoded item's
+/// footprint for the prefetch budget.
+pub(crate) fn run<I, D, R, E>(
+    items: &[I],
+    config: &PipelineConfig,
+    pool: &rayon::ThreadPool,
+    decode: impl Fn(&I) -> Result<D, E> + Sync,
+    size_of: impl Fn(&D) -> usize + Sync,
+    analyze: impl Fn(&I, D) -> Result<R, E> + Sync,
+    mut sink: impl FnMut(&I, Result<R, E>) + Send,
+) where
+    I: Sync,
+    D: Send,
+    R: Send,
+    E: Send,
+{
+    let budget = MemoryBudget::new(config.prefetch_bytes);
+    let next = AtomicUsize::new(0);
+    let (decoded_tx, decoded_rx) =
+        mpsc::sync_channel::<(usize, Result<D, E>, Reservation)>(config.queue_depth.max(1));
+    let (done_tx, done_rx) = mpsc::channel::<(usize, Result<R, E>)>();
+
+    let (budget, next, decode, size_of, analyze) = (&budget, &next, &decode, &size_of, &analyze);
+    std::thread::scope(|s| {
+        for _ in 0..config.decode_jobs.max(1) {
+            let tx = decoded_tx.clone();
+            s.spawn(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else { break };
+                    let decoded = decode(item);
+                    let held = budget.acquire(decoded.as_ref().map_or(0, size_of));
+                    if tx.send((i, decoded, held)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(decoded_tx);
+
+        s.spawn(move || {
+            pool.install(|| {
+                decoded_rx.into_iter().par_bridge().for_each_with(
+                    done_tx,
+                    |tx, (i, decoded, held)| {
+                        let result = decoded.and_then(|d| analyze(&items[i], d));
+                        drop(held);
+                        // Only fails if the sink panicked; the scope re-raises that
+                        let _ = tx.send((i, result));
+                    },
+                );
+            });
+        });
+
+        std::thread::Builder::new()
+            .name(WRITER_THREAD.into())
+            .spawn_scoped(s, move || {
+                for (i, result) in done_rx {
+                    sink(&items[i], result);
+                }
+            })
+            .expect("failed to spawn the writer thread");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn pool(threads: usize) -> rayon::ThreadPool {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_budget_blocks_until_released() {
+        let budget = Arc::new(MemoryBudget::new(10));
+        let held = budget.acquire(8);
+        let waiter = {
+            let budget = Arc::clone(&budget);
+            std::thread::spawn(move || std::mem::forget(budget.acquire(5)))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        drop(held);
+        waiter.join().unwrap();
+        assert_eq!(*budget.used.lock().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_budget_admits_oversized_item_alone() {
+        let budget = MemoryBudget::new(10);
+        drop(budget.acquire(50));
+        assert_eq!(*budget.used.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_panicking_analysis_releases_budget() {
+        let items: Vec<u32> = (0..20).collect();
+        let config = PipelineConfig {
+            jobs: 2,
+            decode_jobs: 2,
+            prefetch_bytes: 1,
+            queue_depth: 1,
+        };
+        // Before the reservation guard, this left decoders blocked on the budget
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run(
+                &items,
+                &config,
+                &pool(2),
+                |&n| Ok::<_, ()>(vec![n; 4]),
+                |v: &Vec<u32>| v.len() * 4,
+                |&n, v| {
+                    assert_ne!(n, 3, "analysis blew up");
+                    Ok(v.len())
+                },
+                |_, _| {},
+            )
+        }));
+        assert!(outcome.is_err());
+    }
+
+    #[test]
+    fn test_run_processes_every_item() {
+        let items: Vec<u32> = (0..40).collect();
+        let config = PipelineConfig {
+            jobs: 3,
+            decode_jobs: 2,
+            // Smaller than any item: forces one decoded item in flight at a time
+            prefetch_bytes: 1,
+            queue_depth: 3,
+        };
+        let mut ok = Vec::new();
+        let mut failed = Vec::new();
+        run(
+            &items,
+            &config,
+            &pool(3),
+            |&n| if n % 7 == 0 { Err(n) } else { Ok(vec![n; 4]) },
+            |v: &Vec<u32>| v.len() * 4,
+            |&n, v| Ok(v.iter().sum::<u32>() + n),
+            |&n, result| {
+                assert_eq!(std::thread::current().name(), Some(WRITER_THREAD));
+                match result {
+                    Ok(sum) => ok.push((n, sum)),
+                    Err(e) => failed.push(e),
+                }
+            },
+        );
+        ok.sort();
+        failed.sort();
+        assert_eq!(failed, vec![0, 7, 14, 21, 28, 35]);
+        assert_eq!(ok.len(), 34);
+        assert!(ok.iter().all(|&(n, sum)| sum == 5 * n));
+    }
+}
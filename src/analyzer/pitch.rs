@@ -0,0 +1,143 @@
+//! Pitch octave-error correction.
+//!
+//! Frame pitch trackers jump to a harmonic (2×, 3×, 4× the note) on bright
+//! attacks and sustained distortion, or drop to the subharmonic on bass-heavy
+//! frames. The raw `pitch_range_high` is the highest frame, so a handful of
+//! such jumps decides it, and `mean_pitch` drifts up with them. Here each
+//! confident frame is compared with a running median of its neighbours: a
+//! frame far from the median that lands near it once divided by 2, 3 or 4 (or
+//! doubled) is folded back, and the range is taken from the 5th and 95th
+//! percentiles of the result, so what's left of the outliers can't set it.
+//!
+//! The pitch track only exists during analysis, so stored rows get corrected
+//! statistics when they're analyzed again.
+
+/// Frames outside this range aren't pitches (matches `pitch_contour_std`).
+const MIN_HZ: f64 = 50.0;
+const MAX_HZ: f64 = 4000.0;
+/// Below this many confident frames there is no contour to speak of.
+const MIN_FRAMES: usize = 10;
+/// Frames on each side of the running median.
+const MEDIAN_HALF_WINDOW: usize = 7;
+/// A frame this far from the median (semitones) is a candidate octave error.
+const OUTLIER_SEMITONES: f64 = 5.0;
+/// A folded frame must land this close to the median (semitones) to be kept.
+const FOLD_TOLERANCE_SEMITONES: f64 = 1.0;
+/// Ratios a tracker commonly mistakes for the note: harmonics and the subharmonic.
+const ERROR_RATIOS: [f64; 4] = [2.0, 3.0, 4.0, 0.5];
+/// Percentiles that bound the corrected range.
+const RANGE_PERCENTILES: (f64, f64) = (0.05, 0.95);
+
+/// Pitch statistics after octave correction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PitchStats {
+    pub mean_hz: f64,
+    pub low_hz: f64,
+    pub high_hz: f64,
+    /// Share of frames folded back (0-1).
+    pub octave_errors: f64,
+}
+
+impl PitchStats {
+    /// Width of the corrected range in octaves.
+    pub fn range_octaves(&self) -> f64 {
+        (self.high_hz / self.low_hz).log2()
+    }
+}
+
+/// Correct octave errors in the frequencies (Hz, in frame order) of the frames
+/// the tracker was confident about, and summarize the result.
+pub fn correct(frequencies: &[f64]) -> Option<PitchStats> {
+    let semitones: Vec<f64> = frequencies
+        .iter()
+        .filter(|hz| (MIN_HZ..=MAX_HZ).contains(*hz))
+        .map(|&hz| 12.0 * hz.log2())
+        .collect();
+    if semitones.len() < MIN_FRAMES {
+        return None;
+    }
+    let reference = running_median(&semitones, MEDIAN_HALF_WINDOW);
+
+    let mut folded = 0;
+    let mut hz: Vec<f64> = semitones
+        .iter()
+        .zip(&reference)
+        .map(|(&s, &r)| {
+            let s = if (s - r).abs() <= OUTLIER_SEMITONES {
+                s
+            } else {
+                match ERROR_RATIOS
+                    .iter()
+                    .map(|ratio| s - 12.0 * ratio.log2())
+                    .min_by(|a, b| (a - r).abs().total_cmp(&(b - r).abs()))
+                {
+                    Some(f) if (f - r).abs() <= FOLD_TOLERANCE_SEMITONES => {
+                        folded += 1;
+                        f
+                    }
+                    _ => s,
+                }
+            };
+            (s / 12.0).exp2()
+        })
+        .collect();
+
+    let n = hz.len();
+    let mean_hz = hz.iter().sum::<f64>() / n as f64;
+    hz.sort_by(f64::total_cmp);
+    let at = |p: f64| hz[((n - 1) as f64 * p).round() as usize];
+    Some(PitchStats {
+        mean_hz,
+        low_hz: at(RANGE_PERCENTILES.0),
+        high_hz: at(RANGE_PERCENTILES.1),
+        octave_errors: folded as f64 / n as f64,
+    })
+}
+
+/// Median of each value's window of `half` values either side (shorter at the ends).
+fn running_median(values: &[f64], half: usize) -> Vec<f64> {
+    (0..values.len())
+        .map(|i| {
+            let mut window =
+                values[i.saturating_sub(half)..(i + half + 1).min(values.len())].to_vec();
+            window.sort_by(f64::total_cmp);
+            window[window.len() / 2]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folds_harmonic_jumps() {
+        // A held A3 with the tracker jumping to the 2nd and 3rd harmonic and
+        // once down to the subharmonic
+        let mut track = vec![220.0; 60];
+        for i in [5, 17, 30, 44] {
+            track[i] = 440.0;
+        }
+        track[23] = 660.0;
+        track[51] = 110.0;
+        let stats = correct(&track).unwrap();
+        assert!((stats.high_hz - 220.0).abs() < 1e-6);
+        assert!((stats.low_hz - 220.0).abs() < 1e-6);
+        assert!((stats.mean_hz - 220.0).abs() < 1e-6);
+        assert!((stats.octave_errors - 6.0 / 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_keeps_real_melodic_range() {
+        // A slow two-octave climb is music, not an error
+        let track: Vec<f64> = (0..100)
+            .map(|i| 110.0 * (2.0 * i as f64 / 99.0).exp2())
+            .collect();
+        let stats = correct(&track).unwrap();
+        assert_eq!(stats.octave_errors, 0.0);
+        assert!((stats.range_octaves() - 1.8).abs() < 0.05);
+
+        assert_eq!(correct(&[220.0; 5]), None);
+        assert_eq!(correct(&[20.0; 50]), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! Preview analysis: provisional scores from excerpts of a track.
+//!
+//! `analyze --preview` runs the analysis engine on three excerpts instead of
+//! the whole track — the opening minute, two minutes from the middle, and the
+//! final two minutes — so a 20-minute jam costs about five minutes of
+//! analysis. Decoding still reads the whole file (none of the decoders seek);
+//! the engine, by far the larger cost, only sees the excerpts.
+//!
+//! Count features (onsets, peaks, key changes, ...) are scaled up to the full
+//! track length and the duration is set to the real one, so rate-based and
+//! length-gated scores come out on the same scale as a full analysis. Time-based
+//! detail (segments, tension curve, transitions, cuts) would be in excerpt
+//! time and is dropped. Rows are marked `preview = 1`; a plain `analyze`
+//! replaces them with full analyses.
+
+use ferrous_waves::AudioFile;
+use ferrous_waves::audio::AudioBuffer;
+
+use crate::db::models::NewAnalysis;
+
+/// Excerpt lengths (seconds): start, middle, end.
+pub const HEAD_SECS: f64 = 60.0;
+pub const MIDDLE_SECS: f64 = 120.0;
+pub const TAIL_SECS: f64 = 120.0;
+
+/// Tracks shorter than this are analyzed whole: excerpting would save little
+/// and the excerpts would overlap.
+pub const MIN_TRACK_SECS: f64 = 420.0;
+
+/// Fade on each side of a join between excerpts (seconds), so it doesn't click.
+/// The track's own start and end are left alone for the boundary features.
+const FADE_SECS: f64 = 0.05;
+
+/// Cut a decoded track down to its preview excerpts. Returns the full length in
+/// seconds, or `None` (audio untouched) when the track is short enough to
+/// analyze whole.
+pub fn excerpt(audio: &mut AudioFile) -> Option<f64> {
+    let channels = audio.buffer.channels.max(1);
+    let rate = audio.buffer.sample_rate as f64;
+    let frames = audio.buffer.samples.len() / channels;
+    let full_secs = frames as f64 / rate;
+    if full_secs < MIN_TRACK_SECS {
+        return None;
+    }
+
+    let to_frame = |secs: f64| ((secs * rate) as usize).min(frames);
+    let middle = full_secs / 2.0;
+    let ranges = [
+        (0, to_frame(HEAD_SECS)),
+        (
+            to_frame(middle - MIDDLE_SECS / 2.0),
+            to_frame(middle + MIDDLE_SECS / 2.0),
+        ),
+        (to_frame(full_secs - TAIL_SECS), frames),
+    ];
+    let fade = to_frame(FADE_SECS).max(1);
+
+    let kept: usize = ranges.iter().map(|(a, b)| b - a).sum();
+    let mut samples = Vec::with_capacity(kept * channels);
+    let last = ranges.len() - 1;
+    for (r, (start, end)) in ranges.into_iter().enumerate() {
+        let len = end - start;
+        let part = &audio.buffer.samples[start * channels..end * channels];
+        for (i, frame) in part.chunks_exact(channels).enumerate() {
+            let from_join = match (r, r == last) {
+                (0, _) => len - 1 - i,
+                (_, true) => i,
+                _ => i.min(len - 1 - i),
+            };
+            let gain = if from_join < fade {
+                from_join as f32 / fade as f32
+            } else {
+                1.0
+            };
+            samples.extend(frame.iter().map(|s| s * gain));
+        }
+    }
+    audio.buffer = AudioBuffer::new(samples, audio.buffer.sample_rate, channels);
+    Some(full_secs)
+}
+
+/// Scale an excerpt analysis up to the full track: counts grow with the length
+/// ratio and the duration becomes the real one. Marks the analysis as a preview.
+pub fn extrapolate(a: &mut NewAnalysis, full_secs: f64) {
+    let excerpt_secs = a.duration.unwrap_or(HEAD_SECS + MIDDLE_SECS + TAIL_SECS);
+    let ratio = if excerpt_secs > 0.0 {
+        full_secs / excerpt_secs
+    } else {
+        1.0
+    };
+    for count in [
+        &mut a.beat_count,
+        &mut a.onset_count,
+        &mut a.energy_peak_count,
+        &mut a.chord_count,
+        &mut a.dynamics_peak_count,
+        &mut a.key_change_count,
+        &mut a.segment_count,
+        &mut a.tension_build_count,
+        &mut a.tension_release_count,
+        &mut a.repetition_count,
+        &mut a.solo_section_count,
+        &mut a.transition_count,
+        &mut a.harmonic_section_count,
+    ] {
+        *count = count.map(|n| (n as f64 * ratio).round() as i32);
+    }
+    a.duration = Some(full_secs);
+    a.preview = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrous_waves::audio::AudioFormat;
+
+    fn audio(secs: f64) -> AudioFile {
+        let rate = 1000;
+        let samples = vec![0.5f32; (secs * rate as f64) as usize * 2];
+        AudioFile {
+            buffer: AudioBuffer::new(samples, rate, 2),
+            format: AudioFormat::from_path(std::path::Path::new("t.wav")),
+            path: "t.wav".into(),
+        }
+    }
+
+    #[test]
+    fn test_excerpt_keeps_head_middle_tail() {
+        let mut short = audio(300.0);
+        assert_eq!(excerpt(&mut short), None);
+        assert_eq!(short.buffer.samples.len(), 600_000);
+
+        let mut long = audio(1200.0);
+        assert_eq!(excerpt(&mut long), Some(1200.0));
+        let kept = (HEAD_SECS + MIDDLE_SECS + TAIL_SECS) as usize * 1000 * 2;
+        assert_eq!(long.buffer.samples.len(), kept);
+        // Faded at the joins only; the track's own start and end are untouched
+        assert_eq!(long.buffer.samples[0], 0.5);
+        assert_eq!(long.buffer.samples[30_000 * 2], 0.5);
+        assert_eq!(long.buffer.samples[60_000 * 2 - 1], 0.0);
+        assert_eq!(long.buffer.samples[60_000 * 2], 0.0);
+        assert_eq!(*long.buffer.samples.last().unwrap(), 0.5);
+    }
+
+    #[test]
+    fn test_extrapolate_scales_counts() {
+        let mut a = NewAnalysis {
+            duration: Some(300.0),
+            onset_count: Some(1500),
+            key_change_count: Some(3),
+            key_alternatives_count: Some(2),
+            ..Default::default()
+        };
+        extrapolate(&mut a, 1200.0);
+        assert_eq!(a.duration, Some(1200.0));
+        assert_eq!(a.onset_count, Some(6000));
+        assert_eq!(a.key_change_count, Some(12));
+        assert_eq!(a.key_alternatives_count, Some(2));
+        assert!(a.preview);
+    }
+}
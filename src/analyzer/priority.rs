@@ -0,0 +1,282 @@
+//! Analysis order: the tracks likely to matter first.
+//!
+//! `analyze --priority "band=gd,year>=1972,duration>600"` sorts the queue by
+//! the listed conditions — a track meeting the first condition goes ahead of
+//! every track that doesn't, the second condition breaks ties among those, and
+//! so on — then longest first, so an interrupted overnight run has already
+//! scored the long jams instead of four-minute filler in path order.
+//! `--priority` with no conditions is just longest first.
+//!
+//! Conditions are `field op value` with `=`, `!=`, `<`, `<=`, `>`, `>=`:
+//!
+//! - `band`: band code or name (`gd`, `Phish`); `=` and `!=` only.
+//! - `year`: four-digit year of the show date.
+//! - `date`: show date compared as text; `=` matches a prefix (`date=1977-05`).
+//! - `duration`: track length in seconds, or with a unit (`10m`, `1h`).
+
+use crate::db::Database;
+use crate::db::models::Track;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("Invalid priority condition '{term}': {message}")]
+pub struct PriorityError {
+    pub term: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    /// Canonical band name.
+    Band {
+        op: Op,
+        name: String,
+    },
+    Year {
+        op: Op,
+        year: i32,
+    },
+    Date {
+        op: Op,
+        date: String,
+    },
+    /// Seconds.
+    Duration {
+        op: Op,
+        secs: f64,
+    },
+}
+
+/// Everything priority conditions can look at for one track.
+#[derive(Debug, Clone, Default)]
+pub struct TrackFacts {
+    /// Canonical band name (parsed band, else the artist tag).
+    pub band: Option<String>,
+    pub date: Option<String>,
+    pub duration_secs: Option<f64>,
+}
+
+/// A parsed `--priority` expression.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Priority {
+    conditions: Vec<Condition>,
+}
+
+impl Priority {
+    /// Parse comma-separated conditions; an empty expression orders by length only.
+    pub fn parse(expr: &str) -> Result<Self, PriorityError> {
+        let conditions = expr
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(parse_condition)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { conditions })
+    }
+
+    /// Which conditions a track meets, in order.
+    fn matches(&self, facts: &TrackFacts) -> Vec<bool> {
+        self.conditions.iter().map(|c| c.holds(facts)).collect()
+    }
+
+    /// Reorder `tracks`: most important conditions met first, then longest.
+    /// The original order breaks remaining ties.
+    pub fn sort(&self, tracks: &mut [Track], facts: &HashMap<i64, TrackFacts>) {
+        let none = TrackFacts::default();
+        tracks.sort_by_cached_key(|t| {
+            let f = facts.get(&t.id).unwrap_or(&none);
+            let millis = (f.duration_secs.unwrap_or(0.0) * 1000.0).round() as i64;
+            (Reverse(self.matches(f)), Reverse(millis))
+        });
+    }
+}
+
+impl Condition {
+    fn holds(&self, f: &TrackFacts) -> bool {
+        match self {
+            Condition::Band { op, name } => {
+                let same = f
+                    .band
+                    .as_ref()
+                    .is_some_and(|b| b.eq_ignore_ascii_case(name));
+                same == (*op == Op::Eq)
+            }
+            Condition::Year { op, year } => f
+                .date
+                .as_deref()
+                .and_then(|d| d.get(..4)?.parse::<i32>().ok())
+                .is_some_and(|y| op.holds(y.cmp(year))),
+            Condition::Date { op, date } => f.date.as_deref().is_some_and(|d| {
+                if *op == Op::Eq {
+                    d.starts_with(date.as_str())
+                } else {
+                    op.holds(d.cmp(date))
+                }
+            }),
+            Condition::Duration { op, secs } => {
+                f.duration_secs.is_some_and(|d| op.holds(d.total_cmp(secs)))
+            }
+        }
+    }
+}
+
+fn parse_condition(term: &str) -> Result<Condition, PriorityError> {
+    let err = |message: &str| PriorityError {
+        term: term.to_string(),
+        message: message.to_string(),
+    };
+    let start = term
+        .find(['=', '!', '<', '>'])
+        .ok_or_else(|| err("expected field, operator, and value (e.g. year>=1972)"))?;
+    let (field, rest) = term.split_at(start);
+    let (op, value) = [
+        (">=", Op::Ge),
+        ("<=", Op::Le),
+        ("!=", Op::Ne),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+        ("=", Op::Eq),
+    ]
+    .iter()
+    .find_map(|(sym, op)| rest.strip_prefix(sym).map(|v| (*op, v.trim())))
+    .ok_or_else(|| err("unknown operator"))?;
+    if value.is_empty() {
+        return Err(err("missing value"));
+    }
+    match field.trim().to_lowercase().as_str() {
+        "band" => {
+            if !matches!(op, Op::Eq | Op::Ne) {
+                return Err(err("band only supports = and !="));
+            }
+            Ok(Condition::Band {
+                op,
+                name: crate::bands::registry().resolve_canonical_name(value),
+            })
+        }
+        "year" => Ok(Condition::Year {
+            op,
+            year: value.parse().map_err(|_| err("year must be a number"))?,
+        }),
+        "date" => Ok(Condition::Date {
+            op,
+            date: value.to_string(),
+        }),
+        "duration" => {
+            // Bare numbers are seconds here, unlike the query language's minutes
+            let secs = value
+                .parse::<f64>()
+                .ok()
+                .or_else(|| crate::query::parse_duration_secs(value))
+                .ok_or_else(|| err("duration must be seconds or a length like 10m"))?;
+            Ok(Condition::Duration { op, secs })
+        }
+        _ => Err(err("unknown field (use band, year, date, or duration)")),
+    }
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Band, date, and length of every track, for ordering the analysis queue.
+    pub fn priority_facts(&self) -> crate::db::Result<HashMap<i64, TrackFacts>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, COALESCE(parsed_band, artist), parsed_date, duration_secs FROM tracks",
+        )?;
+        let registry = crate::bands::registry();
+        let rows = stmt
+            .query_map([], |row| {
+                let band: Option<String> = row.get(1)?;
+                Ok((
+                    row.get(0)?,
+                    TrackFacts {
+                        band: band.map(|b| registry.resolve_canonical_name(&b)),
+                        date: row.get(2)?,
+                        duration_secs: row.get(3)?,
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: i64) -> Track {
+        Track {
+            id,
+            file_path: format!("/m/{id}.flac"),
+            format: "flac".into(),
+            artist: None,
+            parsed_band: None,
+            parsed_date: None,
+        }
+    }
+
+    fn facts(band: &str, date: &str, secs: f64) -> TrackFacts {
+        TrackFacts {
+            band: Some(crate::bands::registry().resolve_canonical_name(band)),
+            date: Some(date.into()),
+            duration_secs: Some(secs),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Priority::parse("").unwrap().conditions.is_empty());
+        assert!(Priority::parse("year>=1972, duration>10m").is_ok());
+        for bad in ["year", "tempo>100", "band>gd", "year>=seventy", "duration>"] {
+            assert!(Priority::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_priority_order() {
+        crate::bands::init_default();
+        let facts: HashMap<i64, TrackFacts> = [
+            (1, facts("Phish", "1997-11-17", 1500.0)),
+            (2, facts("gd", "1969-02-27", 1400.0)),
+            (3, facts("gd", "1977-05-08", 240.0)),
+            (4, facts("gd", "1977-05-08", 1200.0)),
+            (5, facts("gd", "1973-06-10", 300.0)),
+        ]
+        .into_iter()
+        .collect();
+        let ids = |p: &str| {
+            let mut tracks: Vec<Track> = (1..=6).map(track).collect();
+            Priority::parse(p).unwrap().sort(&mut tracks, &facts);
+            tracks.iter().map(|t| t.id).collect::<Vec<_>>()
+        };
+        // Band first, then year among the band's tracks, then longest; track 6 has no facts
+        assert_eq!(ids("band=gd,year>=1972"), vec![4, 5, 3, 2, 1, 6]);
+        assert_eq!(ids(""), vec![1, 2, 4, 5, 3, 6]);
+        assert_eq!(ids("date=1977-05,duration>600"), vec![4, 3, 1, 2, 5, 6]);
+    }
+}
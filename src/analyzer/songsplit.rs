@@ -0,0 +1,470 @@
+//! Song boundary detection inside one continuous recording.
+//!
+//! Some audience transfers arrive as a single file per set with no track
+//! splits. Each second of audio is reduced to a pitch-class profile (chroma,
+//! from Goertzel filters over three octaves) plus level and zero-crossing rate
+//! as a coarse timbre. Two cues then mark likely song changes:
+//!
+//! - **Novelty**: the average profile over the 30s before a point differs from
+//!   the 30s after it — a new key, a new groove, a different instrument mix.
+//! - **Breaks**: the level drops well below the recording's typical loudness
+//!   (a pause between songs), or the spectrum turns to broadband noise with no
+//!   pitch centre (applause and crowd).
+//!
+//! Peaks of the combined score are accepted strongest-first, keeping every
+//! song at least `min_song_secs` long, and each split is nudged to the quietest
+//! second nearby so it lands in the gap rather than on the last chord.
+//! Segues carry no break, so only a strong change of key or texture splits them.
+
+/// Feature frame length and hop (seconds).
+pub const FRAME_SECS: f64 = 1.0;
+
+/// Audio is decimated to roughly this rate before chroma extraction.
+const ANALYSIS_RATE: f64 = 11_025.0;
+
+/// Samples per frame fed to the Goertzel filters (~0.37s at the analysis rate).
+const CHROMA_WINDOW: usize = 4096;
+
+/// Chroma pitch range, MIDI notes (A2 110 Hz to G#5 830 Hz).
+const LOWEST_NOTE: u32 = 45;
+const HIGHEST_NOTE: u32 = 80;
+
+/// Frames averaged on each side of a candidate boundary for novelty.
+const NOVELTY_WINDOW_SECS: f64 = 30.0;
+
+/// A frame this far below the recording's median level counts as a break (dB).
+const BREAK_DROP_DB: f64 = 12.0;
+
+/// Chroma flatness (normalized entropy) above which a frame sounds like noise.
+const NOISE_FLATNESS: f64 = 0.9;
+
+/// Break cues are spread over this many seconds either side (a pause is short
+/// next to the novelty window).
+const BREAK_SPREAD_SECS: f64 = 3.0;
+
+/// Weight of the break cue against novelty in the boundary score.
+const BREAK_WEIGHT: f64 = 0.35;
+
+/// Novelty is scaled by the recording's largest change, but never by less than
+/// this, so a recording with no real change doesn't blow noise up to 1.0.
+const NOVELTY_FLOOR: f64 = 0.3;
+
+/// Splits move to the quietest frame within this distance (seconds)...
+const SNAP_SECS: f64 = 10.0;
+
+/// ...if it's at least this much quieter than the peak itself (dB).
+const SNAP_MIN_DROP_DB: f64 = 3.0;
+
+/// Tuning for `detect_songs`.
+#[derive(Debug, Clone, Copy)]
+pub struct SplitParams {
+    /// Shortest song allowed between two splits (seconds).
+    pub min_song_secs: f64,
+    /// Minimum boundary score (0-1) for a split.
+    pub threshold: f64,
+}
+
+impl Default for SplitParams {
+    fn default() -> Self {
+        Self {
+            min_song_secs: 120.0,
+            // A break alone isn't enough (quiet passages happen mid-jam), and
+            // novelty alone needs nearly the recording's sharpest change
+            threshold: 0.6,
+        }
+    }
+}
+
+/// A proposed split point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SongBoundary {
+    /// Start of the next song (seconds).
+    pub time: f64,
+    /// Boundary score (0-1): novelty plus break cue.
+    pub score: f64,
+    /// Whether a pause or applause was heard at the split.
+    pub has_break: bool,
+}
+
+/// Per-second features. Frames from consecutive files can be concatenated
+/// and searched as one recording (see `detect_in_frames`).
+pub struct Frame {
+    pub(super) chroma: [f64; 12],
+    pub(super) level_db: f64,
+    pub(super) zcr: f64,
+}
+
+/// Propose song boundaries in mono samples, in time order.
+pub fn detect_songs(samples: &[f32], sample_rate: f32, params: &SplitParams) -> Vec<SongBoundary> {
+    detect_in_frames(&frames(samples, sample_rate), params)
+}
+
+/// Reduce mono samples to one `Frame` per `FRAME_SECS` (a trailing partial
+/// frame is dropped).
+pub fn frames(samples: &[f32], sample_rate: f32) -> Vec<Frame> {
+    let (decimated, rate) = decimate(samples, sample_rate as f64);
+    let frame_len = (rate * FRAME_SECS) as usize;
+    if frame_len == 0 {
+        return Vec::new();
+    }
+    decimated
+        .chunks_exact(frame_len)
+        .map(|f| frame_features(f, rate))
+        .collect()
+}
+
+/// Boundary score (0-1) at each frame, and whether a pause or applause was
+/// heard around it.
+pub fn boundary_scores(frames: &[Frame]) -> Vec<(f64, bool)> {
+    if frames.is_empty() {
+        return Vec::new();
+    }
+    let novelty = novelty_curve(frames);
+    let breaks = break_curve(frames);
+    novelty
+        .iter()
+        .zip(&breaks)
+        .map(|(n, b)| {
+            (
+                ((1.0 - BREAK_WEIGHT) * n + BREAK_WEIGHT * b).clamp(0.0, 1.0),
+                *b > 0.5,
+            )
+        })
+        .collect()
+}
+
+/// Propose song boundaries in a sequence of frames, in time order.
+pub fn detect_in_frames(frames: &[Frame], params: &SplitParams) -> Vec<SongBoundary> {
+    let min_gap = (params.min_song_secs / FRAME_SECS).ceil() as usize;
+    if frames.len() < 2 * min_gap.max(1) {
+        return Vec::new();
+    }
+
+    let scored = boundary_scores(frames);
+    let score: Vec<f64> = scored.iter().map(|&(s, _)| s).collect();
+
+    // Local maxima over the threshold, strongest first
+    let mut peaks: Vec<usize> = (1..score.len() - 1)
+        .filter(|&i| score[i] >= params.threshold)
+        .filter(|&i| score[i] >= score[i - 1] && score[i] >= score[i + 1])
+        .collect();
+    peaks.sort_by(|&a, &b| score[b].total_cmp(&score[a]));
+
+    let snap = (SNAP_SECS / FRAME_SECS) as usize;
+    // (split frame, peak frame)
+    let mut accepted: Vec<(usize, usize)> = Vec::new();
+    for peak in peaks {
+        let at = quietest_near(frames, peak, snap);
+        let fits = at >= min_gap
+            && frames.len() - at >= min_gap
+            && accepted.iter().all(|&(a, _)| a.abs_diff(at) >= min_gap);
+        if fits {
+            accepted.push((at, peak));
+        }
+    }
+    accepted.sort_unstable();
+
+    accepted
+        .into_iter()
+        .map(|(at, peak)| SongBoundary {
+            time: at as f64 * FRAME_SECS,
+            score: score[peak],
+            has_break: scored[peak].1,
+        })
+        .collect()
+}
+
+/// (start, end) of each song given the splits and the recording length.
+pub fn song_spans(boundaries: &[SongBoundary], duration: f64) -> Vec<(f64, f64)> {
+    let mut starts = vec![0.0];
+    starts.extend(boundaries.iter().map(|b| b.time));
+    let mut ends: Vec<f64> = starts[1..].to_vec();
+    ends.push(duration);
+    starts.into_iter().zip(ends).collect()
+}
+
+/// Average groups of samples down to about `ANALYSIS_RATE`. Averaging is a
+/// crude low-pass, but chroma only looks below 1 kHz.
+fn decimate(samples: &[f32], rate: f64) -> (Vec<f32>, f64) {
+    let factor = (rate / ANALYSIS_RATE).floor().max(1.0) as usize;
+    if factor == 1 {
+        return (samples.to_vec(), rate);
+    }
+    let out = samples
+        .chunks_exact(factor)
+        .map(|c| c.iter().sum::<f32>() / factor as f32)
+        .collect();
+    (out, rate / factor as f64)
+}
+
+fn frame_features(frame: &[f32], rate: f64) -> Frame {
+    let power = frame.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / frame.len() as f64;
+    let level_db = if power < 1e-20 {
+        -100.0
+    } else {
+        10.0 * power.log10()
+    };
+    let crossings = frame
+        .windows(2)
+        .filter(|p| (p[0] >= 0.0) != (p[1] >= 0.0))
+        .count();
+    let zcr = crossings as f64 / frame.len() as f64;
+    Frame {
+        chroma: chroma(&frame[..frame.len().min(CHROMA_WINDOW)], rate),
+        level_db,
+        zcr,
+    }
+}
+
+/// Pitch-class energy from one Goertzel filter per semitone, Hann windowed,
+/// normalized to sum to 1 (uniform for digital silence).
+fn chroma(window: &[f32], rate: f64) -> [f64; 12] {
+    let n = window.len();
+    let hann: Vec<f64> = (0..n)
+        .map(|i| 0.5 - 0.5 * (std::f64::consts::TAU * i as f64 / n as f64).cos())
+        .collect();
+    let mut bins = [0.0; 12];
+    for note in LOWEST_NOTE..=HIGHEST_NOTE {
+        let freq = 440.0 * 2f64.powf((note as f64 - 69.0) / 12.0);
+        let coeff = 2.0 * (std::f64::consts::TAU * freq / rate).cos();
+        let (mut s1, mut s2) = (0.0, 0.0);
+        for (x, w) in window.iter().zip(&hann) {
+            let s = *x as f64 * w + coeff * s1 - s2;
+            s2 = s1;
+            s1 = s;
+        }
+        bins[(note % 12) as usize] += (s1 * s1 + s2 * s2 - coeff * s1 * s2).max(0.0);
+    }
+    let total: f64 = bins.iter().sum();
+    if total < 1e-12 {
+        return [1.0 / 12.0; 12];
+    }
+    bins.map(|b| b / total)
+}
+
+/// Normalized entropy of a chroma profile: 1.0 flat (noise), lower when pitched.
+fn flatness(chroma: &[f64; 12]) -> f64 {
+    let entropy: f64 = chroma
+        .iter()
+        .filter(|&&p| p > 0.0)
+        .map(|&p| -p * p.ln())
+        .sum();
+    entropy / 12f64.ln()
+}
+
+/// Change between the windows before and after each frame, scaled to 0-1 by the
+/// recording's largest change (see `NOVELTY_FLOOR`).
+fn novelty_curve(frames: &[Frame]) -> Vec<f64> {
+    let w = (NOVELTY_WINDOW_SECS / FRAME_SECS) as usize;
+    let mut novelty = vec![0.0; frames.len()];
+    if frames.len() < 2 * w {
+        return novelty;
+    }
+
+    // Level and ZCR on a z-score scale so they're comparable with chroma distance
+    let (level_mean, level_std) = mean_std(frames.iter().map(|f| f.level_db), 3.0);
+    let (zcr_mean, zcr_std) = mean_std(frames.iter().map(|f| f.zcr), 0.01);
+    let timbre: Vec<[f64; 2]> = frames
+        .iter()
+        .map(|f| {
+            [
+                (f.level_db - level_mean) / level_std,
+                (f.zcr - zcr_mean) / zcr_std,
+            ]
+        })
+        .collect();
+
+    for (t, value) in novelty
+        .iter_mut()
+        .enumerate()
+        .take(frames.len() - w + 1)
+        .skip(w)
+    {
+        let before = &frames[t - w..t];
+        let after = &frames[t..t + w];
+        let chroma_change = cosine_distance(&mean_chroma(before), &mean_chroma(after));
+        let timbre_change = {
+            let a = mean_timbre(&timbre[t - w..t]);
+            let b = mean_timbre(&timbre[t..t + w]);
+            ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+        };
+        *value = chroma_change + 0.25 * timbre_change;
+    }
+
+    let scale = novelty.iter().copied().fold(NOVELTY_FLOOR, f64::max);
+    novelty.iter_mut().for_each(|v| *v /= scale);
+    novelty
+}
+
+/// 1.0 around pauses and applause, 0.0 under music.
+fn break_curve(frames: &[Frame]) -> Vec<f64> {
+    let mut levels: Vec<f64> = frames.iter().map(|f| f.level_db).collect();
+    levels.sort_by(f64::total_cmp);
+    let median_db = levels[levels.len() / 2];
+    let mut zcrs: Vec<f64> = frames.iter().map(|f| f.zcr).collect();
+    zcrs.sort_by(f64::total_cmp);
+    let median_zcr = zcrs[zcrs.len() / 2];
+
+    let raw: Vec<f64> = frames
+        .iter()
+        .map(|f| {
+            let quiet = f.level_db <= median_db - BREAK_DROP_DB;
+            let noisy = flatness(&f.chroma) >= NOISE_FLATNESS && f.zcr > 1.5 * median_zcr;
+            if quiet || noisy { 1.0 } else { 0.0 }
+        })
+        .collect();
+
+    let spread = (BREAK_SPREAD_SECS / FRAME_SECS) as usize;
+    (0..raw.len())
+        .map(|i| {
+            let lo = i.saturating_sub(spread);
+            let hi = (i + spread + 1).min(raw.len());
+            raw[lo..hi].iter().copied().fold(0.0, f64::max)
+        })
+        .collect()
+}
+
+/// Index of the quietest frame within `radius` of `center`, or `center` itself
+/// when nothing nearby is clearly quieter (a segue).
+fn quietest_near(frames: &[Frame], center: usize, radius: usize) -> usize {
+    let lo = center.saturating_sub(radius);
+    let hi = (center + radius + 1).min(frames.len());
+    let quietest = (lo..hi)
+        .min_by(|&a, &b| frames[a].level_db.total_cmp(&frames[b].level_db))
+        .unwrap_or(center);
+    if frames[quietest].level_db <= frames[center].level_db - SNAP_MIN_DROP_DB {
+        quietest
+    } else {
+        center
+    }
+}
+
+fn mean_chroma(frames: &[Frame]) -> [f64; 12] {
+    let mut sum = [0.0; 12];
+    for f in frames {
+        for (s, c) in sum.iter_mut().zip(&f.chroma) {
+            *s += c;
+        }
+    }
+    sum.map(|s| s / frames.len() as f64)
+}
+
+fn mean_timbre(values: &[[f64; 2]]) -> [f64; 2] {
+    let n = values.len() as f64;
+    let sum = values
+        .iter()
+        .fold([0.0, 0.0], |acc, v| [acc[0] + v[0], acc[1] + v[1]]);
+    [sum[0] / n, sum[1] / n]
+}
+
+fn cosine_distance(a: &[f64; 12], b: &[f64; 12]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    (1.0 - dot / (norm_a * norm_b)).max(0.0)
+}
+
+/// Mean and standard deviation, with the deviation floored at `min_std` so a
+/// near-constant feature doesn't turn rounding noise into change.
+fn mean_std(values: impl Iterator<Item = f64> + Clone, min_std: f64) -> (f64, f64) {
+    let n = values.clone().count().max(1) as f64;
+    let mean = values.clone().sum::<f64>() / n;
+    let var = values.map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    (mean, var.sqrt().max(min_std))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: f32 = 4000.0;
+
+    /// A triad at MIDI `root` (major), `secs` long, at -12 dBFS-ish.
+    fn chord(root: u32, secs: f64) -> Vec<f32> {
+        let notes = [root, root + 4, root + 7];
+        (0..(secs * SR as f64) as usize)
+            .map(|i| {
+                let t = i as f64 / SR as f64;
+                notes
+                    .iter()
+                    .map(|&n| {
+                        let f = 440.0 * 2f64.powf((n as f64 - 69.0) / 12.0);
+                        (std::f64::consts::TAU * f * t).sin() * 0.08
+                    })
+                    .sum::<f64>() as f32
+            })
+            .collect()
+    }
+
+    fn pause(secs: f64) -> Vec<f32> {
+        // Faint hiss, not digital silence
+        (0..(secs * SR as f64) as usize)
+            .map(|i| ((i * 7919 % 101) as f32 / 101.0 - 0.5) * 0.002)
+            .collect()
+    }
+
+    #[test]
+    fn test_splits_at_pauses_between_songs() {
+        let mut audio = chord(60, 200.0); // C major
+        audio.extend(pause(6.0));
+        audio.extend(chord(62, 180.0)); // D major
+        audio.extend(pause(6.0));
+        audio.extend(chord(57, 190.0)); // A major
+
+        let found = detect_songs(&audio, SR, &SplitParams::default());
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert!((200.0..=206.0).contains(&found[0].time), "{found:?}");
+        assert!((386.0..=392.0).contains(&found[1].time), "{found:?}");
+        assert!(found.iter().all(|b| b.has_break));
+    }
+
+    #[test]
+    fn test_segue_split_on_key_change() {
+        let mut audio = chord(60, 200.0);
+        audio.extend(chord(66, 200.0)); // F# major, no pause
+
+        let found = detect_songs(&audio, SR, &SplitParams::default());
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!((195.0..=205.0).contains(&found[0].time), "{found:?}");
+        assert!(!found[0].has_break);
+    }
+
+    #[test]
+    fn test_one_long_song_is_not_split() {
+        let audio = chord(60, 400.0);
+        assert!(detect_songs(&audio, SR, &SplitParams::default()).is_empty());
+    }
+
+    #[test]
+    fn test_min_song_length_respected() {
+        let mut audio = chord(60, 200.0);
+        audio.extend(pause(6.0));
+        audio.extend(chord(62, 60.0));
+        audio.extend(pause(6.0));
+        audio.extend(chord(57, 200.0));
+
+        let found = detect_songs(&audio, SR, &SplitParams::default());
+        assert!(found.windows(2).all(|w| w[1].time - w[0].time >= 120.0));
+        let total = audio.len() as f64 / SR as f64;
+        let spans = song_spans(&found, total);
+        assert_eq!(spans.len(), found.len() + 1);
+        assert_eq!(spans[0].0, 0.0);
+        assert_eq!(spans.last().unwrap().1, total);
+    }
+
+    #[test]
+    fn test_chroma_finds_pitch_class() {
+        let tone: Vec<f32> = chord(69, 1.0);
+        let c = chroma(&tone[..CHROMA_WINDOW.min(tone.len())], SR as f64);
+        // A major: A, C#, E
+        let mut order: Vec<usize> = (0..12).collect();
+        order.sort_by(|&a, &b| c[b].total_cmp(&c[a]));
+        let mut top: Vec<usize> = order[..3].to_vec();
+        top.sort_unstable();
+        assert_eq!(top, vec![1, 4, 9]);
+        assert!(flatness(&c) < NOISE_FLATNESS);
+    }
+}
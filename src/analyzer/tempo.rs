@@ -0,0 +1,194 @@
+//! Tempo octave correction (half/double-time ambiguity).
+//!
+//! Beat trackers often lock onto the eighth-note pulse of a mid-tempo song, so raw
+//! `tempo_bpm` piles up at implausible values — a spike near 190 in a library of
+//! mostly 90-130 BPM jams. Each raw estimate is compared with its half and double:
+//! a candidate scores by how strongly the onset train repeats at its beat period
+//! (autocorrelation) times a log-normal prior around a typical rock tempo. The raw
+//! value is kept unless an alternative clearly beats it, and the winner's margin
+//! over the runner-up becomes the confidence.
+//!
+//! A genuinely fast song with a strong half-time pulse is indistinguishable from
+//! an octave error by onsets alone; those come out halved with low confidence.
+
+/// Centre of the tempo prior, BPM.
+const PRIOR_CENTER_BPM: f64 = 110.0;
+/// Width of the tempo prior, in octaves (std of log2 tempo).
+const PRIOR_WIDTH_OCTAVES: f64 = 0.5;
+/// Plausible range for any estimate (matches the beat tracker's search range).
+const MIN_BPM: f64 = 30.0;
+const MAX_BPM: f64 = 300.0;
+/// An alternative must beat the raw tempo's score by this factor to replace it.
+const SWITCH_MARGIN: f64 = 1.15;
+/// Onset train resolution, seconds.
+const BIN_SECS: f64 = 0.01;
+/// Below this many onsets the autocorrelation is noise; fall back to the prior.
+const MIN_ONSETS: usize = 32;
+/// Confidence scale when only the prior decides (no usable onsets).
+const PRIOR_ONLY_CONFIDENCE: f64 = 0.5;
+
+/// A corrected tempo and how sure we are of the octave (0..1).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoEstimate {
+    pub bpm: f64,
+    pub confidence: f64,
+}
+
+/// Resolve half/double-time ambiguity in `raw_bpm` using onset times (seconds).
+/// With fewer than a few dozen onsets (e.g. rescoring stored rows, which don't
+/// keep onsets) only the prior decides, at reduced confidence.
+pub fn correct(raw_bpm: f64, onsets: &[f32]) -> Option<TempoEstimate> {
+    if !raw_bpm.is_finite() || raw_bpm <= 0.0 {
+        return None;
+    }
+    let train = onset_train(onsets);
+    let score = |bpm: f64| {
+        let periodicity = train.as_deref().map_or(1.0, |x| periodicity(x, bpm));
+        prior(bpm) * (0.05 + periodicity)
+    };
+
+    let raw_score = score(raw_bpm);
+    let mut scored: Vec<(f64, f64)> = [raw_bpm / 2.0, raw_bpm, raw_bpm * 2.0]
+        .into_iter()
+        .filter(|bpm| (MIN_BPM..=MAX_BPM).contains(bpm))
+        .map(|bpm| (bpm, score(bpm)))
+        .collect();
+    if scored.is_empty() {
+        return Some(TempoEstimate {
+            bpm: raw_bpm,
+            confidence: 0.0,
+        });
+    }
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let (best_bpm, best) = scored[0];
+    let bpm = if best_bpm != raw_bpm && best < raw_score * SWITCH_MARGIN {
+        raw_bpm
+    } else {
+        best_bpm
+    };
+    let chosen = if bpm == raw_bpm { raw_score } else { best };
+    let runner_up = scored
+        .iter()
+        .filter(|(b, _)| *b != bpm)
+        .map(|(_, s)| *s)
+        .fold(0.0, f64::max);
+    let mut confidence = if chosen > 0.0 {
+        (1.0 - runner_up / chosen).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    if train.is_none() {
+        confidence *= PRIOR_ONLY_CONFIDENCE;
+    }
+    Some(TempoEstimate { bpm, confidence })
+}
+
+/// Tempo of a stretch of onsets (seconds from its start): the beat period the
+/// onset train repeats at most strongly, weighted by the prior. None with too
+/// few onsets to tell.
+pub fn estimate(onsets: &[f32]) -> Option<f64> {
+    let x = onset_train(onsets)?;
+    let energy: f64 = x.iter().map(|v| v * v).sum();
+    let min_lag = (60.0 / MAX_BPM / BIN_SECS).ceil() as usize;
+    let max_lag = ((60.0 / MIN_BPM / BIN_SECS).floor() as usize).min(x.len() - 1);
+    (min_lag..=max_lag)
+        .map(|lag| {
+            let bpm = 60.0 / (lag as f64 * BIN_SECS);
+            let ac = x.iter().zip(&x[lag..]).map(|(a, b)| a * b).sum::<f64>() / energy;
+            (bpm, prior(bpm) * ac)
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(bpm, _)| bpm)
+}
+
+/// Log-normal tempo prior, 1.0 at the centre.
+fn prior(bpm: f64) -> f64 {
+    let octaves = (bpm / PRIOR_CENTER_BPM).log2() / PRIOR_WIDTH_OCTAVES;
+    (-0.5 * octaves * octaves).exp()
+}
+
+/// Onsets as an impulse train at `BIN_SECS` resolution, with neighbouring bins at
+/// half weight to tolerate timing jitter.
+fn onset_train(onsets: &[f32]) -> Option<Vec<f64>> {
+    if onsets.len() < MIN_ONSETS {
+        return None;
+    }
+    let last = onsets.iter().copied().fold(0.0f32, f32::max) as f64;
+    let len = (last / BIN_SECS).ceil() as usize + 3;
+    let mut x = vec![0.0; len];
+    for &t in onsets {
+        let i = (t.max(0.0) as f64 / BIN_SECS).round() as usize + 1;
+        x[i] = 1.0;
+        x[i - 1] = f64::max(x[i - 1], 0.5);
+        x[i + 1] = f64::max(x[i + 1], 0.5);
+    }
+    Some(x)
+}
+
+/// Normalized autocorrelation of the onset train at the beat period of `bpm`,
+/// best of the nearest lags.
+fn periodicity(x: &[f64], bpm: f64) -> f64 {
+    let energy: f64 = x.iter().map(|v| v * v).sum();
+    if energy == 0.0 {
+        return 0.0;
+    }
+    let lag = (60.0 / bpm / BIN_SECS).round() as usize;
+    (lag.saturating_sub(1)..=lag + 1)
+        .filter(|&l| l > 0 && l < x.len())
+        .map(|l| x.iter().zip(&x[l..]).map(|(a, b)| a * b).sum::<f64>() / energy)
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pulse(bpm: f64, secs: f64) -> Vec<f32> {
+        let period = 60.0 / bpm;
+        (0..(secs / period) as usize)
+            .map(|i| (i as f64 * period) as f32)
+            .collect()
+    }
+
+    #[test]
+    fn test_double_time_is_halved() {
+        // Quarter notes at 95 BPM, tracker reported the impossible double
+        let onsets = pulse(95.0, 120.0);
+        let est = correct(190.0, &onsets).unwrap();
+        assert!((est.bpm - 95.0).abs() < 1e-9, "{est:?}");
+        assert!(est.confidence > 0.3, "{est:?}");
+    }
+
+    #[test]
+    fn test_plausible_tempo_kept() {
+        let onsets = pulse(120.0, 120.0);
+        let est = correct(120.0, &onsets).unwrap();
+        assert_eq!(est.bpm, 120.0);
+    }
+
+    #[test]
+    fn test_half_time_is_doubled() {
+        let onsets = pulse(116.0, 120.0);
+        let est = correct(58.0, &onsets).unwrap();
+        assert!((est.bpm - 116.0).abs() < 1e-9, "{est:?}");
+    }
+
+    #[test]
+    fn test_estimate_from_onsets() {
+        for bpm in [72.0, 95.0, 120.0, 150.0] {
+            let est = estimate(&pulse(bpm, 60.0)).unwrap();
+            assert!((est - bpm).abs() < 1.5, "{bpm}: {est}");
+        }
+        assert_eq!(estimate(&pulse(120.0, 5.0)), None);
+    }
+
+    #[test]
+    fn test_prior_only_without_onsets() {
+        let est = correct(190.0, &[]).unwrap();
+        assert_eq!(est.bpm, 95.0);
+        assert!(est.confidence <= PRIOR_ONLY_CONFIDENCE);
+        assert!(correct(f64::NAN, &[]).is_none());
+    }
+}
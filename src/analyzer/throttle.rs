@@ -0,0 +1,150 @@
+//! Running analysis politely in the background.
+//!
+//! Two knobs from `[analysis]`: `nice`/`ionice` lower the scheduling and disk
+//! priority of the analysis threads, so anything interactive wins the CPU and
+//! disk whenever it wants them; `max_cpu_percent` caps how much of the time
+//! each analysis worker spends busy, sleeping between tracks to make up the
+//! difference, so fans and temperatures stay down even on an idle machine.
+//!
+//! Linux keeps nice and I/O priority per thread, and new threads inherit them
+//! from their creator. `lower_priority` is called on the main thread before
+//! any worker pool is built, so every decode and analysis thread starts out
+//! lowered.
+
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Disk priority for the analysis threads (`ionice`).
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IoPriority {
+    /// Leave it as inherited.
+    #[default]
+    Normal,
+    /// Best-effort class, lowest level: served after everyone else's
+    /// best-effort I/O.
+    Low,
+    /// Idle class: only served when no other process wants the disk.
+    Idle,
+}
+
+impl IoPriority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IoPriority::Normal => "normal",
+            IoPriority::Low => "low",
+            IoPriority::Idle => "idle",
+        }
+    }
+}
+
+/// Lower the calling thread's CPU (`nice`, 0-19) and disk priority. Threads
+/// it creates afterwards inherit both. Failures are logged, not fatal: a
+/// platform without ionice still analyzes, just less politely.
+pub fn lower_priority(nice: i32, io: IoPriority) {
+    if nice > 0 {
+        set_nice(nice);
+    }
+    if io != IoPriority::Normal {
+        set_io_priority(io);
+    }
+}
+
+#[cfg(unix)]
+fn set_nice(nice: i32) {
+    // SAFETY: setpriority only reads its arguments; 0 means the calling thread.
+    let rc = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) };
+    if rc == 0 {
+        log::info!("Analysis running at nice {nice}");
+    } else {
+        log::warn!(
+            "Failed to set nice {nice}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(unix))]
+fn set_nice(nice: i32) {
+    log::warn!("nice = {nice} is not supported on this platform; ignored");
+}
+
+#[cfg(target_os = "linux")]
+fn set_io_priority(io: IoPriority) {
+    // From linux/ioprio.h: the class sits above a 13-bit level.
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+    let ioprio = match io {
+        IoPriority::Normal => return,
+        IoPriority::Low => (2 << IOPRIO_CLASS_SHIFT) | 7,
+        IoPriority::Idle => 3 << IOPRIO_CLASS_SHIFT,
+    };
+    // SAFETY: ioprio_set takes plain integers; 0 means the calling thread.
+    let rc = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) };
+    if rc == 0 {
+        log::info!("Analysis running at I/O priority {}", io.as_str());
+    } else {
+        log::warn!(
+            "Failed to set I/O priority {}: {}",
+            io.as_str(),
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_io_priority(io: IoPriority) {
+    log::warn!(
+        "ionice = \"{}\" is only supported on Linux; ignored",
+        io.as_str()
+    );
+}
+
+/// Cooperative CPU cap: after each unit of work, the worker sleeps long
+/// enough that it was busy at most `max_cpu_percent` of the time.
+#[derive(Debug, Clone, Copy)]
+pub struct Throttle {
+    percent: u32,
+}
+
+impl Throttle {
+    /// `max_cpu_percent` of 100 (or more) never sleeps.
+    pub fn new(max_cpu_percent: u32) -> Self {
+        Self {
+            percent: max_cpu_percent.clamp(1, 100),
+        }
+    }
+
+    /// Run `work`, then pause in proportion to how long it took.
+    pub fn run<T>(&self, work: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = work();
+        let pause = self.pause_after(start.elapsed());
+        if !pause.is_zero() {
+            std::thread::sleep(pause);
+        }
+        out
+    }
+
+    /// Idle time that brings `busy` down to the configured share.
+    fn pause_after(&self, busy: Duration) -> Duration {
+        busy.mul_f64(f64::from(100 - self.percent) / f64::from(self.percent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_pause() {
+        let busy = Duration::from_secs(6);
+        assert_eq!(Throttle::new(100).pause_after(busy), Duration::ZERO);
+        assert_eq!(Throttle::new(50).pause_after(busy), Duration::from_secs(6));
+        assert_eq!(
+            Throttle::new(80).pause_after(busy),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(Throttle::new(0).percent, 1);
+    }
+}
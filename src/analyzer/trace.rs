@@ -0,0 +1,323 @@
+//! Single-track trace: re-run decode → engine → features → scores for one
+//! track, time each stage, and diff the result against the stored analysis —
+//! the workflow for "why did this track's groove drop 15 points after the
+//! update". Nothing is written to the library.
+//!
+//! The recomputed row goes through the same `store_full_analysis` as a real
+//! run, into a scratch in-memory database, so the diff covers exactly the
+//! scalar columns `analyze` writes (`db::columns::ANALYSIS_SCHEMA`, minus the
+//! JSON arrays kept in `analysis_blobs`).
+
+use std::time::{Duration, Instant};
+
+use rusqlite::params;
+use rusqlite::types::Value;
+
+use super::{AnalyzeError, analyze_decoded_track, load_track_audio};
+use crate::config::AnalysisTuning;
+use crate::db::columns::{ANALYSIS_SCHEMA, SCORE_COLUMNS};
+use crate::db::models::Track;
+use crate::db::{BLOB_COLUMNS, Database};
+
+/// One pipeline stage: its wall time and a summary of what it produced.
+pub struct Stage {
+    pub name: &'static str,
+    pub elapsed: Duration,
+    pub summary: String,
+}
+
+/// A column whose recomputed value differs from the stored one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub column: &'static str,
+    pub stored: Value,
+    pub traced: Value,
+}
+
+impl Change {
+    /// Numeric change (traced − stored); `None` unless both sides are numbers.
+    pub fn delta(&self) -> Option<f64> {
+        Some(number(&self.traced)? - number(&self.stored)?)
+    }
+
+    /// Size of the change relative to the stored value, for ranking features
+    /// on different scales. A value appearing or vanishing ranks first.
+    pub fn relative(&self) -> f64 {
+        match (self.delta(), number(&self.stored)) {
+            (Some(d), Some(s)) => d.abs() / s.abs().max(1e-9),
+            _ => f64::INFINITY,
+        }
+    }
+
+    pub fn is_score(&self) -> bool {
+        SCORE_COLUMNS.contains(&self.column)
+    }
+}
+
+/// Result of tracing one track.
+pub struct Trace {
+    pub stages: Vec<Stage>,
+    /// Whether there was a stored analysis to diff against.
+    pub stored: bool,
+    /// The stored analysis is an excerpt-based preview, so differences are expected.
+    pub stored_preview: bool,
+    /// Columns compared.
+    pub compared: usize,
+    /// Differing columns: scores first (in `SCORE_COLUMNS` order), then
+    /// features by relative change, largest first.
+    pub changes: Vec<Change>,
+}
+
+/// Re-analyze one track in full and diff it against the stored analysis.
+pub fn trace_track(
+    db: &Database,
+    track: &Track,
+    tuning: &AnalysisTuning,
+) -> Result<Trace, AnalyzeError> {
+    let started = Instant::now();
+    let audio = load_track_audio(track)?;
+    let buffer = &audio.buffer;
+    let frames = buffer.samples.len() / buffer.channels.max(1);
+    let decode = Stage {
+        name: "decode",
+        elapsed: started.elapsed(),
+        summary: format!(
+            "{} Hz, {} ch, {:.1} s",
+            buffer.sample_rate,
+            buffer.channels,
+            frames as f64 / buffer.sample_rate.max(1) as f64
+        ),
+    };
+
+    let analysis = analyze_decoded_track(track, audio, tuning, false)?;
+    let extraction = &analysis.extraction;
+    let traced = recomputed_values(track, extraction)?;
+    let stored = db.scalar_analysis_values(track.id)?;
+
+    let value = |column: &str| -> Option<&Value> {
+        scalar_columns()
+            .position(|c| c == column)
+            .map(|i| &traced[i])
+    };
+    let num = |column: &str| value(column).and_then(number);
+    let fmt = |column: &str, decimals: usize| match num(column) {
+        Some(v) => format!("{v:.decimals$}"),
+        None => "-".to_string(),
+    };
+    let key = match value("estimated_key") {
+        Some(Value::Text(k)) => k.clone(),
+        _ => "-".to_string(),
+    };
+    let summary = |stage: &str| -> String {
+        match stage {
+            "setup" if analysis.engine_built => "engine built".to_string(),
+            "setup" => "engine reused".to_string(),
+            "engine" => format!(
+                "tempo {} BPM, key {key}, {} onsets, {} beats",
+                fmt("tempo_bpm_corrected", 1),
+                fmt("onset_count", 0),
+                fmt("beat_count", 0)
+            ),
+            "boundaries" => format!(
+                "head {} dB ({}% silent), tail {} dB ({}% silent), {} cuts",
+                fmt("head_rms_db", 1),
+                fmt("head_silence_pct", 0),
+                fmt("tail_rms_db", 1),
+                fmt("tail_silence_pct", 0),
+                extraction.cuts.len()
+            ),
+            "features" => format!(
+                "{} of {} columns set, {} chords, {} segments, {} transitions",
+                traced.iter().filter(|v| **v != Value::Null).count(),
+                traced.len(),
+                extraction.chords.len(),
+                extraction.segments.len(),
+                extraction.transitions.len()
+            ),
+            "scores" => crate::scores::SCORES
+                .iter()
+                .map(|s| format!("{} {}", s.short, fmt(s.column, 1)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => String::new(),
+        }
+    };
+    let mut stages = vec![decode];
+    stages.extend(analysis.timings.iter().map(|&(name, elapsed)| Stage {
+        name,
+        elapsed,
+        summary: summary(name),
+    }));
+
+    let (stored_values, stored_preview) = match stored {
+        Some((values, preview)) => (Some(values), preview),
+        None => (None, false),
+    };
+    Ok(Trace {
+        stages,
+        stored: stored_values.is_some(),
+        stored_preview,
+        compared: traced.len(),
+        changes: stored_values.map(|s| diff(&s, &traced)).unwrap_or_default(),
+    })
+}
+
+/// `ANALYSIS_SCHEMA` columns stored in `analysis_results` itself.
+fn scalar_columns() -> impl Iterator<Item = &'static str> {
+    ANALYSIS_SCHEMA
+        .iter()
+        .map(|c| c.name)
+        .filter(|name| !BLOB_COLUMNS.contains(name))
+}
+
+/// Store the recomputed analysis in a scratch database and read its row back.
+fn recomputed_values(
+    track: &Track,
+    extraction: &super::features::ExtractionResult,
+) -> Result<Vec<Value>, AnalyzeError> {
+    let scratch = Database::open_in_memory()?;
+    scratch
+        .conn
+        .execute(
+            "INSERT INTO tracks (id, file_path, file_size, file_modified, format)
+             VALUES (?1, ?2, 0, '', ?3)",
+            params![track.id, track.file_path, track.format],
+        )
+        .map_err(crate::db::DbError::from)?;
+    scratch.store_full_analysis(
+        &extraction.analysis,
+        &extraction.chords,
+        &extraction.segments,
+        &extraction.tension_points,
+        &extraction.transitions,
+        &extraction.cuts,
+    )?;
+    Ok(scratch
+        .scalar_analysis_values(track.id)?
+        .map(|(values, _)| values)
+        .unwrap_or_default())
+}
+
+/// Columns whose values differ, scores first, then by relative change.
+fn diff(stored: &[Value], traced: &[Value]) -> Vec<Change> {
+    let mut changes: Vec<Change> = scalar_columns()
+        .zip(stored.iter().zip(traced))
+        .filter(|(_, (s, t))| !same(s, t))
+        .map(|(column, (s, t))| Change {
+            column,
+            stored: s.clone(),
+            traced: t.clone(),
+        })
+        .collect();
+    let score_rank = |c: &Change| SCORE_COLUMNS.iter().position(|s| *s == c.column);
+    changes.sort_by(|a, b| match (score_rank(a), score_rank(b)) {
+        (Some(x), Some(y)) => x.cmp(&y),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => b.relative().total_cmp(&a.relative()),
+    });
+    changes
+}
+
+/// A stored value as text: numbers to four decimals, NULL as `-`.
+pub fn display(v: &Value) -> String {
+    match v {
+        Value::Null => "-".to_string(),
+        Value::Integer(n) => n.to_string(),
+        Value::Real(x) => format!("{x:.4}"),
+        Value::Text(s) => s.clone(),
+        Value::Blob(b) => format!("<{} bytes>", b.len()),
+    }
+}
+
+/// Equal values, with numbers compared to within float noise.
+fn same(a: &Value, b: &Value) -> bool {
+    match (number(a), number(b)) {
+        (Some(x), Some(y)) => (x - y).abs() <= 1e-9 * x.abs().max(y.abs()).max(1.0),
+        _ => a == b,
+    }
+}
+
+fn number(v: &Value) -> Option<f64> {
+    match v {
+        Value::Integer(n) => Some(*n as f64),
+        Value::Real(x) => Some(*x),
+        _ => None,
+    }
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// A track's scalar analysis values in `ANALYSIS_SCHEMA` order (blob
+    /// columns skipped), and whether the analysis is a preview. `None` if the
+    /// track isn't analyzed.
+    fn scalar_analysis_values(
+        &self,
+        track_id: i64,
+    ) -> crate::db::Result<Option<(Vec<Value>, bool)>> {
+        let columns: Vec<&str> = scalar_columns().collect();
+        let sql = format!(
+            "SELECT {}, preview FROM analysis_results WHERE track_id = ?1",
+            columns.join(", ")
+        );
+        let result = self.conn.query_row(&sql, [track_id], |row| {
+            let values = (0..columns.len())
+                .map(|i| row.get::<_, Value>(i))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok((values, row.get::<_, bool>(columns.len())?))
+        });
+        match result {
+            Ok(row) => Ok(Some(row)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewAnalysis;
+
+    #[test]
+    fn test_diff_orders_scores_then_features() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format)
+                 VALUES (7, '/m/t.flac', 0, '', 'flac')",
+                [],
+            )
+            .unwrap();
+        assert_eq!(db.scalar_analysis_values(7).unwrap(), None);
+
+        let analysis = |groove: f64, onsets: i32, flux: f64| NewAnalysis {
+            track_id: 7,
+            groove_score: Some(groove),
+            energy_score: Some(40.0),
+            onset_count: Some(onsets),
+            spectral_flux_mean: Some(flux),
+            estimated_key: Some("E minor".into()),
+            ..Default::default()
+        };
+        db.store_full_analysis(&analysis(79.0, 1000, 2.0), &[], &[], &[], &[], &[])
+            .unwrap();
+        let (before, preview) = db.scalar_analysis_values(7).unwrap().unwrap();
+        assert!(!preview);
+        assert_eq!(before.len(), scalar_columns().count());
+
+        db.store_full_analysis(&analysis(64.0, 1010, 3.0), &[], &[], &[], &[], &[])
+            .unwrap();
+        let (after, _) = db.scalar_analysis_values(7).unwrap().unwrap();
+        let changes = diff(&before, &after);
+        let columns: Vec<&str> = changes.iter().map(|c| c.column).collect();
+        assert_eq!(
+            columns,
+            vec!["groove_score", "spectral_flux_mean", "onset_count"]
+        );
+        assert_eq!(changes[0].delta(), Some(-15.0));
+        assert!(changes[0].is_score());
+        assert!(diff(&after, &after).is_empty());
+    }
+}
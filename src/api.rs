@@ -0,0 +1,882 @@
+//! Read-only HTTP metadata API for player plugins.
+//!
+//! `setbreak serve` answers a few JSON GET endpoints so an MPD, beets, or
+//! foobar2000 plugin can show "Groove 72, Transcendence 81" for the playing
+//! track:
+//!
+//! - `GET /track?path=<file>&similar=N`: scores, a one-line summary, and the
+//!   N (default 5) most similar tracks.
+//! - `GET /similar?path=<file>&limit=N`: similar tracks only (default 10).
+//! - `GET /health`
+//!
+//! Players often know a file relative to their music directory, so `path`
+//! matches a stored path exactly or, failing that, as its unique trailing
+//! portion (`gd1977-05-08/d1t01.flac`). Requests are plain HTTP/1.1 without
+//! keep-alive, and nothing here writes to the database.
+//!
+//! A small pool of reader threads takes request heads off the connections,
+//! each within one deadline, and hands them to the thread holding the database
+//! connection. Every client address may only have a few connections open at a
+//! time, so a client opening idle sockets ties up its own share and no more.
+//!
+//! `serve --public` swaps those endpoints for a sanitized subset meant for the
+//! open internet: bands, dates, titles and scores, never a file path.
+//!
+//! - `GET /top?score=<name>&band=<band>&song=<title>&limit=N&offset=N`: a
+//!   ranking (default 25, at most 100 rows).
+//! - `GET /bands`: bands with analyzed shows and tracks.
+//! - `GET /health`
+//!
+//! Everything but `/health` needs the token stored as the `serve` credential,
+//! sent as `Authorization: Bearer <token>` (never in the URL, where proxies and
+//! access logs would keep it). Each client address gets a fixed number of
+//! requests per minute, counted as its connections are accepted, before any of
+//! the request is read.
+
+use crate::db::Database;
+use crate::db::columns::{BAND_EXPR, LIVE_ONLY, NOT_GARBAGE};
+use crate::db::models::{TopFilter, TrackScore};
+use crate::remote::percent_decode;
+use crate::scores::{self, Direction};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Credential service holding the `--public` access token.
+pub const CREDENTIAL: &str = "serve";
+
+/// Default requests per client address per minute with `--public`.
+pub const DEFAULT_RATE_LIMIT: u32 = 60;
+
+/// Default similar tracks in a `/track` response.
+const DEFAULT_TRACK_SIMILAR: usize = 5;
+
+/// Default and maximum `limit` for `/similar`.
+const DEFAULT_SIMILAR: usize = 10;
+const MAX_SIMILAR: usize = 50;
+
+/// Default and maximum `limit` for the public `/top`.
+const DEFAULT_PUBLIC_TOP: usize = 25;
+const MAX_PUBLIC_TOP: usize = 100;
+
+/// Scores named in the one-line summary.
+const SUMMARY_SCORES: usize = 2;
+
+/// Longest request head accepted.
+const MAX_HEAD_BYTES: u64 = 16 * 1024;
+
+/// The whole request head must arrive within this, however it's split up.
+const HEAD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A client not reading its response can't hold a reader longer than this.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Threads reading request heads. As many accepted connections again may wait
+/// for one; beyond that, new connections are turned away with `503`.
+const READERS: usize = 16;
+
+/// Connections one client address may have open at once.
+const MAX_CONNECTIONS_PER_CLIENT: usize = 4;
+
+/// Status and JSON body of a response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// Who may use `serve --public`, and how often.
+#[derive(Debug)]
+pub struct PublicAccess {
+    /// Required on every request but `/health` (`None`: open to anyone).
+    token: Option<String>,
+    per_minute: u32,
+    /// Start of each client's current minute and its requests in it.
+    windows: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl PublicAccess {
+    pub fn new(token: Option<String>, per_minute: u32) -> Self {
+        Self {
+            token,
+            per_minute,
+            windows: HashMap::new(),
+        }
+    }
+
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Count a connection from `client` and return the rejection, if any.
+    /// Every connection counts against the limit, so a wrong token can't be
+    /// retried faster than a right one.
+    fn limit(&mut self, client: IpAddr, now: Instant) -> Option<Response> {
+        (!self.allow(client, now)).then(|| {
+            Response::error(
+                429,
+                format!("more than {} requests a minute", self.per_minute),
+            )
+        })
+    }
+
+    /// The rejection for a request without the right token, if any.
+    fn authorize(&self, target: &str, authorization: Option<&str>) -> Option<Response> {
+        let path = target.split_once('?').map_or(target, |(path, _)| path);
+        let token = self.token.as_deref().filter(|_| path != "/health")?;
+        match authorization.and_then(|a| a.strip_prefix("Bearer ")) {
+            Some(given) if same_token(given.trim(), token) => None,
+            Some(_) => Some(Response::error(401, "wrong token")),
+            None => Some(Response::error(
+                401,
+                "send the token as `Authorization: Bearer <token>`",
+            )),
+        }
+    }
+
+    fn allow(&mut self, client: IpAddr, now: Instant) -> bool {
+        const WINDOW: Duration = Duration::from_secs(60);
+        // Forget finished windows now and then so the map stays small
+        if self.windows.len() > 10_000 {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+        }
+        let (start, count) = self.windows.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= self.per_minute
+    }
+}
+
+/// Compare tokens without stopping at the first differing byte.
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Serve requests until the process is stopped. Connection errors are logged
+/// and the next connection is accepted. With `public`, only the sanitized
+/// endpoints are answered, to clients that pass its checks.
+pub fn serve(
+    db: &Database,
+    listener: TcpListener,
+    public: Option<PublicAccess>,
+) -> std::io::Result<()> {
+    let public = public.map(Mutex::new);
+    let open = OpenConnections::default();
+    let (accepted, waiting) = mpsc::sync_channel::<(TcpStream, Slot)>(READERS);
+    let waiting = Mutex::new(waiting);
+    let (ask, questions) = mpsc::channel::<Question>();
+
+    std::thread::scope(|scope| {
+        for _ in 0..READERS {
+            let ask = ask.clone();
+            let waiting = &waiting;
+            scope.spawn(move || {
+                loop {
+                    let next = waiting
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .recv();
+                    let Ok((stream, slot)) = next else { break };
+                    if let Err(e) = handle_connection(&stream, &ask) {
+                        log::debug!("API connection failed: {e}");
+                    }
+                    drop(slot);
+                }
+            });
+        }
+        drop(ask);
+
+        let (public, open) = (&public, &open);
+        scope.spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => admit(stream, public.as_ref(), open, &accepted),
+                    Err(e) => log::warn!("API accept failed: {e}"),
+                }
+            }
+        });
+
+        // The database connection stays on this thread
+        for question in questions {
+            let response = answer(
+                db,
+                &question.request_line,
+                question.authorization.as_deref(),
+                public.as_ref(),
+            );
+            // The reader may have given up on a stalled client meanwhile
+            question.reply.send(response).ok();
+        }
+    });
+    Ok(())
+}
+
+/// Count a new connection against its client's limits and queue it for a
+/// reader, or turn it away at once. Nothing has been read from it yet, so a
+/// client can't stall anyone by keeping its sockets quiet.
+fn admit(
+    stream: TcpStream,
+    public: Option<&Mutex<PublicAccess>>,
+    open: &OpenConnections,
+    queue: &SyncSender<(TcpStream, Slot)>,
+) {
+    let client = match stream.peer_addr() {
+        Ok(addr) => addr.ip(),
+        Err(e) => {
+            log::warn!("API connection failed: {e}");
+            return;
+        }
+    };
+    let limited = public.and_then(|public| {
+        public
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .limit(client, Instant::now())
+    });
+    if let Some(response) = limited {
+        return turn_away(&stream, &response);
+    }
+    let Some(slot) = open.take(client) else {
+        return turn_away(
+            &stream,
+            &Response::error(429, "too many open connections from one address"),
+        );
+    };
+    if let Err(TrySendError::Full((stream, _)) | TrySendError::Disconnected((stream, _))) =
+        queue.try_send((stream, slot))
+    {
+        turn_away(
+            &stream,
+            &Response::error(503, "too many connections; try again"),
+        );
+    }
+}
+
+fn turn_away(stream: &TcpStream, response: &Response) {
+    let written = stream
+        .set_write_timeout(Some(CLIENT_TIMEOUT))
+        .and_then(|()| write_response(stream, response));
+    if let Err(e) = written {
+        log::debug!("API connection failed: {e}");
+    }
+}
+
+/// A request head passed from a reader to the database thread.
+struct Question {
+    request_line: String,
+    authorization: Option<String>,
+    reply: mpsc::Sender<Response>,
+}
+
+/// Read one request head, have the database thread answer it, and write the
+/// response.
+fn handle_connection(stream: &TcpStream, ask: &mpsc::Sender<Question>) -> std::io::Result<()> {
+    let head = Deadline {
+        stream,
+        until: Instant::now() + HEAD_TIMEOUT,
+    };
+    let mut reader = BufReader::new(head.take(MAX_HEAD_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers; only the token in `Authorization` matters here
+    let mut authorization = None;
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && !line.trim_end().is_empty() {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+        line.clear();
+    }
+
+    let (reply, response) = mpsc::channel();
+    let question = Question {
+        request_line,
+        authorization,
+        reply,
+    };
+    let response = match ask.send(question) {
+        Ok(()) => response.recv().ok(),
+        Err(_) => None,
+    }
+    .unwrap_or_else(|| Response::error(500, "server is shutting down"));
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    write_response(stream, &response)
+}
+
+/// The response to a request line, checked against `public` if given.
+fn answer(
+    db: &Database,
+    request_line: &str,
+    authorization: Option<&str>,
+    public: Option<&Mutex<PublicAccess>>,
+) -> Response {
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next(), public) {
+        (Some("GET"), Some(target), None) => respond(db, target),
+        (Some("GET"), Some(target), Some(public)) => public
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .authorize(target, authorization)
+            .unwrap_or_else(|| respond_public(db, target)),
+        (Some(_), Some(_), _) => Response::error(405, "only GET is supported"),
+        _ => Response::error(400, "malformed request"),
+    }
+}
+
+/// A connection read against one deadline for the whole request head, so a
+/// client sending a byte at a time can't keep a reader past it.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "request head took too long",
+            ));
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Open connections per client address.
+#[derive(Clone, Default)]
+struct OpenConnections(Arc<Mutex<HashMap<IpAddr, usize>>>);
+
+impl OpenConnections {
+    /// A slot for one more connection from `client`, unless it has the most
+    /// it may have already.
+    fn take(&self, client: IpAddr) -> Option<Slot> {
+        let mut open = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = open.entry(client).or_insert(0);
+        if *count >= MAX_CONNECTIONS_PER_CLIENT {
+            return None;
+        }
+        *count += 1;
+        Some(Slot {
+            open: self.clone(),
+            client,
+        })
+    }
+}
+
+/// One open connection, counted until dropped.
+struct Slot {
+    open: OpenConnections,
+    client: IpAddr,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut open = self.open.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = open.get_mut(&self.client) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.client);
+            }
+        }
+    }
+}
+
+fn write_response(mut out: impl Write, response: &Response) -> std::io::Result<()> {
+    let body = response.body.to_string();
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let extra = match response.status {
+        401 => "WWW-Authenticate: Bearer\r\n",
+        429 => "Retry-After: 60\r\n",
+        503 => "Retry-After: 5\r\n",
+        _ => "",
+    };
+    write!(
+        out,
+        "HTTP/1.1 {} {reason}\r\n\
+         Content-Type: application/json; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         {extra}Connection: close\r\n\r\n{body}",
+        response.status,
+        body.len()
+    )?;
+    out.flush()
+}
+
+/// Answer a request for `target` (path and query string).
+pub fn respond(db: &Database, target: &str) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let param = |name: &str| query_param(query, name);
+    let limit = |name: &str, default: usize| {
+        param(name)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(default)
+            .min(MAX_SIMILAR)
+    };
+    let result = match path {
+        "/health" => return Response::ok(json!({ "status": "ok" })),
+        "/track" => with_track(db, param("path"), |id, track| {
+            track_body(db, id, track, limit("similar", DEFAULT_TRACK_SIMILAR))
+        }),
+        "/similar" => with_track(db, param("path"), |id, _| {
+            Ok(json!({ "similar": similar_body(db, id, limit("limit", DEFAULT_SIMILAR))? }))
+        }),
+        _ => return Response::error(404, format!("no endpoint {path}")),
+    };
+    result.unwrap_or_else(|e| Response::error(500, e.to_string()))
+}
+
+/// Answer a `--public` request: rankings and bands only, no file paths.
+pub fn respond_public(db: &Database, target: &str) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let param = |name: &str| query_param(query, name).filter(|v| !v.is_empty());
+    let number = |name: &str, default: usize| {
+        param(name)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(default)
+    };
+    let result = match path {
+        "/health" => return Response::ok(json!({ "status": "ok" })),
+        "/bands" => db.public_bands().map(|bands| {
+            Response::ok(json!({
+                "bands": bands
+                    .iter()
+                    .map(|(band, shows, tracks)| json!({
+                        "band": band,
+                        "shows": shows,
+                        "tracks": tracks,
+                    }))
+                    .collect::<Vec<_>>(),
+            }))
+        }),
+        "/top" => {
+            let key = param("score").unwrap_or_else(|| "transcendence".to_string());
+            let Some(score) = scores::get(&key) else {
+                return Response::error(400, format!("no score named {key}"));
+            };
+            let (band, song) = (param("band"), param("song"));
+            let filter = TopFilter {
+                band: band.as_deref(),
+                song: song.as_deref(),
+                offset: number("offset", 0),
+                live_only: true,
+                ..Default::default()
+            };
+            let limit = number("limit", DEFAULT_PUBLIC_TOP).min(MAX_PUBLIC_TOP);
+            db.query_top(score.column, limit, &filter).map(|tracks| {
+                Response::ok(json!({
+                    "score": score.name,
+                    "tracks": tracks.iter().map(public_track_json).collect::<Vec<_>>(),
+                }))
+            })
+        }
+        _ => return Response::error(404, format!("no endpoint {path}")),
+    };
+    result.unwrap_or_else(|e| Response::error(500, e.to_string()))
+}
+
+/// A track as `--public` shows it: no path, no database ids.
+fn public_track_json(t: &TrackScore) -> Value {
+    json!({
+        "band": t.band,
+        "date": t.date,
+        "title": t.title,
+        "duration_min": round1(t.duration_min),
+        "scores": scores::SCORES
+            .iter()
+            .filter_map(|s| Some((s.name.to_string(), json!(round1(t.score(s.column)?)))))
+            .collect::<serde_json::Map<_, _>>(),
+    })
+}
+
+/// Resolve `path` to an analyzed track and build the response from it.
+fn with_track(
+    db: &Database,
+    path: Option<String>,
+    body: impl FnOnce(i64, &TrackScore) -> crate::db::Result<Value>,
+) -> crate::db::Result<Response> {
+    let Some(path) = path.filter(|p| !p.is_empty()) else {
+        return Ok(Response::error(400, "missing ?path="));
+    };
+    let id = match db.track_ids_for_path(&path)?.as_slice() {
+        [] => return Ok(Response::error(404, format!("no track at {path}"))),
+        [id] => *id,
+        ids => {
+            return Ok(Response::error(
+                409,
+                format!(
+                    "{} tracks end with {path}; give more of the path",
+                    ids.len()
+                ),
+            ));
+        }
+    };
+    match db.query_tracks_by_id(&[id], false)?.first() {
+        Some((_, track)) => Ok(Response::ok(body(id, track)?)),
+        None => Ok(Response::error(
+            404,
+            format!("{path} is not analyzed (or is flagged as garbage)"),
+        )),
+    }
+}
+
+fn track_body(
+    db: &Database,
+    id: i64,
+    track: &TrackScore,
+    similar: usize,
+) -> crate::db::Result<Value> {
+    let mut body = track_json(track);
+    body["track_id"] = json!(id);
+    body["scores"] = scores::SCORES
+        .iter()
+        .filter_map(|s| Some((s.name.to_string(), json!(round1(track.score(s.column)?)))))
+        .collect::<serde_json::Map<_, _>>()
+        .into();
+    body["summary"] = json!(summary(track));
+    body["similar"] = similar_body(db, id, similar)?;
+    Ok(body)
+}
+
+/// Nearest neighbors from the stored lists, or ranked on the fly when those
+/// predate the current feature vector layout.
+fn similar_body(db: &Database, id: i64, limit: usize) -> crate::db::Result<Value> {
+    if limit == 0 {
+        return Ok(json!([]));
+    }
+    let outdated = db.similarity_format()?.is_some_and(|f| !f.is_current());
+    let rows = if outdated {
+        crate::similarity::similar_to_seeds(
+            db,
+            &[id],
+            &[],
+            limit,
+            false,
+            &crate::similarity::SimilarFilter::default(),
+        )?
+    } else {
+        db.query_similar(id, limit, false)?
+    };
+    Ok(rows
+        .iter()
+        .map(|(t, distance, similar_id)| {
+            let mut v = track_json(t);
+            v["track_id"] = json!(similar_id);
+            v["distance"] = json!((distance * 1000.0).round() / 1000.0);
+            v
+        })
+        .collect())
+}
+
+fn track_json(t: &TrackScore) -> Value {
+    json!({
+        "title": t.title,
+        "date": t.date,
+        "band": t.band,
+        "file_path": t.file_path,
+        "duration_min": round1(t.duration_min),
+    })
+}
+
+/// The highest "more is better" scores as display text: "Transcendence 81, Groove 72".
+pub fn summary(track: &TrackScore) -> String {
+    let mut ranked: Vec<(&str, f64)> = scores::SCORES
+        .iter()
+        .filter(|s| s.direction == Direction::HigherIsBetter)
+        .filter_map(|s| Some((s.label, track.score(s.column)?)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+        .iter()
+        .take(SUMMARY_SCORES)
+        .map(|(label, v)| format!("{label} {v:.0}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn round1(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+/// Decoded value of `name` in a query string (`+` is a space).
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (percent_decode(key) == name).then(|| percent_decode(&value.replace('+', " ")))
+    })
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// (band, shows, tracks) over analyzed, non-garbage live tracks, most
+    /// tracks first.
+    pub fn public_bands(&self) -> crate::db::Result<Vec<(String, i64, i64)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {BAND_EXPR} AS band, COUNT(DISTINCT t.parsed_date), COUNT(*)
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {NOT_GARBAGE} AND {LIVE_ONLY} AND {BAND_EXPR} != ''
+             GROUP BY band
+             ORDER BY COUNT(*) DESC, band"
+        ))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Tracks stored at `path`, or failing an exact match, whose path ends
+    /// with it after a separator.
+    pub fn track_ids_for_path(&self, path: &str) -> crate::db::Result<Vec<i64>> {
+        let collect = |sql: &str| -> crate::db::Result<Vec<i64>> {
+            let mut stmt = self.conn.prepare(sql)?;
+            let rows = stmt
+                .query_map([path], |row| row.get(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(rows)
+        };
+        let exact = collect("SELECT id FROM tracks WHERE file_path = ?1")?;
+        if !exact.is_empty() {
+            return Ok(exact);
+        }
+        let relative = path.trim_start_matches(['/', '\\']);
+        if relative.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM tracks
+             WHERE substr(file_path, -length(?1)) = ?1
+               AND substr(file_path, -length(?1) - 1, 1) IN ('/', '\\')
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map([relative], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{NewAnalysis, NewTrack};
+    use crate::db::queries::tests::new_track;
+
+    fn track(path: &str) -> NewTrack {
+        NewTrack {
+            title: Some("Morning Dew".to_string()),
+            parsed_band: Some("Grateful Dead".to_string()),
+            parsed_date: Some("1977-05-08".to_string()),
+            recording_type: Some("live".to_string()),
+            ..new_track(path)
+        }
+    }
+
+    #[test]
+    fn test_query_param() {
+        let q = "path=%2Fmusic%2Fgd77%20d1t01.flac&similar=3&x";
+        assert_eq!(
+            query_param(q, "path").as_deref(),
+            Some("/music/gd77 d1t01.flac")
+        );
+        assert_eq!(query_param("path=a+b", "path").as_deref(), Some("a b"));
+        assert_eq!(query_param(q, "x").as_deref(), Some(""));
+        assert_eq!(query_param(q, "limit"), None);
+    }
+
+    #[test]
+    fn test_track_endpoint() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db
+            .upsert_track(&track("/music/gd1977-05-08/d2t01.flac"))
+            .unwrap();
+        db.upsert_track(&track("/other/gd1977-05-08/d2t01.flac"))
+            .unwrap();
+        db.upsert_track(&track("/music/unanalyzed.flac")).unwrap();
+        db.store_analysis(&NewAnalysis {
+            track_id: id,
+            groove_score: Some(72.04),
+            transcendence_score: Some(81.0),
+            energy_score: Some(95.0),
+            ..NewAnalysis::default()
+        })
+        .unwrap();
+
+        let r = respond(&db, "/track?path=%2Fmusic%2Fgd1977-05-08%2Fd2t01.flac");
+        assert_eq!(r.status, 200);
+        assert_eq!(r.body["title"], "Morning Dew");
+        assert_eq!(r.body["scores"]["groove"], 72.0);
+        // Energy is descriptive, not "better", so it stays out of the summary
+        assert_eq!(r.body["summary"], "Transcendence 81, Groove 72");
+        assert_eq!(r.body["similar"], json!([]));
+
+        // Relative paths match a unique suffix
+        assert_eq!(respond(&db, "/track?path=unanalyzed.flac").status, 404);
+        assert_eq!(respond(&db, "/similar?path=d2t01.flac").status, 409);
+        assert_eq!(
+            respond(&db, "/track?path=music/gd1977-05-08/d2t01.flac").body["track_id"],
+            id
+        );
+        assert_eq!(respond(&db, "/track?path=t01.flac").status, 404);
+        assert_eq!(respond(&db, "/track").status, 400);
+        assert_eq!(respond(&db, "/nope").status, 404);
+    }
+
+    #[test]
+    fn test_public_endpoints() {
+        let db = Database::open_in_memory().unwrap();
+        let mut other = track("/music/jrad/d1t01.flac");
+        other.parsed_band = Some("JRAD".to_string());
+        let mut studio = track("/music/gd-studio/01.flac");
+        studio.recording_type = Some("studio".to_string());
+        for (t, groove) in [
+            (track("/music/gd1977-05-08/d2t01.flac"), 72.0),
+            (track("/music/gd1977-05-08/d2t02.flac"), 64.0),
+            (other, 90.0),
+            (studio, 99.0),
+        ] {
+            let id = db.upsert_track(&t).unwrap();
+            db.store_analysis(&NewAnalysis {
+                track_id: id,
+                groove_score: Some(groove),
+                ..NewAnalysis::default()
+            })
+            .unwrap();
+        }
+
+        // Like `top`, only live recordings: the studio take stays out
+        let r = respond_public(&db, "/top?score=groove&band=grateful+dead&limit=1");
+        assert_eq!(r.status, 200);
+        assert_eq!(r.body["score"], "groove");
+        let tracks = r.body["tracks"].as_array().unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0]["scores"]["groove"], 72.0);
+        assert_eq!(tracks[0]["date"], "1977-05-08");
+        assert!(!r.body.to_string().contains("/music"));
+
+        let r = respond_public(&db, "/bands");
+        assert_eq!(
+            r.body["bands"][0],
+            json!({ "band": "Grateful Dead", "shows": 1, "tracks": 2 })
+        );
+        assert_eq!(respond_public(&db, "/top?score=nope").status, 400);
+        // The path-based endpoints aren't public
+        assert_eq!(respond_public(&db, "/track?path=d2t01.flac").status, 404);
+    }
+
+    #[test]
+    fn test_public_access() {
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "203.0.113.8".parse().unwrap();
+        let start = Instant::now();
+        let mut access = PublicAccess::new(Some("s3cret".to_string()), 3);
+        let status = |r: Option<Response>| r.map_or(200, |r| r.status);
+
+        assert_eq!(status(access.authorize("/health", None)), 200);
+        assert_eq!(status(access.authorize("/bands", None)), 401);
+        assert_eq!(
+            status(access.authorize("/bands", Some("Bearer s3cret"))),
+            200
+        );
+        assert_eq!(
+            status(access.authorize("/bands", Some("Bearer s3cres"))),
+            401
+        );
+        // Only the header carries the token, never the URL
+        assert_eq!(status(access.authorize("/bands?token=s3cret", None)), 401);
+
+        // The fourth connection this minute is refused
+        for _ in 0..3 {
+            assert_eq!(status(access.limit(client, start)), 200);
+        }
+        assert_eq!(status(access.limit(client, start)), 429);
+        assert_eq!(status(access.limit(other, start)), 200);
+        let later = start + Duration::from_secs(61);
+        assert_eq!(status(access.limit(client, later)), 200);
+
+        let open = PublicAccess::new(None, 1);
+        assert_eq!(status(open.authorize("/top", None)), 200);
+
+        let connections = OpenConnections::default();
+        let slots: Vec<Slot> = (0..MAX_CONNECTIONS_PER_CLIENT)
+            .map(|_| connections.take(client).unwrap())
+            .collect();
+        assert!(connections.take(client).is_none());
+        assert!(connections.take(other).is_some());
+        drop(slots);
+        assert!(connections.take(client).is_some());
+    }
+
+    #[test]
+    fn test_http_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let db = Database::open_in_memory().unwrap();
+            serve(&db, listener, None).unwrap();
+        });
+
+        // Quiet connections don't hold up the next client
+        let mut idle: Vec<TcpStream> = (0..3).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let started = Instant::now();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        assert!(started.elapsed() < HEAD_TIMEOUT);
+        assert!(reply.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(reply.contains("Content-Length: 15\r\n"));
+        assert!(reply.ends_with("\r\n\r\n{\"status\":\"ok\"}"));
+
+        // A client can't open more than its share at once
+        idle.push(TcpStream::connect(addr).unwrap());
+        let mut extra = TcpStream::connect(addr).unwrap();
+        let mut reply = String::new();
+        extra.read_to_string(&mut reply).unwrap();
+        assert!(reply.starts_with("HTTP/1.1 429 Too Many Requests\r\n"));
+        drop(idle);
+    }
+}
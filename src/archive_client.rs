@@ -0,0 +1,356 @@
+//! Shared HTTP client for archive.org: identification, pacing, and backoff.
+//!
+//! Every archive.org request goes through one `ArchiveClient` per run, which:
+//! - sends a descriptive User-Agent with the project URL and an optional contact
+//!   (`[archive] contact` in config.toml), as archive.org asks of API clients;
+//! - spaces requests at least `rate_limit_ms` apart, even when several threads
+//!   share the client (`concurrency` of them may be waiting on responses at once);
+//! - retries throttling (429/503) and transient failures, honoring `Retry-After`
+//!   and otherwise backing off exponentially with jitter;
+//! - stops once the per-run request budget (`max_requests`) is spent;
+//! - counts what it did, for the end-of-run summary.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::config::ArchiveConfig;
+
+/// Longest exponential backoff between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A `Retry-After` longer than this aborts the request instead of stalling the run.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(600);
+
+/// Per-request timeout.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("HTTP request failed for {url}: {message}")]
+    Http { url: String, message: String },
+    #[error("HTTP {status} from {url}")]
+    Status { url: String, status: u16 },
+    #[error("Failed to parse JSON from {url}: {message}")]
+    Json { url: String, message: String },
+    #[error("archive.org asked us to wait {secs}s (Retry-After) — try again later")]
+    RetryTooLong { secs: u64 },
+    #[error("Request budget of {0} archive.org requests used up for this run")]
+    BudgetExhausted(u64),
+}
+
+/// What the client did during a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RequestStats {
+    /// HTTP requests sent, retries included.
+    pub requests: u64,
+    /// Requests that were retries of an earlier attempt.
+    pub retries: u64,
+    /// 429/503 responses received.
+    pub throttled: u64,
+    /// Time spent waiting on pacing, backoff, and Retry-After.
+    pub waited: Duration,
+    /// The run's request budget, if any.
+    pub budget: Option<u64>,
+}
+
+impl fmt::Display for RequestStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.budget {
+            Some(b) => write!(f, "archive.org: {}/{} requests", self.requests, b)?,
+            None => write!(f, "archive.org: {} requests", self.requests)?,
+        }
+        write!(
+            f,
+            " ({} retried, {} throttled), {:.1}s waiting",
+            self.retries,
+            self.throttled,
+            self.waited.as_secs_f64()
+        )
+    }
+}
+
+/// Rate-limited, retrying archive.org client. One per run, shareable across threads.
+pub struct ArchiveClient {
+    agent: ureq::Agent,
+    min_interval: Duration,
+    max_retries: u32,
+    max_requests: Option<u64>,
+    concurrency: usize,
+    /// Start time of the latest request, or of the next reserved slot.
+    last_request: Mutex<Option<Instant>>,
+    stats: Mutex<RequestStats>,
+    jitter: RandomState,
+}
+
+impl ArchiveClient {
+    pub fn new(config: &ArchiveConfig) -> Self {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .user_agent(user_agent(config.contact.as_deref()))
+            // Inspect 429/503 responses (and their Retry-After) ourselves
+            .http_status_as_error(false)
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into();
+        let max_requests = (config.max_requests > 0).then_some(config.max_requests);
+        Self {
+            agent,
+            min_interval: Duration::from_millis(config.rate_limit_ms),
+            max_retries: config.max_retries,
+            max_requests,
+            concurrency: config.concurrency.max(1),
+            last_request: Mutex::new(None),
+            stats: Mutex::new(RequestStats {
+                budget: max_requests,
+                ..Default::default()
+            }),
+            jitter: RandomState::new(),
+        }
+    }
+
+    /// Counters so far.
+    pub fn stats(&self) -> RequestStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Requests callers may have in flight at once.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// True once the per-run request budget is spent.
+    pub fn budget_exhausted(&self) -> bool {
+        self.max_requests
+            .is_some_and(|max| self.stats().requests >= max)
+    }
+
+    /// GET `url` and deserialize the JSON body, with pacing and retries.
+    pub fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, ArchiveError> {
+        let mut attempt = 0;
+        loop {
+            // Check and count under one lock so concurrent callers can't overrun the budget
+            {
+                let mut stats = self.stats.lock().unwrap();
+                if let Some(max) = self.max_requests {
+                    if stats.requests >= max {
+                        return Err(ArchiveError::BudgetExhausted(max));
+                    }
+                }
+                stats.requests += 1;
+                if attempt > 0 {
+                    stats.retries += 1;
+                }
+            }
+            self.pace();
+            log::debug!("GET {url}");
+
+            let retry_wait = match self.agent.get(url).call() {
+                Ok(mut resp) => {
+                    let status = resp.status().as_u16();
+                    if (200..300).contains(&status) {
+                        return resp.body_mut().read_json().map_err(|e| ArchiveError::Json {
+                            url: url.to_string(),
+                            message: e.to_string(),
+                        });
+                    }
+                    if !is_retryable(status) || attempt >= self.max_retries {
+                        return Err(ArchiveError::Status {
+                            url: url.to_string(),
+                            status,
+                        });
+                    }
+                    if status == 429 || status == 503 {
+                        self.update(|s| s.throttled += 1);
+                    }
+                    let header = resp
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| parse_retry_after(v, chrono::Utc::now()));
+                    match header {
+                        Some(wait) if wait > MAX_RETRY_AFTER => {
+                            return Err(ArchiveError::RetryTooLong {
+                                secs: wait.as_secs(),
+                            });
+                        }
+                        Some(wait) => wait,
+                        None => self.backoff(attempt),
+                    }
+                }
+                Err(e) => {
+                    if attempt >= self.max_retries {
+                        return Err(ArchiveError::Http {
+                            url: url.to_string(),
+                            message: e.to_string(),
+                        });
+                    }
+                    log::debug!("Request to {url} failed ({e}), retrying");
+                    self.backoff(attempt)
+                }
+            };
+
+            log::info!(
+                "archive.org throttled or failed; retrying in {:.1}s",
+                retry_wait.as_secs_f64()
+            );
+            self.sleep(retry_wait);
+            attempt += 1;
+        }
+    }
+
+    /// Wait until `min_interval` has passed since the previous request. The
+    /// slot is reserved before sleeping, so concurrent callers queue up behind
+    /// each other instead of all waking at once.
+    fn pace(&self) {
+        let wait = {
+            let mut last = self.last_request.lock().unwrap();
+            let now = Instant::now();
+            let slot = last.map_or(now, |prev| (prev + self.min_interval).max(now));
+            *last = Some(slot);
+            slot - now
+        };
+        if !wait.is_zero() {
+            self.sleep(wait);
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let jitter = (self.jitter.hash_one((attempt, Instant::now())) % 1000) as f64 / 1000.0;
+        backoff_delay(attempt, self.min_interval, jitter)
+    }
+
+    fn sleep(&self, d: Duration) {
+        std::thread::sleep(d);
+        self.update(|s| s.waited += d);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut RequestStats)) {
+        f(&mut self.stats.lock().unwrap());
+    }
+}
+
+/// User-Agent identifying setbreak, with an optional contact (email or URL).
+pub fn user_agent(contact: Option<&str>) -> String {
+    let base = format!(
+        "setbreak/{} (+{}",
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_REPOSITORY")
+    );
+    match contact.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(c) => format!("{base}; contact: {c})"),
+        None => format!("{base})"),
+    }
+}
+
+/// Throttling and transient server errors are worth retrying; other statuses aren't.
+fn is_retryable(status: u16) -> bool {
+    matches!(status, 429 | 500 | 502 | 503 | 504)
+}
+
+/// Parse a `Retry-After` value: delay in seconds, or an HTTP date.
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (at.with_timezone(&chrono::Utc) - now).num_seconds().max(0);
+    Some(Duration::from_secs(secs as u64))
+}
+
+/// Exponential backoff from `max(min_interval, 1s)`, capped at `MAX_BACKOFF`, scaled
+/// by a jitter in `[0.5, 1.5)` (`jitter` is a uniform sample in `[0, 1)`).
+fn backoff_delay(attempt: u32, min_interval: Duration, jitter: f64) -> Duration {
+    let base = min_interval.max(Duration::from_secs(1));
+    let exp = base
+        .saturating_mul(1u32 << attempt.min(16))
+        .min(MAX_BACKOFF);
+    exp.mul_f64(0.5 + jitter.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_user_agent() {
+        let ua = user_agent(Some(" me@example.com "));
+        assert!(ua.starts_with("setbreak/"));
+        assert!(ua.contains("github.com/lexicone42/setbreak"));
+        assert!(ua.ends_with("; contact: me@example.com)"));
+        assert!(!user_agent(Some("")).contains("contact"));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = chrono::Utc
+            .with_ymd_and_hms(2015, 10, 21, 7, 27, 0)
+            .unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+        // A date in the past means "now"
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let min = Duration::from_millis(500);
+        assert_eq!(backoff_delay(0, min, 0.5), Duration::from_secs(1));
+        assert_eq!(backoff_delay(3, min, 0.5), Duration::from_secs(8));
+        assert_eq!(backoff_delay(3, min, 0.0), Duration::from_secs(4));
+        assert_eq!(backoff_delay(20, min, 0.5), MAX_BACKOFF);
+        assert!(backoff_delay(20, min, 0.99) < MAX_BACKOFF.mul_f64(1.5));
+    }
+
+    #[test]
+    fn test_budget_and_stats_display() {
+        let client = ArchiveClient::new(&ArchiveConfig {
+            max_requests: 2,
+            ..Default::default()
+        });
+        assert!(!client.budget_exhausted());
+        client.update(|s| s.requests = 2);
+        assert!(client.budget_exhausted());
+        assert!(matches!(
+            client.get_json::<serde_json::Value>("http://127.0.0.1:9/"),
+            Err(ArchiveError::BudgetExhausted(2))
+        ));
+        assert_eq!(
+            client.stats().to_string(),
+            "archive.org: 2/2 requests (0 retried, 0 throttled), 0.0s waiting"
+        );
+    }
+
+    #[test]
+    fn test_pacing_across_threads() {
+        let client = ArchiveClient::new(&ArchiveConfig {
+            rate_limit_ms: 20,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| client.pace());
+            }
+        });
+        // Four slots 20ms apart: the last starts at least 60ms in
+        assert!(start.elapsed() >= Duration::from_millis(60));
+        assert!(client.stats().waited >= Duration::from_millis(60));
+    }
+}
@@ -0,0 +1,425 @@
+//! Raw analysis artifacts kept beside the database.
+//!
+//! `analyze` reduces ferrous-waves' per-frame output to scalar features and
+//! drops the rest. With `[analysis] keep_artifacts` set, the chosen series —
+//! the chromagram, the onset envelope (spectral flux), the short-term loudness
+//! curve — are kept as zstd-compressed blobs, so a new score or visualization
+//! can be computed from them without decoding the audio again.
+//!
+//! Blobs live in a sidecar SQLite file (`setbreak.artifacts.db` next to
+//! `setbreak.db`), attached to the main connection as `artifacts`, so the main
+//! database stays small and the sidecar can be deleted at any time. Rows are
+//! keyed by track id and kind; `artifact_budget_mb` caps the sidecar, evicting
+//! the tracks stored longest ago first.
+
+use crate::db::Database;
+use rusqlite::{OptionalExtension, params};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// zstd level: the series are smooth floats, so higher levels buy little.
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Error, Debug)]
+pub enum ArtifactError {
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+    #[error("Compression error: {0}")]
+    Codec(#[from] std::io::Error),
+    #[error("Corrupt artifact for track {track_id} ({kind}): {reason}")]
+    Corrupt {
+        track_id: i64,
+        kind: &'static str,
+        reason: String,
+    },
+}
+
+impl From<rusqlite::Error> for ArtifactError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Db(e.into())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ArtifactError>;
+
+/// A per-frame series `analyze` can keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// 12 pitch-class channels per frame.
+    Chromagram,
+    /// Spectral flux per frame, the onset strength envelope.
+    OnsetEnvelope,
+    /// Short-term (3 s) loudness in LUFS.
+    Loudness,
+}
+
+impl ArtifactKind {
+    pub const ALL: [ArtifactKind; 3] = [Self::Chromagram, Self::OnsetEnvelope, Self::Loudness];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Chromagram => "chromagram",
+            Self::OnsetEnvelope => "onset_envelope",
+            Self::Loudness => "loudness",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+}
+
+/// One stored series, channel-major: channel 0's frames, then channel 1's, ...
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    pub channels: usize,
+    /// Seconds between frames.
+    pub hop_secs: f64,
+    pub values: Vec<f32>,
+}
+
+impl Artifact {
+    pub fn frames(&self) -> usize {
+        self.values.len() / self.channels.max(1)
+    }
+
+    /// One channel's frames.
+    pub fn channel(&self, i: usize) -> &[f32] {
+        let n = self.frames();
+        &self.values[i * n..(i + 1) * n]
+    }
+}
+
+/// Stored artifacts of one kind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KindUsage {
+    pub kind: String,
+    pub tracks: usize,
+    /// Uncompressed size of the series.
+    pub raw_bytes: u64,
+    /// Compressed size on disk (before SQLite page overhead).
+    pub stored_bytes: u64,
+}
+
+/// What a prune removed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PruneSummary {
+    /// Artifacts of tracks no longer in the library.
+    pub orphaned: usize,
+    /// Artifacts removed to get under the budget or by `--kind`.
+    pub evicted: usize,
+    pub freed_bytes: u64,
+}
+
+/// Sidecar path for a database file: `setbreak.db` → `setbreak.artifacts.db`.
+pub fn sidecar_path(db_path: &Path) -> PathBuf {
+    let stem = db_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| crate::APP_NAME.to_string());
+    db_path.with_file_name(format!("{stem}.artifacts.db"))
+}
+
+/// Compress a series as little-endian f32s.
+fn encode(values: &[f32]) -> std::io::Result<Vec<u8>> {
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    zstd::bulk::compress(&bytes, ZSTD_LEVEL)
+}
+
+fn decode(blob: &[u8], raw_bytes: usize) -> std::io::Result<Vec<f32>> {
+    let bytes = zstd::bulk::decompress(blob, raw_bytes)?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Attach the artifact sidecar as `artifacts`, creating it if needed.
+    /// An in-memory database gets an in-memory sidecar. Returns the sidecar
+    /// path, if on disk.
+    pub fn attach_artifacts(&self) -> crate::db::Result<Option<PathBuf>> {
+        let path = self.path().map(sidecar_path);
+        if !self.artifacts_attached()? {
+            let target = path
+                .as_ref()
+                .map_or(":memory:".to_string(), |p| p.to_string_lossy().into_owned());
+            self.conn
+                .execute("ATTACH DATABASE ?1 AS artifacts", params![target])?;
+            self.conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS artifacts.raw_artifacts (
+                    track_id    INTEGER NOT NULL,
+                    kind        TEXT NOT NULL,
+                    channels    INTEGER NOT NULL,
+                    hop_secs    REAL NOT NULL,
+                    raw_bytes   INTEGER NOT NULL,
+                    data        BLOB NOT NULL,
+                    stored_at   TEXT NOT NULL DEFAULT (datetime('now')),
+                    PRIMARY KEY (track_id, kind)
+                );
+                CREATE INDEX IF NOT EXISTS artifacts.idx_raw_artifacts_stored
+                    ON raw_artifacts(stored_at);",
+            )?;
+        }
+        Ok(path)
+    }
+
+    fn artifacts_attached(&self) -> crate::db::Result<bool> {
+        let mut stmt = self.conn.prepare("SELECT name FROM pragma_database_list")?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(names.iter().any(|n| n == "artifacts"))
+    }
+
+    /// Store (or replace) a track's artifacts. Needs `attach_artifacts` first
+    /// unless `artifacts` is empty.
+    pub fn store_artifacts(&self, track_id: i64, artifacts: &[Artifact]) -> Result<()> {
+        if artifacts.is_empty() {
+            return Ok(());
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO artifacts.raw_artifacts
+                    (track_id, kind, channels, hop_secs, raw_bytes, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for a in artifacts {
+                stmt.execute(params![
+                    track_id,
+                    a.kind.name(),
+                    a.channels as i64,
+                    a.hop_secs,
+                    (a.values.len() * 4) as i64,
+                    encode(&a.values)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// A track's stored artifact of `kind`. Needs `attach_artifacts` first.
+    pub fn load_artifact(&self, track_id: i64, kind: ArtifactKind) -> Result<Option<Artifact>> {
+        let row: Option<(i64, f64, i64, Vec<u8>)> = self
+            .conn
+            .query_row(
+                "SELECT channels, hop_secs, raw_bytes, data FROM artifacts.raw_artifacts
+                 WHERE track_id = ?1 AND kind = ?2",
+                params![track_id, kind.name()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?;
+        let Some((channels, hop_secs, raw_bytes, data)) = row else {
+            return Ok(None);
+        };
+        let values = decode(&data, raw_bytes as usize)?;
+        let channels = channels.max(1) as usize;
+        if values.len() * 4 != raw_bytes as usize || values.len() % channels != 0 {
+            return Err(ArtifactError::Corrupt {
+                track_id,
+                kind: kind.name(),
+                reason: format!("{} values for {channels} channels", values.len()),
+            });
+        }
+        Ok(Some(Artifact {
+            kind,
+            channels,
+            hop_secs,
+            values,
+        }))
+    }
+
+    /// Stored artifacts per kind. Needs `attach_artifacts` first.
+    pub fn artifact_usage(&self) -> crate::db::Result<Vec<KindUsage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, COUNT(*), SUM(raw_bytes), SUM(length(data))
+             FROM artifacts.raw_artifacts GROUP BY kind ORDER BY kind",
+        )?;
+        let usage = stmt
+            .query_map([], |row| {
+                Ok(KindUsage {
+                    kind: row.get(0)?,
+                    tracks: row.get::<_, i64>(1)? as usize,
+                    raw_bytes: row.get::<_, i64>(2)? as u64,
+                    stored_bytes: row.get::<_, i64>(3)? as u64,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(usage)
+    }
+
+    /// Drop artifacts of tracks no longer in the library, every artifact of
+    /// `kind` if given, then the tracks stored longest ago until the blobs fit
+    /// in `budget_bytes`. Vacuums the sidecar if anything was removed. Needs
+    /// `attach_artifacts` first.
+    pub fn prune_artifacts(
+        &self,
+        budget_bytes: Option<u64>,
+        kind: Option<ArtifactKind>,
+    ) -> crate::db::Result<PruneSummary> {
+        let stored = |db: &Self| -> crate::db::Result<u64> {
+            Ok(db.conn.query_row(
+                "SELECT COALESCE(SUM(length(data)), 0) FROM artifacts.raw_artifacts",
+                [],
+                |row| row.get::<_, i64>(0),
+            )? as u64)
+        };
+        let before = stored(self)?;
+        let mut summary = PruneSummary {
+            orphaned: self.conn.execute(
+                "DELETE FROM artifacts.raw_artifacts
+                 WHERE track_id NOT IN (SELECT id FROM main.tracks)",
+                [],
+            )?,
+            ..Default::default()
+        };
+        if let Some(kind) = kind {
+            summary.evicted += self.conn.execute(
+                "DELETE FROM artifacts.raw_artifacts WHERE kind = ?1",
+                params![kind.name()],
+            )?;
+        }
+
+        if let Some(budget) = budget_bytes {
+            let mut excess = stored(self)?.saturating_sub(budget);
+            if excess > 0 {
+                // Oldest tracks first; a track's kinds go together
+                let mut stmt = self.conn.prepare(
+                    "SELECT track_id, SUM(length(data)) FROM artifacts.raw_artifacts
+                     GROUP BY track_id ORDER BY MAX(stored_at), track_id",
+                )?;
+                let tracks = stmt
+                    .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                let tx = self.conn.unchecked_transaction()?;
+                for (track_id, bytes) in tracks {
+                    if excess == 0 {
+                        break;
+                    }
+                    summary.evicted += tx.execute(
+                        "DELETE FROM artifacts.raw_artifacts WHERE track_id = ?1",
+                        params![track_id],
+                    )?;
+                    excess = excess.saturating_sub(bytes as u64);
+                }
+                tx.commit()?;
+            }
+        }
+
+        summary.freed_bytes = before - stored(self)?;
+        if summary.orphaned + summary.evicted > 0 {
+            self.conn.execute_batch("VACUUM artifacts")?;
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(kind: ArtifactKind, channels: usize, frames: usize) -> Artifact {
+        Artifact {
+            kind,
+            channels,
+            hop_secs: 0.1,
+            values: (0..channels * frames)
+                .map(|i| (i as f32 * 0.37).sin())
+                .collect(),
+        }
+    }
+
+    fn add_track(db: &Database, id: i64) {
+        db.conn
+            .execute(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format)
+                 VALUES (?1, '/music/' || ?1 || '.flac', 0, '0', 'flac')",
+                params![id],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let db = Database::open_in_memory().unwrap();
+        assert_eq!(db.attach_artifacts().unwrap(), None);
+        // Attaching twice is harmless
+        db.attach_artifacts().unwrap();
+        add_track(&db, 1);
+
+        let chroma = artifact(ArtifactKind::Chromagram, 12, 500);
+        db.store_artifacts(
+            1,
+            &[chroma.clone(), artifact(ArtifactKind::Loudness, 1, 500)],
+        )
+        .unwrap();
+        let loaded = db
+            .load_artifact(1, ArtifactKind::Chromagram)
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded, chroma);
+        assert_eq!(loaded.frames(), 500);
+        assert_eq!(loaded.channel(11), &chroma.values[11 * 500..]);
+        assert!(
+            db.load_artifact(1, ArtifactKind::OnsetEnvelope)
+                .unwrap()
+                .is_none()
+        );
+
+        let usage = db.artifact_usage().unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].kind, "chromagram");
+        assert_eq!(usage[0].raw_bytes, 12 * 500 * 4);
+
+        assert_eq!(
+            sidecar_path(Path::new("/data/setbreak.db")),
+            PathBuf::from("/data/setbreak.artifacts.db")
+        );
+    }
+
+    #[test]
+    fn test_prune() {
+        let db = Database::open_in_memory().unwrap();
+        db.attach_artifacts().unwrap();
+        for id in 1..=4 {
+            add_track(&db, id);
+            db.store_artifacts(id, &[artifact(ArtifactKind::Loudness, 1, 2000)])
+                .unwrap();
+        }
+        db.conn
+            .execute_batch(
+                "UPDATE artifacts.raw_artifacts SET stored_at = '2020-01-0' || track_id;
+                 DELETE FROM tracks WHERE id = 4;",
+            )
+            .unwrap();
+
+        // Room for two tracks' blobs: the orphan goes, then the oldest
+        let each = db.artifact_usage().unwrap()[0].stored_bytes / 4;
+        let summary = db.prune_artifacts(Some(each * 2), None).unwrap();
+        assert_eq!(summary.orphaned, 1);
+        assert_eq!(summary.evicted, 1);
+        assert!(
+            db.load_artifact(1, ArtifactKind::Loudness)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            db.load_artifact(3, ArtifactKind::Loudness)
+                .unwrap()
+                .is_some()
+        );
+
+        let summary = db
+            .prune_artifacts(None, Some(ArtifactKind::Loudness))
+            .unwrap();
+        assert_eq!(summary.evicted, 2);
+        assert!(db.artifact_usage().unwrap().is_empty());
+    }
+}
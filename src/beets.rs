@@ -0,0 +1,470 @@
+//! Interoperability with a beets library (`import-beets`).
+//!
+//! Beets items are matched to tracks by file path. Matched items supply the
+//! song title (locked against rescans like a `titles import` correction, unless
+//! one was already set by hand), the band (which a rescan re-derives, so import
+//! again after scanning), and MusicBrainz ids; everything beets knew is kept in
+//! `beets_items`. With `export_scores`, each matched item also gets the track's
+//! jam scores as beets flexible attributes (`setbreak_energy`,
+//! `setbreak_transcendence`, ...), so they can be queried from beets:
+//! `beet ls setbreak_transcendence:80..` once the field is typed as a float with
+//! the `types` plugin.
+
+use crate::db::Database;
+use crate::db::columns::SCORE_COLUMNS;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, types::ValueRef};
+use std::path::Path;
+use thiserror::Error;
+
+/// Prefix for flexible attributes written back to beets.
+pub const ATTRIBUTE_PREFIX: &str = "setbreak_";
+
+#[derive(Error, Debug)]
+pub enum BeetsError {
+    #[error("Failed to open beets library {path}: {source}")]
+    Open {
+        path: String,
+        source: rusqlite::Error,
+    },
+    #[error("Beets library error: {0}")]
+    Library(#[from] rusqlite::Error),
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+}
+
+/// One beets item, with the fields setbreak imports.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BeetsItem {
+    pub id: i64,
+    pub path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub mb_trackid: Option<String>,
+    pub mb_albumid: Option<String>,
+    pub mb_artistid: Option<String>,
+}
+
+/// Outcome of an import.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BeetsSummary {
+    /// Items in the beets library.
+    pub items: usize,
+    /// Items whose path matches a track.
+    pub matched: usize,
+    /// Tracks whose title changed.
+    pub titles: usize,
+    /// Tracks whose band changed.
+    pub bands: usize,
+    /// Matched items given scores (with `export_scores`).
+    pub exported: usize,
+}
+
+/// Import metadata from the beets library at `library`, optionally writing
+/// scores back to it. With `dry_run` neither database is modified.
+pub fn import(
+    db: &Database,
+    library: &Path,
+    export_scores: bool,
+    dry_run: bool,
+) -> Result<BeetsSummary, BeetsError> {
+    let flags = if export_scores && !dry_run {
+        OpenFlags::SQLITE_OPEN_READ_WRITE
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+    };
+    let beets = Connection::open_with_flags(library, flags).map_err(|source| BeetsError::Open {
+        path: library.display().to_string(),
+        source,
+    })?;
+    let items = read_items(&beets)?;
+    let mut summary = BeetsSummary {
+        items: items.len(),
+        ..BeetsSummary::default()
+    };
+
+    let registry = crate::bands::registry();
+    let tx = db
+        .conn
+        .unchecked_transaction()
+        .map_err(crate::db::DbError::from)?;
+    let mut matched = Vec::new();
+    for item in &items {
+        let Some(track) = db.beets_match(&item.path)? else {
+            continue;
+        };
+        summary.matched += 1;
+        matched.push((track.id, item.id));
+
+        let title = item
+            .title
+            .as_deref()
+            .map(crate::title_normalize::normalize)
+            .filter(|t| !t.is_empty());
+        let title = title.as_deref();
+        let title_changed = title.is_some_and(|t| {
+            track.title_method.as_deref() != Some("manual")
+                && (track.title.as_deref() != Some(t)
+                    || track.title_method.as_deref() != Some("beets"))
+        });
+        let band = item
+            .artist
+            .as_deref()
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(|a| registry.lookup_search_name(a).unwrap_or(a));
+        let band_changed = band.is_some_and(|b| track.band.as_deref() != Some(b));
+        summary.titles += usize::from(title_changed && track.title.as_deref() != title);
+        summary.bands += usize::from(band_changed);
+        if dry_run {
+            continue;
+        }
+
+        db.store_beets_item(track.id, item)?;
+        if title_changed {
+            db.conn
+                .execute(
+                    "UPDATE tracks SET parsed_title = ?1, title_match_method = 'beets',
+                            title_match_confidence = 1.0, updated_at = datetime('now')
+                     WHERE id = ?2",
+                    rusqlite::params![title, track.id],
+                )
+                .map_err(crate::db::DbError::from)?;
+        }
+        if band_changed {
+            db.conn
+                .execute(
+                    "UPDATE tracks SET parsed_band = ?1, updated_at = datetime('now')
+                     WHERE id = ?2",
+                    rusqlite::params![band, track.id],
+                )
+                .map_err(crate::db::DbError::from)?;
+        }
+    }
+    if !dry_run {
+        tx.commit().map_err(crate::db::DbError::from)?;
+        if summary.titles > 0 {
+            if let Err(e) = db.apply_title_aliases() {
+                log::warn!("Failed to apply title aliases: {e}");
+            }
+        }
+    }
+
+    if export_scores {
+        summary.exported = export(db, &beets, &matched, dry_run)?;
+    }
+    Ok(summary)
+}
+
+/// Write each analyzed track's scores onto its beets item. Returns the number
+/// of items written (or that would be).
+fn export(
+    db: &Database,
+    beets: &Connection,
+    matched: &[(i64, i64)],
+    dry_run: bool,
+) -> Result<usize, BeetsError> {
+    let tx = beets.unchecked_transaction()?;
+    let mut exported = 0;
+    for &(track_id, beets_id) in matched {
+        let Some(scores) = db.beets_scores(track_id)? else {
+            continue;
+        };
+        exported += 1;
+        if dry_run {
+            continue;
+        }
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO item_attributes (entity_id, key, value) VALUES (?1, ?2, ?3)",
+        )?;
+        for (column, value) in SCORE_COLUMNS.iter().zip(scores) {
+            let Some(value) = value else { continue };
+            stmt.execute(rusqlite::params![
+                beets_id,
+                attribute_name(column),
+                format!("{value:.1}")
+            ])?;
+        }
+    }
+    if !dry_run {
+        tx.commit()?;
+    }
+    Ok(exported)
+}
+
+/// Flexible attribute name for a score column: `energy_score` → `setbreak_energy`.
+pub fn attribute_name(column: &str) -> String {
+    format!(
+        "{ATTRIBUTE_PREFIX}{}",
+        column.strip_suffix("_score").unwrap_or(column)
+    )
+}
+
+/// Every item in a beets library. Beets stores paths as raw bytes; items whose
+/// path isn't UTF-8 can't match a stored `file_path` and are skipped.
+fn read_items(beets: &Connection) -> Result<Vec<BeetsItem>, BeetsError> {
+    let mut stmt = beets.prepare(
+        "SELECT id, path, title, artist, album, mb_trackid, mb_albumid, mb_artistid
+         FROM items ORDER BY id",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut items = Vec::new();
+    while let Some(row) = rows.next()? {
+        let bytes = match row.get_ref(1)? {
+            ValueRef::Blob(b) | ValueRef::Text(b) => b,
+            _ => continue,
+        };
+        let Some(path) = std::str::from_utf8(bytes)
+            .ok()
+            .map(|p| crate::paths::to_db(Path::new(p)))
+        else {
+            continue;
+        };
+        let text = |i: usize| -> rusqlite::Result<Option<String>> {
+            Ok(row.get::<_, Option<String>>(i)?.filter(|s| !s.is_empty()))
+        };
+        items.push(BeetsItem {
+            id: row.get(0)?,
+            path,
+            title: text(2)?,
+            artist: text(3)?,
+            album: text(4)?,
+            mb_trackid: text(5)?,
+            mb_albumid: text(6)?,
+            mb_artistid: text(7)?,
+        });
+    }
+    Ok(items)
+}
+
+/// A track matched to a beets item, with the values an import may replace.
+struct MatchedTrack {
+    id: i64,
+    title: Option<String>,
+    title_method: Option<String>,
+    band: Option<String>,
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    fn beets_match(&self, file_path: &str) -> crate::db::Result<Option<MatchedTrack>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id, parsed_title, title_match_method, parsed_band
+                 FROM tracks WHERE file_path = ?1",
+                [file_path],
+                |row| {
+                    Ok(MatchedTrack {
+                        id: row.get(0)?,
+                        title: row.get(1)?,
+                        title_method: row.get(2)?,
+                        band: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn store_beets_item(&self, track_id: i64, item: &BeetsItem) -> crate::db::Result<()> {
+        self.conn.execute(
+            "INSERT INTO beets_items
+                (track_id, beets_id, title, artist, album, mb_trackid, mb_albumid, mb_artistid)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(track_id) DO UPDATE SET
+                beets_id = excluded.beets_id, title = excluded.title,
+                artist = excluded.artist, album = excluded.album,
+                mb_trackid = excluded.mb_trackid, mb_albumid = excluded.mb_albumid,
+                mb_artistid = excluded.mb_artistid, imported_at = datetime('now')",
+            rusqlite::params![
+                track_id,
+                item.id,
+                item.title,
+                item.artist,
+                item.album,
+                item.mb_trackid,
+                item.mb_albumid,
+                item.mb_artistid
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// A track's scores in `SCORE_COLUMNS` order, or `None` if it isn't analyzed.
+    fn beets_scores(&self, track_id: i64) -> crate::db::Result<Option<Vec<Option<f64>>>> {
+        let sql = format!(
+            "SELECT {} FROM analysis_results WHERE track_id = ?1",
+            SCORE_COLUMNS.join(", ")
+        );
+        Ok(self
+            .conn
+            .query_row(&sql, [track_id], |row| {
+                (0..SCORE_COLUMNS.len()).map(|i| row.get(i)).collect()
+            })
+            .optional()?)
+    }
+
+    /// MusicBrainz recording id imported from beets for a track.
+    pub fn beets_mbid(&self, track_id: i64) -> crate::db::Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT mb_trackid FROM beets_items WHERE track_id = ?1",
+                [track_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewTrack;
+    use crate::db::queries::tests::new_track;
+
+    fn track(path: &str, title: &str) -> NewTrack {
+        NewTrack {
+            parsed_band: Some("grateful dead".into()),
+            parsed_date: Some("1977-05-08".into()),
+            parsed_title: Some(title.into()),
+            ..new_track(path)
+        }
+    }
+
+    /// The parts of a beets library schema that `import` touches.
+    fn beets_library(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, path BLOB, title TEXT, artist TEXT,
+                 album TEXT, mb_trackid TEXT, mb_albumid TEXT, mb_artistid TEXT);
+             CREATE TABLE item_attributes (id INTEGER PRIMARY KEY, entity_id INTEGER,
+                 key TEXT, value TEXT, UNIQUE(entity_id, key) ON CONFLICT REPLACE);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn title(db: &Database, id: i64) -> (Option<String>, Option<String>) {
+        db.conn
+            .query_row(
+                "SELECT parsed_title, parsed_band FROM tracks WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_import_beets() {
+        crate::bands::init_default();
+        let dir = std::env::temp_dir().join(format!("setbreak-beets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let library = dir.join("library.db");
+        let _ = std::fs::remove_file(&library);
+        let beets = beets_library(&library);
+        let insert = "INSERT INTO items (id, path, title, artist, album, mb_trackid)
+                      VALUES (?1, ?2, ?3, 'Grateful Dead', 'Cornell 5/8/77', ?4)";
+        let paths = [
+            "/music/gd77-05-08/d1t04.flac",
+            "/music/gd77-05-08/d1t05.flac",
+        ];
+        beets
+            .execute(
+                insert,
+                rusqlite::params![1, paths[0].as_bytes(), "Scarlet Begonias", "mbid-1"],
+            )
+            .unwrap();
+        beets
+            .execute(
+                insert,
+                rusqlite::params![2, paths[1].as_bytes(), "Fire on the Mountain", "mbid-2"],
+            )
+            .unwrap();
+        beets
+            .execute(
+                insert,
+                rusqlite::params![3, b"/elsewhere/x.flac".as_slice(), "Other", None::<String>],
+            )
+            .unwrap();
+        drop(beets);
+
+        let db = Database::open_in_memory().unwrap();
+        let scarlet = db
+            .upsert_track(&track(paths[0], "Scarlet Begonia"))
+            .unwrap();
+        let fire = db.upsert_track(&track(paths[1], "Fire")).unwrap();
+        db.conn
+            .execute(
+                "UPDATE tracks SET title_match_method = 'manual' WHERE id = ?1",
+                [fire],
+            )
+            .unwrap();
+        db.conn
+            .execute(
+                "INSERT INTO analysis_results (track_id, energy_score, transcendence_score)
+                 VALUES (?1, 61.26, 88.0)",
+                [scarlet],
+            )
+            .unwrap();
+
+        let dry = import(&db, &library, true, true).unwrap();
+        assert_eq!(
+            dry,
+            BeetsSummary {
+                items: 3,
+                matched: 2,
+                titles: 1,
+                bands: 2,
+                exported: 1,
+            }
+        );
+        assert_eq!(title(&db, scarlet).0.as_deref(), Some("Scarlet Begonia"));
+
+        import(&db, &library, true, false).unwrap();
+        assert_eq!(
+            title(&db, scarlet),
+            (
+                Some("Scarlet Begonias".into()),
+                Some("Grateful Dead".into())
+            )
+        );
+        // A hand-corrected title wins over beets
+        assert_eq!(title(&db, fire).0.as_deref(), Some("Fire"));
+        assert_eq!(db.beets_mbid(scarlet).unwrap().as_deref(), Some("mbid-1"));
+
+        let beets = Connection::open(&library).unwrap();
+        let attributes: Vec<(String, String)> = beets
+            .prepare("SELECT key, value FROM item_attributes WHERE entity_id = 1 ORDER BY key")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            attributes,
+            vec![
+                ("setbreak_energy".into(), "61.3".into()),
+                ("setbreak_transcendence".into(), "88.0".into()),
+            ]
+        );
+
+        // Nothing left to change on a second run
+        let again = import(&db, &library, false, false).unwrap();
+        assert_eq!((again.titles, again.bands), (0, 0));
+
+        // The beets title survives a rescan; the band comes back from the path
+        db.upsert_track(&track(paths[0], "Scarlet Begonia"))
+            .unwrap();
+        assert_eq!(
+            title(&db, scarlet),
+            (
+                Some("Scarlet Begonias".into()),
+                Some("grateful dead".into())
+            )
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,11 @@
+//! `setbreak-query`: the setbreak CLI without audio decoding or analysis, for
+//! a machine that only reads a database synced from the one that analyzes.
+//!
+//! Built with `--no-default-features --features query`, it leaves out
+//! ferrous-waves, the native decoders and tokio, and with them the commands
+//! that decode audio (`analyze`, `trace`, `extract-boundaries`, `split-songs`,
+//! `retrack`). Everything else is the same code as `setbreak` (`setbreak::cli`).
+
+fn main() -> anyhow::Result<()> {
+    setbreak::cli::run(env!("CARGO_BIN_NAME"))
+}
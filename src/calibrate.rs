@@ -2,12 +2,23 @@
 //!
 //! Per-show median LUFS regression: `adjusted = raw - β × (show_lufs - corpus_lufs)`
 //! where β is the OLS slope of each score against show median LUFS.
+//!
+//! Track loudness is `lufs_music` (short-term windows of banter and dead air
+//! gated out) where the track has it, so a single-file set isn't counted as a
+//! quiet tape because of its talking between songs; older analyses fall back
+//! to whole-file integrated LUFS.
+//!
+//! With source offsets, a second step learns how each kind of source (SBD,
+//! AUD, matrix, FM) scores against the other recordings of the same
+//! performances (see `performances`) and subtracts that from its tracks.
 
 use anyhow::Result;
 use std::collections::HashMap;
 
 use crate::db::Database;
 use crate::db::models::{CalibrationRow, NewAnalysis};
+use crate::performances::{PerformanceTrack, SourceOffset};
+use crate::progress::Progress;
 
 const SCORE_NAMES: [&str; 10] = [
     "energy",
@@ -25,6 +36,13 @@ const SCORE_NAMES: [&str; 10] = [
 /// Minimum |β| to bother correcting — below this, the bias is negligible.
 const BETA_THRESHOLD: f64 = 0.1;
 
+/// Performances with more than one kind of source a source offset must be
+/// learned from before it's applied.
+const MIN_OFFSET_PERFORMANCES: usize = 5;
+
+/// Smallest source offset (score points) worth applying.
+const OFFSET_THRESHOLD: f64 = 1.0;
+
 pub struct CalibrateResult {
     pub total_tracks: usize,
     pub calibrated: usize,
@@ -33,13 +51,21 @@ pub struct CalibrateResult {
     pub corpus_median_lufs: f64,
 }
 
-/// Calibrate all jam scores by regressing out per-show LUFS bias.
-pub fn calibrate_scores(db: &Database, dry_run: bool) -> Result<CalibrateResult> {
+/// Calibrate all jam scores by regressing out per-show LUFS bias and, with
+/// `source_offsets`, each source type's offset. Performances must be linked
+/// (`performances::link`) for the offsets to be learned. The fitted β per
+/// score and the offsets table go to `progress` as notes.
+pub fn calibrate_scores(
+    db: &Database,
+    dry_run: bool,
+    source_offsets: bool,
+    progress: &dyn Progress,
+) -> Result<CalibrateResult> {
     let rows = db.get_calibration_data()?;
     let total_tracks = rows.len();
 
     if rows.is_empty() {
-        println!("No calibration data (need analyzed tracks with LUFS and parsed dates).");
+        progress.note("No calibration data (need analyzed tracks with LUFS and parsed dates).");
         return Ok(CalibrateResult {
             total_tracks: 0,
             calibrated: 0,
@@ -67,13 +93,21 @@ pub fn calibrate_scores(db: &Database, dry_run: bool) -> Result<CalibrateResult>
     let mut all_show_medians: Vec<f64> = show_median_lufs.values().copied().collect();
     let corpus_median = median(&mut all_show_medians);
 
-    println!(
+    progress.note(&format!(
         "Calibration: {} tracks across {} shows, corpus median LUFS = {:.1}",
         total_tracks,
         show_median_lufs.len(),
         corpus_median
-    );
-    println!();
+    ));
+    let music_lufs = rows.iter().filter(|r| r.music_lufs).count();
+    if music_lufs < total_tracks {
+        progress.note(&format!(
+            "  {} tracks use music-only loudness; {} use integrated LUFS until re-analyzed",
+            music_lufs,
+            total_tracks - music_lufs
+        ));
+    }
+    progress.note("");
 
     // Per-track show median LUFS (for regression + adjustment)
     let track_show_lufs: Vec<Option<f64>> = rows
@@ -108,22 +142,46 @@ pub fn calibrate_scores(db: &Database, dry_run: bool) -> Result<CalibrateResult>
             "negligible — no correction"
         };
 
-        println!("  {:<15} β = {:+.4}  ({})", score_name, beta, direction);
+        progress.note(&format!(
+            "  {:<15} β = {:+.4}  ({})",
+            score_name, beta, direction
+        ));
 
         betas.push((score_name.to_string(), beta));
     }
-    println!();
+    progress.note("");
+
+    // LUFS-corrected scores; `None` for a track without a show
+    let mut adjusted: Vec<Option<[Option<f64>; 10]>> = rows
+        .iter()
+        .zip(&track_show_lufs)
+        .map(|(row, show_lufs)| {
+            let lufs_delta = (*show_lufs)? - corpus_median;
+            let mut scores = row.scores;
+            for (score, (_, beta)) in scores.iter_mut().zip(&betas) {
+                if beta.abs() < BETA_THRESHOLD {
+                    continue;
+                }
+                if let Some(raw) = score {
+                    *raw = (*raw - beta * lufs_delta).clamp(0.0, 100.0);
+                }
+            }
+            Some(scores)
+        })
+        .collect();
+
+    if source_offsets {
+        let offsets = learn_source_offsets(&rows, &adjusted);
+        note_source_offsets(&offsets, progress);
+        apply_source_offsets(&rows, &mut adjusted, &offsets);
+    }
 
     if dry_run {
-        println!("DRY RUN — no changes written.");
+        progress.note("DRY RUN — no changes written.");
         return Ok(CalibrateResult {
             total_tracks,
             calibrated: 0,
-            skipped_no_show: rows
-                .iter()
-                .enumerate()
-                .filter(|(i, _)| track_show_lufs[*i].is_none())
-                .count(),
+            skipped_no_show: adjusted.iter().filter(|a| a.is_none()).count(),
             betas,
             corpus_median_lufs: corpus_median,
         });
@@ -133,41 +191,25 @@ pub fn calibrate_scores(db: &Database, dry_run: bool) -> Result<CalibrateResult>
     let mut calibrated = 0;
     let mut skipped_no_show = 0;
 
-    for (i, row) in rows.iter().enumerate() {
-        let show_lufs = match track_show_lufs[i] {
-            Some(l) => l,
-            None => {
-                skipped_no_show += 1;
-                continue;
-            }
+    for (row, scores) in rows.iter().zip(&adjusted) {
+        let Some(scores) = scores else {
+            skipped_no_show += 1;
+            continue;
         };
 
-        let lufs_delta = show_lufs - corpus_median;
-        let mut adjusted_scores: [Option<f64>; 10] = row.scores;
-
-        for (score_idx, (_, beta)) in betas.iter().enumerate() {
-            if beta.abs() < BETA_THRESHOLD {
-                continue;
-            }
-            if let Some(raw) = adjusted_scores[score_idx] {
-                let adj = (raw - beta * lufs_delta).clamp(0.0, 100.0);
-                adjusted_scores[score_idx] = Some(adj);
-            }
-        }
-
         // Build a minimal NewAnalysis for update_jam_scores
         let update = NewAnalysis {
             track_id: row.track_id,
-            energy_score: adjusted_scores[0],
-            intensity_score: adjusted_scores[1],
-            groove_score: adjusted_scores[2],
-            improvisation_score: adjusted_scores[3],
-            tightness_score: adjusted_scores[4],
-            build_quality_score: adjusted_scores[5],
-            exploratory_score: adjusted_scores[6],
-            transcendence_score: adjusted_scores[7],
-            valence_score: adjusted_scores[8],
-            arousal_score: adjusted_scores[9],
+            energy_score: scores[0],
+            intensity_score: scores[1],
+            groove_score: scores[2],
+            improvisation_score: scores[3],
+            tightness_score: scores[4],
+            build_quality_score: scores[5],
+            exploratory_score: scores[6],
+            transcendence_score: scores[7],
+            valence_score: scores[8],
+            arousal_score: scores[9],
             // All other fields unused by update_jam_scores
             ..NewAnalysis::default()
         };
@@ -185,6 +227,88 @@ pub fn calibrate_scores(db: &Database, dry_run: bool) -> Result<CalibrateResult>
     })
 }
 
+/// Learn each source type's offset from the LUFS-corrected scores of linked
+/// performances.
+fn learn_source_offsets(
+    rows: &[CalibrationRow],
+    adjusted: &[Option<[Option<f64>; 10]>],
+) -> Vec<SourceOffset> {
+    let tracks: Vec<PerformanceTrack> = rows
+        .iter()
+        .zip(adjusted)
+        .filter_map(|(row, scores)| {
+            Some(PerformanceTrack {
+                performance_id: row.performance_id?,
+                band: row.parsed_band.clone().unwrap_or_default(),
+                date: row.parsed_date.clone(),
+                dir: crate::paths::source_dir(&row.file_path)
+                    .unwrap_or("")
+                    .to_string(),
+                source_type: row.source_type.clone(),
+                title: row.title.clone(),
+                scores: (*scores)?,
+            })
+        })
+        .collect();
+    crate::performances::learn_offsets(&crate::performances::compare(&tracks))
+}
+
+/// Subtract the offsets learned from enough performances from their source
+/// type's tracks.
+fn apply_source_offsets(
+    rows: &[CalibrationRow],
+    adjusted: &mut [Option<[Option<f64>; 10]>],
+    offsets: &[SourceOffset],
+) {
+    for (row, scores) in rows.iter().zip(adjusted.iter_mut()) {
+        let offset = offsets.iter().find(|o| {
+            o.performances >= MIN_OFFSET_PERFORMANCES
+                && row.source_type.as_deref() == Some(o.source_type.as_str())
+        });
+        let (Some(offset), Some(scores)) = (offset, scores) else {
+            continue;
+        };
+        for (score, delta) in scores.iter_mut().zip(offset.offsets) {
+            if delta.abs() < OFFSET_THRESHOLD {
+                continue;
+            }
+            if let Some(value) = score {
+                *value = (*value - delta).clamp(0.0, 100.0);
+            }
+        }
+    }
+}
+
+fn note_source_offsets(offsets: &[SourceOffset], progress: &dyn Progress) {
+    if offsets.is_empty() {
+        progress.note("Source offsets: no performances with more than one kind of source.");
+        progress.note("");
+        return;
+    }
+    progress.note("Source offsets (vs. other recordings of the same performance):");
+    let mut line = format!("  {:<15}", "");
+    for o in offsets {
+        line += &format!(" {:>8}", o.source_type);
+    }
+    progress.note(&line);
+    let mut line = format!("  {:<15}", "performances");
+    for o in offsets {
+        line += &format!(" {:>8}", o.performances);
+    }
+    progress.note(&line);
+    for (i, name) in SCORE_NAMES.iter().enumerate() {
+        let mut line = format!("  {name:<15}");
+        for o in offsets {
+            line += &format!(" {:>+8.1}", o.offsets[i]);
+        }
+        progress.note(&line);
+    }
+    progress.note(&format!(
+        "  (applied where learned from {MIN_OFFSET_PERFORMANCES}+ performances and at least {OFFSET_THRESHOLD:.0} point)"
+    ));
+    progress.note("");
+}
+
 fn show_key(row: &CalibrationRow) -> String {
     match &row.parsed_band {
         Some(band) => format!("{}|{}", band, row.parsed_date),
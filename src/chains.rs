@@ -1,4 +1,12 @@
+use crate::db::Database;
+use crate::db::columns::{NOT_GARBAGE, SHOW_QUALITY, TRACK_SCORE_SELECT, map_track_score};
 use crate::db::models::{ChainScore, TrackScore};
+use crate::progress::Progress;
+use rusqlite::params;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
 
 /// Check if a track title ends with a segue marker.
 /// Matches: " ->", "->", " -->", "-->", " >" (with trailing whitespace tolerance).
@@ -8,6 +16,56 @@ fn has_segue_marker(title: &str) -> bool {
     t.ends_with("-->") || t.ends_with("->") || t.ends_with(" >")
 }
 
+/// Canonical form of a set label, so the spellings of one set compare equal:
+/// "2", "II" and "Set 2" are `2`; "E", "Encore" and "Encore 1" are `e1`,
+/// "E2" is `e2`. Anything else is compared as written (case-folded).
+fn set_key(set: &str) -> Option<String> {
+    let s = set.trim().to_lowercase();
+    let s = s.strip_prefix("set").unwrap_or(&s).trim();
+    if s.is_empty() {
+        return None;
+    }
+    let number = |n: &str| -> Option<u32> {
+        match n.trim() {
+            "i" => Some(1),
+            "ii" => Some(2),
+            "iii" => Some(3),
+            "iv" => Some(4),
+            n => n.parse().ok(),
+        }
+    };
+    if let Some(n) = number(s) {
+        return Some(n.to_string());
+    }
+    for prefix in ["encore", "enc", "e"] {
+        if let Some(rest) = s.strip_prefix(prefix) {
+            let rest = rest.trim_start_matches([' ', '.', '#']);
+            match rest {
+                "" => return Some("e1".to_string()),
+                _ => {
+                    if let Some(n) = number(rest) {
+                        return Some(format!("e{n}"));
+                    }
+                }
+            }
+        }
+    }
+    Some(s.to_string())
+}
+
+/// True if two consecutive tracks are in different sets. A chain never spans
+/// the set break, whatever the titles' segue markers say; tracks with no set
+/// information never break a chain.
+fn crosses_set_break(a: &TrackScore, b: &TrackScore) -> bool {
+    match (
+        a.set.as_deref().and_then(set_key),
+        b.set.as_deref().and_then(set_key),
+    ) {
+        (Some(x), Some(y)) => x != y,
+        _ => false,
+    }
+}
+
 /// Detect segue chains using canonical setlist data as the authoritative source.
 ///
 /// `setlist` is a list of (song_name, segued, set_num, position) from the setlists table.
@@ -33,12 +91,11 @@ pub fn detect_chains_with_setlist(
     let mut current_chain: Vec<&TrackScore> = Vec::new();
 
     for track in tracks {
-        let prev_segued = if current_chain.is_empty() {
-            false
-        } else {
-            let prev = current_chain.last().unwrap();
-            lookup_segue(&prev.title, &setlist_entries)
-                .unwrap_or_else(|| has_segue_marker(&prev.title))
+        let prev_segued = match current_chain.last() {
+            None => false,
+            Some(prev) if crosses_set_break(prev, track) => false,
+            Some(prev) => lookup_segue(&prev.title, &setlist_entries)
+                .unwrap_or_else(|| has_segue_marker(&prev.title)),
         };
 
         if current_chain.is_empty() || prev_segued {
@@ -108,15 +165,20 @@ fn strip_segue_suffix(title: &str) -> &str {
 /// Detect segue chains from an ordered list of tracks within a single show.
 /// Tracks must be pre-sorted by disc/track order.
 /// `min_length` is the minimum number of songs to form a chain (typically 2).
+/// Set changes are hard boundaries: a stray "->" at the end of a set doesn't
+/// chain it into the next set or the encore.
 pub fn detect_chains(tracks: &[TrackScore], min_length: usize) -> Vec<ChainScore> {
     let mut chains = Vec::new();
     let mut current_chain: Vec<&TrackScore> = Vec::new();
 
     for track in tracks {
+        let segued = current_chain
+            .last()
+            .is_some_and(|prev| has_segue_marker(&prev.title) && !crosses_set_break(prev, track));
         if current_chain.is_empty() {
             // Start potential chain
             current_chain.push(track);
-        } else if has_segue_marker(&current_chain.last().unwrap().title) {
+        } else if segued {
             // Previous track segued into this one
             current_chain.push(track);
         } else {
@@ -158,20 +220,10 @@ pub fn filter_and_sort_chains(
         chains.retain(|c| c.songs.iter().any(|s| s.to_lowercase().contains(&p)));
     }
 
-    // Sort by the requested column (descending)
-    let score_fn: fn(&ChainScore) -> f64 = match sort_column {
-        "energy_score" => |c| c.energy,
-        "intensity_score" => |c| c.intensity,
-        "groove_score" => |c| c.groove,
-        "improvisation_score" => |c| c.improvisation,
-        "tightness_score" => |c| c.tightness,
-        "build_quality_score" => |c| c.build_quality,
-        "exploratory_score" => |c| c.exploratory,
-        "transcendence_score" => |c| c.transcendence,
-        "valence_score" => |c| c.valence,
-        "arousal_score" => |c| c.arousal,
-        "duration" => |c| c.duration_min,
-        _ => |c| c.transcendence, // default
+    // Sort by the requested column (descending); unknown columns fall back to transcendence
+    let score_fn = |c: &ChainScore| match sort_column {
+        "duration" => c.duration_min,
+        col => c.score(col).unwrap_or(c.transcendence),
     };
 
     chains.sort_by(|a, b| {
@@ -183,15 +235,454 @@ pub fn filter_and_sort_chains(
     chains
 }
 
+/// One track's place in a persisted chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainMembership {
+    pub chain_id: i64,
+    pub track_id: i64,
+    pub date: String,
+    /// Song titles of the whole chain, in segue order.
+    pub songs: Vec<String>,
+    /// 0-based index of the track within the chain.
+    pub position: usize,
+    pub duration_min: f64,
+    pub transcendence: f64,
+}
+
+impl ChainMembership {
+    /// How the song functions here: "launch" (opens the chain), "landing"
+    /// (closes it), or "bridge" (in between).
+    pub fn role(&self) -> &'static str {
+        if self.position == 0 {
+            "launch"
+        } else if self.position + 1 >= self.songs.len() {
+            "landing"
+        } else {
+            "bridge"
+        }
+    }
+
+    /// The song segued from, if any.
+    pub fn previous(&self) -> Option<&str> {
+        let i = self.position.checked_sub(1)?;
+        self.songs.get(i).map(String::as_str)
+    }
+
+    /// The song segued into, if any.
+    pub fn next(&self) -> Option<&str> {
+        self.songs.get(self.position + 1).map(String::as_str)
+    }
+}
+
+/// Longest song-title part of an exported chain's filename.
+const MAX_TITLE_SLUG: usize = 100;
+
+/// Playlist filename for a chain: `<date>-<song-slugs>.m3u`, e.g.
+/// `1972-08-27-dark-star-el-paso.m3u`. Long chains are cut at a word boundary.
+pub fn chain_playlist_name(chain: &ChainScore) -> String {
+    let mut slug = crate::playlist::slugify(&chain.songs.join(" "));
+    if slug.len() > MAX_TITLE_SLUG {
+        let cut = slug[..MAX_TITLE_SLUG].rfind('-').unwrap_or(MAX_TITLE_SLUG);
+        slug.truncate(cut);
+    }
+    if slug.is_empty() {
+        format!("{}-chain.m3u", chain.date)
+    } else {
+        format!("{}-{}.m3u", chain.date, slug)
+    }
+}
+
+/// Write each chain as an M3U (tracks in segue order) into `out_dir`.
+/// Chains that would share a filename get `-2`, `-3`, ... suffixes.
+/// Returns the written paths.
+pub fn export_m3u(chains: &[ChainScore], out_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(out_dir)?;
+    let mut used: HashSet<String> = HashSet::new();
+    let mut written = Vec::new();
+    for chain in chains {
+        let base = chain_playlist_name(chain);
+        let mut name = base.clone();
+        let mut n = 2;
+        while !used.insert(name.clone()) {
+            name = format!("{}-{n}.m3u", base.trim_end_matches(".m3u"));
+            n += 1;
+        }
+        let path = out_dir.join(name);
+        crate::playlist::write_m3u(&path, &chain.tracks)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Canonical spelling of a chain song: cleaned-up text with title aliases
+/// applied, so the same song reads the same whichever tape it came from.
+fn canonical_song(title: &str, aliases: &HashMap<String, String>) -> String {
+    let song = crate::title_normalize::normalize(title);
+    aliases.get(&song).cloned().unwrap_or(song)
+}
+
+/// Identity of a chain: its date and ordered song list, each song reduced to
+/// its base name (part markers stripped, case folded) and repeats merged, so
+/// "Dark Star (1) > Dark Star (2) > El Paso" from one tape and
+/// "Dark Star > El Paso" from another are the same chain.
+pub fn chain_identity(date: &str, songs: &[String]) -> String {
+    let mut keys: Vec<String> = Vec::new();
+    for song in songs {
+        let key = crate::track_groups::song_part(song).0.to_lowercase();
+        if keys.last() != Some(&key) {
+            keys.push(key);
+        }
+    }
+    format!("{date}|{}", keys.join(" > "))
+}
+
+/// How good a source's copy of a chain is: recording type first (see
+/// `scanner::source::source_quality`), then fewer tracks flagged by `quality`.
+type SourceRank = (i32, Reverse<usize>);
+
+/// Collapse chains with the same identity to one, keeping the copy from the
+/// best-ranked source. On a tie the earlier chain wins (sources come most
+/// complete first). Order of first appearance is kept.
+fn dedup_chains(found: Vec<(SourceRank, ChainScore)>) -> Vec<ChainScore> {
+    let mut kept: Vec<(SourceRank, ChainScore)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (rank, chain) in found {
+        let identity = chain_identity(&chain.date, &chain.songs);
+        match index.get(&identity) {
+            Some(&i) if rank > kept[i].0 => kept[i] = (rank, chain),
+            Some(_) => {}
+            None => {
+                index.insert(identity, kept.len());
+                kept.push((rank, chain));
+            }
+        }
+    }
+    kept.into_iter().map(|(_, chain)| chain).collect()
+}
+
+/// Re-detect chains for every show with segue data and replace the `chains` table.
+/// Chains are stored at the minimum length (2); callers filter longer ones at read time.
+/// Song titles are canonicalized, and a chain found on several tapes of the same
+/// show is stored once, from the best source. Returns the number of chains stored.
+pub fn rebuild_chain_cache(db: &Database, progress: &dyn Progress) -> crate::db::Result<usize> {
+    let fingerprint = db.chain_input_fingerprint()?;
+    let dates = db.get_dates_with_chains_or_setlists()?;
+    let aliases: HashMap<String, String> = db
+        .title_aliases()?
+        .into_iter()
+        .map(|a| (a.alias, a.canonical))
+        .collect();
+
+    progress.start(dates.len() as u64, "shows");
+
+    // Shows are independent, so read them on parallel connections
+    let by_date = db.par_read(&dates, Database::default_readers(), |reader, d| {
+        let sources = crate::show_sources::split_by_source(reader.query_show(d)?);
+        let setlist = reader.get_setlist_for_date(d).unwrap_or_default();
+        let ranks = reader.source_ranks(d)?;
+        // Within each source only: a segue can't cross from one tape to another.
+        // Use setlist segue data if available, else fall back to filename markers
+        let mut found = Vec::new();
+        for source in &sources {
+            let chains = if setlist.is_empty() {
+                detect_chains(&source.tracks, 2)
+            } else {
+                detect_chains_with_setlist(&source.tracks, &setlist, 2)
+            };
+            let rank = ranks.get(&source.dir).copied().unwrap_or_default();
+            for mut chain in chains {
+                for song in &mut chain.songs {
+                    *song = canonical_song(song, &aliases);
+                }
+                found.push((rank, chain));
+            }
+        }
+        progress.inc(1);
+        Ok((d.clone(), dedup_chains(found)))
+    })?;
+    progress.finish("");
+
+    db.store_chains(&by_date, &fingerprint)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Rank of each recording directory for a date, keyed like
+    /// `show_sources::split_by_source`.
+    fn source_ranks(&self, date: &str) -> crate::db::Result<HashMap<String, SourceRank>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT file_path, source_type, COALESCE(data_quality, 'ok')
+             FROM tracks
+             WHERE parsed_date = ?1 OR date = ?1",
+        )?;
+        let mut rows = stmt.query(params![date])?;
+        let mut ranks: HashMap<String, SourceRank> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let path: String = row.get(0)?;
+            let source_type: Option<String> = row.get(1)?;
+            let quality: String = row.get(2)?;
+            let dir = crate::paths::source_dir(&path).unwrap_or("").to_string();
+            let rank = ranks.entry(dir).or_default();
+            rank.0 = rank.0.max(crate::scanner::source::source_quality(
+                source_type.as_deref(),
+            ));
+            if quality != "ok" {
+                rank.1.0 += 1;
+            }
+        }
+        Ok(ranks)
+    }
+
+    /// Cheap fingerprint of everything chain detection depends on: analysis rows and
+    /// scores, track titles and quality flags, and setlist segue data.
+    pub fn chain_input_fingerprint(&self) -> crate::db::Result<String> {
+        let fp = self.conn.query_row(
+            "SELECT
+                (SELECT COUNT(*) || ':' || COALESCE(MAX(analyzed_at), '') || ':' ||
+                        TOTAL(COALESCE(energy_score, 0) + COALESCE(intensity_score, 0) +
+                              COALESCE(groove_score, 0) + COALESCE(improvisation_score, 0) +
+                              COALESCE(tightness_score, 0) + COALESCE(build_quality_score, 0) +
+                              COALESCE(exploratory_score, 0) + COALESCE(transcendence_score, 0) +
+                              COALESCE(valence_score, 0) + COALESCE(arousal_score, 0))
+                 FROM analysis_results)
+                || '|' ||
+                (SELECT COUNT(*) || ':' || COALESCE(MAX(updated_at), '') || ':' ||
+                        TOTAL(COALESCE(data_quality, 'ok') = 'garbage') || ':' ||
+                        TOTAL(LENGTH(COALESCE(parsed_title, title, '')))
+                 FROM tracks)
+                || '|' ||
+                (SELECT COUNT(*) || ':' || TOTAL(segued) FROM setlists)",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(fp)
+    }
+
+    /// True if the chains table was built from the current library state.
+    pub fn chain_cache_is_fresh(&self) -> crate::db::Result<bool> {
+        let stored: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT fingerprint FROM chain_cache_state WHERE id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        let current = self.chain_input_fingerprint()?;
+        Ok(stored.as_deref() == Some(current.as_str()))
+    }
+
+    /// Replace all persisted chains (bulk insert within a transaction).
+    pub fn store_chains(
+        &self,
+        by_date: &[(String, Vec<ChainScore>)],
+        fingerprint: &str,
+    ) -> crate::db::Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM chain_tracks", [])?;
+        tx.execute("DELETE FROM chains", [])?;
+
+        let mut count = 0;
+        {
+            let mut chain_stmt = tx.prepare_cached(
+                "INSERT INTO chains (date, songs_json, chain_length, duration_min,
+                    energy, intensity, groove, improvisation, tightness, build_quality,
+                    exploratory, transcendence, valence, arousal, identity)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            )?;
+            let mut track_stmt = tx.prepare_cached(
+                "INSERT INTO chain_tracks (chain_id, position, track_id)
+                 SELECT ?1, ?2, id FROM tracks WHERE file_path = ?3",
+            )?;
+
+            for (date, chains) in by_date {
+                for c in chains {
+                    chain_stmt.execute(params![
+                        date,
+                        serde_json::to_string(&c.songs).unwrap_or_default(),
+                        c.chain_length as i64,
+                        c.duration_min,
+                        c.energy,
+                        c.intensity,
+                        c.groove,
+                        c.improvisation,
+                        c.tightness,
+                        c.build_quality,
+                        c.exploratory,
+                        c.transcendence,
+                        c.valence,
+                        c.arousal,
+                        chain_identity(date, &c.songs),
+                    ])?;
+                    let chain_id = tx.last_insert_rowid();
+                    for (pos, t) in c.tracks.iter().enumerate() {
+                        track_stmt.execute(params![chain_id, pos as i64, t.file_path])?;
+                    }
+                    count += 1;
+                }
+            }
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO chain_cache_state (id, fingerprint, built_at)
+             VALUES (1, ?1, datetime('now'))",
+            params![fingerprint],
+        )?;
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Every persisted chain the track belongs to, with its position.
+    pub fn chains_for_track(&self, track_id: i64) -> crate::db::Result<Vec<ChainMembership>> {
+        self.chain_memberships("ct.track_id = ?1", params![track_id])
+    }
+
+    /// Chain memberships of every track whose title contains `song`, optionally
+    /// narrowed to one date and/or paths containing `path_substr`; by date.
+    pub fn chains_containing(
+        &self,
+        song: &str,
+        date: Option<&str>,
+        path_substr: Option<&str>,
+    ) -> crate::db::Result<Vec<ChainMembership>> {
+        self.chain_memberships(
+            "(t.parsed_title LIKE ?1 OR t.title LIKE ?1)
+               AND (?2 IS NULL OR c.date = ?2)
+               AND (?3 IS NULL OR INSTR(LOWER(t.file_path), LOWER(?3)) > 0)",
+            params![format!("%{song}%"), date, path_substr],
+        )
+    }
+
+    fn chain_memberships(
+        &self,
+        condition: &str,
+        params: &[&dyn rusqlite::types::ToSql],
+    ) -> crate::db::Result<Vec<ChainMembership>> {
+        let sql = format!(
+            "SELECT c.id, ct.track_id, c.date, c.songs_json, ct.position,
+                    c.duration_min, c.transcendence
+             FROM chain_tracks ct
+             JOIN chains c ON c.id = ct.chain_id
+             JOIN tracks t ON t.id = ct.track_id
+             WHERE {condition}
+             ORDER BY c.date, c.id"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params, |row| {
+                let songs_json: String = row.get(3)?;
+                Ok(ChainMembership {
+                    chain_id: row.get(0)?,
+                    track_id: row.get(1)?,
+                    date: row.get(2)?,
+                    songs: serde_json::from_str(&songs_json).unwrap_or_default(),
+                    position: row.get::<_, i64>(4)? as usize,
+                    duration_min: row.get(5)?,
+                    transcendence: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Load persisted chains (with their track scores), optionally narrowed to one date,
+    /// shows whose first chain track's path contains `path_substr`, and shows at
+    /// least `min_show_quality` clean.
+    pub fn get_cached_chains(
+        &self,
+        date: Option<&str>,
+        path_substr: Option<&str>,
+        min_length: usize,
+        min_show_quality: Option<f64>,
+    ) -> crate::db::Result<Vec<ChainScore>> {
+        let sql = format!(
+            "SELECT c.id, c.date, c.songs_json, c.chain_length, c.duration_min,
+                    c.energy, c.intensity, c.groove, c.improvisation, c.tightness,
+                    c.build_quality, c.exploratory, c.transcendence, c.valence, c.arousal
+             FROM chains c
+             JOIN chain_tracks ct0 ON ct0.chain_id = c.id AND ct0.position = 0
+             JOIN tracks t ON t.id = ct0.track_id
+             WHERE c.chain_length >= ?1
+               AND (?2 IS NULL OR c.date = ?2)
+               AND (?3 IS NULL OR INSTR(LOWER(t.file_path), LOWER(?3)) > 0)
+               AND (?4 IS NULL OR {SHOW_QUALITY} >= ?4)
+             ORDER BY c.id"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut chains: Vec<(i64, ChainScore)> = stmt
+            .query_map(
+                params![min_length as i64, date, path_substr, min_show_quality],
+                |row| {
+                    let songs_json: String = row.get(2)?;
+                    Ok((
+                        row.get(0)?,
+                        ChainScore {
+                            date: row.get(1)?,
+                            songs: serde_json::from_str(&songs_json).unwrap_or_default(),
+                            chain_length: row.get::<_, i64>(3)? as usize,
+                            duration_min: row.get(4)?,
+                            energy: row.get(5)?,
+                            intensity: row.get(6)?,
+                            groove: row.get(7)?,
+                            improvisation: row.get(8)?,
+                            tightness: row.get(9)?,
+                            build_quality: row.get(10)?,
+                            exploratory: row.get(11)?,
+                            transcendence: row.get(12)?,
+                            valence: row.get(13)?,
+                            arousal: row.get(14)?,
+                            tracks: Vec::new(),
+                        },
+                    ))
+                },
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        // Attach track scores in one pass
+        let index: HashMap<i64, usize> = chains
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, i))
+            .collect();
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT},
+                ct.chain_id
+             FROM chain_tracks ct
+             JOIN tracks t ON t.id = ct.track_id
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE {NOT_GARBAGE}
+             ORDER BY ct.chain_id, ct.position"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(19)?, map_track_score(row)?))
+        })?;
+        for row in rows {
+            let (chain_id, track) = row?;
+            if let Some(&i) = index.get(&chain_id) {
+                chains[i].1.tracks.push(track);
+            }
+        }
+
+        Ok(chains.into_iter().map(|(_, c)| c).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::queries::tests::new_track;
+    use crate::progress::SilentProgress;
 
     fn make_track(title: &str, duration_min: f64, transcendence: f64) -> TrackScore {
         TrackScore {
             title: title.to_string(),
             date: "1977-05-08".to_string(),
             file_path: String::new(),
+            band: None,
+            set: None,
+            album: None,
             duration_min,
             key: None,
             tempo: None,
@@ -258,6 +749,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_export_m3u_names_and_order() {
+        let mut tracks = vec![
+            make_track("Playing in the Band ->", 20.0, 90.0),
+            make_track("Uncle John's Band ->", 10.0, 70.0),
+            make_track("Playing in the Band", 5.0, 80.0),
+        ];
+        for (i, t) in tracks.iter_mut().enumerate() {
+            t.file_path = format!("/music/gd72/t{i}.flac");
+        }
+        let chain = detect_chains(&tracks, 2).remove(0);
+        assert_eq!(
+            chain_playlist_name(&chain),
+            "1977-05-08-playing-in-the-band-uncle-john-s-band-playing-in-the-band.m3u"
+        );
+
+        let dir = std::env::temp_dir().join(format!("setbreak-chains-{}", std::process::id()));
+        let written = export_m3u(&[chain.clone(), chain], &dir).unwrap();
+        let names: Vec<_> = written
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert!(names[1].ends_with("-playing-in-the-band-2.m3u"));
+        let m3u = std::fs::read_to_string(&written[0]).unwrap();
+        let paths: Vec<_> = m3u.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/music/gd72/t0.flac",
+                "/music/gd72/t1.flac",
+                "/music/gd72/t2.flac"
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_multiple_chains_per_show() {
         let tracks = vec![
@@ -420,4 +947,217 @@ mod tests {
         assert_eq!(chains[1].songs[0], "Saint Stephen");
         assert_eq!(chains[1].songs[3], "Morning Dew");
     }
+
+    #[test]
+    fn test_set_key() {
+        for (set, key) in [
+            ("2", "2"),
+            ("II", "2"),
+            ("Set 2", "2"),
+            ("set ii", "2"),
+            ("E", "e1"),
+            ("Encore", "e1"),
+            ("Encore 1", "e1"),
+            ("E2", "e2"),
+            ("Enc. 2", "e2"),
+            ("Soundcheck", "soundcheck"),
+        ] {
+            assert_eq!(set_key(set).as_deref(), Some(key), "{set}");
+        }
+        assert_eq!(set_key("  "), None);
+    }
+
+    #[test]
+    fn test_chains_stop_at_set_break() {
+        let in_set = |title: &str, set: Option<&str>| TrackScore {
+            set: set.map(str::to_string),
+            ..make_track(title, 10.0, 50.0)
+        };
+        // Stray "->" closing set 1 and set 2; the encore has its own segue
+        let tracks = vec![
+            in_set("Playing in the Band ->", Some("1")),
+            in_set("Bird Song ->", Some("1")),
+            in_set("Scarlet Begonias ->", Some("II")),
+            in_set("Fire on the Mountain ->", Some("Set 2")),
+            in_set("U.S. Blues ->", Some("Encore")),
+            in_set("Johnny B. Goode ->", Some("E")),
+            in_set("Brokedown Palace", Some("E2")),
+        ];
+        let chains = detect_chains(&tracks, 2);
+        let songs: Vec<Vec<&str>> = chains
+            .iter()
+            .map(|c| c.songs.iter().map(String::as_str).collect())
+            .collect();
+        assert_eq!(
+            songs,
+            vec![
+                vec!["Playing in the Band", "Bird Song"],
+                vec!["Scarlet Begonias", "Fire on the Mountain"],
+                vec!["U.S. Blues", "Johnny B. Goode"],
+            ]
+        );
+
+        // Setlist segue data doesn't override the break either
+        let setlist = vec![
+            ("Bird Song".into(), true, 1, 2),
+            ("Scarlet Begonias".into(), true, 2, 1),
+        ];
+        let chains = detect_chains_with_setlist(&tracks[1..4], &setlist, 2);
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].songs[0], "Scarlet Begonias");
+
+        // Unknown sets never break a chain
+        let tracks = vec![in_set("Dark Star ->", Some("2")), in_set("El Paso", None)];
+        assert_eq!(detect_chains(&tracks, 2).len(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_chains_collapse_to_best_source() {
+        let aud = detect_chains(
+            &[
+                make_track("Dark Star (Part 1) ->", 12.0, 80.0),
+                make_track("Dark Star (Part 2) ->", 10.0, 85.0),
+                make_track("el paso", 5.0, 40.0),
+            ],
+            2,
+        );
+        let sbd = detect_chains(
+            &[
+                make_track("Dark Star ->", 22.0, 90.0),
+                make_track("El Paso", 5.0, 45.0),
+            ],
+            2,
+        );
+        assert_eq!(
+            chain_identity("1972-08-27", &aud[0].songs),
+            "1972-08-27|dark star > el paso"
+        );
+        assert_eq!(
+            chain_identity("1972-08-27", &aud[0].songs),
+            chain_identity("1972-08-27", &sbd[0].songs)
+        );
+
+        let aliases = HashMap::from([("Playin'".to_string(), "Playing in the Band".to_string())]);
+        assert_eq!(canonical_song("Playin'", &aliases), "Playing in the Band");
+        assert_eq!(canonical_song("El Paso", &aliases), "El Paso");
+
+        // The aud tape is first (more files), but the sbd copy wins
+        let other = detect_chains(
+            &[
+                make_track("Sugar Magnolia ->", 8.0, 60.0),
+                make_track("Sunshine Daydream", 3.0, 70.0),
+            ],
+            2,
+        );
+        let kept = dedup_chains(vec![
+            ((1, Reverse(0)), aud[0].clone()),
+            ((1, Reverse(0)), other[0].clone()),
+            ((3, Reverse(1)), sbd[0].clone()),
+        ]);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].songs, vec!["Dark Star", "El Paso"]);
+        assert_eq!(kept[0].transcendence, sbd[0].transcendence);
+        assert_eq!(kept[1].songs[0], "Sugar Magnolia");
+
+        // Same source type: fewer flagged tracks wins, ties keep the first
+        let kept = dedup_chains(vec![
+            ((2, Reverse(1)), aud[0].clone()),
+            ((2, Reverse(0)), sbd[0].clone()),
+        ]);
+        assert_eq!(kept[0].chain_length, 2);
+        let kept = dedup_chains(vec![
+            ((2, Reverse(0)), aud[0].clone()),
+            ((2, Reverse(0)), sbd[0].clone()),
+        ]);
+        assert_eq!(kept[0].chain_length, 3);
+    }
+
+    #[test]
+    fn test_chain_cache_roundtrip() {
+        use crate::db::models::{NewAnalysis, NewTrack};
+
+        let db = Database::open_in_memory().unwrap();
+        let titles = [
+            "Scarlet Begonias ->",
+            "Fire on the Mountain",
+            "Estimated Prophet",
+        ];
+        let mut ids = Vec::new();
+        for (i, title) in titles.iter().enumerate() {
+            let id = db
+                .upsert_track(&NewTrack {
+                    parsed_band: Some("Grateful Dead".into()),
+                    parsed_date: Some("1977-05-08".into()),
+                    parsed_disc: Some(1),
+                    parsed_track: Some(i as i32 + 1),
+                    parsed_title: Some(title.to_string()),
+                    recording_type: Some("live".into()),
+                    ..new_track(&format!("/music/grateful_dead/gd77-05-08d1t0{i}.flac"))
+                })
+                .unwrap();
+            db.store_analysis(&NewAnalysis {
+                track_id: id,
+                duration: Some(600.0),
+                transcendence_score: Some(70.0),
+                ..Default::default()
+            })
+            .unwrap();
+            ids.push(id);
+        }
+
+        assert!(!db.chain_cache_is_fresh().unwrap());
+        assert_eq!(rebuild_chain_cache(&db, &SilentProgress).unwrap(), 1);
+        assert!(db.chain_cache_is_fresh().unwrap());
+
+        let chains = db
+            .get_cached_chains(None, Some("grateful_dead"), 2, None)
+            .unwrap();
+        assert_eq!(chains.len(), 1);
+        assert_eq!(
+            chains[0].chain_title(),
+            "Scarlet Begonias -> Fire on the Mountain"
+        );
+        assert_eq!(chains[0].tracks.len(), 2);
+        assert!(
+            db.get_cached_chains(None, None, 3, None)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            db.get_cached_chains(None, Some("phish"), 2, None)
+                .unwrap()
+                .is_empty()
+        );
+
+        let memberships = db.chains_for_track(ids[1]).unwrap();
+        assert_eq!(memberships.len(), 1);
+        assert_eq!(memberships[0].position, 1);
+        assert_eq!(memberships[0].role(), "landing");
+        assert_eq!(memberships[0].previous(), Some("Scarlet Begonias"));
+        assert!(db.chains_for_track(ids[2]).unwrap().is_empty());
+        let found = db.chains_containing("Scarlet", None, None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].role(), "launch");
+        assert_eq!(found[0].next(), Some("Fire on the Mountain"));
+
+        // One suspect track of three: the show is 67% clean
+        db.update_data_quality(ids[0], "suspect").unwrap();
+        assert_eq!(
+            db.get_cached_chains(None, None, 2, Some(0.5))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(
+            db.get_cached_chains(None, None, 2, Some(0.75))
+                .unwrap()
+                .is_empty()
+        );
+        db.update_data_quality(ids[0], "ok").unwrap();
+
+        // Title edits invalidate the cache
+        db.update_parsed_title(ids[1], "Fire on the Mountain ->")
+            .unwrap();
+        assert!(!db.chain_cache_is_fresh().unwrap());
+    }
 }
@@ -4,6 +4,7 @@
 //! tracks with similar harmonic content, even across different keys.
 
 use crate::db::Database;
+use crate::db::columns::BAND_EXPR;
 
 /// Pitch class names for display.
 const PITCH_CLASSES: [&str; 12] = [
@@ -16,9 +17,13 @@ pub struct ChromaTrack {
     pub track_id: i64,
     pub title: String,
     pub date: String,
+    /// Parsed (canonical) band, else the artist tag.
+    pub band: String,
     pub key: String,
     pub chroma: [f64; 12],
     pub duration_min: f64,
+    /// Octave-corrected tempo, BPM.
+    pub tempo: Option<f64>,
 }
 
 /// Result of a harmonic similarity search.
@@ -27,6 +32,7 @@ pub struct HarmonicMatch {
     pub date: String,
     pub key: String,
     pub duration_min: f64,
+    pub tempo: Option<f64>,
     pub distance: f64,
     pub transposition: i32,
     pub dominant_pitch_class: String,
@@ -68,7 +74,7 @@ fn rotate_chroma(chroma: &[f64; 12], semitones: i32) -> [f64; 12] {
 }
 
 /// Cosine distance between two chroma vectors (0 = identical, 2 = opposite).
-fn chroma_cosine_distance(a: &[f64; 12], b: &[f64; 12]) -> f64 {
+pub(crate) fn chroma_cosine_distance(a: &[f64; 12], b: &[f64; 12]) -> f64 {
     let mut dot = 0.0;
     let mut norm_a = 0.0;
     let mut norm_b = 0.0;
@@ -106,7 +112,7 @@ fn best_transposition(a: &[f64; 12], b: &[f64; 12]) -> (f64, i32) {
 }
 
 /// Find the dominant pitch class in a chroma vector.
-fn dominant_pitch_class(chroma: &[f64; 12]) -> &'static str {
+pub(crate) fn dominant_pitch_class(chroma: &[f64; 12]) -> &'static str {
     let mut max_idx = 0;
     let mut max_val = chroma[0];
     for (i, &val) in chroma.iter().enumerate().skip(1) {
@@ -118,15 +124,30 @@ fn dominant_pitch_class(chroma: &[f64; 12]) -> &'static str {
     PITCH_CLASSES[max_idx]
 }
 
+/// Whether two tempos are within `tolerance` (a fraction, e.g. 0.08) of each other,
+/// for mixing. Unknown tempos never match.
+fn tempo_compatible(a: Option<f64>, b: Option<f64>, tolerance: f64) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) if a > 0.0 => (a - b).abs() / a <= tolerance,
+        _ => false,
+    }
+}
+
 /// Find harmonically similar tracks (transposition-aware chroma matching).
 ///
 /// If `allow_transposition` is true, tries all 12 rotations and picks the best.
-/// Otherwise, compares chroma vectors directly (same key only).
+/// Otherwise, compares chroma vectors directly (same key only). With
+/// `tempo_tolerance`, only tracks whose corrected tempo is that close (as a
+/// fraction of the target's) are kept, so matches can be mixed without a
+/// half/double-time misread throwing them out. With `same_band`, only tracks
+/// by the target's band are considered.
 pub fn find_harmonic_matches(
     db: &Database,
     target_id: i64,
     limit: usize,
     allow_transposition: bool,
+    tempo_tolerance: Option<f64>,
+    same_band: bool,
 ) -> Result<(ChromaTrack, Vec<HarmonicMatch>), String> {
     let tracks = load_chroma_tracks(db).map_err(|e| format!("DB error: {e}"))?;
 
@@ -139,6 +160,8 @@ pub fn find_harmonic_matches(
     let mut matches: Vec<HarmonicMatch> = tracks
         .iter()
         .filter(|t| t.track_id != target_id)
+        .filter(|t| !same_band || t.band.eq_ignore_ascii_case(&target.band))
+        .filter(|t| tempo_tolerance.is_none_or(|tol| tempo_compatible(target.tempo, t.tempo, tol)))
         .map(|t| {
             let (distance, transposition) = if allow_transposition {
                 best_transposition(&target.chroma, &t.chroma)
@@ -153,6 +176,7 @@ pub fn find_harmonic_matches(
                 date: t.date.clone(),
                 key: t.key.clone(),
                 duration_min: t.duration_min,
+                tempo: t.tempo,
                 distance,
                 transposition,
                 dominant_pitch_class: dominant_pitch_class(&t.chroma).to_string(),
@@ -180,18 +204,23 @@ fn load_chroma_tracks(db: &Database) -> crate::db::Result<Vec<ChromaTrack>> {
 impl Database {
     /// Load all tracks that have a chroma_vector.
     pub fn query_chroma_tracks(&self) -> crate::db::Result<Vec<ChromaTrack>> {
-        let sql = "SELECT a.track_id,
-                          COALESCE(t.parsed_title, t.title, '(untitled)'),
-                          COALESCE(t.parsed_date, t.date, '?'),
-                          COALESCE(a.estimated_key, '?'),
-                          a.chroma_vector,
-                          COALESCE(a.duration, 0) / 60.0
-                   FROM analysis_results a
-                   JOIN tracks t ON t.id = a.track_id
-                   WHERE a.chroma_vector IS NOT NULL
-                     AND COALESCE(t.data_quality, 'ok') != 'garbage'";
-
-        let mut stmt = self.conn.prepare(sql)?;
+        let sql = format!(
+            "SELECT a.track_id,
+                    COALESCE(t.parsed_title, t.title, '(untitled)'),
+                    COALESCE(t.parsed_date, t.date, '?'),
+                    COALESCE(a.estimated_key, '?'),
+                    b.chroma_vector,
+                    COALESCE(a.duration, 0) / 60.0,
+                    COALESCE(a.tempo_bpm_corrected, a.tempo_bpm),
+                    {BAND_EXPR}
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             JOIN analysis_blobs b ON b.track_id = a.track_id
+             WHERE b.chroma_vector IS NOT NULL
+               AND COALESCE(t.data_quality, 'ok') != 'garbage'"
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
         let mut tracks = Vec::new();
 
         let mut rows = stmt.query([])?;
@@ -202,15 +231,19 @@ impl Database {
             let key: String = row.get(3)?;
             let chroma_json: String = row.get(4)?;
             let duration_min: f64 = row.get(5)?;
+            let tempo: Option<f64> = row.get(6)?;
+            let band: String = row.get(7)?;
 
             if let Some(chroma) = parse_chroma(&chroma_json) {
                 tracks.push(ChromaTrack {
                     track_id,
                     title,
                     date,
+                    band,
                     key,
                     chroma,
                     duration_min,
+                    tempo,
                 });
             }
         }
@@ -223,6 +256,13 @@ impl Database {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tempo_compatible() {
+        assert!(tempo_compatible(Some(120.0), Some(126.0), 0.08));
+        assert!(!tempo_compatible(Some(120.0), Some(140.0), 0.08));
+        assert!(!tempo_compatible(Some(120.0), None, 0.5));
+    }
+
     #[test]
     fn test_rotate_identity() {
         let chroma = [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
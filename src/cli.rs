@@ -0,0 +1,7566 @@
+//! The command-line interface, shared by the `setbreak` and `setbreak-query`
+//! binaries (which differ only in the `analysis` feature).
+
+use crate::db::models::{ChainScore, TrackScore};
+use crate::progress::{CliProgress, Progress};
+use crate::scores::ScoreMeta;
+use crate::table::{Column, Table, TableOptions};
+use anyhow::{Context, Result};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(version, about = "Jam-band music library analyzer")]
+struct Cli {
+    /// Path to the SQLite database
+    #[arg(long, global = true)]
+    db_path: Option<PathBuf>,
+
+    /// Verbosity (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Score columns for track tables, by short name (e.g. grv,imp,val)
+    #[arg(long, global = true, value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// Don't fit tables to the terminal width (never truncate titles)
+    #[arg(long, global = true)]
+    wide: bool,
+
+    /// Only results, warnings, and errors: no progress bars or notices (for cron/CI)
+    #[arg(long, global = true)]
+    quiet: bool,
+
+    /// Don't draw progress bars (output is otherwise unchanged)
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+/// A non-essential notice on stderr (cache rebuilds, where a file went);
+/// `--quiet` drops it, like the library's `Progress::note` lines. Results go
+/// to stdout and errors to stderr regardless.
+macro_rules! note {
+    ($($arg:tt)*) => {
+        if !crate::progress::notes_hidden() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+#[derive(Clone, ValueEnum)]
+enum ScoreName {
+    Energy,
+    Intensity,
+    Groove,
+    Improvisation,
+    Tightness,
+    #[value(alias = "build")]
+    BuildQuality,
+    Exploratory,
+    Transcendence,
+    Valence,
+    Arousal,
+}
+
+impl ScoreName {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Energy => "energy_score",
+            Self::Intensity => "intensity_score",
+            Self::Groove => "groove_score",
+            Self::Improvisation => "improvisation_score",
+            Self::Tightness => "tightness_score",
+            Self::BuildQuality => "build_quality_score",
+            Self::Exploratory => "exploratory_score",
+            Self::Transcendence => "transcendence_score",
+            Self::Valence => "valence_score",
+            Self::Arousal => "arousal_score",
+        }
+    }
+
+    fn from_column(column: &str) -> Option<Self> {
+        Self::value_variants()
+            .iter()
+            .find(|s| s.column() == column)
+            .cloned()
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Energy => "energy",
+            Self::Intensity => "intensity",
+            Self::Groove => "groove",
+            Self::Improvisation => "improvisation",
+            Self::Tightness => "tightness",
+            Self::BuildQuality => "build quality",
+            Self::Exploratory => "exploratory",
+            Self::Transcendence => "transcendence",
+            Self::Valence => "valence",
+            Self::Arousal => "arousal",
+        }
+    }
+}
+
+/// `shows --top`: what shows are ranked by.
+#[derive(Clone, Copy, ValueEnum)]
+enum ShowRank {
+    /// Minutes of music in tracks with high improvisation
+    JamMinutes,
+    /// Jam minutes as a share of the show
+    JamShare,
+    /// Total minutes
+    Minutes,
+}
+
+impl From<ShowRank> for crate::shows::ShowMetric {
+    fn from(rank: ShowRank) -> Self {
+        match rank {
+            ShowRank::JamMinutes => Self::JamMinutes,
+            ShowRank::JamShare => Self::JamShare,
+            ShowRank::Minutes => Self::Minutes,
+        }
+    }
+}
+
+/// `db prune-artifacts --kind`: a stored raw series.
+#[derive(Clone, Copy, ValueEnum)]
+enum ArtifactKindArg {
+    Chromagram,
+    OnsetEnvelope,
+    Loudness,
+}
+
+impl From<ArtifactKindArg> for crate::artifacts::ArtifactKind {
+    fn from(kind: ArtifactKindArg) -> Self {
+        match kind {
+            ArtifactKindArg::Chromagram => Self::Chromagram,
+            ArtifactKindArg::OnsetEnvelope => Self::OnsetEnvelope,
+            ArtifactKindArg::Loudness => Self::Loudness,
+        }
+    }
+}
+
+/// `reparse --prefer`: which metadata source wins.
+#[derive(Clone, Copy, ValueEnum)]
+enum PreferSource {
+    Tags,
+    Filename,
+}
+
+impl From<PreferSource> for crate::config::MetadataPrecedence {
+    fn from(source: PreferSource) -> Self {
+        match source {
+            PreferSource::Tags => Self::Tags,
+            PreferSource::Filename => Self::Filename,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Scan directories for audio files and add them to the library
+    Scan {
+        /// Directories to scan (defaults to config file music_dirs)
+        paths: Vec<String>,
+
+        /// Force re-scan even if files haven't changed
+        #[arg(long)]
+        force: bool,
+
+        /// Report what would be added/updated/skipped (per band and format) without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Re-derive parsed band/date/venue/track/title from stored tags and paths
+    Reparse {
+        /// Collection roots whose .setbreak.toml applies (defaults to config music_dirs)
+        paths: Vec<String>,
+
+        /// Source that wins for every track, instead of each collection's `prefer`
+        #[arg(long, value_enum)]
+        prefer: Option<PreferSource>,
+
+        /// Count what would change without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Import titles, artists, and MusicBrainz ids from a beets library
+    ImportBeets {
+        /// Path to the beets library database (usually ~/.config/beets/library.db)
+        library: PathBuf,
+
+        /// Also write jam scores back as beets flexible attributes (setbreak_*)
+        #[arg(long)]
+        export_scores: bool,
+
+        /// Count what would change without writing to either database
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Analyze audio files (extract features and compute scores)
+    #[cfg(feature = "analysis")]
+    Analyze {
+        /// Number of parallel workers (0 = auto-detect from config)
+        #[arg(short = 'j', long, default_value = "0")]
+        jobs: usize,
+
+        /// Force re-analysis of already-analyzed tracks
+        #[arg(long)]
+        force: bool,
+
+        /// Only analyze tracks matching this pattern
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Decode workers prefetching audio ahead of analysis (0 = auto-detect from config)
+        #[arg(long, default_value = "0")]
+        decode_jobs: usize,
+
+        /// Memory budget for prefetched audio in MB (0 = from config, default 2048)
+        #[arg(long, value_name = "MB", default_value = "0")]
+        prefetch_mb: usize,
+
+        /// Analyze tracks meeting these conditions first, then longest first
+        /// (e.g. "band=gd,year>=1972,duration>600"); alone, just longest first
+        #[arg(long, value_name = "CONDITIONS", num_args = 0..=1, default_missing_value = "")]
+        priority: Option<String>,
+
+        /// Provisional scores from excerpts of each track (first 60s, middle
+        /// 120s, last 120s); a later plain `analyze` replaces them
+        #[arg(long, conflicts_with = "force")]
+        preview: bool,
+    },
+
+    /// Bring the library up to date in order: scan, analyze new and changed
+    /// files, rescore if the formulas changed, calibrate, similarity, chains.
+    /// Steps whose inputs haven't changed are skipped
+    #[cfg(feature = "analysis")]
+    Refresh {
+        /// Directories to scan (defaults to config file music_dirs)
+        paths: Vec<String>,
+
+        /// Number of parallel workers (0 = auto-detect from config)
+        #[arg(short = 'j', long, default_value = "0")]
+        jobs: usize,
+
+        /// Skip the scan; work from the tracks already in the database
+        #[arg(long, conflicts_with = "paths")]
+        no_scan: bool,
+
+        /// Also remove per-source-type offsets when calibrating (see
+        /// `calibrate --source-offsets`)
+        #[arg(long)]
+        source_offsets: bool,
+
+        /// List the steps that are due and why, without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Look up song titles from archive.org metadata
+    Setlist {
+        /// Dry run — show what would be updated without writing to DB
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Also fetch archive.org show notes for already-titled shows that have none
+        #[arg(long)]
+        notes: bool,
+
+        /// Queue matches below this confidence (0-1) for review instead of applying them
+        #[arg(long, value_name = "SHARE", value_parser = parse_share,
+              default_value_t = crate::setlist::DEFAULT_MIN_CONFIDENCE)]
+        min_confidence: f64,
+
+        /// List queued low-confidence matches instead of fetching
+        #[arg(long, conflicts_with_all = ["dry_run", "notes", "confirm", "reject"])]
+        review: bool,
+
+        /// Apply queued matches for these track IDs
+        #[arg(long, value_name = "ID", num_args = 1.., conflicts_with_all = ["dry_run", "notes"])]
+        confirm: Vec<i64>,
+
+        /// Discard queued matches for these track IDs
+        #[arg(long, value_name = "ID", num_args = 1.., conflicts_with_all = ["dry_run", "notes"])]
+        reject: Vec<i64>,
+
+        /// Check titled shows against their reference setlists (missing songs,
+        /// extra titles, wrong order, duplicates) instead of fetching
+        #[arg(long, conflicts_with_all = ["dry_run", "notes", "review", "confirm", "reject"])]
+        validate: bool,
+
+        /// With --validate, first fetch setlist.fm setlists for shows without a
+        /// reference (needs a `setlistfm` API key, see `setbreak auth`)
+        #[arg(long, requires = "validate")]
+        setlistfm: bool,
+
+        /// With --validate, check only this show date (YYYY-MM-DD)
+        #[arg(long, requires = "validate", value_parser = parse_show_date)]
+        date: Option<String>,
+    },
+
+    /// Recompute jam scores from stored features (no audio re-analysis)
+    Rescore {
+        /// Compute experimental scores from a weights file into a temporary
+        /// table instead; stored scores are left untouched
+        #[arg(long, value_name = "FILE")]
+        experiment: Option<PathBuf>,
+
+        /// Compare the experimental scores with the current ones
+        /// (rank correlation and biggest movers)
+        #[arg(long, requires = "experiment")]
+        compare: bool,
+
+        /// After rescoring, list the tracks whose stored scores moved most
+        #[arg(long, conflicts_with = "experiment")]
+        report: bool,
+
+        /// Number of biggest movers to show per score
+        #[arg(long, default_value = "10")]
+        movers: usize,
+    },
+
+    /// Re-run decode → features → scores for one track with per-stage timing,
+    /// and diff the result against the stored analysis (nothing is written)
+    #[cfg(feature = "analysis")]
+    Trace {
+        /// Track id, or its file path (a unique trailing part is enough)
+        track: String,
+
+        /// Feature changes to list (score changes are always listed)
+        #[arg(long, default_value = "20")]
+        features: usize,
+    },
+
+    /// Adjust scores to remove recording quality bias (LUFS regression)
+    Calibrate {
+        /// Show what would change without writing to DB
+        #[arg(long)]
+        dry_run: bool,
+
+        /// List the tracks whose scores moved most (with --dry-run, what
+        /// would move)
+        #[arg(long)]
+        report: bool,
+
+        /// Number of biggest movers to show per score
+        #[arg(long, default_value = "10")]
+        movers: usize,
+
+        /// Also learn how each source type (SBD, AUD, matrix, FM) scores against
+        /// other recordings of the same performance, and correct for it
+        #[arg(long)]
+        source_offsets: bool,
+    },
+
+    /// Show top tracks ranked by a jam score
+    Top {
+        /// Which score to rank by
+        #[arg(value_enum, default_value = "groove")]
+        score: ScoreName,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Filter by song title (substring match)
+        #[arg(short, long)]
+        song: Option<String>,
+
+        /// Minimum duration in minutes
+        #[arg(long)]
+        min_duration: Option<f64>,
+
+        /// Include studio and non-live recordings (default: live only)
+        #[arg(long)]
+        all_types: bool,
+
+        /// Include tracks with a detected tape flip or splice
+        #[arg(long)]
+        include_cuts: bool,
+
+        /// Include tracks with no usable title (default: titled only)
+        #[arg(long)]
+        include_untitled: bool,
+
+        /// Score split jams ("Dark Star (1)" + "(2)") as one track
+        #[arg(long)]
+        merge_parts: bool,
+
+        /// List each song once per performance, from its best-scoring recording
+        #[arg(long, conflicts_with = "merge_parts")]
+        per_performance: bool,
+
+        /// Skip shows with fewer clean (non-suspect) tracks than this share (0-1)
+        #[arg(long, value_name = "SHARE", value_parser = parse_share)]
+        min_show_quality: Option<f64>,
+
+        /// Only shows a guest whose name contains this sat in on (see `lineup`)
+        #[arg(long, value_name = "NAME")]
+        with_guest: Option<String>,
+
+        /// Rank by a built-in score or a recipe from config.toml [recipes]
+        #[arg(long = "score", value_name = "NAME", conflicts_with = "score")]
+        score_name: Option<String>,
+
+        /// Skip this many ranked tracks (numbers the rows)
+        #[arg(long, default_value = "0", conflicts_with = "page")]
+        offset: usize,
+
+        /// Show this page of -n tracks, 1 being the first (numbers the rows)
+        #[arg(long, value_parser = parse_page)]
+        page: Option<usize>,
+
+        /// Page through the ranking and print everything stored about a row
+        /// (file, source, all features) by entering its number
+        #[arg(short, long)]
+        interactive: bool,
+    },
+
+    /// Compare versions of a song across shows
+    Compare {
+        /// Song title to search for (substring match)
+        song: String,
+
+        /// Sort by this score (or "duration")
+        #[arg(short, long, default_value = "improvisation")]
+        sort: ScoreName,
+
+        /// Number of results (per band unless --all-bands)
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Include studio and non-live recordings (default: live only)
+        #[arg(long)]
+        all_types: bool,
+
+        /// Only consider this band (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Rank all bands' versions together instead of grouping by band
+        #[arg(long)]
+        all_bands: bool,
+    },
+
+    /// Filter tracks with an expression (e.g. "groove>70 and duration>10min and band=gd")
+    Query {
+        /// Filter expression: comparisons on scores, duration, band, date, song, etc.
+        /// joined with and/or/not
+        #[arg(required_unless_present_any = ["run", "list", "delete"])]
+        expr: Option<String>,
+
+        /// Save the expression (and sort/type options) under this name
+        #[arg(long, value_name = "NAME")]
+        save: Option<String>,
+
+        /// Re-run a saved search by name
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["expr", "save"])]
+        run: Option<String>,
+
+        /// List saved searches
+        #[arg(long, conflicts_with_all = ["expr", "run", "delete"])]
+        list: bool,
+
+        /// Delete a saved search
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["expr", "run"])]
+        delete: Option<String>,
+
+        /// Export the results as an M3U playlist
+        #[arg(long, value_name = "PATH")]
+        export: Option<PathBuf>,
+
+        /// Sort by this score (default: chronological)
+        #[arg(short, long, value_enum)]
+        sort: Option<ScoreName>,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Include studio and non-live recordings (default: live only)
+        #[arg(long)]
+        all_types: bool,
+    },
+
+    /// View a show's setlist with scores
+    Show {
+        /// Show date (YYYY-MM-DD)
+        date: String,
+
+        /// Also print archive.org source notes (taper, lineage, description)
+        #[arg(long)]
+        notes: bool,
+
+        /// Draw an ASCII timeline shaded by a score (default: energy)
+        #[arg(long, value_name = "SCORE", num_args = 0..=1, default_missing_value = "energy")]
+        viz: Option<String>,
+
+        /// Only this source when the date has several recording directories
+        /// (numbered as listed, most complete first)
+        #[arg(long, value_name = "N")]
+        source: Option<usize>,
+    },
+
+    /// Rank shows by jam minutes (minutes of high-improvisation music) or length
+    Shows {
+        /// What to rank by
+        #[arg(long, value_enum, default_value = "jam-minutes")]
+        top: ShowRank,
+
+        /// Only this band (code or name)
+        #[arg(long)]
+        band: Option<String>,
+
+        /// Number of shows to list
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Rank studio albums by a score (the album view for libraries without
+    /// show dates), or rank the tracks within matching albums
+    Albums {
+        /// Which score to rank by
+        #[arg(value_enum, default_value = "groove")]
+        score: ScoreName,
+
+        /// Only this band (code or name)
+        #[arg(long)]
+        band: Option<String>,
+
+        /// List the tracks of albums whose title contains this, best first
+        #[arg(long)]
+        album: Option<String>,
+
+        /// Number of albums to list
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Performances with more than one recording (SBD, AUD, matrix), compared
+    Performances {
+        #[command(subcommand)]
+        action: PerformanceAction,
+    },
+
+    /// Show lineups and guest sit-ins, from archive.org notes or added by hand
+    Lineup {
+        #[command(subcommand)]
+        action: LineupAction,
+    },
+
+    /// Cross-check show dates against how the recordings sound, and suggest
+    /// years for undated recordings
+    DateCheck {
+        /// Only this band (code or name)
+        #[arg(long)]
+        band: Option<String>,
+
+        /// Years outside the estimated range before a date is flagged
+        #[arg(long, default_value = "3")]
+        tolerance: i32,
+
+        /// Number of rows to list in each table
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// List split jams (Part 1 / Part 2 files) and their combined scores
+    Groups {
+        /// Filter by song title (substring match)
+        #[arg(short, long)]
+        song: Option<String>,
+
+        /// Filter to a show date (YYYY-MM-DD)
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// Re-detect groups even if nothing changed
+        #[arg(long)]
+        rebuild: bool,
+    },
+
+    /// Show a track's structure over time, with tape flip / splice markers
+    Timeline {
+        /// Song title to search for (substring match)
+        song: String,
+
+        /// Show date to narrow the search (YYYY-MM-DD)
+        #[arg(short, long)]
+        date: Option<String>,
+    },
+
+    /// Cut a show's most intense moments into short excerpts (needs ffmpeg)
+    Highlights {
+        /// Show date (YYYY-MM-DD)
+        date: String,
+
+        /// Directory to write the excerpts to
+        #[arg(short, long, default_value = "highlights")]
+        out: PathBuf,
+
+        /// Number of excerpts
+        #[arg(short = 'n', long, default_value = "5")]
+        count: usize,
+
+        /// Minimum excerpt length in seconds
+        #[arg(long, default_value = "60")]
+        min_secs: f64,
+
+        /// Maximum excerpt length in seconds
+        #[arg(long, default_value = "90")]
+        max_secs: f64,
+
+        /// Output file extension (ffmpeg picks the codec from it)
+        #[arg(long, default_value = "flac")]
+        format: String,
+
+        /// List the chosen excerpts without cutting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Cut a short clip of a track around its peak, to share a moment (needs ffmpeg)
+    Snippet {
+        /// The track, as SONG@DATE (the date is optional)
+        #[arg(value_name = "SONG@DATE")]
+        seed: String,
+
+        /// Moment to lead into: peak (loudest stretch), tension (tension peak),
+        /// or a time (12:34 or seconds)
+        #[arg(long, default_value = "peak", value_parser = parse_snippet_anchor)]
+        at: crate::highlights::SnippetAnchor,
+
+        /// Clip length in seconds
+        #[arg(long, default_value = "45")]
+        secs: f64,
+
+        /// Output file; ffmpeg picks the codec from its extension
+        /// [default: "SONG DATE (START).ogg"]
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+
+        /// Band of the track (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Print the chosen window without cutting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compute track-to-track similarity from audio features
+    Similarity {
+        /// Number of parallel workers (0 = auto-detect from config)
+        #[arg(short = 'j', long, default_value = "0")]
+        jobs: usize,
+
+        /// Only refresh neighborhoods touched by tracks re-analyzed or added
+        /// since the last run
+        #[arg(long)]
+        changed_only: bool,
+    },
+
+    /// Find tracks that sound similar to a given track
+    Similar {
+        /// Song title to search for (substring match)
+        #[arg(required_unless_present = "seeds")]
+        song: Option<String>,
+
+        /// Seed track as SONG@DATE; repeat to find neighbors of the seeds' centroid
+        #[arg(long = "seed", value_name = "SONG@DATE")]
+        seeds: Vec<String>,
+
+        /// Push down tracks that sound like this song (every performance, or one
+        /// with @DATE); repeatable
+        #[arg(long = "not-like", value_name = "SONG[@DATE]")]
+        not_like: Vec<String>,
+
+        /// Show date to narrow the search (YYYY-MM-DD)
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "15")]
+        limit: usize,
+
+        /// Band of the reference track (gd, phish, bts, etc.), and of the results
+        /// unless --all-bands; a seed found only under another band still works
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Include similar tracks by other bands
+        #[arg(long)]
+        all_bands: bool,
+
+        /// Skip tracks from the seeds' own shows
+        #[arg(long)]
+        exclude_same_show: bool,
+
+        /// Skip other performances of the seeds' songs
+        #[arg(long)]
+        exclude_same_song: bool,
+
+        /// Only results from shows in these years (1972-1974, or one year)
+        #[arg(long, value_name = "FROM-TO", value_parser = parse_year_range)]
+        year_range: Option<(i32, i32)>,
+
+        /// Break each distance down by feature group (timbre, rhythm, balance, ...)
+        #[arg(long)]
+        explain: bool,
+    },
+
+    /// Build a best-of compilation: top tracks packed into disc-sized playlists
+    Compile {
+        /// Compilation title (used for the tracklist and output file names)
+        title: String,
+
+        /// Restrict candidates with a filter expression (see `setbreak query`)
+        #[arg(short = 'w', long = "where", value_name = "EXPR")]
+        filter: Option<String>,
+
+        /// Score to rank candidates by
+        #[arg(long, value_enum, default_value = "transcendence")]
+        top: ScoreName,
+
+        /// Disc capacity (e.g. 80min, 74m, 4800s)
+        #[arg(long, value_name = "LENGTH", default_value = "80min", value_parser = parse_disc_length)]
+        tracks_per_disc: f64,
+
+        /// Maximum number of discs
+        #[arg(long, default_value = "1")]
+        discs: usize,
+
+        /// Maximum tracks from any one show
+        #[arg(long, default_value = "2")]
+        per_show: usize,
+
+        /// Allow the same song more than once
+        #[arg(long)]
+        allow_repeats: bool,
+
+        /// Write one M3U per disc plus a tracklist into this directory
+        #[arg(long, value_name = "DIR")]
+        out: Option<PathBuf>,
+
+        /// Include studio and non-live recordings (default: live only)
+        #[arg(long)]
+        all_types: bool,
+    },
+
+    /// Import scrobbles (Last.fm / ListenBrainz) and compare listening with the scores
+    Plays {
+        #[command(subcommand)]
+        action: PlaysAction,
+    },
+
+    /// Re-listen queue: tracks to go back to (new standouts and outliers are
+    /// queued after `analyze`)
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+
+    /// Fix track titles by hand through a CSV round-trip
+    Titles {
+        #[command(subcommand)]
+        action: TitlesAction,
+    },
+
+    /// Run external feature extractors from `[[external_analyzers]]` in the config
+    External {
+        #[command(subcommand)]
+        action: ExternalAction,
+    },
+
+    /// Serve a read-only HTTP API with scores and similar tracks by file path
+    /// (for player plugins)
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7390")]
+        bind: String,
+
+        /// Serve only a sanitized subset (bands, dates, titles, scores; no file
+        /// paths) for the public internet, behind the `serve` credential token
+        #[arg(long)]
+        public: bool,
+
+        /// With --public, serve without a token
+        #[arg(long, requires = "public")]
+        no_auth: bool,
+
+        /// With --public, requests allowed per client address per minute
+        #[arg(long, default_value_t = crate::api::DEFAULT_RATE_LIMIT, requires = "public")]
+        rate_limit: u32,
+    },
+
+    /// Build reference "sound profiles" from shows and rank the library against them
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+
+    /// Find and rank segue chains (multi-song jam suites connected by ->)
+    Chains {
+        /// Sort by this score
+        #[arg(short, long, value_enum, default_value = "transcendence")]
+        sort: ScoreName,
+
+        /// Filter to a specific show date (YYYY-MM-DD)
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// Minimum chain length (number of songs)
+        #[arg(long, default_value = "2")]
+        min_length: usize,
+
+        /// Minimum total chain duration in minutes
+        #[arg(long)]
+        min_duration: Option<f64>,
+
+        /// Filter chains containing this song (substring match)
+        #[arg(long)]
+        song: Option<String>,
+
+        /// Filter by band (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Show individual track scores within each chain
+        #[arg(long)]
+        detail: bool,
+
+        /// Re-detect all chains instead of reading the persisted chains table
+        #[arg(long)]
+        rebuild: bool,
+
+        /// Skip shows with fewer clean (non-suspect) tracks than this share (0-1)
+        #[arg(long, value_name = "SHARE", value_parser = parse_share)]
+        min_show_quality: Option<f64>,
+
+        /// Write each listed chain as an M3U (segue order) into this directory
+        #[arg(long, value_name = "DIR")]
+        export_m3u: Option<PathBuf>,
+
+        /// List every chain this song appears in, with its position and role
+        #[arg(long, value_name = "SONG")]
+        containing: Option<String>,
+
+        /// With --containing: show the songs segued from and into
+        #[arg(long, requires = "containing")]
+        show_context: bool,
+    },
+
+    /// Discover missing shows from archive.org collections
+    Discover {
+        /// Band code (gd, phish, bts)
+        #[arg(long, default_value = "gd")]
+        band: String,
+
+        /// Force refresh of cached archive.org data
+        #[arg(long)]
+        refresh: bool,
+
+        /// Filter by year or year range (e.g., "1977" or "1977-1980")
+        #[arg(long)]
+        year: Option<String>,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "50")]
+        limit: usize,
+
+        /// Search pages fetched at once (default: [archive] concurrency)
+        #[arg(long)]
+        concurrency: Option<usize>,
+    },
+
+    /// Fetch archive.org review ratings for shows matched by `setlist --notes`
+    Ratings {
+        /// Look up every matched show again, not just unrated or stale ones
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Download a show from archive.org (picks best non-SBD source for restricted bands)
+    Download {
+        /// Band code (gd, phish, bts)
+        #[arg(long)]
+        band: String,
+
+        /// Show date (YYYY-MM-DD)
+        date: String,
+
+        /// Destination directory (default: first music_dir from config)
+        #[arg(long)]
+        dest: Option<String>,
+
+        /// Dry run — show what would be downloaded without actually downloading
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Open a show's archive.org page in the browser
+    Open {
+        /// Show date (YYYY-MM-DD, or anything with a date in it like gd77-05-08)
+        date: String,
+
+        /// Band code or name, when several bands played that date
+        #[arg(long)]
+        band: Option<String>,
+
+        /// Open the file listing instead of the details page
+        #[arg(long)]
+        download_page: bool,
+
+        /// Print the URL instead of opening it
+        #[arg(long)]
+        print: bool,
+    },
+
+    /// Import canonical setlists from gdshowsdb YAML files (Grateful Dead)
+    ImportSetlists {
+        /// Path to gdshowsdb data directory (contains year YAML files)
+        path: String,
+
+        /// Source identifier (default: gdshowsdb)
+        #[arg(long, default_value = "gdshowsdb")]
+        source: String,
+
+        /// Dry run — parse and validate without writing to DB
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Fetch Phish setlists from phish.in API (incremental, caches in DB)
+    FetchPhishin {
+        /// Dry run — fetch and count but don't write to DB
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Classify tracks as live, studio, or live_album and parse source lineage
+    /// (sbd/aud/matrix, taper) from directory names (backfill existing tracks)
+    Classify,
+
+    /// Flag tracks with bad audio quality (DTS bitstreams, corrupt files)
+    QualityCheck,
+
+    /// Extract boundary features and segue edges from audio (lightweight decode
+    /// for segue detection and `segue-suggest`)
+    #[cfg(feature = "analysis")]
+    ExtractBoundaries {
+        /// Number of parallel workers (0 = auto-detect from config)
+        #[arg(short = 'j', long, default_value = "0")]
+        jobs: usize,
+    },
+
+    /// Find song boundaries inside long unsplit recordings (one file per set)
+    /// and store the songs as virtual tracks
+    #[cfg(feature = "analysis")]
+    SplitSongs {
+        /// Track ids to split (default: every unsplit recording of --min-minutes or more)
+        track_ids: Vec<i64>,
+
+        /// Minimum duration in minutes for a recording to be picked automatically
+        #[arg(long, alias = "min-minutes", default_value = "40")]
+        min_duration: f64,
+
+        /// Shortest song allowed between two splits, in seconds
+        #[arg(long, default_value = "120")]
+        min_song_secs: f64,
+
+        /// Minimum boundary score (0.0-1.0); lower proposes more splits
+        #[arg(long, default_value = "0.6")]
+        threshold: f64,
+
+        /// Write a CUE sheet per recording into this directory
+        #[arg(long)]
+        cue: Option<PathBuf>,
+
+        /// Re-detect recordings that were already split
+        #[arg(long)]
+        force: bool,
+
+        /// Show proposed splits without storing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Suggest split and merge fixes for a badly tracked show (banter bundled
+    /// with a song, a song split mid-jam) and store the corrected boundaries
+    #[cfg(feature = "analysis")]
+    Retrack {
+        /// Show date (YYYY-MM-DD)
+        #[arg(value_parser = parse_show_date)]
+        date: String,
+
+        /// Only recordings whose directory name contains this
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Shortest piece a split may leave, in seconds
+        #[arg(long, default_value = "30")]
+        min_segment_secs: f64,
+
+        /// Minimum boundary score (0.0-1.0) to split inside a track
+        #[arg(long, default_value = "0.6")]
+        threshold: f64,
+
+        /// Merge track changes scoring below this (0.0-1.0) with no pause or applause
+        #[arg(long, default_value = "0.15")]
+        merge_below: f64,
+
+        /// Cut the corrected tracks into this directory with ffmpeg
+        #[arg(long, value_name = "DIR", conflicts_with = "dry_run")]
+        apply: Option<PathBuf>,
+
+        /// Re-detect recordings that already have stored boundaries
+        #[arg(long)]
+        force: bool,
+
+        /// Show suggestions without storing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Detect segues between consecutive tracks from audio boundary analysis
+    Segues {
+        /// Minimum segue confidence (0.0-1.0)
+        #[arg(long, default_value = "0.5")]
+        min_confidence: f64,
+
+        /// Filter by band (gd, phish, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Filter to a specific show date (YYYY-MM-DD)
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "50")]
+        limit: usize,
+
+        /// Show all tracks in each show (not just detected segues)
+        #[arg(long)]
+        detail: bool,
+    },
+
+    /// Suggest songs to segue into from a track, matching its last minute
+    /// against other tracks' first minute (key, tempo, level, brightness)
+    SegueSuggest {
+        /// The track, as SONG@DATE (the date is optional)
+        #[arg(value_name = "SONG@DATE")]
+        seed: String,
+
+        /// Suggest songs that lead into the track instead of following it
+        #[arg(long)]
+        before: bool,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "15")]
+        limit: usize,
+
+        /// Band of the track (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Suggest songs by any band, not just the track's
+        #[arg(long)]
+        all_bands: bool,
+
+        /// List every performance of a song, not just its best fit
+        #[arg(long)]
+        all_versions: bool,
+    },
+
+    /// Run a read-only SQL query against the database (see `--views` for the
+    /// stable views to query)
+    Sql {
+        /// SQL query to execute
+        #[arg(required_unless_present = "views")]
+        query: Option<String>,
+
+        /// Output the rows as a JSON array of objects
+        #[arg(long)]
+        json: bool,
+
+        /// Allow statements that modify the database
+        #[arg(long)]
+        write: bool,
+
+        /// List the stable `v_*` views and their columns
+        #[arg(long, conflicts_with = "query")]
+        views: bool,
+    },
+
+    /// Show percentile ranks for a specific track across all scores
+    Rank {
+        /// Song title (substring match)
+        song: String,
+
+        /// Show date to narrow the search (YYYY-MM-DD)
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// Band of the track to rank (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Rank against the whole library instead of the track's band
+        #[arg(long)]
+        all_bands: bool,
+    },
+
+    /// Show score distribution as a histogram
+    Dist {
+        /// Which score to show distribution for
+        #[arg(value_enum)]
+        score: ScoreName,
+
+        /// Number of histogram bins
+        #[arg(long, default_value = "20")]
+        bins: usize,
+
+        /// Highlight a specific song in the distribution
+        #[arg(long)]
+        song: Option<String>,
+
+        /// Only tracks by this band (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Filter to only live recordings
+        #[arg(long)]
+        live_only: bool,
+
+        /// Minimum duration in minutes
+        #[arg(long)]
+        min_duration: Option<f64>,
+    },
+
+    /// Find features most correlated with a score (or between two scores)
+    Correlate {
+        /// Target score to find predictors for
+        #[arg(value_enum)]
+        score: ScoreName,
+
+        /// Only analyze tracks by this band (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Only analyze live recordings
+        #[arg(long)]
+        live_only: bool,
+
+        /// Minimum duration in minutes (useful for jam-focused analysis)
+        #[arg(long)]
+        min_duration: Option<f64>,
+
+        /// Number of top features to show
+        #[arg(short = 'n', long, default_value = "25")]
+        limit: usize,
+    },
+
+    /// Flag degenerate analysis features (constant, spiked, pinned, mostly NULL)
+    AuditFeatures {
+        /// Only live recordings
+        #[arg(long)]
+        live_only: bool,
+
+        /// Only columns in this category (substring match)
+        #[arg(short, long)]
+        category: Option<String>,
+
+        /// Show every column, not just the flagged ones
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Show correlation matrix between all jam scores
+    ScoreMatrix {
+        /// Only live recordings
+        #[arg(long)]
+        live_only: bool,
+
+        /// Minimum duration in minutes
+        #[arg(long)]
+        min_duration: Option<f64>,
+    },
+
+    /// Explain the jam scores and how they're distributed in this library
+    Scores {
+        #[command(subcommand)]
+        action: ScoresAction,
+    },
+
+    /// Show library statistics
+    Stats {
+        /// Show library growth over time (one snapshot per scan/analyze run)
+        #[arg(long)]
+        history: bool,
+
+        /// Number of most recent snapshots to show with --history, or slowest
+        /// files with --performance
+        #[arg(short = 'n', long, default_value = "30")]
+        limit: usize,
+
+        /// Show a BPM histogram, raw vs half/double-time corrected
+        #[arg(long, conflicts_with = "history")]
+        tempo: bool,
+
+        /// Show analysis compute time: totals and throughput per version, the
+        /// slowest files, and an estimate for what's left to analyze
+        #[arg(long, conflicts_with_all = ["history", "tempo"])]
+        performance: bool,
+    },
+
+    /// Show analysis_results column inventory (names, types, categories)
+    Schema {
+        /// Filter columns by substring match (case-insensitive)
+        #[arg(long)]
+        grep: Option<String>,
+
+        /// Show only a specific category
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Show only jam score columns
+        #[arg(long)]
+        scores: bool,
+
+        /// Output as JSON (for tooling integration)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Write an anonymized feature + score dataset (CSV + manifest) for sharing
+    ExportResearch {
+        /// Directory to write features.csv and manifest.json into
+        #[arg(value_name = "DIR")]
+        output: PathBuf,
+
+        /// Secret for the identifier hashes; reuse it to keep ids stable across
+        /// exports (default: random, so ids match nothing else)
+        #[arg(long)]
+        salt: Option<String>,
+
+        /// Include song titles (song_id alone still groups versions)
+        #[arg(long)]
+        titles: bool,
+
+        /// Only live recordings
+        #[arg(long)]
+        live_only: bool,
+    },
+
+    /// Test score formulas interactively without recompiling
+    ScoreLab {
+        /// Expression to evaluate (uses feature column names as variables).
+        /// Example: "rms_level / 0.18 * 30 + (lufs_integrated + 55) / 22 * 30"
+        #[arg(required_unless_present = "list")]
+        formula: Option<String>,
+
+        /// List all available variable names
+        #[arg(long)]
+        list: bool,
+
+        /// Number of top results to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Minimum duration in minutes
+        #[arg(long)]
+        min_duration: Option<f64>,
+
+        /// Only evaluate live recordings (default: all types)
+        #[arg(long)]
+        live_only: bool,
+
+        /// Show bottom results instead of top (ascending sort)
+        #[arg(long)]
+        bottom: bool,
+    },
+
+    /// Find tracks with similar harmonic content (chroma-based, transposition-aware)
+    HarmonicMatch {
+        /// Song title to search for (substring match)
+        song: String,
+
+        /// Show date to narrow the search (YYYY-MM-DD)
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// Band of the reference track (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Include matches by other bands
+        #[arg(long)]
+        all_bands: bool,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Require same key (disable transposition matching)
+        #[arg(long)]
+        same_key: bool,
+
+        /// Only tracks within this share (0-1) of the target's corrected tempo, e.g. 0.08
+        #[arg(long, value_name = "SHARE", value_parser = parse_share)]
+        tempo_tolerance: Option<f64>,
+    },
+
+    /// Manage API keys for external services (setlist.fm, MusicBrainz, webhooks)
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+
+    /// Suggest files to delete to free space (garbage, lesser sources of a
+    /// performance, bottom-tenth studio tracks), least value per GB first, as
+    /// a shell script to review; nothing is deleted
+    PruneSuggest {
+        /// Space to free, e.g. 500GB, 1.5TB, 800MiB (default: every candidate)
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        target_free: Option<u64>,
+
+        /// Only this band's tracks (gd, phish, bts, etc.)
+        #[arg(short, long)]
+        band: Option<String>,
+
+        /// Write the script to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Inspect configuration (global config.toml plus per-collection .setbreak.toml)
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlaysAction {
+    /// Import a Last.fm CSV or ListenBrainz JSON export (replaces that source's counts)
+    Import {
+        /// Export file
+        file: PathBuf,
+    },
+
+    /// Your most played tracks next to the algorithm's favorites
+    Top {
+        /// Score the algorithm ranks by
+        #[arg(long, value_enum, default_value = "transcendence")]
+        score: ScoreName,
+
+        /// Number of tracks in each list
+        #[arg(short = 'n', long, default_value = "15")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueueAction {
+    /// Queue tracks by id or path (a unique path suffix is enough)
+    Add {
+        #[arg(required = true)]
+        tracks: Vec<String>,
+
+        /// Why you want to hear it again
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// List the queue, oldest first, with how long each track has waited
+    List {
+        /// Also write the queue as an M3U playlist
+        #[arg(long, value_name = "FILE")]
+        export_m3u: Option<PathBuf>,
+    },
+
+    /// Take the oldest tracks off the queue and print their paths (for a player)
+    Pop {
+        /// How many to take
+        #[arg(short = 'n', long, default_value = "1")]
+        count: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum TitlesAction {
+    /// Write tracks as CSV (id, file_path, title) for editing in a spreadsheet
+    Export {
+        /// Only tracks without a usable title (the ones setlist lookup couldn't fix)
+        #[arg(long)]
+        missing: bool,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Apply edited titles from a CSV (all rows or none; blank titles are skipped)
+    Import {
+        /// CSV with id and title columns (file_path, if present, is checked)
+        file: PathBuf,
+
+        /// Check the file and report what would change without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Find near-identical titles and merge them into one spelling (interactive)
+    Dedupe {
+        /// Most character edits between titles that still count as the same song
+        #[arg(long, default_value = "2")]
+        max_edits: usize,
+
+        /// Accept every suggested merge without asking
+        #[arg(long, conflicts_with = "dry_run")]
+        yes: bool,
+
+        /// List the clusters without asking or writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// List stored title aliases (misspelling -> canonical title)
+    Aliases,
+
+    /// Clean up stored titles: decode HTML entities, straighten quotes, compose
+    /// accents, collapse whitespace (new titles are cleaned as they're written)
+    Normalize {
+        /// Count what would change without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExternalAction {
+    /// Run analyzers on tracks they haven't processed yet
+    Run {
+        /// Analyzer names (default: all configured)
+        names: Vec<String>,
+
+        /// Processes to run at once (0 = auto-detect from config)
+        #[arg(short = 'j', long, default_value = "0")]
+        jobs: usize,
+
+        /// Re-run on every track, including ones already processed
+        #[arg(long)]
+        force: bool,
+
+        /// Retry tracks where the analyzer failed before
+        #[arg(long)]
+        retry_failed: bool,
+
+        /// Process at most this many tracks per analyzer
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+
+    /// Show configured analyzers, their columns, and how many tracks they've run on
+    List,
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// Build a profile from the feature centroid of one or more shows
+    Create {
+        /// Show dates to build the profile from (YYYY-MM-DD; partial dates like 1974 allowed)
+        dates: Vec<String>,
+
+        /// Profile name
+        #[arg(long)]
+        name: String,
+
+        /// Select (or narrow) source tracks with a query expression instead
+        #[arg(short, long, required_unless_present = "dates")]
+        query: Option<String>,
+    },
+
+    /// Rank the library by similarity to a profile
+    Match {
+        /// Profile name
+        name: String,
+
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Include the tracks the profile was built from
+        #[arg(long)]
+        include_source: bool,
+
+        /// Include studio and non-live recordings (default: live only)
+        #[arg(long)]
+        all_types: bool,
+    },
+
+    /// List saved profiles
+    List,
+
+    /// Delete a profile
+    Delete {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthAction {
+    /// Store a secret for a service (prompts for it, or reads stdin when piped)
+    Set {
+        /// Service name (e.g. setlistfm, musicbrainz)
+        service: String,
+
+        /// Store in the OS keyring instead of credentials.toml
+        #[arg(long)]
+        keyring: bool,
+    },
+
+    /// List services with stored secrets (values are not shown)
+    List,
+
+    /// Remove a service's secret
+    Remove {
+        /// Service name
+        service: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum PerformanceAction {
+    /// Performances with more than one recording, each source's mean scores
+    /// on the songs they share
+    List {
+        /// Only this band (code or name)
+        #[arg(long)]
+        band: Option<String>,
+
+        /// Only this date (YYYY-MM-DD)
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Number of performances to list
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// File a recording directory under another date's performance (a late set
+    /// saved under the next day, a mislabeled folder)
+    Link {
+        /// Recording directory, as scanned
+        dir: String,
+
+        /// Date of the performance it belongs to (YYYY-MM-DD)
+        #[arg(value_parser = parse_show_date)]
+        date: String,
+    },
+
+    /// Drop a manual link; the recording goes back to its own date
+    Unlink {
+        /// Recording directory, as scanned
+        dir: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum LineupAction {
+    /// A show's lineup, or every show a guest sat in on
+    List {
+        /// Show date (YYYY-MM-DD)
+        #[arg(value_parser = parse_show_date)]
+        date: Option<String>,
+
+        /// Only guests whose name contains this
+        #[arg(long, value_name = "NAME")]
+        guest: Option<String>,
+
+        /// Only this band (code or name)
+        #[arg(long)]
+        band: Option<String>,
+    },
+
+    /// Add a musician to a show's lineup, as a guest unless --member
+    Add {
+        /// Show date (YYYY-MM-DD)
+        #[arg(value_parser = parse_show_date)]
+        date: String,
+
+        /// Musician's name
+        name: String,
+
+        /// Instrument or part (sax, vocals)
+        #[arg(long)]
+        role: Option<String>,
+
+        /// Band of the show, when more than one played that date (code or name)
+        #[arg(long)]
+        band: Option<String>,
+
+        /// A regular member rather than a guest
+        #[arg(long)]
+        member: bool,
+    },
+
+    /// Remove a musician from a show's lineup
+    Remove {
+        /// Show date (YYYY-MM-DD)
+        #[arg(value_parser = parse_show_date)]
+        date: String,
+
+        /// Musician's name
+        name: String,
+
+        /// Band of the show (code or name)
+        #[arg(long)]
+        band: Option<String>,
+    },
+
+    /// Find guests in the stored archive.org notes again (`setlist --notes`
+    /// does this after fetching)
+    Import,
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Delete detail rows (segments, tension, chords, similarity, ...) for garbage-flagged
+    /// tracks and tracks whose files no longer exist, then VACUUM
+    PruneDetails {
+        /// Keep deleted-file tracks and their scores; only drop their detail rows
+        #[arg(long)]
+        keep_aggregates: bool,
+
+        /// Report what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show what the raw artifact sidecar holds (see `[analysis] keep_artifacts`)
+    Artifacts,
+
+    /// Drop raw artifacts of deleted tracks, then of one kind or the oldest
+    /// tracks until under a size budget, then VACUUM the sidecar
+    PruneArtifacts {
+        /// Keep at most this many MB of artifacts (default: `artifact_budget_mb`)
+        #[arg(long, value_name = "MB")]
+        budget_mb: Option<u64>,
+
+        /// Drop every artifact of this kind
+        #[arg(long, value_enum)]
+        kind: Option<ArtifactKindArg>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScoresAction {
+    /// Definition, inputs, live min/median/max, and caveats for each score
+    Describe {
+        /// Only this score (default: all)
+        #[arg(value_enum)]
+        score: Option<ScoreName>,
+
+        /// Distribution for one band only (code or name), with any configured adjustment
+        #[arg(long)]
+        band: Option<String>,
+
+        /// Output as JSON (for tooling integration)
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the global config file, or with --effective the merged settings and their sources
+    Show {
+        /// Show merged settings from every layer, with where each value comes from
+        #[arg(long)]
+        effective: bool,
+
+        /// Collection roots to resolve (default: music_dirs from config)
+        dirs: Vec<PathBuf>,
+    },
+}
+
+/// Parse the command line and run the command. `bin_name` is the name
+/// `--help` and `--version` show.
+pub fn run(bin_name: &'static str) -> Result<()> {
+    let matches = Cli::command().name(bin_name).get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    if cli.quiet {
+        crate::progress::hide_notes();
+    }
+    if cli.quiet || cli.no_progress {
+        crate::progress::hide_bars();
+    }
+
+    // Set up logging based on verbosity
+    let log_level = match cli.verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level))
+        .format_timestamp(None)
+        .init();
+
+    // Load config file (optional, defaults if missing)
+    let config = crate::config::AppConfig::load();
+    config
+        .analysis
+        .validate()
+        .map_err(|e| anyhow::anyhow!("Invalid [analysis] config: {e}"))?;
+    crate::external::validate(&config.external_analyzers)
+        .map_err(|e| anyhow::anyhow!("Invalid [[external_analyzers]] config: {e}"))?;
+    crate::hooks::validate(&config.hooks)
+        .map_err(|e| anyhow::anyhow!("Invalid [[hooks]] config: {e}"))?;
+
+    // Initialize global band registry (must happen before any band lookups)
+    crate::bands::init(&config.custom_bands);
+    let band_adjustments =
+        crate::analyzer::jam_metrics::BandAdjustments::from_config(&config.band_scoring)
+            .map_err(|e| anyhow::anyhow!("Invalid [band_scoring] config: {e}"))?;
+    crate::analyzer::jam_metrics::init_band_adjustments(band_adjustments);
+
+    // Resolve database path: CLI > config > XDG default
+    let db_origin = if cli.db_path.is_some() {
+        "--db-path"
+    } else if config.db_path.is_some() {
+        "config.toml"
+    } else {
+        "default"
+    };
+    let db_path = cli
+        .db_path
+        .or(config.db_path.clone())
+        .unwrap_or_else(crate::config::default_db_path);
+    log::info!("Database: {}", db_path.display());
+
+    // Only `analyze` writes through a `&mut` connection
+    #[cfg_attr(not(feature = "analysis"), allow(unused_mut))]
+    let mut db = crate::db::Database::open(&db_path).context("Failed to open database")?;
+    let table_opts = TableOptions::new(&cli.columns, cli.wide).map_err(anyhow::Error::msg)?;
+
+    match cli.command {
+        Commands::Scan {
+            paths,
+            force,
+            dry_run,
+        } => {
+            let scan_paths = resolve_scan_paths(paths, &config)?;
+
+            if dry_run {
+                println!("DRY RUN — no changes will be written to the database");
+                let preview = crate::scanner::preview(&db, &scan_paths, force, &config)
+                    .context("Scan preview failed")?;
+                print_scan_preview(&preview);
+                return Ok(());
+            }
+
+            run_scan(&db, &scan_paths, force, &config)?;
+            let stale = db
+                .stale_analysis_count()
+                .context("Failed to count stale analyses")?;
+            if stale > 0 {
+                println!(
+                    "Note: {stale} analyzed tracks changed on disk; `setbreak analyze` will re-analyze them."
+                );
+            }
+        }
+
+        Commands::Reparse {
+            paths,
+            prefer,
+            dry_run,
+        } => {
+            let roots = if paths.is_empty() {
+                config
+                    .music_dirs
+                    .iter()
+                    .map(|p| crate::paths::to_db(p))
+                    .collect()
+            } else {
+                paths
+            };
+            let summary = crate::scanner::reparse::reparse(
+                &db,
+                &roots,
+                &config,
+                prefer.map(Into::into),
+                dry_run,
+            )
+            .context("Reparse failed")?;
+            println!(
+                "{}{} tracks: {} {}",
+                if dry_run { "DRY RUN — " } else { "" },
+                summary.tracks,
+                summary.changed,
+                if dry_run { "would change" } else { "changed" }
+            );
+            for (field, count) in summary.fields.iter().filter(|(_, n)| *n > 0) {
+                println!("  {:<14} {}", field, count);
+            }
+        }
+        Commands::ImportBeets {
+            library,
+            export_scores,
+            dry_run,
+        } => {
+            let summary = crate::beets::import(&db, &library, export_scores, dry_run)
+                .context("Beets import failed")?;
+            let verb = if dry_run { "would change" } else { "changed" };
+            println!(
+                "{}{} beets items, {} matched to tracks",
+                if dry_run { "DRY RUN — " } else { "" },
+                summary.items,
+                summary.matched
+            );
+            println!("  titles {verb}: {}", summary.titles);
+            println!("  bands {verb}:  {}", summary.bands);
+            if export_scores {
+                println!(
+                    "  scores {}: {} items",
+                    if dry_run { "to export" } else { "exported" },
+                    summary.exported
+                );
+            }
+        }
+        #[cfg(feature = "analysis")]
+        Commands::Analyze {
+            jobs,
+            force,
+            filter,
+            decode_jobs,
+            prefetch_mb,
+            priority,
+            preview,
+        } => {
+            let priority = priority
+                .as_deref()
+                .map(crate::analyzer::priority::Priority::parse)
+                .transpose()?;
+            let pipeline = pipeline_config(&config, jobs, decode_jobs, prefetch_mb);
+            let started =
+                crate::standouts::clock(&db).context("Failed to read the database clock")?;
+            let mode = if preview {
+                crate::analyzer::AnalyzeMode::Preview
+            } else if force {
+                crate::analyzer::AnalyzeMode::Force
+            } else {
+                crate::analyzer::AnalyzeMode::Missing
+            };
+            let result = crate::analyzer::analyze_tracks(
+                &mut db,
+                mode,
+                &pipeline,
+                &track_tuning(&config)?,
+                filter.as_deref(),
+                priority.as_ref(),
+                &CliProgress::new(),
+            )
+            .context("Analysis failed")?;
+            println!(
+                "Analysis complete: {} analyzed, {} failed",
+                result.analyzed, result.failed
+            );
+            after_analysis(&db, &started, &result, &config, &table_opts)?;
+        }
+
+        #[cfg(feature = "analysis")]
+        Commands::Refresh {
+            paths,
+            jobs,
+            no_scan,
+            source_offsets,
+            dry_run,
+        } => {
+            use crate::refresh::STEPS;
+            let workers = if jobs > 0 {
+                jobs
+            } else {
+                config.resolve_workers()
+            };
+            let formulas = crate::refresh::formula_fingerprint(&config.band_scoring);
+            let step = |i: usize, status: &str| {
+                println!("[{}/{}] {}: {status}", i + 1, STEPS.len(), STEPS[i])
+            };
+            if dry_run {
+                println!("DRY RUN — nothing will be scanned, analyzed or written");
+                println!();
+            }
+
+            // Scan; a dry run previews it and counts what it would add for analysis
+            let mut scanned = 0;
+            if no_scan {
+                step(0, "skipped (--no-scan)");
+            } else {
+                let scan_paths = resolve_scan_paths(paths, &config)?;
+                if dry_run {
+                    let preview = crate::scanner::preview(&db, &scan_paths, false, &config)
+                        .context("Scan preview failed")?;
+                    step(
+                        0,
+                        &format!(
+                            "{} new and {} changed files",
+                            preview.totals.new, preview.totals.updated
+                        ),
+                    );
+                    scanned = (preview.totals.new + preview.totals.updated) as usize;
+                } else {
+                    step(0, "scanning");
+                    run_scan(&db, &scan_paths, false, &config)?;
+                }
+            }
+
+            // Analyze
+            let pending = db.get_unanalyzed_tracks().context("Query failed")?.len() + scanned;
+            if pending == 0 {
+                step(1, "up to date");
+            } else {
+                step(1, &format!("{pending} tracks new, changed or previewed"));
+                if !dry_run {
+                    let pipeline = pipeline_config(&config, workers, 0, 0);
+                    let started = crate::standouts::clock(&db)
+                        .context("Failed to read the database clock")?;
+                    let result = crate::analyzer::analyze_tracks(
+                        &mut db,
+                        crate::analyzer::AnalyzeMode::Missing,
+                        &pipeline,
+                        &track_tuning(&config)?,
+                        None,
+                        None,
+                        &CliProgress::new(),
+                    )
+                    .context("Analysis failed")?;
+                    println!(
+                        "Analysis complete: {} analyzed, {} failed",
+                        result.analyzed, result.failed
+                    );
+                    after_analysis(&db, &started, &result, &config, &table_opts)?;
+                }
+            }
+
+            // Rescore, then calibrate from the fresh raw scores
+            let rescore = crate::refresh::rescore_reason(&db, &formulas)?;
+            let calibrate = if dry_run && pending > 0 {
+                Some("new analyses to calibrate")
+            } else {
+                crate::refresh::calibration_reason(&db, source_offsets)?
+            };
+            match (rescore, calibrate) {
+                (Some(reason), _) => step(2, reason),
+                (None, Some(_)) => step(2, "restoring raw scores before calibrating"),
+                (None, None) => step(2, "up to date"),
+            }
+            if !dry_run && (rescore.is_some() || calibrate.is_some()) {
+                let result = crate::analyzer::rescore_tracks(&db, &CliProgress::new())
+                    .context("Rescore failed")?;
+                crate::refresh::record_rescore(&db, &formulas)?;
+                println!("Rescore complete: {} tracks updated", result.rescored);
+            }
+            match calibrate.or(rescore.map(|_| "scores rescored")) {
+                Some(reason) => {
+                    step(3, reason);
+                    if !dry_run {
+                        if source_offsets {
+                            link_performances(&db)?;
+                        }
+                        let result = crate::calibrate::calibrate_scores(
+                            &db,
+                            false,
+                            source_offsets,
+                            &CliProgress::new(),
+                        )
+                        .context("Calibration failed")?;
+                        crate::refresh::record_calibration(&db, source_offsets)?;
+                        println!(
+                            "Calibration complete: {} calibrated, {} skipped (no show date)",
+                            result.calibrated, result.skipped_no_show
+                        );
+                    }
+                }
+                None => step(3, "up to date"),
+            }
+
+            // Similarity
+            let stale_format = db
+                .similarity_format()
+                .context("Query failed")?
+                .is_none_or(|f| !f.is_current());
+            // A dry run counts the tracks analysis would add
+            let changed = db.similarity_changed_ids().context("Query failed")?.len()
+                + if dry_run { pending } else { 0 };
+            if !stale_format && changed == 0 {
+                step(4, "up to date");
+            } else {
+                if stale_format {
+                    step(
+                        4,
+                        "full rebuild (no neighbors for the current feature layout)",
+                    );
+                } else {
+                    step(4, &format!("{changed} tracks changed since the last run"));
+                }
+                if !dry_run {
+                    let result = crate::similarity::refresh_changed_similarity(
+                        &db,
+                        workers,
+                        &CliProgress::new(),
+                    )
+                    .context("Similarity computation failed")?;
+                    println!(
+                        "Similarity complete: {} tracks processed, {} pairs stored",
+                        result.tracks_processed, result.pairs_stored
+                    );
+                }
+            }
+
+            // Chains
+            if db.chain_cache_is_fresh().context("Query failed")? && !(dry_run && pending > 0) {
+                step(5, "up to date");
+            } else {
+                step(
+                    5,
+                    "analyses, titles or setlists changed since the last build",
+                );
+                if !dry_run {
+                    let n = crate::chains::rebuild_chain_cache(&db, &CliProgress::new())
+                        .context("Failed to rebuild chains")?;
+                    println!("Stored {} chains.", n);
+                }
+            }
+        }
+
+        Commands::Setlist {
+            dry_run,
+            notes,
+            min_confidence,
+            review,
+            confirm,
+            reject,
+            validate,
+            setlistfm,
+            date,
+        } => {
+            if validate {
+                if setlistfm {
+                    let key = crate::credentials::CredentialStore::load()?
+                        .get(crate::setlist::setlistfm::CREDENTIAL)?
+                        .context(
+                            "No setlist.fm API key (run `setbreak auth set setlistfm` \
+                             or set SETBREAK_SETLISTFM_KEY)",
+                        )?;
+                    let result = crate::setlist::setlistfm::fetch_missing_setlists(
+                        &db,
+                        &key,
+                        config.archive.rate_limit_ms.max(SETLISTFM_RATE_LIMIT_MS),
+                        &CliProgress::new(),
+                    )
+                    .context("Failed to fetch setlists from setlist.fm")?;
+                    if result.files_processed > 0 {
+                        println!(
+                            "setlist.fm: {} shows, {} songs imported",
+                            result.shows_imported, result.songs_imported
+                        );
+                        println!();
+                    }
+                }
+                let report = crate::setlist::validate::validate(&db, date.as_deref())
+                    .context("Setlist validation failed")?;
+                print_setlist_validation(&report);
+                return Ok(());
+            }
+            if review {
+                let pending = db.pending_title_matches().context("Query failed")?;
+                if pending.is_empty() {
+                    println!("No title matches waiting for review.");
+                    return Ok(());
+                }
+                println!("{} title matches need review:", pending.len());
+                println!();
+                print_pending_title_matches(&pending, &table_opts);
+                println!();
+                println!("Apply with `setbreak setlist --confirm ID...`, discard with --reject.");
+                return Ok(());
+            }
+            if !confirm.is_empty() || !reject.is_empty() {
+                for id in &confirm {
+                    if db.confirm_title_match(*id)? {
+                        println!("Confirmed track {id}");
+                    } else {
+                        println!("No pending match for track {id}");
+                    }
+                }
+                for id in &reject {
+                    if db.reject_title_match(*id)? {
+                        println!("Rejected track {id}");
+                    } else {
+                        println!("No pending match for track {id}");
+                    }
+                }
+                return Ok(());
+            }
+
+            if dry_run {
+                println!("DRY RUN — no changes will be written to the database");
+            }
+            let client = crate::archive_client::ArchiveClient::new(&config.archive);
+            let result = crate::setlist::lookup_setlists(
+                &db,
+                dry_run,
+                notes,
+                min_confidence,
+                &client,
+                &CliProgress::new(),
+            )
+            .context("Setlist lookup failed")?;
+            println!();
+            println!(
+                "Setlist lookup complete: {} dirs fetched, {} titles updated, {} queued for review, {} show notes stored, {} errors",
+                result.directories_fetched,
+                result.titles_updated,
+                result.queued_for_review,
+                result.notes_stored,
+                result.fetch_errors
+            );
+            println!("{}", client.stats());
+            if notes && !dry_run {
+                let guests = crate::lineup::import_notes(&db).context("Failed to read lineups")?;
+                println!(
+                    "{guests} guest appearances found in show notes (see `setbreak lineup list`)"
+                );
+            }
+            if dry_run && (result.titles_updated > 0 || result.queued_for_review > 0) {
+                println!("(dry run — re-run without --dry-run to write changes)");
+            } else if result.queued_for_review > 0 {
+                println!("Review uncertain matches with `setbreak setlist --review`.");
+            }
+        }
+
+        Commands::Rescore {
+            experiment: Some(path),
+            compare,
+            movers,
+            ..
+        } => {
+            let experiment = crate::experiment::Experiment::load(&path)?;
+            let summaries = experiment.run(&db).context("Experiment failed")?;
+            println!(
+                "Experiment {} (temporary; stored scores unchanged)",
+                path.display()
+            );
+            println!();
+            for s in &summaries {
+                println!(
+                    "  {:<14} {:>6} tracks  mean {:>5.1}  p5-p95 {:.1}-{:.1}",
+                    s.score.label, s.tracks, s.mean, s.p5, s.p95
+                );
+            }
+
+            if compare {
+                for c in experiment
+                    .compare(&db, movers)
+                    .context("Comparison failed")?
+                {
+                    println!();
+                    println!(
+                        "{}: rank correlation {:.3} over {} tracks, mean {:.1} -> {:.1}",
+                        c.score.label, c.spearman, c.tracks, c.current_mean, c.experiment_mean
+                    );
+                    if c.movers.is_empty() {
+                        continue;
+                    }
+                    let mut table = Table::new(vec![
+                        Column::left("Song").flex(12),
+                        Column::left("Date"),
+                        Column::right("Now"),
+                        Column::right("Exp"),
+                        Column::right("Rank").gap(1),
+                        Column::right("Move"),
+                    ]);
+                    for m in &c.movers {
+                        table.push(vec![
+                            m.title.clone(),
+                            m.date.clone(),
+                            format!("{:.1}", m.current),
+                            format!("{:.1}", m.experiment),
+                            format!("{:.0} -> {:.0}", m.current_rank, m.experiment_rank),
+                            format!("{:+.0}", m.current_rank - m.experiment_rank),
+                        ]);
+                    }
+                    print!("{}", table.render(table_opts.max_width));
+                }
+            }
+        }
+
+        Commands::Rescore { report, movers, .. } => {
+            let before = report.then(|| db.score_snapshot()).transpose()?;
+            let result = crate::analyzer::rescore_tracks(&db, &CliProgress::new())
+                .context("Rescore failed")?;
+            crate::refresh::record_rescore(
+                &db,
+                &crate::refresh::formula_fingerprint(&config.band_scoring),
+            )?;
+            println!("Rescore complete: {} tracks updated", result.rescored);
+            if let Some(before) = before {
+                let deltas = before.compare(&db.score_snapshot()?, movers);
+                print_score_deltas(&deltas, table_opts.max_width);
+            }
+        }
+
+        #[cfg(feature = "analysis")]
+        Commands::Trace { track, features } => {
+            let id = resolve_track(&db, &track)?;
+            let Some(found) = db.get_track(id).context("Query failed")? else {
+                anyhow::bail!("No track with id {id}");
+            };
+            note!("Tracing {} (track {id})...", found.file_path);
+            let tuning = track_tuning(&config)?;
+            let trace = crate::analyzer::trace::trace_track(
+                &db,
+                &found,
+                tuning.for_track(&found.file_path),
+            )
+            .context("Trace failed")?;
+            print_trace(&trace, features);
+        }
+
+        Commands::Calibrate {
+            dry_run,
+            report,
+            movers,
+            source_offsets,
+        } => {
+            if dry_run {
+                println!("DRY RUN — no changes will be written to the database");
+                println!();
+            }
+            let before = report.then(|| db.score_snapshot()).transpose()?;
+            // A dry-run report applies the calibration inside a transaction that
+            // is rolled back once the new scores have been read
+            if source_offsets {
+                link_performances(&db)?;
+            }
+            let preview = (dry_run && report)
+                .then(|| db.conn.unchecked_transaction())
+                .transpose()?;
+            let result = crate::calibrate::calibrate_scores(
+                &db,
+                dry_run && !report,
+                source_offsets,
+                &CliProgress::new(),
+            )
+            .context("Calibration failed")?;
+            let after = report.then(|| db.score_snapshot()).transpose()?;
+            drop(preview);
+            if !dry_run {
+                crate::refresh::record_calibration(&db, source_offsets)?;
+            }
+            println!(
+                "Calibration complete: {} calibrated, {} skipped (no show date)",
+                result.calibrated, result.skipped_no_show
+            );
+            if let (Some(before), Some(after)) = (before, after) {
+                print_score_deltas(&before.compare(&after, movers), table_opts.max_width);
+            }
+            if dry_run && result.total_tracks > 0 {
+                println!("(dry run — re-run without --dry-run to apply)");
+            }
+        }
+
+        Commands::Top {
+            score,
+            limit,
+            song,
+            min_duration,
+            all_types,
+            include_cuts,
+            include_untitled,
+            merge_parts,
+            per_performance,
+            score_name,
+            min_show_quality,
+            with_guest,
+            offset,
+            page,
+            interactive,
+        } => {
+            let mut filter = crate::db::models::TopFilter {
+                song: song.as_deref(),
+                band: None,
+                min_duration_secs: min_duration.map(|m| m * 60.0),
+                live_only: !all_types,
+                include_cuts,
+                include_untitled,
+                min_show_quality,
+                with_guest: with_guest.as_deref(),
+                per_performance,
+                offset: page.map_or(offset, |p| (p - 1) * limit),
+            };
+            if interactive && !std::io::stdin().is_terminal() {
+                anyhow::bail!("--interactive needs a terminal on stdin");
+            }
+            if per_performance {
+                link_performances(&db)?;
+            }
+            if merge_parts {
+                refresh_track_groups(&db)?;
+            }
+            let untitled = if include_untitled {
+                0
+            } else {
+                db.count_untitled_excluded(&filter)
+                    .context("Query failed")?
+            };
+
+            // --score NAME: a built-in score, else a recipe from config
+            let (score, recipe) = match score_name {
+                None => (score, None),
+                Some(name) => match ScoreName::from_str(&name, true) {
+                    Ok(builtin) => (builtin, None),
+                    Err(_) => {
+                        let formula = config.recipes.get(&name).with_context(|| {
+                            let known: Vec<&str> =
+                                config.recipes.keys().map(|k| k.as_str()).collect();
+                            format!(
+                                "Unknown score or recipe '{}' (recipes in config: {})",
+                                name,
+                                if known.is_empty() {
+                                    "none".to_string()
+                                } else {
+                                    known.join(", ")
+                                }
+                            )
+                        })?;
+                        (score, Some(crate::recipes::compile(&name, formula)?))
+                    }
+                },
+            };
+            // Rows are numbered whenever they don't start at the top, or can be picked
+            let numbered = interactive || filter.offset > 0;
+
+            loop {
+                let opts = TableOptions {
+                    first_rank: numbered.then_some(filter.offset + 1),
+                    ..table_opts.clone()
+                };
+                let from = if filter.offset > 0 {
+                    format!(" from #{}", filter.offset + 1)
+                } else {
+                    String::new()
+                };
+                let tracks: Vec<TrackScore> = match &recipe {
+                    Some(recipe) => {
+                        let results = db
+                            .query_top_recipe(recipe, limit, &filter)
+                            .context("Query failed")?;
+                        if !results.is_empty() {
+                            println!("Top {} tracks by {}{from}:", results.len(), recipe.name);
+                            println!("  = {}", recipe.formula);
+                            println!();
+                            print_recipe_table(&results, &recipe.name, &opts);
+                        }
+                        results.into_iter().map(|(t, _)| t).collect()
+                    }
+                    None => {
+                        let results = if merge_parts {
+                            db.query_top_merged(score.column(), limit, &filter)
+                        } else {
+                            db.query_top(score.column(), limit, &filter)
+                        }
+                        .context("Query failed")?;
+                        if !results.is_empty() {
+                            println!("Top {} tracks by {}{from}:", results.len(), score.label());
+                            println!();
+                            print_score_table(&results, Some(&score), &opts);
+                        }
+                        results
+                    }
+                };
+                if tracks.is_empty() {
+                    if filter.offset > 0 {
+                        println!("No results past #{}.", filter.offset);
+                    } else {
+                        println!("No results found.");
+                    }
+                }
+                print_untitled_note(untitled);
+                if !interactive || (tracks.is_empty() && filter.offset == 0) {
+                    return Ok(());
+                }
+
+                // Pick rows until the user moves to another page
+                loop {
+                    println!();
+                    let answer = prompt_line(
+                        "Row # for details, [n]ext / [p]revious page, Enter to quit: ",
+                    )?;
+                    match answer.as_str() {
+                        "" | "q" | "quit" => return Ok(()),
+                        "n" | "next" if !tracks.is_empty() => {
+                            filter.offset += limit;
+                            break;
+                        }
+                        "p" | "prev" | "previous" if filter.offset > 0 => {
+                            filter.offset = filter.offset.saturating_sub(limit);
+                            break;
+                        }
+                        n => {
+                            let track = n
+                                .parse::<usize>()
+                                .ok()
+                                .and_then(|n| n.checked_sub(filter.offset + 1))
+                                .and_then(|i| tracks.get(i));
+                            match track {
+                                Some(t) => print_track_detail(&db, &t.file_path)?,
+                                None => println!("No row {n} on this page."),
+                            }
+                        }
+                    }
+                }
+                println!();
+            }
+        }
+
+        Commands::Compare {
+            song,
+            sort,
+            limit,
+            all_types,
+            band,
+            all_bands,
+        } => {
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let results = db
+                .query_compare(
+                    &song,
+                    sort.column(),
+                    limit,
+                    !all_types,
+                    band.as_deref(),
+                    !all_bands,
+                )
+                .context("Query failed")?;
+
+            if results.is_empty() {
+                println!("No analyzed tracks matching \"{}\".", song);
+                return Ok(());
+            }
+
+            let groups = if all_bands {
+                vec![(None, results)]
+            } else {
+                group_by_band(results)
+            };
+
+            for (i, (band, tracks)) in groups.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                match band {
+                    Some(b) if groups.len() > 1 => println!(
+                        "{} — {} versions of \"{}\" (sorted by {}):",
+                        b,
+                        tracks.len(),
+                        tracks[0].title,
+                        sort.label()
+                    ),
+                    _ => println!(
+                        "{} versions of \"{}\" (sorted by {}):",
+                        tracks.len(),
+                        tracks[0].title,
+                        sort.label()
+                    ),
+                }
+                println!();
+                print_score_table(tracks, Some(&sort), &table_opts);
+            }
+        }
+
+        Commands::Query {
+            expr,
+            save,
+            run,
+            list,
+            delete,
+            export,
+            sort,
+            limit,
+            all_types,
+        } => {
+            if list {
+                let searches = db.list_saved_searches().context("Query failed")?;
+                if searches.is_empty() {
+                    println!(
+                        "No saved searches. Save one with: setbreak query \"<expr>\" --save <name>"
+                    );
+                    return Ok(());
+                }
+                println!("{:<24} {:<14} Expression", "Name", "Sort");
+                println!("{}", "-".repeat(80));
+                for s in &searches {
+                    let sort = s
+                        .sort_column
+                        .as_deref()
+                        .and_then(ScoreName::from_column)
+                        .map(|s| s.label())
+                        .unwrap_or("date");
+                    let types = if s.all_types { " [all types]" } else { "" };
+                    println!("{:<24} {:<14} {}{}", s.name, sort, s.expr, types);
+                }
+                return Ok(());
+            }
+
+            if let Some(name) = delete {
+                if db.delete_saved_search(&name).context("Query failed")? {
+                    println!("Deleted saved search \"{}\".", name);
+                } else {
+                    println!("No saved search named \"{}\".", name);
+                }
+                return Ok(());
+            }
+
+            let (expr, sort, all_types) = match run {
+                Some(name) => {
+                    let saved = db
+                        .get_saved_search(&name)
+                        .context("Query failed")?
+                        .with_context(|| {
+                            format!(
+                                "No saved search named \"{}\" (see `setbreak query --list`)",
+                                name
+                            )
+                        })?;
+                    let saved_sort = saved
+                        .sort_column
+                        .as_deref()
+                        .and_then(ScoreName::from_column);
+                    (
+                        saved.expr,
+                        sort.or(saved_sort),
+                        all_types || saved.all_types,
+                    )
+                }
+                None => (expr.unwrap_or_default(), sort, all_types),
+            };
+
+            let filter = crate::query::compile(&expr).context("Invalid query expression")?;
+
+            if let Some(name) = &save {
+                db.save_search(name, &expr, sort.as_ref().map(|s| s.column()), all_types)
+                    .context("Failed to save search")?;
+                println!("Saved search \"{}\".", name);
+            }
+
+            let results = db
+                .query_filter(
+                    &filter,
+                    sort.as_ref().map(|s| s.column()),
+                    limit,
+                    !all_types,
+                )
+                .context("Query failed")?;
+
+            if results.is_empty() {
+                println!("No tracks match \"{}\".", expr);
+                return Ok(());
+            }
+
+            println!("{} tracks matching \"{}\":", results.len(), expr);
+            println!();
+            print_score_table(&results, sort.as_ref(), &table_opts);
+
+            if let Some(path) = export {
+                crate::playlist::write_m3u(&path, &results)
+                    .with_context(|| format!("Failed to write playlist {}", path.display()))?;
+                println!();
+                println!("Wrote {} tracks to {}", results.len(), path.display());
+            }
+        }
+
+        Commands::Compile {
+            title,
+            filter,
+            top,
+            tracks_per_disc,
+            discs,
+            per_show,
+            allow_repeats,
+            out,
+            all_types,
+        } => {
+            use crate::compilation::{self, CompileOptions};
+
+            let filter = filter
+                .as_deref()
+                .map(crate::query::compile)
+                .transpose()
+                .context("Invalid --where expression")?;
+            let ranked = compilation::candidates(&db, filter, top.column(), !all_types)
+                .context("Query failed")?;
+            let opts = CompileOptions {
+                disc_secs: tracks_per_disc,
+                max_discs: discs.max(1),
+                max_per_show: per_show.max(1),
+                allow_repeats,
+            };
+            let comp = compilation::build(&title, ranked, &opts);
+
+            if comp.discs.is_empty() {
+                println!("No tracks fit the constraints.");
+                return Ok(());
+            }
+
+            print!("{}", compilation::tracklist(&comp));
+            println!(
+                "{} tracks on {} disc(s), ranked by {}",
+                comp.track_count(),
+                comp.discs.len(),
+                top.label()
+            );
+            println!(
+                "Skipped: {} repeated songs, {} over the per-show cap, {} with no room left",
+                comp.skipped_repeat, comp.skipped_show_cap, comp.skipped_no_room
+            );
+
+            if let Some(dir) = out {
+                let written = compilation::write(&comp, &dir)
+                    .with_context(|| format!("Failed to write to {}", dir.display()))?;
+                println!();
+                for path in &written {
+                    println!("Wrote {}", path.display());
+                }
+            }
+        }
+
+        Commands::Albums {
+            score,
+            band,
+            album,
+            limit,
+        } => {
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let albums = crate::albums::ranked_albums(
+                &db,
+                score.column(),
+                band.as_deref(),
+                album.as_deref(),
+            )
+            .context("Query failed")?;
+            if albums.is_empty() {
+                println!(
+                    "No analyzed studio albums found (studio tracks need an album tag;                      see `setbreak classify`)."
+                );
+                return Ok(());
+            }
+            if album.is_some() {
+                for a in albums.iter().take(limit) {
+                    println!(
+                        "{} — {}{} ({} {:.0}):",
+                        a.band.as_deref().unwrap_or("Unknown band"),
+                        a.title,
+                        a.year
+                            .as_ref()
+                            .map(|y| format!(", {y}"))
+                            .unwrap_or_default(),
+                        score.label(),
+                        a.score(score.column()).unwrap_or(0.0)
+                    );
+                    println!();
+                    let tracks: Vec<TrackScore> = a
+                        .ranked_tracks(score.column())
+                        .into_iter()
+                        .cloned()
+                        .collect();
+                    print_score_table(&tracks, Some(&score), &table_opts);
+                    println!();
+                }
+                return Ok(());
+            }
+            let mut table = Table::new(vec![
+                Column::left("Album").flex(15),
+                Column::left("Band").flex(8),
+                Column::left("Year"),
+                Column::right("Tracks"),
+                Column::right("Min"),
+                Column::right(score.label()),
+                Column::left("Best track").flex(12),
+            ]);
+            for a in albums.iter().take(limit) {
+                let best = a.ranked_tracks(score.column()).first().map(|t| {
+                    format!(
+                        "{} ({:.0})",
+                        t.title,
+                        t.score(score.column()).unwrap_or(0.0)
+                    )
+                });
+                table.push(vec![
+                    a.title.clone(),
+                    a.band.clone().unwrap_or_default(),
+                    a.year.clone().unwrap_or_default(),
+                    a.tracks.len().to_string(),
+                    format!("{:.0}", a.minutes()),
+                    format!("{:.0}", a.score(score.column()).unwrap_or(0.0)),
+                    best.unwrap_or_default(),
+                ]);
+            }
+            print!("{}", table.render(table_opts.max_width));
+            println!();
+            println!(
+                "Album {}: duration-weighted mean over its tracks.                  `--album TITLE` ranks an album's tracks.",
+                score.label()
+            );
+        }
+
+        Commands::Shows { top, band, limit } => {
+            if !db.shows_are_fresh().context("Query failed")? {
+                let n =
+                    crate::shows::rebuild_shows(&db).context("Failed to compute show metrics")?;
+                note!("Computed metrics for {n} shows.");
+            }
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let shows = db
+                .top_shows(top.into(), band.as_deref(), limit)
+                .context("Query failed")?;
+            if shows.is_empty() {
+                println!("No analyzed live shows yet.");
+                return Ok(());
+            }
+            let mut table = Table::new(vec![
+                Column::left("Date"),
+                Column::left("Band").flex(8),
+                Column::right("Jam min"),
+                Column::right("Jam %"),
+                Column::right("Min"),
+                Column::right("Jams"),
+                Column::left("Longest jam").flex(12),
+            ]);
+            for s in &shows {
+                table.push(vec![
+                    s.date.clone(),
+                    s.band.clone(),
+                    format!("{:.0}", s.jam_minutes),
+                    format!("{:.0}%", s.jam_share() * 100.0),
+                    format!("{:.0}", s.minutes),
+                    format!("{}/{}", s.jam_tracks, s.tracks),
+                    s.longest_jam
+                        .as_ref()
+                        .map(|(title, min)| format!("{title} ({min:.0}m)"))
+                        .unwrap_or_default(),
+                ]);
+            }
+            print!("{}", table.render(table_opts.max_width));
+            println!();
+            println!(
+                "Jam minutes: music in tracks with improvisation >= {:.0}, scaled by each track's music share.",
+                crate::shows::JAM_IMPROVISATION
+            );
+        }
+
+        Commands::Lineup { action } => match action {
+            LineupAction::List { date, guest, band } => {
+                let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+                let entries = db
+                    .lineup(date.as_deref(), band.as_deref(), guest.as_deref())
+                    .context("Query failed")?;
+                if entries.is_empty() {
+                    println!(
+                        "No lineups stored. Fetch show notes with `setbreak setlist --notes`, \
+                         or add a musician with `setbreak lineup add`."
+                    );
+                    return Ok(());
+                }
+                let mut table = Table::new(vec![
+                    Column::left("Date"),
+                    Column::left("Band").flex(15),
+                    Column::left("Name").flex(20),
+                    Column::left("Role").flex(12),
+                    Column::left("As"),
+                    Column::left("From"),
+                ]);
+                for e in &entries {
+                    table.push(vec![
+                        e.date.clone(),
+                        e.band.clone(),
+                        e.name.clone(),
+                        e.role.clone().unwrap_or_default(),
+                        if e.guest { "guest" } else { "member" }.to_string(),
+                        e.source.clone(),
+                    ]);
+                }
+                print!("{}", table.render(table_opts.max_width));
+            }
+            LineupAction::Add {
+                date,
+                name,
+                role,
+                band,
+                member,
+            } => {
+                let band = match band {
+                    Some(b) => crate::bands::registry().resolve_canonical_name(&b),
+                    None => {
+                        let bands = db.bands_on_date(&date).context("Query failed")?;
+                        match <[String; 1]>::try_from(bands) {
+                            Ok([band]) => band,
+                            Err(bands) if bands.is_empty() => {
+                                anyhow::bail!("No tracks dated {date}; give the show's --band")
+                            }
+                            Err(bands) => anyhow::bail!(
+                                "{} bands played {date} ({}); pick one with --band",
+                                bands.len(),
+                                bands.join(", ")
+                            ),
+                        }
+                    }
+                };
+                db.add_lineup(&date, &band, &name, role.as_deref(), !member)
+                    .context("Failed to store the lineup")?;
+                println!(
+                    "Added {name} to {band} {date} as a {}.",
+                    if member { "member" } else { "guest" }
+                );
+            }
+            LineupAction::Remove { date, name, band } => {
+                let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+                let removed = db
+                    .remove_lineup(&date, band.as_deref(), &name)
+                    .context("Failed to update the lineup")?;
+                if removed == 0 {
+                    println!("{name} isn't in the lineup for {date}.");
+                } else {
+                    println!("Removed {name} from {date}.");
+                }
+            }
+            LineupAction::Import => {
+                let guests = crate::lineup::import_notes(&db).context("Failed to read lineups")?;
+                println!("{guests} guest appearances found in show notes.");
+            }
+        },
+
+        Commands::Performances { action } => match action {
+            PerformanceAction::List { band, date, limit } => {
+                link_performances(&db)?;
+                let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+                let tracks = db
+                    .performance_tracks(band.as_deref(), date.as_deref())
+                    .context("Query failed")?;
+                let performances = crate::performances::compare(&tracks);
+                if performances.is_empty() {
+                    println!("No analyzed performances with more than one recording.");
+                    return Ok(());
+                }
+                println!(
+                    "{} performances with more than one recording{}:",
+                    performances.len(),
+                    if performances.len() > limit {
+                        format!(" (first {limit})")
+                    } else {
+                        String::new()
+                    }
+                );
+                for p in performances.iter().take(limit) {
+                    println!();
+                    println!(
+                        "{}  {}  ({} recordings, {} shared songs)",
+                        p.date,
+                        p.band,
+                        p.recordings.len(),
+                        p.shared
+                    );
+                    let mut table = Table::new(vec![
+                        Column::left("Source").flex(16),
+                        Column::left("Type"),
+                        Column::right("Tracks"),
+                        Column::right("Energy"),
+                        Column::right("Groove"),
+                        Column::right("Improv"),
+                        Column::right("Transc"),
+                    ]);
+                    for r in &p.recordings {
+                        let mean = |column: &str| {
+                            r.mean(column)
+                                .map_or("-".to_string(), |m| format!("{m:.1}"))
+                        };
+                        table.push(vec![
+                            r.name().to_string(),
+                            r.kind().to_string(),
+                            r.tracks.to_string(),
+                            mean("energy_score"),
+                            mean("groove_score"),
+                            mean("improvisation_score"),
+                            mean("transcendence_score"),
+                        ]);
+                    }
+                    print!("{}", table.render(table_opts.max_width));
+                }
+            }
+            PerformanceAction::Link { dir, date } => {
+                let dir = dir.trim_end_matches(['/', '\\']);
+                let tracks = db
+                    .link_recording(dir, &date)
+                    .context("Failed to store link")?;
+                if tracks == 0 {
+                    anyhow::bail!("No scanned tracks in recording directory {dir}");
+                }
+                link_performances(&db)?;
+                println!("Linked {dir} ({tracks} tracks) to {date}.");
+            }
+            PerformanceAction::Unlink { dir } => {
+                let dir = dir.trim_end_matches(['/', '\\']);
+                if !db.unlink_recording(dir).context("Failed to drop link")? {
+                    println!("No manual link for {dir}.");
+                    return Ok(());
+                }
+                link_performances(&db)?;
+                println!("Unlinked {dir}.");
+            }
+        },
+
+        Commands::DateCheck {
+            band,
+            tolerance,
+            limit,
+        } => {
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let check = crate::date_check::check(&db, band.as_deref(), tolerance.max(0))
+                .context("Query failed")?;
+            if !check.thin_bands.is_empty() {
+                note!(
+                    "Skipped (too few dated shows to compare): {}",
+                    check.thin_bands.join(", ")
+                );
+            }
+            if check.checked == 0 {
+                println!("No band has enough analyzed, dated shows to compare against.");
+                return Ok(());
+            }
+
+            let era_columns = |first: &str| {
+                vec![
+                    Column::left(first).flex(12),
+                    Column::left("Band").flex(8),
+                    Column::right("Tracks"),
+                    Column::right("Sounds like"),
+                    Column::right("Range"),
+                ]
+            };
+            let era_row = |e: &crate::date_check::EraEstimate| {
+                vec![
+                    e.label.clone(),
+                    e.band.clone(),
+                    e.tracks.to_string(),
+                    e.estimate.to_string(),
+                    format!("{}-{}", e.range.0, e.range.1),
+                ]
+            };
+
+            if check.suspicious.is_empty() {
+                println!(
+                    "All {} dated shows sound within {tolerance} years of their date.",
+                    check.checked
+                );
+            } else {
+                println!(
+                    "{} of {} dated shows sound more than {tolerance} years off their date:",
+                    check.suspicious.len(),
+                    check.checked
+                );
+                let mut table = Table::new(era_columns("Date"));
+                for e in check.suspicious.iter().take(limit) {
+                    table.push(era_row(e));
+                }
+                print!("{}", table.render(table_opts.max_width));
+            }
+
+            if !check.undated.is_empty() {
+                println!();
+                println!("Undated recordings ({}):", check.undated.len());
+                let mut table = Table::new(era_columns("Directory"));
+                for e in check.undated.iter().take(limit) {
+                    table.push(era_row(e));
+                }
+                print!("{}", table.render(table_opts.max_width));
+            }
+            println!();
+            println!(
+                "Estimates come from the nearest-sounding dated shows of the same band (tape hiss, rolloff, tilt, timbre); treat them as leads, not verdicts."
+            );
+        }
+
+        Commands::Show {
+            date,
+            notes,
+            viz,
+            source,
+        } => {
+            let viz_score = viz
+                .map(|key| {
+                    crate::scores::get(&key)
+                        .with_context(|| format!("Unknown score '{}' for --viz", key))
+                })
+                .transpose()?;
+            let results = db.query_show(&date).context("Query failed")?;
+
+            if results.is_empty() {
+                println!("No analyzed tracks for date {}.", date);
+                return Ok(());
+            }
+
+            let mut sources = crate::show_sources::split_by_source(results);
+            let source_count = sources.len();
+            if let Some(n) = source {
+                if n == 0 || n > source_count {
+                    anyhow::bail!(
+                        "{} has {} source{}; --source must be 1-{}",
+                        date,
+                        source_count,
+                        if source_count == 1 { "" } else { "s" },
+                        source_count
+                    );
+                }
+                sources = vec![sources.swap_remove(n - 1)];
+            }
+
+            println!("Show: {}", date);
+            if let Some(rating) = db.rating_for_date(&date).context("Query failed")? {
+                println!("archive.org rating: {rating}");
+            }
+            let guests: Vec<String> = db
+                .lineup(Some(&date), None, None)
+                .context("Query failed")?
+                .into_iter()
+                .filter(|e| e.guest)
+                .map(|e| match e.role {
+                    Some(role) => format!("{} ({role})", e.name),
+                    None => e.name,
+                })
+                .collect();
+            if !guests.is_empty() {
+                println!("Guests: {}", guests.join(", "));
+            }
+            println!();
+            let chains = match viz_score {
+                Some(_) => {
+                    if !db.chain_cache_is_fresh().context("Query failed")? {
+                        crate::chains::rebuild_chain_cache(&db, &CliProgress::new())
+                            .context("Failed to rebuild chains")?;
+                    }
+                    db.get_cached_chains(Some(&date), None, 2, None)
+                        .context("Query failed")?
+                }
+                None => Vec::new(),
+            };
+            // Every chain track but the last segues into the next
+            let segues: std::collections::HashSet<&str> = chains
+                .iter()
+                .flat_map(|c| &c.tracks[..c.tracks.len().saturating_sub(1)])
+                .map(|t| t.file_path.as_str())
+                .collect();
+            for (i, s) in sources.iter().enumerate() {
+                if source_count > 1 {
+                    let n = source.unwrap_or(i + 1);
+                    println!("Source {n}: {} ({} tracks)", s.name(), s.tracks.len());
+                }
+                match viz_score {
+                    Some(score) => print!(
+                        "{}",
+                        crate::show_viz::render(&s.tracks, &segues, score, table_opts.max_width)
+                    ),
+                    None => print_score_table(&s.tracks, None, &table_opts),
+                }
+                if source_count > 1 {
+                    println!();
+                }
+            }
+            if source_count > 1 && source.is_none() {
+                println!("{source_count} sources for this date; pick one with --source N.");
+            }
+
+            let quality = db.show_quality(&date).context("Query failed")?;
+            if !quality.flagged.is_empty() {
+                println!();
+                println!(
+                    "Quality: {:.0}% clean ({} ok, {} suspect, {} garbage; garbage not listed above)",
+                    quality.clean_share() * 100.0,
+                    quality.clean,
+                    quality.suspect,
+                    quality.garbage
+                );
+                for (title, flag) in &quality.flagged {
+                    println!("  [{}] {}", flag, title);
+                }
+            }
+
+            if notes {
+                let show_notes = db.get_show_notes(&date).context("Query failed")?;
+                println!();
+                if show_notes.is_empty() {
+                    println!(
+                        "No show notes stored for {}. Fetch them with `setbreak setlist --notes`.",
+                        date
+                    );
+                }
+                for n in &show_notes {
+                    print_show_notes(n);
+                }
+            }
+        }
+
+        Commands::Groups {
+            song,
+            date,
+            rebuild,
+        } => {
+            if rebuild {
+                let n = crate::track_groups::rebuild_track_groups(&db)
+                    .context("Failed to rebuild track groups")?;
+                println!("Stored {} track groups.", n);
+                println!();
+            } else {
+                refresh_track_groups(&db)?;
+            }
+
+            let groups = db
+                .get_track_groups(song.as_deref(), date.as_deref())
+                .context("Query failed")?;
+            if groups.is_empty() {
+                println!("No split jams found.");
+                return Ok(());
+            }
+
+            println!(
+                "{:<32} {:>10} {:>5}  {:>4} {:>4} {:>4} {:>4} {:>4}",
+                "Song", "Date", "Min", "Grv", "Imp", "Bld", "Exp", "Trn"
+            );
+            println!("{}", "-".repeat(80));
+            for g in &groups {
+                let c = &g.combined;
+                println!(
+                    "{:<32} {:>10} {:>5.1}  {:>4.0} {:>4.0} {:>4.0} {:>4.0} {:>4.0}",
+                    format!("{} [{} parts]", c.title, g.parts.len()),
+                    c.date,
+                    c.duration_min,
+                    c.groove,
+                    c.improvisation,
+                    c.build_quality,
+                    c.exploratory,
+                    c.transcendence,
+                );
+                for p in &g.parts {
+                    let title: String = p.title.chars().take(28).collect();
+                    println!(
+                        "  {:<30} {:>10} {:>5.1}  {:>4.0} {:>4.0} {:>4.0} {:>4.0} {:>4.0}",
+                        title,
+                        "",
+                        p.duration_min,
+                        p.groove,
+                        p.improvisation,
+                        p.build_quality,
+                        p.exploratory,
+                        p.transcendence,
+                    );
+                }
+            }
+            println!();
+            println!("{} split jams", groups.len());
+        }
+
+        Commands::Timeline { song, date } => {
+            let found = db
+                .find_track_id(&song, date.as_deref(), None)
+                .context("Search failed")?;
+            let (track_id, title, track_date) = match found {
+                Some(t) => t,
+                None => {
+                    println!("No analyzed track matching \"{}\".", song);
+                    return Ok(());
+                }
+            };
+
+            // A split jam is shown as one timeline, its parts laid end to end
+            let parts = db.get_group_parts(track_id).context("Query failed")?;
+            let mut segments = Vec::new();
+            let mut cuts = Vec::new();
+            let mut part_starts = Vec::new();
+            let mut offset = 0.0;
+            for &(part_id, duration) in &parts {
+                part_starts.push(offset);
+                for mut seg in db.get_track_segments(part_id).context("Query failed")? {
+                    seg.start_time += offset;
+                    segments.push(seg);
+                }
+                for mut cut in db.get_track_cuts(part_id).context("Query failed")? {
+                    cut.time += offset;
+                    cuts.push(cut);
+                }
+                offset += duration;
+            }
+
+            if parts.len() > 1 {
+                println!(
+                    "Timeline: {} ({}) — {} parts",
+                    title,
+                    track_date,
+                    parts.len()
+                );
+            } else {
+                println!("Timeline: {} ({})", title, track_date);
+            }
+            println!();
+            println!("{:>7}  {:<20} {:>6}", "Time", "Section", "Energy");
+            println!("{}", "-".repeat(36));
+
+            // Merge segments and cut markers in time order
+            let mut cut_iter = cuts.iter().peekable();
+            let mut part_iter = part_starts.iter().enumerate().skip(1).peekable();
+            for seg in &segments {
+                while let Some(c) = cut_iter.next_if(|c| c.time <= seg.start_time) {
+                    print_cut_marker(c);
+                }
+                while let Some((n, &start)) = part_iter.next_if(|(_, s)| **s <= seg.start_time) {
+                    println!("{:>7}  ── part {} ──", fmt_clock(start), n + 1);
+                }
+                let energy = seg.energy.map(|e| format!("{:.2}", e)).unwrap_or_default();
+                println!(
+                    "{:>7}  {:<20} {:>6}",
+                    fmt_clock(seg.start_time),
+                    seg.section,
+                    energy
+                );
+            }
+            for c in cut_iter {
+                print_cut_marker(c);
+            }
+
+            if segments.is_empty() {
+                println!("(no segment data)");
+            }
+            if !cuts.is_empty() {
+                println!();
+                println!(
+                    "{} cut(s) detected — this track is excluded from `top` unless --include-cuts.",
+                    cuts.len()
+                );
+            }
+        }
+
+        Commands::Highlights {
+            date,
+            out,
+            count,
+            min_secs,
+            max_secs,
+            format,
+            dry_run,
+        } => {
+            if min_secs <= 0.0 || max_secs < min_secs {
+                anyhow::bail!("--max-secs must be at least --min-secs, and both positive");
+            }
+            let length = crate::highlights::ExcerptLength { min_secs, max_secs };
+            let excerpts = crate::highlights::find_highlights(&db, &date, count, length)
+                .context("Query failed")?;
+
+            if excerpts.is_empty() {
+                println!(
+                    "No tension data for {}. Analyze the show first (`setbreak analyze`).",
+                    date
+                );
+                return Ok(());
+            }
+
+            println!("Highlights: {}", date);
+            println!();
+            println!(
+                "{:>3}  {:<35} {:>9} {:>6} {:>9}",
+                "#", "Song", "Start", "Len", "Intensity"
+            );
+            println!("{}", "-".repeat(66));
+            for (i, e) in excerpts.iter().enumerate() {
+                let start = e.start.round() as i64;
+                let title: String = if e.title.chars().count() > 35 {
+                    format!("{}...", e.title.chars().take(32).collect::<String>())
+                } else {
+                    e.title.clone()
+                };
+                println!(
+                    "{:>3}  {:<35} {:>5}:{:02} {:>5.0}s {:>9.2}",
+                    i + 1,
+                    title,
+                    start / 60,
+                    start % 60,
+                    e.duration,
+                    e.intensity
+                );
+            }
+
+            if dry_run {
+                println!();
+                println!("Dry run — no files written.");
+                return Ok(());
+            }
+
+            let written = crate::highlights::write_excerpts(&excerpts, &out, &format)
+                .context("Failed to cut excerpts")?;
+            println!();
+            println!("Wrote {} excerpts to {}", written.len(), out.display());
+        }
+
+        Commands::Snippet {
+            seed,
+            at,
+            secs,
+            out,
+            band,
+            dry_run,
+        } => {
+            if secs <= 0.0 {
+                anyhow::bail!("--secs must be positive");
+            }
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let (song, date) = crate::similarity::parse_seed(&seed);
+            let Some((id, _, _)) = db
+                .find_track_id(&song, date.as_deref(), band.as_deref())
+                .context("Search failed")?
+            else {
+                println!("No analyzed track matching \"{seed}\".");
+                return Ok(());
+            };
+            let Some(source) = db.snippet_source(id).context("Query failed")? else {
+                println!("No analyzed track matching \"{seed}\".");
+                return Ok(());
+            };
+            let anchor = match source.anchor_secs(at) {
+                Ok(anchor) => anchor,
+                Err(why) => {
+                    println!(
+                        "Can't place the snippet in {} ({}): {why}.",
+                        source.title, source.date
+                    );
+                    return Ok(());
+                }
+            };
+            let (start, duration) =
+                crate::highlights::snippet_window(anchor, secs, source.duration_secs);
+            let out = out.unwrap_or_else(|| {
+                PathBuf::from(crate::highlights::snippet_filename(&source, start, "ogg"))
+            });
+            let clock = |t: f64| {
+                let t = t.round() as i64;
+                format!("{}:{:02}", t / 60, t % 60)
+            };
+            println!(
+                "{} ({}): {} to {} ({:.0}s), leading into {}",
+                source.title,
+                source.date,
+                clock(start),
+                clock(start + duration),
+                duration,
+                clock(anchor)
+            );
+            if dry_run {
+                println!("Dry run — no file written.");
+                return Ok(());
+            }
+            crate::highlights::write_snippet(&source.file_path, start, duration, &out)
+                .context("Failed to cut the snippet")?;
+            println!("Wrote {}", out.display());
+        }
+
+        Commands::Similarity { jobs, changed_only } => {
+            let workers = if jobs > 0 {
+                jobs
+            } else {
+                config.resolve_workers()
+            };
+            let result = if changed_only {
+                crate::similarity::refresh_changed_similarity(&db, workers, &CliProgress::new())
+            } else {
+                crate::similarity::compute_similarity(&db, workers, &CliProgress::new())
+            }
+            .context("Similarity computation failed")?;
+            if changed_only && result.tracks_processed == 0 {
+                println!(
+                    "Similarity is up to date: no tracks re-analyzed or added since the last run"
+                );
+            } else {
+                println!(
+                    "Similarity complete: {} tracks processed, {} pairs stored",
+                    result.tracks_processed, result.pairs_stored
+                );
+            }
+        }
+
+        Commands::Similar {
+            song,
+            seeds,
+            not_like,
+            date,
+            limit,
+            band,
+            all_bands,
+            exclude_same_show,
+            exclude_same_song,
+            year_range,
+            explain,
+        } => {
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let filter = crate::similarity::SimilarFilter {
+                exclude_same_show,
+                exclude_same_song,
+                band: band.clone().filter(|_| !all_bands),
+                years: year_range,
+            };
+            let mut specs: Vec<(String, Option<String>)> = seeds
+                .iter()
+                .map(|s| crate::similarity::parse_seed(s))
+                .collect();
+            if let Some(song) = song {
+                specs.insert(0, (song, date));
+            }
+
+            let mut found_seeds = Vec::new();
+            for (song, date) in &specs {
+                // A seed outside --band is fine: "Phish tracks like this Dead jam"
+                let mut found = db
+                    .find_track_id(song, date.as_deref(), band.as_deref())
+                    .context("Search failed")?;
+                if found.is_none() && band.is_some() {
+                    found = db
+                        .find_track_id(song, date.as_deref(), None)
+                        .context("Search failed")?;
+                }
+                match found {
+                    Some(t) => found_seeds.push(t),
+                    None => {
+                        match date {
+                            Some(d) => println!("No analyzed track matching \"{song}\" on {d}."),
+                            None => println!("No analyzed track matching \"{song}\"."),
+                        }
+                        return Ok(());
+                    }
+                }
+            }
+            let seed_ids: Vec<i64> = found_seeds.iter().map(|(id, _, _)| *id).collect();
+
+            let mut avoid_ids = Vec::new();
+            for spec in &not_like {
+                let (song, date) = crate::similarity::parse_seed(spec);
+                let ids = db
+                    .matching_track_ids(&song, date.as_deref(), band.as_deref())
+                    .context("Search failed")?;
+                if ids.is_empty() {
+                    println!("No analyzed track matching \"{spec}\" for --not-like.");
+                    return Ok(());
+                }
+                avoid_ids.extend(ids);
+            }
+
+            // Stored neighbors cover the plain single-seed case; anything else
+            // ranks against the seeds' centroid on the fly, as does a library
+            // whose stored neighbors predate the current feature vector layout.
+            // Metadata filters rank on the fly too: a stored list is only the
+            // nearest 20, which a filter could empty
+            let format = db.similarity_format().context("Query failed")?;
+            let outdated = format.filter(|f| !f.is_current());
+            let stored = matches!(found_seeds.as_slice(), [_] if avoid_ids.is_empty())
+                && outdated.is_none()
+                && !filter.is_active();
+            let results = if stored {
+                db.query_similar(seed_ids[0], limit, !all_bands)
+                    .context("Query failed")?
+            } else {
+                crate::similarity::similar_to_seeds(
+                    &db, &seed_ids, &avoid_ids, limit, !all_bands, &filter,
+                )
+                .context("Query failed")?
+            };
+
+            // Stored pairs go stale when either end is re-analyzed
+            let stale = if stored {
+                let changed: std::collections::HashSet<i64> = db
+                    .similarity_changed_ids()
+                    .context("Query failed")?
+                    .into_iter()
+                    .collect();
+                seed_ids
+                    .iter()
+                    .chain(results.iter().map(|(_, _, id)| id))
+                    .filter(|id| changed.contains(id))
+                    .count()
+            } else {
+                0
+            };
+
+            if results.is_empty() {
+                if stale > 0 {
+                    println!(
+                        "No stored neighbors for this track yet. Run `setbreak similarity --changed-only`."
+                    );
+                } else {
+                    println!("No similarity data. Run `setbreak similarity` first.");
+                }
+                return Ok(());
+            }
+
+            if let [(_, title, track_date)] = found_seeds.as_slice() {
+                println!("Tracks similar to \"{}\" ({}):", title, track_date);
+            } else {
+                println!(
+                    "Tracks similar to the centroid of {} seeds:",
+                    found_seeds.len()
+                );
+                for (_, title, track_date) in &found_seeds {
+                    println!("  {} ({})", title, track_date);
+                }
+            }
+            if !avoid_ids.is_empty() {
+                println!(
+                    "Not like: {} ({} tracks; distances include the penalty)",
+                    not_like.join(", "),
+                    avoid_ids.len()
+                );
+            }
+            if let Some(format) = outdated {
+                println!(
+                    "(stored neighbors use feature vector {format}; ranked on the fly — run `setbreak similarity` to rebuild)"
+                );
+            }
+            println!();
+
+            let rows: Vec<(TrackScore, f64)> =
+                results.iter().map(|(t, d, _)| (t.clone(), *d)).collect();
+            print_distance_table(&rows, &table_opts);
+            if stale > 0 {
+                println!();
+                println!(
+                    "Warning: {stale} of these tracks were re-analyzed since similarity was computed, so their distances may be stale."
+                );
+                println!("Run `setbreak similarity --changed-only` to refresh them.");
+            }
+
+            if explain {
+                let explainer = crate::similarity::Explainer::load(&db)
+                    .context("Failed to load feature vectors")?;
+                print_similarity_explanations(&explainer, &seed_ids, &results, &table_opts);
+            }
+        }
+
+        Commands::Plays { action } => match action {
+            PlaysAction::Import { file } => {
+                let (source, scrobbles) = crate::scrobbles::load(&file)?;
+                let summary = crate::scrobbles::import(&db, source, &scrobbles)?;
+                println!(
+                    "Imported {} {} scrobbles: {} matched to {} tracks, {} unmatched, {} without a show date",
+                    summary.scrobbles,
+                    source.as_str(),
+                    summary.matched,
+                    summary.tracks,
+                    summary.unmatched,
+                    summary.undated
+                );
+                if summary.undated > 0 {
+                    println!(
+                        "Note: scrobbles are matched by the show date in the album (or title) name."
+                    );
+                }
+            }
+            PlaysAction::Top { score, limit } => {
+                let played = db.most_played(limit).context("Query failed")?;
+                if played.is_empty() {
+                    println!("No plays imported. Run `setbreak plays import <export>` first.");
+                    return Ok(());
+                }
+                println!("Your most played:");
+                println!();
+                print_play_table(&played, &score, &table_opts);
+
+                let counts = db.play_counts().context("Query failed")?;
+                let favorites = db
+                    .query_top(
+                        score.column(),
+                        limit,
+                        &crate::db::models::TopFilter {
+                            live_only: true,
+                            ..Default::default()
+                        },
+                    )
+                    .context("Query failed")?;
+                let rows: Vec<(TrackScore, i64)> = favorites
+                    .into_iter()
+                    .map(|t| {
+                        let plays = counts.get(&t.file_path).copied().unwrap_or(0);
+                        (t, plays)
+                    })
+                    .collect();
+                let heard = rows.iter().filter(|(_, plays)| *plays > 0).count();
+
+                println!();
+                println!("Top {} by {}:", rows.len(), score.label());
+                println!();
+                print_play_table(&rows, &score, &table_opts);
+                println!();
+                println!(
+                    "You've played {} of the algorithm's top {}. Find the rest with `setbreak query \"plays=0\"`.",
+                    heard,
+                    rows.len()
+                );
+            }
+        },
+
+        Commands::Queue { action } => match action {
+            QueueAction::Add { tracks, note } => {
+                for track in &tracks {
+                    let id = resolve_track(&db, track)?;
+                    let source = crate::queue::QueueSource::Manual;
+                    if db.queue_add(id, source, note.as_deref())? {
+                        println!("Queued track {id}");
+                    } else {
+                        println!("Track {id} is already queued");
+                    }
+                }
+            }
+            QueueAction::List { export_m3u } => {
+                let queue = db.queue_list().context("Query failed")?;
+                if queue.is_empty() {
+                    println!("The re-listen queue is empty.");
+                    return Ok(());
+                }
+                print_queue(&queue, &table_opts);
+                if let Some(path) = export_m3u {
+                    let tracks: Vec<_> = queue.iter().map(|e| e.track.clone()).collect();
+                    crate::playlist::write_m3u(&path, &tracks)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    note!("Wrote {} tracks to {}", tracks.len(), path.display());
+                }
+            }
+            QueueAction::Pop { count } => {
+                for entry in db.queue_pop(count).context("Query failed")? {
+                    println!("{}", entry.track.file_path);
+                }
+            }
+        },
+
+        Commands::Titles { action } => match action {
+            TitlesAction::Export { missing, output } => {
+                let rows = db.title_rows(missing).context("Query failed")?;
+                match &output {
+                    Some(path) => {
+                        let file = std::fs::File::create(path)
+                            .with_context(|| format!("Failed to create {}", path.display()))?;
+                        crate::titles::write_csv(&rows, std::io::BufWriter::new(file))?;
+                        note!("Wrote {} tracks to {}", rows.len(), path.display());
+                    }
+                    None => crate::titles::write_csv(&rows, std::io::stdout().lock())?,
+                }
+            }
+            TitlesAction::Import { file, dry_run } => {
+                let corrections = crate::titles::load(&file)?;
+                let summary = crate::titles::import(&db, &corrections, dry_run)?;
+                println!(
+                    "{}{} rows: {} updated, {} unchanged, {} blank",
+                    if dry_run { "DRY RUN — " } else { "" },
+                    summary.rows,
+                    summary.updated,
+                    summary.unchanged,
+                    summary.blank
+                );
+            }
+            TitlesAction::Dedupe {
+                max_edits,
+                yes,
+                dry_run,
+            } => {
+                let variants = db.title_variants().context("Query failed")?;
+                let clusters = crate::title_aliases::cluster_titles(variants, max_edits);
+                if clusters.is_empty() {
+                    println!("No near-duplicate titles found.");
+                    return Ok(());
+                }
+                let interactive = !yes && !dry_run && std::io::stdin().is_terminal();
+                if !yes && !dry_run && !interactive {
+                    println!("(stdin is not a terminal: listing only; pass --yes to merge all)");
+                }
+                let mut merges = Vec::new();
+                let mut accept_rest = yes;
+                for (i, cluster) in clusters.iter().enumerate() {
+                    println!();
+                    println!("[{}/{}] {} tracks", i + 1, clusters.len(), cluster.tracks());
+                    for (n, v) in cluster.variants.iter().enumerate() {
+                        println!("  {}. {} ({})", n + 1, v.title, v.tracks);
+                    }
+                    let mut canonical = Some(cluster.canonical());
+                    if interactive && !accept_rest {
+                        let answer = prompt_line(
+                            "Merge into 1? [y]es / [n]o / number / [a]ll remaining / [q]uit: ",
+                        )?;
+                        match answer.as_str() {
+                            "y" | "yes" | "" => {}
+                            "a" | "all" => accept_rest = true,
+                            "q" | "quit" => break,
+                            n => {
+                                canonical = n
+                                    .parse::<usize>()
+                                    .ok()
+                                    .and_then(|n| n.checked_sub(1))
+                                    .and_then(|n| cluster.variants.get(n))
+                                    .map(|v| v.title.as_str());
+                            }
+                        }
+                    } else if !accept_rest {
+                        canonical = None;
+                    }
+                    if let Some(canonical) = canonical {
+                        if interactive || yes {
+                            println!("  -> {canonical}");
+                        }
+                        merges.extend(cluster.merges_into(canonical));
+                    }
+                }
+
+                println!();
+                if merges.is_empty() {
+                    println!("{} clusters found; nothing merged.", clusters.len());
+                    return Ok(());
+                }
+                db.add_title_aliases(&merges)
+                    .context("Failed to store title aliases")?;
+                let retitled = db
+                    .apply_title_aliases()
+                    .context("Failed to apply title aliases")?;
+                println!(
+                    "Stored {} aliases; {} tracks retitled. Future scans and setlist lookups apply them too.",
+                    merges.len(),
+                    retitled
+                );
+            }
+            TitlesAction::Aliases => {
+                let aliases = db.title_aliases().context("Query failed")?;
+                if aliases.is_empty() {
+                    println!("No title aliases. Create some with `setbreak titles dedupe`.");
+                    return Ok(());
+                }
+                let mut table = Table::new(vec![
+                    Column::left("Alias").flex(10),
+                    Column::left("Canonical").flex(10),
+                ]);
+                for a in &aliases {
+                    table.push(vec![a.alias.clone(), a.canonical.clone()]);
+                }
+                print!("{}", table.render(table_opts.max_width));
+            }
+            TitlesAction::Normalize { dry_run } => {
+                let summary = crate::title_normalize::backfill(&db, dry_run)
+                    .context("Failed to normalize titles")?;
+                let verb = if dry_run { "would change" } else { "changed" };
+                println!("Track titles {verb}: {}", summary.tracks);
+                println!("Setlist songs {verb}: {}", summary.setlist_songs);
+                println!("Alias targets {verb}: {}", summary.aliases);
+                if !dry_run && summary.tracks + summary.aliases > 0 {
+                    let retitled = db
+                        .apply_title_aliases()
+                        .context("Failed to apply title aliases")?;
+                    if retitled > 0 {
+                        println!("{retitled} tracks retitled by aliases.");
+                    }
+                }
+            }
+        },
+
+        Commands::External { action } => match action {
+            ExternalAction::Run {
+                names,
+                jobs,
+                force,
+                retry_failed,
+                limit,
+            } => {
+                let analyzers: Vec<_> = if names.is_empty() {
+                    config.external_analyzers.iter().collect()
+                } else {
+                    names
+                        .iter()
+                        .map(|n| {
+                            config
+                                .external_analyzers
+                                .iter()
+                                .find(|a| &a.name == n)
+                                .with_context(|| format!("No external analyzer named '{n}'"))
+                        })
+                        .collect::<Result<_>>()?
+                };
+                if analyzers.is_empty() {
+                    println!("No [[external_analyzers]] configured.");
+                    return Ok(());
+                }
+                let added = db
+                    .ensure_external_columns(&config.external_analyzers)
+                    .context("Failed to add external feature columns")?;
+                if !added.is_empty() {
+                    println!("Added columns: {}", added.join(", "));
+                }
+                let jobs = if jobs > 0 {
+                    jobs
+                } else {
+                    config.resolve_workers()
+                };
+
+                for analyzer in analyzers {
+                    let mut tracks = db.external_pending(&analyzer.name, force, retry_failed)?;
+                    if let Some(limit) = limit {
+                        tracks.truncate(limit);
+                    }
+                    if tracks.is_empty() {
+                        println!("{}: nothing to do", analyzer.name);
+                        continue;
+                    }
+
+                    let progress = CliProgress::new();
+                    progress.start(tracks.len() as u64, "tracks");
+                    progress.set_message(&analyzer.name);
+                    let (mut done, mut failed) = (0usize, 0usize);
+                    let mut db_error = None;
+                    crate::external::run_many(analyzer, &tracks, jobs, |(id, path), result| {
+                        let stored = match result {
+                            Ok(values) => {
+                                done += 1;
+                                db.store_external(*id, &analyzer.name, &values)
+                            }
+                            Err(e) => {
+                                failed += 1;
+                                log::warn!("{path}: {e}");
+                                db.record_external_failure(*id, &analyzer.name, &e.to_string())
+                            }
+                        };
+                        if let Err(e) = stored {
+                            db_error.get_or_insert(e);
+                        }
+                        progress.inc(1);
+                    });
+                    progress.finish("");
+                    if let Some(e) = db_error {
+                        return Err(e).context("Failed to store external features");
+                    }
+                    println!("{}: {done} processed, {failed} failed", analyzer.name);
+                }
+            }
+            ExternalAction::List => {
+                if config.external_analyzers.is_empty() {
+                    println!("No [[external_analyzers]] configured.");
+                    return Ok(());
+                }
+                let counts = db.external_run_counts()?;
+                for a in &config.external_analyzers {
+                    let (ok, failed) = counts
+                        .iter()
+                        .find(|(name, _, _)| name == &a.name)
+                        .map_or((0, 0), |&(_, ok, failed)| (ok, failed));
+                    println!(
+                        "{} — {} ({} tracks, {} failed)",
+                        a.name,
+                        a.command.join(" "),
+                        ok,
+                        failed
+                    );
+                    for o in &a.outputs {
+                        println!("    ext.{:<30} ← {}", o.column(), o.key);
+                    }
+                }
+            }
+        },
+
+        Commands::Serve {
+            bind,
+            public,
+            no_auth,
+            rate_limit,
+        } => {
+            let access = if public {
+                let token = if no_auth {
+                    None
+                } else {
+                    Some(
+                        crate::credentials::CredentialStore::load()?
+                            .get(crate::api::CREDENTIAL)?
+                            .context(
+                                "No token for --public (run `setbreak auth set serve` \
+                                 or set SETBREAK_SERVE_KEY), or pass --no-auth",
+                            )?,
+                    )
+                };
+                Some(crate::api::PublicAccess::new(token, rate_limit))
+            } else {
+                None
+            };
+            let listener = std::net::TcpListener::bind(&bind)
+                .with_context(|| format!("Failed to listen on {bind}"))?;
+            let addr = listener.local_addr()?;
+            if let Some(access) = &access {
+                println!("Serving public rankings on http://{addr} (Ctrl-C to stop)");
+                println!("  GET /top?score=<name>&band=<band>  ranked tracks (no file paths)");
+                println!("  GET /bands                         bands with analyzed shows");
+                println!(
+                    "  {}, {rate_limit} requests/minute per client",
+                    if access.has_token() {
+                        "Token required"
+                    } else {
+                        "No token"
+                    }
+                );
+            } else {
+                println!("Serving read-only metadata API on http://{addr} (Ctrl-C to stop)");
+                println!("  GET /track?path=<file>    scores, summary, similar tracks");
+                println!("  GET /similar?path=<file>  similar tracks");
+            }
+            crate::api::serve(&db, listener, access).context("API server failed")?;
+        }
+
+        Commands::Profile { action } => match action {
+            ProfileAction::Create { dates, name, query } => {
+                let source = crate::profile::source_expression(&dates, query.as_deref())
+                    .context("Give show dates or --query to select source tracks")?;
+                let profile = crate::profile::create_profile(&db, &name, &source)?;
+                println!(
+                    "Created profile \"{}\" from {} tracks ({}).",
+                    profile.name,
+                    profile.track_ids.len(),
+                    profile.source
+                );
+            }
+
+            ProfileAction::Match {
+                name,
+                limit,
+                include_source,
+                all_types,
+            } => {
+                let results =
+                    crate::profile::match_profile(&db, &name, limit, !all_types, include_source)?;
+
+                if results.is_empty() {
+                    println!("No analyzed tracks to match against.");
+                    return Ok(());
+                }
+
+                println!("Tracks closest to profile \"{}\":", name);
+                println!();
+                print_distance_table(&results, &table_opts);
+            }
+
+            ProfileAction::List => {
+                let profiles = db.list_sound_profiles().context("Query failed")?;
+                if profiles.is_empty() {
+                    println!(
+                        "No sound profiles. Create one with: setbreak profile create <date> --name <name>"
+                    );
+                    return Ok(());
+                }
+                println!("{:<24} {:>6}  {:<19}  Source", "Name", "Tracks", "Created");
+                println!("{}", "-".repeat(80));
+                for p in &profiles {
+                    let outdated = if p.vector_format().is_current() {
+                        ""
+                    } else {
+                        "  (outdated features: recreate)"
+                    };
+                    println!(
+                        "{:<24} {:>6}  {:<19}  {}{}",
+                        p.name,
+                        p.track_ids.len(),
+                        p.created_at,
+                        p.source,
+                        outdated
+                    );
+                }
+            }
+
+            ProfileAction::Delete { name } => {
+                if db.delete_sound_profile(&name).context("Query failed")? {
+                    println!("Deleted profile \"{}\".", name);
+                } else {
+                    println!("No profile named \"{}\".", name);
+                }
+            }
+        },
+
+        Commands::Chains {
+            sort,
+            date,
+            min_length,
+            min_duration,
+            song,
+            band,
+            limit,
+            detail,
+            rebuild,
+            export_m3u,
+            min_show_quality,
+            containing,
+            show_context,
+        } => {
+            if let Some(ref d) = date {
+                if !db.date_has_analysis(d).context("Query failed")? {
+                    println!("No analyzed tracks for date {}.", d);
+                    return Ok(());
+                }
+            }
+
+            // Re-detect when forced or when analysis/titles/setlists changed since the last build
+            if rebuild || !db.chain_cache_is_fresh().context("Query failed")? {
+                note!("Detecting segue chains...");
+                let n = crate::chains::rebuild_chain_cache(&db, &CliProgress::new())
+                    .context("Failed to rebuild chains")?;
+                println!("Stored {} chains.", n);
+                println!();
+            }
+
+            // Resolve band filter: match band code against file paths
+            let band_path_substr: Option<String> = band.as_ref().map(|b| {
+                match b.to_lowercase().as_str() {
+                    "gd" | "grateful dead" | "dead" => "grateful_dead",
+                    "phish" | "ph" => "phish",
+                    "bts" | "built to spill" => "built_to_spill",
+                    other => other,
+                }
+                .to_string()
+            });
+
+            if let Some(song) = containing {
+                let memberships = db
+                    .chains_containing(&song, date.as_deref(), band_path_substr.as_deref())
+                    .context("Query failed")?;
+                if memberships.is_empty() {
+                    println!("\"{}\" doesn't appear in any segue chain.", song);
+                    return Ok(());
+                }
+                print_chain_memberships(&song, &memberships, show_context, &table_opts);
+                return Ok(());
+            }
+
+            let all_chains = db
+                .get_cached_chains(
+                    date.as_deref(),
+                    band_path_substr.as_deref(),
+                    min_length,
+                    min_show_quality,
+                )
+                .context("Query failed")?;
+
+            if all_chains.is_empty() {
+                println!("No segue chains found in analyzed tracks.");
+                return Ok(());
+            }
+
+            let chains = crate::chains::filter_and_sort_chains(
+                all_chains,
+                min_duration,
+                song.as_deref(),
+                sort.column(),
+                limit,
+            );
+
+            if chains.is_empty() {
+                println!("No chains match the given criteria.");
+                return Ok(());
+            }
+
+            println!(
+                "Top {} segue chains (sorted by {}):",
+                chains.len(),
+                sort.label()
+            );
+            println!();
+            print_chain_table(&chains, &sort, &table_opts);
+
+            if detail {
+                println!();
+                for c in &chains {
+                    println!(
+                        "=== {} ({}) — {} songs, {:.1} min ===",
+                        c.chain_title(),
+                        c.date,
+                        c.chain_length,
+                        c.duration_min
+                    );
+                    print_score_table(&c.tracks, None, &table_opts);
+                    println!();
+                }
+            }
+
+            if let Some(dir) = export_m3u {
+                let written = crate::chains::export_m3u(&chains, &dir)
+                    .with_context(|| format!("Failed to write playlists to {}", dir.display()))?;
+                println!();
+                println!("Wrote {} playlists to {}", written.len(), dir.display());
+            }
+        }
+
+        Commands::Discover {
+            band,
+            refresh,
+            year,
+            limit,
+            concurrency,
+        } => {
+            let mut archive = config.archive.clone();
+            if let Some(n) = concurrency {
+                archive.concurrency = n;
+            }
+            let client = crate::archive_client::ArchiveClient::new(&archive);
+            let result = crate::discovery::discover_missing_shows(
+                &db,
+                &band,
+                refresh,
+                year.as_deref(),
+                limit,
+                config.archive.cache_ttl_days,
+                &client,
+            )
+            .context("Discovery failed")?;
+
+            if result.pages_left > 0 {
+                println!(
+                    "Collection: {} ({} shows fetched so far, {} pages left)",
+                    result.collection, result.archive_count, result.pages_left
+                );
+            } else {
+                println!(
+                    "Collection: {} ({} total shows in archive)",
+                    result.collection, result.archive_count
+                );
+            }
+            println!(
+                "Local shows: {} dates | Missing: {} dates",
+                result.local_count,
+                result.missing.len()
+            );
+            println!();
+
+            if !result.shifted.is_empty() {
+                println!(
+                    "Not counted as missing — same venue in your library a day off ({}):",
+                    result.shifted.len()
+                );
+                for s in &result.shifted {
+                    println!(
+                        "  {} filed as {}  {}  ({})",
+                        s.archive_date, s.local_date, s.venue, s.identifier
+                    );
+                }
+                println!();
+            }
+
+            if result.missing.is_empty() {
+                println!("You have every show! (or no missing shows match the filter)");
+            } else {
+                print_missing_shows(&result.missing);
+                if result.missing.iter().any(|s| s.near_local.is_some()) {
+                    println!();
+                    println!(
+                        "* You have a show a day off with no venue to compare; check it before downloading."
+                    );
+                }
+                println!();
+                println!("Download with: setbreak download --band {} <DATE>", band);
+                let registry = crate::bands::registry();
+                if registry.is_sbd_stream_only(&band) {
+                    println!(
+                        "  (SBD/matrix sources are stream-only; download will auto-select audience tapes)"
+                    );
+                }
+            }
+            if client.stats().requests > 0 {
+                println!();
+                println!("{}", client.stats());
+            }
+        }
+
+        Commands::Ratings { refresh } => {
+            let client = crate::archive_client::ArchiveClient::new(&config.archive);
+            let summary =
+                crate::ratings::fetch_matched(&db, &client, refresh, config.archive.cache_ttl_days)
+                    .context("Rating lookup failed")?;
+            if summary.looked_up + summary.skipped == 0 {
+                println!(
+                    "No matched shows need ratings. Match local shows to archive.org items with `setbreak setlist --notes`."
+                );
+                return Ok(());
+            }
+            println!(
+                "Looked up {} archive.org items: {} have reviews.",
+                summary.looked_up, summary.rated
+            );
+            if summary.skipped > 0 {
+                println!(
+                    "{} items left for the next run (request failures or budget).",
+                    summary.skipped
+                );
+            }
+            if client.stats().requests > 0 {
+                println!();
+                println!("{}", client.stats());
+            }
+        }
+
+        Commands::Open {
+            date,
+            band,
+            download_page,
+            print,
+        } => {
+            use crate::show_page::{self, PageKind};
+
+            let date = crate::dates::find_date(&date, Default::default())
+                .context(format!("No date found in '{}'", date))?;
+            let kind = if download_page {
+                PageKind::Download
+            } else {
+                PageKind::Details
+            };
+            let sources = show_page::resolve(&db, &date, band.as_deref())?;
+            let url = match sources.first() {
+                Some(best) => show_page::page_url(&best.identifier, kind),
+                None => {
+                    note!(
+                        "No archive.org item known for {}; searching by date instead",
+                        date
+                    );
+                    show_page::search_url(&date, band.as_deref())
+                }
+            };
+            if print {
+                println!("{}", url);
+            } else {
+                println!("Opening {}", url);
+                show_page::open_url(&url)
+                    .context("Failed to open a browser (use --print to get the URL)")?;
+            }
+            if sources.len() > 1 {
+                note!("Other sources for {}:", date);
+                for s in &sources[1..] {
+                    note!("  {:<48} ({})", s.identifier, s.origin);
+                }
+            }
+        }
+
+        Commands::Download {
+            band,
+            date,
+            dest,
+            dry_run,
+        } => {
+            let registry = crate::bands::registry();
+
+            // Resolve band → archive strategy
+            let strategy = registry.resolve_archive_query(&band).context(format!(
+                "Unknown band '{}' or no archive.org strategy configured",
+                band
+            ))?;
+            let collection = match strategy {
+                crate::bands::ArchiveStrategy::Collection(c) => c.as_str(),
+                crate::bands::ArchiveStrategy::Creator(c) => c.as_str(),
+            };
+            let sbd_restricted = registry.is_sbd_stream_only(&band);
+
+            // Pick best source
+            let result = crate::discovery::pick_best_source(&db, collection, &date, sbd_restricted)
+                .context("Failed to query archive shows")?;
+
+            match result {
+                None => {
+                    if sbd_restricted {
+                        println!("No downloadable recordings found for {} on {}.", band, date);
+                        println!(
+                            "(SBD/matrix sources are stream-only for this band; no audience tapes available)"
+                        );
+                    } else {
+                        println!("No recordings found for {} on {}.", band, date);
+                        println!(
+                            "Try running `setbreak discover --band {}` first to populate the cache.",
+                            band
+                        );
+                    }
+                }
+                Some((identifier, source_q, format_q, skipped_sbd)) => {
+                    let src = crate::discovery::source_label(source_q);
+                    let fmt = crate::discovery::format_label(format_q);
+
+                    if skipped_sbd {
+                        println!("Note: Skipping SBD/matrix sources (stream-only for this band)");
+                    }
+                    println!("Best source: {} ({}/{})", identifier, src, fmt);
+
+                    if dry_run {
+                        let glob = crate::discovery::download_glob(format_q);
+                        println!("\nWould run:");
+                        println!(
+                            "  ia download {} --glob='{}' --no-directories",
+                            identifier, glob
+                        );
+                    } else {
+                        // Resolve destination directory
+                        let dest_dir = dest.map(PathBuf::from).unwrap_or_else(|| {
+                            config
+                                .music_dirs
+                                .first()
+                                .cloned()
+                                .unwrap_or_else(|| PathBuf::from("."))
+                        });
+
+                        let glob = crate::discovery::download_glob(format_q);
+                        let dest_path = dest_dir.join(&identifier);
+
+                        println!("Downloading to: {}", dest_path.display());
+                        println!(
+                            "Running: ia download {} --destdir={} --glob='{}'",
+                            identifier,
+                            dest_dir.display(),
+                            glob
+                        );
+
+                        let mut destdir = std::ffi::OsString::from("--destdir=");
+                        destdir.push(&dest_dir);
+                        let status = std::process::Command::new("ia")
+                            .args(["download", &identifier])
+                            .arg(destdir)
+                            .arg(format!("--glob={}", glob))
+                            .status()
+                            .context("Failed to run 'ia' command. Install with: pip install internetarchive")?;
+
+                        if status.success() {
+                            println!("Download complete!");
+                            println!(
+                                "Next: setbreak scan {} && setbreak analyze",
+                                dest_path.display()
+                            );
+                        } else {
+                            println!("Download failed (exit code: {:?})", status.code());
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::ImportSetlists {
+            path,
+            source,
+            dry_run,
+        } => {
+            if dry_run {
+                println!("DRY RUN — no changes will be written to the database");
+            }
+
+            let data_path = std::path::Path::new(&path);
+            if !data_path.is_dir() {
+                anyhow::bail!("Not a directory: {}", path);
+            }
+
+            println!("Parsing setlist data from {}...", path);
+            let entries = crate::setlist::import::parse_gdshowsdb(data_path)
+                .context("Failed to parse setlist data")?;
+
+            let show_count = entries
+                .iter()
+                .map(|e| &e.date)
+                .collect::<std::collections::HashSet<_>>()
+                .len();
+
+            println!("Parsed {} songs across {} shows", entries.len(), show_count);
+
+            if dry_run {
+                // Show a sample
+                let mut by_date: std::collections::BTreeMap<
+                    &str,
+                    Vec<&crate::setlist::import::SetlistEntry>,
+                > = std::collections::BTreeMap::new();
+                for e in &entries {
+                    by_date.entry(&e.date).or_default().push(e);
+                }
+
+                // Show last 3 dates as a sample
+                let sample: Vec<_> = by_date.iter().rev().take(3).collect();
+                for (date, songs) in sample.iter().rev() {
+                    println!("\n{date}:");
+                    let mut current_set = 0;
+                    for s in *songs {
+                        if s.set_num != current_set {
+                            current_set = s.set_num;
+                            println!("  Set {current_set}:");
+                        }
+                        let segue = if s.segued { " ->" } else { "" };
+                        println!("    {: >2}. {}{}", s.position, s.song, segue);
+                    }
+                }
+            } else {
+                let result = crate::setlist::import::import_setlists(&db, &entries, &source)
+                    .context("Failed to import setlists")?;
+                println!(
+                    "Import complete: {} shows, {} songs (source: {})",
+                    result.shows_imported, result.songs_imported, source
+                );
+            }
+        }
+
+        Commands::FetchPhishin { dry_run } => {
+            if dry_run {
+                println!("DRY RUN — no changes will be written to the database");
+            }
+
+            let result = crate::setlist::phishin::fetch_phish_setlists(
+                &db,
+                config.archive.rate_limit_ms,
+                dry_run,
+                &CliProgress::new(),
+            )
+            .context("Failed to fetch Phish setlists from phish.in")?;
+
+            println!(
+                "Phish setlists: {} shows, {} songs imported",
+                result.shows_imported, result.songs_imported
+            );
+        }
+
+        Commands::Classify => {
+            let tracks = db
+                .get_tracks_for_classify()
+                .context("Failed to load tracks for classification")?;
+            let total = tracks.len();
+
+            let tx = db.conn.unchecked_transaction()?;
+            let mut counts = std::collections::HashMap::new();
+            let mut sources = std::collections::BTreeMap::new();
+
+            for (id, file_path, parsed_date, album) in &tracks {
+                let rtype = crate::scanner::classify::classify_recording_type(
+                    file_path,
+                    parsed_date.as_deref(),
+                    album.as_deref(),
+                );
+                *counts.entry(rtype).or_insert(0usize) += 1;
+                let path = if crate::remote::is_remote(file_path) {
+                    crate::remote::percent_decode(file_path)
+                } else {
+                    file_path.clone()
+                };
+                let source = crate::scanner::source::parse_source(std::path::Path::new(&path));
+                *sources
+                    .entry(source.source_type.unwrap_or("unknown"))
+                    .or_insert(0usize) += 1;
+                tx.execute(
+                    "UPDATE tracks SET recording_type = ?1, source_type = ?2, taper = ?3,
+                            lineage_hint = ?4
+                     WHERE id = ?5",
+                    rusqlite::params![
+                        rtype,
+                        source.source_type,
+                        source.taper,
+                        source.lineage_hint,
+                        id
+                    ],
+                )?;
+            }
+            tx.commit()?;
+
+            let live = counts.get("live").copied().unwrap_or(0);
+            let studio = counts.get("studio").copied().unwrap_or(0);
+            let live_album = counts.get("live_album").copied().unwrap_or(0);
+            let unknown = counts.get("unknown").copied().unwrap_or(0);
+
+            println!(
+                "Classify complete: {} tracks — {} live, {} studio, {} live_album, {} unknown",
+                total, live, studio, live_album, unknown
+            );
+            let sources: Vec<String> = sources
+                .iter()
+                .map(|(source, n)| format!("{n} {source}"))
+                .collect();
+            println!("Sources: {}", sources.join(", "));
+            let filler = db
+                .classify_filler()
+                .context("Failed to classify soundchecks and interviews")?;
+            println!(
+                "Non-music items: {} soundcheck, {} interview (excluded from top/compare)",
+                filler.soundcheck, filler.interview
+            );
+        }
+
+        Commands::QualityCheck => {
+            let tracks = db
+                .get_tracks_for_quality_check()
+                .context("Failed to load tracks for quality check")?;
+            let total = tracks.len();
+
+            let tx = db.conn.unchecked_transaction()?;
+            let mut ok = 0usize;
+            let mut suspect = 0usize;
+            let mut garbage = 0usize;
+
+            for (id, file_path, snr_db, clipping_ratio) in &tracks {
+                let quality =
+                    crate::analyzer::classify_data_quality(*snr_db, *clipping_ratio, file_path);
+                match quality {
+                    "ok" => ok += 1,
+                    "suspect" => suspect += 1,
+                    "garbage" => garbage += 1,
+                    _ => ok += 1,
+                }
+                tx.execute(
+                    "UPDATE tracks SET data_quality = ?1 WHERE id = ?2",
+                    rusqlite::params![quality, id],
+                )?;
+            }
+            tx.commit()?;
+
+            println!(
+                "Quality check complete: {} tracks — {} ok, {} suspect, {} garbage",
+                total, ok, suspect, garbage
+            );
+        }
+
+        #[cfg(feature = "analysis")]
+        Commands::ExtractBoundaries { jobs } => {
+            let workers = if jobs > 0 {
+                jobs
+            } else {
+                config.resolve_workers()
+            };
+            let chunk_size = config.analysis.resolve_chunk_size(workers);
+            let result =
+                crate::analyzer::extract_boundaries(&db, workers, chunk_size, &CliProgress::new())
+                    .context("Boundary extraction failed")?;
+            println!(
+                "Boundary extraction complete: {} extracted, {} failed",
+                result.analyzed, result.failed
+            );
+        }
+
+        #[cfg(feature = "analysis")]
+        Commands::SplitSongs {
+            track_ids,
+            min_duration,
+            min_song_secs,
+            threshold,
+            cue,
+            force,
+            dry_run,
+        } => {
+            if !(0.0..=1.0).contains(&threshold) {
+                anyhow::bail!("--threshold must be between 0.0 and 1.0");
+            }
+            let params = crate::analyzer::songsplit::SplitParams {
+                min_song_secs,
+                threshold,
+            };
+            let candidates = if track_ids.is_empty() {
+                db.split_candidates(min_duration * 60.0, force)
+                    .context("Query failed")?
+            } else {
+                let mut found = Vec::new();
+                for &id in &track_ids {
+                    match db.split_candidate(id).context("Query failed")? {
+                        Some(c) => found.push(c),
+                        None => anyhow::bail!("No track with id {id}"),
+                    }
+                }
+                found
+            };
+            if candidates.is_empty() {
+                println!(
+                    "No unsplit recordings of {min_duration} minutes or more (--force to redo split ones)."
+                );
+                return Ok(());
+            }
+            if let Some(dir) = &cue {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+            }
+
+            for (i, c) in candidates.iter().enumerate() {
+                let name = crate::paths::file_name(&c.track.file_path);
+                println!("[{}/{}] {}", i + 1, candidates.len(), name);
+                let stored = if force {
+                    Vec::new()
+                } else {
+                    db.virtual_tracks(c.track.id).context("Query failed")?
+                };
+                let songs = if stored.is_empty() {
+                    let (boundaries, duration) =
+                        match crate::analyzer::split_recording(&c.track, &params) {
+                            Ok(found) => found,
+                            Err(e) => {
+                                eprintln!("  Failed: {e}");
+                                continue;
+                            }
+                        };
+                    let songs = crate::virtual_tracks::from_boundaries(&boundaries, duration);
+                    if !dry_run {
+                        db.replace_virtual_tracks(c.track.id, &songs)
+                            .context("Failed to store virtual tracks")?;
+                    }
+                    songs
+                } else {
+                    println!("  (stored split; --force to re-detect)");
+                    stored
+                };
+                print_virtual_tracks(&songs);
+
+                if let Some(dir) = &cue {
+                    let stem = std::path::Path::new(name)
+                        .file_stem()
+                        .and_then(|s| s.to_str())
+                        .unwrap_or(name);
+                    let path = dir.join(format!("{stem}.cue"));
+                    let file = std::fs::File::create(&path)
+                        .with_context(|| format!("Failed to create {}", path.display()))?;
+                    crate::virtual_tracks::write_cue(c, &songs, std::io::BufWriter::new(file))?;
+                    println!("  Wrote {}", path.display());
+                }
+            }
+            if dry_run {
+                println!("DRY RUN — nothing stored.");
+            }
+        }
+
+        #[cfg(feature = "analysis")]
+        Commands::Retrack {
+            date,
+            source,
+            min_segment_secs,
+            threshold,
+            merge_below,
+            apply,
+            force,
+            dry_run,
+        } => {
+            if !(0.0..=1.0).contains(&threshold) || !(0.0..=1.0).contains(&merge_below) {
+                anyhow::bail!("--threshold and --merge-below must be between 0.0 and 1.0");
+            }
+            let params = crate::retrack::RetrackParams {
+                min_segment_secs,
+                threshold,
+                merge_below,
+            };
+            let recordings: Vec<_> = db
+                .retrack_recordings(&date)
+                .context("Query failed")?
+                .into_iter()
+                .filter(|r| {
+                    source
+                        .as_ref()
+                        .is_none_or(|s| r.name().contains(s.as_str()))
+                })
+                .collect();
+            if recordings.is_empty() {
+                println!("No recordings for {date}.");
+                return Ok(());
+            }
+
+            for recording in &recordings {
+                println!("{} ({} tracks)", recording.name(), recording.tracks.len());
+                let stored = if force {
+                    Vec::new()
+                } else {
+                    db.retrack_segments(&recording.dir)
+                        .context("Query failed")?
+                };
+                let segments = if stored.is_empty() {
+                    let mut frames = Vec::with_capacity(recording.tracks.len());
+                    for t in &recording.tracks {
+                        note!("  decoding {}", crate::paths::file_name(&t.track.file_path));
+                        match crate::analyzer::song_frames(&t.track) {
+                            Ok(f) => frames.push(f),
+                            Err(e) => {
+                                eprintln!("  Failed: {e}");
+                                break;
+                            }
+                        }
+                    }
+                    if frames.len() < recording.tracks.len() {
+                        continue;
+                    }
+                    let segments = crate::retrack::propose(recording, frames, &params);
+                    if !dry_run {
+                        db.replace_retrack_segments(&recording.dir, &segments)
+                            .context("Failed to store corrected boundaries")?;
+                    }
+                    segments
+                } else {
+                    println!("  (stored boundaries; --force to re-detect)");
+                    stored
+                };
+                print_retrack(recording, &segments);
+
+                if let Some(dir) = &apply {
+                    let out = dir.join(recording.name());
+                    let written = crate::retrack::write_segments(recording, &segments, &out)?;
+                    println!("  Wrote {} tracks to {}", written.len(), out.display());
+                }
+                println!();
+            }
+            if dry_run {
+                println!("DRY RUN — nothing stored.");
+            }
+        }
+
+        Commands::Segues {
+            min_confidence,
+            band,
+            date,
+            limit,
+            detail,
+        } => {
+            crate::segues::run_segue_detection(
+                &db,
+                min_confidence,
+                band.as_deref(),
+                date.as_deref(),
+                limit,
+                detail,
+            )
+            .context("Segue detection failed")?;
+        }
+
+        Commands::SegueSuggest {
+            seed,
+            before,
+            limit,
+            band,
+            all_bands,
+            all_versions,
+        } => {
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let (song, date) = crate::similarity::parse_seed(&seed);
+            let Some((id, title, date)) = db
+                .find_track_id(&song, date.as_deref(), band.as_deref())
+                .context("Search failed")?
+            else {
+                println!("No analyzed track matching \"{seed}\".");
+                return Ok(());
+            };
+            let opts = crate::segue_match::SuggestOptions {
+                before,
+                all_bands,
+                all_versions,
+            };
+            let Some(suggestions) =
+                crate::segue_match::suggest(&db, id, limit, opts).context("Query failed")?
+            else {
+                println!(
+                    "{title} ({date}) has no segue edges yet: run `setbreak extract-boundaries` \
+                     or re-analyze it."
+                );
+                return Ok(());
+            };
+            if suggestions.is_empty() {
+                println!("No other songs with segue edges to match against.");
+                return Ok(());
+            }
+            if before {
+                println!("Songs to lead into {title} ({date}):");
+            } else {
+                println!("Songs to follow {title} ({date}):");
+            }
+            println!();
+            print_segue_suggestions(&suggestions, &table_opts);
+        }
+
+        Commands::Sql {
+            query,
+            json,
+            write,
+            views,
+        } => {
+            if views {
+                for view in crate::sql::VIEWS {
+                    let columns = db.view_columns(view.name).context("Query failed")?;
+                    println!("{:<12} {}", view.name, view.description);
+                    println!("{:<12} {}", "", columns.join(", "));
+                    println!();
+                }
+                println!(
+                    "Views keep their columns across upgrades (new ones are appended). \
+                     Database: {}",
+                    db.path()
+                        .map_or("(in memory)".into(), |p| p.display().to_string())
+                );
+                return Ok(());
+            }
+            let query = query.unwrap_or_default();
+            let out = crate::sql::run(&db, &query, write).context("SQL query failed")?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&out.to_json())?);
+                return Ok(());
+            }
+            // Numeric columns right-aligned, text ones left and shrinkable
+            let columns = out
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let numeric = out.rows.iter().all(|r| r[i].is_number() || r[i].is_null());
+                    if numeric {
+                        Column::right(name.as_str())
+                    } else {
+                        Column::left(name.as_str()).flex(10)
+                    }
+                })
+                .collect();
+            let mut table = Table::new(columns);
+            for row in &out.rows {
+                table.push(row.iter().map(crate::sql::cell_text).collect());
+            }
+            print!("{}", table.render(table_opts.max_width));
+            println!();
+            println!("{} rows", out.rows.len());
+        }
+
+        Commands::Rank {
+            song,
+            date,
+            band,
+            all_bands,
+        } => {
+            use crate::db::columns::{BAND_EXPR, SCORE_COLUMNS};
+
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let found = db
+                .find_track_id(&song, date.as_deref(), band.as_deref())
+                .context("Search failed")?;
+            let (track_id, title, track_date) = match found {
+                Some(t) => t,
+                None => {
+                    println!("No analyzed track matching \"{}\".", song);
+                    return Ok(());
+                }
+            };
+
+            // Rank within the track's own band unless asked for the whole library
+            let scope_band: Option<String> = if all_bands {
+                None
+            } else {
+                Some(db.conn.query_row(
+                    &format!("SELECT {BAND_EXPR} FROM tracks t WHERE t.id = ?1"),
+                    rusqlite::params![track_id],
+                    |row| row.get(0),
+                )?)
+            };
+
+            match scope_band.as_deref() {
+                Some(b) if !b.is_empty() => println!(
+                    "Percentile ranks for \"{}\" ({}) among {} tracks",
+                    title, track_date, b
+                ),
+                _ => println!("Percentile ranks for \"{}\" ({})", title, track_date),
+            }
+            println!();
+
+            // Get this track's scores and compute percentiles
+            let sql = format!(
+                "SELECT {} FROM analysis_results WHERE track_id = ?1",
+                SCORE_COLUMNS.join(", ")
+            );
+            let scores: Vec<f64> = self::rusqlite_row_to_f64_vec(&db, &sql, track_id)?;
+
+            println!("{:<16} {:>6} {:>8} {:>8}", "Score", "Value", "Pctl", "Rank");
+            println!("{}", "-".repeat(42));
+
+            for (i, meta) in crate::scores::SCORES.iter().enumerate() {
+                let col = meta.column;
+                let label = meta.label;
+                let val = scores[i];
+
+                // Count tracks below this score, and total with non-null scores
+                let pctl_sql = format!(
+                    "SELECT
+                        COUNT(CASE WHEN a.{col} < ?1 THEN 1 END),
+                        COUNT(a.{col})
+                     FROM analysis_results a
+                     JOIN tracks t ON t.id = a.track_id
+                     WHERE {NOT_GARBAGE}
+                       AND (?2 IS NULL OR {BAND_EXPR} = ?2)",
+                    col = col,
+                    NOT_GARBAGE = crate::db::columns::NOT_GARBAGE,
+                );
+                let (below, total): (i64, i64) =
+                    db.conn
+                        .query_row(&pctl_sql, rusqlite::params![val, scope_band], |row| {
+                            Ok((row.get(0)?, row.get(1)?))
+                        })?;
+
+                let pctl = if total > 0 {
+                    100.0 * below as f64 / total as f64
+                } else {
+                    0.0
+                };
+                let rank_from_top = total - below;
+
+                println!(
+                    "{:<16} {:>6.0} {:>7.1}% {:>5}/{:<5}",
+                    label, val, pctl, rank_from_top, total
+                );
+            }
+
+            // Also show duration
+            let dur: f64 = db.conn.query_row(
+                "SELECT COALESCE(duration, 0) / 60.0 FROM analysis_results WHERE track_id = ?1",
+                rusqlite::params![track_id],
+                |row| row.get(0),
+            )?;
+            println!();
+            println!("Duration: {:.1} min", dur);
+        }
+
+        Commands::Dist {
+            score,
+            bins,
+            song,
+            band,
+            live_only,
+            min_duration,
+        } => {
+            use crate::db::columns::BAND_EXPR;
+
+            let col = score.column();
+            let min_dur_secs = min_duration.map(|m| m * 60.0);
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+
+            // Build WHERE clause (?1 is the optional band)
+            let mut where_parts = vec![
+                format!("a.{col} IS NOT NULL"),
+                crate::db::columns::NOT_GARBAGE.to_string(),
+                format!("(?1 IS NULL OR LOWER({BAND_EXPR}) = LOWER(?1))"),
+            ];
+            if live_only {
+                where_parts.push(crate::db::columns::LIVE_ONLY.to_string());
+            }
+            if let Some(dur) = min_dur_secs {
+                where_parts.push(format!("a.duration >= {dur}"));
+            }
+            let where_clause = where_parts.join(" AND ");
+
+            // Get min, max, and all values
+            let stats_sql = format!(
+                "SELECT MIN(a.{col}), MAX(a.{col}), COUNT(*), AVG(a.{col}),
+                        ROUND(AVG(a.{col} * a.{col}) - AVG(a.{col}) * AVG(a.{col}), 2)
+                 FROM analysis_results a
+                 JOIN tracks t ON t.id = a.track_id
+                 WHERE {where_clause}"
+            );
+            let (min_val, max_val, total, mean, variance): (f64, f64, i64, f64, f64) = db
+                .conn
+                .query_row(&stats_sql, rusqlite::params![band], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                })?;
+
+            let std_dev = variance.max(0.0).sqrt();
+
+            match band.as_deref() {
+                Some(b) => println!("Distribution: {} ({} {} tracks)", score.label(), total, b),
+                None => println!("Distribution: {} ({} tracks)", score.label(), total),
+            }
+            println!(
+                "  min={:.1}  mean={:.1}  std={:.1}  max={:.1}",
+                min_val, mean, std_dev, max_val
+            );
+            println!();
+
+            // Build histogram buckets
+            let range = max_val - min_val;
+            if range <= 0.0 || total == 0 {
+                println!("No variation in data.");
+                return Ok(());
+            }
+            let bin_width = range / bins as f64;
+
+            let hist_sql = format!(
+                "SELECT CAST((a.{col} - ?2) / ?3 AS INTEGER) as bucket, COUNT(*)
+                 FROM analysis_results a
+                 JOIN tracks t ON t.id = a.track_id
+                 WHERE {where_clause}
+                 GROUP BY bucket
+                 ORDER BY bucket"
+            );
+
+            let mut stmt = db.conn.prepare(&hist_sql)?;
+            let mut bucket_counts = vec![0i64; bins];
+            let rows = stmt.query_map(rusqlite::params![band, min_val, bin_width], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+            })?;
+            for r in rows {
+                let (bucket, count) = r?;
+                let idx = (bucket as usize).min(bins - 1);
+                bucket_counts[idx] += count;
+            }
+
+            let max_count = *bucket_counts.iter().max().unwrap_or(&1);
+            let bar_max = 50;
+
+            // If song filter, find where it falls
+            let song_bucket: Option<usize> = if let Some(ref s) = song {
+                let song_sql = format!(
+                    "SELECT a.{col}
+                     FROM analysis_results a
+                     JOIN tracks t ON t.id = a.track_id
+                     WHERE (t.parsed_title LIKE ?1 OR t.title LIKE ?1)
+                       AND (?2 IS NULL OR LOWER({BAND_EXPR}) = LOWER(?2))
+                       AND a.{col} IS NOT NULL
+                     ORDER BY a.{col} DESC LIMIT 1"
+                );
+                let pattern = format!("%{s}%");
+                let val: Option<f64> = db
+                    .conn
+                    .query_row(&song_sql, rusqlite::params![pattern, band], |row| {
+                        row.get(0)
+                    })
+                    .ok();
+                val.map(|v| ((v - min_val) / bin_width).floor() as usize)
+                    .map(|b| b.min(bins - 1))
+            } else {
+                None
+            };
+
+            for (i, &count) in bucket_counts.iter().enumerate() {
+                let lo = min_val + i as f64 * bin_width;
+                let hi = lo + bin_width;
+                let bar_len = if max_count > 0 {
+                    (count as f64 / max_count as f64 * bar_max as f64) as usize
+                } else {
+                    0
+                };
+                let marker = if song_bucket == Some(i) { "*" } else { " " };
+                let bar: String = "#".repeat(bar_len);
+                println!(
+                    "{:>5.0}-{:<5.0} {:>5} {}{} {}",
+                    lo,
+                    hi,
+                    count,
+                    marker,
+                    bar,
+                    if count > 0 {
+                        format!("{:.1}%", 100.0 * count as f64 / total as f64)
+                    } else {
+                        String::new()
+                    }
+                );
+            }
+
+            if let Some(ref s) = song {
+                println!();
+                println!("* = bucket containing \"{}\"", s);
+            }
+        }
+
+        Commands::Correlate {
+            score,
+            band,
+            live_only,
+            min_duration,
+            limit,
+        } => {
+            use crate::db::columns::{ANALYSIS_SCHEMA, BAND_EXPR};
+
+            let target_col = score.column();
+            let min_dur_secs = min_duration.map(|m| m * 60.0);
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+
+            // Build WHERE clause (?1 is the optional band)
+            let mut where_parts = vec![
+                format!("a.{target_col} IS NOT NULL"),
+                crate::db::columns::NOT_GARBAGE.to_string(),
+                format!("(?1 IS NULL OR LOWER({BAND_EXPR}) = LOWER(?1))"),
+            ];
+            if live_only {
+                where_parts.push(crate::db::columns::LIVE_ONLY.to_string());
+            }
+            if let Some(dur) = min_dur_secs {
+                where_parts.push(format!("a.duration >= {dur}"));
+            }
+            let where_clause = where_parts.join(" AND ");
+
+            // Collect numeric feature columns (skip TEXT/JSON columns and the target itself)
+            let feature_cols: Vec<&str> = ANALYSIS_SCHEMA
+                .iter()
+                .filter(|c| c.sql_type == "REAL" || c.sql_type == "INT")
+                .filter(|c| c.name != target_col)
+                .filter(|c| !c.name.ends_with("_score")) // skip other scores
+                .map(|c| c.name)
+                .collect();
+
+            println!(
+                "Correlating {} features with {}...",
+                feature_cols.len(),
+                score.label()
+            );
+            println!();
+
+            // Compute Pearson correlation for each feature
+            let mut correlations: Vec<(&str, f64, &str, &str)> = Vec::new();
+
+            for &feat in &feature_cols {
+                // SQLite Pearson r via covariance / (std_x * std_y)
+                let sql = format!(
+                    "SELECT
+                        COUNT(*),
+                        AVG(a.{feat}),
+                        AVG(a.{target_col}),
+                        AVG(a.{feat} * a.{target_col}),
+                        AVG(a.{feat} * a.{feat}),
+                        AVG(a.{target_col} * a.{target_col})
+                     FROM analysis_results a
+                     JOIN tracks t ON t.id = a.track_id
+                     WHERE {where_clause} AND a.{feat} IS NOT NULL"
+                );
+
+                let result = db.conn.query_row(&sql, rusqlite::params![band], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, f64>(1)?,
+                        row.get::<_, f64>(2)?,
+                        row.get::<_, f64>(3)?,
+                        row.get::<_, f64>(4)?,
+                        row.get::<_, f64>(5)?,
+                    ))
+                });
+
+                if let Ok((n, mean_x, mean_y, mean_xy, mean_x2, mean_y2)) = result {
+                    if n < 100 {
+                        continue;
+                    } // skip sparse features
+                    let cov = mean_xy - mean_x * mean_y;
+                    let var_x = mean_x2 - mean_x * mean_x;
+                    let var_y = mean_y2 - mean_y * mean_y;
+                    if var_x > 1e-10 && var_y > 1e-10 {
+                        let r = cov / (var_x.sqrt() * var_y.sqrt());
+                        let category = ANALYSIS_SCHEMA
+                            .iter()
+                            .find(|c| c.name == feat)
+                            .map(|c| c.category)
+                            .unwrap_or("?");
+                        let desc = ANALYSIS_SCHEMA
+                            .iter()
+                            .find(|c| c.name == feat)
+                            .map(|c| c.description)
+                            .unwrap_or("");
+                        correlations.push((feat, r, category, desc));
+                    }
+                }
+            }
+
+            // Sort by absolute correlation
+            correlations.sort_by(|a, b| b.1.abs().partial_cmp(&a.1.abs()).unwrap());
+
+            println!(
+                "{:<35} {:>6} {:<15} Description",
+                "Feature", "r", "Category"
+            );
+            println!("{}", "-".repeat(100));
+
+            for (feat, r, cat, desc) in correlations.iter().take(limit) {
+                let sign = if *r > 0.0 { "+" } else { "-" };
+                println!(
+                    "{:<35} {}{:.3} {:<15} {}",
+                    feat,
+                    sign,
+                    r.abs(),
+                    cat,
+                    if desc.len() > 35 { &desc[..35] } else { desc }
+                );
+            }
+            println!();
+            println!("{} features analyzed", correlations.len());
+        }
+
+        Commands::AuditFeatures {
+            live_only,
+            category,
+            all,
+        } => {
+            let stats = crate::feature_audit::audit_features(&db, live_only, category.as_deref())
+                .context("Feature audit failed")?;
+            let flagged = stats.iter().filter(|s| s.is_degenerate()).count();
+            let total_rows = stats.first().map(|s| s.total).unwrap_or(0);
+
+            println!(
+                "Audited {} features over {} analyzed tracks",
+                stats.len(),
+                total_rows
+            );
+            println!();
+            println!(
+                "{:<32} {:<12} {:>6} {:>8} {:>10} {:>10} {:>10} {:>6} {:>5}  Flags",
+                "Feature", "Category", "Null%", "Distinct", "Min", "Max", "StdDev", "Top%", "Modes"
+            );
+            println!("{}", "-".repeat(120));
+            for s in stats.iter().filter(|s| all || s.is_degenerate()) {
+                let null_pct = if s.total > 0 {
+                    100.0 * (s.total - s.count) as f64 / s.total as f64
+                } else {
+                    0.0
+                };
+                let flags = s
+                    .flags
+                    .iter()
+                    .map(|f| f.label())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let category: String = s.category.chars().take(12).collect();
+                println!(
+                    "{:<32} {:<12} {:>5.0}% {:>8} {:>10.4} {:>10.4} {:>10.4} {:>5.0}% {:>5}  {}",
+                    s.name,
+                    category,
+                    null_pct,
+                    s.distinct,
+                    s.min,
+                    s.max,
+                    s.variance.sqrt(),
+                    s.top_share * 100.0,
+                    s.modes,
+                    flags
+                );
+            }
+            println!();
+            println!(
+                "{} of {} features flagged as degenerate",
+                flagged,
+                stats.len()
+            );
+            if flagged > 0 {
+                println!(
+                    "Scores consuming flagged features are ranking on noise; fix the extractor or drop the input."
+                );
+            }
+        }
+
+        Commands::ExportResearch {
+            output,
+            salt,
+            titles,
+            live_only,
+        } => {
+            let options = crate::research::ResearchOptions {
+                salt: salt.unwrap_or_else(crate::research::random_salt),
+                titles,
+                live_only,
+            };
+            let summary = crate::research::export(&db, &output, &options)?;
+            println!(
+                "Wrote {} tracks from {} shows ({} features) to {}",
+                summary.tracks,
+                summary.shows,
+                summary.features,
+                output.display()
+            );
+        }
+
+        Commands::Schema {
+            grep,
+            category,
+            scores,
+            json,
+        } => {
+            use crate::db::columns::ANALYSIS_SCHEMA;
+
+            let columns: Vec<_> = ANALYSIS_SCHEMA
+                .iter()
+                .filter(|c| {
+                    if scores {
+                        return c.category == "Score";
+                    }
+                    if let Some(ref pat) = grep {
+                        let pat_lower = pat.to_lowercase();
+                        return c.name.to_lowercase().contains(&pat_lower)
+                            || c.category.to_lowercase().contains(&pat_lower)
+                            || c.description.to_lowercase().contains(&pat_lower);
+                    }
+                    if let Some(ref cat) = category {
+                        let cat_lower = cat.to_lowercase();
+                        return c.category.to_lowercase().contains(&cat_lower);
+                    }
+                    true
+                })
+                .collect();
+
+            if json {
+                // JSON output for tooling integration
+                let json_cols: Vec<serde_json::Value> = columns
+                    .iter()
+                    .map(|c| {
+                        serde_json::json!({
+                            "name": c.name,
+                            "type": c.sql_type,
+                            "category": c.category,
+                            "description": c.description,
+                        })
+                    })
+                    .collect();
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json_cols).unwrap_or_else(|_| "[]".to_string())
+                );
+            } else if columns.is_empty() {
+                println!("No matching columns found.");
+            } else {
+                let mut current_category = "";
+                for c in &columns {
+                    if c.category != current_category {
+                        if !current_category.is_empty() {
+                            println!();
+                        }
+                        println!("  {}", c.category);
+                        println!("  {}", "-".repeat(c.category.len()));
+                        current_category = c.category;
+                    }
+                    println!("  {:<40} {:<4}  {}", c.name, c.sql_type, c.description);
+                }
+                println!();
+                println!("{} columns", columns.len());
+            }
+        }
+
+        Commands::Scores {
+            action: ScoresAction::Describe { score, band, json },
+        } => {
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let metas: Vec<&ScoreMeta> = crate::scores::SCORES
+                .iter()
+                .filter(|m| score.as_ref().is_none_or(|s| s.column() == m.column))
+                .collect();
+            let mut described = Vec::new();
+            for meta in metas {
+                let dist = db
+                    .score_distribution(meta, band.as_deref())
+                    .context("Query failed")?;
+                let warnings = dist
+                    .map(|d| crate::scores::sanity_warnings(meta, &d))
+                    .unwrap_or_default();
+                let band_range = band.as_deref().and_then(|b| {
+                    crate::analyzer::jam_metrics::band_adjustments()?.range(b, meta.name)
+                });
+                described.push((meta, dist, warnings, band_range));
+            }
+
+            if json {
+                let out: Vec<serde_json::Value> = described
+                    .iter()
+                    .map(|(meta, dist, warnings, band_range)| {
+                        serde_json::json!({
+                            "score": meta,
+                            "band": band,
+                            "band_range": band_range,
+                            "distribution": dist,
+                            "warnings": warnings,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&out)?);
+                return Ok(());
+            }
+
+            if let Some(band) = &band {
+                println!("Distributions for {band} only");
+                println!();
+            }
+            for (i, (meta, dist, warnings, band_range)) in described.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                print_score_description(meta, dist.as_ref(), warnings, *band_range);
+            }
+        }
+
+        Commands::ScoreMatrix {
+            live_only,
+            min_duration,
+        } => {
+            use crate::db::columns::SCORE_COLUMNS;
+            let min_dur_secs = min_duration.map(|m| m * 60.0);
+
+            let mut where_parts = vec![
+                crate::db::columns::NOT_GARBAGE.to_string(),
+                "a.energy_score IS NOT NULL".to_string(),
+            ];
+            if live_only {
+                where_parts.push(crate::db::columns::LIVE_ONLY.to_string());
+            }
+            if let Some(dur) = min_dur_secs {
+                where_parts.push(format!("a.duration >= {dur}"));
+            }
+            let where_clause = where_parts.join(" AND ");
+
+            // Load all score vectors
+            let sql = format!(
+                "SELECT {} FROM analysis_results a JOIN tracks t ON t.id = a.track_id WHERE {}",
+                SCORE_COLUMNS.join(", "),
+                where_clause,
+            );
+            let mut stmt = db.conn.prepare(&sql)?;
+            let mut data: Vec<Vec<f64>> = vec![Vec::new(); SCORE_COLUMNS.len()];
+            let mut n = 0usize;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                for (i, col_data) in data.iter_mut().enumerate() {
+                    col_data.push(row.get::<_, f64>(i).unwrap_or(0.0));
+                }
+                n += 1;
+            }
+
+            // Compute Pearson r for each pair
+            let labels: Vec<&str> = crate::scores::SCORES.iter().map(|s| s.short).collect();
+
+            println!("Score correlation matrix ({} tracks)", n);
+            println!();
+
+            // Header
+            print!("{:>5}", "");
+            for l in &labels {
+                print!("{:>6}", l);
+            }
+            println!();
+            println!("{}", "-".repeat(5 + labels.len() * 6));
+
+            for i in 0..SCORE_COLUMNS.len() {
+                print!("{:>5}", labels[i]);
+                for j in 0..SCORE_COLUMNS.len() {
+                    if j > i {
+                        print!("{:>6}", "");
+                        continue;
+                    }
+                    let r = pearson_r(&data[i], &data[j]);
+                    print!("{:>6.2}", r);
+                }
+                println!();
+            }
+
+            println!();
+            println!("Read: row correlates with column at r value");
+        }
+
+        Commands::Stats { tempo: true, .. } => {
+            let bins = db.tempo_histogram(10.0).context("Failed to load tempos")?;
+            if bins.is_empty() {
+                println!("No analyzed tempos yet.");
+                return Ok(());
+            }
+            let max = bins
+                .iter()
+                .map(|&(_, raw, fixed)| raw.max(fixed))
+                .max()
+                .unwrap_or(1)
+                .max(1);
+            let bar = |n: i64| "#".repeat((n as f64 / max as f64 * 30.0).round() as usize);
+            println!("Tempo (BPM): raw vs corrected for half/double-time errors");
+            println!();
+            println!("{:>7}  {:<37} {:<37}", "BPM", "Raw", "Corrected");
+            for (lo, raw, fixed) in bins {
+                println!(
+                    "{:>3.0}-{:<3.0}  {:>5} {:<31} {:>5} {}",
+                    lo,
+                    lo + 10.0,
+                    raw,
+                    bar(raw),
+                    fixed,
+                    bar(fixed)
+                );
+            }
+            println!();
+            println!("Rows analyzed before correction existed get it on the next `rescore`.");
+        }
+
+        Commands::Stats {
+            performance: true,
+            limit,
+            ..
+        } => {
+            use crate::analysis_cost::{estimate_hours, realtime_factor};
+
+            let versions = db
+                .analysis_cost_by_version()
+                .context("Failed to load analysis times")?;
+            if versions.is_empty() {
+                println!(
+                    "No analysis times yet — they're recorded for every track `analyze` stores."
+                );
+                return Ok(());
+            }
+            let hours = |secs: f64| format!("{:.1}", secs / 3600.0);
+            let factor = |f: Option<f64>| f.map(|f| format!("{f:.1}x")).unwrap_or("-".into());
+
+            let tracks: i64 = versions.iter().map(|v| v.tracks).sum();
+            let audio: f64 = versions.iter().map(|v| v.audio_secs).sum();
+            let decode: f64 = versions.iter().map(|v| v.decode_secs).sum();
+            let compute: f64 = versions.iter().map(|v| v.compute_secs()).sum();
+            println!("Analysis Performance");
+            println!("====================");
+            println!("Tracks timed:     {tracks}");
+            println!("Audio analyzed:   {} hours", hours(audio));
+            println!(
+                "Compute spent:    {} worker-hours ({:.0}% decoding)",
+                hours(compute),
+                decode / compute.max(f64::EPSILON) * 100.0
+            );
+            println!(
+                "Realtime factor:  {} (audio seconds per worker-second)",
+                factor(realtime_factor(audio, compute))
+            );
+            println!();
+
+            let mut table = Table::new(vec![
+                Column::left("Version"),
+                Column::right("Tracks"),
+                Column::right("Previews"),
+                Column::right("Audio h"),
+                Column::right("Compute h"),
+                Column::right("Realtime"),
+                Column::left("First"),
+                Column::left("Last"),
+            ]);
+            for v in &versions {
+                table.push(vec![
+                    v.version.clone(),
+                    v.tracks.to_string(),
+                    v.previews.to_string(),
+                    hours(v.audio_secs),
+                    hours(v.compute_secs()),
+                    factor(v.realtime_factor()),
+                    v.first_at.clone(),
+                    v.last_at.clone(),
+                ]);
+            }
+            print!("{}", table.render(table_opts.max_width));
+            println!();
+
+            let slowest = db
+                .slowest_analyses(limit)
+                .context("Failed to load analysis times")?;
+            let mut table = Table::new(vec![
+                Column::right("Secs"),
+                Column::right("Decode"),
+                Column::right("Minutes"),
+                Column::right("Realtime"),
+                Column::left("Version"),
+                Column::left("File").flex(30),
+            ]);
+            for t in &slowest {
+                table.push(vec![
+                    format!("{:.1}", t.compute_secs()),
+                    format!("{:.1}", t.decode_secs),
+                    t.audio_secs
+                        .map(|a| format!("{:.1}", a / 60.0))
+                        .unwrap_or("-".into()),
+                    factor(
+                        t.audio_secs
+                            .and_then(|a| realtime_factor(a, t.compute_secs())),
+                    ),
+                    t.version.clone(),
+                    t.file_path.clone(),
+                ]);
+            }
+            println!("Slowest files:");
+            print!("{}", table.render(table_opts.max_width));
+
+            let (pending, pending_secs) = db
+                .analysis_backlog()
+                .context("Failed to count unanalyzed tracks")?;
+            // The latest version's rate is the best guess for the next run
+            let latest = versions.last().and_then(|v| v.realtime_factor());
+            if let (true, Some(rate)) = (pending > 0, latest) {
+                let jobs = db.last_analysis_jobs()?.unwrap_or(1);
+                println!();
+                println!(
+                    "Not analyzed yet: {pending} tracks, {} hours of audio — about {:.1} hours with {jobs} workers at {rate:.1}x",
+                    hours(pending_secs),
+                    estimate_hours(pending_secs, rate, jobs)
+                );
+            }
+        }
+
+        Commands::Stats {
+            history: true,
+            limit,
+            ..
+        } => {
+            let snapshots = db
+                .library_snapshots(limit)
+                .context("Failed to load library snapshots")?;
+            if snapshots.is_empty() {
+                println!("No snapshots yet — one is recorded after every scan and analyze run.");
+                return Ok(());
+            }
+
+            let mut table = Table::new(vec![
+                Column::left("When"),
+                Column::left("Run"),
+                Column::right("Tracks"),
+                Column::right("Change"),
+                Column::right("Shows"),
+                Column::right("Bands"),
+                Column::right("Analyzed"),
+                Column::right("Hours"),
+                Column::right("Size GB"),
+            ]);
+            let mut warnings = Vec::new();
+            for (i, s) in snapshots.iter().enumerate() {
+                let prev = i.checked_sub(1).map(|j| &snapshots[j]);
+                let change = prev
+                    .map(|p| format!("{:+}", s.tracks - p.tracks))
+                    .unwrap_or_default();
+                if let Some(w) = prev.and_then(|p| crate::snapshots::duplicate_warning(p, s)) {
+                    warnings.push(format!("{}: {w}", s.taken_at));
+                }
+                let run = match s.trigger.as_str() {
+                    "scan" => format!("scan +{}", s.run_count),
+                    other => format!("{other} {}", s.run_count),
+                };
+                table.push(vec![
+                    s.taken_at.clone(),
+                    run,
+                    s.tracks.to_string(),
+                    change,
+                    s.shows.to_string(),
+                    s.bands.to_string(),
+                    s.analyzed.to_string(),
+                    format!("{:.1}", s.hours),
+                    format!("{:.1}", s.bytes as f64 / 1e9),
+                ]);
+            }
+            print!("{}", table.render(table_opts.max_width));
+
+            if let (Some(first), Some(last)) = (snapshots.first(), snapshots.last()) {
+                println!();
+                println!(
+                    "Since {}: {:+} tracks, {:+} shows, {:+.1} hours",
+                    first.taken_at,
+                    last.tracks - first.tracks,
+                    last.shows - first.shows,
+                    last.hours - first.hours
+                );
+            }
+            for w in &warnings {
+                println!("Warning: {w}");
+            }
+        }
+
+        Commands::Stats { .. } => {
+            let stats = db.stats().context("Failed to get stats")?;
+            println!("Library Statistics");
+            println!("==================");
+            println!("Total tracks:     {}", stats.total_tracks);
+            println!("Analyzed tracks:  {}", stats.analyzed_tracks);
+            if stats.stale_analyses > 0 {
+                println!(
+                    "Stale analyses:   {} (file changed; run `setbreak analyze`)",
+                    stats.stale_analyses
+                );
+            }
+            if stats.preview_analyses > 0 {
+                println!(
+                    "Preview analyses: {} (excerpt scores; run `setbreak analyze` for full ones)",
+                    stats.preview_analyses
+                );
+            }
+            println!("Total duration:   {:.1} hours", stats.total_duration_hours);
+            println!();
+
+            if !stats.formats.is_empty() {
+                println!("Formats:");
+                for (fmt, count) in &stats.formats {
+                    println!("  {:<8} {}", fmt, count);
+                }
+                println!();
+            }
+
+            if !stats.bands.is_empty() {
+                println!("Bands:");
+                for (band, count) in &stats.bands {
+                    println!("  {:<30} {}", band, count);
+                }
+            }
+        }
+
+        Commands::ScoreLab {
+            formula,
+            list,
+            limit,
+            min_duration,
+            live_only,
+            bottom,
+        } => {
+            if list {
+                let vars = crate::score_lab::list_variables();
+                let mut current_category = "";
+                for (name, cat, desc) in &vars {
+                    if *cat != current_category {
+                        if !current_category.is_empty() {
+                            println!();
+                        }
+                        println!("  {cat}");
+                        println!("  {}", "-".repeat(cat.len()));
+                        current_category = cat;
+                    }
+                    println!("  {:<40}  {}", name, desc);
+                }
+                println!();
+                println!("{} variables available", vars.len());
+                println!();
+                println!(
+                    "Usage: setbreak score-lab \"rms_level / 0.18 * 30 + (lufs_integrated + 55) / 22 * 30\""
+                );
+                println!("Operators: + - * / ^ %  |  Functions: min, max, floor, ceil, sqrt, abs");
+                println!("Comparisons: < > <= >= == != && ||  |  Ternary: if(cond, then, else)");
+            } else if let Some(expr) = formula {
+                let min_dur_secs = min_duration.map(|m| m * 60.0);
+                match crate::score_lab::evaluate_formula(&db, &expr, limit, min_dur_secs, live_only)
+                {
+                    Ok(mut results) => {
+                        if bottom {
+                            results.reverse();
+                        }
+                        if results.is_empty() {
+                            println!("No results (expression returned no finite values).");
+                        } else {
+                            let label = if bottom { "Bottom" } else { "Top" };
+                            println!("{label} {} by: {}", results.len(), expr);
+                            println!();
+                            println!(
+                                "{:>4}  {:<40} {:>10} {:>6.1}  {:>10}",
+                                "#", "Title", "Date", "Min", "Score"
+                            );
+                            println!("{}", "-".repeat(80));
+                            for (i, r) in results.iter().enumerate() {
+                                let title_display = if r.title.len() > 38 {
+                                    format!("{}...", &r.title[..35])
+                                } else {
+                                    r.title.clone()
+                                };
+                                println!(
+                                    "{:>4}  {:<40} {:>10} {:>6.1}  {:>10.2}",
+                                    i + 1,
+                                    title_display,
+                                    r.date,
+                                    r.duration_min,
+                                    r.computed_score
+                                );
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {e}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+
+        Commands::HarmonicMatch {
+            song,
+            date,
+            band,
+            all_bands,
+            limit,
+            same_key,
+            tempo_tolerance,
+        } => {
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let track_id = match db.find_track_id(&song, date.as_deref(), band.as_deref())? {
+                Some((id, title, date)) => {
+                    println!("Harmonic matches for: {} ({})", title, date);
+                    id
+                }
+                None => {
+                    println!("No analyzed track matching \"{}\".", song);
+                    return Ok(());
+                }
+            };
+
+            match crate::chroma::find_harmonic_matches(
+                &db,
+                track_id,
+                limit,
+                !same_key,
+                tempo_tolerance,
+                !all_bands,
+            ) {
+                Ok((target, matches)) => {
+                    if matches.is_empty() {
+                        println!("No tracks with chroma data found.");
+                        return Ok(());
+                    }
+
+                    let mode = if same_key {
+                        "same key only"
+                    } else {
+                        "transposition-aware"
+                    };
+                    let bpm = |t: Option<f64>| t.map(|b| format!("{b:.0}")).unwrap_or_default();
+                    println!(
+                        "Mode: {mode}  |  Target key: {}  |  Tempo: {} BPM",
+                        target.key,
+                        bpm(target.tempo)
+                    );
+                    println!();
+                    println!(
+                        "{:>4}  {:<35} {:>10} {:>8} {:>5} {:>5} {:>6}",
+                        "#", "Title", "Date", "Key", "BPM", "Dist", "Shift"
+                    );
+                    println!("{}", "-".repeat(80));
+
+                    for (i, m) in matches.iter().enumerate() {
+                        let title_display = if m.title.len() > 33 {
+                            format!("{}...", &m.title[..30])
+                        } else {
+                            m.title.clone()
+                        };
+                        let shift_display = if m.transposition == 0 {
+                            "=".to_string()
+                        } else {
+                            format!("+{}", m.transposition)
+                        };
+                        println!(
+                            "{:>4}  {:<35} {:>10} {:>8} {:>5} {:>5.3} {:>6}",
+                            i + 1,
+                            title_display,
+                            m.date,
+                            m.key,
+                            bpm(m.tempo),
+                            m.distance,
+                            shift_display
+                        );
+                    }
+                    println!();
+                    println!(
+                        "Dist=cosine distance (0=identical harmony), Shift=semitones transposed"
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::PruneSuggest {
+            target_free,
+            band,
+            output,
+        } => {
+            let band = band.map(|b| crate::bands::registry().resolve_canonical_name(&b));
+            let candidates = db
+                .prune_candidates(band.as_deref())
+                .context("Query failed")?;
+            let plan = crate::prune_suggest::plan(candidates, target_free);
+            match &output {
+                Some(path) => {
+                    let file = std::fs::File::create(path)
+                        .with_context(|| format!("Failed to create {}", path.display()))?;
+                    crate::prune_suggest::write_script(&plan, std::io::BufWriter::new(file))?;
+                    note!("Wrote {}", path.display());
+                }
+                None => crate::prune_suggest::write_script(&plan, std::io::stdout().lock())?,
+            }
+            let size = crate::prune_suggest::format_size;
+            note!(
+                "Suggested {} files ({}) of {} in candidates.",
+                plan.files(),
+                size(plan.bytes),
+                size(plan.available)
+            );
+            if !plan.reaches_target() {
+                note!(
+                    "Short of the {} target: nothing else is low-value enough to suggest.",
+                    size(target_free.unwrap_or_default())
+                );
+            }
+        }
+
+        Commands::Db { action } => match action {
+            DbAction::PruneDetails {
+                keep_aggregates,
+                dry_run,
+            } => {
+                if dry_run {
+                    println!("DRY RUN — no changes will be written to the database");
+                }
+                let report = db
+                    .prune_details(keep_aggregates, dry_run)
+                    .context("Prune failed")?;
+
+                println!(
+                    "{} garbage-flagged tracks, {} tracks with missing files",
+                    report.garbage_tracks, report.orphaned_tracks
+                );
+                println!();
+                for (table, n) in &report.rows_deleted {
+                    println!("  {:<22} {:>10} rows", table, n);
+                }
+                println!();
+                let verb = if dry_run { "Would delete" } else { "Deleted" };
+                println!("{} {} rows.", verb, report.total_rows());
+
+                let mb = |bytes: i64| bytes as f64 / (1024.0 * 1024.0);
+                match report.bytes_after {
+                    Some(after) => println!(
+                        "Database size: {:.1} MB -> {:.1} MB ({:.1} MB reclaimed)",
+                        mb(report.bytes_before),
+                        mb(after),
+                        mb(report.bytes_before - after)
+                    ),
+                    None => println!("Database size: {:.1} MB", mb(report.bytes_before)),
+                }
+            }
+            DbAction::Artifacts => {
+                let path = db
+                    .attach_artifacts()
+                    .context("Failed to open the artifact sidecar")?;
+                if let Some(path) = &path {
+                    println!("Sidecar: {}", path.display());
+                }
+                let usage = db.artifact_usage().context("Query failed")?;
+                if usage.is_empty() {
+                    println!("No raw artifacts stored.");
+                    if config.analysis.keep_artifacts.is_empty() {
+                        println!(
+                            "Set `keep_artifacts = [\"chromagram\", \"onset_envelope\", \"loudness\"]` under [analysis] to keep them on the next analyze."
+                        );
+                    }
+                    return Ok(());
+                }
+                let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+                let mut table = Table::new(vec![
+                    Column::left("Kind"),
+                    Column::right("Tracks"),
+                    Column::right("Raw MB"),
+                    Column::right("Stored MB"),
+                    Column::right("Ratio"),
+                ]);
+                for u in &usage {
+                    table.push(vec![
+                        u.kind.clone(),
+                        u.tracks.to_string(),
+                        format!("{:.1}", mb(u.raw_bytes)),
+                        format!("{:.1}", mb(u.stored_bytes)),
+                        format!("{:.1}x", u.raw_bytes as f64 / u.stored_bytes.max(1) as f64),
+                    ]);
+                }
+                print!("{}", table.render(table_opts.max_width));
+                let stored: u64 = usage.iter().map(|u| u.stored_bytes).sum();
+                match config.analysis.artifact_budget_mb {
+                    0 => println!("Total: {:.1} MB (no budget)", mb(stored)),
+                    budget => println!("Total: {:.1} MB of a {budget} MB budget", mb(stored)),
+                }
+            }
+            DbAction::PruneArtifacts { budget_mb, kind } => {
+                db.attach_artifacts()
+                    .context("Failed to open the artifact sidecar")?;
+                let budget = budget_mb
+                    .or(Some(config.analysis.artifact_budget_mb).filter(|&mb| mb > 0))
+                    .map(|mb| mb << 20);
+                let summary = db
+                    .prune_artifacts(budget, kind.map(Into::into))
+                    .context("Prune failed")?;
+                println!(
+                    "Removed {} artifacts of deleted tracks and {} more; {:.1} MB freed.",
+                    summary.orphaned,
+                    summary.evicted,
+                    summary.freed_bytes as f64 / (1024.0 * 1024.0)
+                );
+            }
+        },
+
+        Commands::Config { action } => match action {
+            ConfigAction::Show { effective, dirs } => {
+                if effective {
+                    let dirs = if dirs.is_empty() {
+                        config.music_dirs.clone()
+                    } else {
+                        dirs
+                    };
+                    print_effective_config(&config, &db_path, db_origin, &dirs);
+                } else {
+                    match crate::config::AppConfig::config_path() {
+                        Some(path) if path.exists() => {
+                            let contents = std::fs::read_to_string(&path)
+                                .with_context(|| format!("Failed to read {}", path.display()))?;
+                            println!("# {}", path.display());
+                            print!("{}", contents);
+                        }
+                        Some(path) => {
+                            println!("No config file at {} (using defaults).", path.display())
+                        }
+                        None => println!("Could not determine the config directory."),
+                    }
+                }
+            }
+        },
+
+        Commands::Auth { action } => {
+            let mut store = crate::credentials::CredentialStore::load()?;
+            match action {
+                AuthAction::Set { service, keyring } => {
+                    let secret = if std::io::stdin().is_terminal() {
+                        rpassword::prompt_password(format!("Secret for {}: ", service))
+                            .context("Failed to read secret")?
+                    } else {
+                        let mut line = String::new();
+                        std::io::stdin()
+                            .read_line(&mut line)
+                            .context("Failed to read secret from stdin")?;
+                        line
+                    };
+                    let secret = secret.trim();
+                    if secret.is_empty() {
+                        anyhow::bail!("Empty secret; nothing stored");
+                    }
+
+                    store.set(&service, secret, keyring)?;
+                    if keyring {
+                        println!("Stored {} secret in the OS keyring.", service);
+                    } else {
+                        println!("Stored {} secret in {}.", service, store.path().display());
+                    }
+                }
+
+                AuthAction::List => {
+                    let services: Vec<_> = store.services().collect();
+                    if services.is_empty() {
+                        println!(
+                            "No stored credentials. Add one with: setbreak auth set <service>"
+                        );
+                        return Ok(());
+                    }
+                    println!("{:<20} {:<10} Override", "Service", "Storage");
+                    println!("{}", "-".repeat(60));
+                    for (service, entry) in services {
+                        let storage = match entry {
+                            crate::credentials::CredentialEntry::Plain { .. } => "file",
+                            crate::credentials::CredentialEntry::Keyring => "keyring",
+                        };
+                        println!(
+                            "{:<20} {:<10} {}",
+                            service,
+                            storage,
+                            crate::credentials::env_var_name(service)
+                        );
+                    }
+                }
+
+                AuthAction::Remove { service } => {
+                    if store.remove(&service)? {
+                        println!("Removed {} secret.", service);
+                    } else {
+                        println!("No stored secret for {}.", service);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Pearson correlation coefficient between two equal-length f64 slices.
+fn pearson_r(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    if n < 2.0 {
+        return 0.0;
+    }
+    let sum_x: f64 = x.iter().sum();
+    let sum_y: f64 = y.iter().sum();
+    let sum_xy: f64 = x.iter().zip(y.iter()).map(|(a, b)| a * b).sum();
+    let sum_x2: f64 = x.iter().map(|a| a * a).sum();
+    let sum_y2: f64 = y.iter().map(|a| a * a).sum();
+    let num = n * sum_xy - sum_x * sum_y;
+    let den = ((n * sum_x2 - sum_x * sum_x) * (n * sum_y2 - sum_y * sum_y)).sqrt();
+    if den < 1e-10 { 0.0 } else { num / den }
+}
+
+/// Extract a row of f64 values from a single-row query.
+fn rusqlite_row_to_f64_vec(db: &crate::db::Database, sql: &str, track_id: i64) -> Result<Vec<f64>> {
+    let mut stmt = db.conn.prepare(sql)?;
+    let col_count = stmt.column_count();
+    let row = stmt.query_row(rusqlite::params![track_id], |row| {
+        let mut vals = Vec::with_capacity(col_count);
+        for i in 0..col_count {
+            vals.push(row.get::<_, f64>(i).unwrap_or(0.0));
+        }
+        Ok(vals)
+    })?;
+    Ok(row)
+}
+
+/// One `scores describe` entry: registry text plus the live distribution.
+fn print_score_description(
+    meta: &ScoreMeta,
+    dist: Option<&crate::scores::Distribution>,
+    warnings: &[String],
+    band_range: Option<(f64, f64)>,
+) {
+    use crate::scores::Direction;
+
+    println!("{} ({}, {})", meta.label, meta.name, meta.short);
+    println!("  {}", meta.description);
+    let scale = match (meta.direction, meta.poles) {
+        (Direction::Bipolar, Some((low, high))) => format!("0 = {low}, 100 = {high}"),
+        (Direction::HigherIsBetter, _) => "0-100, higher is better".to_string(),
+        _ => "0-100, descriptive (neither end is better)".to_string(),
+    };
+    println!("  Scale:    {scale}");
+    println!("  Inputs:   {}", meta.inputs.join(", "));
+    let (lo, hi) = meta.healthy_range;
+    match dist {
+        Some(d) => println!(
+            "  Library:  min {:.1}  median {:.1}  max {:.1}  ({} tracks; 90% in {:.0}-{:.0}, expected {lo:.0}-{hi:.0})",
+            d.min, d.median, d.max, d.tracks, d.p5, d.p95
+        ),
+        None => println!("  Library:  no scored tracks (expected 90% in {lo:.0}-{hi:.0})"),
+    }
+    if let Some((raw_lo, raw_hi)) = band_range {
+        println!(
+            "  Band:     raw {raw_lo:.0}-{raw_hi:.0} stretched to {lo:.0}-{hi:.0} (values above are adjusted)"
+        );
+    }
+    for caveat in meta.caveats {
+        println!("  Caveat:   {caveat}");
+    }
+    for warning in warnings {
+        println!("  WARNING:  {warning}");
+    }
+}
+
+/// Header for the column placing each track (`TrackScore::when`): "Date",
+/// "Album" when every track is a studio one, "Date/Album" for a mix.
+fn when_header<'a>(tracks: impl IntoIterator<Item = &'a TrackScore>) -> &'static str {
+    let (mut albums, mut dates) = (false, false);
+    for t in tracks {
+        match t.album {
+            Some(_) => albums = true,
+            None => dates = true,
+        }
+    }
+    match (albums, dates) {
+        (true, false) => "Album",
+        (true, true) => "Date/Album",
+        _ => "Date",
+    }
+}
+
+/// Print a table of track scores with the sort column highlighted.
+fn print_score_table(tracks: &[TrackScore], highlight: Option<&ScoreName>, opts: &TableOptions) {
+    let scores = opts.score_columns(&[
+        "groove",
+        "improvisation",
+        "energy",
+        "intensity",
+        "tightness",
+        "build_quality",
+        "exploratory",
+        "transcendence",
+    ]);
+    let mut columns: Vec<Column> = opts.rank_column().into_iter().collect();
+    columns.extend([
+        Column::left("Song").flex(15),
+        Column::right(when_header(tracks)),
+        Column::right("Min"),
+    ]);
+    columns.extend(score_headers(&scores));
+    let mut table = Table::new(columns);
+    for (i, t) in tracks.iter().enumerate() {
+        let mut row: Vec<String> = opts.rank_cell(i).into_iter().collect();
+        row.extend([
+            t.title.clone(),
+            t.when().to_string(),
+            format!("{:.1}", t.duration_min),
+        ]);
+        row.extend(score_cells(&scores, |col| t.score(col)));
+        table.push(row);
+    }
+    print!("{}", table.render(opts.max_width));
+
+    // Legend
+    println!();
+    println!("{}", crate::scores::legend(&scores, 4));
+
+    if let Some(hl) = highlight {
+        println!("Sorted by: {}", hl.label());
+    }
+}
+
+/// Per-score summary and biggest movers from a before/after score comparison.
+fn print_score_deltas(deltas: &[crate::score_deltas::ScoreDeltas], max_width: Option<usize>) {
+    for d in deltas {
+        println!();
+        println!(
+            "{}: {} of {} tracks moved, mean shift {:+.1} (mean |shift| {:.1})",
+            d.score.label, d.changed, d.tracks, d.mean_shift, d.mean_abs_shift
+        );
+        if d.movers.is_empty() {
+            continue;
+        }
+        let mut table = Table::new(vec![
+            Column::left("Song").flex(12),
+            Column::left("Date"),
+            Column::right("Old"),
+            Column::right("New"),
+            Column::right("Delta"),
+        ]);
+        for m in &d.movers {
+            table.push(vec![
+                m.title.clone(),
+                m.date.clone(),
+                format!("{:.1}", m.old),
+                format!("{:.1}", m.new),
+                format!("{:+.1}", m.delta()),
+            ]);
+        }
+        print!("{}", table.render(max_width));
+    }
+}
+
+/// Print `segue-suggest` results: the seam's key and tempo in playing order, and
+/// how smooth it is.
+fn print_segue_suggestions(
+    suggestions: &[crate::segue_match::SegueSuggestion],
+    opts: &TableOptions,
+) {
+    let mut table = Table::new(vec![
+        Column::left("Song").flex(15),
+        Column::right(when_header(suggestions.iter().map(|s| &s.track))),
+        Column::right("Min"),
+        Column::right("Key").gap(1),
+        Column::right("BPM"),
+        Column::right("Fit").gap(1),
+    ]);
+    let bpm = |t: Option<f64>| t.map_or("?".to_string(), |t| format!("{t:.0}"));
+    for s in suggestions {
+        table.push(vec![
+            s.track.title.clone(),
+            s.track.when().to_string(),
+            format!("{:.1}", s.track.duration_min),
+            format!("{}>{}", s.keys.0, s.keys.1),
+            format!("{}>{}", bpm(s.tempos.0), bpm(s.tempos.1)),
+            format!("{:.0}", s.fit.score()),
+        ]);
+    }
+    print!("{}", table.render(opts.max_width));
+    println!();
+    println!(
+        "Key/BPM: end of the earlier song > start of the later one. Fit: 0-100, smoother seam higher."
+    );
+}
+
+/// Print a prompt and read one trimmed, lowercased line from stdin.
+fn prompt_line(prompt: &str) -> Result<String> {
+    use std::io::Write;
+    print!("{prompt}");
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .context("Failed to read answer")?;
+    Ok(line.trim().to_lowercase())
+}
+
+/// Print everything stored about the track at `file_path`: metadata, source
+/// and every analysis feature, grouped by category.
+fn print_track_detail(db: &crate::db::Database, file_path: &str) -> Result<()> {
+    let Some(d) = db.track_detail(file_path).context("Query failed")? else {
+        println!("{file_path} is no longer in the database.");
+        return Ok(());
+    };
+    println!();
+    println!("{} (track {})", d.title, d.track_id);
+    let source = [
+        d.source_type.clone(),
+        d.taper.as_ref().map(|t| format!("taper {t}")),
+        d.lineage_hint.as_ref().map(|l| format!("lineage {l}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(", ");
+    let fields = [
+        ("File", Some(d.file_path.clone())),
+        ("Format", Some(d.format.clone())),
+        ("Band", d.band.clone()),
+        ("Date", d.date.clone()),
+        ("Venue", d.venue.clone()),
+        ("Set", d.set.clone()),
+        ("Album", d.album.clone()),
+        ("Type", d.recording_type.clone()),
+        ("Quality", d.data_quality.clone()),
+        ("Source", (!source.is_empty()).then_some(source)),
+        ("Analyzed", d.analyzed_at.clone()),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            println!("  {:<10} {}", format!("{label}:"), value);
+        }
+    }
+    if d.features.is_empty() {
+        println!("  (not analyzed)");
+        return Ok(());
+    }
+    let mut category = "";
+    for (column, value) in &d.features {
+        if column.category != category {
+            category = column.category;
+            println!();
+            println!("  {category}");
+        }
+        println!("    {:<36} {}", column.name, value);
+    }
+    Ok(())
+}
+
+/// Split tracks into per-band groups, keeping the input order within and across groups
+/// (the band of the best-ranked track comes first).
+fn group_by_band(tracks: Vec<TrackScore>) -> Vec<(Option<String>, Vec<TrackScore>)> {
+    let mut groups: Vec<(Option<String>, Vec<TrackScore>)> = Vec::new();
+    for t in tracks {
+        match groups.iter_mut().find(|(b, _)| *b == t.band) {
+            Some((_, group)) => group.push(t),
+            None => groups.push((t.band.clone(), vec![t])),
+        }
+    }
+    for (band, _) in &mut groups {
+        if band.is_none() {
+            *band = Some("Unknown band".to_string());
+        }
+    }
+    groups
+}
+
+/// Print tracks with a cosine-distance column (for `similar` and `profile match`).
+fn print_distance_table(results: &[(TrackScore, f64)], opts: &TableOptions) {
+    let scores = opts.score_columns(&[
+        "groove",
+        "improvisation",
+        "energy",
+        "intensity",
+        "build_quality",
+        "exploratory",
+    ]);
+    let mut columns = vec![
+        Column::left("Song").flex(15),
+        Column::right(when_header(results.iter().map(|(t, _)| t))),
+        Column::right("Min"),
+        Column::right("Dist"),
+    ];
+    columns.extend(score_headers(&scores));
+    let mut table = Table::new(columns);
+    for (t, dist) in results {
+        let mut row = vec![
+            t.title.clone(),
+            t.when().to_string(),
+            format!("{:.1}", t.duration_min),
+            format!("{:.3}", dist),
+        ];
+        row.extend(score_cells(&scores, |col| t.score(col)));
+        table.push(row);
+    }
+    print!("{}", table.render(opts.max_width));
+
+    println!();
+    println!("Dist = cosine distance (0 = identical, lower = more similar)");
+}
+
+/// Print how each feature group contributes to the distances in a `similar` listing.
+fn print_similarity_explanations(
+    explainer: &crate::similarity::Explainer,
+    seeds: &[i64],
+    results: &[(TrackScore, f64, i64)],
+    opts: &TableOptions,
+) {
+    use crate::similarity::FeatureGroup;
+
+    let mut columns = vec![
+        Column::left("Song").flex(15),
+        Column::right(when_header(results.iter().map(|(t, _, _)| t))),
+    ];
+    columns.extend(FeatureGroup::ALL.iter().map(|g| Column::right(g.short())));
+    columns.push(Column::left("Top features").gap(1).flex(12));
+    let mut table = Table::new(columns);
+    for (t, _, other_id) in results {
+        let Some(e) = explainer.explain_seeds(seeds, *other_id, 3) else {
+            continue;
+        };
+        let mut row = vec![t.title.clone(), t.when().to_string()];
+        row.extend(
+            FeatureGroup::ALL
+                .iter()
+                .map(|&g| format!("{:.0}%", e.share(g) * 100.0)),
+        );
+        row.push(
+            e.top_features
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        table.push(row);
+    }
+
+    println!();
+    println!("Share of each distance by feature group:");
+    println!();
+    print!("{}", table.render(opts.max_width));
+    println!();
+    let legend: Vec<String> = FeatureGroup::ALL
+        .iter()
+        .map(|g| format!("{}={}", g.short(), g.label()))
+        .collect();
+    println!("{}", legend.join("  "));
+}
+
+/// Print archive.org provenance notes for one source of a show.
+fn print_show_notes(n: &crate::db::models::ShowNotes) {
+    println!("=== {} ===", n.identifier);
+    let fields = [
+        ("Venue", &n.venue),
+        ("Source", &n.source),
+        ("Lineage", &n.lineage),
+        ("Taper", &n.taper),
+        ("Transfer", &n.transferer),
+    ];
+    for (label, value) in fields {
+        if let Some(v) = value {
+            println!(
+                "{:<9} {}",
+                format!("{}:", label),
+                v.replace('\n', "\n          ")
+            );
+        }
+    }
+    for text in [&n.description, &n.notes].into_iter().flatten() {
+        println!();
+        println!("{}", text);
+    }
+    println!();
+}
+
+/// Print a table of segue chains.
+fn print_chain_table(chains: &[ChainScore], sort: &ScoreName, opts: &TableOptions) {
+    let scores = opts.score_columns(&["transcendence", "improvisation", "energy", "exploratory"]);
+    let mut columns = vec![
+        Column::left("Chain").flex(20),
+        Column::right("Date"),
+        Column::right("Len"),
+        Column::right("Min"),
+    ];
+    columns.extend(score_headers(&scores));
+    let mut table = Table::new(columns);
+    for c in chains {
+        let mut row = vec![
+            c.chain_title(),
+            c.date.clone(),
+            c.chain_length.to_string(),
+            format!("{:.1}", c.duration_min),
+        ];
+        row.extend(score_cells(&scores, |col| c.score(col)));
+        table.push(row);
+    }
+    print!("{}", table.render(opts.max_width));
+
+    println!();
+    println!("{}", crate::scores::legend(&scores, 4));
+    println!("Sorted by: {}", sort.label());
+}
+
+/// Print where a song sits in each chain it appears in, plus a role summary.
+fn print_chain_memberships(
+    song: &str,
+    memberships: &[crate::chains::ChainMembership],
+    show_context: bool,
+    opts: &TableOptions,
+) {
+    let mut columns = vec![
+        Column::right("Date"),
+        Column::right("Pos"),
+        Column::left("Role"),
+    ];
+    if show_context {
+        columns.push(Column::left("From").flex(10));
+        columns.push(Column::left("Into").flex(10));
+    } else {
+        columns.push(Column::left("Chain").flex(20));
+    }
+    columns.extend([Column::right("Min"), Column::right("Trn")]);
+    let mut table = Table::new(columns);
+    for m in memberships {
+        let mut row = vec![
+            m.date.clone(),
+            format!("{}/{}", m.position + 1, m.songs.len()),
+            m.role().to_string(),
+        ];
+        if show_context {
+            row.push(m.previous().unwrap_or("-").to_string());
+            row.push(m.next().unwrap_or("-").to_string());
+        } else {
+            row.push(m.songs.join(" -> "));
+        }
+        row.push(format!("{:.1}", m.duration_min));
+        row.push(format!("{:.0}", m.transcendence));
+        table.push(row);
+    }
+
+    println!("Segue chains containing \"{}\":", song);
+    println!();
+    print!("{}", table.render(opts.max_width));
+    println!();
+    let count = |role: &str| memberships.iter().filter(|m| m.role() == role).count();
+    println!(
+        "{} chains: launches {}, bridges {}, lands {}",
+        memberships.len(),
+        count("launch"),
+        count("bridge"),
+        count("landing")
+    );
+}
+
+/// Print tracks with their play counts and one score.
+fn print_play_table(rows: &[(TrackScore, i64)], score: &ScoreName, opts: &TableOptions) {
+    let mut table = Table::new(vec![
+        Column::left("Song").flex(20),
+        Column::right("Date"),
+        Column::right("Min"),
+        Column::right("Plays"),
+        Column::right(score.label()),
+    ]);
+    for (t, plays) in rows {
+        table.push(vec![
+            t.title.clone(),
+            t.date.clone(),
+            format!("{:.1}", t.duration_min),
+            plays.to_string(),
+            t.score(score.column())
+                .map(|v| format!("{:.0}", v))
+                .unwrap_or_default(),
+        ]);
+    }
+    print!("{}", table.render(opts.max_width));
+}
+
+/// Print low-confidence setlist title matches awaiting review.
+fn print_pending_title_matches(pending: &[crate::setlist::PendingTitleMatch], opts: &TableOptions) {
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("File").flex(30),
+        Column::left("Title").flex(20),
+        Column::left("Method"),
+        Column::right("Conf"),
+    ]);
+    for p in pending {
+        let file = std::path::Path::new(&p.file_path)
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_else(|| p.file_path.clone());
+        table.push(vec![
+            p.track_id.to_string(),
+            file,
+            p.title.clone(),
+            p.method.clone(),
+            format!("{:.2}", p.confidence),
+        ]);
+    }
+    print!("{}", table.render(opts.max_width));
+}
+
+/// Print a table of missing shows from archive.org.
+fn print_missing_shows(shows: &[crate::db::models::MissingShow]) {
+    println!(
+        "{:<12} {:>6} {:>6} {:>5} {:>11}  Identifier",
+        "Date", "Source", "Format", "Tapes", "Rating"
+    );
+    println!("{}", "-".repeat(80));
+
+    for s in shows {
+        let source = match s.source_quality {
+            3 => "SBD",
+            2 => "Matrix",
+            1 => "AUD",
+            _ => "?",
+        };
+        let format = match s.format_quality {
+            3 => "FLAC",
+            2 => "SHN",
+            1 => "MP3",
+            _ => "?",
+        };
+
+        let near = match &s.near_local {
+            Some(local) => format!("  * have {local}?"),
+            None => String::new(),
+        };
+        let rating = match &s.rating {
+            Some(r) => format!("{:.1} ({})", r.stars, r.reviews),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:<12} {:>6} {:>6} {:>5} {:>11}  {}{}",
+            s.date, source, format, s.tape_count, rating, s.best_identifier, near
+        );
+    }
+}
+
+/// Format seconds as m:ss.
+fn fmt_clock(secs: f64) -> String {
+    let s = secs.round() as i64;
+    format!("{}:{:02}", s / 60, s % 60)
+}
+
+#[cfg(feature = "analysis")]
+fn print_virtual_tracks(songs: &[crate::virtual_tracks::VirtualTrack]) {
+    for s in songs {
+        let split = match s.boundary_score {
+            Some(score) if s.after_break => format!("after break ({score:.2})"),
+            Some(score) => format!("segue ({score:.2})"),
+            None => String::new(),
+        };
+        println!(
+            "  {:>3}. {:<12} {:>8} {:>7}  {}",
+            s.song_index,
+            s.display_title(),
+            fmt_clock(s.start_time),
+            fmt_clock(s.duration()),
+            split
+        );
+    }
+}
+
+/// Print a recording's retracking suggestions.
+#[cfg(feature = "analysis")]
+fn print_retrack(recording: &crate::retrack::Recording, segments: &[crate::retrack::Segment]) {
+    use crate::retrack::Operation;
+
+    let title = |id: i64| {
+        recording
+            .tracks
+            .iter()
+            .position(|t| t.track.id == id)
+            .map(|i| format!("t{:02} {}", i + 1, recording.tracks[i].display_title()))
+            .unwrap_or_default()
+    };
+    let ops = crate::retrack::operations(recording, segments);
+    if ops.is_empty() {
+        println!("  Tracking looks right.");
+        return;
+    }
+    for op in &ops {
+        match op {
+            Operation::Split {
+                track_id,
+                at,
+                score,
+                banter,
+            } => println!(
+                "  split  {} at {}{}{}",
+                title(*track_id),
+                fmt_clock(*at),
+                score
+                    .map(|s| format!("  (score {s:.2})"))
+                    .unwrap_or_default(),
+                if *banter { "  banter?" } else { "" }
+            ),
+            Operation::Merge { first, second } => {
+                println!("  merge  {} + {}", title(*first), title(*second))
+            }
+        }
+    }
+    println!(
+        "  {} tracks -> {} ({} splits, {} merges)",
+        recording.tracks.len(),
+        segments.len(),
+        ops.iter()
+            .filter(|o| matches!(o, Operation::Split { .. }))
+            .count(),
+        ops.iter()
+            .filter(|o| matches!(o, Operation::Merge { .. }))
+            .count()
+    );
+}
+
+/// Print `trace` output: each stage's timing and summary, then the changes
+/// against the stored analysis.
+/// Resolve a track given by id or by a unique path suffix.
+fn resolve_track(db: &crate::db::Database, track: &str) -> Result<i64> {
+    let ids = match track.parse::<i64>() {
+        Ok(id) => vec![id],
+        Err(_) => db.track_ids_for_path(track).context("Query failed")?,
+    };
+    match ids.as_slice() {
+        [] => anyhow::bail!("No track at {track}"),
+        [id] => Ok(*id),
+        ids => anyhow::bail!(
+            "{} tracks end with {track}; give more of the path",
+            ids.len()
+        ),
+    }
+}
+
+/// Queue age: "3h", "5d", "7w".
+fn fmt_age(days: f64) -> String {
+    if days < 1.0 {
+        format!("{:.0}h", (days * 24.0).floor())
+    } else if days < 14.0 {
+        format!("{:.0}d", days.floor())
+    } else {
+        format!("{:.0}w", (days / 7.0).floor())
+    }
+}
+
+fn print_queue(queue: &[crate::queue::QueueEntry], table_opts: &TableOptions) {
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("Song").flex(12),
+        Column::left("Date"),
+        Column::left("Why").flex(12),
+        Column::right("Waiting"),
+    ]);
+    for e in queue {
+        let why = match &e.reason {
+            Some(reason) => format!("{}: {reason}", e.source.as_str()),
+            None => e.source.as_str().to_string(),
+        };
+        table.push(vec![
+            e.track_id.to_string(),
+            e.track.title.clone(),
+            e.track.date.clone(),
+            why,
+            fmt_age(e.age_days),
+        ]);
+    }
+    print!("{}", table.render(table_opts.max_width));
+    println!();
+    println!(
+        "{} queued. `setbreak queue pop` takes the oldest off.",
+        queue.len()
+    );
+}
+
+#[cfg(feature = "analysis")]
+fn print_trace(trace: &crate::analyzer::trace::Trace, features: usize) {
+    use crate::analyzer::trace::display;
+
+    for stage in &trace.stages {
+        println!(
+            "  {:<11} {:>7.2}s  {}",
+            stage.name,
+            stage.elapsed.as_secs_f64(),
+            stage.summary
+        );
+    }
+    println!();
+    if !trace.stored {
+        println!("No stored analysis to compare with.");
+        return;
+    }
+    if trace.stored_preview {
+        println!("Note: the stored analysis is a preview (excerpts), so some drift is expected.");
+    }
+    if trace.changes.is_empty() {
+        println!("All {} columns match the stored analysis.", trace.compared);
+        return;
+    }
+
+    let print_change = |c: &crate::analyzer::trace::Change| {
+        let delta = c.delta().map(|d| format!("({d:+.4})")).unwrap_or_default();
+        println!(
+            "  {:<30} {:>12} -> {:<12} {}",
+            c.column,
+            display(&c.stored),
+            display(&c.traced),
+            delta
+        );
+    };
+    let (scores, rest): (Vec<_>, Vec<_>) = trace.changes.iter().partition(|c| c.is_score());
+    if scores.is_empty() {
+        println!("Scores: unchanged");
+    } else {
+        println!("Scores (stored -> traced):");
+        scores.iter().for_each(|c| print_change(c));
+    }
+    if !rest.is_empty() {
+        println!();
+        println!(
+            "Features: {} of {} changed (largest relative change first):",
+            rest.len(),
+            trace.compared
+        );
+        rest.iter().take(features).for_each(|c| print_change(c));
+        if rest.len() > features {
+            println!("  ... {} more (--features N)", rest.len() - features);
+        }
+    }
+    if !scores.is_empty() {
+        println!();
+        println!(
+            "Stored scores include any `calibrate` adjustment and the trace doesn't: a similar shift on every track of a show is calibration, not a regression."
+        );
+    }
+}
+
+fn print_cut_marker(c: &crate::db::models::CutPointRecord) {
+    println!(
+        "{:>7}  {:<20} {:>6}",
+        fmt_clock(c.time),
+        format!("--- CUT ({:.0} dB drop)", c.drop_db),
+        ""
+    );
+}
+
+/// Print merged configuration with the layer each value comes from.
+fn print_effective_config(
+    config: &crate::config::AppConfig,
+    db_path: &std::path::Path,
+    db_origin: &str,
+    dirs: &[PathBuf],
+) {
+    use crate::config::{AppConfig, CollectionConfig};
+
+    let defaults = AppConfig::default();
+    let origin = |is_default: bool| if is_default { "default" } else { "config.toml" };
+
+    println!("Precedence (lowest to highest): defaults, config.toml, .setbreak.toml, CLI flags");
+    if let Some(path) = AppConfig::config_path() {
+        println!("config.toml: {}", path.display());
+    }
+    println!();
+
+    let workers = if config.workers == 0 {
+        format!("{} (auto)", config.resolve_workers())
+    } else {
+        config.workers.to_string()
+    };
+    let decode_workers = if config.decode_workers == 0 {
+        format!(
+            "{} (auto)",
+            config.resolve_decode_workers(config.resolve_workers())
+        )
+    } else {
+        config.decode_workers.to_string()
+    };
+    let music_dirs = config
+        .music_dirs
+        .iter()
+        .map(|d| d.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let rows = [
+        ("db_path", db_path.display().to_string(), db_origin),
+        ("workers", workers, origin(config.workers == 0)),
+        (
+            "decode_workers",
+            decode_workers,
+            origin(config.decode_workers == 0),
+        ),
+        (
+            "prefetch_mb",
+            config.resolve_prefetch_mb().to_string(),
+            origin(config.prefetch_mb == 0),
+        ),
+        (
+            "music_dirs",
+            music_dirs,
+            origin(config.music_dirs.is_empty()),
+        ),
+        (
+            "archive.cache_ttl_days",
+            config.archive.cache_ttl_days.to_string(),
+            origin(config.archive.cache_ttl_days == defaults.archive.cache_ttl_days),
+        ),
+        (
+            "archive.rate_limit_ms",
+            config.archive.rate_limit_ms.to_string(),
+            origin(config.archive.rate_limit_ms == defaults.archive.rate_limit_ms),
+        ),
+        (
+            "archive.contact",
+            config
+                .archive
+                .contact
+                .clone()
+                .unwrap_or_else(|| "(none)".into()),
+            origin(config.archive.contact.is_none()),
+        ),
+        (
+            "archive.max_requests",
+            match config.archive.max_requests {
+                0 => "unlimited".to_string(),
+                n => n.to_string(),
+            },
+            origin(config.archive.max_requests == defaults.archive.max_requests),
+        ),
+        (
+            "archive.max_retries",
+            config.archive.max_retries.to_string(),
+            origin(config.archive.max_retries == defaults.archive.max_retries),
+        ),
+        (
+            "archive.concurrency",
+            config.archive.concurrency.to_string(),
+            origin(config.archive.concurrency == defaults.archive.concurrency),
+        ),
+        (
+            "analysis.chunk_size",
+            match config.analysis.chunk_size {
+                0 => "auto (workers × 4)".to_string(),
+                n => n.to_string(),
+            },
+            origin(config.analysis.chunk_size == defaults.analysis.chunk_size),
+        ),
+        (
+            "analysis.queue_depth",
+            match config.analysis.queue_depth {
+                0 => "auto (one per worker)".to_string(),
+                n => n.to_string(),
+            },
+            origin(config.analysis.queue_depth == defaults.analysis.queue_depth),
+        ),
+        (
+            "analysis.pyin_threshold_count",
+            config.analysis.pyin_threshold_count.to_string(),
+            origin(config.analysis.pyin_threshold_count == defaults.analysis.pyin_threshold_count),
+        ),
+        (
+            "analysis.pyin_hop_multiplier",
+            config.analysis.pyin_hop_multiplier.to_string(),
+            origin(config.analysis.pyin_hop_multiplier == defaults.analysis.pyin_hop_multiplier),
+        ),
+        (
+            "analysis.skip_classification_segments",
+            config.analysis.skip_classification_segments.to_string(),
+            origin(
+                config.analysis.skip_classification_segments
+                    == defaults.analysis.skip_classification_segments,
+            ),
+        ),
+        (
+            "analysis.keep_artifacts",
+            config
+                .analysis
+                .keep_artifacts
+                .iter()
+                .map(|k| k.name())
+                .collect::<Vec<_>>()
+                .join(", "),
+            origin(config.analysis.keep_artifacts.is_empty()),
+        ),
+        (
+            "analysis.artifact_budget_mb",
+            match config.analysis.artifact_budget_mb {
+                0 => "unlimited".to_string(),
+                n => n.to_string(),
+            },
+            origin(config.analysis.artifact_budget_mb == 0),
+        ),
+        (
+            "analysis.nice",
+            config.analysis.nice.to_string(),
+            origin(config.analysis.nice == defaults.analysis.nice),
+        ),
+        (
+            "analysis.ionice",
+            config.analysis.ionice.as_str().to_string(),
+            origin(config.analysis.ionice == defaults.analysis.ionice),
+        ),
+        (
+            "analysis.max_cpu_percent",
+            config.analysis.max_cpu_percent.to_string(),
+            origin(config.analysis.max_cpu_percent == defaults.analysis.max_cpu_percent),
+        ),
+        (
+            "bands",
+            format!("{} custom", config.custom_bands.len()),
+            origin(config.custom_bands.is_empty()),
+        ),
+        (
+            "band_scoring",
+            config
+                .band_scoring
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+            origin(config.band_scoring.is_empty()),
+        ),
+        (
+            "external_analyzers",
+            config
+                .external_analyzers
+                .iter()
+                .map(|a| a.name.clone())
+                .collect::<Vec<_>>()
+                .join(", "),
+            origin(config.external_analyzers.is_empty()),
+        ),
+        (
+            "recipes",
+            config
+                .recipes
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+            origin(config.recipes.is_empty()),
+        ),
+    ];
+    for (key, value, from) in &rows {
+        println!("{:<38} {:<40} [{}]", key, value, from);
+    }
+
+    let print_collection = |title: &str, local: Option<(PathBuf, CollectionConfig)>| {
+        println!();
+        println!("{}", title);
+        let local_name = local
+            .as_ref()
+            .map(|(p, _)| p.display().to_string())
+            .unwrap_or_default();
+        let global = &config.collection;
+        let empty = CollectionConfig::default();
+        let over = local.as_ref().map(|(_, c)| c).unwrap_or(&empty);
+
+        let scalar = |key: &str, g: &Option<String>, l: &Option<String>, unset: &str| {
+            let (value, from) = match (l, g) {
+                (Some(v), _) => (v.clone(), local_name.as_str()),
+                (None, Some(v)) => (v.clone(), "config.toml"),
+                (None, None) => (unset.to_string(), "default"),
+            };
+            println!("  {:<30} {:<40} [{}]", key, value, from);
+        };
+        scalar("band", &global.band, &over.band, "(from path)");
+        scalar(
+            "recording_type",
+            &global.recording_type,
+            &over.recording_type,
+            "(auto)",
+        );
+        let prefer = |c: &CollectionConfig| c.prefer.map(|p| p.as_str().to_string());
+        scalar("prefer", &prefer(global), &prefer(over), "filename");
+        let order = |c: &CollectionConfig| c.date_order.map(|o| o.as_str().to_string());
+        scalar("date_order", &order(global), &order(over), "mdy");
+
+        // Analysis profile: unset keys fall through to the global [analysis]
+        let (g, l, base) = (&global.analysis, &over.analysis, &config.analysis);
+        scalar(
+            "pyin_threshold_count",
+            &g.pyin_threshold_count.map(|v| v.to_string()),
+            &l.pyin_threshold_count.map(|v| v.to_string()),
+            &format!("{} (from [analysis])", base.pyin_threshold_count),
+        );
+        scalar(
+            "pyin_hop_multiplier",
+            &g.pyin_hop_multiplier.map(|v| v.to_string()),
+            &l.pyin_hop_multiplier.map(|v| v.to_string()),
+            &format!("{} (from [analysis])", base.pyin_hop_multiplier),
+        );
+        scalar(
+            "skip_classification_segments",
+            &g.skip_classification_segments.map(|v| v.to_string()),
+            &l.skip_classification_segments.map(|v| v.to_string()),
+            &format!("{} (from [analysis])", base.skip_classification_segments),
+        );
+
+        let patterns: Vec<(&String, &str)> = global
+            .ignore
+            .iter()
+            .map(|p| (p, "config.toml"))
+            .chain(over.ignore.iter().map(|p| (p, local_name.as_str())))
+            .collect();
+        if patterns.is_empty() {
+            println!("  {:<30} {:<40} [default]", "ignore", "(none)");
+        }
+        for (i, (pattern, from)) in patterns.iter().enumerate() {
+            let key = if i == 0 { "ignore" } else { "" };
+            println!("  {:<30} {:<40} [{}]", key, pattern, from);
+        }
+    };
+
+    if dirs.is_empty() {
+        print_collection("Collection defaults", None);
+    }
+    for dir in dirs {
+        print_collection(
+            &format!("Collection: {}", dir.display()),
+            CollectionConfig::load_from_dir(dir),
+        );
+    }
+}
+
+fn print_scan_preview(preview: &crate::scanner::ScanPreview) {
+    use crate::scanner::ActionCounts;
+    use std::collections::BTreeMap;
+
+    let print_group = |heading: &str, groups: &BTreeMap<String, ActionCounts>| {
+        println!();
+        println!(
+            "{:<30} {:>8} {:>8} {:>8} {:>8}",
+            heading, "New", "Update", "Skip", "Total"
+        );
+        println!("{}", "-".repeat(66));
+        let mut rows: Vec<(&String, &ActionCounts)> = groups.iter().collect();
+        rows.sort_by_key(|(_, c)| std::cmp::Reverse(c.total()));
+        for (name, c) in rows {
+            println!(
+                "{:<30} {:>8} {:>8} {:>8} {:>8}",
+                name,
+                c.new,
+                c.updated,
+                c.skipped,
+                c.total()
+            );
+        }
+    };
+
+    print_group("Band", &preview.by_band);
+    print_group("Format", &preview.by_format);
+
+    let t = &preview.totals;
+    println!();
+    println!(
+        "Would scan {} files: {} new, {} updated, {} skipped, {} errors",
+        t.total(),
+        t.new,
+        t.updated,
+        t.skipped,
+        preview.errors
+    );
+
+    if preview.undated > 0 {
+        println!();
+        println!(
+            "{} files have no parseable show date, e.g.:",
+            preview.undated
+        );
+        for path in &preview.undated_examples {
+            println!("  {}", path);
+        }
+    }
+}
+
+/// Score table with a leading column for a recipe's value.
+fn print_recipe_table(results: &[(TrackScore, f64)], name: &str, opts: &TableOptions) {
+    let scores = opts.score_columns(&[
+        "groove",
+        "improvisation",
+        "energy",
+        "intensity",
+        "build_quality",
+        "transcendence",
+    ]);
+    let mut columns: Vec<Column> = opts.rank_column().into_iter().collect();
+    columns.extend([
+        Column::left("Song").flex(15),
+        Column::right(when_header(results.iter().map(|(t, _)| t))),
+        Column::right("Min"),
+        Column::right(name.chars().take(9).collect::<String>()).gap(1),
+    ]);
+    columns.extend(score_headers(&scores));
+    let mut table = Table::new(columns);
+    for (i, (t, value)) in results.iter().enumerate() {
+        let mut row: Vec<String> = opts.rank_cell(i).into_iter().collect();
+        row.extend([
+            t.title.clone(),
+            t.when().to_string(),
+            format!("{:.1}", t.duration_min),
+            format!("{:.1}", value),
+        ]);
+        row.extend(score_cells(&scores, |col| t.score(col)));
+        table.push(row);
+    }
+    print!("{}", table.render(opts.max_width));
+}
+
+/// Right-aligned score column headers; the first is set apart from the columns before it.
+fn score_headers(scores: &[&ScoreMeta]) -> Vec<Column> {
+    scores
+        .iter()
+        .enumerate()
+        .map(|(i, s)| Column::right(s.short).gap(if i == 0 { 1 } else { 0 }))
+        .collect()
+}
+
+/// Score values for a row, rounded to whole points.
+fn score_cells(scores: &[&ScoreMeta], value: impl Fn(&str) -> Option<f64>) -> Vec<String> {
+    scores
+        .iter()
+        .map(|s| {
+            value(s.column)
+                .map(|v| format!("{:.0}", v))
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// clap value parser for disc lengths like `80min`.
+fn parse_disc_length(value: &str) -> Result<f64, String> {
+    crate::compilation::parse_disc_length(value)
+        .ok_or_else(|| format!("invalid length '{value}' (e.g. 80min, 74m, 4800s)"))
+}
+
+/// clap value parser for a size in bytes (e.g. `500GB`).
+fn parse_size(value: &str) -> Result<u64, String> {
+    crate::prune_suggest::parse_size(value)
+        .ok_or_else(|| format!("invalid size '{value}' (e.g. 500GB, 1.5TB, 800MiB)"))
+}
+
+/// clap value parser for `snippet --at`.
+fn parse_snippet_anchor(value: &str) -> Result<crate::highlights::SnippetAnchor, String> {
+    crate::highlights::SnippetAnchor::parse(value)
+        .ok_or_else(|| format!("invalid position '{value}' (peak, tension, 12:34 or seconds)"))
+}
+
+/// clap value parser for a 1-based page number.
+fn parse_page(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(n) if n >= 1 => Ok(n),
+        _ => Err(format!("invalid page '{value}' (pages start at 1)")),
+    }
+}
+
+/// clap value parser for a share between 0 and 1 (e.g. `0.8`).
+fn parse_share(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err(format!(
+            "invalid share '{value}' (a number from 0 to 1, e.g. 0.8)"
+        )),
+    }
+}
+
+/// setlist.fm allows about two requests a second per API key.
+const SETLISTFM_RATE_LIMIT_MS: u64 = 600;
+
+/// Print `setlist --validate` results: anomalies per flagged recording, then a summary.
+fn print_setlist_validation(report: &crate::setlist::validate::ValidationReport) {
+    use crate::setlist::validate::Anomaly;
+
+    if report.checked == 0 {
+        println!("No titled shows with a reference setlist to check.");
+        println!(
+            "Import one with `import-setlists`, `fetch-phishin`, or `setlist --validate --setlistfm`."
+        );
+        return;
+    }
+    for v in &report.flagged {
+        println!(
+            "{} {}  ({} of {} songs matched against {}{}{})",
+            v.date,
+            v.name(),
+            v.matched,
+            v.reference_songs,
+            v.reference,
+            if v.not_on_tape > 0 {
+                format!(", {} not on tape", v.not_on_tape)
+            } else {
+                String::new()
+            },
+            if v.untitled > 0 {
+                format!(", {} untitled", v.untitled)
+            } else {
+                String::new()
+            },
+        );
+        for anomaly in &v.anomalies {
+            let detail = match anomaly {
+                Anomaly::Missing { song } => song.clone(),
+                Anomaly::Extra { title, track } => format!("track {track}: {title}"),
+                Anomaly::OutOfOrder {
+                    title,
+                    track,
+                    expected,
+                } => format!("track {track}: {title} (song {expected} in the reference)"),
+                Anomaly::Duplicate { title, tracks } => {
+                    let tracks: Vec<String> = tracks.iter().map(|t| t.to_string()).collect();
+                    format!("tracks {}: {title}", tracks.join(", "))
+                }
+            };
+            println!("  {:<13}{detail}", anomaly.kind());
+        }
+        println!();
+    }
+    println!(
+        "{} recordings checked, {} with anomalies, {} with no songs in common with the reference",
+        report.checked,
+        report.flagged.len(),
+        report.no_overlap
+    );
+}
+
+/// clap value parser for `similar --year-range`.
+fn parse_year_range(value: &str) -> Result<(i32, i32), String> {
+    crate::similarity::parse_year_range(value).ok_or_else(|| {
+        format!("invalid year range '{value}' (e.g. 1972-1974, 1972..1974, or 1977)")
+    })
+}
+
+/// clap value parser for a show date (`1977-05-08`).
+fn parse_show_date(value: &str) -> Result<String, String> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|d| d.to_string())
+        .map_err(|_| format!("invalid date '{value}' (YYYY-MM-DD)"))
+}
+
+/// New standouts listed after `analyze`; the notify hook gets all of them.
+#[cfg(feature = "analysis")]
+const STANDOUTS_SHOWN: usize = 15;
+
+/// List newly analyzed tracks in the library's top decile and fire the notify
+/// hook. Returns the standouts.
+#[cfg(feature = "analysis")]
+fn report_standouts(
+    db: &crate::db::Database,
+    since: &str,
+    notify: &crate::config::NotifyConfig,
+    table_opts: &TableOptions,
+) -> Result<Vec<crate::standouts::Standout>> {
+    let Some(standouts) =
+        crate::standouts::find(db, since).context("Failed to find new standouts")?
+    else {
+        return Ok(Vec::new());
+    };
+    if standouts.is_empty() {
+        println!("No new standouts (top 10% of the library on any score).");
+        return Ok(standouts);
+    }
+    println!();
+    println!(
+        "New standouts — top 10% of the library on at least one score ({}):",
+        standouts.len()
+    );
+    let mut table = Table::new(vec![
+        Column::left("Song").flex(12),
+        Column::left("Date"),
+        Column::left("Stands out on").flex(12),
+    ]);
+    for s in standouts.iter().take(STANDOUTS_SHOWN) {
+        let scores: Vec<String> = s
+            .scores
+            .iter()
+            .map(|m| format!("{} {:.0}", m.label, s.track.score(m.column).unwrap_or(0.0)))
+            .collect();
+        table.push(vec![
+            s.track.title.clone(),
+            s.track.date.clone(),
+            scores.join(", "),
+        ]);
+    }
+    print!("{}", table.render(table_opts.max_width));
+    if standouts.len() > STANDOUTS_SHOWN {
+        println!("... and {} more", standouts.len() - STANDOUTS_SHOWN);
+    }
+    if let Err(e) = crate::standouts::notify(&notify.command, &standouts) {
+        eprintln!("Warning: notify command failed: {e}");
+    }
+    Ok(standouts)
+}
+
+/// Directories to scan: the ones given, else the config's `music_dirs`.
+fn resolve_scan_paths(
+    paths: Vec<String>,
+    config: &crate::config::AppConfig,
+) -> Result<Vec<String>> {
+    if !paths.is_empty() {
+        return Ok(paths);
+    }
+    if config.music_dirs.is_empty() {
+        anyhow::bail!(
+            "No directories to scan. Pass paths as arguments or set music_dirs in config."
+        );
+    }
+    Ok(config
+        .music_dirs
+        .iter()
+        .map(|p| crate::paths::to_db(p))
+        .collect())
+}
+
+fn run_scan(
+    db: &crate::db::Database,
+    paths: &[String],
+    force: bool,
+    config: &crate::config::AppConfig,
+) -> Result<()> {
+    let result = crate::scanner::scan(db, paths, force, config, &CliProgress::new())
+        .context("Scan failed")?;
+    println!(
+        "Scan complete: {} scanned, {} new, {} updated, {} skipped, {} errors",
+        result.scanned, result.new, result.updated, result.skipped, result.errors
+    );
+    Ok(())
+}
+
+/// `[analysis]` tuning for each collection in `music_dirs`, checked before a run.
+#[cfg(feature = "analysis")]
+fn track_tuning(config: &crate::config::AppConfig) -> anyhow::Result<crate::config::TrackTuning> {
+    let tuning = config.track_tuning(&config.music_dirs);
+    tuning
+        .validate()
+        .map_err(|e| anyhow::anyhow!("Invalid [analysis] profile: {e}"))?;
+    Ok(tuning)
+}
+
+/// Analysis pipeline sizes; zeros resolve from the config.
+#[cfg(feature = "analysis")]
+fn pipeline_config(
+    config: &crate::config::AppConfig,
+    jobs: usize,
+    decode_jobs: usize,
+    prefetch_mb: usize,
+) -> crate::analyzer::pipeline::PipelineConfig {
+    let workers = if jobs > 0 {
+        jobs
+    } else {
+        config.resolve_workers()
+    };
+    let decode_jobs = if decode_jobs > 0 {
+        decode_jobs
+    } else {
+        config.resolve_decode_workers(workers)
+    };
+    let prefetch_mb = if prefetch_mb > 0 {
+        prefetch_mb
+    } else {
+        config.resolve_prefetch_mb()
+    };
+    crate::analyzer::pipeline::PipelineConfig {
+        jobs: workers,
+        decode_jobs,
+        prefetch_bytes: prefetch_mb << 20,
+        queue_depth: config.analysis.resolve_queue_depth(workers),
+    }
+}
+
+/// After an `analyze` run: report what building analysis engines cost and what
+/// reusing them saved; when the run stored something, classify filler, report
+/// new standouts and queue them and new outliers for a re-listen; then fire
+/// the run's `[[hooks]]` events.
+#[cfg(feature = "analysis")]
+fn after_analysis(
+    db: &crate::db::Database,
+    started: &str,
+    result: &crate::analyzer::AnalyzeResult,
+    config: &crate::config::AppConfig,
+    table_opts: &TableOptions,
+) -> Result<()> {
+    let setup = &result.engine_setup;
+    if let Some(mean) = setup.mean_build_secs() {
+        println!(
+            "Engine setup: built {} time{} ({:.0} ms each), reused for {} track{} (~{:.1} s saved)",
+            setup.builds,
+            if setup.builds == 1 { "" } else { "s" },
+            mean * 1000.0,
+            setup.reuses,
+            if setup.reuses == 1 { "" } else { "s" },
+            setup.saved_secs()
+        );
+    }
+    if result.analyzed == 0 {
+        return fire_analyze_hooks(db, started, result, &config.hooks, &[]);
+    }
+    let filler = db
+        .classify_filler()
+        .context("Failed to classify soundchecks and interviews")?;
+    println!(
+        "Non-music items: {} soundcheck, {} interview (excluded from top/compare)",
+        filler.soundcheck, filler.interview
+    );
+    let standouts = report_standouts(db, started, &config.notify, table_opts)?;
+    let outliers = crate::queue::find_outliers(db, started).context("Failed to find outliers")?;
+    let queued = crate::queue::queue_new_finds(db, &standouts, &outliers)
+        .context("Failed to update the re-listen queue")?;
+    if queued > 0 {
+        println!(
+            "Queued {queued} new track{} to re-listen to (`setbreak queue list`).",
+            if queued == 1 { "" } else { "s" }
+        );
+    }
+    fire_analyze_hooks(db, started, result, &config.hooks, &standouts)
+}
+
+#[cfg(feature = "analysis")]
+fn fire_analyze_hooks(
+    db: &crate::db::Database,
+    started: &str,
+    result: &crate::analyzer::AnalyzeResult,
+    hooks: &[crate::hooks::HookConfig],
+    standouts: &[crate::standouts::Standout],
+) -> Result<()> {
+    let run = crate::hooks::AnalyzeRun {
+        since: started,
+        analyzed: result.analyzed,
+        failed: result.failed,
+        standouts,
+    };
+    for failure in crate::hooks::fire_analyze_events(db, hooks, &run).context("Query failed")? {
+        eprintln!("Warning: {failure}");
+    }
+    Ok(())
+}
+
+/// Bring performance links up to date with the tracks table.
+fn link_performances(db: &crate::db::Database) -> Result<()> {
+    let summary =
+        crate::performances::link(db).context("Failed to link recordings to performances")?;
+    if summary.relinked > 0 {
+        note!(
+            "Linked {} tracks to {} performances.",
+            summary.relinked,
+            summary.performances
+        );
+    }
+    Ok(())
+}
+
+/// Re-detect split jams if analysis or titles changed since they were last built.
+fn refresh_track_groups(db: &crate::db::Database) -> Result<()> {
+    if !db.track_groups_are_fresh().context("Query failed")? {
+        let n = crate::track_groups::rebuild_track_groups(db)
+            .context("Failed to rebuild track groups")?;
+        note!("Detected {} split jams.", n);
+    }
+    Ok(())
+}
+
+/// Tell `top` users how many untitled tracks were left out, and how to fix that.
+fn print_untitled_note(untitled: usize) {
+    if untitled > 0 {
+        println!();
+        println!(
+            "{} untitled track{} hidden (--include-untitled to show). \
+             Identify them with `setbreak setlist` or `setbreak titles import`.",
+            untitled,
+            if untitled == 1 { "" } else { "s" }
+        );
+    }
+}
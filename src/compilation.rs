@@ -0,0 +1,287 @@
+//! Best-of compilations: pick top tracks under constraints and pack them onto discs.
+//!
+//! Candidates arrive ranked by a jam score. Walking down the ranking, each track
+//! is skipped if its song is already on the compilation or its show has hit the
+//! per-show cap; otherwise it goes on the first disc with room (first-fit), opening
+//! a new disc while under the disc limit. Each disc's tracklist is then put back
+//! in chronological order.
+
+use crate::db::Database;
+use crate::db::columns::NO_CUTS;
+use crate::db::models::TrackScore;
+use crate::query::CompiledFilter;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Ranked candidates fetched before applying the constraints.
+const CANDIDATE_POOL: usize = 5000;
+
+/// Constraints for building a compilation.
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    /// Disc (or playlist chunk) capacity in seconds.
+    pub disc_secs: f64,
+    /// Maximum number of discs.
+    pub max_discs: usize,
+    /// Maximum tracks taken from any one show.
+    pub max_per_show: usize,
+    /// Allow the same song more than once.
+    pub allow_repeats: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            disc_secs: 80.0 * 60.0,
+            max_discs: 1,
+            max_per_show: 2,
+            allow_repeats: false,
+        }
+    }
+}
+
+/// One disc's tracks, in chronological order once built.
+#[derive(Debug, Clone, Default)]
+pub struct Disc {
+    pub tracks: Vec<TrackScore>,
+}
+
+impl Disc {
+    pub fn duration_secs(&self) -> f64 {
+        self.tracks.iter().map(|t| t.duration_min * 60.0).sum()
+    }
+}
+
+/// A packed compilation plus counts of why candidates were left out.
+#[derive(Debug, Clone, Default)]
+pub struct Compilation {
+    pub title: String,
+    pub discs: Vec<Disc>,
+    pub skipped_repeat: usize,
+    pub skipped_show_cap: usize,
+    pub skipped_no_room: usize,
+}
+
+impl Compilation {
+    pub fn track_count(&self) -> usize {
+        self.discs.iter().map(|d| d.tracks.len()).sum()
+    }
+}
+
+/// Fetch ranked candidates: analyzed tracks matching `filter` (all if None), best
+/// `sort_column` first. Tracks with a detected tape flip or splice are left out.
+pub fn candidates(
+    db: &Database,
+    filter: Option<CompiledFilter>,
+    sort_column: &str,
+    live_only: bool,
+) -> crate::db::Result<Vec<TrackScore>> {
+    let mut filter = filter.unwrap_or(CompiledFilter {
+        where_clause: "1".to_string(),
+        params: Vec::new(),
+    });
+    filter.where_clause = format!("({}) AND {NO_CUTS}", filter.where_clause);
+    db.query_filter(&filter, Some(sort_column), CANDIDATE_POOL, live_only)
+}
+
+/// Select and pack ranked candidates onto discs.
+pub fn build(title: &str, ranked: Vec<TrackScore>, opts: &CompileOptions) -> Compilation {
+    let mut comp = Compilation {
+        title: title.to_string(),
+        ..Default::default()
+    };
+    let mut songs: HashSet<String> = HashSet::new();
+    let mut per_show: HashMap<(Option<String>, String), usize> = HashMap::new();
+
+    for track in ranked {
+        let song = song_key(&track.title);
+        if !opts.allow_repeats && songs.contains(&song) {
+            comp.skipped_repeat += 1;
+            continue;
+        }
+        let show = (track.band.clone(), track.date.clone());
+        if per_show.get(&show).copied().unwrap_or(0) >= opts.max_per_show {
+            comp.skipped_show_cap += 1;
+            continue;
+        }
+
+        let secs = track.duration_min * 60.0;
+        let slot = comp
+            .discs
+            .iter()
+            .position(|d| d.duration_secs() + secs <= opts.disc_secs);
+        let disc = match slot {
+            Some(i) => &mut comp.discs[i],
+            None if comp.discs.len() < opts.max_discs && secs <= opts.disc_secs => {
+                comp.discs.push(Disc::default());
+                comp.discs.last_mut().unwrap()
+            }
+            None => {
+                comp.skipped_no_room += 1;
+                continue;
+            }
+        };
+        disc.tracks.push(track);
+        songs.insert(song);
+        *per_show.entry(show).or_default() += 1;
+    }
+
+    for disc in &mut comp.discs {
+        disc.tracks.sort_by(|a, b| {
+            (a.date.as_str(), a.file_path.as_str()).cmp(&(b.date.as_str(), b.file_path.as_str()))
+        });
+    }
+    comp
+}
+
+/// Song identity for duplicate detection: case-insensitive, segue markers ignored.
+fn song_key(title: &str) -> String {
+    let mut t = title.trim_end();
+    for marker in &[" -->", "-->", " ->", "->", " >", ">"] {
+        if let Some(stripped) = t.strip_suffix(marker) {
+            t = stripped.trim_end();
+            break;
+        }
+    }
+    t.to_lowercase()
+}
+
+/// Parse a disc length like `80min`, `74m`, `4800s` or `1.5h` (bare numbers are minutes).
+pub fn parse_disc_length(value: &str) -> Option<f64> {
+    crate::query::parse_duration_secs(value).filter(|&secs| secs > 0.0)
+}
+
+/// Plain-text tracklist with per-disc running times.
+pub fn tracklist(comp: &Compilation) -> String {
+    let mut out = format!("{}\n\n", comp.title);
+    for (i, disc) in comp.discs.iter().enumerate() {
+        out.push_str(&format!(
+            "Disc {} ({})\n",
+            i + 1,
+            fmt_length(disc.duration_secs())
+        ));
+        for (n, t) in disc.tracks.iter().enumerate() {
+            out.push_str(&format!(
+                "{:>3}. {} — {} [{}]\n",
+                n + 1,
+                t.title,
+                t.date,
+                fmt_length(t.duration_min * 60.0)
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Write one M3U per disc plus a tracklist into `out_dir`. Returns the written paths.
+pub fn write(comp: &Compilation, out_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(out_dir)?;
+    let slug = match crate::playlist::slugify(&comp.title) {
+        s if s.is_empty() => "compilation".to_string(),
+        s => s,
+    };
+    let mut written = Vec::new();
+    for (i, disc) in comp.discs.iter().enumerate() {
+        let path = out_dir.join(format!("{}-disc{}.m3u", slug, i + 1));
+        crate::playlist::write_m3u(&path, &disc.tracks)?;
+        written.push(path);
+    }
+    let path = out_dir.join(format!("{slug}.txt"));
+    std::fs::write(&path, tracklist(comp))?;
+    written.push(path);
+    Ok(written)
+}
+
+fn fmt_length(secs: f64) -> String {
+    let secs = secs.round() as i64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str, date: &str, minutes: f64) -> TrackScore {
+        TrackScore {
+            title: title.into(),
+            date: date.into(),
+            file_path: format!("/music/{date}/{title}.flac"),
+            band: Some("gd".into()),
+            set: None,
+            album: None,
+            duration_min: minutes,
+            key: None,
+            tempo: None,
+            energy: 0.0,
+            intensity: 0.0,
+            groove: 0.0,
+            improvisation: 0.0,
+            tightness: 0.0,
+            build_quality: 0.0,
+            exploratory: 0.0,
+            transcendence: 0.0,
+            valence: 0.0,
+            arousal: 0.0,
+        }
+    }
+
+    fn titles(disc: &Disc) -> Vec<&str> {
+        disc.tracks.iter().map(|t| t.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_build_constraints_and_packing() {
+        let ranked = vec![
+            track("Scarlet Begonias ->", "1977-05-08", 12.0),
+            track("Fire on the Mountain", "1977-05-08", 14.0),
+            track("Morning Dew", "1977-05-08", 13.0), // show cap
+            track("scarlet begonias", "1977-05-22", 11.0), // repeat
+            track("Help on the Way", "1977-05-22", 50.0),
+            track("Estimated Prophet", "1977-06-09", 40.0), // no room
+            track("Sugaree", "1977-06-09", 16.0),
+        ];
+        let opts = CompileOptions {
+            disc_secs: 60.0 * 60.0,
+            max_discs: 2,
+            max_per_show: 2,
+            allow_repeats: false,
+        };
+        let comp = build("Best of 1977", ranked, &opts);
+        assert_eq!(comp.discs.len(), 2);
+        assert_eq!(
+            titles(&comp.discs[0]),
+            vec!["Fire on the Mountain", "Scarlet Begonias ->", "Sugaree"]
+        );
+        assert_eq!(titles(&comp.discs[1]), vec!["Help on the Way"]);
+        assert!(
+            comp.discs
+                .iter()
+                .all(|d| d.duration_secs() <= opts.disc_secs)
+        );
+        assert_eq!(comp.skipped_show_cap, 1);
+        assert_eq!(comp.skipped_repeat, 1);
+        assert_eq!(comp.skipped_no_room, 1);
+    }
+
+    #[test]
+    fn test_oversized_track_never_opens_disc() {
+        let comp = build(
+            "x",
+            vec![track("Dark Star", "1972-08-27", 90.0)],
+            &CompileOptions::default(),
+        );
+        assert!(comp.discs.is_empty());
+        assert_eq!(comp.skipped_no_room, 1);
+    }
+
+    #[test]
+    fn test_parse_disc_length() {
+        assert_eq!(parse_disc_length("80min"), Some(4800.0));
+        assert_eq!(parse_disc_length("74"), Some(4440.0));
+        assert_eq!(parse_disc_length("1.5h"), Some(5400.0));
+        assert_eq!(parse_disc_length("0"), None);
+        assert_eq!(parse_disc_length("80 tracks"), None);
+    }
+}
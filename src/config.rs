@@ -1,9 +1,27 @@
-use std::path::PathBuf;
+//! Layered configuration.
+//!
+//! Precedence, lowest to highest:
+//! 1. Built-in defaults
+//! 2. Global config: `~/.config/setbreak/config.toml`
+//! 3. Per-collection overrides: `.setbreak.toml` in a music directory root
+//!    (only the `[collection]` settings — band, recording type, ignore patterns,
+//!    metadata precedence, date order — and an `[analysis]` profile)
+//! 4. Command-line flags
+//!
+//! Ignore patterns accumulate across layers; every other setting is replaced
+//! by the closest layer that sets it.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 use directories::ProjectDirs;
 use serde::Deserialize;
 
 use crate::bands::CustomBandConfig;
+use crate::dates::DateOrder;
+
+/// Per-collection config file, looked up in the root of each music directory.
+pub const COLLECTION_CONFIG_FILE: &str = ".setbreak.toml";
 
 /// Application configuration loaded from TOML config file.
 /// All fields have sensible defaults — the config file is optional.
@@ -16,21 +34,188 @@ pub struct AppConfig {
     pub db_path: Option<PathBuf>,
     /// Number of parallel workers. 0 = auto-detect (cores / 2, min 1).
     pub workers: usize,
+    /// Decode workers prefetching audio for `analyze`. 0 = auto (workers / 2, min 1).
+    pub decode_workers: usize,
+    /// Budget for decoded audio waiting on analysis, in MB. 0 = default (2048).
+    pub prefetch_mb: usize,
     /// Archive.org API settings.
     pub archive: ArchiveConfig,
+    /// Analyzer speed/accuracy tuning (`[analysis]`).
+    pub analysis: AnalysisTuning,
     /// Custom band definitions (merged with built-in registry).
     #[serde(rename = "bands")]
     pub custom_bands: Vec<CustomBandConfig>,
+    /// Collection defaults, overridable per music directory via `.setbreak.toml`.
+    pub collection: CollectionConfig,
+    /// Composite score formulas by name, e.g. `face_melt = "0.5*transcendence + 0.5*intensity"`.
+    pub recipes: BTreeMap<String, String>,
+    /// Per-band score normalization: band (code or name) → score → the band's
+    /// own raw p5..p95, stretched onto the score's healthy range.
+    pub band_scoring: BTreeMap<String, crate::analyzer::jam_metrics::BandRanges>,
+    /// Feature extractors run as subprocesses by `external run`.
+    pub external_analyzers: Vec<crate::external::ExternalAnalyzerConfig>,
+    /// Command run when `analyze` finds new standouts (`[notify]`).
+    pub notify: NotifyConfig,
+    /// Commands run on pipeline events (`[[hooks]]`, see `hooks`).
+    pub hooks: Vec<crate::hooks::HookConfig>,
+}
+
+/// Settings that a collection root can override in its `.setbreak.toml`
+/// (at the top level there, under `[collection]` in the global config).
+#[derive(Debug, Clone, Deserialize, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectionConfig {
+    /// Band code or name for files whose band can't be parsed from the path.
+    pub band: Option<String>,
+    /// Recording type to assign instead of auto-classifying ("live", "studio", ...).
+    pub recording_type: Option<String>,
+    /// Path patterns to skip when scanning, relative to the collection root.
+    /// `*` matches any run of characters; a pattern without `/` matches any
+    /// single path component (e.g. `"*.dts"` skips every `.dts` directory).
+    pub ignore: Vec<String>,
+    /// Where parsed band/date/venue/disc/track/title come from first:
+    /// `"filename"` (default) or `"tags"`, for well-tagged, badly named files.
+    pub prefer: Option<MetadataPrecedence>,
+    /// How dates with the year last are read: `"mdy"` (default, "05-08-1977")
+    /// or `"dmy"` ("08-05-1977"). Year-first dates read the same either way.
+    pub date_order: Option<DateOrder>,
+    /// Analysis profile for the collection's tracks (`[analysis]`), e.g. full
+    /// PYIN quality for a small studio archive.
+    pub analysis: AnalysisOverrides,
+}
+
+/// The `[analysis]` settings a collection can override: the ones that shape
+/// each track's own analysis. Worker counts, throttling and artifacts stay global.
+#[derive(Debug, Clone, Deserialize, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalysisOverrides {
+    pub pyin_threshold_count: Option<usize>,
+    pub pyin_hop_multiplier: Option<usize>,
+    pub skip_classification_segments: Option<bool>,
+}
+
+impl AnalysisOverrides {
+    fn merged_with(&self, over: &AnalysisOverrides) -> AnalysisOverrides {
+        AnalysisOverrides {
+            pyin_threshold_count: over.pyin_threshold_count.or(self.pyin_threshold_count),
+            pyin_hop_multiplier: over.pyin_hop_multiplier.or(self.pyin_hop_multiplier),
+            skip_classification_segments: over
+                .skip_classification_segments
+                .or(self.skip_classification_segments),
+        }
+    }
+}
+
+/// Which source wins when a file's tags and its path both name a value.
+/// The other source only fills in what the preferred one lacks.
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataPrecedence {
+    #[default]
+    Filename,
+    Tags,
+}
+
+impl MetadataPrecedence {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MetadataPrecedence::Filename => "filename",
+            MetadataPrecedence::Tags => "tags",
+        }
+    }
+}
+
+impl CollectionConfig {
+    /// Layer `over` on top of `self`: set values replace, ignore patterns accumulate.
+    pub fn merged_with(&self, over: &CollectionConfig) -> CollectionConfig {
+        CollectionConfig {
+            band: over.band.clone().or_else(|| self.band.clone()),
+            recording_type: over
+                .recording_type
+                .clone()
+                .or_else(|| self.recording_type.clone()),
+            ignore: self.ignore.iter().chain(&over.ignore).cloned().collect(),
+            prefer: over.prefer.or(self.prefer),
+            date_order: over.date_order.or(self.date_order),
+            analysis: self.analysis.merged_with(&over.analysis),
+        }
+    }
+
+    /// Whether a file (path relative to the collection root) matches an ignore pattern.
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        let rel = relative.to_string_lossy().replace('\\', "/");
+        self.ignore.iter().any(|pattern| {
+            if pattern.contains('/') {
+                wildcard_match(pattern.trim_start_matches('/'), &rel)
+            } else {
+                rel.split('/').any(|comp| wildcard_match(pattern, comp))
+            }
+        })
+    }
+
+    /// Load a collection root's `.setbreak.toml`, if it has one.
+    /// Logs a warning and returns `None` if the file can't be read or parsed.
+    pub fn load_from_dir(root: &Path) -> Option<(PathBuf, CollectionConfig)> {
+        let path = root.join(COLLECTION_CONFIG_FILE);
+        if !path.is_file() {
+            return None;
+        }
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| toml::from_str(&contents).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(config) => {
+                log::info!("Loaded collection overrides from {}", path.display());
+                Some((path, config))
+            }
+            Err(e) => {
+                log::warn!("Ignoring {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// Case-sensitive glob match supporting `*` (any run, including `/`) and `?`.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star, matched)) = backtrack {
+            pi = star + 1;
+            ti = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
 }
 
 /// Archive.org API configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
 pub struct ArchiveConfig {
     /// Cache TTL in days before re-fetching from archive.org.
     pub cache_ttl_days: i64,
     /// Rate limit between API requests in milliseconds.
     pub rate_limit_ms: u64,
+    /// Contact (email or URL) added to the User-Agent so archive.org can reach you.
+    pub contact: Option<String>,
+    /// Maximum archive.org requests per run (0 = unlimited).
+    pub max_requests: u64,
+    /// Retries for throttled (429/503) or failed requests.
+    pub max_retries: u32,
+    /// Search pages fetched at once during `discover` (still paced by `rate_limit_ms`).
+    pub concurrency: usize,
 }
 
 impl Default for ArchiveConfig {
@@ -38,7 +223,160 @@ impl Default for ArchiveConfig {
         Self {
             cache_ttl_days: 30,
             rate_limit_ms: 500,
+            contact: None,
+            max_requests: 0,
+            max_retries: 4,
+            concurrency: 4,
+        }
+    }
+}
+
+/// Hook for new standouts after `analyze` (see `standouts`).
+#[derive(Debug, Clone, Deserialize, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Program and arguments; `{count}` and `{summary}` are substituted and the
+    /// standouts arrive on stdin as JSON. Empty = no hook.
+    pub command: Vec<String>,
+}
+
+/// Analyzer tuning: trade accuracy for speed without recompiling.
+/// The defaults are what setbreak's scores were calibrated against; changing
+/// the PYIN settings shifts pitch features, so rescore comparisons across
+/// settings aren't like for like.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalysisTuning {
+    /// Tracks per batch for `extract-boundaries`. 0 = workers × 4.
+    pub chunk_size: usize,
+    /// Decoded tracks queued for analysis workers. 0 = one per worker.
+    pub queue_depth: usize,
+    /// PYIN thresholds tried per frame (1-100; ferrous-waves' full quality is 100).
+    pub pyin_threshold_count: usize,
+    /// Run PYIN on every Nth analysis frame (1-16).
+    pub pyin_hop_multiplier: usize,
+    /// Skip per-segment content classification (only the overall class is stored).
+    pub skip_classification_segments: bool,
+    /// Raw per-frame series to keep in the artifact sidecar (see `artifacts`).
+    pub keep_artifacts: Vec<crate::artifacts::ArtifactKind>,
+    /// Cap on the artifact sidecar's blobs in MB, enforced after `analyze`. 0 = no cap.
+    pub artifact_budget_mb: u64,
+    /// CPU scheduling niceness for `analyze` (0-19; 0 leaves it unchanged).
+    pub nice: i32,
+    /// Disk priority for `analyze`: "normal", "low" or "idle" (Linux only).
+    pub ionice: crate::analyzer::throttle::IoPriority,
+    /// Share of the time each analysis worker may spend busy (1-100); workers
+    /// sleep between tracks to stay under it.
+    pub max_cpu_percent: u32,
+}
+
+impl Default for AnalysisTuning {
+    fn default() -> Self {
+        Self {
+            chunk_size: 0,
+            queue_depth: 0,
+            pyin_threshold_count: 25,
+            pyin_hop_multiplier: 2,
+            skip_classification_segments: true,
+            keep_artifacts: Vec::new(),
+            artifact_budget_mb: 0,
+            nice: 0,
+            ionice: Default::default(),
+            max_cpu_percent: 100,
+        }
+    }
+}
+
+impl AnalysisTuning {
+    /// Check ranges, so a typo fails at startup instead of mid-run.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=100).contains(&self.pyin_threshold_count) {
+            return Err(format!(
+                "pyin_threshold_count must be 1-100, got {}",
+                self.pyin_threshold_count
+            ));
+        }
+        if !(1..=16).contains(&self.pyin_hop_multiplier) {
+            return Err(format!(
+                "pyin_hop_multiplier must be 1-16, got {}",
+                self.pyin_hop_multiplier
+            ));
+        }
+        if !(0..=19).contains(&self.nice) {
+            return Err(format!("nice must be 0-19, got {}", self.nice));
+        }
+        if !(1..=100).contains(&self.max_cpu_percent) {
+            return Err(format!(
+                "max_cpu_percent must be 1-100, got {}",
+                self.max_cpu_percent
+            ));
         }
+        Ok(())
+    }
+
+    /// Boundary-extraction batch size for `jobs` workers.
+    pub fn resolve_chunk_size(&self, jobs: usize) -> usize {
+        if self.chunk_size > 0 {
+            self.chunk_size
+        } else {
+            jobs * 4
+        }
+    }
+
+    /// Decoded-track queue depth for `jobs` analysis workers.
+    pub fn resolve_queue_depth(&self, jobs: usize) -> usize {
+        if self.queue_depth > 0 {
+            self.queue_depth
+        } else {
+            jobs.max(1)
+        }
+    }
+
+    /// These settings with a collection's overrides applied.
+    pub fn with_overrides(&self, over: &AnalysisOverrides) -> AnalysisTuning {
+        AnalysisTuning {
+            pyin_threshold_count: over
+                .pyin_threshold_count
+                .unwrap_or(self.pyin_threshold_count),
+            pyin_hop_multiplier: over.pyin_hop_multiplier.unwrap_or(self.pyin_hop_multiplier),
+            skip_classification_segments: over
+                .skip_classification_segments
+                .unwrap_or(self.skip_classification_segments),
+            ..self.clone()
+        }
+    }
+}
+
+/// `[analysis]` tuning resolved per collection: each track is analyzed with the
+/// settings of the longest music directory containing it, `global` otherwise.
+#[derive(Debug, Clone)]
+pub struct TrackTuning {
+    /// Global `[analysis]` with the `[collection.analysis]` defaults applied;
+    /// also the source of the run-wide settings (throttling, artifacts).
+    pub global: AnalysisTuning,
+    roots: Vec<(String, AnalysisTuning)>,
+}
+
+impl TrackTuning {
+    /// The tuning for a track stored at `file_path`.
+    pub fn for_track(&self, file_path: &str) -> &AnalysisTuning {
+        self.roots
+            .iter()
+            .find(|(root, _)| {
+                file_path
+                    .strip_prefix(root.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .map_or(&self.global, |(_, tuning)| tuning)
+    }
+
+    /// Check every collection's resolved settings.
+    pub fn validate(&self) -> Result<(), String> {
+        self.global.validate()?;
+        for (root, tuning) in &self.roots {
+            tuning.validate().map_err(|e| format!("{root}: {e}"))?;
+        }
+        Ok(())
     }
 }
 
@@ -84,8 +422,53 @@ impl AppConfig {
         }
     }
 
+    /// Resolve decode worker count for `workers` analysis workers: 0 → workers / 2, min 1.
+    pub fn resolve_decode_workers(&self, workers: usize) -> usize {
+        if self.decode_workers > 0 {
+            self.decode_workers
+        } else {
+            (workers / 2).max(1)
+        }
+    }
+
+    /// Resolve the decode prefetch budget in MB: 0 → the built-in default.
+    pub fn resolve_prefetch_mb(&self) -> usize {
+        if self.prefetch_mb > 0 {
+            self.prefetch_mb
+        } else {
+            crate::analyzer::pipeline::DEFAULT_PREFETCH_MB
+        }
+    }
+
+    /// Effective collection settings for a music directory: global
+    /// `[collection]` defaults with the directory's `.setbreak.toml` on top.
+    pub fn collection_for(&self, root: &Path) -> CollectionConfig {
+        match CollectionConfig::load_from_dir(root) {
+            Some((_, over)) => self.collection.merged_with(&over),
+            None => self.collection.clone(),
+        }
+    }
+
+    /// Analysis tuning for tracks under each of `roots`, with their
+    /// `.setbreak.toml` `[analysis]` profiles applied.
+    pub fn track_tuning(&self, roots: &[PathBuf]) -> TrackTuning {
+        let mut tuned: Vec<(String, AnalysisTuning)> = roots
+            .iter()
+            .map(|root| {
+                let key = crate::paths::to_db(root).trim_end_matches('/').to_string();
+                let collection = self.collection_for(root);
+                (key, self.analysis.with_overrides(&collection.analysis))
+            })
+            .collect();
+        tuned.sort_by_key(|(root, _)| std::cmp::Reverse(root.len()));
+        TrackTuning {
+            global: self.analysis.with_overrides(&self.collection.analysis),
+            roots: tuned,
+        }
+    }
+
     /// Get the config file path.
-    fn config_path() -> Option<PathBuf> {
+    pub fn config_path() -> Option<PathBuf> {
         ProjectDirs::from("", "", crate::APP_NAME).map(|dirs| dirs.config_dir().join("config.toml"))
     }
 }
@@ -101,3 +484,119 @@ pub fn default_db_path() -> PathBuf {
         PathBuf::from("setbreak.db")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.dts", "gd77-05-08.dts"));
+        assert!(wildcard_match("gd??-*", "gd77-05-08"));
+        assert!(wildcard_match("bootlegs/*/*.mp3", "bootlegs/1977/t01.mp3"));
+        assert!(!wildcard_match("*.dts", "gd77-05-08.flac"));
+        assert!(!wildcard_match("gd?", "gd77"));
+    }
+
+    #[test]
+    fn test_collection_merge_and_ignore() {
+        let global = CollectionConfig {
+            band: Some("gd".into()),
+            recording_type: None,
+            ignore: vec!["*.dts".into()],
+            prefer: None,
+            date_order: Some(DateOrder::Dmy),
+            analysis: AnalysisOverrides::default(),
+        };
+        let local: CollectionConfig =
+            toml::from_str("band = \"jrad\"\nignore = [\"soundboards/*\"]\nprefer = \"tags\"")
+                .unwrap();
+        let merged = global.merged_with(&local);
+
+        assert_eq!(merged.band.as_deref(), Some("jrad"));
+        assert_eq!(merged.recording_type, None);
+        assert_eq!(merged.prefer, Some(MetadataPrecedence::Tags));
+        assert_eq!(merged.date_order, Some(DateOrder::Dmy));
+        assert!(merged.is_ignored(Path::new("2019/show.dts/t01.flac")));
+        assert!(merged.is_ignored(Path::new("soundboards/2019/t01.flac")));
+        assert!(!merged.is_ignored(Path::new("2019/aud/t01.flac")));
+    }
+
+    #[test]
+    fn test_analysis_tuning() {
+        let config: AppConfig =
+            toml::from_str("[analysis]\npyin_threshold_count = 100\nchunk_size = 8").unwrap();
+        assert_eq!(config.analysis.pyin_threshold_count, 100);
+        assert_eq!(config.analysis.pyin_hop_multiplier, 2);
+        assert_eq!(config.analysis.resolve_chunk_size(4), 8);
+        assert_eq!(config.analysis.resolve_queue_depth(4), 4);
+        assert!(config.analysis.validate().is_ok());
+
+        let bad: AppConfig = toml::from_str("[analysis]\npyin_hop_multiplier = 0").unwrap();
+        assert!(bad.analysis.validate().is_err());
+        assert!(toml::from_str::<AppConfig>("[analysis]\nfft = 4096").is_err());
+
+        let polite: AppConfig =
+            toml::from_str("[analysis]\nnice = 10\nionice = \"idle\"\nmax_cpu_percent = 50")
+                .unwrap();
+        assert_eq!(
+            polite.analysis.ionice,
+            crate::analyzer::throttle::IoPriority::Idle
+        );
+        assert!(polite.analysis.validate().is_ok());
+        let greedy: AppConfig = toml::from_str("[analysis]\nnice = -5").unwrap();
+        assert!(greedy.analysis.validate().is_err());
+        let stalled: AppConfig = toml::from_str("[analysis]\nmax_cpu_percent = 0").unwrap();
+        assert!(stalled.analysis.validate().is_err());
+
+        let keep: AppConfig =
+            toml::from_str("[analysis]\nkeep_artifacts = [\"chromagram\", \"loudness\"]").unwrap();
+        assert_eq!(keep.analysis.keep_artifacts.len(), 2);
+        assert!(toml::from_str::<AppConfig>("[analysis]\nkeep_artifacts = [\"mfcc\"]").is_err());
+    }
+
+    #[test]
+    fn test_collection_rejects_unknown_keys() {
+        assert!(toml::from_str::<CollectionConfig>("bnad = \"gd\"").is_err());
+        // Only per-track analysis settings can be overridden per collection
+        assert!(toml::from_str::<CollectionConfig>("[analysis]\nnice = 10").is_err());
+    }
+
+    #[test]
+    fn test_track_tuning_per_collection() {
+        let dir = std::env::temp_dir().join(format!("setbreak-config-{}", std::process::id()));
+        let studio = dir.join("studio");
+        let tapes = dir.join("tapes");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&studio).unwrap();
+        std::fs::create_dir_all(&tapes).unwrap();
+        std::fs::write(
+            studio.join(COLLECTION_CONFIG_FILE),
+            "[analysis]\npyin_threshold_count = 100\npyin_hop_multiplier = 1",
+        )
+        .unwrap();
+        let config: AppConfig =
+            toml::from_str("[analysis]\nnice = 5\n[collection.analysis]\npyin_hop_multiplier = 4")
+                .unwrap();
+        let tuning = config.track_tuning(&[studio.clone(), tapes.clone()]);
+        assert!(tuning.validate().is_ok());
+
+        let path = |root: &Path| format!("{}/t01.flac", crate::paths::to_db(root));
+        let full = tuning.for_track(&path(&studio));
+        assert_eq!(full.pyin_threshold_count, 100);
+        assert_eq!(full.pyin_hop_multiplier, 1);
+        assert_eq!(full.nice, 5);
+        let tapes_tuning = tuning.for_track(&path(&tapes));
+        assert_eq!(tapes_tuning.pyin_threshold_count, 25);
+        assert_eq!(tapes_tuning.pyin_hop_multiplier, 4);
+        assert_eq!(tuning.for_track("/elsewhere/t01.flac"), &tuning.global);
+
+        std::fs::write(
+            tapes.join(COLLECTION_CONFIG_FILE),
+            "[analysis]\npyin_hop_multiplier = 99",
+        )
+        .unwrap();
+        assert!(config.track_tuning(&[tapes]).validate().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,340 @@
+//! Credential store for third-party API keys (setlist.fm, MusicBrainz, webhooks, ...).
+//!
+//! Secrets live in `~/.config/setbreak/credentials.toml`, separate from `config.toml`
+//! so the main config can be shared or synced without leaking keys. The file is
+//! written with owner-only permissions on Unix. With the `keyring` cargo feature,
+//! a service can instead be stored in the OS keyring, leaving only a marker in the file:
+//!
+//! ```toml
+//! [setlistfm]
+//! storage = "plain"
+//! secret = "abc123"
+//!
+//! [musicbrainz]
+//! storage = "keyring"
+//! ```
+//!
+//! An environment variable `SETBREAK_<SERVICE>_KEY` (e.g. `SETBREAK_SETLISTFM_KEY`)
+//! always takes precedence over the stored value.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CredentialError {
+    #[error("I/O error on {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    #[error("Invalid service name '{0}' (use lowercase letters, digits, '-' or '_')")]
+    InvalidService(String),
+    #[error("OS keyring support not compiled in (rebuild with `--features keyring`)")]
+    KeyringUnavailable,
+    #[error("Keyring error: {0}")]
+    Keyring(String),
+    #[error("Could not determine the config directory")]
+    NoConfigDir,
+}
+
+/// Where a service's secret is kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "storage", rename_all = "lowercase")]
+pub enum CredentialEntry {
+    /// Stored directly in credentials.toml.
+    Plain { secret: String },
+    /// Stored in the OS keyring under service `setbreak`, user `<service>`.
+    Keyring,
+}
+
+/// The credentials file, keyed by service name.
+#[derive(Debug, Default)]
+pub struct CredentialStore {
+    path: PathBuf,
+    entries: BTreeMap<String, CredentialEntry>,
+}
+
+impl CredentialStore {
+    /// Load the store from the default location (empty if the file doesn't exist).
+    pub fn load() -> Result<Self, CredentialError> {
+        Self::load_from(&credentials_path().ok_or(CredentialError::NoConfigDir)?)
+    }
+
+    /// Load the store from a specific file (empty if it doesn't exist).
+    pub fn load_from(path: &Path) -> Result<Self, CredentialError> {
+        let entries = if path.exists() {
+            let contents = std::fs::read_to_string(path).map_err(|e| CredentialError::Io {
+                path: path.to_path_buf(),
+                source: e,
+            })?;
+            toml::from_str(&contents).map_err(|e| CredentialError::Parse {
+                path: path.to_path_buf(),
+                message: e.to_string(),
+            })?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Store a secret for `service`, in the OS keyring if `use_keyring`.
+    pub fn set(
+        &mut self,
+        service: &str,
+        secret: &str,
+        use_keyring: bool,
+    ) -> Result<(), CredentialError> {
+        validate_service(service)?;
+        let entry = if use_keyring {
+            keyring_set(service, secret)?;
+            CredentialEntry::Keyring
+        } else {
+            // Moving from keyring to plain: don't leave a stale secret behind
+            if self.entries.get(service) == Some(&CredentialEntry::Keyring) {
+                keyring_delete(service)?;
+            }
+            CredentialEntry::Plain {
+                secret: secret.to_string(),
+            }
+        };
+        self.entries.insert(service.to_string(), entry);
+        self.save()
+    }
+
+    /// Look up the secret for `service`. The `SETBREAK_<SERVICE>_KEY` env var wins.
+    pub fn get(&self, service: &str) -> Result<Option<String>, CredentialError> {
+        if let Ok(value) = std::env::var(env_var_name(service)) {
+            if !value.is_empty() {
+                return Ok(Some(value));
+            }
+        }
+        match self.entries.get(service) {
+            Some(CredentialEntry::Plain { secret }) => Ok(Some(secret.clone())),
+            Some(CredentialEntry::Keyring) => keyring_get(service).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove a service's secret. Returns true if it existed.
+    pub fn remove(&mut self, service: &str) -> Result<bool, CredentialError> {
+        match self.entries.remove(service) {
+            Some(entry) => {
+                if entry == CredentialEntry::Keyring {
+                    keyring_delete(service)?;
+                }
+                self.save()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Stored services and how each is kept, alphabetically.
+    pub fn services(&self) -> impl Iterator<Item = (&str, &CredentialEntry)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Write the store back to disk (owner read/write only on Unix).
+    fn save(&self) -> Result<(), CredentialError> {
+        let io_err = |e| CredentialError::Io {
+            path: self.path.clone(),
+            source: e,
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        let contents = toml::to_string(&self.entries).map_err(|e| CredentialError::Parse {
+            path: self.path.clone(),
+            message: e.to_string(),
+        })?;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&self.path).map_err(io_err)?;
+        // `mode` only applies to a new file: tighten an existing one before
+        // any secret goes into it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))
+                .map_err(io_err)?;
+        }
+        std::io::Write::write_all(&mut file, contents.as_bytes()).map_err(io_err)
+    }
+}
+
+/// Default credentials file: `~/.config/setbreak/credentials.toml`.
+pub fn credentials_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", crate::APP_NAME)
+        .map(|dirs| dirs.config_dir().join("credentials.toml"))
+}
+
+/// Environment variable that overrides a stored secret: `SETBREAK_<SERVICE>_KEY`.
+pub fn env_var_name(service: &str) -> String {
+    format!("SETBREAK_{}_KEY", service.to_uppercase().replace('-', "_"))
+}
+
+fn validate_service(service: &str) -> Result<(), CredentialError> {
+    let valid = !service.is_empty()
+        && service
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(CredentialError::InvalidService(service.to_string()))
+    }
+}
+
+// ── OS keyring backend ──────────────────────────────────────────────────
+
+#[cfg(feature = "keyring")]
+fn keyring_entry(service: &str) -> Result<keyring::Entry, CredentialError> {
+    keyring::Entry::new(crate::APP_NAME, service)
+        .map_err(|e| CredentialError::Keyring(e.to_string()))
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_set(service: &str, secret: &str) -> Result<(), CredentialError> {
+    keyring_entry(service)?
+        .set_password(secret)
+        .map_err(|e| CredentialError::Keyring(e.to_string()))
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_get(service: &str) -> Result<String, CredentialError> {
+    keyring_entry(service)?
+        .get_password()
+        .map_err(|e| CredentialError::Keyring(e.to_string()))
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_delete(service: &str) -> Result<(), CredentialError> {
+    match keyring_entry(service)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(CredentialError::Keyring(e.to_string())),
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_set(_service: &str, _secret: &str) -> Result<(), CredentialError> {
+    Err(CredentialError::KeyringUnavailable)
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_get(_service: &str) -> Result<String, CredentialError> {
+    Err(CredentialError::KeyringUnavailable)
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_delete(_service: &str) -> Result<(), CredentialError> {
+    Err(CredentialError::KeyringUnavailable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "setbreak-credentials-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("credentials.toml")
+    }
+
+    #[test]
+    fn test_plain_roundtrip() {
+        let path = temp_store("roundtrip");
+        let mut store = CredentialStore::load_from(&path).unwrap();
+        store.set("musicbrainz-test", "s3cret", false).unwrap();
+
+        let reloaded = CredentialStore::load_from(&path).unwrap();
+        assert_eq!(
+            reloaded.get("musicbrainz-test").unwrap().as_deref(),
+            Some("s3cret")
+        );
+        assert_eq!(reloaded.services().count(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut store = reloaded;
+        assert!(store.remove("musicbrainz-test").unwrap());
+        assert!(!store.remove("musicbrainz-test").unwrap());
+        assert!(store.get("musicbrainz-test").unwrap().is_none());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tightens_existing_file() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = temp_store("tighten");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut store = CredentialStore::load_from(&path).unwrap();
+        store.set("serve", "s3cret", false).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_invalid_service_name() {
+        let mut store = CredentialStore::load_from(&temp_store("invalid")).unwrap();
+        assert!(matches!(
+            store.set("Setlist FM", "x", false),
+            Err(CredentialError::InvalidService(_))
+        ));
+    }
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(env_var_name("setlistfm"), "SETBREAK_SETLISTFM_KEY");
+        assert_eq!(env_var_name("my-webhook"), "SETBREAK_MY_WEBHOOK_KEY");
+    }
+
+    #[test]
+    fn test_entry_toml_format() {
+        let mut entries = BTreeMap::new();
+        entries.insert(
+            "setlistfm".to_string(),
+            CredentialEntry::Plain {
+                secret: "abc".into(),
+            },
+        );
+        entries.insert("musicbrainz".to_string(), CredentialEntry::Keyring);
+        let text = toml::to_string(&entries).unwrap();
+        let back: BTreeMap<String, CredentialEntry> = toml::from_str(&text).unwrap();
+        assert_eq!(back, entries);
+        assert!(text.contains("storage = \"keyring\""));
+    }
+}
@@ -0,0 +1,304 @@
+//! Era fingerprints: estimating a recording's year from how it sounds.
+//!
+//! Tape hiss, high-frequency rolloff, spectral tilt, stereo image, and the
+//! band's instrument timbre (MFCCs) all drift over a band's career. Each show
+//! (date and band) is reduced to the mean of its tracks' era features, z-scored
+//! within the band, and compared with the band's other dated shows: the years
+//! of the nearest `NEIGHBORS` give an estimate and a likely range. No model is
+//! trained or stored — the library's own dated shows are the reference.
+//!
+//! `date-check` uses this two ways: a dated show whose year falls well outside
+//! its estimated range is listed as suspicious (a mislabeled directory, a
+//! wrong tag), and undated recording directories get a suggested year range.
+
+use crate::db::Database;
+use crate::db::columns::{BAND_EXPR, NOT_GARBAGE};
+use crate::similarity::{centroid, feature_stats, normalize_vector};
+use std::collections::BTreeMap;
+
+/// Per-track features that carry the era: recording chain and instrument sound.
+pub const ERA_FEATURES: &[&str] = &[
+    "noise_floor_db",
+    "snr_db",
+    "spectral_slope_mean",
+    "spectral_rolloff_mean",
+    "spectral_flatness_mean",
+    "sub_band_high_mean",
+    "sub_band_presence_mean",
+    "bass_treble_ratio_mean",
+    "stereo_width_mean",
+    "hnr",
+    "mfcc_1_mean",
+    "mfcc_2_mean",
+    "mfcc_3_mean",
+    "mfcc_4_mean",
+];
+
+/// Dated shows whose years are pooled into an estimate.
+const NEIGHBORS: usize = 7;
+
+/// Fewest dated shows a band needs before its recordings are checked.
+const MIN_REFERENCE: usize = 15;
+
+/// A show or undated directory with its estimated year range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EraEstimate {
+    /// Show date, or the directory name of an undated recording.
+    pub label: String,
+    pub band: String,
+    /// Year from the parsed date; `None` when undated.
+    pub year: Option<i32>,
+    /// Distance-weighted mean year of the nearest dated shows.
+    pub estimate: i32,
+    /// Likely range: the nearest shows' years, trimmed of one outlier each side.
+    pub range: (i32, i32),
+    pub tracks: usize,
+}
+
+impl EraEstimate {
+    /// Years between the parsed year and the estimated range (0 inside it).
+    pub fn deviation(&self) -> Option<i32> {
+        let year = self.year?;
+        Some(if year < self.range.0 {
+            self.range.0 - year
+        } else {
+            (year - self.range.1).max(0)
+        })
+    }
+}
+
+/// Result of `date-check`.
+#[derive(Debug, Default)]
+pub struct DateCheck {
+    /// Dated shows more than the tolerance outside their range, worst first.
+    pub suspicious: Vec<EraEstimate>,
+    /// Undated recording directories with a suggested range.
+    pub undated: Vec<EraEstimate>,
+    /// Bands skipped for having fewer than `MIN_REFERENCE` dated shows.
+    pub thin_bands: Vec<String>,
+    /// Dated shows compared.
+    pub checked: usize,
+}
+
+/// Estimate a year from reference `(year, vector)` pairs, skipping the
+/// reference at `exclude` (the show being checked). `None` without enough
+/// references.
+pub fn estimate(
+    reference: &[(i32, Vec<f64>)],
+    target: &[f64],
+    exclude: Option<usize>,
+) -> Option<(i32, (i32, i32))> {
+    let mut nearest: Vec<(f64, i32)> = reference
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| Some(*i) != exclude)
+        .map(|(_, (year, v))| {
+            let d: f64 = v.iter().zip(target).map(|(a, b)| (a - b).powi(2)).sum();
+            (d.sqrt(), *year)
+        })
+        .collect();
+    if nearest.len() < NEIGHBORS {
+        return None;
+    }
+    nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+    nearest.truncate(NEIGHBORS);
+
+    let weights: Vec<f64> = nearest.iter().map(|(d, _)| 1.0 / (d + 1e-6)).collect();
+    let total: f64 = weights.iter().sum();
+    let mean = nearest
+        .iter()
+        .zip(&weights)
+        .map(|((_, y), w)| *y as f64 * w)
+        .sum::<f64>()
+        / total;
+
+    let mut years: Vec<i32> = nearest.iter().map(|(_, y)| *y).collect();
+    years.sort_unstable();
+    let range = (years[1], years[years.len() - 2]);
+    Some((mean.round() as i32, range))
+}
+
+/// Check every band with enough dated shows (or just `band`, canonical name).
+/// Dated shows more than `tolerance` years outside their estimated range are
+/// reported as suspicious.
+pub fn check(db: &Database, band: Option<&str>, tolerance: i32) -> crate::db::Result<DateCheck> {
+    // (band, dated?, label) → track vectors
+    let mut groups: BTreeMap<(String, Option<i32>, String), Vec<Vec<f64>>> = BTreeMap::new();
+    for track in db.era_tracks()? {
+        if band.is_some_and(|b| b != track.band) {
+            continue;
+        }
+        let (year, label) = match track.date.as_deref().and_then(parse_year) {
+            Some(year) => (Some(year), track.date.unwrap_or_default()),
+            None => (
+                None,
+                crate::paths::source_dir(&track.file_path)
+                    .map(crate::paths::file_name)
+                    .unwrap_or("?")
+                    .to_string(),
+            ),
+        };
+        groups
+            .entry((track.band, year, label))
+            .or_default()
+            .push(track.features);
+    }
+
+    let mut by_band: BTreeMap<String, Vec<EraShow>> = BTreeMap::new();
+    for ((band, year, label), vectors) in groups {
+        by_band.entry(band).or_default().push(EraShow {
+            year,
+            label,
+            vector: centroid(&vectors),
+            tracks: vectors.len(),
+        });
+    }
+
+    let mut result = DateCheck::default();
+    for (band, shows) in by_band {
+        let dated = shows.iter().filter(|s| s.year.is_some()).count();
+        if dated < MIN_REFERENCE {
+            result.thin_bands.push(band);
+            continue;
+        }
+        let raw: Vec<(i64, Vec<f64>)> = shows.iter().map(|s| (0, s.vector.clone())).collect();
+        let (means, stds) = feature_stats(&raw, ERA_FEATURES.len());
+        let normalized: Vec<Vec<f64>> = shows
+            .iter()
+            .map(|s| normalize_vector(&s.vector, &means, &stds))
+            .collect();
+        let reference: Vec<(i32, Vec<f64>)> = shows
+            .iter()
+            .zip(&normalized)
+            .filter_map(|(s, v)| Some((s.year?, v.clone())))
+            .collect();
+
+        let mut dated_index = 0;
+        for (show, vector) in shows.into_iter().zip(&normalized) {
+            let exclude = show.year.map(|_| {
+                dated_index += 1;
+                dated_index - 1
+            });
+            let Some((estimate, range)) = self::estimate(&reference, vector, exclude) else {
+                continue;
+            };
+            let e = EraEstimate {
+                label: show.label,
+                band: band.clone(),
+                year: show.year,
+                estimate,
+                range,
+                tracks: show.tracks,
+            };
+            match e.deviation() {
+                Some(d) => {
+                    result.checked += 1;
+                    if d > tolerance {
+                        result.suspicious.push(e);
+                    }
+                }
+                None => result.undated.push(e),
+            }
+        }
+    }
+    result
+        .suspicious
+        .sort_by_key(|e| std::cmp::Reverse(e.deviation()));
+    Ok(result)
+}
+
+/// Year of a "YYYY..." date.
+fn parse_year(date: &str) -> Option<i32> {
+    date.get(..4)?
+        .parse()
+        .ok()
+        .filter(|y| (1900..=2100).contains(y))
+}
+
+/// A show (or undated directory) reduced to its tracks' mean era features.
+struct EraShow {
+    year: Option<i32>,
+    label: String,
+    vector: Vec<f64>,
+    tracks: usize,
+}
+
+/// One analyzed track's era features.
+struct EraTrack {
+    date: Option<String>,
+    band: String,
+    file_path: String,
+    features: Vec<f64>,
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Analyzed, non-garbage tracks with every era feature present.
+    fn era_tracks(&self) -> crate::db::Result<Vec<EraTrack>> {
+        let columns: Vec<String> = ERA_FEATURES.iter().map(|c| format!("a.{c}")).collect();
+        let present: Vec<String> = columns.iter().map(|c| format!("{c} IS NOT NULL")).collect();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT COALESCE(t.parsed_date, t.date), {BAND_EXPR}, t.file_path, {}
+             FROM analysis_results a JOIN tracks t ON t.id = a.track_id
+             WHERE {NOT_GARBAGE} AND {}",
+            columns.join(", "),
+            present.join(" AND ")
+        ))?;
+        let tracks = stmt
+            .query_map([], |row| {
+                let features = (0..ERA_FEATURES.len())
+                    .map(|i| row.get(3 + i))
+                    .collect::<rusqlite::Result<Vec<f64>>>()?;
+                Ok(EraTrack {
+                    date: row.get(0)?,
+                    band: row.get(1)?,
+                    file_path: row.get(2)?,
+                    features,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_from_neighbors() {
+        // One feature drifting with the year, two shows per year
+        let reference: Vec<(i32, Vec<f64>)> = (1966..1996)
+            .flat_map(|y| {
+                [
+                    (y, vec![(y - 1966) as f64]),
+                    (y, vec![(y - 1966) as f64 + 0.3]),
+                ]
+            })
+            .collect();
+
+        let (year, range) = estimate(&reference, &[11.1], None).unwrap();
+        assert_eq!(year, 1977);
+        assert!(range.0 >= 1975 && range.1 <= 1979, "{range:?}");
+
+        // The show being checked doesn't vote for itself
+        let (year, _) = estimate(&reference, &reference[0].1, Some(0)).unwrap();
+        assert!(year <= 1968);
+
+        let thin = &reference[..NEIGHBORS];
+        assert!(estimate(thin, &[0.0], Some(0)).is_none());
+
+        let e = EraEstimate {
+            label: "1985-06-21".into(),
+            band: "Grateful Dead".into(),
+            year: Some(1985),
+            estimate: 1972,
+            range: (1970, 1974),
+            tracks: 12,
+        };
+        assert_eq!(e.deviation(), Some(11));
+        assert_eq!(parse_year("1977-05-08"), Some(1977));
+        assert_eq!(parse_year("77-05-08"), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Show dates from free text: file names, directory names, tags and
+//! archive.org metadata all go through here.
+//!
+//! Tapers write dates year first ("gd77-05-08", "1977.05.08"), and that
+//! reading is the only one for dates whose year has two digits. A four-digit
+//! year at the end ("05-08-1977", "08.05.1977") is read month first or day
+//! first as the collection's `date_order` says, unless one of the two can only
+//! be a day. Two-digit years are this century up to the current year and the
+//! last one after it.
+//!
+//! Anything that isn't a real calendar date, or falls before `EARLIEST_YEAR`
+//! or after today, is no date at all: a wrong date splits or merges shows,
+//! while a missing one only leaves the track undated.
+
+use chrono::{Datelike, Local, NaiveDate};
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+/// Earliest year accepted as a show date.
+pub const EARLIEST_YEAR: i32 = 1900;
+
+/// Month and day order of dates written with the year last.
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    /// "05-08-1977" is May 8.
+    #[default]
+    Mdy,
+    /// "08-05-1977" is May 8.
+    Dmy,
+}
+
+impl DateOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DateOrder::Mdy => "mdy",
+            DateOrder::Dmy => "dmy",
+        }
+    }
+}
+
+// Three numbers joined by '.', '-' or '/', year first or year last.
+static DATE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?P<a>\d{1,4})[./-](?P<b>\d{1,2})[./-](?P<c>\d{1,4})").unwrap());
+
+/// First plausible date anywhere in free text ("1977-05-08 Barton Hall",
+/// "gd77.05.08", "08.05.1977"), as YYYY-MM-DD.
+pub fn find_date(text: &str, order: DateOrder) -> Option<String> {
+    DATE_RE
+        .captures_iter(text)
+        .find_map(|caps| interpret(&caps["a"], &caps["b"], &caps["c"], order))
+}
+
+/// A year-first date from its parts ("77", "5", "8"), if it's plausible.
+pub fn ymd(year: &str, month: &str, day: &str) -> Option<String> {
+    let year = match year.len() {
+        2 => expand_year(year.parse().ok()?),
+        4 => year.parse().ok()?,
+        _ => return None,
+    };
+    checked(year, month.parse().ok()?, day.parse().ok()?)
+}
+
+/// The leading YYYY-MM-DD of an ISO date or timestamp ("1977-05-08T00:00:00Z"),
+/// if it's plausible.
+pub fn parse_iso(raw: &str) -> Option<String> {
+    let date = NaiveDate::parse_from_str(raw.trim().get(..10)?, "%Y-%m-%d").ok()?;
+    plausible(date).then(|| date.format("%Y-%m-%d").to_string())
+}
+
+/// Whether a date could be a show: a year no earlier than `EARLIEST_YEAR`
+/// and not in the future.
+pub fn plausible(date: NaiveDate) -> bool {
+    date.year() >= EARLIEST_YEAR && date <= Local::now().date_naive()
+}
+
+/// Four-digit year for a two-digit one: this century up to the current year,
+/// the last one after it.
+pub fn expand_year(yy: i32) -> i32 {
+    let this_year = Local::now().year();
+    let year = this_year / 100 * 100 + yy;
+    if year > this_year { year - 100 } else { year }
+}
+
+/// Read three matched numbers as a date: year first unless the last one is a
+/// four-digit year.
+fn interpret(a: &str, b: &str, c: &str, order: DateOrder) -> Option<String> {
+    if c.len() == 4 {
+        if a.len() > 2 {
+            return None;
+        }
+        let (a, b): (u32, u32) = (a.parse().ok()?, b.parse().ok()?);
+        let (month, day) = match order {
+            _ if a > 12 => (b, a),
+            _ if b > 12 => (a, b),
+            DateOrder::Mdy => (a, b),
+            DateOrder::Dmy => (b, a),
+        };
+        return checked(c.parse().ok()?, month, day);
+    }
+    if c.len() > 2 {
+        return None;
+    }
+    ymd(a, b, c)
+}
+
+fn checked(year: i32, month: u32, day: u32) -> Option<String> {
+    let date = NaiveDate::from_ymd_opt(year, month, day)?;
+    plausible(date).then(|| date.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_year_first() {
+        let mdy = DateOrder::Mdy;
+        assert_eq!(
+            find_date("gd1977-05-08d1t01", mdy).as_deref(),
+            Some("1977-05-08")
+        );
+        assert_eq!(find_date("gd77.5.8", mdy).as_deref(), Some("1977-05-08"));
+        assert_eq!(find_date("ph09/12/31", mdy).as_deref(), Some("2009-12-31"));
+        // Two-digit groups are always year first, never "08-06-1971"
+        assert_eq!(find_date("gd08-06-71", mdy), None);
+        assert_eq!(find_date("1977-05-081", mdy), None);
+    }
+
+    #[test]
+    fn test_year_last_follows_order() {
+        assert_eq!(
+            find_date("05-08-1977 Barton Hall", DateOrder::Mdy).as_deref(),
+            Some("1977-05-08")
+        );
+        assert_eq!(
+            find_date("08.05.1977 Barton Hall", DateOrder::Dmy).as_deref(),
+            Some("1977-05-08")
+        );
+        // A part over 12 can only be the day, whatever the order
+        assert_eq!(
+            find_date("31.12.1999", DateOrder::Mdy).as_deref(),
+            Some("1999-12-31")
+        );
+        assert_eq!(
+            find_date("12/31/1999", DateOrder::Dmy).as_deref(),
+            Some("1999-12-31")
+        );
+    }
+
+    #[test]
+    fn test_rejects_impossible_dates() {
+        let mdy = DateOrder::Mdy;
+        assert_eq!(find_date("1977-02-30", mdy), None);
+        assert_eq!(find_date("2077-05-08", mdy), None);
+        assert_eq!(find_date("1877-05-08", mdy), None);
+        // The first plausible date wins
+        assert_eq!(
+            find_date("2077-01-01 1977-05-08", mdy).as_deref(),
+            Some("1977-05-08")
+        );
+        assert_eq!(ymd("77", "13", "1"), None);
+        assert_eq!(ymd("197", "5", "8"), None);
+    }
+
+    #[test]
+    fn test_expand_year() {
+        let yy = Local::now().year() % 100;
+        assert_eq!(expand_year(yy), Local::now().year());
+        assert_eq!(expand_year(77), 1977);
+        assert_eq!(expand_year(0), 2000);
+    }
+
+    #[test]
+    fn test_parse_iso() {
+        assert_eq!(
+            parse_iso("1977-05-08T00:00:00Z").as_deref(),
+            Some("1977-05-08")
+        );
+        assert_eq!(parse_iso(" 1977-05-08 ").as_deref(), Some("1977-05-08"));
+        assert_eq!(parse_iso("1977-13-08"), None);
+        assert_eq!(parse_iso("2077-05-08"), None);
+        assert_eq!(parse_iso("bad"), None);
+    }
+}
@@ -4,9 +4,12 @@
 //! - `SCORE_COLUMNS`: validated score column names for SQL ORDER BY
 //! - `TRACK_SCORE_SELECT`: shared SELECT fragment for TrackScore queries
 //! - `NOT_GARBAGE`: common WHERE clause filter
+//! - `SHOW_QUALITY`: share of a track's show that is clean
+//! - `PERFORMANCE_SONG`: versions of one song within a performance
+//! - `ANNOUNCER_TALK`: sorts talked-over versions after clean ones
 //! - `ANALYSIS_SCHEMA`: full column inventory for the `schema` command
 
-use super::models::TrackScore;
+use super::models::{TopFilter, TrackScore};
 
 // ---------------------------------------------------------------------------
 // Query helper constants
@@ -27,32 +30,124 @@ pub const SCORE_COLUMNS: &[&str] = &[
 ];
 
 /// SQL SELECT fragment shared by all TrackScore queries.
-/// Produces columns 0..15 matching `map_track_score` positional indices.
+/// Produces columns 0..16 matching `map_track_score` positional indices.
 /// Use with: `FROM analysis_results a JOIN tracks t ON t.id = a.track_id`
 pub const TRACK_SCORE_SELECT: &str = "COALESCE(t.parsed_title, t.title, '(untitled)'),
      COALESCE(t.parsed_date, t.date, '?'),
      COALESCE(a.duration, 0.0) / 60.0,
-     a.estimated_key, a.tempo_bpm,
+     a.estimated_key, COALESCE(a.tempo_bpm_corrected, a.tempo_bpm),
      COALESCE(a.energy_score, 0), COALESCE(a.intensity_score, 0),
      COALESCE(a.groove_score, 0), COALESCE(a.improvisation_score, 0),
      COALESCE(a.tightness_score, 0), COALESCE(a.build_quality_score, 0),
      COALESCE(a.exploratory_score, 0), COALESCE(a.transcendence_score, 0),
      COALESCE(a.valence_score, 0), COALESCE(a.arousal_score, 0),
-     COALESCE(t.file_path, '')";
+     COALESCE(t.file_path, ''),
+     COALESCE(t.parsed_band, t.artist),
+     COALESCE(t.parsed_set, t.set_name),
+     CASE WHEN t.recording_type = 'studio' THEN t.album END";
+
+/// SQL expression for a studio track's album (NULL for everything else), as
+/// read into `TrackScore::album`.
+pub const STUDIO_ALBUM: &str = "CASE WHEN t.recording_type = 'studio' THEN t.album END";
+
+/// SQL expression for a track's band: parsed (canonical) band, else the artist tag.
+/// Used to keep songs from different bands apart (e.g. two bands' "Not Fade Away").
+pub const BAND_EXPR: &str = "COALESCE(t.parsed_band, t.artist, '')";
 
 /// Common WHERE clause to exclude garbage-quality tracks.
 pub const NOT_GARBAGE: &str = "COALESCE(t.data_quality, 'ok') != 'garbage'";
 
+/// SQL expression for the share (0..1) of a track's show — same band and date —
+/// flagged clean rather than suspect or garbage. Undated tracks count as clean.
+pub const SHOW_QUALITY: &str = "COALESCE((
+    SELECT AVG(COALESCE(sq.data_quality, 'ok') = 'ok')
+    FROM tracks sq
+    WHERE sq.parsed_date = t.parsed_date
+      AND COALESCE(sq.parsed_band, sq.artist, '') = COALESCE(t.parsed_band, t.artist, '')
+), 1.0)";
+
+/// WHERE clause for tracks without a usable title: no parsed title, and the tag
+/// title is absent, empty, or a placeholder ("??", "unknown", "Track N").
+pub const MISSING_TITLE: &str = "t.parsed_title IS NULL
+    AND (t.title IS NULL OR t.title = '' OR t.title = '??'
+         OR LOWER(t.title) = 'unknown' OR LOWER(t.title) LIKE 'untitled%'
+         OR t.title LIKE 'Track __' OR t.title LIKE 'Track ___')";
+
 /// WHERE clause to show only live recordings (excludes studio, live_album, unknown).
 pub const LIVE_ONLY: &str = "COALESCE(t.recording_type, 'unknown') = 'live'";
 
+/// Sort key that puts a song's versions talked over by an announcer after the
+/// clean ones (false sorts first); unanalyzed rows count as clean. The
+/// threshold is `analyzer::announcer::ANNOUNCER_RATIO`.
+pub const ANNOUNCER_TALK: &str = "(COALESCE(a.announcer_ratio, 0) >= 0.1)";
+
+/// Window partition for one song at one performance: its versions on the
+/// night's different recordings. Tracks not linked to a performance stand alone.
+pub const PERFORMANCE_SONG: &str = "COALESCE(t.performance_id, -t.id),
+    COALESCE(LOWER(t.parsed_title), LOWER(t.title), t.id)";
+
+/// WHERE clause to skip tracks with a detected tape flip or splice.
+pub const NO_CUTS: &str = "COALESCE(a.has_cut, 0) = 0";
+
+/// WHERE conditions for a `top` ranking's filters over `analysis_results a JOIN
+/// tracks t`, garbage always excluded. Parameters are appended to `params`
+/// and numbered after any already there.
+pub fn top_filter_sql(
+    filter: &TopFilter,
+    params: &mut Vec<Box<dyn rusqlite::types::ToSql>>,
+) -> String {
+    let mut sql = NOT_GARBAGE.to_string();
+    if filter.live_only {
+        sql += &format!(" AND {LIVE_ONLY}");
+    }
+    if !filter.include_cuts {
+        sql += &format!(" AND {NO_CUTS}");
+    }
+    if !filter.include_untitled {
+        sql += &format!(" AND NOT ({MISSING_TITLE})");
+    }
+    if let Some(song) = filter.song {
+        params.push(Box::new(format!("%{song}%")));
+        sql += &format!(
+            " AND (t.parsed_title LIKE ?{n} OR t.title LIKE ?{n})",
+            n = params.len()
+        );
+    }
+    if let Some(band) = filter.band {
+        params.push(Box::new(band.to_string()));
+        sql += &format!(" AND LOWER({BAND_EXPR}) = LOWER(?{})", params.len());
+    }
+    if let Some(min_dur) = filter.min_duration_secs {
+        params.push(Box::new(min_dur));
+        sql += &format!(" AND a.duration >= ?{}", params.len());
+    }
+    if let Some(min_quality) = filter.min_show_quality {
+        params.push(Box::new(min_quality));
+        sql += &format!(" AND {SHOW_QUALITY} >= ?{}", params.len());
+    }
+    if let Some(guest) = filter.with_guest {
+        params.push(Box::new(format!("%{guest}%")));
+        sql += &format!(
+            " AND EXISTS (SELECT 1 FROM show_lineup l
+                 WHERE l.guest = 1 AND l.name LIKE ?{}
+                   AND l.date = COALESCE(t.parsed_date, t.date)
+                   AND LOWER(l.band) = LOWER({BAND_EXPR}))",
+            params.len()
+        );
+    }
+    sql
+}
+
 /// Map a rusqlite row (from TRACK_SCORE_SELECT) to a TrackScore.
-/// Expects columns 0..14 in the order produced by TRACK_SCORE_SELECT.
+/// Expects columns 0..18 in the order produced by TRACK_SCORE_SELECT.
 pub fn map_track_score(row: &rusqlite::Row) -> rusqlite::Result<TrackScore> {
     Ok(TrackScore {
         title: row.get(0)?,
         date: row.get(1)?,
         file_path: row.get(15)?,
+        band: row.get(16)?,
+        set: row.get(17)?,
+        album: row.get(18)?,
         duration_min: row.get(2)?,
         key: row.get(3)?,
         tempo: row.get(4)?,
@@ -81,6 +176,8 @@ pub struct ColumnDef {
 }
 
 /// Complete analysis_results schema (excludes id, track_id, analyzed_at).
+/// The JSON array columns (`super::BLOB_COLUMNS`) are stored in `analysis_blobs`;
+/// the `analysis_full` view has every column in one row.
 pub const ANALYSIS_SCHEMA: &[ColumnDef] = &[
     // ── Summary ──────────────────────────────────────────────────────
     ColumnDef {
@@ -405,6 +502,18 @@ pub const ANALYSIS_SCHEMA: &[ColumnDef] = &[
         category: "Temporal",
         description: "Estimated tempo (30-300 BPM)",
     },
+    ColumnDef {
+        name: "tempo_bpm_corrected",
+        sql_type: "REAL",
+        category: "Temporal",
+        description: "Tempo after half/double-time correction",
+    },
+    ColumnDef {
+        name: "tempo_confidence",
+        sql_type: "REAL",
+        category: "Temporal",
+        description: "Confidence in the corrected tempo's octave (0-1)",
+    },
     ColumnDef {
         name: "beat_count",
         sql_type: "INT",
@@ -448,6 +557,30 @@ pub const ANALYSIS_SCHEMA: &[ColumnDef] = &[
         category: "Pitch",
         description: "Highest detected pitch (Hz)",
     },
+    ColumnDef {
+        name: "mean_pitch_corrected",
+        sql_type: "REAL",
+        category: "Pitch",
+        description: "Average pitch after octave-error correction (Hz)",
+    },
+    ColumnDef {
+        name: "pitch_range_low_corrected",
+        sql_type: "REAL",
+        category: "Pitch",
+        description: "5th-percentile pitch after octave-error correction (Hz)",
+    },
+    ColumnDef {
+        name: "pitch_range_high_corrected",
+        sql_type: "REAL",
+        category: "Pitch",
+        description: "95th-percentile pitch after octave-error correction (Hz)",
+    },
+    ColumnDef {
+        name: "pitch_octave_errors",
+        sql_type: "REAL",
+        category: "Pitch",
+        description: "Share of pitch frames folded back from a harmonic (0-1)",
+    },
     ColumnDef {
         name: "pitch_stability",
         sql_type: "REAL",
@@ -1169,6 +1302,18 @@ pub const ANALYSIS_SCHEMA: &[ColumnDef] = &[
         category: "Classification",
         description: "Music vs non-music score",
     },
+    ColumnDef {
+        name: "classification_speech_score",
+        sql_type: "REAL",
+        category: "Classification",
+        description: "Speech score (interviews, stage talk)",
+    },
+    ColumnDef {
+        name: "announcer_ratio",
+        sql_type: "REAL",
+        category: "Classification",
+        description: "Share of the track with a voice over the music (0-1)",
+    },
     ColumnDef {
         name: "hnr",
         sql_type: "REAL",
@@ -1385,4 +1530,11 @@ pub const ANALYSIS_SCHEMA: &[ColumnDef] = &[
         category: "Rhythm",
         description: "Std dev of rolling flux CV (high = lock-in moments detected)",
     },
+    // v24: Cut detection
+    ColumnDef {
+        name: "has_cut",
+        sql_type: "INTEGER",
+        category: "Boundary",
+        description: "1 if a tape flip or splice was detected mid-track (see track_cuts)",
+    },
 ];
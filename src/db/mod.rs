@@ -1,9 +1,11 @@
 pub mod columns;
 pub mod models;
+pub mod prune;
 pub mod queries;
+pub mod readers;
 
 use rusqlite::Connection;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -12,12 +14,46 @@ pub enum DbError {
     Sqlite(#[from] rusqlite::Error),
     #[error("Migration failed: {0}")]
     Migration(String),
+    #[error(
+        "Database schema v{found} is newer than this build supports (v{supported}); upgrade setbreak"
+    )]
+    TooNew { found: i32, supported: i32 },
 }
 
 pub type Result<T> = std::result::Result<T, DbError>;
 
+/// Schema version this build migrates databases to.
+pub const SCHEMA_VERSION: i32 = 59;
+
+/// Array-valued (JSON) analysis columns, stored in `analysis_blobs` rather
+/// than `analysis_results`.
+pub const BLOB_COLUMNS: &[&str] = &[
+    "chroma_vector",
+    "spectral_contrast_json",
+    "tonnetz_json",
+    "beat_pattern_json",
+    "mfcc_delta_mean_json",
+    "mfcc_delta_delta_mean_json",
+    "temporal_modulation_json",
+    "onset_strength_contour_json",
+    "beat_loudness_band_ratio_json",
+];
+
+/// One row of the `schema_migrations` log.
+#[derive(Debug, Clone)]
+pub struct MigrationRecord {
+    pub version: i32,
+    pub applied_at: String,
+    pub duration_ms: i64,
+    pub app_version: String,
+    /// Copy of the database taken before this upgrade run, if any.
+    pub backup_path: Option<String>,
+}
+
 pub struct Database {
     pub conn: Connection,
+    /// File the connection is on; `None` for an in-memory database.
+    path: Option<PathBuf>,
 }
 
 impl Database {
@@ -26,18 +62,26 @@ impl Database {
             std::fs::create_dir_all(parent).ok();
         }
         let conn = Connection::open(path)?;
-        let db = Self { conn };
+        let db = Self {
+            conn,
+            path: Some(path.to_path_buf()),
+        };
         db.init()?;
         Ok(db)
     }
 
     pub fn open_in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
-        let db = Self { conn };
+        let db = Self { conn, path: None };
         db.init()?;
         Ok(db)
     }
 
+    /// File the database is on; `None` for an in-memory database.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
     fn init(&self) -> Result<()> {
         // WAL mode for better concurrent read performance
         self.conn.pragma_update(None, "journal_mode", "WAL")?;
@@ -52,69 +96,162 @@ impl Database {
             .conn
             .pragma_query_value(None, "user_version", |row| row.get(0))
             .unwrap_or(0);
-
-        if version < 1 {
-            self.migrate_v1()?;
-        }
-        if version < 2 {
-            self.migrate_v2()?;
-        }
-        if version < 3 {
-            self.migrate_v3()?;
-        }
-        if version < 4 {
-            self.migrate_v4()?;
-        }
-        if version < 5 {
-            self.migrate_v5()?;
-        }
-        if version < 6 {
-            self.migrate_v6()?;
+        if version > SCHEMA_VERSION {
+            return Err(DbError::TooNew {
+                found: version,
+                supported: SCHEMA_VERSION,
+            });
         }
-        if version < 7 {
-            self.migrate_v7()?;
-        }
-        if version < 8 {
-            self.migrate_v8()?;
-        }
-        if version < 9 {
-            self.migrate_v9()?;
-        }
-        if version < 10 {
-            self.migrate_v10()?;
-        }
-        if version < 11 {
-            self.migrate_v11()?;
-        }
-        if version < 12 {
-            self.migrate_v12()?;
-        }
-        if version < 13 {
-            self.migrate_v13()?;
-        }
-        if version < 14 {
-            self.migrate_v14()?;
-        }
-        if version < 15 {
-            self.migrate_v15()?;
-        }
-        if version < 16 {
-            self.migrate_v16()?;
-        }
-        if version < 17 {
-            self.migrate_v17()?;
-        }
-        if version < 18 {
-            self.migrate_v18()?;
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS schema_migrations (
+                version     INTEGER PRIMARY KEY,
+                applied_at  TEXT NOT NULL DEFAULT (datetime('now')),
+                duration_ms INTEGER NOT NULL,
+                app_version TEXT NOT NULL,
+                backup_path TEXT
+            );
+            ",
+        )?;
+        if version == SCHEMA_VERSION {
+            return Ok(());
         }
-        if version < 19 {
-            self.migrate_v19()?;
+        // Upgrading an existing database: keep a copy to go back to if a
+        // migration goes wrong in a way the transaction can't undo
+        let backup = if version > 0 {
+            self.backup_before_migration(version)?
+        } else {
+            None
+        };
+
+        let migrations: [fn(&Self) -> Result<()>; SCHEMA_VERSION as usize] = [
+            Self::migrate_v1,
+            Self::migrate_v2,
+            Self::migrate_v3,
+            Self::migrate_v4,
+            Self::migrate_v5,
+            Self::migrate_v6,
+            Self::migrate_v7,
+            Self::migrate_v8,
+            Self::migrate_v9,
+            Self::migrate_v10,
+            Self::migrate_v11,
+            Self::migrate_v12,
+            Self::migrate_v13,
+            Self::migrate_v14,
+            Self::migrate_v15,
+            Self::migrate_v16,
+            Self::migrate_v17,
+            Self::migrate_v18,
+            Self::migrate_v19,
+            Self::migrate_v20,
+            Self::migrate_v21,
+            Self::migrate_v22,
+            Self::migrate_v23,
+            Self::migrate_v24,
+            Self::migrate_v25,
+            Self::migrate_v26,
+            Self::migrate_v27,
+            Self::migrate_v28,
+            Self::migrate_v29,
+            Self::migrate_v30,
+            Self::migrate_v31,
+            Self::migrate_v32,
+            Self::migrate_v33,
+            Self::migrate_v34,
+            Self::migrate_v35,
+            Self::migrate_v36,
+            Self::migrate_v37,
+            Self::migrate_v38,
+            Self::migrate_v39,
+            Self::migrate_v40,
+            Self::migrate_v41,
+            Self::migrate_v42,
+            Self::migrate_v43,
+            Self::migrate_v44,
+            Self::migrate_v45,
+            Self::migrate_v46,
+            Self::migrate_v47,
+            Self::migrate_v48,
+            Self::migrate_v49,
+            Self::migrate_v50,
+            Self::migrate_v51,
+            Self::migrate_v52,
+            Self::migrate_v53,
+            Self::migrate_v54,
+            Self::migrate_v55,
+            Self::migrate_v56,
+            Self::migrate_v57,
+            Self::migrate_v58,
+            Self::migrate_v59,
+        ];
+        for (target, step) in (1..).zip(migrations).skip(version as usize) {
+            self.apply_migration(target, step, backup.as_deref())
+                .map_err(|e| DbError::Migration(format!("v{target}: {e}")))?;
         }
+        Ok(())
+    }
 
-        self.conn.pragma_update(None, "user_version", 19)?;
+    /// Run one migration in its own transaction, bumping `user_version` and
+    /// logging it only if every statement succeeded.
+    fn apply_migration(
+        &self,
+        target: i32,
+        step: fn(&Self) -> Result<()>,
+        backup: Option<&str>,
+    ) -> Result<()> {
+        let started = std::time::Instant::now();
+        let tx = self.conn.unchecked_transaction()?;
+        step(self)?;
+        self.conn.pragma_update(None, "user_version", target)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO schema_migrations (version, duration_ms, app_version, backup_path)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                target,
+                started.elapsed().as_millis() as i64,
+                env!("CARGO_PKG_VERSION"),
+                backup,
+            ],
+        )?;
+        tx.commit()?;
         Ok(())
     }
 
+    /// Copy a file-backed database to `<path>.v<version>.bak` before upgrading it.
+    /// Returns the backup path, or `None` for in-memory databases.
+    fn backup_before_migration(&self, version: i32) -> Result<Option<String>> {
+        let Some(path) = self.conn.path().filter(|p| !p.is_empty()) else {
+            return Ok(None);
+        };
+        let backup = format!("{path}.v{version}.bak");
+        // VACUUM INTO refuses to overwrite; an older backup of the same version is stale
+        let _ = std::fs::remove_file(&backup);
+        self.conn.execute("VACUUM INTO ?1", [&backup])?;
+        log::info!("Backed up schema v{version} database to {backup}");
+        Ok(Some(backup))
+    }
+
+    /// Migrations applied to this database, oldest first.
+    pub fn migration_log(&self) -> Result<Vec<MigrationRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT version, applied_at, duration_ms, app_version, backup_path
+             FROM schema_migrations ORDER BY version",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(MigrationRecord {
+                    version: row.get(0)?,
+                    applied_at: row.get(1)?,
+                    duration_ms: row.get(2)?,
+                    app_version: row.get(3)?,
+                    backup_path: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
     /// V1: Original schema — tracks + analysis_results
     fn migrate_v1(&self) -> Result<()> {
         self.conn.execute_batch(
@@ -813,6 +950,842 @@ impl Database {
         try_add_column(&self.conn, "analysis_results", "groove_stability_std REAL")?;
         Ok(())
     }
+
+    /// V20: Saved searches — named query expressions re-runnable as smart playlists.
+    fn migrate_v20(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS saved_searches (
+                name        TEXT PRIMARY KEY,
+                expr        TEXT NOT NULL,
+                sort_column TEXT,
+                all_types   INTEGER NOT NULL DEFAULT 0,
+                created_at  TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at  TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V21: Sound profiles — reference feature centroids built from a show or track set.
+    fn migrate_v21(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS sound_profiles (
+                name           TEXT PRIMARY KEY,
+                source         TEXT NOT NULL,
+                track_ids_json TEXT NOT NULL,
+                centroid_json  TEXT NOT NULL,
+                created_at     TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V22: Persisted segue chains, rebuilt when analysis, titles, or setlists change.
+    fn migrate_v22(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS chains (
+                id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+                date                TEXT NOT NULL,
+                songs_json          TEXT NOT NULL,
+                chain_length        INTEGER NOT NULL,
+                duration_min        REAL NOT NULL,
+                energy              REAL NOT NULL,
+                intensity           REAL NOT NULL,
+                groove              REAL NOT NULL,
+                improvisation       REAL NOT NULL,
+                tightness           REAL NOT NULL,
+                build_quality       REAL NOT NULL,
+                exploratory         REAL NOT NULL,
+                transcendence       REAL NOT NULL,
+                valence             REAL NOT NULL,
+                arousal             REAL NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_chains_date ON chains(date);
+
+            CREATE TABLE IF NOT EXISTS chain_tracks (
+                chain_id INTEGER NOT NULL REFERENCES chains(id) ON DELETE CASCADE,
+                position INTEGER NOT NULL,
+                track_id INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+
+                PRIMARY KEY (chain_id, position)
+            );
+
+            -- Single row: fingerprint of the inputs the chains table was built from
+            CREATE TABLE IF NOT EXISTS chain_cache_state (
+                id          INTEGER PRIMARY KEY CHECK (id = 1),
+                fingerprint TEXT NOT NULL,
+                built_at    TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V23: Show notes — archive.org item description and provenance fields per source.
+    fn migrate_v23(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS show_notes (
+                identifier  TEXT PRIMARY KEY,
+                local_dir   TEXT NOT NULL,
+                date        TEXT,
+                venue       TEXT,
+                source      TEXT,
+                lineage     TEXT,
+                taper       TEXT,
+                transferer  TEXT,
+                description TEXT,
+                notes       TEXT,
+                fetched_at  TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_show_notes_date ON show_notes(date);
+            CREATE INDEX IF NOT EXISTS idx_show_notes_dir ON show_notes(local_dir);
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V24: In-track cut detection — tape flip / splice points and a per-track flag.
+    fn migrate_v24(&self) -> Result<()> {
+        try_add_column(&self.conn, "analysis_results", "has_cut INTEGER")?;
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS track_cuts (
+                id            INTEGER PRIMARY KEY AUTOINCREMENT,
+                track_id      INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+                time          REAL NOT NULL,
+                drop_db       REAL NOT NULL,
+                discontinuity REAL NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_cuts_track ON track_cuts(track_id);
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V25: Track groups — jams split across files ("Dark Star (1)", "Dark Star (2)")
+    /// scored as one virtual track.
+    fn migrate_v25(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS track_groups (
+                id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+                title               TEXT NOT NULL,
+                date                TEXT NOT NULL,
+                part_count          INTEGER NOT NULL,
+                first_track_id      INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+                duration            REAL NOT NULL,
+                estimated_key       TEXT,
+                tempo_bpm           REAL,
+                has_cut             INTEGER NOT NULL DEFAULT 0,
+                energy_score        REAL,
+                intensity_score     REAL,
+                groove_score        REAL,
+                improvisation_score REAL,
+                tightness_score     REAL,
+                build_quality_score REAL,
+                exploratory_score   REAL,
+                transcendence_score REAL,
+                valence_score       REAL,
+                arousal_score       REAL
+            );
+
+            CREATE TABLE IF NOT EXISTS track_group_members (
+                group_id INTEGER NOT NULL REFERENCES track_groups(id) ON DELETE CASCADE,
+                part     INTEGER NOT NULL,
+                track_id INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+
+                PRIMARY KEY (group_id, part)
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_group_members_track
+                ON track_group_members(track_id);
+
+            -- Single row: fingerprint of the inputs the groups were built from
+            CREATE TABLE IF NOT EXISTS track_group_state (
+                id          INTEGER PRIMARY KEY CHECK (id = 1),
+                fingerprint TEXT NOT NULL,
+                built_at    TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V26: Source lineage parsed from directory names (sbd/aud/matrix, taper, transfer hints).
+    /// Existing rows are filled by `setbreak classify` or a forced rescan.
+    fn migrate_v26(&self) -> Result<()> {
+        try_add_column(&self.conn, "tracks", "source_type TEXT")?;
+        try_add_column(&self.conn, "tracks", "taper TEXT")?;
+        try_add_column(&self.conn, "tracks", "lineage_hint TEXT")?;
+        self.conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_tracks_source_type ON tracks(source_type);",
+        )?;
+        Ok(())
+    }
+
+    /// V27: Library size snapshots after each scan/analyze run, for `stats --history`.
+    fn migrate_v27(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS library_snapshots (
+                id        INTEGER PRIMARY KEY AUTOINCREMENT,
+                taken_at  TEXT NOT NULL DEFAULT (datetime('now')),
+                trigger   TEXT NOT NULL,
+                run_count INTEGER NOT NULL,
+                tracks    INTEGER NOT NULL,
+                analyzed  INTEGER NOT NULL,
+                shows     INTEGER NOT NULL,
+                bands     INTEGER NOT NULL,
+                hours     REAL NOT NULL,
+                bytes     INTEGER NOT NULL
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V28: Stale analyses — a rescan that sees a file's size or mtime change
+    /// flags its analysis row so `analyze` redoes it; storing a new analysis clears it.
+    fn migrate_v28(&self) -> Result<()> {
+        try_add_column(
+            &self.conn,
+            "analysis_results",
+            "stale INTEGER NOT NULL DEFAULT 0",
+        )?;
+        self.conn.execute_batch(
+            "
+            CREATE TRIGGER IF NOT EXISTS mark_analysis_stale
+            AFTER UPDATE OF file_size, file_modified ON tracks
+            WHEN OLD.file_size IS NOT NEW.file_size
+              OR OLD.file_modified IS NOT NEW.file_modified
+            BEGIN
+                UPDATE analysis_results SET stale = 1 WHERE track_id = NEW.id;
+            END;
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V29: Play counts imported from scrobble exports, one row per track and source.
+    fn migrate_v29(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS track_plays (
+                track_id    INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+                source      TEXT NOT NULL,
+                play_count  INTEGER NOT NULL,
+                last_played TEXT,
+
+                PRIMARY KEY (track_id, source)
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V30: How each setlist title was matched, plus a review queue for
+    /// matches too uncertain to apply automatically.
+    fn migrate_v30(&self) -> Result<()> {
+        try_add_column(&self.conn, "tracks", "title_match_method TEXT")?;
+        try_add_column(&self.conn, "tracks", "title_match_confidence REAL")?;
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS title_match_review (
+                track_id    INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE,
+                title       TEXT NOT NULL,
+                method      TEXT NOT NULL,
+                confidence  REAL NOT NULL,
+                identifier  TEXT,
+                rejected    INTEGER NOT NULL DEFAULT 0,
+                queued_at   TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V31: Tempo after half/double-time correction, with its confidence.
+    /// Filled by `analyze`, or from the tempo prior alone by `rescore`.
+    fn migrate_v31(&self) -> Result<()> {
+        try_add_column(&self.conn, "analysis_results", "tempo_bpm_corrected REAL")?;
+        try_add_column(&self.conn, "analysis_results", "tempo_confidence REAL")?;
+        self.conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_analysis_tempo_corrected
+                 ON analysis_results(tempo_bpm_corrected);",
+        )?;
+        Ok(())
+    }
+
+    /// V32: Each track's `analyzed_at` as of its last neighbor computation, so
+    /// `similar` can spot stale pairs and `similarity --changed-only` can refresh
+    /// just the affected neighborhoods. Existing neighbor lists are assumed current.
+    fn migrate_v32(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS similarity_state (
+                track_id     INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE,
+                analyzed_at  TEXT NOT NULL
+            );
+            INSERT OR IGNORE INTO similarity_state (track_id, analyzed_at)
+                SELECT a.track_id, a.analyzed_at FROM analysis_results a
+                WHERE EXISTS (SELECT 1 FROM track_similarity s WHERE s.track_id = a.track_id);
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V33: Songs found inside unsplit continuous recordings by `split-songs`,
+    /// as time ranges of the parent file. `boundary_score` is the strength of
+    /// the split that starts the song (NULL for the first).
+    fn migrate_v33(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS virtual_tracks (
+                id              INTEGER PRIMARY KEY,
+                track_id        INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+                song_index      INTEGER NOT NULL,
+                start_time      REAL NOT NULL,
+                end_time        REAL NOT NULL,
+                boundary_score  REAL,
+                after_break     INTEGER NOT NULL DEFAULT 0,
+                title           TEXT,
+                created_at      TEXT NOT NULL DEFAULT (datetime('now')),
+                UNIQUE(track_id, song_index)
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V34: archive.org crawl bookkeeping. A collection's cached listing only
+    /// counts once its crawl completes; until then the fetched pages are kept
+    /// so `discover` can resume instead of starting over. Existing caches are
+    /// taken as complete crawls started when their oldest row was fetched.
+    fn migrate_v34(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS archive_crawls (
+                collection    TEXT PRIMARY KEY,
+                started_at    TEXT NOT NULL DEFAULT (datetime('now')),
+                completed_at  TEXT
+            );
+            CREATE TABLE IF NOT EXISTS archive_crawl_pages (
+                collection    TEXT NOT NULL,
+                year_start    INTEGER NOT NULL,
+                year_end      INTEGER NOT NULL,
+                page_offset   INTEGER NOT NULL,
+                num_found     INTEGER NOT NULL,
+                shows         INTEGER NOT NULL,
+                fetched_at    TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (collection, year_start, page_offset)
+            );
+            INSERT OR IGNORE INTO archive_crawls (collection, started_at, completed_at)
+                SELECT collection, MIN(fetched_at), MIN(fetched_at)
+                FROM archive_shows GROUP BY collection;
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V35: ferrous-waves' speech score, the audio cue for marking interviews
+    /// and stage talk as non-music.
+    fn migrate_v35(&self) -> Result<()> {
+        try_add_column(
+            &self.conn,
+            "analysis_results",
+            "classification_speech_score REAL",
+        )?;
+        Ok(())
+    }
+
+    /// V36: Features from external analyzers. `external_features` starts with
+    /// just the key; each configured output adds its own column on first run.
+    /// `external_runs` records which analyzer has run on which track.
+    fn migrate_v36(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS external_features (
+                track_id  INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE
+            );
+            CREATE TABLE IF NOT EXISTS external_runs (
+                track_id  INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+                analyzer  TEXT NOT NULL,
+                ran_at    TEXT NOT NULL DEFAULT (datetime('now')),
+                error     TEXT,
+                PRIMARY KEY (track_id, analyzer)
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V37: Feature vector layout (`feature_vector::VERSION` and dimension)
+    /// that the stored neighbor lists and sound profiles were computed with.
+    /// Everything stored so far predates versioning and used layout 1.
+    fn migrate_v37(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS similarity_format (
+                id           INTEGER PRIMARY KEY CHECK (id = 1),
+                version      INTEGER NOT NULL,
+                dim          INTEGER NOT NULL,
+                computed_at  TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            INSERT OR IGNORE INTO similarity_format (id, version, dim)
+                SELECT 1, 1, 47 WHERE EXISTS (SELECT 1 FROM track_similarity);
+            ",
+        )?;
+        try_add_column(
+            &self.conn,
+            "sound_profiles",
+            "vector_version INTEGER NOT NULL DEFAULT 1",
+        )?;
+        Ok(())
+    }
+
+    /// V38: Song title aliases from `titles dedupe`: each misspelled or variant
+    /// title maps to the canonical spelling applied to `parsed_title`.
+    fn migrate_v38(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS title_aliases (
+                alias       TEXT PRIMARY KEY,
+                canonical   TEXT NOT NULL,
+                created_at  TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V39: Move the JSON array columns out of `analysis_results` into
+    /// `analysis_blobs`, so scans over scores and scalar features read half-size
+    /// rows. `analysis_full` joins them back for ad-hoc SQL against the old layout.
+    fn migrate_v39(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS analysis_blobs (
+                track_id                      INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE,
+                chroma_vector                 TEXT,
+                spectral_contrast_json        TEXT,
+                tonnetz_json                  TEXT,
+                beat_pattern_json             TEXT,
+                mfcc_delta_mean_json          TEXT,
+                mfcc_delta_delta_mean_json    TEXT,
+                temporal_modulation_json      TEXT,
+                onset_strength_contour_json   TEXT,
+                beat_loudness_band_ratio_json TEXT
+            );
+            ",
+        )?;
+        // Only columns still on analysis_results, so a re-run is a no-op
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name FROM pragma_table_info('analysis_results')")?;
+        let present: Vec<&str> = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .iter()
+            .filter_map(|name| BLOB_COLUMNS.iter().copied().find(|c| c == name))
+            .collect();
+        if !present.is_empty() {
+            let columns = present.join(", ");
+            let any_set = present
+                .iter()
+                .map(|c| format!("{c} IS NOT NULL"))
+                .collect::<Vec<_>>()
+                .join(" OR ");
+            self.conn.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO analysis_blobs (track_id, {columns})
+                     SELECT track_id, {columns} FROM analysis_results WHERE {any_set}"
+                ),
+                [],
+            )?;
+        }
+        for column in present {
+            self.conn.execute_batch(&format!(
+                "ALTER TABLE analysis_results DROP COLUMN {column}"
+            ))?;
+        }
+        let joined = BLOB_COLUMNS
+            .iter()
+            .map(|c| format!("b.{c}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.conn.execute_batch(&format!(
+            "
+            CREATE VIEW IF NOT EXISTS analysis_full AS
+                SELECT a.*, {joined}
+                FROM analysis_results a
+                LEFT JOIN analysis_blobs b ON b.track_id = a.track_id;
+            "
+        ))?;
+        Ok(())
+    }
+
+    /// V40: Windowed loudness — music-only LUFS and the short-term loudness
+    /// spread, so calibration isn't skewed by banter in single-file sets.
+    fn migrate_v40(&self) -> Result<()> {
+        for column in ["lufs_music", "lufs_window_p10", "lufs_window_p90"] {
+            try_add_column(&self.conn, "analysis_results", &format!("{column} REAL"))?;
+        }
+        Ok(())
+    }
+
+    /// V41: Metadata from a beets library (`import-beets`), keyed by track, with
+    /// the beets item id for writing scores back.
+    fn migrate_v41(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS beets_items (
+                track_id     INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE,
+                beets_id     INTEGER NOT NULL,
+                title        TEXT,
+                artist       TEXT,
+                album        TEXT,
+                mb_trackid   TEXT,
+                mb_albumid   TEXT,
+                mb_artistid  TEXT,
+                imported_at  TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V42: Index the tag date. Show lookups match `parsed_date OR date`, which
+    /// SQLite can only answer from indexes when both columns have one; without
+    /// it, every per-show query scanned `tracks`.
+    fn migrate_v42(&self) -> Result<()> {
+        self.conn
+            .execute_batch("CREATE INDEX IF NOT EXISTS idx_tracks_date ON tracks(date);")?;
+        Ok(())
+    }
+
+    /// V43: Show-level metrics (`shows`), one row per date and band, rebuilt
+    /// from track analysis when stale.
+    fn migrate_v43(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS shows (
+                date                TEXT NOT NULL,
+                band                TEXT NOT NULL,
+                tracks              INTEGER NOT NULL,
+                minutes             REAL NOT NULL,
+                jam_minutes         REAL NOT NULL,
+                jam_tracks          INTEGER NOT NULL,
+                longest_jam         TEXT,
+                longest_jam_minutes REAL,
+                PRIMARY KEY (date, band)
+            );
+
+            -- Single row: fingerprint of the inputs the shows were built from
+            CREATE TABLE IF NOT EXISTS show_state (
+                id          INTEGER PRIMARY KEY CHECK (id = 1),
+                fingerprint TEXT NOT NULL,
+                built_at    TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V44: Chains are now detected within each source directory of a date.
+    /// Drop the cache fingerprint so chains found across two tapes of the same
+    /// show are rebuilt away on next use.
+    fn migrate_v44(&self) -> Result<()> {
+        self.conn.execute_batch("DELETE FROM chain_cache_state;")?;
+        Ok(())
+    }
+
+    /// V45: archive.org community ratings per item, from `discover` listings
+    /// and `ratings` lookups of matched shows.
+    fn migrate_v45(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS archive_ratings (
+                identifier  TEXT PRIMARY KEY,
+                date        TEXT,
+                avg_rating  REAL,
+                num_reviews INTEGER NOT NULL DEFAULT 0,
+                fetched_at  TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_archive_ratings_date ON archive_ratings(date);
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V46: Performances (one band on one date) and the recordings of each.
+    /// `tracks.performance_id` ties every source of a night — SBD, AUD,
+    /// matrix — to the same performance; `performance_links` holds manual
+    /// links for a recording filed under the wrong date.
+    fn migrate_v46(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS performances (
+                id    INTEGER PRIMARY KEY,
+                band  TEXT NOT NULL,
+                date  TEXT NOT NULL,
+                UNIQUE (band, date)
+            );
+
+            CREATE TABLE IF NOT EXISTS performance_links (
+                source_dir TEXT PRIMARY KEY,
+                date       TEXT NOT NULL,
+                linked_at  TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        try_add_column(&self.conn, "tracks", "performance_id INTEGER")?;
+        self.conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_tracks_performance ON tracks(performance_id);",
+        )?;
+        Ok(())
+    }
+
+    /// V47: Corrected track boundaries for badly tracked shows (`retrack`).
+    /// Each segment of a recording runs from a time in one track to a time in
+    /// the same or a later one, so a split song or a "banter + song" track is
+    /// fixed without touching the files.
+    fn migrate_v47(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS retrack_segments (
+                id              INTEGER PRIMARY KEY,
+                source_dir      TEXT NOT NULL,
+                segment_index   INTEGER NOT NULL,
+                start_track_id  INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+                start_time      REAL NOT NULL,
+                end_track_id    INTEGER NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+                end_time        REAL NOT NULL,
+                boundary_score  REAL,
+                after_break     INTEGER NOT NULL DEFAULT 0,
+                created_at      TEXT NOT NULL DEFAULT (datetime('now')),
+                UNIQUE(source_dir, segment_index)
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V48: Preview analyses (`analyze --preview`), scored from excerpts of a
+    /// track. A plain `analyze` picks them up again for a full analysis.
+    fn migrate_v48(&self) -> Result<()> {
+        try_add_column(
+            &self.conn,
+            "analysis_results",
+            "preview INTEGER NOT NULL DEFAULT 0",
+        )?;
+        Ok(())
+    }
+
+    /// V49: Chain identity (date + canonical song list) for collapsing the same
+    /// chain found on several tapes of a show. Clears the chain cache so the
+    /// next read rebuilds it with identities and duplicates collapsed.
+    fn migrate_v49(&self) -> Result<()> {
+        try_add_column(&self.conn, "chains", "identity TEXT")?;
+        self.conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_chains_identity ON chains(identity);
+             DELETE FROM chain_cache_state;",
+        )?;
+        Ok(())
+    }
+
+    /// V50: Set changes are now hard chain boundaries. Drop the cache
+    /// fingerprint so chains spanning a set break are rebuilt away on next use.
+    fn migrate_v50(&self) -> Result<()> {
+        self.conn.execute_batch("DELETE FROM chain_cache_state;")?;
+        Ok(())
+    }
+
+    /// V51: Re-listen queue (`queue`): tracks added by hand or queued after
+    /// `analyze` as new standouts and outliers.
+    fn migrate_v51(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS listen_queue (
+                id          INTEGER PRIMARY KEY,
+                track_id    INTEGER NOT NULL UNIQUE REFERENCES tracks(id) ON DELETE CASCADE,
+                source      TEXT NOT NULL,
+                reason      TEXT,
+                added_at    TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V52: Backfill `tracks.duration_secs` from analyzed lengths for files
+    /// whose tags carried none (kept up to date by `analyze` from here on).
+    fn migrate_v52(&self) -> Result<()> {
+        self.backfill_durations()?;
+        Ok(())
+    }
+
+    /// V53: Segue edges (`segue-suggest`): pitch-class profile, level,
+    /// brightness and tempo of each track's first and last minute. Filled by
+    /// `analyze` and `extract-boundaries`.
+    fn migrate_v53(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS segue_edges (
+                track_id         INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE,
+                head_chroma      TEXT NOT NULL,
+                head_level_db    REAL NOT NULL,
+                head_brightness  REAL NOT NULL,
+                head_tempo       REAL,
+                tail_chroma      TEXT NOT NULL,
+                tail_level_db    REAL NOT NULL,
+                tail_brightness  REAL NOT NULL,
+                tail_tempo       REAL
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V54: What `rescore` and `calibrate` last ran on, so `refresh` can skip
+    /// them when nothing changed.
+    fn migrate_v54(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS refresh_state (
+                step        TEXT PRIMARY KEY,
+                fingerprint TEXT NOT NULL,
+                ran_at      TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V55: Per-track analysis wall time, for `stats --performance`.
+    fn migrate_v55(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS analysis_cost (
+                track_id      INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE,
+                version       TEXT NOT NULL,
+                preview       INTEGER NOT NULL DEFAULT 0,
+                audio_secs    REAL,
+                decode_secs   REAL NOT NULL,
+                analysis_secs REAL NOT NULL,
+                jobs          INTEGER NOT NULL,
+                recorded_at   TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V56: Pitch statistics after octave-error correction. Filled by `analyze`;
+    /// the pitch track isn't stored, so older rows keep NULLs until re-analyzed.
+    fn migrate_v56(&self) -> Result<()> {
+        for column in [
+            "mean_pitch_corrected",
+            "pitch_range_low_corrected",
+            "pitch_range_high_corrected",
+            "pitch_octave_errors",
+        ] {
+            try_add_column(&self.conn, "analysis_results", &format!("{column} REAL"))?;
+        }
+        Ok(())
+    }
+
+    /// V57: Share of each track with an announcer talking over the music.
+    /// Rows analyzed before this stay NULL, which rankings treat as clean.
+    fn migrate_v57(&self) -> Result<()> {
+        try_add_column(&self.conn, "analysis_results", "announcer_ratio REAL")
+    }
+
+    /// V58: Show lineups (`show_lineup`): musicians per show, guests marked,
+    /// from show notes or added by hand. Kept apart from `shows`, which is
+    /// rebuilt from analysis and only holds analyzed shows.
+    fn migrate_v58(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS show_lineup (
+                date   TEXT NOT NULL,
+                band   TEXT NOT NULL,
+                name   TEXT NOT NULL COLLATE NOCASE,
+                role   TEXT,
+                guest  INTEGER NOT NULL DEFAULT 1,
+                source TEXT NOT NULL,
+                PRIMARY KEY (date, band, name)
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// V59: Stable views for `sql` (see `crate::sql`). Their columns are a
+    /// contract: a later migration may append to them but never renames or
+    /// drops one.
+    fn migrate_v59(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "
+            CREATE VIEW IF NOT EXISTS v_tracks AS
+                SELECT t.id AS track_id,
+                       t.file_path,
+                       COALESCE(t.parsed_band, t.artist) AS band,
+                       COALESCE(t.parsed_date, t.date) AS date,
+                       COALESCE(t.parsed_title, t.title) AS title,
+                       COALESCE(t.parsed_set, t.set_name) AS set_name,
+                       COALESCE(t.parsed_disc, t.disc_number) AS disc,
+                       COALESCE(t.parsed_track, t.track_number) AS track_number,
+                       COALESCE(a.duration, t.duration_secs) / 60.0 AS minutes,
+                       t.recording_type,
+                       t.source_type,
+                       COALESCE(t.data_quality, 'ok') AS data_quality,
+                       t.performance_id,
+                       a.track_id IS NOT NULL AS analyzed
+                FROM tracks t
+                LEFT JOIN analysis_results a ON a.track_id = t.id;
+
+            CREATE VIEW IF NOT EXISTS v_scores AS
+                SELECT v.track_id, v.band, v.date, v.title, v.minutes, v.data_quality,
+                       a.energy_score AS energy,
+                       a.intensity_score AS intensity,
+                       a.groove_score AS groove,
+                       a.improvisation_score AS improvisation,
+                       a.tightness_score AS tightness,
+                       a.build_quality_score AS build_quality,
+                       a.exploratory_score AS exploratory,
+                       a.transcendence_score AS transcendence,
+                       a.valence_score AS valence,
+                       a.arousal_score AS arousal,
+                       COALESCE(a.tempo_bpm_corrected, a.tempo_bpm) AS tempo_bpm,
+                       a.estimated_key AS key,
+                       COALESCE(a.preview, 0) AS preview
+                FROM v_tracks v
+                JOIN analysis_results a ON a.track_id = v.track_id;
+
+            CREATE VIEW IF NOT EXISTS v_features AS
+                SELECT v.band, v.date, v.title, f.*
+                FROM v_tracks v
+                JOIN analysis_full f ON f.track_id = v.track_id;
+
+            CREATE VIEW IF NOT EXISTS v_shows AS
+                SELECT s.date, s.band, s.tracks, s.minutes, s.jam_minutes, s.jam_tracks,
+                       s.longest_jam, s.longest_jam_minutes,
+                       (SELECT GROUP_CONCAT(l.name, ', ') FROM show_lineup l
+                        WHERE l.date = s.date AND LOWER(l.band) = LOWER(s.band)
+                          AND l.guest = 1) AS guests
+                FROM shows s;
+            ",
+        )?;
+        Ok(())
+    }
 }
 
 /// Helper: try to add a column, ignore if it already exists.
@@ -824,3 +1797,106 @@ fn try_add_column(conn: &Connection, table: &str, column_def: &str) -> Result<()
         Err(e) => Err(e.into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        let db = Database::open_in_memory().unwrap();
+        assert_eq!(db.migration_log().unwrap().len(), SCHEMA_VERSION as usize);
+
+        let failing: fn(&Database) -> Result<()> = |db| {
+            db.conn
+                .execute_batch("CREATE TABLE half_done (x INTEGER); SELECT * FROM missing;")?;
+            Ok(())
+        };
+        assert!(
+            db.apply_migration(SCHEMA_VERSION + 1, failing, None)
+                .is_err()
+        );
+        let version: i32 = db
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        let leftover: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(leftover, 0);
+        assert_eq!(db.migration_log().unwrap().len(), SCHEMA_VERSION as usize);
+    }
+
+    #[test]
+    fn test_v39_moves_array_columns() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format)
+                     VALUES (1, '/m/a.flac', 1, '0', 'flac'), (2, '/m/b.flac', 1, '0', 'flac');
+                 ALTER TABLE analysis_results ADD COLUMN tonnetz_json TEXT;
+                 INSERT INTO analysis_results (track_id, tonnetz_json) VALUES (1, '[0.5]'), (2, NULL);",
+            )
+            .unwrap();
+        db.migrate_v39().unwrap();
+
+        let moved: Vec<(i64, String)> = db
+            .conn
+            .prepare("SELECT track_id, tonnetz_json FROM analysis_blobs")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(moved, vec![(1, "[0.5]".to_string())]);
+        assert!(
+            db.conn
+                .prepare("SELECT tonnetz_json FROM analysis_results")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_backup_and_downgrade_guard() {
+        let dir = std::env::temp_dir().join(format!("setbreak-migrate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("library.db");
+
+        // Pretend an older build left the database one version behind
+        let db = Database::open(&path).unwrap();
+        db.conn
+            .execute(
+                "DELETE FROM schema_migrations WHERE version = ?1",
+                [SCHEMA_VERSION],
+            )
+            .unwrap();
+        db.conn
+            .pragma_update(None, "user_version", SCHEMA_VERSION - 1)
+            .unwrap();
+        drop(db);
+
+        let db = Database::open(&path).unwrap();
+        let last = db.migration_log().unwrap().pop().unwrap();
+        assert_eq!(last.version, SCHEMA_VERSION);
+        let backup = last.backup_path.unwrap();
+        assert!(backup.ends_with(&format!(".v{}.bak", SCHEMA_VERSION - 1)));
+        assert!(Path::new(&backup).exists());
+
+        // A newer build's database is refused untouched
+        db.conn
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        drop(db);
+        assert!(matches!(
+            Database::open(&path),
+            Err(DbError::TooNew { found, .. }) if found == SCHEMA_VERSION + 1
+        ));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,4 +1,5 @@
 /// Data for inserting or updating a track (scan phase).
+#[derive(Default)]
 pub struct NewTrack {
     pub file_path: String,
     pub file_size: i64,
@@ -25,6 +26,11 @@ pub struct NewTrack {
 
     pub duration_secs: Option<f64>,
     pub recording_type: Option<String>,
+
+    /// Source lineage parsed from directory names (see `scanner::source`).
+    pub source_type: Option<String>,
+    pub taper: Option<String>,
+    pub lineage_hint: Option<String>,
 }
 
 /// A track row read from the database.
@@ -105,6 +111,10 @@ pub struct NewAnalysis {
     pub onset_count: Option<i32>,
     pub tempo_stability: Option<f64>,
     pub rhythmic_complexity: Option<f64>,
+    /// `tempo_bpm` after half/double-time correction (see `analyzer::tempo`).
+    pub tempo_bpm_corrected: Option<f64>,
+    /// Confidence (0-1) that `tempo_bpm_corrected` has the right octave.
+    pub tempo_confidence: Option<f64>,
 
     // Pitch
     pub mean_pitch: Option<f64>,
@@ -115,6 +125,12 @@ pub struct NewAnalysis {
     pub vibrato_presence: Option<f64>,
     pub vibrato_rate: Option<f64>,
     pub pitch_confidence_mean: Option<f64>,
+    /// Pitch statistics after octave-error correction (see `analyzer::pitch`).
+    pub mean_pitch_corrected: Option<f64>,
+    pub pitch_range_low_corrected: Option<f64>,
+    pub pitch_range_high_corrected: Option<f64>,
+    /// Share of confident pitch frames folded back from a harmonic (0-1).
+    pub pitch_octave_errors: Option<f64>,
 
     // Perceptual
     pub lufs_integrated: Option<f64>,
@@ -124,6 +140,11 @@ pub struct NewAnalysis {
     pub energy_level: Option<f64>,
     pub loudness_std: Option<f64>, // std of short-term loudness (3s window)
     pub peak_loudness: Option<f64>, // max momentary loudness
+    /// Loudness of the music only: short-term windows far below the track's
+    /// loud end (banter, tuning, silence) gated out.
+    pub lufs_music: Option<f64>,
+    pub lufs_window_p10: Option<f64>, // 10th percentile of short-term loudness
+    pub lufs_window_p90: Option<f64>, // 90th percentile of short-term loudness
     pub spectral_flux_skewness: Option<f64>, // skewness of flux: +ve = percussive hits, -ve = sustained
     pub spectral_centroid_slope: Option<f64>, // brightness trend over time (build = positive)
     pub energy_buildup_ratio: Option<f64>,   // energy last_third / first_third
@@ -279,6 +300,9 @@ pub struct NewAnalysis {
 
     // Classification
     pub classification_music_score: Option<f64>,
+    pub classification_speech_score: Option<f64>,
+    /// Share of the track with a voice over the music (see `analyzer::announcer`).
+    pub announcer_ratio: Option<f64>,
     pub hnr: Option<f64>,
 
     // Emotion (Russell circumplex)
@@ -322,6 +346,93 @@ pub struct NewAnalysis {
     pub spectral_novelty_std: Option<f64>,
     pub groove_stability_mean: Option<f64>,
     pub groove_stability_std: Option<f64>,
+
+    /// Features come from excerpts (`analyze --preview`), scaled to the full track.
+    pub preview: bool,
+}
+
+impl NewAnalysis {
+    /// Start building an analysis from externally extracted features, e.g. to
+    /// score them with `jam_metrics::score_batch`.
+    pub fn builder() -> NewAnalysisBuilder {
+        NewAnalysisBuilder::default()
+    }
+}
+
+/// Builder for a [`NewAnalysis`] holding the features the jam scores read.
+/// Anything not set stays `None`.
+#[derive(Default)]
+pub struct NewAnalysisBuilder {
+    analysis: NewAnalysis,
+}
+
+macro_rules! feature_setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, value: $ty) -> Self {
+                self.analysis.$field = Some(value);
+                self
+            }
+        )*
+    };
+}
+
+impl NewAnalysisBuilder {
+    pub fn track_id(mut self, track_id: i64) -> Self {
+        self.analysis.track_id = track_id;
+        self
+    }
+
+    feature_setters! {
+        duration: f64,
+        rms_level: f64,
+        dynamic_range: f64,
+        crest_factor: f64,
+        lufs_integrated: f64,
+        loudness_range: f64,
+        spectral_centroid_mean: f64,
+        spectral_centroid_std: f64,
+        spectral_flux_mean: f64,
+        spectral_flux_std: f64,
+        spectral_flatness_std: f64,
+        zcr_mean: f64,
+        zcr_std: f64,
+        sub_band_bass_mean: f64,
+        sub_band_bass_std: f64,
+        roughness_mean: f64,
+        onset_count: i32,
+        onset_strength_mean: f64,
+        onset_interval_entropy: f64,
+        beat_count: i32,
+        tempo_bpm: f64,
+        tempo_bpm_corrected: f64,
+        tempo_stability: f64,
+        rhythmic_complexity: f64,
+        pitch_stability: f64,
+        pitch_confidence_mean: f64,
+        pitch_range_low_corrected: f64,
+        pitch_range_high_corrected: f64,
+        chord_count: i32,
+        major_chord_ratio: f64,
+        key_change_count: i32,
+        key_alternatives_count: i32,
+        harmonic_percussive_ratio: f64,
+        chromagram_entropy: f64,
+        chroma_self_similarity_bandwidth: f64,
+        energy_level: f64,
+        peak_energy: f64,
+        energy_variance: f64,
+        energy_peak_count: i32,
+        dynamics_entropy: f64,
+        dynamics_peak_count: i32,
+        repetition_similarity: f64,
+        transition_count: i32,
+        section_diversity_score: f64,
+    }
+
+    pub fn build(self) -> NewAnalysis {
+        self.analysis
+    }
 }
 
 /// Chord event for relational storage.
@@ -362,6 +473,25 @@ pub struct TensionPointRecord {
     pub change_type: String,
 }
 
+/// Tape flip / splice point for relational storage.
+#[derive(Debug, Clone)]
+pub struct CutPointRecord {
+    pub track_id: i64,
+    pub time: f64,
+    pub drop_db: f64,
+    pub discontinuity: f64,
+}
+
+/// A structural segment as shown by `setbreak timeline`.
+#[derive(Debug, Clone)]
+pub struct TimelineSegment {
+    pub start_time: f64,
+    pub duration: f64,
+    /// Section type if classified, else the segment label.
+    pub section: String,
+    pub energy: Option<f64>,
+}
+
 /// Transition for relational storage.
 pub struct TransitionRecord {
     pub track_id: i64,
@@ -377,6 +507,14 @@ pub struct TrackScore {
     pub title: String,
     pub date: String,
     pub file_path: String,
+    /// Parsed band (or artist tag); None if unknown.
+    pub band: Option<String>,
+    /// Set the track was played in ("1", "II", "Encore", ...), from the parsed
+    /// filename or the set tag; None if unknown.
+    pub set: Option<String>,
+    /// Album, for studio recordings only: what places a studio track the way
+    /// a date places a live one.
+    pub album: Option<String>,
     pub duration_min: f64,
     pub key: Option<String>,
     pub tempo: Option<f64>,
@@ -392,6 +530,40 @@ pub struct TrackScore {
     pub arousal: f64,
 }
 
+impl TrackScore {
+    /// Where the track belongs, for display: the album for studio recordings,
+    /// the show date for everything else.
+    pub fn when(&self) -> &str {
+        self.album.as_deref().unwrap_or(&self.date)
+    }
+
+    /// Value of a jam score by its `analysis_results` column name.
+    pub fn score(&self, column: &str) -> Option<f64> {
+        Some(match column {
+            "energy_score" => self.energy,
+            "intensity_score" => self.intensity,
+            "groove_score" => self.groove,
+            "improvisation_score" => self.improvisation,
+            "tightness_score" => self.tightness,
+            "build_quality_score" => self.build_quality,
+            "exploratory_score" => self.exploratory,
+            "transcendence_score" => self.transcendence,
+            "valence_score" => self.valence,
+            "arousal_score" => self.arousal,
+            _ => return None,
+        })
+    }
+}
+
+/// A jam split across consecutive files, scored as one virtual track.
+#[derive(Debug, Clone)]
+pub struct TrackGroup {
+    /// Combined row: base title, first part's path, summed duration, merged scores.
+    pub combined: TrackScore,
+    /// The underlying files, in playing order.
+    pub parts: Vec<TrackScore>,
+}
+
 /// A chain of consecutive tracks connected by segue markers (->).
 #[derive(Debug, Clone)]
 pub struct ChainScore {
@@ -453,6 +625,23 @@ impl ChainScore {
     pub fn chain_title(&self) -> String {
         self.songs.join(" -> ")
     }
+
+    /// Value of a jam score by its `analysis_results` column name.
+    pub fn score(&self, column: &str) -> Option<f64> {
+        Some(match column {
+            "energy_score" => self.energy,
+            "intensity_score" => self.intensity,
+            "groove_score" => self.groove,
+            "improvisation_score" => self.improvisation,
+            "tightness_score" => self.tightness,
+            "build_quality_score" => self.build_quality,
+            "exploratory_score" => self.exploratory,
+            "transcendence_score" => self.transcendence,
+            "valence_score" => self.valence,
+            "arousal_score" => self.arousal,
+            _ => return None,
+        })
+    }
 }
 
 /// Strip trailing segue markers from a song title.
@@ -477,6 +666,19 @@ pub struct ArchiveShow {
     pub format_quality: i32, // flac=3, shn=2, mp3=1
 }
 
+/// One stored page of an unfinished archive.org crawl.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveCrawlPage {
+    /// Year range the page's query covered.
+    pub year_start: u32,
+    pub year_end: u32,
+    pub offset: usize,
+    /// Items the range's query matched in total.
+    pub num_found: usize,
+    /// Shows stored from this page.
+    pub shows: usize,
+}
+
 /// A missing show with best available tape info.
 #[derive(Debug, Clone)]
 pub struct MissingShow {
@@ -486,6 +688,10 @@ pub struct MissingShow {
     pub source_quality: i32,
     pub format_quality: i32,
     pub tape_count: usize,
+    /// A local show a day off that may be this one (no venue to confirm it).
+    pub near_local: Option<String>,
+    /// archive.org community rating pooled over the date's tapes.
+    pub rating: Option<crate::ratings::ShowRating>,
 }
 
 /// Library statistics.
@@ -493,18 +699,76 @@ pub struct MissingShow {
 pub struct LibraryStats {
     pub total_tracks: i64,
     pub analyzed_tracks: i64,
+    /// Analyses whose file changed since; `analyze` redoes them.
+    pub stale_analyses: i64,
+    /// Analyses scored from excerpts (`analyze --preview`); `analyze` upgrades them.
+    pub preview_analyses: i64,
     pub total_duration_hours: f64,
     pub formats: Vec<(String, i64)>,
     pub bands: Vec<(String, i64)>,
 }
 
+/// Filters shared by the `top` rankings (per-track, merged parts, and recipes).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TopFilter<'a> {
+    /// Song title substring.
+    pub song: Option<&'a str>,
+    /// Band name (see `columns::BAND_EXPR`), matched ignoring case.
+    pub band: Option<&'a str>,
+    pub min_duration_secs: Option<f64>,
+    pub live_only: bool,
+    pub include_cuts: bool,
+    /// Keep tracks with no usable title (see `columns::MISSING_TITLE`).
+    pub include_untitled: bool,
+    /// Minimum clean share of the track's show (see `columns::SHOW_QUALITY`).
+    pub min_show_quality: Option<f64>,
+    /// Guest name substring: keep shows with a matching guest in their lineup
+    /// (see `lineup`).
+    pub with_guest: Option<&'a str>,
+    /// Keep only the best-ranked version of a song per performance (see
+    /// `columns::PERFORMANCE_SONG`), clean versions before ones talked over
+    /// (`columns::ANNOUNCER_TALK`). Not applied to merged split jams.
+    pub per_performance: bool,
+    /// Ranked rows to skip before the first one returned (paging).
+    pub offset: usize,
+}
+
+/// Data-quality breakdown of one show's tracks.
+#[derive(Debug, Clone, Default)]
+pub struct ShowQuality {
+    pub clean: i64,
+    pub suspect: i64,
+    pub garbage: i64,
+    /// (title, flag) for every suspect or garbage track, in running order.
+    pub flagged: Vec<(String, String)>,
+}
+
+impl ShowQuality {
+    /// Share of the show's tracks that are clean, 0..1 (1.0 for an empty show).
+    pub fn clean_share(&self) -> f64 {
+        let total = self.clean + self.suspect + self.garbage;
+        if total == 0 {
+            1.0
+        } else {
+            self.clean as f64 / total as f64
+        }
+    }
+}
+
 /// A row of calibration data: track scores + LUFS + show grouping key.
 pub struct CalibrationRow {
     pub track_id: i64,
+    /// Music-only loudness where analyzed, else integrated LUFS.
     pub lufs: f64,
+    /// Whether `lufs` is the music-only value.
+    pub music_lufs: bool,
     pub scores: [Option<f64>; 10], // energy, intensity, groove, improv, tight, build, explor, trans, valence, arousal
     pub parsed_date: String,
     pub parsed_band: Option<String>,
+    pub file_path: String,
+    pub title: Option<String>,
+    pub source_type: Option<String>,
+    pub performance_id: Option<i64>,
 }
 
 /// A track row with boundary features for segue detection.
@@ -543,3 +807,43 @@ impl SegueTrackRow {
         })
     }
 }
+
+/// A named filter expression saved with `setbreak query --save`.
+#[derive(Debug, Clone)]
+pub struct SavedSearch {
+    pub name: String,
+    pub expr: String,
+    pub sort_column: Option<String>,
+    pub all_types: bool,
+    pub updated_at: String,
+}
+
+/// A reference "sound profile": the mean raw feature vector of a set of tracks.
+#[derive(Debug, Clone)]
+pub struct SoundProfile {
+    pub name: String,
+    /// Filter expression the source tracks were selected with.
+    pub source: String,
+    pub track_ids: Vec<i64>,
+    pub centroid: Vec<f64>,
+    /// `feature_vector::VERSION` the centroid was computed with.
+    pub vector_version: i64,
+    pub created_at: String,
+}
+
+/// Provenance notes for one archive.org source (tape) of a show.
+#[derive(Debug, Clone, Default)]
+pub struct ShowNotes {
+    pub identifier: String,
+    /// Local directory name the notes were fetched for; empty for notes
+    /// `discover` stored for a source not in the library.
+    pub local_dir: String,
+    pub date: Option<String>,
+    pub venue: Option<String>,
+    pub source: Option<String>,
+    pub lineage: Option<String>,
+    pub taper: Option<String>,
+    pub transferer: Option<String>,
+    pub description: Option<String>,
+    pub notes: Option<String>,
+}
@@ -0,0 +1,210 @@
+//! Detail-table pruning for garbage and deleted tracks.
+//!
+//! Per-track detail rows (chords, segments, tension points, transitions, cuts,
+//! segue edges, similarity neighbors) make up most of the database. Tracks flagged garbage
+//! never use them, and tracks whose files have been deleted can't be re-analyzed
+//! anyway, so their detail rows can go.
+
+use super::{Database, Result};
+
+/// Detail tables keyed by `track_id`, pruned for every target track.
+const DETAIL_TABLES: &[&str] = &[
+    "track_chords",
+    "track_segments",
+    "track_tension_points",
+    "track_transitions",
+    "track_cuts",
+    "analysis_blobs",
+    "segue_edges",
+];
+
+/// Outcome of a prune run.
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    /// Tracks flagged `data_quality = 'garbage'`.
+    pub garbage_tracks: usize,
+    /// Tracks whose file no longer exists on disk.
+    pub orphaned_tracks: usize,
+    /// Rows deleted per table (detail tables, `track_similarity`, and `tracks`).
+    pub rows_deleted: Vec<(&'static str, usize)>,
+    /// Database size before and after, in bytes (after is `None` for dry runs).
+    pub bytes_before: i64,
+    pub bytes_after: Option<i64>,
+}
+
+impl PruneReport {
+    pub fn total_rows(&self) -> usize {
+        self.rows_deleted.iter().map(|(_, n)| n).sum()
+    }
+}
+
+impl Database {
+    /// Remove detail rows for garbage-flagged tracks and tracks whose files are gone,
+    /// plus any rows left pointing at tracks that no longer exist.
+    ///
+    /// Deleted-file tracks are removed entirely (scores included) unless
+    /// `keep_aggregates`; garbage tracks always keep their `analysis_results` row so
+    /// they aren't picked up for re-analysis. With `dry_run` the deletes are rolled back.
+    pub fn prune_details(&self, keep_aggregates: bool, dry_run: bool) -> Result<PruneReport> {
+        let mut report = PruneReport {
+            bytes_before: self.database_size()?,
+            ..Default::default()
+        };
+
+        let garbage: Vec<i64> = self
+            .conn
+            .prepare("SELECT id FROM tracks WHERE data_quality = 'garbage'")?
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        let orphaned: Vec<i64> = self
+            .conn
+            .prepare("SELECT id, file_path FROM tracks")?
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            // Remote files can't be checked cheaply; a rescan of the share updates them
+            .filter(|(_, path)| {
+                !crate::remote::is_remote(path) && !crate::paths::to_fs(path).exists()
+            })
+            .map(|(id, _)| id)
+            .collect();
+        report.garbage_tracks = garbage.len();
+        report.orphaned_tracks = orphaned.len();
+
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS prune_ids (id INTEGER PRIMARY KEY);
+             DELETE FROM prune_ids;",
+        )?;
+        {
+            let mut stmt = tx.prepare("INSERT OR IGNORE INTO prune_ids (id) VALUES (?1)")?;
+            for id in garbage.iter().chain(&orphaned) {
+                stmt.execute([id])?;
+            }
+        }
+
+        for table in DETAIL_TABLES {
+            let n = tx.execute(
+                &format!(
+                    "DELETE FROM {table}
+                     WHERE track_id IN (SELECT id FROM prune_ids)
+                        OR track_id NOT IN (SELECT id FROM tracks)"
+                ),
+                [],
+            )?;
+            report.rows_deleted.push((table, n));
+        }
+        let n = tx.execute(
+            "DELETE FROM track_similarity
+             WHERE track_id IN (SELECT id FROM prune_ids)
+                OR similar_track_id IN (SELECT id FROM prune_ids)
+                OR track_id NOT IN (SELECT id FROM tracks)
+                OR similar_track_id NOT IN (SELECT id FROM tracks)",
+            [],
+        )?;
+        report.rows_deleted.push(("track_similarity", n));
+
+        if !keep_aggregates {
+            // Cascades to analysis_results and chain membership
+            let mut stmt = tx.prepare("DELETE FROM tracks WHERE id = ?1")?;
+            let mut n = 0;
+            for id in &orphaned {
+                n += stmt.execute([id])?;
+            }
+            report.rows_deleted.push(("tracks", n));
+        }
+
+        tx.execute_batch("DROP TABLE prune_ids;")?;
+        if dry_run {
+            tx.rollback()?;
+            return Ok(report);
+        }
+        tx.commit()?;
+
+        // Return freed pages to the filesystem
+        self.conn.execute_batch("VACUUM;")?;
+        report.bytes_after = Some(self.database_size()?);
+        Ok(report)
+    }
+
+    /// Current database size in bytes (page_count × page_size).
+    fn database_size(&self) -> Result<i64> {
+        let pages: i64 = self
+            .conn
+            .pragma_query_value(None, "page_count", |row| row.get(0))?;
+        let page_size: i64 = self
+            .conn
+            .pragma_query_value(None, "page_size", |row| row.get(0))?;
+        Ok(pages * page_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queries::tests::new_track;
+    use rusqlite::params;
+
+    fn add_track(db: &Database, path: &str, quality: &str) -> i64 {
+        let id = db.upsert_track(&new_track(path)).unwrap();
+        db.conn
+            .execute(
+                "UPDATE tracks SET data_quality = ?2 WHERE id = ?1",
+                params![id, quality],
+            )
+            .unwrap();
+        db.conn
+            .execute(
+                "INSERT INTO track_tension_points (track_id, time, tension, change_type)
+                 VALUES (?1, 1.0, 0.5, 'BuildUp')",
+                [id],
+            )
+            .unwrap();
+        id
+    }
+
+    fn tension_rows(db: &Database) -> i64 {
+        db.conn
+            .query_row("SELECT COUNT(*) FROM track_tension_points", [], |r| {
+                r.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_prune_details() {
+        let db = Database::open_in_memory().unwrap();
+        // Paths relative to the crate root, which is the test's working directory
+        let kept = add_track(&db, "Cargo.toml", "ok");
+        let garbage = add_track(&db, "src/lib.rs", "garbage");
+        add_track(&db, "/nonexistent/setbreak/t01.flac", "ok");
+
+        // Dry run reports but keeps everything
+        let report = db.prune_details(false, true).unwrap();
+        assert_eq!(report.garbage_tracks, 1);
+        assert_eq!(report.orphaned_tracks, 1);
+        // Two tension rows plus the deleted-file track itself
+        assert_eq!(report.total_rows(), 3);
+        assert!(report.bytes_after.is_none());
+        assert_eq!(tension_rows(&db), 3);
+
+        // Keeping aggregates: detail rows go, track rows stay
+        db.prune_details(true, false).unwrap();
+        assert_eq!(tension_rows(&db), 1);
+        assert_eq!(db.stats().unwrap().total_tracks, 3);
+
+        // Full prune removes the deleted-file track; the garbage track keeps its row
+        db.prune_details(false, false).unwrap();
+        let remaining: Vec<i64> = db
+            .conn
+            .prepare("SELECT id FROM tracks ORDER BY id")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(remaining, vec![kept, garbage]);
+    }
+}
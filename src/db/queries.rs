@@ -1,10 +1,49 @@
-use super::columns::{LIVE_ONLY, NOT_GARBAGE, SCORE_COLUMNS, TRACK_SCORE_SELECT, map_track_score};
+use super::columns::{
+    ANNOUNCER_TALK, BAND_EXPR, LIVE_ONLY, MISSING_TITLE, NOT_GARBAGE, PERFORMANCE_SONG,
+    SCORE_COLUMNS, TRACK_SCORE_SELECT, map_track_score, top_filter_sql,
+};
 use super::models::{
-    ArchiveShow, CalibrationRow, ChordEvent, LibraryStats, NewAnalysis, NewTrack, SegmentRecord,
-    SegueTrackRow, TensionPointRecord, Track, TrackScore, TransitionRecord,
+    ArchiveCrawlPage, ArchiveShow, CalibrationRow, ChordEvent, CutPointRecord, LibraryStats,
+    NewAnalysis, NewTrack, SegmentRecord, SegueTrackRow, ShowNotes, ShowQuality,
+    TensionPointRecord, TimelineSegment, TopFilter, Track, TrackScore, TransitionRecord,
 };
 use super::{Database, Result};
+use crate::feature_vector::{FEATURES, VectorFormat};
 use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+
+/// Upsert one `show_notes` row. An empty `local_dir` (notes from `discover`)
+/// keeps the directory a `setlist` run matched the source to.
+const STORE_SHOW_NOTES: &str = "INSERT INTO show_notes
+        (identifier, local_dir, date, venue, source, lineage, taper, transferer,
+         description, notes, fetched_at)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, datetime('now'))
+     ON CONFLICT(identifier) DO UPDATE SET
+        local_dir = CASE WHEN excluded.local_dir = '' THEN local_dir ELSE excluded.local_dir END,
+        date = COALESCE(excluded.date, date),
+        venue = excluded.venue,
+        source = excluded.source,
+        lineage = excluded.lineage,
+        taper = excluded.taper,
+        transferer = excluded.transferer,
+        description = excluded.description,
+        notes = excluded.notes,
+        fetched_at = excluded.fetched_at";
+
+fn show_notes_params(n: &ShowNotes) -> impl rusqlite::Params + '_ {
+    (
+        &n.identifier,
+        &n.local_dir,
+        &n.date,
+        &n.venue,
+        &n.source,
+        &n.lineage,
+        &n.taper,
+        &n.transferer,
+        &n.description,
+        &n.notes,
+    )
+}
 
 impl Database {
     /// Insert or update a track. Returns the track id.
@@ -16,14 +55,14 @@ impl Database {
                 set_name, venue, comment,
                 parsed_band, parsed_date, parsed_venue, parsed_disc,
                 parsed_track, parsed_set, parsed_title, duration_secs,
-                recording_type, updated_at
+                recording_type, source_type, taper, lineage_hint, updated_at
             ) VALUES (
                 ?1, ?2, ?3, ?4,
                 ?5, ?6, ?7, ?8, ?9, ?10,
                 ?11, ?12, ?13,
                 ?14, ?15, ?16, ?17,
                 ?18, ?19, ?20, ?21,
-                ?22, datetime('now')
+                ?22, ?23, ?24, ?25, datetime('now')
             )
             ON CONFLICT(file_path) DO UPDATE SET
                 file_size = excluded.file_size,
@@ -44,9 +83,17 @@ impl Database {
                 parsed_disc = excluded.parsed_disc,
                 parsed_track = excluded.parsed_track,
                 parsed_set = excluded.parsed_set,
-                parsed_title = excluded.parsed_title,
-                duration_secs = excluded.duration_secs,
-                recording_type = excluded.recording_type,
+                parsed_title = CASE WHEN tracks.title_match_method IN ('manual', 'beets')
+                    THEN tracks.parsed_title ELSE excluded.parsed_title END,
+                duration_secs = COALESCE(excluded.duration_secs,
+                    CASE WHEN tracks.file_size IS excluded.file_size
+                          AND tracks.file_modified IS excluded.file_modified
+                    THEN tracks.duration_secs END),
+                recording_type = CASE WHEN tracks.recording_type IN ('soundcheck', 'interview')
+                    THEN tracks.recording_type ELSE excluded.recording_type END,
+                source_type = excluded.source_type,
+                taper = excluded.taper,
+                lineage_hint = excluded.lineage_hint,
                 updated_at = datetime('now')
             ",
             params![
@@ -72,6 +119,9 @@ impl Database {
                 t.parsed_title,
                 t.duration_secs,
                 t.recording_type,
+                t.source_type,
+                t.taper,
+                t.lineage_hint,
             ],
         )?;
 
@@ -83,18 +133,29 @@ impl Database {
         Ok(id)
     }
 
-    /// Get all tracks that have not been analyzed yet.
+    /// Get all tracks that have not been analyzed yet, whose file changed
+    /// (size or mtime) since it was analyzed, or that only have a preview.
     pub fn get_unanalyzed_tracks(&self) -> Result<Vec<Track>> {
+        self.tracks_needing_analysis(true)
+    }
+
+    /// Get tracks with no analysis at all, or a stale one (for `analyze --preview`,
+    /// which leaves existing previews alone).
+    pub fn get_unpreviewed_tracks(&self) -> Result<Vec<Track>> {
+        self.tracks_needing_analysis(false)
+    }
+
+    fn tracks_needing_analysis(&self, include_previews: bool) -> Result<Vec<Track>> {
         let mut stmt = self.conn.prepare(
             "SELECT t.id, t.file_path, t.format, t.artist, t.parsed_band, t.parsed_date
              FROM tracks t
              LEFT JOIN analysis_results a ON a.track_id = t.id
-             WHERE a.id IS NULL
+             WHERE a.id IS NULL OR a.stale = 1 OR (?1 AND a.preview = 1)
              ORDER BY t.id",
         )?;
 
         let tracks = stmt
-            .query_map([], |row| {
+            .query_map(params![include_previews], |row| {
                 Ok(Track {
                     id: row.get(0)?,
                     file_path: row.get(1)?,
@@ -132,6 +193,30 @@ impl Database {
         Ok(tracks)
     }
 
+    /// Get one track by id.
+    pub fn get_track(&self, id: i64) -> Result<Option<Track>> {
+        let result = self.conn.query_row(
+            "SELECT id, file_path, format, artist, parsed_band, parsed_date
+             FROM tracks WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(Track {
+                    id: row.get(0)?,
+                    file_path: row.get(1)?,
+                    format: row.get(2)?,
+                    artist: row.get(3)?,
+                    parsed_band: row.get(4)?,
+                    parsed_date: row.get(5)?,
+                })
+            },
+        );
+        match result {
+            Ok(track) => Ok(Some(track)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Store all analysis data for a track in a single transaction.
     /// This includes the main analysis row plus relational detail tables.
     pub fn store_full_analysis(
@@ -141,6 +226,7 @@ impl Database {
         segments: &[SegmentRecord],
         tension: &[TensionPointRecord],
         transitions: &[TransitionRecord],
+        cuts: &[CutPointRecord],
     ) -> Result<()> {
         let tx = self.conn.unchecked_transaction()?;
 
@@ -164,6 +250,10 @@ impl Database {
             "DELETE FROM track_transitions WHERE track_id = ?1",
             params![a.track_id],
         )?;
+        tx.execute(
+            "DELETE FROM track_cuts WHERE track_id = ?1",
+            params![a.track_id],
+        )?;
 
         // Batch insert chords
         if !chords.is_empty() {
@@ -242,6 +332,21 @@ impl Database {
             }
         }
 
+        // Batch insert cut points and flag the track
+        if !cuts.is_empty() {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO track_cuts (track_id, time, drop_db, discontinuity)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for c in cuts {
+                stmt.execute(params![c.track_id, c.time, c.drop_db, c.discontinuity])?;
+            }
+        }
+        tx.execute(
+            "UPDATE analysis_results SET has_cut = ?2 WHERE track_id = ?1",
+            params![a.track_id, !cuts.is_empty()],
+        )?;
+
         tx.commit()?;
         Ok(())
     }
@@ -285,27 +390,26 @@ impl Database {
                 spectral_loudness_correlation,
                 spectral_skewness_mean, spectral_kurtosis_mean,
                 spectral_entropy_mean, spectral_entropy_std,
-                spectral_slope_mean, spectral_contrast_json,
+                spectral_slope_mean,
                 sub_band_flux_bass_mean, sub_band_flux_bass_std,
                 sub_band_flux_mid_mean, sub_band_flux_high_mean,
-                tonnetz_json, tonnetz_flux_mean, chroma_flux_mean,
-                beat_pattern_json, syncopation, pulse_clarity, offbeat_ratio,
+                tonnetz_flux_mean, chroma_flux_mean,
+                syncopation, pulse_clarity, offbeat_ratio,
                 spectral_spread_mean, spectral_spread_std,
                 spectral_crest_mean, spectral_crest_std,
                 roughness_mean, roughness_std,
-                mfcc_delta_mean_json, mfcc_delta_delta_mean_json,
                 stereo_width_mean, stereo_width_std,
                 attack_time_mean, attack_time_std, decay_time_mean, decay_time_std,
                 onset_strength_mean, onset_strength_std, onset_strength_skewness,
                 swing_ratio, microtiming_deviation_mean, microtiming_deviation_std,
-                microtiming_bias, temporal_modulation_json,
+                microtiming_bias,
                 chroma_self_similarity_bandwidth,
                 harmonic_percussive_ratio, chromagram_entropy,
                 spectral_contrast_slope, spectral_contrast_range,
-                onset_strength_contour_json, section_diversity_score,
+                section_diversity_score,
                 estimated_key, key_confidence, tonality, harmonic_complexity,
                 chord_count, chord_change_rate, mode_clarity, key_alternatives_count,
-                time_sig_numerator, time_sig_denominator, chroma_vector,
+                time_sig_numerator, time_sig_denominator,
                 recording_quality_score, snr_db, clipping_ratio, noise_floor_db,
                 segment_count, temporal_complexity, coherence_score,
                 energy_shape, peak_energy, energy_variance,
@@ -322,7 +426,7 @@ impl Database {
                 tail_rms_db, tail_silence_pct, head_rms_db, head_silence_pct,
                 centroid_dmean, centroid_dvar, flux_dmean, flux_dvar,
                 roughness_dmean, roughness_dvar, bass_energy_dmean,
-                beat_loudness_mean, beat_loudness_std, beat_loudness_band_ratio_json,
+                beat_loudness_mean, beat_loudness_std,
                 danceability, harmonic_section_count,
                 timbral_departure_max, timbral_departure_mean,
                 crowd_energy_mean, crowd_energy_std,
@@ -365,51 +469,52 @@ impl Database {
                 ?97,
                 ?98, ?99,
                 ?100, ?101,
-                ?102, ?103,
-                ?104, ?105,
-                ?106, ?107,
-                ?108, ?109, ?110,
-                ?111, ?112, ?113, ?114,
-                ?115, ?116,
-                ?117, ?118,
-                ?119, ?120,
-                ?121, ?122,
-                ?123, ?124,
-                ?125, ?126, ?127, ?128,
-                ?129, ?130, ?131,
-                ?132, ?133, ?134, ?135,
-                ?136, ?137,
-                ?138, ?139,
-                ?140, ?141,
-                ?142, ?143,
-                ?144, ?145, ?146, ?147,
-                ?148, ?149, ?150, ?151,
-                ?152, ?153, ?154,
-                ?155, ?156, ?157, ?158,
-                ?159, ?160, ?161,
-                ?162, ?163, ?164,
-                ?165, ?166,
-                ?167, ?168,
-                ?169, ?170, ?171,
-                ?172, ?173,
+                ?102,
+                ?103, ?104,
+                ?105, ?106,
+                ?107, ?108,
+                ?109, ?110, ?111,
+                ?112, ?113,
+                ?114, ?115,
+                ?116, ?117,
+                ?118, ?119,
+                ?120, ?121, ?122, ?123,
+                ?124, ?125, ?126,
+                ?127, ?128, ?129,
+                ?130,
+                ?131,
+                ?132, ?133,
+                ?134, ?135,
+                ?136,
+                ?137, ?138, ?139, ?140,
+                ?141, ?142, ?143, ?144,
+                ?145, ?146,
+                ?147, ?148, ?149, ?150,
+                ?151, ?152, ?153,
+                ?154, ?155, ?156,
+                ?157, ?158,
+                ?159, ?160,
+                ?161, ?162, ?163,
+                ?164, ?165,
+                ?166, ?167,
+                ?168, ?169, ?170,
+                ?171, ?172, ?173,
                 ?174, ?175,
-                ?176, ?177, ?178,
-                ?179, ?180, ?181,
-                ?182, ?183,
-                ?184, ?185,
+                ?176, ?177,
+                ?178, ?179, ?180, ?181,
+                ?182, ?183, ?184, ?185,
                 ?186, ?187, ?188, ?189,
-                ?190, ?191, ?192, ?193,
-                ?194, ?195, ?196, ?197,
-                ?198, ?199, ?200,
-                ?201, ?202, ?203,
-                ?204, ?205,
-                ?206, ?207,
-                ?208, ?209,
-                ?210, ?211,
-                ?212, ?213,
+                ?190, ?191, ?192,
+                ?193, ?194,
+                ?195, ?196,
+                ?197, ?198,
+                ?199, ?200,
+                ?201, ?202,
+                ?203, ?204,
                 datetime('now')
             )
             ON CONFLICT(track_id) DO UPDATE SET
+                stale = 0,
                 duration = excluded.duration,
                 sample_rate = excluded.sample_rate,
                 channels = excluded.channels,
@@ -498,15 +603,12 @@ impl Database {
                 spectral_entropy_mean = excluded.spectral_entropy_mean,
                 spectral_entropy_std = excluded.spectral_entropy_std,
                 spectral_slope_mean = excluded.spectral_slope_mean,
-                spectral_contrast_json = excluded.spectral_contrast_json,
                 sub_band_flux_bass_mean = excluded.sub_band_flux_bass_mean,
                 sub_band_flux_bass_std = excluded.sub_band_flux_bass_std,
                 sub_band_flux_mid_mean = excluded.sub_band_flux_mid_mean,
                 sub_band_flux_high_mean = excluded.sub_band_flux_high_mean,
-                tonnetz_json = excluded.tonnetz_json,
                 tonnetz_flux_mean = excluded.tonnetz_flux_mean,
                 chroma_flux_mean = excluded.chroma_flux_mean,
-                beat_pattern_json = excluded.beat_pattern_json,
                 syncopation = excluded.syncopation,
                 pulse_clarity = excluded.pulse_clarity,
                 offbeat_ratio = excluded.offbeat_ratio,
@@ -516,8 +618,6 @@ impl Database {
                 spectral_crest_std = excluded.spectral_crest_std,
                 roughness_mean = excluded.roughness_mean,
                 roughness_std = excluded.roughness_std,
-                mfcc_delta_mean_json = excluded.mfcc_delta_mean_json,
-                mfcc_delta_delta_mean_json = excluded.mfcc_delta_delta_mean_json,
                 stereo_width_mean = excluded.stereo_width_mean,
                 stereo_width_std = excluded.stereo_width_std,
                 attack_time_mean = excluded.attack_time_mean,
@@ -531,13 +631,11 @@ impl Database {
                 microtiming_deviation_mean = excluded.microtiming_deviation_mean,
                 microtiming_deviation_std = excluded.microtiming_deviation_std,
                 microtiming_bias = excluded.microtiming_bias,
-                temporal_modulation_json = excluded.temporal_modulation_json,
                 chroma_self_similarity_bandwidth = excluded.chroma_self_similarity_bandwidth,
                 harmonic_percussive_ratio = excluded.harmonic_percussive_ratio,
                 chromagram_entropy = excluded.chromagram_entropy,
                 spectral_contrast_slope = excluded.spectral_contrast_slope,
                 spectral_contrast_range = excluded.spectral_contrast_range,
-                onset_strength_contour_json = excluded.onset_strength_contour_json,
                 section_diversity_score = excluded.section_diversity_score,
                 estimated_key = excluded.estimated_key,
                 key_confidence = excluded.key_confidence,
@@ -549,7 +647,6 @@ impl Database {
                 key_alternatives_count = excluded.key_alternatives_count,
                 time_sig_numerator = excluded.time_sig_numerator,
                 time_sig_denominator = excluded.time_sig_denominator,
-                chroma_vector = excluded.chroma_vector,
                 recording_quality_score = excluded.recording_quality_score,
                 snr_db = excluded.snr_db,
                 clipping_ratio = excluded.clipping_ratio,
@@ -598,7 +695,6 @@ impl Database {
                 bass_energy_dmean = excluded.bass_energy_dmean,
                 beat_loudness_mean = excluded.beat_loudness_mean,
                 beat_loudness_std = excluded.beat_loudness_std,
-                beat_loudness_band_ratio_json = excluded.beat_loudness_band_ratio_json,
                 danceability = excluded.danceability,
                 harmonic_section_count = excluded.harmonic_section_count,
                 timbral_departure_max = excluded.timbral_departure_max,
@@ -647,27 +743,26 @@ impl Database {
                 a.spectral_loudness_correlation,
                 a.spectral_skewness_mean, a.spectral_kurtosis_mean,
                 a.spectral_entropy_mean, a.spectral_entropy_std,
-                a.spectral_slope_mean, a.spectral_contrast_json,
+                a.spectral_slope_mean,
                 a.sub_band_flux_bass_mean, a.sub_band_flux_bass_std,
                 a.sub_band_flux_mid_mean, a.sub_band_flux_high_mean,
-                a.tonnetz_json, a.tonnetz_flux_mean, a.chroma_flux_mean,
-                a.beat_pattern_json, a.syncopation, a.pulse_clarity, a.offbeat_ratio,
+                a.tonnetz_flux_mean, a.chroma_flux_mean,
+                a.syncopation, a.pulse_clarity, a.offbeat_ratio,
                 a.spectral_spread_mean, a.spectral_spread_std,
                 a.spectral_crest_mean, a.spectral_crest_std,
                 a.roughness_mean, a.roughness_std,
-                a.mfcc_delta_mean_json, a.mfcc_delta_delta_mean_json,
                 a.stereo_width_mean, a.stereo_width_std,
                 a.attack_time_mean, a.attack_time_std, a.decay_time_mean, a.decay_time_std,
                 a.onset_strength_mean, a.onset_strength_std, a.onset_strength_skewness,
                 a.swing_ratio, a.microtiming_deviation_mean, a.microtiming_deviation_std,
-                a.microtiming_bias, a.temporal_modulation_json,
+                a.microtiming_bias,
                 a.chroma_self_similarity_bandwidth,
                 a.harmonic_percussive_ratio, a.chromagram_entropy,
                 a.spectral_contrast_slope, a.spectral_contrast_range,
-                a.onset_strength_contour_json, a.section_diversity_score,
+                a.section_diversity_score,
                 a.estimated_key, a.key_confidence, a.tonality, a.harmonic_complexity,
                 a.chord_count, a.chord_change_rate, a.mode_clarity, a.key_alternatives_count,
-                a.time_sig_numerator, a.time_sig_denominator, a.chroma_vector,
+                a.time_sig_numerator, a.time_sig_denominator,
                 a.recording_quality_score, a.snr_db, a.clipping_ratio, a.noise_floor_db,
                 a.segment_count, a.temporal_complexity, a.coherence_score,
                 a.energy_shape, a.peak_energy, a.energy_variance,
@@ -684,7 +779,7 @@ impl Database {
                 a.tail_rms_db, a.tail_silence_pct, a.head_rms_db, a.head_silence_pct,
                 a.centroid_dmean, a.centroid_dvar, a.flux_dmean, a.flux_dvar,
                 a.roughness_dmean, a.roughness_dvar, a.bass_energy_dmean,
-                a.beat_loudness_mean, a.beat_loudness_std, a.beat_loudness_band_ratio_json,
+                a.beat_loudness_mean, a.beat_loudness_std,
                 a.danceability, a.harmonic_section_count,
                 a.timbral_departure_max, a.timbral_departure_mean,
                 a.crowd_energy_mean, a.crowd_energy_std,
@@ -692,6 +787,63 @@ impl Database {
                 a.groove_stability_mean, a.groove_stability_std,
             ],
         )?;
+        // Kept out of the bulk insert: written together, like has_cut
+        conn.execute(
+            "UPDATE analysis_results SET tempo_bpm_corrected = ?2, tempo_confidence = ?3,
+                    classification_speech_score = ?4, lufs_music = ?5,
+                    lufs_window_p10 = ?6, lufs_window_p90 = ?7, preview = ?8,
+                    mean_pitch_corrected = ?9, pitch_range_low_corrected = ?10,
+                    pitch_range_high_corrected = ?11, pitch_octave_errors = ?12,
+                    announcer_ratio = ?13
+             WHERE track_id = ?1",
+            params![
+                a.track_id,
+                a.tempo_bpm_corrected,
+                a.tempo_confidence,
+                a.classification_speech_score,
+                a.lufs_music,
+                a.lufs_window_p10,
+                a.lufs_window_p90,
+                a.preview,
+                a.mean_pitch_corrected,
+                a.pitch_range_low_corrected,
+                a.pitch_range_high_corrected,
+                a.pitch_octave_errors,
+                a.announcer_ratio
+            ],
+        )?;
+        // Per-band and per-coefficient arrays live beside the row so score
+        // queries scanning analysis_results don't page them in
+        conn.execute(
+            "INSERT INTO analysis_blobs (
+                track_id, chroma_vector, spectral_contrast_json, tonnetz_json,
+                beat_pattern_json, mfcc_delta_mean_json, mfcc_delta_delta_mean_json,
+                temporal_modulation_json, onset_strength_contour_json,
+                beat_loudness_band_ratio_json
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ON CONFLICT(track_id) DO UPDATE SET
+                chroma_vector = excluded.chroma_vector,
+                spectral_contrast_json = excluded.spectral_contrast_json,
+                tonnetz_json = excluded.tonnetz_json,
+                beat_pattern_json = excluded.beat_pattern_json,
+                mfcc_delta_mean_json = excluded.mfcc_delta_mean_json,
+                mfcc_delta_delta_mean_json = excluded.mfcc_delta_delta_mean_json,
+                temporal_modulation_json = excluded.temporal_modulation_json,
+                onset_strength_contour_json = excluded.onset_strength_contour_json,
+                beat_loudness_band_ratio_json = excluded.beat_loudness_band_ratio_json",
+            params![
+                a.track_id,
+                a.chroma_vector,
+                a.spectral_contrast_json,
+                a.tonnetz_json,
+                a.beat_pattern_json,
+                a.mfcc_delta_mean_json,
+                a.mfcc_delta_delta_mean_json,
+                a.temporal_modulation_json,
+                a.onset_strength_contour_json,
+                a.beat_loudness_band_ratio_json
+            ],
+        )?;
         Ok(())
     }
 
@@ -700,6 +852,15 @@ impl Database {
         Self::store_analysis_row(&self.conn, a)
     }
 
+    /// `parsed_band` by track id, for per-band score adjustments.
+    pub fn track_bands(&self) -> Result<HashMap<i64, String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, parsed_band FROM tracks WHERE parsed_band IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
     /// Load all analysis rows with fields needed for score computation.
     /// Returns NewAnalysis structs with score-relevant fields populated.
     pub fn get_analyses_for_rescore(&self) -> Result<Vec<NewAnalysis>> {
@@ -727,7 +888,9 @@ impl Database {
                 major_frame_ratio, major_chord_ratio,
                 dynamics_entropy, dynamics_slope,
                 dynamics_peak_count, key_change_count,
-                rhythmic_periodicity_strength
+                rhythmic_periodicity_strength,
+                tempo_bpm_corrected, tempo_confidence,
+                pitch_range_low_corrected, pitch_range_high_corrected
              FROM analysis_results",
         )?;
         let rows = stmt
@@ -746,10 +909,14 @@ impl Database {
                     onset_count: row.get(10)?,
                     beat_count: row.get(11)?,
                     tempo_bpm: row.get(12)?,
+                    tempo_bpm_corrected: row.get(54)?,
+                    tempo_confidence: row.get(55)?,
                     tempo_stability: row.get(13)?,
                     coherence_score: row.get(14)?,
                     pitch_range_low: row.get(15)?,
                     pitch_range_high: row.get(16)?,
+                    pitch_range_low_corrected: row.get(56)?,
+                    pitch_range_high_corrected: row.get(57)?,
                     harmonic_complexity: row.get(17)?,
                     key_confidence: row.get(18)?,
                     key_alternatives_count: row.get(19)?,
@@ -830,6 +997,8 @@ impl Database {
                     mfcc_12_std: None,
                     rhythmic_complexity: None,
                     mean_pitch: None,
+                    mean_pitch_corrected: None,
+                    pitch_octave_errors: None,
                     dominant_pitch: None,
                     vibrato_presence: None,
                     vibrato_rate: None,
@@ -846,9 +1015,14 @@ impl Database {
                     repetition_count: None,
                     solo_section_ratio: None,
                     classification_music_score: None,
+                    classification_speech_score: None,
+                    announcer_ratio: None,
                     hnr: None,
                     loudness_std: None,
                     peak_loudness: None,
+                    lufs_music: None,
+                    lufs_window_p10: None,
+                    lufs_window_p90: None,
                     spectral_flux_skewness: None,
                     spectral_centroid_slope: None,
                     energy_buildup_ratio: None,
@@ -947,12 +1121,23 @@ impl Database {
                     spectral_novelty_std: None,
                     groove_stability_mean: None,
                     groove_stability_std: None,
+                    preview: false,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(rows)
     }
 
+    /// Store a track's corrected tempo and its confidence.
+    pub fn update_tempo_correction(&self, track_id: i64, bpm: f64, confidence: f64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE analysis_results SET tempo_bpm_corrected = ?2, tempo_confidence = ?3
+             WHERE track_id = ?1",
+            params![track_id, bpm, confidence],
+        )?;
+        Ok(())
+    }
+
     /// Update only the 10 jam score columns for a given track.
     pub fn update_jam_scores(&self, a: &NewAnalysis) -> Result<()> {
         self.conn.execute(
@@ -979,6 +1164,48 @@ impl Database {
         Ok(())
     }
 
+    /// Structural segments of a track, in time order.
+    pub fn get_track_segments(&self, track_id: i64) -> Result<Vec<TimelineSegment>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT start_time, duration, COALESCE(section_type, label), energy
+             FROM track_segments
+             WHERE track_id = ?1
+             ORDER BY start_time",
+        )?;
+        let rows = stmt
+            .query_map(params![track_id], |row| {
+                Ok(TimelineSegment {
+                    start_time: row.get(0)?,
+                    duration: row.get(1)?,
+                    section: row.get(2)?,
+                    energy: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Tape flip / splice points detected in a track, in time order.
+    pub fn get_track_cuts(&self, track_id: i64) -> Result<Vec<CutPointRecord>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT track_id, time, drop_db, discontinuity
+             FROM track_cuts
+             WHERE track_id = ?1
+             ORDER BY time",
+        )?;
+        let rows = stmt
+            .query_map(params![track_id], |row| {
+                Ok(CutPointRecord {
+                    track_id: row.get(0)?,
+                    time: row.get(1)?,
+                    drop_db: row.get(2)?,
+                    discontinuity: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
     /// Load per-segment energy values for a track, ordered by time.
     /// Returns (start_time, energy) pairs for arc detection in build quality scoring.
     pub fn get_segment_energies(&self, track_id: i64) -> Result<Vec<(f64, f64)>> {
@@ -999,41 +1226,41 @@ impl Database {
         &self,
         score_column: &str,
         limit: usize,
-        song_filter: Option<&str>,
-        min_duration_secs: Option<f64>,
-        live_only: bool,
+        filter: &TopFilter,
     ) -> Result<Vec<TrackScore>> {
         if !SCORE_COLUMNS.contains(&score_column) {
             return Ok(vec![]);
         }
 
-        let mut sql = format!(
-            "SELECT {TRACK_SCORE_SELECT}
-             FROM analysis_results a
-             JOIN tracks t ON t.id = a.track_id
-             WHERE a.{score_column} IS NOT NULL
-               AND {NOT_GARBAGE}"
-        );
-        if live_only {
-            sql += &format!(" AND {LIVE_ONLY}");
-        }
-
         let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = vec![];
-
-        if let Some(song) = song_filter {
-            params_vec.push(Box::new(format!("%{song}%")));
-            sql += &format!(
-                " AND (t.parsed_title LIKE ?{n} OR t.title LIKE ?{n})",
-                n = params_vec.len()
-            );
-        }
-
-        if let Some(min_dur) = min_duration_secs {
-            params_vec.push(Box::new(min_dur));
-            sql += &format!(" AND a.duration >= ?{}", params_vec.len());
-        }
-
-        sql += &format!(" ORDER BY a.{score_column} DESC LIMIT {limit}");
+        let conditions = top_filter_sql(filter, &mut params_vec);
+        let offset = filter.offset;
+        let sql = if filter.per_performance {
+            format!(
+                "SELECT * FROM (
+                    SELECT {TRACK_SCORE_SELECT}, a.{score_column} AS rank_value,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY {PERFORMANCE_SONG}
+                            ORDER BY {ANNOUNCER_TALK}, a.{score_column} DESC, t.id
+                        ) AS version
+                    FROM analysis_results a
+                    JOIN tracks t ON t.id = a.track_id
+                    WHERE a.{score_column} IS NOT NULL
+                      AND {conditions}
+                 )
+                 WHERE version = 1
+                 ORDER BY rank_value DESC LIMIT {limit} OFFSET {offset}"
+            )
+        } else {
+            format!(
+                "SELECT {TRACK_SCORE_SELECT}
+                 FROM analysis_results a
+                 JOIN tracks t ON t.id = a.track_id
+                 WHERE a.{score_column} IS NOT NULL
+                   AND {conditions}
+                 ORDER BY a.{score_column} DESC LIMIT {limit} OFFSET {offset}"
+            )
+        };
 
         let params_refs: Vec<&dyn rusqlite::types::ToSql> =
             params_vec.iter().map(|p| p.as_ref()).collect();
@@ -1044,6 +1271,31 @@ impl Database {
         Ok(rows)
     }
 
+    /// Analyzed tracks that pass `filter` except for having no usable title —
+    /// what `top` leaves out unless `include_untitled` is set.
+    pub fn count_untitled_excluded(&self, filter: &TopFilter) -> Result<usize> {
+        let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = vec![];
+        let conditions = top_filter_sql(
+            &TopFilter {
+                include_untitled: true,
+                ..*filter
+            },
+            &mut params_vec,
+        );
+        let sql = format!(
+            "SELECT COUNT(*)
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {conditions} AND {MISSING_TITLE}"
+        );
+        let params_refs: Vec<&dyn rusqlite::types::ToSql> =
+            params_vec.iter().map(|p| p.as_ref()).collect();
+        let n: i64 = self
+            .conn
+            .query_row(&sql, params_refs.as_slice(), |row| row.get(0))?;
+        Ok(n as usize)
+    }
+
     /// Compare versions of a song across shows.
     pub fn query_compare(
         &self,
@@ -1051,6 +1303,8 @@ impl Database {
         sort_by: &str,
         limit: usize,
         live_only: bool,
+        band: Option<&str>,
+        per_band: bool,
     ) -> Result<Vec<TrackScore>> {
         let order_col = if SCORE_COLUMNS.contains(&sort_by) || sort_by == "duration" {
             sort_by
@@ -1063,21 +1317,33 @@ impl Database {
         } else {
             String::new()
         };
+        // Per-band mode applies the limit within each band so a prolific band
+        // can't crowd another band's versions out of the results.
+        let partition = if per_band {
+            format!("PARTITION BY {BAND_EXPR}")
+        } else {
+            String::new()
+        };
         let sql = format!(
-            "SELECT {TRACK_SCORE_SELECT}
-             FROM analysis_results a
-             JOIN tracks t ON t.id = a.track_id
-             WHERE (t.parsed_title LIKE ?1 OR t.title LIKE ?1)
-               AND {NOT_GARBAGE}
-               {live_filter}
-             ORDER BY a.{order_col} DESC
-             LIMIT ?2"
+            "SELECT * FROM (
+                SELECT {TRACK_SCORE_SELECT},
+                    ROW_NUMBER() OVER ({partition} ORDER BY a.{order_col} DESC) AS rn,
+                    a.{order_col} AS sort_val
+                FROM analysis_results a
+                JOIN tracks t ON t.id = a.track_id
+                WHERE (t.parsed_title LIKE ?1 OR t.title LIKE ?1)
+                  AND (?3 IS NULL OR {BAND_EXPR} = ?3)
+                  AND {NOT_GARBAGE}
+                  {live_filter}
+             )
+             WHERE rn <= ?2
+             ORDER BY sort_val DESC"
         );
 
         let pattern = format!("%{song}%");
         let mut stmt = self.conn.prepare(&sql)?;
         let rows = stmt
-            .query_map(params![pattern, limit as i64], map_track_score)?
+            .query_map(params![pattern, limit as i64, band], map_track_score)?
             .collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(rows)
     }
@@ -1093,7 +1359,8 @@ impl Database {
              ORDER BY COALESCE(t.parsed_disc, t.disc_number, CAST(t.parsed_set AS INTEGER), 1),
                       COALESCE(t.parsed_track, t.track_number, 999)"
         );
-        let mut stmt = self.conn.prepare(&sql)?;
+        // Cached: chain detection runs this once per show
+        let mut stmt = self.conn.prepare_cached(&sql)?;
 
         let rows = stmt
             .query_map(params![date], map_track_score)?
@@ -1101,44 +1368,50 @@ impl Database {
         Ok(rows)
     }
 
+    /// Data-quality flags for a show's tracks, garbage included.
+    pub fn show_quality(&self, date: &str) -> Result<ShowQuality> {
+        let mut stmt = self.conn.prepare(
+            "SELECT COALESCE(t.parsed_title, t.title, '(untitled)'),
+                    COALESCE(t.data_quality, 'ok')
+             FROM tracks t
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE (t.parsed_date = ?1 OR t.date = ?1)
+             ORDER BY COALESCE(t.parsed_disc, t.disc_number, CAST(t.parsed_set AS INTEGER), 1),
+                      COALESCE(t.parsed_track, t.track_number, 999)",
+        )?;
+        let rows = stmt
+            .query_map(params![date], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut quality = ShowQuality::default();
+        for (title, flag) in rows {
+            match flag.as_str() {
+                "ok" => quality.clean += 1,
+                "garbage" => quality.garbage += 1,
+                _ => quality.suspect += 1,
+            }
+            if flag != "ok" {
+                quality.flagged.push((title, flag));
+            }
+        }
+        Ok(quality)
+    }
+
     /// Load feature vectors for similarity computation.
     /// Returns (track_id, feature_vector) pairs for all analyzed tracks.
     pub fn get_feature_vectors(&self) -> Result<Vec<(i64, Vec<f64>)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT track_id,
-                -- MFCCs (26 dims)
-                COALESCE(mfcc_0_mean, 0), COALESCE(mfcc_0_std, 0),
-                COALESCE(mfcc_1_mean, 0), COALESCE(mfcc_1_std, 0),
-                COALESCE(mfcc_2_mean, 0), COALESCE(mfcc_2_std, 0),
-                COALESCE(mfcc_3_mean, 0), COALESCE(mfcc_3_std, 0),
-                COALESCE(mfcc_4_mean, 0), COALESCE(mfcc_4_std, 0),
-                COALESCE(mfcc_5_mean, 0), COALESCE(mfcc_5_std, 0),
-                COALESCE(mfcc_6_mean, 0), COALESCE(mfcc_6_std, 0),
-                COALESCE(mfcc_7_mean, 0), COALESCE(mfcc_7_std, 0),
-                COALESCE(mfcc_8_mean, 0), COALESCE(mfcc_8_std, 0),
-                COALESCE(mfcc_9_mean, 0), COALESCE(mfcc_9_std, 0),
-                COALESCE(mfcc_10_mean, 0), COALESCE(mfcc_10_std, 0),
-                COALESCE(mfcc_11_mean, 0), COALESCE(mfcc_11_std, 0),
-                COALESCE(mfcc_12_mean, 0), COALESCE(mfcc_12_std, 0),
-                -- Spectral (10 dims)
-                COALESCE(spectral_centroid_mean, 0), COALESCE(spectral_centroid_std, 0),
-                COALESCE(spectral_flux_mean, 0), COALESCE(spectral_flux_std, 0),
-                COALESCE(spectral_flatness_mean, 0), COALESCE(spectral_flatness_std, 0),
-                COALESCE(spectral_bandwidth_mean, 0), COALESCE(spectral_bandwidth_std, 0),
-                COALESCE(spectral_rolloff_mean, 0), COALESCE(spectral_rolloff_std, 0),
-                -- Sub-band energy (8 dims)
-                COALESCE(sub_band_bass_mean, 0), COALESCE(sub_band_bass_std, 0),
-                COALESCE(sub_band_mid_mean, 0), COALESCE(sub_band_mid_std, 0),
-                COALESCE(sub_band_high_mean, 0), COALESCE(sub_band_high_std, 0),
-                COALESCE(sub_band_presence_mean, 0), COALESCE(sub_band_presence_std, 0),
-                -- ZCR (2 dims)
-                COALESCE(zcr_mean, 0), COALESCE(zcr_std, 0),
-                -- Tempo (1 dim)
-                COALESCE(tempo_bpm, 0)
-             FROM analysis_results",
-        )?;
+        let cols = FEATURES
+            .iter()
+            .map(|c| format!("COALESCE({c}, 0)"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT track_id, {cols} FROM analysis_results"))?;
 
-        let dim = 47; // 26 + 10 + 8 + 2 + 1
+        let dim = FEATURES.len();
         let rows = stmt
             .query_map([], |row| {
                 let track_id: i64 = row.get(0)?;
@@ -1152,7 +1425,9 @@ impl Database {
         Ok(rows)
     }
 
-    /// Store similarity results (bulk insert within a transaction).
+    /// Store similarity results (bulk insert within a transaction), marking
+    /// every analyzed track's neighbors as current and recording the vector
+    /// layout they were computed with.
     pub fn store_similarities(&self, similarities: &[(i64, i64, f64, i32)]) -> Result<()> {
         let tx = self.conn.unchecked_transaction()?;
         tx.execute("DELETE FROM track_similarity", [])?;
@@ -1166,20 +1441,114 @@ impl Database {
             stmt.execute(params![track_id, similar_id, distance, rank])?;
         }
         drop(stmt);
+        tx.execute_batch(
+            "DELETE FROM similarity_state;
+             INSERT INTO similarity_state (track_id, analyzed_at)
+                 SELECT track_id, analyzed_at FROM analysis_results;",
+        )?;
+        let format = VectorFormat::CURRENT;
+        tx.execute(
+            "INSERT OR REPLACE INTO similarity_format (id, version, dim, computed_at)
+             VALUES (1, ?1, ?2, datetime('now'))",
+            params![format.version, format.dim as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Replace the neighbor lists of `track_ids` only, marking those tracks
+    /// current. State for tracks that are no longer analyzed is dropped.
+    pub fn replace_similarities(
+        &self,
+        track_ids: &[i64],
+        similarities: &[(i64, i64, f64, i32)],
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut delete =
+                tx.prepare_cached("DELETE FROM track_similarity WHERE track_id = ?1")?;
+            let mut mark = tx.prepare_cached(
+                "INSERT OR REPLACE INTO similarity_state (track_id, analyzed_at)
+                 SELECT track_id, analyzed_at FROM analysis_results WHERE track_id = ?1",
+            )?;
+            for &id in track_ids {
+                delete.execute([id])?;
+                mark.execute([id])?;
+            }
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO track_similarity (track_id, similar_track_id, distance, rank)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for &(track_id, similar_id, distance, rank) in similarities {
+                insert.execute(params![track_id, similar_id, distance, rank])?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM similarity_state
+             WHERE track_id NOT IN (SELECT track_id FROM analysis_results)",
+            [],
+        )?;
         tx.commit()?;
         Ok(())
     }
 
+    /// Analyzed tracks whose features changed since their neighbors were
+    /// computed: re-analyzed, or added after the last `similarity` run.
+    pub fn similarity_changed_ids(&self) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT a.track_id
+             FROM analysis_results a
+             LEFT JOIN similarity_state s ON s.track_id = a.track_id
+             WHERE s.analyzed_at IS NULL OR s.analyzed_at != a.analyzed_at
+             ORDER BY a.track_id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Stored neighbor lists as track → [(neighbor, distance)], nearest first.
+    pub fn stored_neighbors(&self) -> Result<HashMap<i64, Vec<(i64, f64)>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT track_id, similar_track_id, distance FROM track_similarity
+             ORDER BY track_id, rank",
+        )?;
+        let mut neighbors: HashMap<i64, Vec<(i64, f64)>> = HashMap::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            neighbors
+                .entry(row.get(0)?)
+                .or_default()
+                .push((row.get(1)?, row.get(2)?));
+        }
+        Ok(neighbors)
+    }
+
     /// Query similar tracks for a given track.
-    pub fn query_similar(&self, track_id: i64, limit: usize) -> Result<Vec<(TrackScore, f64)>> {
+    /// With `same_band`, neighbors from other bands are skipped.
+    pub fn query_similar(
+        &self,
+        track_id: i64,
+        limit: usize,
+        same_band: bool,
+    ) -> Result<Vec<(TrackScore, f64, i64)>> {
+        let band_filter = if same_band {
+            format!(
+                "AND {BAND_EXPR} = (SELECT COALESCE(parsed_band, artist, '') FROM tracks WHERE id = ?1)"
+            )
+        } else {
+            String::new()
+        };
         let sql = format!(
             "SELECT {TRACK_SCORE_SELECT},
-                s.distance
+                s.distance, s.similar_track_id
              FROM track_similarity s
              JOIN tracks t ON t.id = s.similar_track_id
              JOIN analysis_results a ON a.track_id = s.similar_track_id
              WHERE s.track_id = ?1
                AND {NOT_GARBAGE}
+               {band_filter}
              ORDER BY s.rank
              LIMIT ?2"
         );
@@ -1187,50 +1556,79 @@ impl Database {
 
         let rows = stmt
             .query_map(params![track_id, limit as i64], |row| {
-                Ok((map_track_score(row)?, row.get::<_, f64>(16)?))
+                Ok((map_track_score(row)?, row.get::<_, f64>(19)?, row.get(20)?))
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(rows)
     }
 
-    /// Find a track ID by song title and optional date.
+    /// Load score rows for specific tracks, returned in the order of `track_ids`.
+    /// Garbage tracks (and non-live tracks if `live_only`) are dropped.
+    pub fn query_tracks_by_id(
+        &self,
+        track_ids: &[i64],
+        live_only: bool,
+    ) -> Result<Vec<(i64, TrackScore)>> {
+        if track_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; track_ids.len()].join(", ");
+        let live_filter = if live_only {
+            format!("AND {LIVE_ONLY}")
+        } else {
+            String::new()
+        };
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT},
+                t.id
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE t.id IN ({placeholders})
+               AND {NOT_GARBAGE}
+               {live_filter}"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+
+        let mut rows = stmt
+            .query_map(rusqlite::params_from_iter(track_ids.iter()), |row| {
+                Ok((row.get::<_, i64>(19)?, map_track_score(row)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let order: HashMap<i64, usize> = track_ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, i))
+            .collect();
+        rows.sort_by_key(|(id, _)| order[id]);
+        Ok(rows)
+    }
+
+    /// Find a track ID by song title, optionally narrowed by date and band.
     pub fn find_track_id(
         &self,
         song: &str,
         date: Option<&str>,
+        band: Option<&str>,
     ) -> Result<Option<(i64, String, String)>> {
         let pattern = format!("%{song}%");
-        let sql = if date.is_some() {
-            format!(
-                "SELECT t.id, COALESCE(t.parsed_title, t.title, '?'), COALESCE(t.parsed_date, t.date, '?')
-                 FROM tracks t
-                 JOIN analysis_results a ON a.track_id = t.id
-                 WHERE (t.parsed_title LIKE ?1 OR t.title LIKE ?1)
-                   AND (t.parsed_date = ?2 OR t.date = ?2)
-                   AND {NOT_GARBAGE}
-                 LIMIT 1"
-            )
-        } else {
-            format!(
-                "SELECT t.id, COALESCE(t.parsed_title, t.title, '?'), COALESCE(t.parsed_date, t.date, '?')
-                 FROM tracks t
-                 JOIN analysis_results a ON a.track_id = t.id
-                 WHERE (t.parsed_title LIKE ?1 OR t.title LIKE ?1)
-                   AND {NOT_GARBAGE}
-                 ORDER BY a.duration DESC
-                 LIMIT 1"
-            )
-        };
+        let sql = format!(
+            "SELECT t.id, COALESCE(t.parsed_title, t.title, '?'), COALESCE(t.parsed_date, t.date, '?')
+             FROM tracks t
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE (t.parsed_title LIKE ?1 OR t.title LIKE ?1)
+               AND (?2 IS NULL OR t.parsed_date = ?2 OR t.date = ?2)
+               AND (?3 IS NULL OR {BAND_EXPR} = ?3)
+               AND {NOT_GARBAGE}
+             ORDER BY a.duration DESC
+             LIMIT 1"
+        );
 
-        let result = if let Some(d) = date {
-            self.conn.query_row(&sql, params![pattern, d], |row| {
-                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-            })
-        } else {
-            self.conn.query_row(&sql, params![pattern], |row| {
+        let result = self
+            .conn
+            .query_row(&sql, params![pattern, date, band], |row| {
                 Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-            })
-        };
+            });
 
         match result {
             Ok(row) => Ok(Some(row)),
@@ -1251,6 +1649,13 @@ impl Database {
                     row.get(0)
                 })?;
 
+        let stale_analyses = self.stale_analysis_count()?;
+        let preview_analyses: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM analysis_results WHERE preview = 1",
+            [],
+            |row| row.get(0),
+        )?;
+
         let total_duration_hours: f64 = self.conn.query_row(
             "SELECT COALESCE(SUM(duration), 0.0) / 3600.0 FROM analysis_results",
             [],
@@ -1278,25 +1683,77 @@ impl Database {
         Ok(LibraryStats {
             total_tracks,
             analyzed_tracks,
+            stale_analyses,
+            preview_analyses,
             total_duration_hours,
             formats,
             bands,
         })
     }
 
+    /// Fill `tracks.duration_secs` from the decoded length for tracks whose
+    /// tags carried none, so duration filters that run before or without the
+    /// analysis (`split-songs`, `retrack`, `analyze --priority`) see them.
+    /// Returns the number of tracks filled.
+    pub fn backfill_durations(&self) -> Result<usize> {
+        let filled = self.conn.execute(
+            "UPDATE tracks
+             SET duration_secs = (SELECT a.duration FROM analysis_results a
+                                  WHERE a.track_id = tracks.id)
+             WHERE duration_secs IS NULL
+               AND EXISTS (SELECT 1 FROM analysis_results a
+                           WHERE a.track_id = tracks.id AND a.stale = 0 AND a.duration > 0)",
+            [],
+        )?;
+        Ok(filled)
+    }
+
+    /// Count analyses whose file changed since they were computed.
+    pub fn stale_analysis_count(&self) -> Result<i64> {
+        let count = self.conn.query_row(
+            "SELECT COUNT(*) FROM analysis_results WHERE stale = 1",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Raw vs octave-corrected tempo counts in `bin_bpm`-wide bins, as
+    /// (bin start, raw count, corrected count), lowest bin first.
+    pub fn tempo_histogram(&self, bin_bpm: f64) -> Result<Vec<(f64, i64, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT bin, SUM(kind = 0), SUM(kind = 1) FROM (
+                 SELECT CAST(tempo_bpm / ?1 AS INTEGER) AS bin, 0 AS kind
+                 FROM analysis_results WHERE tempo_bpm > 0
+                 UNION ALL
+                 SELECT CAST(tempo_bpm_corrected / ?1 AS INTEGER), 1
+                 FROM analysis_results WHERE tempo_bpm_corrected > 0
+             )
+             GROUP BY bin ORDER BY bin",
+        )?;
+        let rows = stmt
+            .query_map(params![bin_bpm], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as f64 * bin_bpm,
+                    row.get(1)?,
+                    row.get(2)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
     /// Get tracks missing usable titles for setlist lookup.
     /// Matches tracks where parsed_title is NULL and the tag title is absent,
     /// empty, or a known placeholder (e.g. "??", "unknown", "Track N").
     /// Returns (track_id, file_path) pairs.
     pub fn get_tracks_missing_titles(&self) -> Result<Vec<(i64, String)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, file_path FROM tracks
-             WHERE parsed_title IS NULL
-               AND (title IS NULL OR title = '' OR title = '??'
-                    OR LOWER(title) = 'unknown' OR LOWER(title) LIKE 'untitled%'
-                    OR title LIKE 'Track __' OR title LIKE 'Track ___')
-             ORDER BY file_path",
-        )?;
+        let sql = format!(
+            "SELECT t.id, t.file_path FROM tracks t
+             WHERE {MISSING_TITLE}
+             ORDER BY t.file_path"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
 
         let rows = stmt
             .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
@@ -1309,7 +1766,7 @@ impl Database {
     pub fn update_parsed_title(&self, track_id: i64, title: &str) -> Result<()> {
         self.conn.execute(
             "UPDATE tracks SET parsed_title = ?1, updated_at = datetime('now') WHERE id = ?2",
-            params![title, track_id],
+            params![crate::title_normalize::normalize(title), track_id],
         )?;
         Ok(())
     }
@@ -1349,9 +1806,14 @@ impl Database {
         Ok(count > 0)
     }
 
-    /// Store archive.org show entries (bulk insert within a transaction).
-    /// Returns the total number of rows in the table for this collection after storing.
-    pub fn store_archive_shows(&self, shows: &[ArchiveShow]) -> Result<usize> {
+    /// Store one fetched search page: its shows, and the page itself so an
+    /// interrupted crawl can resume where it stopped.
+    pub fn store_archive_page(
+        &self,
+        page: &ArchiveCrawlPage,
+        collection: &str,
+        shows: &[ArchiveShow],
+    ) -> Result<()> {
         let tx = self.conn.unchecked_transaction()?;
 
         let mut stmt = tx.prepare_cached(
@@ -1359,7 +1821,6 @@ impl Database {
                 (identifier, collection, date, title, source_quality, format_quality, fetched_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
         )?;
-
         for s in shows {
             stmt.execute(params![
                 s.identifier,
@@ -1371,54 +1832,128 @@ impl Database {
             ])?;
         }
         drop(stmt);
+
+        tx.execute(
+            "INSERT OR REPLACE INTO archive_crawl_pages
+                (collection, year_start, year_end, page_offset, num_found, shows)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                collection,
+                page.year_start,
+                page.year_end,
+                page.offset as i64,
+                page.num_found as i64,
+                page.shows as i64,
+            ],
+        )?;
         tx.commit()?;
+        Ok(())
+    }
 
-        // Return actual row count (deduplicated by primary key)
-        let collection = match shows.first() {
-            Some(s) => s.collection.as_str(),
-            None => return Ok(0),
-        };
-        let count: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM archive_shows WHERE collection = ?1",
+    /// Start a fresh crawl of a collection, forgetting any unfinished one.
+    pub fn begin_archive_crawl(&self, collection: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO archive_crawls (collection, started_at, completed_at)
+             VALUES (?1, datetime('now'), NULL)",
             params![collection],
-            |row| row.get(0),
         )?;
-        Ok(count as usize)
+        self.conn.execute(
+            "DELETE FROM archive_crawl_pages WHERE collection = ?1",
+            params![collection],
+        )?;
+        Ok(())
+    }
+
+    /// Mark a crawl finished: its listing becomes the cache.
+    pub fn complete_archive_crawl(&self, collection: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE archive_crawls SET completed_at = datetime('now') WHERE collection = ?1",
+            params![collection],
+        )?;
+        self.conn.execute(
+            "DELETE FROM archive_crawl_pages WHERE collection = ?1",
+            params![collection],
+        )?;
+        Ok(())
+    }
+
+    /// Pages stored by an unfinished crawl started within the TTL, or `None`
+    /// if there's nothing to resume.
+    pub fn resumable_archive_crawl(
+        &self,
+        collection: &str,
+        ttl_days: i64,
+    ) -> Result<Option<Vec<ArchiveCrawlPage>>> {
+        use rusqlite::OptionalExtension;
+        let unfinished = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM archive_crawls
+                 WHERE collection = ?1 AND completed_at IS NULL
+                   AND started_at >= datetime('now', ?2)",
+                params![collection, format!("-{ttl_days} days")],
+                |_| Ok(()),
+            )
+            .optional()?;
+        match unfinished {
+            Some(()) => Ok(Some(self.archive_crawl_pages(collection)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Pages stored so far by the current crawl of a collection.
+    pub fn archive_crawl_pages(&self, collection: &str) -> Result<Vec<ArchiveCrawlPage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT year_start, year_end, page_offset, num_found, shows
+             FROM archive_crawl_pages
+             WHERE collection = ?1
+             ORDER BY year_start, page_offset",
+        )?;
+        let pages = stmt
+            .query_map(params![collection], |row| {
+                Ok(ArchiveCrawlPage {
+                    year_start: row.get(0)?,
+                    year_end: row.get(1)?,
+                    offset: row.get::<_, i64>(2)? as usize,
+                    num_found: row.get::<_, i64>(3)? as usize,
+                    shows: row.get::<_, i64>(4)? as usize,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(pages)
     }
 
-    /// Get cached archive shows for a collection, returning None if cache is expired.
+    /// Get cached archive shows for a collection, returning None unless a crawl
+    /// finished and started within the TTL.
     pub fn get_cached_archive_shows(
         &self,
         collection: &str,
         ttl_days: i64,
     ) -> Result<Option<Vec<ArchiveShow>>> {
-        // Check if we have any data and if it's fresh enough
-        let freshness: std::result::Result<String, _> = self.conn.query_row(
-            "SELECT MIN(fetched_at) FROM archive_shows WHERE collection = ?1",
-            params![collection],
-            |row| row.get(0),
-        );
+        use rusqlite::OptionalExtension;
+        let fresh: Option<bool> = self
+            .conn
+            .query_row(
+                "SELECT started_at >= datetime('now', ?2) FROM archive_crawls
+                 WHERE collection = ?1 AND completed_at IS NOT NULL",
+                params![collection, format!("-{ttl_days} days")],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if fresh != Some(true) {
+            return Ok(None);
+        }
 
-        match freshness {
-            Ok(oldest) => {
-                // Check if oldest fetch is within TTL
-                let expired: bool = self.conn.query_row(
-                    "SELECT datetime(?1) < datetime('now', ?2)",
-                    params![oldest, format!("-{ttl_days} days")],
-                    |row| row.get(0),
-                )?;
-
-                if expired {
-                    return Ok(None);
-                }
-            }
-            Err(rusqlite::Error::InvalidColumnType(_, _, _)) => {
-                // No rows — cache miss
-                return Ok(None);
-            }
-            Err(e) => return Err(e.into()),
+        let shows = self.archive_shows(collection)?;
+        if shows.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(shows))
         }
+    }
 
+    /// Every stored archive show for a collection, finished crawl or not.
+    pub fn archive_shows(&self, collection: &str) -> Result<Vec<ArchiveShow>> {
         let mut stmt = self.conn.prepare(
             "SELECT identifier, collection, date, title, source_quality, format_quality
              FROM archive_shows
@@ -1438,12 +1973,7 @@ impl Database {
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
-
-        if shows.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(shows))
-        }
+        Ok(shows)
     }
 
     /// Get distinct local show dates for a given band.
@@ -1460,6 +1990,37 @@ impl Database {
         Ok(dates)
     }
 
+    /// Venue names known for a band's local show dates: from tags and
+    /// filenames, fetched setlists, and archive.org show notes.
+    pub fn local_show_venues(&self, band: &str) -> Result<HashMap<String, Vec<String>>> {
+        let mut stmt = self.conn.prepare(
+            "WITH local AS (
+                 SELECT DISTINCT parsed_date AS date FROM tracks
+                 WHERE parsed_band = ?1 AND parsed_date IS NOT NULL
+             )
+             SELECT parsed_date, COALESCE(parsed_venue, venue) FROM tracks
+             WHERE parsed_band = ?1 AND parsed_date IS NOT NULL
+               AND COALESCE(parsed_venue, venue) IS NOT NULL
+             UNION
+             SELECT date, venue FROM setlists
+             WHERE venue IS NOT NULL AND date IN (SELECT date FROM local)
+             UNION
+             SELECT date, venue FROM show_notes
+             WHERE venue IS NOT NULL AND local_dir != '' AND date IN (SELECT date FROM local)",
+        )?;
+        let mut venues: HashMap<String, Vec<String>> = HashMap::new();
+        let rows = stmt.query_map(params![band], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (date, venue) = row?;
+            if !venue.trim().is_empty() {
+                venues.entry(date).or_default().push(venue);
+            }
+        }
+        Ok(venues)
+    }
+
     /// Get archive shows for a specific date and collection.
     pub fn get_archive_shows_by_date(
         &self,
@@ -1488,6 +2049,81 @@ impl Database {
         Ok(shows)
     }
 
+    /// Insert or replace show notes for an archive.org source. Notes without
+    /// a local directory keep the one already stored for the source.
+    pub fn store_show_notes(&self, n: &ShowNotes) -> Result<()> {
+        self.conn.execute(STORE_SHOW_NOTES, show_notes_params(n))?;
+        Ok(())
+    }
+
+    /// Store the notes `discover` found in search results, in one transaction.
+    pub fn store_discovered_notes(&self, notes: &[ShowNotes]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(STORE_SHOW_NOTES)?;
+            for n in notes {
+                stmt.execute(show_notes_params(n))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get show notes for every source of a show date. Sources whose item
+    /// metadata has no date are found through the local directory of the
+    /// date's tracks instead.
+    pub fn get_show_notes(&self, date: &str) -> Result<Vec<ShowNotes>> {
+        let mut dirs = HashSet::new();
+        {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT file_path FROM tracks WHERE COALESCE(parsed_date, date) = ?1")?;
+            for path in stmt.query_map(params![date], |row| row.get::<_, String>(0))? {
+                if let Some(dir) = crate::paths::parent_name(&path?) {
+                    dirs.insert(dir.to_string());
+                }
+            }
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT identifier, local_dir, date, venue, source, lineage, taper, transferer,
+                    description, notes
+             FROM show_notes
+             WHERE date = ?1 OR COALESCE(date, '') = ''
+             ORDER BY identifier",
+        )?;
+        let rows = stmt
+            .query_map(params![date], |row| {
+                Ok(ShowNotes {
+                    identifier: row.get(0)?,
+                    local_dir: row.get(1)?,
+                    date: row.get(2)?,
+                    venue: row.get(3)?,
+                    source: row.get(4)?,
+                    lineage: row.get(5)?,
+                    taper: row.get(6)?,
+                    transferer: row.get(7)?,
+                    description: row.get(8)?,
+                    notes: row.get(9)?,
+                })
+            })?
+            .filter(|row| {
+                row.as_ref().map_or(true, |n| {
+                    n.date.as_deref().is_some_and(|d| !d.is_empty()) || dirs.contains(&n.local_dir)
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Local directory names that already have show notes.
+    pub fn get_show_notes_dirs(&self) -> Result<std::collections::HashSet<String>> {
+        let mut stmt = self.conn.prepare("SELECT local_dir FROM show_notes")?;
+        let dirs = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(dirs)
+    }
+
     /// Check if a file path already exists and hasn't changed (same size+mtime).
     pub fn track_unchanged(
         &self,
@@ -1511,15 +2147,17 @@ impl Database {
     /// Load calibration data: scores, LUFS, and show grouping info for all analyzed tracks.
     pub fn get_calibration_data(&self) -> Result<Vec<CalibrationRow>> {
         let mut stmt = self.conn.prepare(
-            "SELECT a.track_id, a.lufs_integrated,
+            "SELECT a.track_id, COALESCE(a.lufs_music, a.lufs_integrated),
                     a.energy_score, a.intensity_score, a.groove_score,
                     a.improvisation_score, a.tightness_score, a.build_quality_score,
                     a.exploratory_score, a.transcendence_score,
                     a.valence_score, a.arousal_score,
-                    t.parsed_date, t.parsed_band
+                    t.parsed_date, t.parsed_band, a.lufs_music IS NOT NULL,
+                    t.file_path, COALESCE(t.parsed_title, t.title), t.source_type,
+                    t.performance_id
              FROM analysis_results a
              JOIN tracks t ON t.id = a.track_id
-             WHERE a.lufs_integrated IS NOT NULL
+             WHERE COALESCE(a.lufs_music, a.lufs_integrated) IS NOT NULL
                AND a.energy_score IS NOT NULL
                AND t.parsed_date IS NOT NULL",
         )?;
@@ -1542,6 +2180,11 @@ impl Database {
                     ],
                     parsed_date: row.get(12)?,
                     parsed_band: row.get(13)?,
+                    music_lufs: row.get(14)?,
+                    file_path: row.get(15)?,
+                    title: row.get(16)?,
+                    source_type: row.get(17)?,
+                    performance_id: row.get(18)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -1610,7 +2253,7 @@ impl Database {
     /// Get setlist entries for a given date, ordered by set and position.
     /// Returns (song, segued, set_num, position) tuples.
     pub fn get_setlist_for_date(&self, date: &str) -> Result<Vec<(String, bool, i32, i32)>> {
-        let mut stmt = self.conn.prepare(
+        let mut stmt = self.conn.prepare_cached(
             "SELECT song, segued, set_num, position
              FROM setlists
              WHERE date = ?1
@@ -1722,13 +2365,15 @@ impl Database {
         Ok(())
     }
 
-    /// Get analyzed tracks that are missing boundary features (for backfill).
+    /// Get analyzed tracks that are missing boundary features or segue edges
+    /// (for backfill).
     pub fn get_tracks_missing_boundaries(&self) -> Result<Vec<Track>> {
         let mut stmt = self.conn.prepare(
             "SELECT t.id, t.file_path, t.format, t.artist, t.parsed_band, t.parsed_date
              FROM tracks t
              JOIN analysis_results a ON a.track_id = t.id
              WHERE a.tail_rms_db IS NULL
+                OR t.id NOT IN (SELECT track_id FROM segue_edges)
              ORDER BY t.id",
         )?;
         let tracks = stmt
@@ -1781,10 +2426,22 @@ impl Database {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::db::models::NewTrack;
 
+    /// A bare track at `path` (format from its extension), for fixtures to
+    /// fill in with `NewTrack { .., ..new_track(path) }`.
+    pub(crate) fn new_track(path: &str) -> NewTrack {
+        NewTrack {
+            file_path: path.to_string(),
+            file_size: 1,
+            file_modified: "1700000000".to_string(),
+            format: path.rsplit_once('.').map_or("", |(_, ext)| ext).to_string(),
+            ..Default::default()
+        }
+    }
+
     fn test_track() -> NewTrack {
         NewTrack {
             file_path: "/music/gd1977-05-08d1t01.shn".to_string(),
@@ -1809,6 +2466,9 @@ mod tests {
             parsed_title: None,
             duration_secs: Some(300.0),
             recording_type: Some("live".to_string()),
+            source_type: None,
+            taper: None,
+            lineage_hint: None,
         }
     }
 
@@ -1868,6 +2528,8 @@ mod tests {
             mfcc_12_mean: None,
             mfcc_12_std: None,
             tempo_bpm: None,
+            tempo_bpm_corrected: None,
+            tempo_confidence: None,
             beat_count: None,
             onset_count: None,
             tempo_stability: None,
@@ -1880,6 +2542,10 @@ mod tests {
             vibrato_presence: None,
             vibrato_rate: None,
             pitch_confidence_mean: None,
+            mean_pitch_corrected: None,
+            pitch_range_low_corrected: None,
+            pitch_range_high_corrected: None,
+            pitch_octave_errors: None,
             lufs_integrated: None,
             loudness_range: None,
             true_peak_dbfs: None,
@@ -1916,9 +2582,14 @@ mod tests {
             solo_section_ratio: None,
             transition_count: None,
             classification_music_score: None,
+            classification_speech_score: None,
+            announcer_ratio: None,
             hnr: None,
             loudness_std: None,
             peak_loudness: None,
+            lufs_music: None,
+            lufs_window_p10: None,
+            lufs_window_p90: None,
             spectral_flux_skewness: None,
             spectral_centroid_slope: None,
             energy_buildup_ratio: None,
@@ -2027,6 +2698,7 @@ mod tests {
             spectral_novelty_std: None,
             groove_stability_mean: None,
             groove_stability_std: None,
+            preview: false,
         }
     }
 
@@ -2095,6 +2767,165 @@ mod tests {
         assert_eq!(db.stats().unwrap().analyzed_tracks, 1);
     }
 
+    #[test]
+    fn test_changed_file_marks_analysis_stale() {
+        let db = Database::open_in_memory().unwrap();
+        let mut t = test_track();
+        let id = db.upsert_track(&t).unwrap();
+        db.store_analysis(&minimal_analysis(id)).unwrap();
+
+        // Re-scanning an unchanged file leaves the analysis alone
+        db.upsert_track(&t).unwrap();
+        assert_eq!(db.stale_analysis_count().unwrap(), 0);
+
+        // A replaced file (new size) queues the track for re-analysis
+        t.file_size += 1024;
+        db.upsert_track(&t).unwrap();
+        assert_eq!(db.stale_analysis_count().unwrap(), 1);
+        assert_eq!(db.get_unanalyzed_tracks().unwrap()[0].id, id);
+
+        db.store_analysis(&minimal_analysis(id)).unwrap();
+        assert_eq!(db.stale_analysis_count().unwrap(), 0);
+        assert!(db.get_unanalyzed_tracks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_duration_backfilled_from_analysis() {
+        let db = Database::open_in_memory().unwrap();
+        let mut t = test_track();
+        t.duration_secs = None;
+        let id = db.upsert_track(&t).unwrap();
+        let duration = |db: &Database| -> Option<f64> {
+            db.conn
+                .query_row(
+                    "SELECT duration_secs FROM tracks WHERE id = ?1",
+                    [id],
+                    |r| r.get(0),
+                )
+                .unwrap()
+        };
+
+        db.store_analysis(&minimal_analysis(id)).unwrap();
+        assert_eq!(db.backfill_durations().unwrap(), 1);
+        assert_eq!(duration(&db), Some(300.0));
+        assert_eq!(db.backfill_durations().unwrap(), 0);
+
+        // A rescan of the unchanged file keeps the decoded length
+        db.upsert_track(&t).unwrap();
+        assert_eq!(duration(&db), Some(300.0));
+
+        // A replaced file drops it until it's analyzed again
+        t.file_size += 1024;
+        db.upsert_track(&t).unwrap();
+        assert_eq!(duration(&db), None);
+        assert_eq!(db.backfill_durations().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_tempo_correction_roundtrip() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db.upsert_track(&test_track()).unwrap();
+        let mut a = minimal_analysis(id);
+        a.tempo_bpm = Some(190.0);
+        a.tempo_bpm_corrected = Some(95.0);
+        a.tempo_confidence = Some(0.8);
+        db.store_analysis(&a).unwrap();
+
+        let loaded = &db.get_analyses_for_rescore().unwrap()[0];
+        assert_eq!(loaded.tempo_bpm_corrected, Some(95.0));
+        assert_eq!(loaded.tempo_confidence, Some(0.8));
+        assert_eq!(
+            db.tempo_histogram(10.0).unwrap(),
+            vec![(90.0, 0, 1), (190.0, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_top_hides_untitled() {
+        let db = Database::open_in_memory().unwrap();
+        let titled = db.upsert_track(&test_track()).unwrap();
+        let untitled = db
+            .upsert_track(&NewTrack {
+                file_path: "/music/gd1977-05-08d1t02.shn".into(),
+                title: Some("Track 02".into()),
+                ..test_track()
+            })
+            .unwrap();
+        for (id, groove) in [(titled, 60.0), (untitled, 90.0)] {
+            let mut a = minimal_analysis(id);
+            a.groove_score = Some(groove);
+            db.store_analysis(&a).unwrap();
+        }
+
+        let filter = TopFilter::default();
+        let top = db.query_top("groove_score", 10, &filter).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].title, "Scarlet Begonias");
+        assert_eq!(db.count_untitled_excluded(&filter).unwrap(), 1);
+
+        let all = TopFilter {
+            include_untitled: true,
+            ..filter
+        };
+        assert_eq!(db.query_top("groove_score", 10, &all).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_array_columns_stored_beside_row() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db.upsert_track(&test_track()).unwrap();
+        let mut a = minimal_analysis(id);
+        a.chroma_vector = Some("[1.0,0.0,0.5,0,0,0,0,0,0,0,0,0]".into());
+        a.tonnetz_json = Some("[0.1,0.2,0.3,0.4,0.5,0.6]".into());
+        db.store_analysis(&a).unwrap();
+
+        let (chroma, tonnetz, groove): (String, String, Option<f64>) = db
+            .conn
+            .query_row(
+                "SELECT chroma_vector, tonnetz_json, groove_score FROM analysis_full
+                 WHERE track_id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(chroma, a.chroma_vector.unwrap());
+        assert_eq!(tonnetz, a.tonnetz_json.unwrap());
+        assert_eq!(groove, a.groove_score);
+        assert!(
+            db.conn
+                .prepare("SELECT chroma_vector FROM analysis_results")
+                .is_err()
+        );
+        assert_eq!(db.query_chroma_tracks().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_harmonic_matches_stay_in_band() {
+        let db = Database::open_in_memory().unwrap();
+        let mut ids = Vec::new();
+        for (path, band) in [
+            ("/music/gd1.flac", "Grateful Dead"),
+            ("/music/gd2.flac", "Grateful Dead"),
+            ("/music/ph1.flac", "Phish"),
+        ] {
+            let mut t = test_track();
+            t.file_path = path.to_string();
+            t.parsed_band = Some(band.to_string());
+            let id = db.upsert_track(&t).unwrap();
+            let mut a = minimal_analysis(id);
+            a.chroma_vector = Some("[1.0,0.0,0.5,0,0,0,0,0,0,0,0,0]".into());
+            db.store_analysis(&a).unwrap();
+            ids.push(id);
+        }
+
+        let (_, same) =
+            crate::chroma::find_harmonic_matches(&db, ids[0], 10, true, None, true).unwrap();
+        assert_eq!(same.len(), 1);
+        let (_, all) =
+            crate::chroma::find_harmonic_matches(&db, ids[0], 10, true, None, false).unwrap();
+        assert_eq!(all.len(), 2);
+    }
+
     #[test]
     fn test_store_full_analysis_with_details() {
         let db = Database::open_in_memory().unwrap();
@@ -2151,7 +2982,14 @@ mod tests {
             duration: Some(2.0),
         }];
 
-        db.store_full_analysis(&analysis, &chords, &segments, &tension, &transitions)
+        let cuts = vec![CutPointRecord {
+            track_id: id,
+            time: 600.5,
+            drop_db: 42.0,
+            discontinuity: 18.0,
+        }];
+
+        db.store_full_analysis(&analysis, &chords, &segments, &tension, &transitions, &cuts)
             .unwrap();
 
         // Verify counts
@@ -2175,6 +3013,126 @@ mod tests {
             .unwrap();
         assert_eq!(seg_count, 1);
 
+        assert_eq!(db.get_track_cuts(id).unwrap().len(), 1);
+        let has_cut: bool = db
+            .conn
+            .query_row(
+                "SELECT has_cut FROM analysis_results WHERE track_id = ?1",
+                params![id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert!(has_cut);
+
         assert_eq!(db.stats().unwrap().analyzed_tracks, 1);
     }
+
+    #[test]
+    fn test_query_compare_per_band() {
+        let db = Database::open_in_memory().unwrap();
+        let versions = [
+            ("Grateful Dead", 1, 90.0),
+            ("Grateful Dead", 2, 80.0),
+            ("Grateful Dead", 3, 70.0),
+            ("Phish", 4, 50.0),
+        ];
+        for (band, i, improv) in versions {
+            let mut t = test_track();
+            t.file_path = format!("/music/nfa{i}.flac");
+            t.parsed_title = Some("Not Fade Away".into());
+            t.parsed_band = Some(band.into());
+            let id = db.upsert_track(&t).unwrap();
+            let mut a = minimal_analysis(id);
+            a.improvisation_score = Some(improv);
+            db.store_analysis(&a).unwrap();
+        }
+
+        // Mixed: the global top 2 are both Dead versions
+        let mixed = db
+            .query_compare("Not Fade", "improvisation_score", 2, true, None, false)
+            .unwrap();
+        assert!(
+            mixed
+                .iter()
+                .all(|t| t.band.as_deref() == Some("Grateful Dead"))
+        );
+
+        // Per band: the limit applies within each band, so Phish still shows up
+        let grouped = db
+            .query_compare("Not Fade", "improvisation_score", 2, true, None, true)
+            .unwrap();
+        assert_eq!(grouped.len(), 3);
+        assert_eq!(grouped[2].band.as_deref(), Some("Phish"));
+
+        let phish = db
+            .query_compare(
+                "Not Fade",
+                "improvisation_score",
+                10,
+                true,
+                Some("Phish"),
+                true,
+            )
+            .unwrap();
+        assert_eq!(phish.len(), 1);
+    }
+
+    #[test]
+    fn test_archive_crawl_resume_and_complete() {
+        let db = Database::open_in_memory().unwrap();
+        let show = |id: &str| ArchiveShow {
+            identifier: id.to_string(),
+            collection: "GratefulDead".to_string(),
+            date: "1977-05-08".to_string(),
+            title: String::new(),
+            source_quality: 1,
+            format_quality: 3,
+        };
+        let page = ArchiveCrawlPage {
+            year_start: 1976,
+            year_end: 1977,
+            offset: 0,
+            num_found: 900,
+            shows: 2,
+        };
+
+        db.begin_archive_crawl("GratefulDead").unwrap();
+        db.store_archive_page(&page, "GratefulDead", &[show("a"), show("b")])
+            .unwrap();
+        // Partial: not a cache yet, but resumable and readable
+        assert!(
+            db.get_cached_archive_shows("GratefulDead", 30)
+                .unwrap()
+                .is_none()
+        );
+        let resumed = db.resumable_archive_crawl("GratefulDead", 30).unwrap();
+        assert_eq!(resumed, Some(vec![page.clone()]));
+        assert_eq!(db.archive_shows("GratefulDead").unwrap().len(), 2);
+
+        db.complete_archive_crawl("GratefulDead").unwrap();
+        assert_eq!(
+            db.get_cached_archive_shows("GratefulDead", 30)
+                .unwrap()
+                .map(|s| s.len()),
+            Some(2)
+        );
+        assert!(
+            db.resumable_archive_crawl("GratefulDead", 30)
+                .unwrap()
+                .is_none()
+        );
+        assert!(db.archive_crawl_pages("GratefulDead").unwrap().is_empty());
+
+        // A forced refresh starts over and hides the old listing until it finishes
+        db.begin_archive_crawl("GratefulDead").unwrap();
+        assert!(
+            db.get_cached_archive_shows("GratefulDead", 30)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            db.resumable_archive_crawl("GratefulDead", 30).unwrap(),
+            Some(vec![])
+        );
+    }
 }
@@ -0,0 +1,152 @@
+//! Parallel read-only access for query-heavy commands.
+//!
+//! A rusqlite `Connection` can't be shared between threads, so `par_read`
+//! gives each worker thread its own read-only connection to the database file
+//! (WAL mode lets them read alongside each other and the writer). Items are
+//! split into contiguous chunks, one per thread, and results come back in input
+//! order. Readers keep a larger prepared-statement cache, so a per-item query
+//! is compiled once per thread instead of once per item, and memory-map the
+//! file, so pages are read straight from the OS cache instead of being copied
+//! into each connection's own page cache.
+//!
+//! An in-memory database can't be opened a second time; there the work runs on
+//! the caller's connection, as it does for a single reader or a short list.
+
+use super::{Database, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::path::Path;
+
+/// Upper bound on reader threads; past this, SQLite's page cache contention
+/// outweighs the extra cores.
+const MAX_READERS: usize = 8;
+
+/// Prepared statements each reader keeps compiled.
+const READER_STATEMENT_CACHE: usize = 64;
+
+/// Bytes of the database file each reader memory-maps.
+const READER_MMAP_BYTES: i64 = 256 << 20;
+
+/// Fewer items than this per reader aren't worth a connection.
+const MIN_ITEMS_PER_READER: usize = 16;
+
+impl Database {
+    /// Open a read-only connection to an existing database file. No migrations
+    /// run, so open the database normally first.
+    pub fn open_reader(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.set_prepared_statement_cache_capacity(READER_STATEMENT_CACHE);
+        conn.pragma_update(None, "mmap_size", READER_MMAP_BYTES)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        Ok(Self {
+            conn,
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Reader threads to use by default: one per core, at most `MAX_READERS`.
+    pub fn default_readers() -> usize {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(MAX_READERS)
+    }
+
+    /// Run `f` over `items` on up to `readers` threads, each with its own
+    /// read-only connection, returning results in input order. The first error
+    /// from any thread is returned. Writes made through `self` must be
+    /// committed before calling, or the readers won't see them.
+    pub fn par_read<I, T, F>(&self, items: &[I], readers: usize, f: F) -> Result<Vec<T>>
+    where
+        I: Sync,
+        T: Send,
+        F: Fn(&Database, &I) -> Result<T> + Sync,
+    {
+        let readers = readers.min(items.len() / MIN_ITEMS_PER_READER).max(1);
+        let path = match &self.path {
+            Some(path) if readers > 1 => path,
+            _ => return items.iter().map(|item| f(self, item)).collect(),
+        };
+
+        let chunk = items.len().div_ceil(readers);
+        let f = &f;
+        let chunks: Vec<Result<Vec<T>>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = items
+                .chunks(chunk)
+                .map(|part| {
+                    scope.spawn(move || {
+                        let reader = Database::open_reader(path)?;
+                        part.iter().map(|item| f(&reader, item)).collect()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        });
+
+        let mut out = Vec::with_capacity(items.len());
+        for part in chunks {
+            out.extend(part?);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_par_read_matches_sequential() {
+        let dir = std::env::temp_dir().join(format!("setbreak-readers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("library.db");
+        let _ = std::fs::remove_file(&path);
+        let db = Database::open(&path).unwrap();
+        db.conn
+            .execute_batch(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200)
+                 INSERT INTO tracks (file_path, file_size, file_modified, format, parsed_date)
+                 SELECT '/music/' || i || '.flac', i, '0', 'flac', '1977-05-' || (i % 20)
+                 FROM n;",
+            )
+            .unwrap();
+
+        let dates: Vec<String> = (0..20).map(|d| format!("1977-05-{d}")).collect();
+        let count = |db: &Database, date: &String| -> Result<i64> {
+            Ok(db
+                .conn
+                .prepare_cached("SELECT COUNT(*) FROM tracks WHERE parsed_date = ?1")?
+                .query_row([date], |row| row.get(0))?)
+        };
+        let sequential = db.par_read(&dates, 1, count).unwrap();
+        assert_eq!(sequential, vec![10; 20]);
+
+        // 64 items over 4 readers
+        let many: Vec<String> = dates.iter().cycle().take(64).cloned().collect();
+        let parallel = db.par_read(&many, 4, count).unwrap();
+        assert_eq!(parallel.len(), 64);
+        assert!(parallel.iter().all(|&n| n == 10));
+
+        let reader = Database::open_reader(&path).unwrap();
+        let mmap: i64 = reader
+            .conn
+            .pragma_query_value(None, "mmap_size", |row| row.get(0))
+            .unwrap();
+        assert_eq!(mmap, READER_MMAP_BYTES);
+        drop(reader);
+
+        // Readers can't write
+        let err = db.par_read(&many, 4, |reader, _| {
+            Ok(reader.conn.execute("DELETE FROM tracks", [])?)
+        });
+        assert!(err.is_err());
+
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,20 @@
-use std::collections::HashMap;
-use std::thread;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
 
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::ProgressStyle;
+use regex::Regex;
 use serde::Deserialize;
 
+use crate::archive_client::ArchiveClient;
 use crate::bands::ArchiveStrategy;
 use crate::db::Database;
-use crate::db::models::{ArchiveShow, MissingShow};
+use crate::db::models::{ArchiveCrawlPage, ArchiveShow, MissingShow, ShowNotes};
+use crate::ratings::{ItemRating, parse_rating};
+use crate::scanner::source;
+use crate::setlist::ArchiveItemMetadata;
 
 /// Results per page from archive.org search API.
 const PAGE_SIZE: usize = 500;
@@ -19,6 +25,35 @@ pub struct DiscoveryResult {
     pub archive_count: usize,
     pub local_count: usize,
     pub missing: Vec<MissingShow>,
+    /// Search pages still unfetched; non-zero means `missing` came from a partial listing.
+    pub pages_left: usize,
+    /// Archive shows left out of `missing` because the library has the same
+    /// venue a day off (a late-night show filed under the next date, say).
+    pub shifted: Vec<DateShift>,
+}
+
+/// An archive show the library seems to have under a neighboring date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateShift {
+    pub archive_date: String,
+    pub local_date: String,
+    pub venue: String,
+    pub identifier: String,
+}
+
+/// What a local show a day off says about an archive date.
+#[derive(Debug, Clone, PartialEq)]
+enum NearMatch {
+    /// Same venue: the library has this show under `local_date`.
+    SameVenue {
+        local_date: String,
+        venue: String,
+    },
+    /// A local show a day off, but no venue to confirm or rule it out.
+    Unconfirmed {
+        local_date: String,
+    },
+    None,
 }
 
 /// Archive.org advanced search response.
@@ -39,6 +74,11 @@ struct SearchDoc {
     identifier: String,
     date: Option<String>,
     title: Option<String>,
+    avg_rating: Option<serde_json::Value>,
+    num_reviews: Option<serde_json::Value>,
+    /// Venue, source, lineage, taper, transferer, description and notes.
+    #[serde(flatten)]
+    notes: ArchiveItemMetadata,
 }
 
 /// Discover missing shows from archive.org for a given band.
@@ -49,7 +89,7 @@ pub fn discover_missing_shows(
     year_filter: Option<&str>,
     limit: usize,
     cache_ttl_days: i64,
-    rate_limit_ms: u64,
+    client: &ArchiveClient,
 ) -> Result<DiscoveryResult> {
     let registry = crate::bands::registry();
     let strategy = registry
@@ -72,12 +112,13 @@ pub fn discover_missing_shows(
             .context("Failed to read cache")?
     };
 
+    let mut pages_left = 0;
     let shows = match archive_shows {
         Some(cached) => {
-            println!(
+            crate::progress::note(&format!(
                 "Using cached data ({} shows, refresh with --refresh)",
                 cached.len()
-            );
+            ));
             cached
         }
         None => {
@@ -85,13 +126,22 @@ pub fn discover_missing_shows(
                 ArchiveStrategy::Collection(c) => format!("collection '{c}'"),
                 ArchiveStrategy::Creator(c) => format!("creator '{c}'"),
             };
-            println!("Fetching shows from archive.org {}...", label);
-            let fetched = fetch_collection_shows(&strategy, rate_limit_ms)?;
-            let count = db
-                .store_archive_shows(&fetched)
-                .context("Failed to cache shows")?;
-            println!("Cached {} shows from archive.org", count);
-            fetched
+            crate::progress::note(&format!("Fetching shows from archive.org {}...", label));
+            pages_left =
+                fetch_collection_shows(db, &strategy, client, !force_refresh, cache_ttl_days)?;
+            let stored = db
+                .archive_shows(&cache_key)
+                .context("Failed to read cached shows")?;
+            if pages_left == 0 {
+                crate::progress::note(&format!("Cached {} shows from archive.org", stored.len()));
+            } else {
+                log::warn!(
+                    "{} pages failed; using the {} shows fetched so far (run discover again to fetch the rest)",
+                    pages_left,
+                    stored.len()
+                );
+            }
+            stored
         }
     };
 
@@ -106,6 +156,12 @@ pub fn discover_missing_shows(
     // Build a set of local dates for fast lookup
     let local_set: std::collections::HashSet<&str> =
         local_dates.iter().map(|d| d.as_str()).collect();
+    let local_venues = db
+        .local_show_venues(&parsed_band)
+        .context("Failed to get local venues")?;
+    let ratings = db
+        .ratings_by_date(&cache_key)
+        .context("Failed to read ratings")?;
 
     // Group archive shows by date, keeping the best quality per date
     let mut by_date: HashMap<String, Vec<&ArchiveShow>> = HashMap::new();
@@ -116,7 +172,9 @@ pub fn discover_missing_shows(
     }
 
     // Find missing dates and build MissingShow entries
+    let archive_dates: HashSet<&str> = by_date.keys().map(String::as_str).collect();
     let mut missing: Vec<MissingShow> = Vec::new();
+    let mut shifted: Vec<DateShift> = Vec::new();
     for (date, tapes) in &by_date {
         if local_set.contains(date.as_str()) {
             continue;
@@ -134,6 +192,27 @@ pub fn discover_missing_shows(
             .max_by_key(|t| t.source_quality * 10 + t.format_quality)
             .unwrap();
 
+        let archive_venue = tapes.iter().find_map(|t| title_venue(&t.title));
+        let near_local = match near_local_show(
+            date,
+            archive_venue.as_deref(),
+            &local_set,
+            &archive_dates,
+            &local_venues,
+        ) {
+            NearMatch::SameVenue { local_date, venue } => {
+                shifted.push(DateShift {
+                    archive_date: date.clone(),
+                    local_date,
+                    venue,
+                    identifier: best.identifier.clone(),
+                });
+                continue;
+            }
+            NearMatch::Unconfirmed { local_date } => Some(local_date),
+            NearMatch::None => None,
+        };
+
         missing.push(MissingShow {
             date: date.clone(),
             best_identifier: best.identifier.clone(),
@@ -141,21 +220,135 @@ pub fn discover_missing_shows(
             source_quality: best.source_quality,
             format_quality: best.format_quality,
             tape_count: tapes.len(),
+            near_local,
+            rating: ratings.get(date).copied(),
         });
     }
 
     // Sort by date, then truncate
     missing.sort_by(|a, b| a.date.cmp(&b.date));
     missing.truncate(limit);
+    shifted.sort_by(|a, b| a.archive_date.cmp(&b.archive_date));
 
     Ok(DiscoveryResult {
         collection: cache_key,
         archive_count,
         local_count,
         missing,
+        pages_left,
+        shifted,
     })
 }
 
+/// Look for the archive show at `date` in the library a day either side.
+///
+/// Late-night shows run past midnight and get filed under either date, and
+/// hand-typed directory names slip a day. A neighboring local date only counts
+/// if the archive has no show of its own that day (a multi-night run would
+/// otherwise swallow its neighbors), and the venues, when both are known, must
+/// agree.
+fn near_local_show(
+    date: &str,
+    archive_venue: Option<&str>,
+    local_dates: &HashSet<&str>,
+    archive_dates: &HashSet<&str>,
+    local_venues: &HashMap<String, Vec<String>>,
+) -> NearMatch {
+    let Ok(day) = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+        return NearMatch::None;
+    };
+    let mut unconfirmed = None;
+    for neighbor in [day.pred_opt(), day.succ_opt()].into_iter().flatten() {
+        let neighbor = neighbor.format("%Y-%m-%d").to_string();
+        if !local_dates.contains(neighbor.as_str()) || archive_dates.contains(neighbor.as_str()) {
+            continue;
+        }
+        let venues = local_venues
+            .get(&neighbor)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        match archive_venue {
+            Some(theirs) if !venues.is_empty() => {
+                if let Some(ours) = venues.iter().find(|v| same_venue(theirs, v)) {
+                    return NearMatch::SameVenue {
+                        local_date: neighbor,
+                        venue: ours.clone(),
+                    };
+                }
+            }
+            _ => {
+                unconfirmed.get_or_insert(neighbor);
+            }
+        }
+    }
+    match unconfirmed {
+        Some(local_date) => NearMatch::Unconfirmed { local_date },
+        None => NearMatch::None,
+    }
+}
+
+/// "Grateful Dead Live at Barton Hall, Cornell University on 1977-05-08"
+static TITLE_VENUE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\blive at (?P<venue>.+?)(?: on \d{4}-\d{2}-\d{2})?\s*$").unwrap()
+});
+
+/// Venue from an archive.org item title in the usual "Live at" form.
+fn title_venue(title: &str) -> Option<String> {
+    let venue = TITLE_VENUE_RE
+        .captures(title)?
+        .name("venue")?
+        .as_str()
+        .trim();
+    (!venue.is_empty()).then(|| venue.to_string())
+}
+
+/// Words that say what kind of place a venue is, not which one.
+const GENERIC_VENUE_WORDS: &[&str] = &[
+    "the",
+    "at",
+    "of",
+    "and",
+    "hall",
+    "arena",
+    "theatre",
+    "theater",
+    "center",
+    "centre",
+    "auditorium",
+    "coliseum",
+    "stadium",
+    "university",
+    "college",
+    "club",
+    "ballroom",
+    "amphitheatre",
+    "amphitheater",
+    "pavilion",
+    "civic",
+    "memorial",
+    "park",
+    "field",
+];
+
+/// True if two venue strings name the same place: more than half of the
+/// shorter one's distinctive words appear in the other. Generic words only
+/// count when a name has nothing else ("Civic Center").
+fn same_venue(a: &str, b: &str) -> bool {
+    let words = |s: &str, distinctive: bool| -> HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .filter(|w| !distinctive || !GENERIC_VENUE_WORDS.contains(&w.as_str()))
+            .collect()
+    };
+    let (mut wa, mut wb) = (words(a, true), words(b, true));
+    if wa.is_empty() || wb.is_empty() {
+        (wa, wb) = (words(a, false), words(b, false));
+    }
+    let shared = wa.intersection(&wb).count();
+    shared > 0 && shared * 2 > wa.len().min(wb.len())
+}
+
 /// Solr deep pagination limit — archive.org returns errors past this offset.
 const MAX_SOLR_OFFSET: usize = 10_000;
 
@@ -176,77 +369,246 @@ const YEAR_RANGES: &[(u32, u32)] = &[
     (1996, 2025),
 ];
 
-/// Fetch all shows from an archive.org collection or creator.
+/// One page of a year-range search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PageRequest {
+    years: (u32, u32),
+    offset: usize,
+}
+
+/// Fetch all shows from an archive.org collection or creator into the cache.
 /// Uses year-range chunking to avoid Solr's 10K deep-pagination limit.
+///
+/// Pages are fetched `client.concurrency()` at a time and stored as they
+/// arrive, so an interrupted crawl keeps what it got: with `resume`, pages
+/// stored by an unfinished crawl (started within the TTL) are skipped. The
+/// first page of each year range comes first, since it tells how many pages
+/// the range has. Returns the number of pages still missing; zero completes
+/// the crawl and makes the listing the cache.
 fn fetch_collection_shows(
+    db: &Database,
     strategy: &ArchiveStrategy,
-    rate_limit_ms: u64,
-) -> Result<Vec<ArchiveShow>> {
+    client: &ArchiveClient,
+    resume: bool,
+    ttl_days: i64,
+) -> Result<usize> {
     let cache_key = query_cache_key(strategy);
+    let resumed = if resume {
+        db.resumable_archive_crawl(cache_key, ttl_days)
+            .context("Failed to read crawl progress")?
+    } else {
+        None
+    };
+    let stored: usize = match &resumed {
+        Some(pages) => {
+            let shows = pages.iter().map(|p| p.shows).sum();
+            crate::progress::note(&format!(
+                "Resuming an unfinished fetch: {} pages ({} shows) already stored",
+                pages.len(),
+                shows
+            ));
+            shows
+        }
+        None => {
+            db.begin_archive_crawl(cache_key)
+                .context("Failed to start crawl")?;
+            0
+        }
+    };
+
     // First, get total count for progress bar
-    let first_resp = fetch_search_page(strategy, None, 0, 0)?;
+    let first_resp = fetch_search_page(client, strategy, None, 0, 0)?;
     let total = first_resp.response.num_found;
 
-    let pb = ProgressBar::new(total as u64);
+    let pb = crate::progress::new_bar(total as u64);
     pb.set_style(
         ProgressStyle::with_template(
-            "  [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} shows ({per_sec})",
+            "  [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} shows ({per_sec}, {eta} left)",
         )
         .unwrap()
         .progress_chars("##-"),
     );
+    pb.set_position(stored as u64);
+    pb.reset_eta();
 
-    let mut all_shows = Vec::new();
-
-    for &(year_start, year_end) in YEAR_RANGES {
-        let date_range = Some((year_start, year_end));
-        let mut offset = 0;
-
-        loop {
-            if offset >= MAX_SOLR_OFFSET {
+    let mut store_error = None;
+    let mut on_page = |page: &PageRequest, result: Result<SearchResponse>| {
+        let (year_start, year_end) = page.years;
+        match result {
+            Ok(resp) => {
+                let num_found = resp.response.num_found;
+                if page.offset == 0 && num_found > MAX_SOLR_OFFSET {
+                    log::warn!(
+                        "Hit Solr limit for {cache_key} years {year_start}-{year_end}: \
+                         {num_found} items, only the first {MAX_SOLR_OFFSET} are reachable"
+                    );
+                }
+                let shows: Vec<ArchiveShow> = resp
+                    .response
+                    .docs
+                    .iter()
+                    .filter_map(|doc| parse_search_doc(doc, cache_key))
+                    .collect();
+                let record = ArchiveCrawlPage {
+                    year_start,
+                    year_end,
+                    offset: page.offset,
+                    num_found,
+                    shows: shows.len(),
+                };
+                let ratings: Vec<ItemRating> = resp
+                    .response
+                    .docs
+                    .iter()
+                    .map(|doc| {
+                        let (avg_rating, num_reviews) =
+                            parse_rating(doc.avg_rating.as_ref(), doc.num_reviews.as_ref());
+                        ItemRating {
+                            identifier: doc.identifier.clone(),
+                            date: doc.date.as_deref().and_then(extract_date),
+                            avg_rating,
+                            num_reviews,
+                        }
+                    })
+                    .collect();
+                let notes: Vec<ShowNotes> = resp
+                    .response
+                    .docs
+                    .iter()
+                    .filter_map(search_doc_notes)
+                    .collect();
+                match db
+                    .store_archive_page(&record, cache_key, &shows)
+                    .and_then(|()| db.store_ratings(&ratings))
+                    .and_then(|()| db.store_discovered_notes(&notes))
+                {
+                    Ok(()) => pb.inc(shows.len() as u64),
+                    Err(e) => {
+                        store_error.get_or_insert(e);
+                    }
+                }
+            }
+            Err(e) if client.budget_exhausted() => {
+                log::debug!(
+                    "Skipped {cache_key} {year_start}-{year_end} offset {}: {e}",
+                    page.offset
+                );
+            }
+            Err(e) => {
                 log::warn!(
-                    "Hit Solr limit for {cache_key} years {year_start}-{year_end} at offset {offset}"
+                    "Failed to fetch {cache_key} {year_start}-{year_end} offset {}: {e}",
+                    page.offset
                 );
-                break;
             }
+        }
+    };
 
-            thread::sleep(Duration::from_millis(rate_limit_ms));
+    // Round one learns each range's size; round two fetches the pages that
+    // revealed (and retries anything round one missed)
+    for _ in 0..2 {
+        let done = db
+            .archive_crawl_pages(cache_key)
+            .context("Failed to read crawl progress")?;
+        let pending = pending_pages(&done);
+        if pending.is_empty() || client.budget_exhausted() {
+            break;
+        }
+        fetch_pages(client, strategy, &pending, &mut on_page);
+    }
+    if let Some(e) = store_error {
+        pb.abandon();
+        return Err(e).context("Failed to cache shows");
+    }
 
-            match fetch_search_page(strategy, date_range, offset, PAGE_SIZE) {
-                Ok(resp) => {
-                    let docs = &resp.response.docs;
-                    if docs.is_empty() {
-                        break;
-                    }
-                    for doc in docs {
-                        if let Some(show) = parse_search_doc(doc, cache_key) {
-                            all_shows.push(show);
-                        }
-                    }
-                    pb.set_position(all_shows.len() as u64);
+    let done = db
+        .archive_crawl_pages(cache_key)
+        .context("Failed to read crawl progress")?;
+    let left = pending_pages(&done).len();
+    if left == 0 {
+        db.complete_archive_crawl(cache_key)
+            .context("Failed to finish crawl")?;
+        pb.finish_with_message("done");
+        return Ok(0);
+    }
+    pb.abandon();
+    if client.budget_exhausted() {
+        // Progress is saved, but don't treat a partial listing as complete
+        anyhow::bail!(
+            "Request budget reached with {left} pages left; fetched pages are saved, run discover again to resume"
+        );
+    }
+    Ok(left)
+}
 
-                    if docs.len() < PAGE_SIZE {
-                        break; // Last page
-                    }
-                    offset += PAGE_SIZE;
-                }
-                Err(e) => {
-                    log::warn!(
-                        "Failed to fetch {cache_key} {year_start}-{year_end} offset {offset}: {e}"
+/// Pages a crawl still needs, given what's stored: the first page of every
+/// year range not seen yet, plus the remaining pages of ranges whose size is
+/// known (up to the Solr offset limit).
+fn pending_pages(done: &[ArchiveCrawlPage]) -> Vec<PageRequest> {
+    let fetched: HashSet<(u32, usize)> = done.iter().map(|p| (p.year_start, p.offset)).collect();
+    let mut pending = Vec::new();
+    for &years in YEAR_RANGES {
+        let first = done
+            .iter()
+            .find(|p| p.year_start == years.0 && p.offset == 0);
+        let Some(first) = first else {
+            pending.push(PageRequest { years, offset: 0 });
+            continue;
+        };
+        let end = first.num_found.min(MAX_SOLR_OFFSET);
+        pending.extend(
+            (PAGE_SIZE..end)
+                .step_by(PAGE_SIZE)
+                .filter(|&offset| !fetched.contains(&(years.0, offset)))
+                .map(|offset| PageRequest { years, offset }),
+        );
+    }
+    pending
+}
+
+/// Fetch `pages` with up to `client.concurrency()` requests in flight, handing
+/// each result to `sink` on the calling thread as it arrives (the database
+/// connection stays on this thread). Workers stop taking new pages once the
+/// request budget is spent.
+fn fetch_pages(
+    client: &ArchiveClient,
+    strategy: &ArchiveStrategy,
+    pages: &[PageRequest],
+    mut sink: impl FnMut(&PageRequest, Result<SearchResponse>),
+) {
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel::<(usize, Result<SearchResponse>)>();
+    let next = &next;
+    std::thread::scope(|s| {
+        for _ in 0..client.concurrency().min(pages.len()) {
+            let tx = tx.clone();
+            s.spawn(move || {
+                while !client.budget_exhausted() {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(page) = pages.get(i) else { break };
+                    let result = fetch_search_page(
+                        client,
+                        strategy,
+                        Some(page.years),
+                        page.offset,
+                        PAGE_SIZE,
                     );
-                    break;
+                    if tx.send((i, result)).is_err() {
+                        break;
+                    }
                 }
-            }
+            });
         }
-    }
-
-    pb.finish_with_message(format!("Fetched {} shows", all_shows.len()));
-    Ok(all_shows)
+        drop(tx);
+        for (i, result) in rx {
+            sink(&pages[i], result);
+        }
+    });
 }
 
 /// Fetch a single page from the archive.org advanced search API.
 /// If `date_range` is Some, restricts to items with dates in that year range.
 fn fetch_search_page(
+    client: &ArchiveClient,
     strategy: &ArchiveStrategy,
     date_range: Option<(u32, u32)>,
     start: usize,
@@ -261,16 +623,16 @@ fn fetch_search_page(
         "https://archive.org/advancedsearch.php?\
          q={q_clause}{date_clause}&\
          fl%5B%5D=identifier&fl%5B%5D=date&fl%5B%5D=title&\
-         sort%5B%5D=date+asc&\
+         fl%5B%5D=avg_rating&fl%5B%5D=num_reviews&\
+         fl%5B%5D=venue&fl%5B%5D=source&fl%5B%5D=lineage&fl%5B%5D=taper&\
+         fl%5B%5D=transferer&fl%5B%5D=description&fl%5B%5D=notes&\
+         sort%5B%5D=date+asc&sort%5B%5D=identifier+asc&\
          rows={rows}&start={start}&output=json"
     );
 
-    let resp: SearchResponse = ureq::get(&url)
-        .call()
-        .with_context(|| format!("HTTP request failed (page at offset {start})"))?
-        .body_mut()
-        .read_json()
-        .with_context(|| format!("Failed to parse JSON (page at offset {start})"))?;
+    let resp: SearchResponse = client
+        .get_json(&url)
+        .with_context(|| format!("Search request failed (page at offset {start})"))?;
 
     Ok(resp)
 }
@@ -291,41 +653,33 @@ fn parse_search_doc(doc: &SearchDoc, collection: &str) -> Option<ArchiveShow> {
     })
 }
 
+/// Show notes from a search result, if it has any provenance to keep. They
+/// aren't tied to a local directory; `setlist` fills that in for the sources
+/// in the library.
+fn search_doc_notes(doc: &SearchDoc) -> Option<ShowNotes> {
+    let mut notes = doc.notes.clone().into_show_notes(&doc.identifier, "");
+    notes.date = doc.date.as_deref().and_then(extract_date);
+    let provenance = [
+        &notes.source,
+        &notes.lineage,
+        &notes.taper,
+        &notes.transferer,
+        &notes.description,
+        &notes.notes,
+    ];
+    provenance.iter().any(|f| f.is_some()).then_some(notes)
+}
+
 /// Extract YYYY-MM-DD date from archive.org date strings.
 /// Handles: "1977-05-08T00:00:00Z", "1977-05-08", "1977-05-08T00:00:00"
 fn extract_date(raw: &str) -> Option<String> {
-    let d = raw.trim();
-    if d.len() >= 10 {
-        let date_part = &d[..10];
-        // Basic validation: YYYY-MM-DD
-        if date_part.len() == 10
-            && date_part.as_bytes()[4] == b'-'
-            && date_part.as_bytes()[7] == b'-'
-        {
-            return Some(date_part.to_string());
-        }
-    }
-    None
+    crate::dates::parse_iso(raw)
 }
 
 /// Parse source quality from identifier string.
 /// sbd=3 (soundboard), matrix=2, aud=1 (audience), unknown=0
 fn parse_source_quality(identifier: &str) -> i32 {
-    let id_lower = identifier.to_lowercase();
-    if id_lower.contains(".sbd.")
-        || id_lower.contains("_sbd_")
-        || id_lower.contains("-sbd-")
-        || id_lower.contains(".sbd")
-    {
-        3
-    } else if id_lower.contains("matrix") || id_lower.contains(".mtx.") {
-        2
-    } else if id_lower.contains(".aud.") || id_lower.contains("_aud_") || id_lower.contains("-aud-")
-    {
-        1
-    } else {
-        0 // Unknown source
-    }
+    source::source_quality(source::parse_name(identifier).source_type)
 }
 
 /// Parse format quality from identifier string.
@@ -356,7 +710,7 @@ fn query_cache_key(strategy: &ArchiveStrategy) -> &str {
 }
 
 /// Build the search query string for archive.org.
-fn query_clause(strategy: &ArchiveStrategy) -> String {
+pub(crate) fn query_clause(strategy: &ArchiveStrategy) -> String {
     match strategy {
         ArchiveStrategy::Collection(c) => format!("collection%3A{c}"),
         ArchiveStrategy::Creator(c) => format!("creator%3A{c}"),
@@ -475,6 +829,53 @@ pub fn download_glob(format_quality: i32) -> &'static str {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_search_doc_notes() {
+        let doc: SearchDoc = serde_json::from_str(
+            r#"{"identifier": "gd1977-05-08.sbd.hicks.4982", "date": "1977-05-08T00:00:00Z",
+                "taper": "Betty Cantor-Jackson", "lineage": ["SBD > Reel", "> DAT > FLAC"]}"#,
+        )
+        .unwrap();
+        let notes = search_doc_notes(&doc).unwrap();
+        assert_eq!(notes.date.as_deref(), Some("1977-05-08"));
+        assert_eq!(notes.lineage.as_deref(), Some("SBD > Reel\n> DAT > FLAC"));
+        assert_eq!(notes.local_dir, "");
+        let bare: SearchDoc =
+            serde_json::from_str(r#"{"identifier": "x", "date": "1977-05-08"}"#).unwrap();
+        assert!(search_doc_notes(&bare).is_none());
+
+        // Discovery keeps the directory setlist matched, and notes without a
+        // date are found through the directory's tracks
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (file_path, file_size, file_modified, format, parsed_date)
+                 VALUES ('/m/gd77-05-08.sbd/d1t01.flac', 1, '0', 'flac', '1977-05-08');",
+            )
+            .unwrap();
+        db.store_show_notes(&ShowNotes {
+            identifier: notes.identifier.clone(),
+            local_dir: "gd77-05-08.sbd".into(),
+            ..Default::default()
+        })
+        .unwrap();
+        db.store_discovered_notes(std::slice::from_ref(&notes))
+            .unwrap();
+        db.store_show_notes(&ShowNotes {
+            identifier: "undated".into(),
+            local_dir: "gd77-05-08.sbd".into(),
+            taper: Some("Unknown".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        let stored = db.get_show_notes("1977-05-08").unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].local_dir, "gd77-05-08.sbd");
+        assert_eq!(stored[0].taper.as_deref(), Some("Betty Cantor-Jackson"));
+        assert_eq!(stored[1].identifier, "undated");
+        assert!(db.get_show_notes("1977-05-09").unwrap().is_empty());
+    }
+
     #[test]
     fn test_extract_date() {
         assert_eq!(
@@ -490,6 +891,49 @@ mod tests {
         assert_eq!(extract_date(""), None);
     }
 
+    #[test]
+    fn test_near_local_show() {
+        let local: HashSet<&str> = ["1977-05-09", "1978-12-31", "1979-01-02"].into();
+        let archive: HashSet<&str> = ["1977-05-08", "1979-01-01", "1979-01-02"].into();
+        let venues: HashMap<String, Vec<String>> = [(
+            "1977-05-09".to_string(),
+            vec!["Barton Hall, Cornell University".to_string()],
+        )]
+        .into();
+        let near = |date, venue| near_local_show(date, venue, &local, &archive, &venues);
+
+        // Filed a day late, same venue
+        assert_eq!(
+            near("1977-05-08", Some("Barton Hall")),
+            NearMatch::SameVenue {
+                local_date: "1977-05-09".into(),
+                venue: "Barton Hall, Cornell University".into()
+            }
+        );
+        // A different place is a different show
+        assert_eq!(
+            near("1977-05-08", Some("Boston University")),
+            NearMatch::None
+        );
+        // 1979-01-02 is a show of its own, so only 1978-12-31 is a candidate,
+        // and with no local venue it can't be confirmed
+        assert_eq!(
+            near("1979-01-01", Some("Winterland Arena")),
+            NearMatch::Unconfirmed {
+                local_date: "1978-12-31".into()
+            }
+        );
+
+        assert_eq!(
+            title_venue("Grateful Dead Live at Barton Hall, Cornell University on 1977-05-08")
+                .as_deref(),
+            Some("Barton Hall, Cornell University")
+        );
+        assert_eq!(title_venue("gd1977-05-08"), None);
+        assert!(same_venue("Winterland", "Winterland Arena"));
+        assert!(!same_venue("Cornell University", "Boston University"));
+    }
+
     #[test]
     fn test_source_quality() {
         assert_eq!(
@@ -515,6 +959,39 @@ mod tests {
         assert_eq!(parse_format_quality("gd1977-05-08.sbd.miller.12345"), 0);
     }
 
+    #[test]
+    fn test_pending_pages() {
+        let page = |year_start, year_end, offset, num_found| ArchiveCrawlPage {
+            year_start,
+            year_end,
+            offset,
+            num_found,
+            shows: 0,
+        };
+        // Nothing stored: one first page per year range
+        let pending = pending_pages(&[]);
+        assert_eq!(pending.len(), YEAR_RANGES.len());
+        assert!(pending.iter().all(|p| p.offset == 0));
+
+        let done = [
+            page(1976, 1977, 0, 1200),
+            page(1976, 1977, 1000, 1200),
+            // Past the Solr limit: only the reachable pages are pending
+            page(1972, 1973, 0, 25_000),
+        ];
+        let pending = pending_pages(&done);
+        let for_range = |start| {
+            pending
+                .iter()
+                .filter(|p| p.years.0 == start)
+                .map(|p| p.offset)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(for_range(1976), vec![500]);
+        assert_eq!(for_range(1972).len(), MAX_SOLR_OFFSET / PAGE_SIZE - 1);
+        assert_eq!(for_range(1960), vec![0]);
+    }
+
     #[test]
     fn test_year_filter() {
         assert!(matches_year_filter("1977-05-08", "1977"));
@@ -0,0 +1,475 @@
+//! Rescore experiments: try new score weights without touching stored scores.
+//!
+//! An experiment file redefines some of the jam scores, either as weights over
+//! feature columns (any `score-lab --list` variable, including the current
+//! scores) or as a score-lab formula:
+//!
+//! ```toml
+//! # Weighted sum: groove = 0.7*groove_score + 30*tempo_stability
+//! [groove]
+//! groove_score = 0.7
+//! tempo_stability = 30.0
+//!
+//! # Formula; the table's numbers are bound as variables
+//! [energy]
+//! formula = "rms_level / 0.18 * w_rms + (lufs_integrated + 55) / 22 * w_lufs"
+//! w_rms = 40
+//! w_lufs = 20
+//! ```
+//!
+//! Experimental scores are clamped to 0-100 and written to a TEMP table, which
+//! disappears with the connection. Comparison reports each score's Spearman rank
+//! correlation with the stored scores and the tracks whose rank moved most.
+
+use crate::db::Database;
+use crate::db::columns::ANALYSIS_SCHEMA;
+use crate::score_lab::{FeatureRow, feature_context, load_feature_rows};
+use crate::scores::{self, ScoreMeta};
+use evalexpr::{ContextWithMutableVariables, DefaultNumericTypes, Node, Value};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ExperimentError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Invalid experiment file: {0}")]
+    Parse(String),
+    #[error("Unknown score '{0}' (use a name from `setbreak top --help`)")]
+    UnknownScore(String),
+    #[error("Score '{score}': {message}")]
+    Definition { score: String, message: String },
+    #[error("{0}")]
+    Load(String),
+    #[error(transparent)]
+    Db(#[from] crate::db::DbError),
+}
+
+pub type Result<T> = std::result::Result<T, ExperimentError>;
+
+/// How an experimental score is computed.
+enum Definition {
+    /// Sum of `weight * feature`.
+    Weights(Vec<(String, f64)>),
+    /// Score-lab expression, with the table's numbers bound as variables.
+    Formula {
+        tree: Node<DefaultNumericTypes>,
+        vars: Vec<(String, f64)>,
+    },
+}
+
+struct ScoreDef {
+    meta: &'static ScoreMeta,
+    definition: Definition,
+}
+
+/// A parsed experiment file.
+pub struct Experiment {
+    defs: Vec<ScoreDef>,
+}
+
+/// Summary of one experimental score after a run.
+#[derive(Debug, Clone)]
+pub struct ScoreSummary {
+    pub score: &'static ScoreMeta,
+    /// Tracks the definition produced a finite value for.
+    pub tracks: usize,
+    pub mean: f64,
+    pub p5: f64,
+    pub p95: f64,
+}
+
+/// A track whose rank changed under the experiment.
+#[derive(Debug, Clone)]
+pub struct Mover {
+    pub title: String,
+    pub date: String,
+    pub current: f64,
+    pub experiment: f64,
+    /// 1 = highest score; ties share their average rank.
+    pub current_rank: f64,
+    pub experiment_rank: f64,
+}
+
+/// Experimental vs stored values for one score.
+#[derive(Debug, Clone)]
+pub struct ScoreComparison {
+    pub score: &'static ScoreMeta,
+    pub tracks: usize,
+    pub spearman: f64,
+    pub current_mean: f64,
+    pub experiment_mean: f64,
+    /// Biggest rank changes first.
+    pub movers: Vec<Mover>,
+}
+
+impl Experiment {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ExperimentError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let table: toml::Table =
+            toml::from_str(contents).map_err(|e| ExperimentError::Parse(e.to_string()))?;
+        let features: Vec<&str> = ANALYSIS_SCHEMA
+            .iter()
+            .filter(|c| c.sql_type == "REAL" || c.sql_type == "INT")
+            .map(|c| c.name)
+            .chain(std::iter::once("duration_min"))
+            .collect();
+
+        let mut defs: Vec<ScoreDef> = Vec::new();
+        for (key, value) in &table {
+            let meta =
+                scores::get(key).ok_or_else(|| ExperimentError::UnknownScore(key.clone()))?;
+            let err = |message: String| ExperimentError::Definition {
+                score: key.clone(),
+                message,
+            };
+            if defs.iter().any(|d| d.meta.name == meta.name) {
+                return Err(err("defined more than once".into()));
+            }
+            let entries = value
+                .as_table()
+                .ok_or_else(|| err("expected a table of weights or a formula".into()))?;
+
+            let mut formula = None;
+            let mut weights = Vec::new();
+            for (name, v) in entries {
+                match v {
+                    toml::Value::String(f) if name == "formula" => formula = Some(f.clone()),
+                    toml::Value::Float(w) => weights.push((name.clone(), *w)),
+                    toml::Value::Integer(w) => weights.push((name.clone(), *w as f64)),
+                    _ => return Err(err(format!("'{name}' must be a number"))),
+                }
+            }
+
+            let definition = match formula {
+                Some(f) => {
+                    let tree = evalexpr::build_operator_tree::<DefaultNumericTypes>(&f)
+                        .map_err(|e| err(format!("parse error: {e}")))?;
+                    Definition::Formula {
+                        tree,
+                        vars: weights,
+                    }
+                }
+                None => {
+                    if weights.is_empty() {
+                        return Err(err("no weights given".into()));
+                    }
+                    if let Some((name, _)) = weights
+                        .iter()
+                        .find(|(n, _)| !features.contains(&n.as_str()))
+                    {
+                        return Err(err(format!(
+                            "unknown feature '{name}' (see `setbreak score-lab --list`)"
+                        )));
+                    }
+                    Definition::Weights(weights)
+                }
+            };
+            defs.push(ScoreDef { meta, definition });
+        }
+
+        if defs.is_empty() {
+            return Err(ExperimentError::Parse("no scores defined".into()));
+        }
+        // Registry order, so output lines up with the usual score order
+        defs.sort_by_key(|d| scores::SCORES.iter().position(|s| s.name == d.meta.name));
+        Ok(Self { defs })
+    }
+
+    /// The scores this experiment redefines.
+    pub fn scores(&self) -> Vec<&'static ScoreMeta> {
+        self.defs.iter().map(|d| d.meta).collect()
+    }
+
+    /// Compute every experimental score into the `experiment_scores` TEMP table.
+    pub fn run(&self, db: &Database) -> Result<Vec<ScoreSummary>> {
+        let rows = load_feature_rows(db, None, false).map_err(ExperimentError::Load)?;
+
+        let mut values: Vec<Vec<(i64, f64)>> = vec![Vec::new(); self.defs.len()];
+        for row in &rows {
+            for (def, out) in self.defs.iter().zip(values.iter_mut()) {
+                if let Some(v) = def.evaluate(row)? {
+                    out.push((row.track_id, v));
+                }
+            }
+        }
+
+        db.write_experiment_scores(
+            &self
+                .defs
+                .iter()
+                .map(|d| d.meta.name)
+                .zip(values.iter().map(Vec::as_slice))
+                .collect::<Vec<_>>(),
+        )?;
+
+        Ok(self
+            .defs
+            .iter()
+            .zip(&values)
+            .map(|(def, vals)| {
+                let mut sorted: Vec<f64> = vals.iter().map(|(_, v)| *v).collect();
+                sorted.sort_by(f64::total_cmp);
+                ScoreSummary {
+                    score: def.meta,
+                    tracks: sorted.len(),
+                    mean: mean(&sorted),
+                    p5: percentile(&sorted, 0.05),
+                    p95: percentile(&sorted, 0.95),
+                }
+            })
+            .collect())
+    }
+
+    /// Compare the experimental scores from `run` against the stored ones.
+    pub fn compare(&self, db: &Database, movers: usize) -> Result<Vec<ScoreComparison>> {
+        self.defs
+            .iter()
+            .map(|def| {
+                let pairs = db.experiment_pairs(def.meta)?;
+                Ok(compare_pairs(def.meta, pairs, movers))
+            })
+            .collect()
+    }
+}
+
+impl ScoreDef {
+    /// This score for one track, clamped to 0-100; `None` if it isn't a finite number.
+    fn evaluate(&self, row: &FeatureRow) -> Result<Option<f64>> {
+        let value = match &self.definition {
+            Definition::Weights(weights) => Some(
+                weights
+                    .iter()
+                    .map(|(name, w)| w * feature(row, name))
+                    .sum::<f64>(),
+            ),
+            Definition::Formula { tree, vars } => {
+                let mut context = feature_context(row).map_err(ExperimentError::Load)?;
+                for (name, v) in vars {
+                    context
+                        .set_value(name.clone(), Value::Float(*v))
+                        .map_err(|e| ExperimentError::Definition {
+                            score: self.meta.name.to_string(),
+                            message: format!("cannot bind '{name}': {e}"),
+                        })?;
+                }
+                tree.eval_with_context(&context).ok().and_then(|v| {
+                    v.as_float()
+                        .ok()
+                        .or_else(|| v.as_int().ok().map(|i: i64| i as f64))
+                })
+            }
+        };
+        Ok(value.filter(|v| v.is_finite()).map(|v| v.clamp(0.0, 100.0)))
+    }
+}
+
+fn feature(row: &FeatureRow, name: &str) -> f64 {
+    if name == "duration_min" {
+        return row.duration_min;
+    }
+    row.features.get(name).copied().unwrap_or(0.0)
+}
+
+/// Rank correlation and biggest movers from (title, date, current, experiment) rows.
+fn compare_pairs(
+    score: &'static ScoreMeta,
+    pairs: Vec<(String, String, f64, f64)>,
+    movers: usize,
+) -> ScoreComparison {
+    let current: Vec<f64> = pairs.iter().map(|p| p.2).collect();
+    let experiment: Vec<f64> = pairs.iter().map(|p| p.3).collect();
+    let current_ranks = ranks(&current);
+    let experiment_ranks = ranks(&experiment);
+
+    let mut moved: Vec<Mover> = pairs
+        .into_iter()
+        .enumerate()
+        .map(|(i, (title, date, cur, exp))| Mover {
+            title,
+            date,
+            current: cur,
+            experiment: exp,
+            current_rank: current_ranks[i],
+            experiment_rank: experiment_ranks[i],
+        })
+        .filter(|m| m.current_rank != m.experiment_rank)
+        .collect();
+    let distance = |m: &Mover| (m.current_rank - m.experiment_rank).abs();
+    moved.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+    moved.truncate(movers);
+
+    ScoreComparison {
+        score,
+        tracks: current.len(),
+        spearman: pearson(&current_ranks, &experiment_ranks),
+        current_mean: mean(&current),
+        experiment_mean: mean(&experiment),
+        movers: moved,
+    }
+}
+
+/// Descending ranks (1 = highest); ties get the average of their positions.
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+    let mut out = vec![0.0; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        for &idx in &order[i..=j] {
+            out[idx] = rank;
+        }
+        i = j + 1;
+    }
+    out
+}
+
+fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let (mx, my) = (mean(x), mean(y));
+    let (mut cov, mut vx, mut vy) = (0.0, 0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        cov += (a - mx) * (b - my);
+        vx += (a - mx).powi(2);
+        vy += (b - my).powi(2);
+    }
+    if vx == 0.0 || vy == 0.0 {
+        return 0.0;
+    }
+    cov / (vx.sqrt() * vy.sqrt())
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Nearest-rank percentile of an already sorted slice.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let idx = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[idx]
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Replace the TEMP `experiment_scores` table with the given (score, values) sets.
+    fn write_experiment_scores(&self, sets: &[(&str, &[(i64, f64)])]) -> crate::db::Result<()> {
+        self.conn.execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS experiment_scores (
+                track_id INTEGER NOT NULL,
+                score TEXT NOT NULL,
+                value REAL NOT NULL,
+                PRIMARY KEY (track_id, score)
+            );
+            DELETE FROM temp.experiment_scores;",
+        )?;
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO temp.experiment_scores (track_id, score, value) VALUES (?1, ?2, ?3)",
+            )?;
+            for (score, values) in sets {
+                for (track_id, value) in *values {
+                    stmt.execute(rusqlite::params![track_id, score, value])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// (title, date, stored score, experimental score) for every track with both.
+    fn experiment_pairs(
+        &self,
+        score: &ScoreMeta,
+    ) -> crate::db::Result<Vec<(String, String, f64, f64)>> {
+        // The column name comes from the score registry, never from user input
+        let sql = format!(
+            "SELECT COALESCE(t.parsed_title, t.title, '(untitled)'),
+                    COALESCE(t.parsed_date, t.date, '?'),
+                    a.{col}, e.value
+             FROM temp.experiment_scores e
+             JOIN analysis_results a ON a.track_id = e.track_id
+             JOIN tracks t ON t.id = e.track_id
+             WHERE e.score = ?1 AND a.{col} IS NOT NULL",
+            col = score.column
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map([score.name], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_experiment() {
+        let exp = Experiment::parse(
+            "[Grv]\ngroove_score = 0.7\ntempo_stability = 30\n\n\
+             [energy]\nformula = \"rms_level * w\"\nw = 100\n",
+        )
+        .unwrap();
+        let names: Vec<_> = exp.scores().iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["energy", "groove"]);
+
+        assert!(matches!(
+            Experiment::parse("[vibes]\nrms_level = 1\n"),
+            Err(ExperimentError::UnknownScore(_))
+        ));
+        assert!(matches!(
+            Experiment::parse("[groove]\nnot_a_feature = 1\n"),
+            Err(ExperimentError::Definition { .. })
+        ));
+        assert!(matches!(
+            Experiment::parse("[groove]\nformula = \"(\"\n"),
+            Err(ExperimentError::Definition { .. })
+        ));
+        assert!(Experiment::parse("").is_err());
+    }
+
+    #[test]
+    fn test_ranks_and_spearman() {
+        assert_eq!(ranks(&[10.0, 30.0, 20.0, 30.0]), vec![4.0, 1.5, 3.0, 1.5]);
+
+        let pairs = vec![
+            ("A".into(), "1977-05-08".into(), 90.0, 10.0),
+            ("B".into(), "1977-05-09".into(), 50.0, 50.0),
+            ("C".into(), "1977-05-10".into(), 10.0, 90.0),
+        ];
+        let cmp = compare_pairs(scores::get("groove").unwrap(), pairs, 1);
+        assert!((cmp.spearman + 1.0).abs() < 1e-9);
+        assert_eq!(cmp.tracks, 3);
+        assert_eq!(cmp.movers.len(), 1);
+        assert_eq!(cmp.movers[0].title, "A");
+        assert_eq!(
+            (cmp.movers[0].current_rank, cmp.movers[0].experiment_rank),
+            (1.0, 3.0)
+        );
+    }
+}
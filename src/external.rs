@@ -0,0 +1,635 @@
+//! External analyzers: feature extractors run as subprocesses.
+//!
+//! Each `[[external_analyzers]]` entry in the config names a command (Essentia,
+//! a Python model, ...) that setbreak runs once per track. The protocol is one
+//! JSON object each way:
+//!
+//! - stdin: `{"track_id": 12, "path": "/music/gd77-05-08d1t01.flac"}`. The
+//!   placeholders `{path}` and `{track_id}` in the command's arguments are
+//!   replaced too, for tools that only take arguments.
+//! - stdout: a JSON object of features, e.g. `{"mood": {"happy": 0.71}}`. Log
+//!   lines before it are tolerated as long as the object is the last line.
+//!
+//! A non-zero exit or a timeout fails the track. Each declared output picks a
+//! key from the object (dotted paths reach into nested objects) and stores it
+//! in a column of its own in `external_features`, added on first run. Columns
+//! are queryable as `ext.<column>` in `setbreak query`.
+
+use crate::db::Database;
+use rusqlite::types::Value;
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How often a running analyzer is checked for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Error, Debug)]
+pub enum ExternalError {
+    #[error("{analyzer}: failed to start '{program}': {source}")]
+    Spawn {
+        analyzer: String,
+        program: String,
+        source: std::io::Error,
+    },
+    #[error("{analyzer}: IO error: {source}")]
+    Io {
+        analyzer: String,
+        source: std::io::Error,
+    },
+    #[error("{analyzer}: timed out after {secs}s")]
+    Timeout { analyzer: String, secs: u64 },
+    #[error("{analyzer}: exited with {status}: {stderr}")]
+    Failed {
+        analyzer: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+    #[error("{analyzer}: bad output: {message}")]
+    BadOutput { analyzer: String, message: String },
+    #[error("Remote fetch failed: {0}")]
+    Remote(#[from] crate::remote::RemoteError),
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+}
+
+/// One external analyzer from `[[external_analyzers]]`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalAnalyzerConfig {
+    /// Short name, used on the command line and to track which tracks ran.
+    pub name: String,
+    /// Program and arguments; `{path}` and `{track_id}` are substituted.
+    pub command: Vec<String>,
+    /// Kill the process after this many seconds.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Values to keep from the output.
+    pub outputs: Vec<ExternalOutput>,
+}
+
+fn default_timeout_secs() -> u64 {
+    600
+}
+
+/// One value kept from an analyzer's output.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalOutput {
+    /// Key in the output object; dots reach into nested objects (`mood.happy`).
+    pub key: String,
+    /// Column in `external_features`. Defaults to the key with dots as underscores.
+    pub column: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: OutputType,
+}
+
+/// SQL type of an output column.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputType {
+    #[default]
+    Real,
+    Integer,
+    Text,
+}
+
+impl OutputType {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Real => "REAL",
+            Self::Integer => "INTEGER",
+            Self::Text => "TEXT",
+        }
+    }
+}
+
+impl ExternalOutput {
+    pub fn column(&self) -> String {
+        self.column
+            .clone()
+            .unwrap_or_else(|| self.key.replace('.', "_"))
+    }
+}
+
+/// Check names, commands and columns, so a typo fails at startup instead of
+/// mid-run. Columns must be unique across all analyzers.
+pub fn validate(analyzers: &[ExternalAnalyzerConfig]) -> Result<(), String> {
+    let mut names = std::collections::HashSet::new();
+    let mut columns = std::collections::HashMap::new();
+    for a in analyzers {
+        if !is_identifier(&a.name) {
+            return Err(format!(
+                "analyzer name '{}' must be lowercase letters, digits and underscores",
+                a.name
+            ));
+        }
+        if !names.insert(a.name.as_str()) {
+            return Err(format!("analyzer '{}' is defined twice", a.name));
+        }
+        if a.command.is_empty() {
+            return Err(format!("{}: command is empty", a.name));
+        }
+        if a.timeout_secs == 0 {
+            return Err(format!("{}: timeout_secs must be at least 1", a.name));
+        }
+        if a.outputs.is_empty() {
+            return Err(format!("{}: no outputs declared", a.name));
+        }
+        for o in &a.outputs {
+            let column = o.column();
+            if !is_identifier(&column) || column == "track_id" {
+                return Err(format!(
+                    "{}: '{column}' is not a valid column name (lowercase letters, digits, underscores)",
+                    a.name
+                ));
+            }
+            if let Some(other) = columns.insert(column.clone(), a.name.as_str()) {
+                return Err(format!(
+                    "column '{column}' is declared by both {other} and {}",
+                    a.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Lowercase ASCII identifier, safe to inline in SQL.
+pub(crate) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Result of one run: the outputs as `(column, value)` pairs.
+pub type RunResult = Result<Vec<(String, Value)>, ExternalError>;
+
+/// Run an analyzer on one file and return its declared outputs as
+/// `(column, value)` pairs. Missing keys come back as NULL.
+pub fn run(analyzer: &ExternalAnalyzerConfig, track_id: i64, file_path: &str) -> RunResult {
+    // Remote files are run against a cached copy, deleted when `cached` drops
+    let cached = if crate::remote::is_remote(file_path) {
+        Some(crate::remote::fetch(file_path)?)
+    } else {
+        None
+    };
+    let path = match &cached {
+        Some(c) => c.path().to_path_buf(),
+        None => crate::paths::to_fs(file_path),
+    };
+    let stdout = run_process(analyzer, track_id, &path.to_string_lossy())?;
+    parse_output(analyzer, &stdout)
+}
+
+/// Run an analyzer over `(track_id, file_path)` pairs, `jobs` processes at a
+/// time, handing each result to `sink` on the calling thread as it finishes.
+pub fn run_many(
+    analyzer: &ExternalAnalyzerConfig,
+    tracks: &[(i64, String)],
+    jobs: usize,
+    mut sink: impl FnMut(&(i64, String), RunResult),
+) {
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel::<(usize, RunResult)>();
+    let next = &next;
+    std::thread::scope(|s| {
+        for _ in 0..jobs.max(1).min(tracks.len()) {
+            let tx = tx.clone();
+            s.spawn(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((id, path)) = tracks.get(i) else {
+                        break;
+                    };
+                    if tx.send((i, run(analyzer, *id, path))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+        for (i, result) in rx {
+            sink(&tracks[i], result);
+        }
+    });
+}
+
+/// Spawn the command, send the request on stdin, and collect stdout.
+fn run_process(
+    analyzer: &ExternalAnalyzerConfig,
+    track_id: i64,
+    path: &str,
+) -> Result<String, ExternalError> {
+    let io_err = |source| ExternalError::Io {
+        analyzer: analyzer.name.clone(),
+        source,
+    };
+    let id = track_id.to_string();
+    let args: Vec<String> = analyzer
+        .command
+        .iter()
+        .map(|a| a.replace("{path}", path).replace("{track_id}", &id))
+        .collect();
+    let mut child = Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| ExternalError::Spawn {
+            analyzer: analyzer.name.clone(),
+            program: args[0].clone(),
+            source,
+        })?;
+
+    // The request fits in a pipe buffer. A tool that ignores stdin may exit
+    // before reading it, so a broken pipe here isn't an error.
+    let request = serde_json::json!({ "track_id": track_id, "path": path });
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "{request}");
+    }
+
+    // Drain both pipes on their own threads so a chatty tool can't block on a full pipe
+    let mut out = child.stdout.take().expect("piped stdout");
+    let mut err = child.stderr.take().expect("piped stderr");
+    let out_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        out.read_to_end(&mut buf).map(|_| buf)
+    });
+    let err_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = err.read_to_end(&mut buf);
+        buf
+    });
+
+    let deadline = Instant::now() + Duration::from_secs(analyzer.timeout_secs);
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(io_err)? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(ExternalError::Timeout {
+                analyzer: analyzer.name.clone(),
+                secs: analyzer.timeout_secs,
+            });
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let stdout = out_reader
+        .join()
+        .expect("stdout reader panicked")
+        .map_err(io_err)?;
+    let stderr = err_reader.join().expect("stderr reader panicked");
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(ExternalError::Failed {
+            analyzer: analyzer.name.clone(),
+            status,
+            stderr: stderr.trim().lines().last().unwrap_or("").to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// Pick the declared outputs out of an analyzer's stdout.
+pub fn parse_output(analyzer: &ExternalAnalyzerConfig, stdout: &str) -> RunResult {
+    let bad = |message: String| ExternalError::BadOutput {
+        analyzer: analyzer.name.clone(),
+        message,
+    };
+    let text = stdout.trim();
+    let object = serde_json::from_str::<serde_json::Value>(text)
+        .ok()
+        .or_else(|| {
+            let last = text.lines().rev().find(|l| !l.trim().is_empty())?;
+            serde_json::from_str(last).ok()
+        })
+        .ok_or_else(|| bad("no JSON object on stdout".to_string()))?;
+    if !object.is_object() {
+        return Err(bad("output is not a JSON object".to_string()));
+    }
+
+    analyzer
+        .outputs
+        .iter()
+        .map(|o| {
+            let raw = o
+                .key
+                .split('.')
+                .try_fold(&object, |v, part| v.get(part))
+                .unwrap_or(&serde_json::Value::Null);
+            let value = convert(raw, o.kind)
+                .ok_or_else(|| bad(format!("'{}' is not {}: {raw}", o.key, o.kind.sql())))?;
+            Ok((o.column(), value))
+        })
+        .collect()
+}
+
+/// JSON value as the column type; `None` if it doesn't fit.
+fn convert(raw: &serde_json::Value, kind: OutputType) -> Option<Value> {
+    use serde_json::Value as J;
+    Some(match (raw, kind) {
+        (J::Null, _) => Value::Null,
+        (J::Bool(b), OutputType::Real) => Value::Real(f64::from(u8::from(*b))),
+        (J::Bool(b), OutputType::Integer) => Value::Integer(i64::from(*b)),
+        (J::Number(n), OutputType::Real) => Value::Real(n.as_f64()?),
+        (J::Number(n), OutputType::Integer) => Value::Integer(
+            n.as_i64()
+                .or_else(|| n.as_f64().map(|f| f.round() as i64))?,
+        ),
+        (J::String(s), OutputType::Real) => Value::Real(s.trim().parse().ok()?),
+        (J::String(s), OutputType::Integer) => Value::Integer(s.trim().parse().ok()?),
+        (J::String(s), OutputType::Text) => Value::Text(s.clone()),
+        (other, OutputType::Text) => Value::Text(other.to_string()),
+        _ => return None,
+    })
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Add any declared output columns missing from `external_features`.
+    /// Returns the columns added.
+    pub fn ensure_external_columns(
+        &self,
+        analyzers: &[ExternalAnalyzerConfig],
+    ) -> crate::db::Result<Vec<String>> {
+        let existing = self.external_columns()?;
+        let mut added = Vec::new();
+        for o in analyzers.iter().flat_map(|a| &a.outputs) {
+            let column = o.column();
+            if !existing.contains(&column) && !added.contains(&column) {
+                self.conn.execute(
+                    &format!(
+                        "ALTER TABLE external_features ADD COLUMN {column} {}",
+                        o.kind.sql()
+                    ),
+                    [],
+                )?;
+                added.push(column);
+            }
+        }
+        Ok(added)
+    }
+
+    /// Columns of `external_features`, excluding the key.
+    pub fn external_columns(&self) -> crate::db::Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name FROM pragma_table_info('external_features')")?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter(|name| !matches!(name.as_deref(), Ok("track_id")))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Tracks an analyzer hasn't run on yet, as `(id, file_path)`. Failed runs
+    /// are retried only with `retry_failed`; `force` takes every track.
+    pub fn external_pending(
+        &self,
+        analyzer: &str,
+        force: bool,
+        retry_failed: bool,
+    ) -> crate::db::Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.file_path FROM tracks t
+             WHERE ?2 OR NOT EXISTS (
+                 SELECT 1 FROM external_runs r
+                 WHERE r.track_id = t.id AND r.analyzer = ?1
+                   AND (r.error IS NULL OR NOT ?3))
+             ORDER BY t.file_path",
+        )?;
+        let rows = stmt
+            .query_map(rusqlite::params![analyzer, force, retry_failed], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Store one track's outputs and mark the run done. Column names must have
+    /// passed `validate`.
+    pub fn store_external(
+        &self,
+        track_id: i64,
+        analyzer: &str,
+        values: &[(String, Value)],
+    ) -> crate::db::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO external_features (track_id) VALUES (?1)",
+            [track_id],
+        )?;
+        if !values.is_empty() {
+            let sets: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(i, (column, _))| format!("{column} = ?{}", i + 2))
+                .collect();
+            let mut params: Vec<&dyn rusqlite::ToSql> = vec![&track_id];
+            params.extend(values.iter().map(|(_, v)| v as &dyn rusqlite::ToSql));
+            tx.execute(
+                &format!(
+                    "UPDATE external_features SET {} WHERE track_id = ?1",
+                    sets.join(", ")
+                ),
+                params.as_slice(),
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO external_runs (track_id, analyzer, ran_at, error)
+             VALUES (?1, ?2, datetime('now'), NULL)",
+            rusqlite::params![track_id, analyzer],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Record a failed run, so it isn't retried on every invocation.
+    pub fn record_external_failure(
+        &self,
+        track_id: i64,
+        analyzer: &str,
+        error: &str,
+    ) -> crate::db::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO external_runs (track_id, analyzer, ran_at, error)
+             VALUES (?1, ?2, datetime('now'), ?3)",
+            rusqlite::params![track_id, analyzer, error],
+        )?;
+        Ok(())
+    }
+
+    /// Per-analyzer run counts: (analyzer, succeeded, failed).
+    pub fn external_run_counts(&self) -> crate::db::Result<Vec<(String, usize, usize)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT analyzer, SUM(error IS NULL), SUM(error IS NOT NULL)
+             FROM external_runs GROUP BY analyzer ORDER BY analyzer",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get::<_, i64>(1)? as usize,
+                    row.get::<_, i64>(2)? as usize,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyzer(toml_src: &str) -> ExternalAnalyzerConfig {
+        toml::from_str(toml_src).unwrap()
+    }
+
+    fn mood() -> ExternalAnalyzerConfig {
+        analyzer(
+            r#"
+            name = "mood"
+            command = ["sh", "-c", "cat >/dev/null; echo loading model; echo '{\"mood\": {\"happy\": 0.7}, \"bpm\": 121.6, \"label\": \"jam\"}'"]
+            outputs = [
+                { key = "mood.happy" },
+                { key = "bpm", column = "ext_bpm", type = "integer" },
+                { key = "label", type = "text" },
+                { key = "missing" },
+            ]
+            "#,
+        )
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[mood()]).is_ok());
+        let clash = analyzer(
+            r#"
+            name = "other"
+            command = ["true"]
+            outputs = [{ key = "mood.happy" }]
+            "#,
+        );
+        assert!(validate(&[mood(), clash]).is_err());
+        let bad_column = analyzer(
+            r#"
+            name = "x"
+            command = ["true"]
+            outputs = [{ key = "a", column = "a; DROP TABLE tracks" }]
+            "#,
+        );
+        assert!(validate(&[bad_column]).is_err());
+        assert!(
+            toml::from_str::<ExternalAnalyzerConfig>(
+                "name = \"x\"\ncommand = [\"true\"]\noutputs = []\ncmd = 1"
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let a = mood();
+        let values = parse_output(&a, "{\"mood\": {\"happy\": 1}, \"bpm\": \"98\"}").unwrap();
+        assert_eq!(
+            values,
+            vec![
+                ("mood_happy".to_string(), Value::Real(1.0)),
+                ("ext_bpm".to_string(), Value::Integer(98)),
+                ("label".to_string(), Value::Null),
+                ("missing".to_string(), Value::Null),
+            ]
+        );
+        assert!(parse_output(&a, "{\"bpm\": [1, 2]}").is_err());
+        assert!(parse_output(&a, "not json").is_err());
+        assert!(parse_output(&a, "[1]").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_and_store() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format)
+                 VALUES (1, '/m/t01.flac', 1, '0', 'flac'), (2, '/m/t02.flac', 1, '0', 'flac')",
+                [],
+            )
+            .unwrap();
+        let a = mood();
+        assert_eq!(
+            db.ensure_external_columns(std::slice::from_ref(&a))
+                .unwrap()
+                .len(),
+            4
+        );
+        assert!(
+            db.ensure_external_columns(std::slice::from_ref(&a))
+                .unwrap()
+                .is_empty()
+        );
+
+        let values = run(&a, 1, "/m/t01.flac").unwrap();
+        db.store_external(1, "mood", &values).unwrap();
+        let (happy, bpm, label): (f64, i64, String) = db
+            .conn
+            .query_row(
+                "SELECT mood_happy, ext_bpm, label FROM external_features WHERE track_id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((happy, bpm, label.as_str()), (0.7, 122, "jam"));
+
+        let failing = analyzer(
+            r#"
+            name = "broken"
+            command = ["sh", "-c", "echo oops >&2; exit 3"]
+            outputs = [{ key = "x" }]
+            "#,
+        );
+        let err = run(&failing, 2, "/m/t02.flac").unwrap_err();
+        assert!(err.to_string().contains("oops"), "{err}");
+        db.record_external_failure(2, "mood", &err.to_string())
+            .unwrap();
+
+        // Done and failed tracks are skipped; failures come back on request
+        assert!(
+            db.external_pending("mood", false, false)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(db.external_pending("mood", false, true).unwrap().len(), 1);
+        assert_eq!(db.external_pending("mood", true, false).unwrap().len(), 2);
+        assert_eq!(
+            db.external_run_counts().unwrap(),
+            vec![("mood".to_string(), 1, 1)]
+        );
+
+        let slow = analyzer(
+            r#"
+            name = "slow"
+            command = ["sleep", "5"]
+            timeout_secs = 1
+            outputs = [{ key = "x" }]
+            "#,
+        );
+        assert!(matches!(
+            run(&slow, 1, "/m/t01.flac"),
+            Err(ExternalError::Timeout { .. })
+        ));
+    }
+}
@@ -0,0 +1,315 @@
+//! Feature distribution audit: find degenerate analysis columns.
+//!
+//! Some extractors produce values that look fine per-track but carry no
+//! information across the library — tempo piling up at 190 BPM, harmonic
+//! complexity pinned at 0.99. Scores built on those inputs are ranking noise.
+//! This computes per-column distribution stats and flags the junk.
+
+use crate::db::Database;
+use crate::db::columns::{ANALYSIS_SCHEMA, LIVE_ONLY, NOT_GARBAGE};
+
+/// Histogram resolution used for the modality / concentration checks.
+const BINS: usize = 20;
+
+/// A single exact value holding at least this share of rows is a spike.
+const SPIKE_SHARE: f64 = 0.5;
+
+/// One histogram bin (5% of the range) holding this share is concentrated.
+const CONCENTRATED_SHARE: f64 = 0.8;
+
+/// REAL columns with fewer distinct values than this are effectively categorical.
+const MIN_DISTINCT: usize = 10;
+
+/// Columns NULL for at least this share of analyzed tracks are sparse.
+const SPARSE_SHARE: f64 = 0.9;
+
+/// A bin counts as a mode if it is a local maximum holding this share of rows.
+const MODE_MIN_SHARE: f64 = 0.05;
+
+/// Why a column looks degenerate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degeneracy {
+    /// No non-NULL values at all.
+    Empty,
+    /// Almost always NULL.
+    Sparse,
+    /// Every value is identical.
+    Constant,
+    /// A REAL column with only a handful of distinct values.
+    LowCardinality,
+    /// One exact value dominates (e.g. tempo clustering at 190).
+    Spike,
+    /// Nearly everything falls in one narrow slice of the range (e.g. pinned at 0.99).
+    Concentrated,
+}
+
+impl Degeneracy {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Degeneracy::Empty => "empty",
+            Degeneracy::Sparse => "sparse",
+            Degeneracy::Constant => "constant",
+            Degeneracy::LowCardinality => "low-cardinality",
+            Degeneracy::Spike => "spike",
+            Degeneracy::Concentrated => "concentrated",
+        }
+    }
+}
+
+/// Distribution summary for one analysis column.
+#[derive(Debug, Clone)]
+pub struct FeatureStats {
+    pub name: &'static str,
+    pub category: &'static str,
+    /// Non-NULL values.
+    pub count: usize,
+    /// Rows considered, NULL or not.
+    pub total: usize,
+    pub distinct: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub variance: f64,
+    /// Most common exact value and its share of non-NULL rows.
+    pub top_value: f64,
+    pub top_share: f64,
+    /// Share of non-NULL rows in the fullest histogram bin.
+    pub peak_bin_share: f64,
+    /// Number of histogram peaks (1 = unimodal).
+    pub modes: usize,
+    pub flags: Vec<Degeneracy>,
+}
+
+impl FeatureStats {
+    pub fn is_degenerate(&self) -> bool {
+        !self.flags.is_empty()
+    }
+}
+
+/// Compute distribution stats for a column's non-NULL values out of `total` rows.
+pub fn compute_stats(
+    name: &'static str,
+    category: &'static str,
+    is_real: bool,
+    values: &[f64],
+    total: usize,
+) -> FeatureStats {
+    let mut stats = FeatureStats {
+        name,
+        category,
+        count: values.len(),
+        total,
+        distinct: 0,
+        min: 0.0,
+        max: 0.0,
+        mean: 0.0,
+        variance: 0.0,
+        top_value: 0.0,
+        top_share: 0.0,
+        peak_bin_share: 0.0,
+        modes: 0,
+        flags: Vec::new(),
+    };
+    if values.is_empty() {
+        stats.flags.push(Degeneracy::Empty);
+        return stats;
+    }
+
+    let n = values.len() as f64;
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    stats.min = sorted[0];
+    stats.max = sorted[sorted.len() - 1];
+    stats.mean = sorted.iter().sum::<f64>() / n;
+    stats.variance = sorted.iter().map(|v| (v - stats.mean).powi(2)).sum::<f64>() / n;
+
+    // Runs of equal values in sorted order give distinct count and the mode
+    let mut run_start = 0;
+    for i in 1..=sorted.len() {
+        if i == sorted.len() || sorted[i] != sorted[run_start] {
+            stats.distinct += 1;
+            let share = (i - run_start) as f64 / n;
+            if share > stats.top_share {
+                stats.top_share = share;
+                stats.top_value = sorted[run_start];
+            }
+            run_start = i;
+        }
+    }
+
+    let range = stats.max - stats.min;
+    if range > 0.0 {
+        let mut bins = [0usize; BINS];
+        for v in &sorted {
+            let b = (((v - stats.min) / range) * BINS as f64) as usize;
+            bins[b.min(BINS - 1)] += 1;
+        }
+        stats.peak_bin_share = *bins.iter().max().unwrap_or(&0) as f64 / n;
+        stats.modes = (0..BINS)
+            .filter(|&i| {
+                let c = bins[i];
+                let left = if i > 0 { bins[i - 1] } else { 0 };
+                let right = if i + 1 < BINS { bins[i + 1] } else { 0 };
+                c as f64 / n >= MODE_MIN_SHARE && c > left && c >= right
+            })
+            .count();
+    } else {
+        stats.peak_bin_share = 1.0;
+        stats.modes = 1;
+    }
+
+    if total > 0 && 1.0 - n / total as f64 >= SPARSE_SHARE {
+        stats.flags.push(Degeneracy::Sparse);
+    }
+    if stats.distinct == 1 {
+        stats.flags.push(Degeneracy::Constant);
+    } else {
+        if is_real && stats.distinct < MIN_DISTINCT {
+            stats.flags.push(Degeneracy::LowCardinality);
+        }
+        if stats.top_share >= SPIKE_SHARE {
+            stats.flags.push(Degeneracy::Spike);
+        }
+        if stats.peak_bin_share >= CONCENTRATED_SHARE {
+            stats.flags.push(Degeneracy::Concentrated);
+        }
+    }
+    stats
+}
+
+/// Audit every numeric analysis column, in schema order.
+pub fn audit_features(
+    db: &Database,
+    live_only: bool,
+    category: Option<&str>,
+) -> crate::db::Result<Vec<FeatureStats>> {
+    let total = db.count_audit_rows(live_only)?;
+    let category = category.map(|c| c.to_lowercase());
+    let mut out = Vec::new();
+    for col in ANALYSIS_SCHEMA {
+        if col.sql_type != "REAL" && col.sql_type != "INT" {
+            continue;
+        }
+        if let Some(ref cat) = category {
+            if !col.category.to_lowercase().contains(cat) {
+                continue;
+            }
+        }
+        let values = db.get_feature_values(col.name, live_only)?;
+        out.push(compute_stats(
+            col.name,
+            col.category,
+            col.sql_type == "REAL",
+            &values,
+            total,
+        ));
+    }
+    Ok(out)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    fn audit_where(live_only: bool) -> String {
+        let mut clause = format!("a.energy_score IS NOT NULL AND {NOT_GARBAGE}");
+        if live_only {
+            clause += &format!(" AND {LIVE_ONLY}");
+        }
+        clause
+    }
+
+    /// Analyzed, non-garbage tracks considered by the audit.
+    fn count_audit_rows(&self, live_only: bool) -> crate::db::Result<usize> {
+        let n: i64 = self.conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM analysis_results a
+                 JOIN tracks t ON t.id = a.track_id
+                 WHERE {}",
+                Self::audit_where(live_only)
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(n as usize)
+    }
+
+    /// Non-NULL values of one analysis column. `column` must come from `ANALYSIS_SCHEMA`.
+    fn get_feature_values(&self, column: &str, live_only: bool) -> crate::db::Result<Vec<f64>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT a.{column} FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {} AND a.{column} IS NOT NULL",
+            Self::audit_where(live_only)
+        ))?;
+        let values = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spread(n: usize) -> Vec<f64> {
+        (0..n).map(|i| i as f64 / n as f64).collect()
+    }
+
+    #[test]
+    fn test_healthy_feature_has_no_flags() {
+        let s = compute_stats("x", "Test", true, &spread(200), 200);
+        assert!(!s.is_degenerate(), "{:?}", s.flags);
+        assert_eq!(s.distinct, 200);
+        assert!((s.mean - 0.4975).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_spike_and_concentration() {
+        // Tempo clustering: most tracks report exactly 190
+        let mut tempo: Vec<f64> = vec![190.0; 150];
+        tempo.extend((0..50).map(|i| 60.0 + i as f64 * 2.0));
+        let s = compute_stats("tempo_bpm", "Rhythm", true, &tempo, 200);
+        assert!(s.flags.contains(&Degeneracy::Spike));
+        assert_eq!(s.top_value, 190.0);
+
+        // Pinned near the top: lots of distinct values, all within 0.98-0.99
+        let mut pinned: Vec<f64> = (0..190).map(|i| 0.98 + i as f64 * 0.00005).collect();
+        pinned.extend([0.1, 0.3, 0.5, 0.7, 0.2, 0.4, 0.6, 0.8, 0.05, 0.15]);
+        let s = compute_stats("harmonic_complexity", "Harmony", true, &pinned, 200);
+        assert_eq!(s.flags, vec![Degeneracy::Concentrated]);
+    }
+
+    #[test]
+    fn test_empty_constant_sparse_and_low_cardinality() {
+        assert_eq!(
+            compute_stats("x", "T", true, &[], 10).flags,
+            vec![Degeneracy::Empty]
+        );
+        assert_eq!(
+            compute_stats("x", "T", true, &[0.5; 100], 100).flags,
+            vec![Degeneracy::Constant]
+        );
+        let s = compute_stats("x", "T", true, &spread(5), 100);
+        assert!(s.flags.contains(&Degeneracy::Sparse));
+        assert!(s.flags.contains(&Degeneracy::LowCardinality));
+        // Small integer ranges (keys, counts) are fine for INT columns
+        let ints: Vec<f64> = (0..120).map(|i| (i % 6) as f64).collect();
+        assert!(!compute_stats("x", "T", false, &ints, 120).is_degenerate());
+    }
+
+    #[test]
+    fn test_bimodal_modes() {
+        let mut v: Vec<f64> = (0..100).map(|i| i as f64 * 0.001).collect();
+        v.extend((0..100).map(|i| 0.9 + i as f64 * 0.001));
+        assert_eq!(compute_stats("x", "T", true, &v, 200).modes, 2);
+    }
+
+    #[test]
+    fn test_audit_features_on_empty_db() {
+        let db = Database::open_in_memory().unwrap();
+        let stats = audit_features(&db, false, Some("score")).unwrap();
+        assert!(!stats.is_empty());
+        assert!(stats.iter().all(|s| s.flags == vec![Degeneracy::Empty]));
+    }
+}
@@ -0,0 +1,145 @@
+//! The similarity feature vector: which `analysis_results` columns it holds,
+//! in what order, and a version number stored with everything derived from it.
+//!
+//! Neighbor lists (`track_similarity`) and sound profile centroids are computed
+//! from these vectors and kept in the database. Adding, removing, or reordering
+//! a feature changes the vector's meaning, so stored data built from an older
+//! layout is compared against [`VectorFormat::CURRENT`]: `similarity
+//! --changed-only` rebuilds everything instead of mixing layouts, `similar`
+//! ranks on the fly instead of reading outdated neighbors, and `profile match`
+//! asks for the profile to be recreated.
+//!
+//! **Changing [`FEATURES`] means bumping [`VERSION`]** (the fingerprint test
+//! fails until you do).
+
+use crate::db::Database;
+
+/// Layout version of [`FEATURES`]. Bump on any change to the list.
+pub const VERSION: i64 = 1;
+
+/// `analysis_results` columns forming the similarity vector, in vector order:
+/// MFCCs (26), spectral (10), sub-band energy (8), ZCR (2), tempo (1).
+pub const FEATURES: &[&str] = &[
+    "mfcc_0_mean",
+    "mfcc_0_std",
+    "mfcc_1_mean",
+    "mfcc_1_std",
+    "mfcc_2_mean",
+    "mfcc_2_std",
+    "mfcc_3_mean",
+    "mfcc_3_std",
+    "mfcc_4_mean",
+    "mfcc_4_std",
+    "mfcc_5_mean",
+    "mfcc_5_std",
+    "mfcc_6_mean",
+    "mfcc_6_std",
+    "mfcc_7_mean",
+    "mfcc_7_std",
+    "mfcc_8_mean",
+    "mfcc_8_std",
+    "mfcc_9_mean",
+    "mfcc_9_std",
+    "mfcc_10_mean",
+    "mfcc_10_std",
+    "mfcc_11_mean",
+    "mfcc_11_std",
+    "mfcc_12_mean",
+    "mfcc_12_std",
+    "spectral_centroid_mean",
+    "spectral_centroid_std",
+    "spectral_flux_mean",
+    "spectral_flux_std",
+    "spectral_flatness_mean",
+    "spectral_flatness_std",
+    "spectral_bandwidth_mean",
+    "spectral_bandwidth_std",
+    "spectral_rolloff_mean",
+    "spectral_rolloff_std",
+    "sub_band_bass_mean",
+    "sub_band_bass_std",
+    "sub_band_mid_mean",
+    "sub_band_mid_std",
+    "sub_band_high_mean",
+    "sub_band_high_std",
+    "sub_band_presence_mean",
+    "sub_band_presence_std",
+    "zcr_mean",
+    "zcr_std",
+    "tempo_bpm",
+];
+
+/// The layout a stored vector (or data derived from vectors) was built with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VectorFormat {
+    pub version: i64,
+    /// Number of dimensions.
+    pub dim: usize,
+}
+
+impl VectorFormat {
+    /// The layout `get_feature_vectors` produces.
+    pub const CURRENT: VectorFormat = VectorFormat {
+        version: VERSION,
+        dim: FEATURES.len(),
+    };
+
+    pub fn is_current(&self) -> bool {
+        *self == Self::CURRENT
+    }
+}
+
+impl std::fmt::Display for VectorFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{} ({}-dim)", self.version, self.dim)
+    }
+}
+
+/// FNV-1a hash of the feature names in order, pinned per version in tests so a
+/// changed list can't ship under an old version number.
+pub fn fingerprint() -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for name in FEATURES {
+        for b in name.bytes().chain(std::iter::once(0)) {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Layout the stored neighbor lists were computed with; `None` if
+    /// similarity has never been computed.
+    pub fn similarity_format(&self) -> crate::db::Result<Option<VectorFormat>> {
+        use rusqlite::OptionalExtension;
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT version, dim FROM similarity_format WHERE id = 1",
+                [],
+                |row| {
+                    Ok(VectorFormat {
+                        version: row.get(0)?,
+                        dim: row.get::<_, i64>(1)? as usize,
+                    })
+                },
+            )
+            .optional()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_list_matches_version() {
+        // If this fails you changed FEATURES: bump VERSION and pin the new
+        // fingerprint here, so stored neighbors and profiles get rebuilt.
+        assert_eq!((VERSION, fingerprint()), (1, 0xbc01_ce98_3365_98b6));
+        assert_eq!(VectorFormat::CURRENT.dim, 47);
+    }
+}
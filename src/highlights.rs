@@ -0,0 +1,607 @@
+//! Highlight reels: cut a show's most intense moments into short excerpts.
+//!
+//! Each track's stored tension profile (`track_tension_points`) is scanned for
+//! peaks. A peak's excerpt covers the build arc that leads into it — walking back
+//! while tension keeps falling — plus a short payoff after the peak, clamped to a
+//! 60–90s window. Excerpts are ranked by peak tension plus the height of the
+//! climb, the top N (non-overlapping) are kept, and ffmpeg cuts each one to a
+//! separate file in show order.
+//!
+//! A snippet is a single clip of one track, for sharing a moment without the
+//! whole jam: it's placed around the track's loudest stretch
+//! (`peak_energy_time`), its tension peak, or a given time, with most of the
+//! clip spent on the build into it.
+
+use crate::db::Database;
+use crate::db::columns::NOT_GARBAGE;
+use rusqlite::{OptionalExtension, params};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+
+/// Seconds of music kept after the tension peak (the "payoff").
+const PEAK_TAIL_SECS: f64 = 15.0;
+
+/// Fade in/out length applied to each cut, in seconds.
+const FADE_SECS: f64 = 1.5;
+
+/// Share of a snippet before its anchor, so the clip leads into the moment.
+const SNIPPET_LEAD: f64 = 2.0 / 3.0;
+
+#[derive(Error, Debug)]
+pub enum HighlightError {
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ffmpeg not found — required to cut excerpts")]
+    FfmpegNotFound,
+    #[error("ffmpeg failed on {path}: {stderr}")]
+    Ffmpeg { path: String, stderr: String },
+}
+
+/// Excerpt window limits, in seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct ExcerptLength {
+    pub min_secs: f64,
+    pub max_secs: f64,
+}
+
+impl Default for ExcerptLength {
+    fn default() -> Self {
+        Self {
+            min_secs: 60.0,
+            max_secs: 90.0,
+        }
+    }
+}
+
+/// A show track with its stored tension profile.
+#[derive(Debug, Clone)]
+pub struct TensionTrack {
+    pub track_id: i64,
+    pub title: String,
+    pub file_path: String,
+    pub duration_secs: f64,
+    /// (time, tension) points, ordered by time.
+    pub points: Vec<(f64, f64)>,
+}
+
+/// One excerpt of a highlight reel.
+#[derive(Debug, Clone)]
+pub struct Excerpt {
+    pub track_id: i64,
+    pub title: String,
+    pub file_path: String,
+    /// Position of the track in the show (0-based), for ordering the reel.
+    pub track_index: usize,
+    pub start: f64,
+    pub duration: f64,
+    pub peak_time: f64,
+    /// Ranking score: peak tension plus the height of the build into it.
+    pub intensity: f64,
+}
+
+/// Where a snippet is placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnippetAnchor {
+    /// The loudest stretch of the track (`peak_energy_time`).
+    Peak,
+    /// The highest point of the stored tension profile.
+    Tension,
+    /// A position in seconds.
+    At(f64),
+}
+
+impl SnippetAnchor {
+    /// `peak`, `tension`, or a position as `12:34`, `1:02:03` or seconds.
+    pub fn parse(value: &str) -> Option<Self> {
+        let v = value.trim().to_lowercase();
+        match v.as_str() {
+            "peak" => return Some(Self::Peak),
+            "tension" => return Some(Self::Tension),
+            _ => {}
+        }
+        let mut secs = 0.0;
+        for part in v.split(':') {
+            let n: f64 = part.parse().ok()?;
+            if !n.is_finite() || n < 0.0 {
+                return None;
+            }
+            secs = secs * 60.0 + n;
+        }
+        Some(Self::At(secs))
+    }
+}
+
+/// A track to cut a snippet from, with the peaks stored for it.
+#[derive(Debug, Clone)]
+pub struct SnippetSource {
+    pub track_id: i64,
+    pub title: String,
+    pub date: String,
+    pub file_path: String,
+    pub duration_secs: f64,
+    /// Normalized (0-1) position of the loudest stretch.
+    pub peak_energy_time: Option<f64>,
+    /// Analyzed from excerpts, so `peak_energy_time` isn't in track time.
+    pub preview: bool,
+    /// (time, tension) points, ordered by time.
+    pub points: Vec<(f64, f64)>,
+}
+
+impl SnippetSource {
+    /// Seconds into the track the anchor points at, or why it can't be placed.
+    pub fn anchor_secs(&self, anchor: SnippetAnchor) -> Result<f64, &'static str> {
+        match anchor {
+            SnippetAnchor::Peak if self.preview => {
+                Err("it was analyzed as a preview; re-analyze it in full or give --at a time")
+            }
+            SnippetAnchor::Peak => self
+                .peak_energy_time
+                .map(|p| p * self.duration_secs)
+                .ok_or("no energy peak is stored; re-analyze it or give --at a time"),
+            SnippetAnchor::Tension => self
+                .points
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|p| p.0)
+                .ok_or("no tension profile is stored; re-analyze it in full or use --at peak"),
+            SnippetAnchor::At(secs) if secs > self.duration_secs => {
+                Err("the time is past the end of the track")
+            }
+            SnippetAnchor::At(secs) => Ok(secs),
+        }
+    }
+}
+
+/// `(start, duration)` of a `secs`-long snippet leading into `anchor`, kept
+/// inside the track (and no longer than it).
+pub fn snippet_window(anchor: f64, secs: f64, track_len: f64) -> (f64, f64) {
+    let duration = secs.min(track_len);
+    let start = (anchor - duration * SNIPPET_LEAD).clamp(0.0, track_len - duration);
+    (start, duration)
+}
+
+/// Pick the `count` most intense excerpts of a show, returned in show order.
+pub fn find_highlights(
+    db: &Database,
+    date: &str,
+    count: usize,
+    length: ExcerptLength,
+) -> Result<Vec<Excerpt>, HighlightError> {
+    let tracks = db.get_show_tension(date)?;
+    Ok(select_excerpts(&tracks, count, length))
+}
+
+/// Rank every tension peak across `tracks` and keep the top `count` that don't overlap.
+pub fn select_excerpts(
+    tracks: &[TensionTrack],
+    count: usize,
+    length: ExcerptLength,
+) -> Vec<Excerpt> {
+    let mut candidates: Vec<Excerpt> = tracks
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, track)| peak_excerpts(idx, track, length))
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.intensity
+            .partial_cmp(&a.intensity)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut chosen: Vec<Excerpt> = Vec::with_capacity(count);
+    for c in candidates {
+        if chosen.len() >= count {
+            break;
+        }
+        let overlaps = chosen.iter().any(|e| {
+            e.track_id == c.track_id
+                && c.start < e.start + e.duration
+                && e.start < c.start + c.duration
+        });
+        if !overlaps {
+            chosen.push(c);
+        }
+    }
+
+    chosen.sort_by(|a, b| {
+        a.track_index.cmp(&b.track_index).then(
+            a.start
+                .partial_cmp(&b.start)
+                .unwrap_or(std::cmp::Ordering::Equal),
+        )
+    });
+    chosen
+}
+
+/// Candidate excerpts for every local tension maximum in one track.
+fn peak_excerpts(track_index: usize, track: &TensionTrack, length: ExcerptLength) -> Vec<Excerpt> {
+    let pts = &track.points;
+    // Tracks shorter than the minimum window are never excerpted
+    if pts.len() < 3 || track.duration_secs < length.min_secs {
+        return Vec::new();
+    }
+
+    let mut out = Vec::new();
+    for i in 1..pts.len() - 1 {
+        let (peak_time, peak) = pts[i];
+        if peak < pts[i - 1].1 || peak <= pts[i + 1].1 {
+            continue;
+        }
+
+        // Walk back along the build arc while tension keeps falling
+        let mut j = i;
+        while j > 0 && pts[j - 1].1 <= pts[j].1 {
+            j -= 1;
+        }
+        let (build_start, base) = pts[j];
+
+        let (start, duration) = clamp_window(
+            build_start,
+            peak_time + PEAK_TAIL_SECS,
+            track.duration_secs,
+            length,
+        );
+        out.push(Excerpt {
+            track_id: track.track_id,
+            title: track.title.clone(),
+            file_path: track.file_path.clone(),
+            track_index,
+            start,
+            duration,
+            peak_time,
+            intensity: peak + (peak - base),
+        });
+    }
+    out
+}
+
+/// Fit `[start, end)` into the excerpt length limits without leaving the track.
+/// Over-long builds are trimmed from the front so the peak is always kept.
+fn clamp_window(start: f64, end: f64, track_len: f64, length: ExcerptLength) -> (f64, f64) {
+    let end = end.min(track_len);
+    let mut start = start.max(end - length.max_secs).max(0.0);
+    let mut end = end;
+    if end - start < length.min_secs {
+        // Too short: extend back first (more build), then forward
+        start = (end - length.min_secs).max(0.0);
+        end = (start + length.min_secs).min(track_len);
+    }
+    (start, end - start)
+}
+
+/// Output filename for an excerpt: `01 - Title (12m34s).flac`.
+pub fn excerpt_filename(position: usize, excerpt: &Excerpt, extension: &str) -> String {
+    let secs = excerpt.start.round() as i64;
+    format!(
+        "{:02} - {} ({}m{:02}s).{}",
+        position,
+        file_safe(&excerpt.title),
+        secs / 60,
+        secs % 60,
+        extension
+    )
+}
+
+/// Default filename for a snippet: `Title 1977-05-08 (12m34s).ogg`.
+pub fn snippet_filename(source: &SnippetSource, start: f64, extension: &str) -> String {
+    let secs = start.round() as i64;
+    format!(
+        "{} {} ({}m{:02}s).{}",
+        file_safe(&source.title),
+        source.date,
+        secs / 60,
+        secs % 60,
+        extension
+    )
+}
+
+/// A title with the characters filesystems reject replaced by `_`.
+fn file_safe(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            _ => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Cut each excerpt into `out_dir` with ffmpeg. Returns the written paths.
+pub fn write_excerpts(
+    excerpts: &[Excerpt],
+    out_dir: &Path,
+    extension: &str,
+) -> Result<Vec<PathBuf>, HighlightError> {
+    check_ffmpeg()?;
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut written = Vec::with_capacity(excerpts.len());
+    for (i, e) in excerpts.iter().enumerate() {
+        let out = out_dir.join(excerpt_filename(i + 1, e, extension));
+        cut(&e.file_path, e.start, e.duration, &out)?;
+        written.push(out);
+    }
+    Ok(written)
+}
+
+/// Cut one snippet of `file_path` to `out` with ffmpeg.
+pub fn write_snippet(
+    file_path: &str,
+    start: f64,
+    duration: f64,
+    out: &Path,
+) -> Result<(), HighlightError> {
+    check_ffmpeg()?;
+    if let Some(dir) = out.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    cut(file_path, start, duration, out)
+}
+
+fn check_ffmpeg() -> Result<(), HighlightError> {
+    match Command::new("ffmpeg").arg("-version").output() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(HighlightError::FfmpegNotFound),
+    }
+}
+
+/// Re-encode `[start, start + duration)` of a file with fades at both ends.
+/// ffmpeg decodes from the keyframe before `start` and drops what precedes it
+/// (`-accurate_seek`), so the cut lands on the requested sample, not a packet.
+fn cut(file_path: &str, start: f64, duration: f64, out: &Path) -> Result<(), HighlightError> {
+    let fades = format!(
+        "afade=t=in:d={FADE_SECS},afade=t=out:st={:.3}:d={FADE_SECS}",
+        (duration - FADE_SECS).max(0.0)
+    );
+    let output = Command::new("ffmpeg")
+        .args([
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-accurate_seek",
+            "-ss",
+            &format!("{start:.3}"),
+            "-t",
+            &format!("{duration:.3}"),
+            "-i",
+        ])
+        .arg(crate::paths::to_fs(file_path))
+        .args(["-af", &fades, "-vn", "-y"])
+        .arg(out)
+        .output()?;
+    if !output.status.success() {
+        return Err(HighlightError::Ffmpeg {
+            path: file_path.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(())
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Tension profiles for every analyzed track of a show, in setlist order.
+    pub fn get_show_tension(&self, date: &str) -> crate::db::Result<Vec<TensionTrack>> {
+        let sql = format!(
+            "SELECT t.id, COALESCE(t.parsed_title, t.title, '(untitled)'),
+                    t.file_path, COALESCE(a.duration, 0.0)
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE (t.parsed_date = ?1 OR t.date = ?1)
+               AND {NOT_GARBAGE}
+             ORDER BY COALESCE(t.parsed_disc, t.disc_number, CAST(t.parsed_set AS INTEGER), 1),
+                      COALESCE(t.parsed_track, t.track_number, 999)"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut tracks = stmt
+            .query_map(params![date], |row| {
+                Ok(TensionTrack {
+                    track_id: row.get(0)?,
+                    title: row.get(1)?,
+                    file_path: row.get(2)?,
+                    duration_secs: row.get(3)?,
+                    points: Vec::new(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut points_stmt = self.conn.prepare_cached(
+            "SELECT time, tension FROM track_tension_points
+             WHERE track_id = ?1 ORDER BY time",
+        )?;
+        for track in &mut tracks {
+            track.points = points_stmt
+                .query_map(params![track.track_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+        }
+        Ok(tracks)
+    }
+
+    /// A track's snippet source: its path, length, energy peak and tension profile.
+    pub fn snippet_source(&self, track_id: i64) -> crate::db::Result<Option<SnippetSource>> {
+        let source = self
+            .conn
+            .query_row(
+                "SELECT t.id, COALESCE(t.parsed_title, t.title, '(untitled)'),
+                        COALESCE(t.parsed_date, t.date, '?'), t.file_path,
+                        COALESCE(a.duration, t.duration_secs, 0.0), a.peak_energy_time,
+                        COALESCE(a.preview, 0)
+                 FROM analysis_results a
+                 JOIN tracks t ON t.id = a.track_id
+                 WHERE t.id = ?1",
+                params![track_id],
+                |row| {
+                    Ok(SnippetSource {
+                        track_id: row.get(0)?,
+                        title: row.get(1)?,
+                        date: row.get(2)?,
+                        file_path: row.get(3)?,
+                        duration_secs: row.get(4)?,
+                        peak_energy_time: row.get(5)?,
+                        preview: row.get(6)?,
+                        points: Vec::new(),
+                    })
+                },
+            )
+            .optional()?;
+        let Some(mut source) = source else {
+            return Ok(None);
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT time, tension FROM track_tension_points
+             WHERE track_id = ?1 ORDER BY time",
+        )?;
+        source.points = stmt
+            .query_map(params![track_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Some(source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: i64, duration_secs: f64, points: Vec<(f64, f64)>) -> TensionTrack {
+        TensionTrack {
+            track_id: id,
+            title: format!("Song {id}"),
+            file_path: format!("/music/t{id}.flac"),
+            duration_secs,
+            points,
+        }
+    }
+
+    #[test]
+    fn test_clamp_window() {
+        let len = ExcerptLength::default();
+        // Long build is trimmed from the front, keeping the end
+        assert_eq!(clamp_window(0.0, 300.0, 600.0, len), (210.0, 90.0));
+        // Short window grows backwards to the minimum
+        assert_eq!(clamp_window(100.0, 130.0, 600.0, len), (70.0, 60.0));
+        // ...and forwards when it hits the start of the track
+        assert_eq!(clamp_window(0.0, 20.0, 600.0, len), (0.0, 60.0));
+        // Never runs past the end of the track
+        assert_eq!(clamp_window(500.0, 620.0, 600.0, len), (510.0, 90.0));
+    }
+
+    #[test]
+    fn test_select_excerpts_ranks_and_orders() {
+        let tracks = vec![
+            // Solid build, but lower than track 2's main peak
+            track(1, 400.0, vec![(0.0, 0.1), (100.0, 0.7), (200.0, 0.3)]),
+            // Big build to a high peak, plus a small secondary bump
+            track(
+                2,
+                900.0,
+                vec![
+                    (0.0, 0.1),
+                    (200.0, 0.5),
+                    (300.0, 0.95),
+                    (400.0, 0.4),
+                    (600.0, 0.6),
+                    (700.0, 0.5),
+                ],
+            ),
+        ];
+        let picked = select_excerpts(&tracks, 2, ExcerptLength::default());
+        assert_eq!(picked.len(), 2);
+        // Reel plays in show order even though track 2's peak ranked first
+        assert_eq!(picked[0].track_id, 1);
+        assert_eq!(picked[1].track_id, 2);
+        assert_eq!(picked[1].peak_time, 300.0);
+        assert!(
+            picked
+                .iter()
+                .all(|e| e.duration >= 60.0 && e.duration <= 90.0)
+        );
+    }
+
+    #[test]
+    fn test_select_excerpts_skips_overlaps() {
+        // Two adjacent peaks whose windows overlap: only one survives
+        let tracks = vec![track(
+            1,
+            600.0,
+            vec![
+                (0.0, 0.1),
+                (100.0, 0.9),
+                (110.0, 0.8),
+                (120.0, 0.85),
+                (200.0, 0.1),
+            ],
+        )];
+        let picked = select_excerpts(&tracks, 5, ExcerptLength::default());
+        assert_eq!(picked.len(), 1);
+        assert_eq!(picked[0].peak_time, 100.0);
+    }
+
+    #[test]
+    fn test_snippet_anchor_and_window() {
+        assert_eq!(SnippetAnchor::parse("Peak"), Some(SnippetAnchor::Peak));
+        assert_eq!(
+            SnippetAnchor::parse("12:34"),
+            Some(SnippetAnchor::At(754.0))
+        );
+        assert_eq!(
+            SnippetAnchor::parse("1:02:03.5"),
+            Some(SnippetAnchor::At(3723.5))
+        );
+        assert_eq!(SnippetAnchor::parse("90"), Some(SnippetAnchor::At(90.0)));
+        assert_eq!(SnippetAnchor::parse("climax"), None);
+        assert_eq!(SnippetAnchor::parse("-5"), None);
+
+        let mut source = SnippetSource {
+            track_id: 1,
+            title: "Dark Star".into(),
+            date: "1972-08-27".into(),
+            file_path: "/music/t1.flac".into(),
+            duration_secs: 1800.0,
+            peak_energy_time: Some(0.5),
+            preview: false,
+            points: vec![(300.0, 0.4), (1200.0, 0.9), (1500.0, 0.6)],
+        };
+        assert_eq!(source.anchor_secs(SnippetAnchor::Peak), Ok(900.0));
+        assert_eq!(source.anchor_secs(SnippetAnchor::Tension), Ok(1200.0));
+        assert!(source.anchor_secs(SnippetAnchor::At(2000.0)).is_err());
+        source.preview = true;
+        assert!(source.anchor_secs(SnippetAnchor::Peak).is_err());
+
+        // Two thirds of the clip lead into the moment
+        assert_eq!(snippet_window(900.0, 45.0, 1800.0), (870.0, 45.0));
+        // Kept inside the track at both ends
+        assert_eq!(snippet_window(10.0, 45.0, 1800.0), (0.0, 45.0));
+        assert_eq!(snippet_window(1795.0, 45.0, 1800.0), (1755.0, 45.0));
+        assert_eq!(snippet_window(20.0, 45.0, 30.0), (0.0, 30.0));
+        assert_eq!(
+            snippet_filename(&source, 870.0, "ogg"),
+            "Dark Star 1972-08-27 (14m30s).ogg"
+        );
+    }
+
+    #[test]
+    fn test_excerpt_filename() {
+        let e = Excerpt {
+            track_id: 1,
+            title: "Scarlet Begonias / Fire".into(),
+            file_path: String::new(),
+            track_index: 0,
+            start: 754.4,
+            duration: 90.0,
+            peak_time: 830.0,
+            intensity: 1.0,
+        };
+        assert_eq!(
+            excerpt_filename(3, &e, "flac"),
+            "03 - Scarlet Begonias _ Fire (12m34s).flac"
+        );
+    }
+}
@@ -0,0 +1,463 @@
+//! Event hooks: shell commands run on pipeline events.
+//!
+//! Each `[[hooks]]` entry in the config names an event and a command. After an
+//! `analyze` run the events it produced are fired in order — every track
+//! analyzed, every show it completed, every new standout, then the run itself —
+//! and each matching hook is run once per event with a JSON object on stdin:
+//!
+//! - `track-analyzed`: `{"event", "title", "date", "band", "file_path", "scores"}`
+//! - `show-completed`: `{"event", "band", "date", "tracks": [...]}`, once the
+//!   last unanalyzed track of a band's date is analyzed
+//! - `standout-detected`: a track's fields plus `"standout_on"`, the scores it
+//!   reached the library's top decile on (see `standouts`)
+//! - `analyze-finished`: `{"event", "analyzed", "failed", "standouts"}`
+//!
+//! `{event}` and `{summary}` in a command's arguments are substituted. An
+//! optional `filter` in `query` syntax (`transcendence>=90 and band=gd`) limits
+//! a hook to matching tracks; a show fires if any of its tracks match. A failing
+//! hook is reported and the rest still run.
+//!
+//! Hooks run one at a time and the run waits for each, so a hook sees events in
+//! order. One still running after `HOOK_TIMEOUT` is killed and reported as
+//! failed, so a hung notify script can't stall `analyze`; anything slow should
+//! background itself.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::db::Database;
+use crate::db::columns::{BAND_EXPR, TRACK_SCORE_SELECT, map_track_score};
+use crate::db::models::TrackScore;
+use crate::query::CompiledFilter;
+use crate::scores::SCORES;
+use crate::standouts::Standout;
+
+/// How long a hook may run before it's killed.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Something a hook can run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    TrackAnalyzed,
+    ShowCompleted,
+    StandoutDetected,
+    AnalyzeFinished,
+}
+
+impl Event {
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::TrackAnalyzed => "track-analyzed",
+            Event::ShowCompleted => "show-completed",
+            Event::StandoutDetected => "standout-detected",
+            Event::AnalyzeFinished => "analyze-finished",
+        }
+    }
+}
+
+/// One hook from `[[hooks]]`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HookConfig {
+    pub event: Event,
+    /// Program and arguments; `{event}` and `{summary}` are substituted and the
+    /// payload arrives on stdin as JSON.
+    pub command: Vec<String>,
+    /// Only tracks matching this `query` expression.
+    pub filter: Option<String>,
+}
+
+/// Check commands and filters, so a typo fails at startup instead of after
+/// an overnight run.
+pub fn validate(hooks: &[HookConfig]) -> Result<(), String> {
+    for h in hooks {
+        if h.command.is_empty() {
+            return Err(format!("{}: command is empty", h.event.name()));
+        }
+        if let Some(filter) = &h.filter {
+            crate::query::compile(filter)
+                .map_err(|e| format!("{}: filter '{filter}': {e}", h.event.name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// What an `analyze` run did, for firing its events.
+pub struct AnalyzeRun<'a> {
+    /// Database clock when the run started (`standouts::clock`).
+    pub since: &'a str,
+    pub analyzed: u64,
+    pub failed: u64,
+    pub standouts: &'a [Standout],
+}
+
+/// A hook that failed, for a warning.
+#[derive(Debug)]
+pub struct HookFailure {
+    pub event: Event,
+    pub program: String,
+    pub error: std::io::Error,
+}
+
+impl std::fmt::Display for HookFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hook {}: {}",
+            self.event.name(),
+            self.program,
+            self.error
+        )
+    }
+}
+
+/// Fire the events of an `analyze` run. Returns the hooks that failed.
+pub fn fire_analyze_events(
+    db: &Database,
+    hooks: &[HookConfig],
+    run: &AnalyzeRun,
+) -> crate::db::Result<Vec<HookFailure>> {
+    let mut failures = Vec::new();
+    if hooks.is_empty() {
+        return Ok(failures);
+    }
+    let wants = |event| hooks.iter().any(|h| h.event == event);
+    let tracks = if run.analyzed > 0 && (wants(Event::TrackAnalyzed) || wants(Event::ShowCompleted))
+    {
+        db.hook_tracks_since(run.since)?
+    } else {
+        Vec::new()
+    };
+
+    for hook in hooks {
+        let matching = match &hook.filter {
+            Some(f) => Some(db.hook_filter_ids(f)?),
+            None => None,
+        };
+        let matches = |id: i64| matching.as_ref().is_none_or(|ids| ids.contains(&id));
+        let mut fire = |payload: Value, summary: String| {
+            if let Err(error) =
+                run_hook(&hook.command, hook.event, &payload, &summary, HOOK_TIMEOUT)
+            {
+                failures.push(HookFailure {
+                    event: hook.event,
+                    program: hook.command[0].clone(),
+                    error,
+                });
+            }
+        };
+        match hook.event {
+            Event::TrackAnalyzed => {
+                for (_, t) in tracks.iter().filter(|(id, _)| matches(*id)) {
+                    fire(track_json(hook.event, t), format!("{} {}", t.title, t.date));
+                }
+            }
+            Event::ShowCompleted => {
+                for show in db.completed_shows(&tracks)? {
+                    if !show.iter().any(|(id, _)| matches(*id)) {
+                        continue;
+                    }
+                    let first = &show[0].1;
+                    let band = first.band.as_deref().unwrap_or("");
+                    let tracks: Vec<Value> = show
+                        .iter()
+                        .map(|(_, t)| track_json(hook.event, t))
+                        .collect();
+                    let payload = json!({
+                        "event": hook.event.name(),
+                        "band": band,
+                        "date": first.date,
+                        "tracks": tracks,
+                    });
+                    fire(payload, format!("{band} {} complete", first.date));
+                }
+            }
+            Event::StandoutDetected => {
+                for s in run.standouts {
+                    let id = db.find_track_by_path(&s.track.file_path)?;
+                    if !id.is_some_and(matches) {
+                        continue;
+                    }
+                    let mut payload = track_json(hook.event, &s.track);
+                    payload["standout_on"] = s.scores.iter().map(|m| m.name).collect();
+                    let summary = format!(
+                        "{} {} stands out on {}",
+                        s.track.title, s.track.date, s.scores[0].label
+                    );
+                    fire(payload, summary);
+                }
+            }
+            Event::AnalyzeFinished => {
+                let payload = json!({
+                    "event": hook.event.name(),
+                    "analyzed": run.analyzed,
+                    "failed": run.failed,
+                    "standouts": run.standouts.len(),
+                });
+                fire(
+                    payload,
+                    format!(
+                        "{} analyzed, {} failed, {} standouts",
+                        run.analyzed,
+                        run.failed,
+                        run.standouts.len()
+                    ),
+                );
+            }
+        }
+    }
+    Ok(failures)
+}
+
+/// A track's payload: identity and every score by name.
+fn track_json(event: Event, t: &TrackScore) -> Value {
+    let scores: serde_json::Map<String, Value> = SCORES
+        .iter()
+        .map(|m| (m.name.to_string(), t.score(m.column).into()))
+        .collect();
+    json!({
+        "event": event.name(),
+        "title": t.title,
+        "date": t.date,
+        "band": t.band,
+        "file_path": t.file_path,
+        "scores": scores,
+    })
+}
+
+/// Run one hook with its payload on stdin, killing it after `timeout`.
+fn run_hook(
+    command: &[String],
+    event: Event,
+    payload: &Value,
+    summary: &str,
+    timeout: Duration,
+) -> std::io::Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        return Ok(());
+    };
+    let mut child = Command::new(program)
+        .args(args.iter().map(|a| {
+            a.replace("{event}", event.name())
+                .replace("{summary}", summary)
+        }))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // On its own thread, so a command that never reads a large payload
+        // still times out; one that ignores stdin may exit before reading it
+        let line = format!("{payload}\n");
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(line.as_bytes());
+        });
+    }
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("killed after {}s", timeout.as_secs_f64()),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("exited with {status}")))
+    }
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Tracks analyzed at or after `since`, with their ids, by date and path.
+    fn hook_tracks_since(&self, since: &str) -> crate::db::Result<Vec<(i64, TrackScore)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {TRACK_SCORE_SELECT}, t.id
+             FROM analysis_results a JOIN tracks t ON t.id = a.track_id
+             WHERE a.analyzed_at >= ?1
+             ORDER BY COALESCE(t.parsed_date, t.date), t.file_path"
+        ))?;
+        let tracks = stmt
+            .query_map([since], |row| Ok((row.get(19)?, map_track_score(row)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// Ids of the analyzed tracks a hook's filter matches.
+    fn hook_filter_ids(&self, filter: &str) -> crate::db::Result<HashSet<i64>> {
+        // Filters were checked by `validate` at startup
+        let compiled = crate::query::compile(filter).unwrap_or(CompiledFilter {
+            where_clause: "0".to_string(),
+            params: Vec::new(),
+        });
+        Ok(self
+            .query_filter_ids(&compiled, false)?
+            .into_iter()
+            .collect())
+    }
+
+    /// The id of the track at `file_path`.
+    fn find_track_by_path(&self, file_path: &str) -> crate::db::Result<Option<i64>> {
+        use rusqlite::OptionalExtension;
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id FROM tracks WHERE file_path = ?1",
+                [file_path],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// The band dates among `fresh` tracks with nothing left to analyze, each
+    /// with all of its tracks.
+    fn completed_shows(
+        &self,
+        fresh: &[(i64, TrackScore)],
+    ) -> crate::db::Result<Vec<Vec<(i64, TrackScore)>>> {
+        let shows: BTreeMap<(&str, &str), ()> = fresh
+            .iter()
+            .filter(|(_, t)| t.date != "?")
+            .map(|(_, t)| ((t.band.as_deref().unwrap_or(""), t.date.as_str()), ()))
+            .collect();
+        let mut pending = self.conn.prepare(&format!(
+            "SELECT COUNT(*)
+             FROM tracks t LEFT JOIN analysis_results a ON a.track_id = t.id
+             WHERE {BAND_EXPR} = ?1 AND COALESCE(t.parsed_date, t.date) = ?2
+               AND (a.id IS NULL OR a.stale = 1 OR a.preview = 1)"
+        ))?;
+        let mut tracks = self.conn.prepare(&format!(
+            "SELECT {TRACK_SCORE_SELECT}, t.id
+             FROM analysis_results a JOIN tracks t ON t.id = a.track_id
+             WHERE {BAND_EXPR} = ?1 AND COALESCE(t.parsed_date, t.date) = ?2
+             ORDER BY t.parsed_disc, t.parsed_track, t.file_path"
+        ))?;
+        let mut completed = Vec::new();
+        for (band, date) in shows.into_keys() {
+            let left: i64 = pending.query_row([band, date], |row| row.get(0))?;
+            if left > 0 {
+                continue;
+            }
+            let show = tracks
+                .query_map([band, date], |row| {
+                    Ok((row.get(19)?, map_track_score(row)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            if !show.is_empty() {
+                completed.push(show);
+            }
+        }
+        Ok(completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fire_analyze_events() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format,
+                                     parsed_title, parsed_date, parsed_band)
+                 VALUES (1, '/m/gd77/t01.flac', 1, '0', 'flac', 'Bertha', '1977-05-08', 'Grateful Dead'),
+                        (2, '/m/gd77/t02.flac', 1, '0', 'flac', 'Dark Star', '1977-05-08', 'Grateful Dead'),
+                        (3, '/m/gd78/t01.flac', 1, '0', 'flac', 'Loser', '1978-04-15', 'Grateful Dead'),
+                        (4, '/m/gd78/t02.flac', 1, '0', 'flac', 'Deal', '1978-04-15', 'Grateful Dead');
+                 INSERT INTO analysis_results (track_id, transcendence_score, analyzed_at)
+                 VALUES (1, 40, '2026-01-02 00:00:00'), (2, 95, '2026-01-02 00:00:00'),
+                        (3, 50, '2026-01-02 00:00:00'), (4, 60, '2025-12-01 00:00:00');",
+            )
+            .unwrap();
+        // Track 4 is still to analyze, so 1978-04-15 isn't complete
+        db.conn
+            .execute(
+                "UPDATE analysis_results SET stale = 1 WHERE track_id = 4",
+                [],
+            )
+            .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("setbreak-hooks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("events.jsonl");
+        let _ = std::fs::remove_file(&log);
+        let hook = |event: Event, filter: Option<&str>| HookConfig {
+            event,
+            command: vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                format!("cat >> '{}'", log.display()),
+            ],
+            filter: filter.map(String::from),
+        };
+        let hooks = vec![
+            hook(Event::TrackAnalyzed, Some("transcendence>=90")),
+            hook(Event::ShowCompleted, None),
+            hook(Event::AnalyzeFinished, None),
+        ];
+        assert!(validate(&hooks).is_ok());
+        let run = AnalyzeRun {
+            since: "2026-01-01 00:00:00",
+            analyzed: 3,
+            failed: 0,
+            standouts: &[],
+        };
+        let failures = fire_analyze_events(&db, &hooks, &run).unwrap();
+        assert!(failures.is_empty(), "{failures:?}");
+
+        let events: Vec<Value> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["event"], "track-analyzed");
+        assert_eq!(events[0]["title"], "Dark Star");
+        assert_eq!(events[0]["scores"]["transcendence"], 95.0);
+        assert_eq!(events[1]["event"], "show-completed");
+        assert_eq!(events[1]["date"], "1977-05-08");
+        assert_eq!(events[1]["tracks"].as_array().unwrap().len(), 2);
+        assert_eq!(events[2]["analyzed"], 3);
+
+        let bad = HookConfig {
+            command: vec!["false".to_string()],
+            ..hook(Event::AnalyzeFinished, None)
+        };
+        let failures = fire_analyze_events(&db, &[bad], &run).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert!(validate(&[hook(Event::TrackAnalyzed, Some("groove>>"))]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_hung_hook_is_killed() {
+        let command = ["sleep".to_string(), "10".to_string()];
+        let started = Instant::now();
+        let error = run_hook(
+            &command,
+            Event::AnalyzeFinished,
+            &json!({}),
+            "",
+            Duration::from_millis(200),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
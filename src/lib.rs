@@ -1,16 +1,66 @@
+pub mod albums;
+pub mod analysis_cost;
 pub mod analyzer;
+pub mod api;
+pub mod archive_client;
+pub mod artifacts;
 pub mod bands;
+pub mod beets;
 pub mod calibrate;
 pub mod chains;
 pub mod chroma;
+pub mod cli;
+pub mod compilation;
 pub mod config;
+pub mod credentials;
+pub mod date_check;
+pub mod dates;
 pub mod db;
 pub mod discovery;
+pub mod experiment;
+pub mod external;
+pub mod feature_audit;
+pub mod feature_vector;
+pub mod highlights;
+pub mod hooks;
+pub mod lineup;
+pub mod paths;
+pub mod performances;
+pub mod playlist;
+pub mod profile;
+pub mod progress;
+pub mod prune_suggest;
+pub mod query;
+pub mod queue;
+pub mod ratings;
+pub mod recipes;
+pub mod refresh;
+pub mod remote;
+pub mod research;
+pub mod retrack;
 pub mod scanner;
+pub mod score_deltas;
 pub mod score_lab;
+pub mod scores;
+pub mod scrobbles;
+pub mod segue_match;
 pub mod segues;
 pub mod setlist;
+pub mod show_page;
+pub mod show_sources;
+pub mod show_viz;
+pub mod shows;
 pub mod similarity;
+pub mod snapshots;
+pub mod sql;
+pub mod standouts;
+pub mod table;
+pub mod title_aliases;
+pub mod title_normalize;
+pub mod titles;
+pub mod track_detail;
+pub mod track_groups;
+pub mod virtual_tracks;
 
 /// Audio file extensions we support
 pub const SUPPORTED_EXTENSIONS: &[&str] = &[
@@ -0,0 +1,510 @@
+//! Show lineups: who played, and above all who sat in.
+//!
+//! Guest sit-ins are the shows people hunt for, so each show (date and band)
+//! can carry a lineup in `show_lineup`: musicians with an optional role, each
+//! marked as a guest or a regular member. Entries come from two places:
+//!
+//! - archive.org item descriptions and notes (`setlist --notes`), scanned for
+//!   guest mentions: "featuring X", "special guest X", "w/ X", and "with X"
+//!   when a role follows ("with Branford Marsalis on sax") or the line talks
+//!   about a guest. Plain "with" is too common in taping notes to trust alone.
+//! - `lineup add`, which always wins over what the notes say.
+//!
+//! `top --with-guest NAME` keeps tracks from shows a matching guest sat in on.
+
+use rusqlite::params;
+use std::collections::HashMap;
+
+use crate::db::Database;
+use crate::db::columns::BAND_EXPR;
+
+/// `show_lineup.source` for entries found in show notes.
+pub const SOURCE_NOTES: &str = "notes";
+/// `show_lineup.source` for entries added by hand.
+pub const SOURCE_MANUAL: &str = "manual";
+
+/// Phrases that introduce guests whatever follows them.
+const GUEST_MARKERS: [&str; 8] = [
+    "special guests",
+    "special guest",
+    "featuring",
+    "feat.",
+    "guests:",
+    "guest:",
+    "sitting in:",
+    "w/",
+];
+
+/// Capitalized words that start a sentence or a setlist line, not a name.
+const NOT_NAMES: [&str; 12] = [
+    "The", "Set", "Encore", "Disc", "Band", "Thanks", "Special", "Guest", "Guests", "And", "Also",
+    "Intro",
+];
+
+/// Longest name taken, in words.
+const MAX_NAME_WORDS: usize = 4;
+
+/// One musician in a show's lineup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineupEntry {
+    pub date: String,
+    pub band: String,
+    pub name: String,
+    /// Instrument or part, as given ("sax", "vocals").
+    pub role: Option<String>,
+    /// Sat in, rather than a regular member.
+    pub guest: bool,
+    /// `SOURCE_NOTES` or `SOURCE_MANUAL`.
+    pub source: String,
+}
+
+/// Guests mentioned in show-note text, as (name, role), in order and without
+/// repeats.
+pub fn extract_guests(text: &str) -> Vec<(String, Option<String>)> {
+    let mut found: Vec<(String, Option<String>)> = Vec::new();
+    for line in text.lines() {
+        // ASCII-only lowering keeps byte offsets in step with `line`
+        let lower = line.to_ascii_lowercase();
+        let talks_of_guests = lower.contains("guest") || lower.contains("sit in");
+        let mut from = 0;
+        while from < line.len() {
+            let Some((at, len, trusted)) = next_marker(&lower, from, talks_of_guests) else {
+                break;
+            };
+            from = at + len;
+            for (name, role) in names_after(&line[from..]) {
+                if !(trusted || role.is_some()) {
+                    continue;
+                }
+                if !found.iter().any(|(n, _)| n.eq_ignore_ascii_case(&name)) {
+                    found.push((name, role));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Earliest guest marker at or after `from`: (byte offset, length, whether a
+/// name after it counts without a role).
+fn next_marker(lower: &str, from: usize, talks_of_guests: bool) -> Option<(usize, usize, bool)> {
+    let rest = &lower[from..];
+    let guest = GUEST_MARKERS
+        .iter()
+        .filter_map(|m| rest.find(m).map(|i| (from + i, m.len(), true)));
+    // "with" only as a whole word
+    let with = rest
+        .match_indices("with ")
+        .find(|(i, _)| {
+            *i == 0
+                || !rest[..*i]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_alphanumeric())
+        })
+        .map(|(i, m)| (from + i, m.len(), talks_of_guests));
+    guest
+        .chain(with)
+        .min_by_key(|(at, len, _)| (*at, usize::MAX - len))
+}
+
+/// Names listed at the start of `text` ("A, B and C"), each with the role that
+/// follows it ("on sax", "(sax)", "- sax").
+fn names_after(text: &str) -> Vec<(String, Option<String>)> {
+    let mut out = Vec::new();
+    let mut rest = text.trim_start_matches([':', ' ', '\t']);
+    while let Some((name, after)) = take_name(rest) {
+        let (role, after) = take_role(after);
+        out.push((name, role));
+        let next = after.trim_start();
+        let next = next
+            .strip_prefix(',')
+            .or_else(|| next.strip_prefix('&'))
+            .or_else(|| next.strip_prefix("and "))
+            .map(str::trim_start)
+            .map(|n| n.strip_prefix("and ").unwrap_or(n));
+        match next {
+            Some(n) => rest = n.trim_start(),
+            None => break,
+        }
+    }
+    out
+}
+
+/// Up to `MAX_NAME_WORDS` capitalized words; returns the name and what follows.
+fn take_name(text: &str) -> Option<(String, &str)> {
+    let mut words = Vec::new();
+    let mut end = 0;
+    for (start, word) in word_spans(text) {
+        let word = word.trim_end_matches([',', ';', ')', ':']);
+        if words.len() == MAX_NAME_WORDS || !is_name_word(word) {
+            break;
+        }
+        if words.is_empty() && NOT_NAMES.contains(&word) {
+            return None;
+        }
+        words.push(word);
+        end = start + word.len();
+        if text[end..].starts_with([',', ';', ')', ':']) {
+            break;
+        }
+    }
+    if words.is_empty() {
+        return None;
+    }
+    Some((words.join(" "), &text[end..]))
+}
+
+/// A name word: capitalized letters ("Marsalis", "O'Brien", "Jr.", "J.").
+fn is_name_word(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_uppercase())
+        && word
+            .chars()
+            .all(|c| c.is_alphabetic() || matches!(c, '\'' | '-' | '.'))
+        && word.chars().any(|c| c.is_lowercase() || c == '.')
+}
+
+/// A role right after a name: "on sax", "(sax)" or "- sax".
+fn take_role(text: &str) -> (Option<String>, &str) {
+    let t = text.trim_start();
+    if let Some(inner) = t.strip_prefix('(') {
+        if let Some(close) = inner.find(')') {
+            let role = inner[..close].trim();
+            if !role.is_empty() {
+                return (Some(role.to_string()), &inner[close + 1..]);
+            }
+        }
+    }
+    let Some(after) = t.strip_prefix("on ").or_else(|| t.strip_prefix("- ")) else {
+        return (None, text);
+    };
+    let end = after
+        .find([',', ';', '.', ')', '&', '\n'])
+        .unwrap_or(after.len());
+    let mut role = &after[..end];
+    if let Some(i) = role.find(" and ") {
+        role = &role[..i];
+    }
+    let role = role.trim_end();
+    if role.is_empty() || role.starts_with(|c: char| c.is_uppercase()) {
+        return (None, text);
+    }
+    (Some(role.to_string()), &after[role.len()..])
+}
+
+/// Whitespace-separated words with their byte offsets.
+fn word_spans(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_whitespace()
+        .map(move |w| (w.as_ptr() as usize - text.as_ptr() as usize, w))
+}
+
+/// Replace the lineup entries found in show notes with a fresh scan of every
+/// stored note. Manual entries stay as they are. Returns the entries stored.
+pub fn import_notes(db: &Database) -> crate::db::Result<usize> {
+    let notes = db.lineup_note_texts()?;
+    let tx = db.conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM show_lineup WHERE source = ?1",
+        params![SOURCE_NOTES],
+    )?;
+    let mut stored = 0;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO show_lineup (date, band, name, role, guest, source)
+             VALUES (?1, ?2, ?3, ?4, 1, ?5)",
+        )?;
+        for (date, band, text) in &notes {
+            for (name, role) in extract_guests(text) {
+                stored += stmt.execute(params![date, band, name, role, SOURCE_NOTES])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(stored)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Add or replace a musician in a show's lineup by hand.
+    pub fn add_lineup(
+        &self,
+        date: &str,
+        band: &str,
+        name: &str,
+        role: Option<&str>,
+        guest: bool,
+    ) -> crate::db::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO show_lineup (date, band, name, role, guest, source)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![date, band, name, role, guest, SOURCE_MANUAL],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a musician (name matched ignoring case) from a show's lineup.
+    /// Returns the entries removed.
+    pub fn remove_lineup(
+        &self,
+        date: &str,
+        band: Option<&str>,
+        name: &str,
+    ) -> crate::db::Result<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM show_lineup
+             WHERE date = ?1 AND (?2 IS NULL OR LOWER(band) = LOWER(?2))
+               AND LOWER(name) = LOWER(?3)",
+            params![date, band, name],
+        )?)
+    }
+
+    /// Lineup entries, by date then band, guests after members. `date`
+    /// narrows to one show; `guest` to guests whose name contains it.
+    pub fn lineup(
+        &self,
+        date: Option<&str>,
+        band: Option<&str>,
+        guest: Option<&str>,
+    ) -> crate::db::Result<Vec<LineupEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date, band, name, role, guest, source FROM show_lineup
+             WHERE (?1 IS NULL OR date = ?1)
+               AND (?2 IS NULL OR LOWER(band) = LOWER(?2))
+               AND (?3 IS NULL OR (guest = 1 AND name LIKE '%' || ?3 || '%'))
+             ORDER BY date, band, guest, name",
+        )?;
+        let rows = stmt
+            .query_map(params![date, band, guest], |row| {
+                Ok(LineupEntry {
+                    date: row.get(0)?,
+                    band: row.get(1)?,
+                    name: row.get(2)?,
+                    role: row.get(3)?,
+                    guest: row.get(4)?,
+                    source: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Bands with tracks on a date, for filing a lineup entry under its show.
+    pub fn bands_on_date(&self, date: &str) -> crate::db::Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT DISTINCT {BAND_EXPR} FROM tracks t
+             WHERE COALESCE(t.parsed_date, t.date) = ?1 AND {BAND_EXPR} != ''
+             ORDER BY 1"
+        ))?;
+        let bands = stmt
+            .query_map(params![date], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(bands)
+    }
+
+    /// (date, band, description and notes) of every stored show note whose
+    /// recording is in the library. The band is the one its tracks are filed under.
+    fn lineup_note_texts(&self) -> crate::db::Result<Vec<(String, String, String)>> {
+        // Date and band of each recording directory, by the name notes store
+        let mut dirs: HashMap<String, (Option<String>, String)> = HashMap::new();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT t.file_path, COALESCE(t.parsed_date, t.date), {BAND_EXPR} FROM tracks t
+             WHERE {BAND_EXPR} != ''"
+        ))?;
+        let tracks = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for track in tracks {
+            let (path, date, band) = track?;
+            let Some(dir) = crate::paths::parent_name(&path) else {
+                continue;
+            };
+            let entry = dirs
+                .entry(dir.to_string())
+                .or_insert_with(|| (None, band.clone()));
+            if entry.0.is_none() {
+                entry.0 = date;
+            }
+            if band < entry.1 {
+                entry.1 = band;
+            }
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT local_dir, date,
+                    COALESCE(description, '') || char(10) || COALESCE(notes, '')
+             FROM show_notes WHERE local_dir != '' ORDER BY identifier",
+        )?;
+        let notes = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut rows = Vec::new();
+        for note in notes {
+            let (dir, date, text) = note?;
+            let Some((dir_date, band)) = dirs.get(&dir) else {
+                continue;
+            };
+            if let Some(date) = date.or_else(|| dir_date.clone()) {
+                rows.push((date, band.clone(), text));
+            }
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(text: &str) -> Vec<(String, Option<String>)> {
+        extract_guests(text)
+    }
+
+    fn guest(name: &str, role: Option<&str>) -> (String, Option<String>) {
+        (name.to_string(), role.map(str::to_string))
+    }
+
+    #[test]
+    fn test_extract_guests() {
+        assert_eq!(
+            names("Set 2 featuring Branford Marsalis on saxophone"),
+            vec![guest("Branford Marsalis", Some("saxophone"))]
+        );
+        assert_eq!(
+            names("Bird Song (w/ Branford)\nEyes Of The World"),
+            vec![guest("Branford", None)]
+        );
+        assert_eq!(
+            names("Special guests: Bruce Hornsby (accordion), Clarence Clemons and Carlos Santana"),
+            vec![
+                guest("Bruce Hornsby", Some("accordion")),
+                guest("Clarence Clemons", None),
+                guest("Carlos Santana", None),
+            ]
+        );
+        // "with" needs a role, or a line that talks about guests
+        assert_eq!(
+            names("Encore with Ornette Coleman on alto sax and Bob Weir"),
+            vec![guest("Ornette Coleman", Some("alto sax"))]
+        );
+        assert_eq!(names("Recorded with Nakamichi CM-300 mics"), vec![]);
+        assert_eq!(names("Shared the bill with Jefferson Airplane"), vec![]);
+        assert_eq!(
+            names("Guest sit-in with Ned Lagin"),
+            vec![guest("Ned Lagin", None)]
+        );
+        // Repeats are kept once
+        assert_eq!(
+            names("featuring Branford Marsalis\nw/ Branford Marsalis on sax"),
+            vec![guest("Branford Marsalis", None)]
+        );
+    }
+
+    #[test]
+    fn test_notes_match_their_directory_by_name() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (file_path, file_size, file_modified, format, parsed_date,
+                                     parsed_band, parsed_title)
+                 VALUES ('C:\\m\\gd90_03_29\\t01.flac', 1, '0', 'flac', '1990-03-29',
+                         'Grateful Dead', 'Bird Song'),
+                        ('/m/gd90x03x30/t01.flac', 1, '0', 'flac', '1990-03-30',
+                         'Grateful Dead', 'Bird Song');
+                 INSERT INTO show_notes (identifier, local_dir, description)
+                 VALUES ('a', 'gd90_03_29', 'featuring Branford Marsalis on saxophone'),
+                        ('b', 'gd90_03_30', 'featuring Bruce Hornsby on accordion'),
+                        ('c', '%', 'featuring Rob Wasserman on bass');",
+            )
+            .unwrap();
+        // Backslash paths match; `_` and `%` in a name aren't wildcards
+        let texts = db.lineup_note_texts().unwrap();
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].0, "1990-03-29");
+        assert_eq!(texts[0].1, "Grateful Dead");
+        assert!(texts[0].2.contains("Branford"));
+    }
+
+    #[test]
+    fn test_import_and_manual_lineup() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (file_path, file_size, file_modified, format, parsed_date,
+                                     parsed_band, parsed_title)
+                 VALUES ('/m/gd90-03-29.sbd/t01.flac', 1, '0', 'flac', '1990-03-29',
+                         'Grateful Dead', 'Bird Song');
+                 INSERT INTO show_notes (identifier, local_dir, date, description)
+                 VALUES ('gd90-03-29.sbd', 'gd90-03-29.sbd', '1990-03-29',
+                         'Second set featuring Branford Marsalis on saxophone');",
+            )
+            .unwrap();
+        assert_eq!(import_notes(&db).unwrap(), 1);
+        // Re-importing replaces rather than adds
+        assert_eq!(import_notes(&db).unwrap(), 1);
+
+        db.add_lineup(
+            "1990-03-29",
+            "Grateful Dead",
+            "Jerry Garcia",
+            Some("guitar"),
+            false,
+        )
+        .unwrap();
+        db.add_lineup(
+            "1990-03-29",
+            "Grateful Dead",
+            "Branford Marsalis",
+            Some("sax"),
+            true,
+        )
+        .unwrap();
+        // The manual entry wins over the notes
+        assert_eq!(import_notes(&db).unwrap(), 0);
+        let lineup = db.lineup(Some("1990-03-29"), None, None).unwrap();
+        assert_eq!(lineup.len(), 2);
+        assert!(!lineup[0].guest);
+        assert_eq!(lineup[1].role.as_deref(), Some("sax"));
+        assert_eq!(lineup[1].source, SOURCE_MANUAL);
+
+        let filter = crate::db::models::TopFilter {
+            with_guest: Some("branford"),
+            ..Default::default()
+        };
+        db.conn
+            .execute(
+                "INSERT INTO analysis_results (track_id, groove_score) VALUES (1, 50.0)",
+                [],
+            )
+            .unwrap();
+        assert_eq!(db.query_top("groove_score", 10, &filter).unwrap().len(), 1);
+        let filter = crate::db::models::TopFilter {
+            with_guest: Some("Garcia"),
+            ..Default::default()
+        };
+        assert!(
+            db.query_top("groove_score", 10, &filter)
+                .unwrap()
+                .is_empty()
+        );
+
+        assert_eq!(
+            db.bands_on_date("1990-03-29").unwrap(),
+            vec!["Grateful Dead"]
+        );
+        assert_eq!(
+            db.remove_lineup("1990-03-29", None, "branford marsalis")
+                .unwrap(),
+            1
+        );
+        assert_eq!(db.lineup(None, None, Some("Branford")).unwrap(), vec![]);
+    }
+}
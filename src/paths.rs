@@ -0,0 +1,262 @@
+//! Converting between filesystem paths and the `file_path` strings stored in the DB.
+//!
+//! Stored paths are plain UTF-8 in the platform's own form (`/music/...` or
+//! `C:\Music\...`). Windows verbatim prefixes (`\\?\`) are stripped on the way in
+//! so the same file always gets the same key, and added back on the way out for
+//! paths past the legacy 260-character limit.
+//!
+//! Names that aren't valid UTF-8 (Latin-1 tapes copied off old Linux boxes) are
+//! stored losslessly: each byte that isn't part of valid UTF-8 becomes U+FFFD
+//! followed by the byte in two hex digits, so `Jos\xe9.flac` is stored as
+//! `Jos�E9.flac` and `to_fs` turns it back into the original bytes. A literal
+//! U+FFFD in a name is escaped the same way, byte by byte, so the form can't be
+//! confused with a real name. Valid UTF-8 names are stored as they are.
+//!
+//! Helpers that only need a component of a stored path split on both separators,
+//! so a database scanned on Windows still groups correctly when read from WSL.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Paths at least this long need the `\\?\` prefix on Windows.
+const WINDOWS_MAX_PATH: usize = 260;
+
+/// Introduces an escaped byte in a stored path (followed by two hex digits).
+const RAW_BYTE: char = '\u{FFFD}';
+
+/// The stored form of a filesystem path.
+pub fn to_db(path: &Path) -> String {
+    match path.to_str() {
+        Some(s) if !s.contains(RAW_BYTE) => strip_verbatim(s),
+        _ => strip_verbatim(&escape(path.as_os_str().as_encoded_bytes())),
+    }
+}
+
+/// The filesystem path for a stored `file_path`.
+pub fn to_fs(stored: &str) -> PathBuf {
+    let stored = with_long_path_prefix(stored, cfg!(windows));
+    if stored.contains(RAW_BYTE) {
+        path_from_bytes(unescape(&stored))
+    } else {
+        PathBuf::from(stored)
+    }
+}
+
+/// Final component of a stored path (file name with extension).
+pub fn file_name(stored: &str) -> &str {
+    stored.rsplit(['/', '\\']).next().unwrap_or(stored)
+}
+
+/// Name of the directory containing a stored path, if it has one.
+pub fn parent_name(stored: &str) -> Option<&str> {
+    let mut parts = stored.rsplit(['/', '\\']);
+    parts.next()?;
+    parts.next().filter(|p| !p.is_empty() && !p.ends_with(':'))
+}
+
+/// The recording directory of a stored path: its parent, or the grandparent when
+/// the parent is a disc or set subfolder ("CD1", "Disc 2", "d1", "Set II").
+/// Tracks from one taper's source share it; `None` for a bare file name.
+pub fn source_dir(stored: &str) -> Option<&str> {
+    let dir = &stored[..stored.rfind(['/', '\\'])?];
+    let name = dir.rsplit(['/', '\\']).next().unwrap_or(dir);
+    match dir.rfind(['/', '\\']) {
+        Some(cut) if is_disc_folder(name) => Some(&dir[..cut]),
+        _ => Some(dir),
+    }
+}
+
+/// "CD1", "Disc 2", "disk_3", "d1", "Set 2", "Set II" (any case).
+fn is_disc_folder(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    let rest = ["disc", "disk", "cd", "set", "d", "s"]
+        .iter()
+        .find_map(|p| lower.strip_prefix(p))
+        .map(|r| r.trim_start_matches([' ', '_', '-']));
+    match rest {
+        Some(r) if !r.is_empty() && r.len() <= 2 && r.bytes().all(|b| b.is_ascii_digit()) => true,
+        Some(r) => lower.starts_with("set") && ["i", "ii", "iii", "iv"].contains(&r),
+        None => false,
+    }
+}
+
+/// Valid UTF-8 as is, everything else (and U+FFFD itself) as escaped bytes.
+fn escape(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    let raw = |out: &mut String, byte: u8| {
+        let _ = write!(out, "{RAW_BYTE}{byte:02X}");
+    };
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c == RAW_BYTE {
+                let mut buf = [0; 4];
+                for &byte in c.encode_utf8(&mut buf).as_bytes() {
+                    raw(&mut out, byte);
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        for &byte in chunk.invalid() {
+            raw(&mut out, byte);
+        }
+    }
+    out
+}
+
+/// The bytes a stored path stands for. A U+FFFD without two hex digits after
+/// it (from a lossy conversion by an older version) is kept as it is.
+fn unescape(stored: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(stored.len());
+    let mut rest = stored;
+    while let Some(at) = rest.find(RAW_BYTE) {
+        let after = &rest[at + RAW_BYTE.len_utf8()..];
+        let byte = after
+            .get(..2)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(byte) => {
+                out.extend_from_slice(&rest.as_bytes()[..at]);
+                out.push(byte);
+                rest = &after[2..];
+            }
+            None => {
+                out.extend_from_slice(&rest.as_bytes()[..at + RAW_BYTE.len_utf8()]);
+                rest = after;
+            }
+        }
+    }
+    out.extend_from_slice(rest.as_bytes());
+    out
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+/// Windows names are UTF-16; one that isn't valid (an unpaired surrogate) is
+/// the only kind that gets escaped there, and can't be rebuilt safely.
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// `\\?\C:\x` → `C:\x`, `\\?\UNC\server\share` → `\\server\share`.
+fn strip_verbatim(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{rest}")
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        rest.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Add the verbatim prefix Windows needs for long absolute paths. Verbatim paths
+/// skip normalization, so forward slashes are converted first.
+fn with_long_path_prefix(stored: &str, windows: bool) -> String {
+    if !windows || stored.len() < WINDOWS_MAX_PATH || stored.starts_with(r"\\?\") {
+        return stored.to_string();
+    }
+    let stored = stored.replace('/', "\\");
+    if let Some(unc) = stored.strip_prefix(r"\\") {
+        format!(r"\\?\UNC\{unc}")
+    } else if stored.get(1..3) == Some(":\\") {
+        format!(r"\\?\{stored}")
+    } else {
+        // Relative paths can't be verbatim
+        stored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_verbatim() {
+        assert_eq!(strip_verbatim(r"\\?\C:\Music\a.flac"), r"C:\Music\a.flac");
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\nas\music\a.flac"),
+            r"\\nas\music\a.flac"
+        );
+        assert_eq!(strip_verbatim("/music/a.flac"), "/music/a.flac");
+    }
+
+    #[test]
+    fn test_long_path_prefix() {
+        let dir = "x".repeat(WINDOWS_MAX_PATH);
+        let drive = format!(r"C:\Music\{dir}\a.flac");
+        assert_eq!(with_long_path_prefix(&drive, true), format!(r"\\?\{drive}"));
+        let unc = format!(r"\\nas\music/{dir}/a.flac");
+        assert_eq!(
+            with_long_path_prefix(&unc, true),
+            format!(r"\\?\UNC\nas\music\{dir}\a.flac")
+        );
+        // Short paths and other platforms are untouched
+        assert_eq!(with_long_path_prefix(r"C:\a.flac", true), r"C:\a.flac");
+        assert_eq!(with_long_path_prefix(&drive, false), drive);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_round_trip() {
+        use std::os::unix::ffi::OsStrExt;
+        let latin1 = Path::new(std::ffi::OsStr::from_bytes(
+            b"/music/Jos\xe9 Gonz\xe1lez/t01.flac",
+        ));
+        let stored = to_db(latin1);
+        assert_eq!(stored, "/music/Jos\u{FFFD}E9 Gonz\u{FFFD}E1lez/t01.flac");
+        assert_eq!(to_fs(&stored), latin1);
+        assert_eq!(file_name(&stored), "t01.flac");
+
+        // A literal U+FFFD is escaped too, so it can't pass for an escape
+        let literal = Path::new("/music/\u{FFFD}E9.flac");
+        let stored = to_db(literal);
+        assert_eq!(stored, "/music/\u{FFFD}EF\u{FFFD}BF\u{FFFD}BDE9.flac");
+        assert_eq!(to_fs(&stored), literal);
+
+        // Valid names are stored as they are; old lossy rows stay as they were
+        assert_eq!(to_db(Path::new("/music/José.flac")), "/music/José.flac");
+        assert_eq!(
+            to_fs("/music/Jos\u{FFFD}.flac"),
+            Path::new("/music/Jos\u{FFFD}.flac")
+        );
+    }
+
+    #[test]
+    fn test_components_either_separator() {
+        assert_eq!(file_name("/music/gd77-05-08/d1t01.flac"), "d1t01.flac");
+        assert_eq!(file_name(r"C:\Music\gd77-05-08\d1t01.flac"), "d1t01.flac");
+        assert_eq!(
+            parent_name(r"C:\Music\gd77-05-08\d1t01.flac"),
+            Some("gd77-05-08")
+        );
+        assert_eq!(parent_name("/music/gd77/d1t01.flac"), Some("gd77"));
+        assert_eq!(parent_name(r"C:\d1t01.flac"), None);
+        assert_eq!(parent_name("d1t01.flac"), None);
+
+        assert_eq!(source_dir("/music/gd77/d1t01.flac"), Some("/music/gd77"));
+        assert_eq!(
+            source_dir(r"C:\Music\gd77\CD 2\t01.flac"),
+            Some(r"C:\Music\gd77")
+        );
+        assert_eq!(
+            source_dir("/music/gd77/Set II/t01.flac"),
+            Some("/music/gd77")
+        );
+        assert_eq!(
+            source_dir("/music/gd77/disc_1/t01.flac"),
+            Some("/music/gd77")
+        );
+        assert_eq!(
+            source_dir("/music/dick's picks 3/t01.flac"),
+            Some("/music/dick's picks 3")
+        );
+        assert_eq!(source_dir("/music/sbd/t01.flac"), Some("/music/sbd"));
+        assert_eq!(source_dir("t01.flac"), None);
+    }
+}
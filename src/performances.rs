@@ -0,0 +1,516 @@
+//! Performances: every recording of one night, linked.
+//!
+//! A performance is one band on one date. Collectors often have several
+//! recordings of it — a soundboard, an audience tape, a matrix of the two, an
+//! FM broadcast — each in its own recording directory (`paths::source_dir`),
+//! typed by the scanner's `source_type`. `link` gives every dated track the
+//! `performance_id` of its band and date, or of the date a recording was
+//! manually linked to (a late set filed under the next day). With that, sources
+//! can be compared on the same music, calibration can learn what each kind of
+//! source does to the scores, and `top --per-performance` keeps one version of
+//! a song per night.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rusqlite::params;
+
+use crate::db::Database;
+use crate::db::columns::{BAND_EXPR, NOT_GARBAGE, SCORE_COLUMNS};
+
+/// One analyzed track of a linked performance.
+#[derive(Debug, Clone)]
+pub struct PerformanceTrack {
+    pub performance_id: i64,
+    pub band: String,
+    pub date: String,
+    /// Recording directory (`paths::source_dir`).
+    pub dir: String,
+    /// Scanner source type ("sbd", "aud", "matrix", "fm"); `None` if unknown.
+    pub source_type: Option<String>,
+    pub title: Option<String>,
+    /// Scores in `SCORE_COLUMNS` order.
+    pub scores: [Option<f64>; 10],
+}
+
+/// One recording of a performance.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub dir: String,
+    pub source_type: Option<String>,
+    pub tracks: usize,
+    /// Mean scores over the songs every recording of the performance has (all
+    /// of the recording's tracks when they share none), in `SCORE_COLUMNS` order.
+    pub means: [Option<f64>; 10],
+}
+
+impl Recording {
+    /// Directory name for display.
+    pub fn name(&self) -> &str {
+        crate::paths::file_name(&self.dir)
+    }
+
+    /// Source type for display ("?" when unknown).
+    pub fn kind(&self) -> &str {
+        self.source_type.as_deref().unwrap_or("?")
+    }
+
+    /// Mean of a score column ("groove_score").
+    pub fn mean(&self, column: &str) -> Option<f64> {
+        let i = SCORE_COLUMNS.iter().position(|c| *c == column)?;
+        self.means[i]
+    }
+}
+
+/// A performance with more than one recording.
+#[derive(Debug, Clone)]
+pub struct Performance {
+    pub id: i64,
+    pub band: String,
+    pub date: String,
+    /// Most tracks first.
+    pub recordings: Vec<Recording>,
+    /// Songs every recording has; the means are taken over these.
+    pub shared: usize,
+}
+
+/// How far one source type scores from the other recordings of the same nights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceOffset {
+    pub source_type: String,
+    /// Performances the offset was learned from.
+    pub performances: usize,
+    /// Mean difference from the performance's mean, in `SCORE_COLUMNS` order.
+    pub offsets: [f64; 10],
+}
+
+/// What `link` changed.
+#[derive(Debug, Default)]
+pub struct LinkSummary {
+    pub performances: usize,
+    /// Tracks whose performance was set or changed.
+    pub relinked: usize,
+}
+
+/// Group tracks into the performances that have more than one recording,
+/// ordered by date.
+pub fn compare(tracks: &[PerformanceTrack]) -> Vec<Performance> {
+    let mut by_performance: BTreeMap<(&str, i64), BTreeMap<&str, Vec<&PerformanceTrack>>> =
+        BTreeMap::new();
+    for t in tracks {
+        by_performance
+            .entry((t.date.as_str(), t.performance_id))
+            .or_default()
+            .entry(t.dir.as_str())
+            .or_default()
+            .push(t);
+    }
+
+    let mut performances = Vec::new();
+    for ((_, id), sources) in by_performance {
+        if sources.len() < 2 {
+            continue;
+        }
+        let titles: Vec<HashSet<String>> = sources
+            .values()
+            .map(|tracks| tracks.iter().filter_map(|t| song_key(t)).collect())
+            .collect();
+        let shared: HashSet<String> = titles
+            .iter()
+            .skip(1)
+            .fold(titles[0].clone(), |acc, set| &acc & set);
+
+        let mut recordings: Vec<Recording> = sources
+            .iter()
+            .map(|(dir, tracks)| {
+                let compared: Vec<&&PerformanceTrack> = tracks
+                    .iter()
+                    .filter(|t| {
+                        shared.is_empty() || song_key(t).is_some_and(|k| shared.contains(&k))
+                    })
+                    .collect();
+                let mut means = [None; 10];
+                for (i, mean) in means.iter_mut().enumerate() {
+                    let values: Vec<f64> = compared.iter().filter_map(|t| t.scores[i]).collect();
+                    if !values.is_empty() {
+                        *mean = Some(values.iter().sum::<f64>() / values.len() as f64);
+                    }
+                }
+                Recording {
+                    dir: dir.to_string(),
+                    source_type: tracks[0].source_type.clone(),
+                    tracks: tracks.len(),
+                    means,
+                }
+            })
+            .collect();
+        recordings.sort_by(|a, b| b.tracks.cmp(&a.tracks).then(a.dir.cmp(&b.dir)));
+
+        let first = sources.values().next().and_then(|t| t.first());
+        performances.push(Performance {
+            id,
+            band: first.map(|t| t.band.clone()).unwrap_or_default(),
+            date: first.map(|t| t.date.clone()).unwrap_or_default(),
+            recordings,
+            shared: shared.len(),
+        });
+    }
+    performances
+}
+
+/// Learn each source type's offset from performances recorded by more than
+/// one type: the mean of its recordings' distance from their performance's
+/// mean. Recordings of unknown type are left out.
+pub fn learn_offsets(performances: &[Performance]) -> Vec<SourceOffset> {
+    // source type → (performances, per-score sum, per-score count)
+    let mut acc: BTreeMap<&str, (usize, [f64; 10], [usize; 10])> = BTreeMap::new();
+    for p in performances {
+        let typed: Vec<&Recording> = p
+            .recordings
+            .iter()
+            .filter(|r| r.source_type.is_some())
+            .collect();
+        let types: HashSet<&str> = typed.iter().map(|r| r.kind()).collect();
+        if types.len() < 2 {
+            continue;
+        }
+        for kind in &types {
+            acc.entry(*kind).or_default().0 += 1;
+        }
+        for i in 0..SCORE_COLUMNS.len() {
+            let values: Vec<(&str, f64)> = typed
+                .iter()
+                .filter_map(|r| Some((r.kind(), r.means[i]?)))
+                .collect();
+            if values.len() < 2 {
+                continue;
+            }
+            let mean = values.iter().map(|(_, v)| v).sum::<f64>() / values.len() as f64;
+            for (kind, v) in values {
+                let entry = acc.entry(kind).or_default();
+                entry.1[i] += v - mean;
+                entry.2[i] += 1;
+            }
+        }
+    }
+    acc.into_iter()
+        .map(|(kind, (performances, sums, counts))| {
+            let mut offsets = [0.0; 10];
+            for i in 0..offsets.len() {
+                if counts[i] > 0 {
+                    offsets[i] = sums[i] / counts[i] as f64;
+                }
+            }
+            SourceOffset {
+                source_type: kind.to_string(),
+                performances,
+                offsets,
+            }
+        })
+        .collect()
+}
+
+/// Give every dated track the performance of its band and date — or, for a
+/// manually linked recording, of the linked date — and drop performances no
+/// track belongs to any more. Only tracks whose performance changed are written.
+pub fn link(db: &Database) -> crate::db::Result<LinkSummary> {
+    let manual = db.performance_links()?;
+    let tracks = db.link_candidates()?;
+
+    let tx = db.conn.unchecked_transaction()?;
+    let mut ids: HashMap<(String, String), i64> = HashMap::new();
+    {
+        let mut stmt = tx.prepare("SELECT band, date, id FROM performances")?;
+        let rows = stmt.query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))?;
+        for row in rows {
+            let (key, id) = row?;
+            ids.insert(key, id);
+        }
+    }
+
+    let mut summary = LinkSummary::default();
+    {
+        let mut insert = tx.prepare("INSERT INTO performances (band, date) VALUES (?1, ?2)")?;
+        let mut update = tx.prepare("UPDATE tracks SET performance_id = ?2 WHERE id = ?1")?;
+        for t in tracks {
+            let dir = crate::paths::source_dir(&t.file_path).unwrap_or("");
+            let date = manual.get(dir).or(t.date.as_ref());
+            let wanted = match date {
+                Some(date) => {
+                    let key = (t.band, date.clone());
+                    match ids.get(&key) {
+                        Some(&id) => Some(id),
+                        None => {
+                            insert.execute(params![key.0, key.1])?;
+                            let id = tx.last_insert_rowid();
+                            ids.insert(key, id);
+                            Some(id)
+                        }
+                    }
+                }
+                None => None,
+            };
+            if wanted != t.performance_id {
+                update.execute(params![t.id, wanted])?;
+                summary.relinked += 1;
+            }
+        }
+    }
+    tx.execute(
+        "DELETE FROM performances WHERE id NOT IN
+            (SELECT performance_id FROM tracks WHERE performance_id IS NOT NULL)",
+        [],
+    )?;
+    summary.performances = tx.query_row("SELECT COUNT(*) FROM performances", [], |row| {
+        row.get::<_, i64>(0)
+    })? as usize;
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// Lowercased title for matching songs across recordings.
+fn song_key(t: &PerformanceTrack) -> Option<String> {
+    t.title
+        .as_deref()
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+}
+
+/// A track as `link` sees it.
+struct LinkTrack {
+    id: i64,
+    file_path: String,
+    date: Option<String>,
+    band: String,
+    performance_id: Option<i64>,
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Link the recording directory `dir` to the performance on `date`.
+    /// Returns the number of tracks in it; nothing is stored when it's 0.
+    pub fn link_recording(&self, dir: &str, date: &str) -> crate::db::Result<usize> {
+        let tracks = self.tracks_in_source(dir)?;
+        if tracks > 0 {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO performance_links (source_dir, date, linked_at)
+                 VALUES (?1, ?2, datetime('now'))",
+                params![dir, date],
+            )?;
+        }
+        Ok(tracks)
+    }
+
+    /// Drop a manual link. Returns false if `dir` had none.
+    pub fn unlink_recording(&self, dir: &str) -> crate::db::Result<bool> {
+        let n = self.conn.execute(
+            "DELETE FROM performance_links WHERE source_dir = ?1",
+            params![dir],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// Manual links: recording directory → date.
+    pub fn performance_links(&self) -> crate::db::Result<HashMap<String, String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT source_dir, date FROM performance_links")?;
+        let links = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(links)
+    }
+
+    /// Analyzed, non-garbage tracks of linked performances, optionally for one
+    /// band (canonical name) or date.
+    pub fn performance_tracks(
+        &self,
+        band: Option<&str>,
+        date: Option<&str>,
+    ) -> crate::db::Result<Vec<PerformanceTrack>> {
+        let scores: Vec<String> = SCORE_COLUMNS.iter().map(|c| format!("a.{c}")).collect();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT p.id, p.band, p.date, t.file_path, t.source_type,
+                    COALESCE(t.parsed_title, t.title), {}
+             FROM tracks t
+             JOIN performances p ON p.id = t.performance_id
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE {NOT_GARBAGE}
+               AND (?1 IS NULL OR p.band = ?1) AND (?2 IS NULL OR p.date = ?2)",
+            scores.join(", ")
+        ))?;
+        let tracks = stmt
+            .query_map(params![band, date], |row| {
+                let mut scores = [None; 10];
+                for (i, score) in scores.iter_mut().enumerate() {
+                    *score = row.get(6 + i)?;
+                }
+                let path: String = row.get(3)?;
+                Ok(PerformanceTrack {
+                    performance_id: row.get(0)?,
+                    band: row.get(1)?,
+                    date: row.get(2)?,
+                    dir: crate::paths::source_dir(&path).unwrap_or("").to_string(),
+                    source_type: row.get(4)?,
+                    title: row.get(5)?,
+                    scores,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// Every track with what `link` needs to place it.
+    fn link_candidates(&self) -> crate::db::Result<Vec<LinkTrack>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT t.id, t.file_path, COALESCE(t.parsed_date, t.date), {BAND_EXPR},
+                    t.performance_id
+             FROM tracks t"
+        ))?;
+        let tracks = stmt
+            .query_map([], |row| {
+                Ok(LinkTrack {
+                    id: row.get(0)?,
+                    file_path: row.get(1)?,
+                    date: row.get(2)?,
+                    band: row.get(3)?,
+                    performance_id: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// Tracks whose recording directory is `dir`.
+    fn tracks_in_source(&self, dir: &str) -> crate::db::Result<usize> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT file_path FROM tracks WHERE substr(file_path, 1, length(?1)) = ?1")?;
+        let paths = stmt
+            .query_map(params![dir], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(paths
+            .iter()
+            .filter(|p| crate::paths::source_dir(p) == Some(dir))
+            .count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(dir: &str, source: &str, title: &str, groove: f64) -> PerformanceTrack {
+        let mut scores = [None; 10];
+        scores[2] = Some(groove);
+        PerformanceTrack {
+            performance_id: 1,
+            band: "Grateful Dead".into(),
+            date: "1977-05-08".into(),
+            dir: dir.into(),
+            source_type: Some(source.into()),
+            title: Some(title.into()),
+            scores,
+        }
+    }
+
+    #[test]
+    fn test_compare_and_learn_offsets() {
+        let tracks = vec![
+            track("/music/gd77-05-08.sbd", "sbd", "Scarlet Begonias", 70.0),
+            track("/music/gd77-05-08.sbd", "sbd", "Fire on the Mountain", 74.0),
+            track("/music/gd77-05-08.sbd", "sbd", "Morning Dew", 90.0),
+            track("/music/gd77-05-08.aud", "aud", "Scarlet Begonias", 64.0),
+            track("/music/gd77-05-08.aud", "aud", "Fire On The Mountain", 68.0),
+        ];
+        let performances = compare(&tracks);
+        assert_eq!(performances.len(), 1);
+        let p = &performances[0];
+        assert_eq!(p.shared, 2);
+        assert_eq!(p.recordings[0].name(), "gd77-05-08.sbd");
+        // Morning Dew is only on the SBD, so it isn't compared
+        assert_eq!(p.recordings[0].mean("groove_score"), Some(72.0));
+        assert_eq!(p.recordings[1].mean("groove_score"), Some(66.0));
+
+        let offsets = learn_offsets(&performances);
+        let sbd = offsets.iter().find(|o| o.source_type == "sbd").unwrap();
+        let aud = offsets.iter().find(|o| o.source_type == "aud").unwrap();
+        assert_eq!((sbd.offsets[2], aud.offsets[2]), (3.0, -3.0));
+        assert_eq!(sbd.performances, 1);
+
+        // A single recording isn't a comparison
+        assert!(compare(&tracks[..3]).is_empty());
+    }
+
+    #[test]
+    fn test_link_with_manual_override() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (file_path, file_size, file_modified, format, parsed_date,
+                                     parsed_band, parsed_title)
+                 VALUES ('/m/gd77-05-08.sbd/t01.flac', 1, '0', 'flac', '1977-05-08',
+                         'Grateful Dead', 'Scarlet Begonias'),
+                        ('/m/gd77-05-08.aud/cd1/t01.flac', 1, '0', 'flac', '1977-05-08',
+                         'Grateful Dead', 'Scarlet Begonias'),
+                        ('/m/gd77-05-09.aud/t01.flac', 1, '0', 'flac', '1977-05-09',
+                         'Grateful Dead', 'Scarlet Begonias');
+                 INSERT INTO analysis_results (track_id, groove_score)
+                 VALUES (1, 70.0), (2, 64.0), (3, 80.0);",
+            )
+            .unwrap();
+        let summary = link(&db).unwrap();
+        assert_eq!((summary.performances, summary.relinked), (2, 3));
+        assert_eq!(link(&db).unwrap().relinked, 0);
+
+        // Both tapes of the 8th collapse to the better one
+        let filter = crate::db::models::TopFilter {
+            per_performance: true,
+            ..Default::default()
+        };
+        let top = db.query_top("groove_score", 10, &filter).unwrap();
+        let dirs: Vec<&str> = top
+            .iter()
+            .filter_map(|t| crate::paths::source_dir(&t.file_path))
+            .collect();
+        assert_eq!(dirs, vec!["/m/gd77-05-09.aud", "/m/gd77-05-08.sbd"]);
+
+        // ...unless a DJ talks over it and the other tape is clean
+        db.conn
+            .execute(
+                "UPDATE analysis_results SET announcer_ratio = 0.4 WHERE track_id = 1",
+                [],
+            )
+            .unwrap();
+        let top = db.query_top("groove_score", 10, &filter).unwrap();
+        assert_eq!(top[1].file_path, "/m/gd77-05-08.aud/cd1/t01.flac");
+        db.conn
+            .execute("UPDATE analysis_results SET announcer_ratio = NULL", [])
+            .unwrap();
+
+        // The "next day" tape is a late set from the 8th
+        assert_eq!(
+            db.link_recording("/m/gd77-05-09.aud", "1977-05-08")
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            db.link_recording("/m/nothing-here", "1977-05-08").unwrap(),
+            0
+        );
+        let summary = link(&db).unwrap();
+        assert_eq!((summary.performances, summary.relinked), (1, 1));
+        let distinct: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(DISTINCT performance_id) FROM tracks",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(distinct, 1);
+
+        assert!(db.unlink_recording("/m/gd77-05-09.aud").unwrap());
+        assert_eq!(link(&db).unwrap().performances, 2);
+    }
+}
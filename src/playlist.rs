@@ -0,0 +1,79 @@
+//! Playlist export (extended M3U) for query results and saved searches.
+
+use crate::db::models::TrackScore;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Render tracks as an extended M3U playlist.
+pub fn to_m3u(tracks: &[TrackScore]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for t in tracks {
+        let secs = (t.duration_min * 60.0).round() as i64;
+        out.push_str(&format!("#EXTINF:{},{} ({})\n", secs, t.title, t.date));
+        out.push_str(&t.file_path);
+        out.push('\n');
+    }
+    out
+}
+
+/// Write tracks to an M3U playlist file, overwriting it if it exists.
+pub fn write_m3u(path: &Path, tracks: &[TrackScore]) -> io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(to_m3u(tracks).as_bytes())?;
+    Ok(())
+}
+
+/// Lowercase, filename-safe slug: runs of non-alphanumerics become one `-`.
+/// Empty if the title has no alphanumeric characters.
+pub fn slugify(title: &str) -> String {
+    let slug: String = title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_m3u() {
+        let track = TrackScore {
+            title: "Dark Star".into(),
+            date: "1972-08-27".into(),
+            file_path: "/music/gd72-08-27d2t01.flac".into(),
+            band: None,
+            set: None,
+            album: None,
+            duration_min: 31.5,
+            key: None,
+            tempo: None,
+            energy: 0.0,
+            intensity: 0.0,
+            groove: 0.0,
+            improvisation: 0.0,
+            tightness: 0.0,
+            build_quality: 0.0,
+            exploratory: 0.0,
+            transcendence: 0.0,
+            valence: 0.0,
+            arousal: 0.0,
+        };
+        assert_eq!(
+            to_m3u(&[track]),
+            "#EXTM3U\n#EXTINF:1890,Dark Star (1972-08-27)\n/music/gd72-08-27d2t01.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Best of 1977!"), "best-of-1977");
+        assert_eq!(slugify("Dark Star -> St. Stephen"), "dark-star-st-stephen");
+        assert_eq!(slugify("?!"), "");
+    }
+}
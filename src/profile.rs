@@ -0,0 +1,298 @@
+//! Sound profiles: reference feature centroids for "sounds like this era" matching.
+//!
+//! A profile is the mean of the raw 47-dim similarity vectors (MFCCs, spectral,
+//! sub-band, ZCR, tempo) of a chosen set of tracks — a whole show, several shows,
+//! or anything selectable with a `setbreak query` expression. Matching z-scores the
+//! centroid with the *current* library statistics and ranks every track by cosine
+//! distance to it, so profiles stay meaningful as the library grows. A profile
+//! built from an older feature vector layout has to be recreated.
+
+use crate::db::Database;
+use crate::db::columns::{LIVE_ONLY, NOT_GARBAGE};
+use crate::db::models::{SoundProfile, TrackScore};
+use crate::feature_vector::{self, VectorFormat};
+use crate::query::{self, QueryError};
+use crate::similarity::{centroid, cosine_similarity, feature_stats, normalize_vector};
+use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+    #[error("Invalid track selection: {0}")]
+    Query(#[from] QueryError),
+    #[error("No sound profile named '{0}' (see `setbreak profile list`)")]
+    NotFound(String),
+    #[error("No analyzed tracks match {0}")]
+    NoTracks(String),
+    #[error(
+        "Profile '{name}' was built with feature vector {stored}, but the library now uses {}; recreate it",
+        VectorFormat::CURRENT
+    )]
+    OutdatedVector { name: String, stored: VectorFormat },
+}
+
+impl SoundProfile {
+    /// Layout of the stored centroid.
+    pub fn vector_format(&self) -> VectorFormat {
+        VectorFormat {
+            version: self.vector_version,
+            dim: self.centroid.len(),
+        }
+    }
+}
+
+/// Build the filter expression that selects a profile's source tracks.
+/// Dates are OR'd together; an extra query expression is AND'd on top.
+pub fn source_expression(dates: &[String], extra: Option<&str>) -> Option<String> {
+    let dates_expr = match dates.len() {
+        0 => None,
+        1 => Some(format!("date={}", dates[0])),
+        _ => Some(format!(
+            "({})",
+            dates
+                .iter()
+                .map(|d| format!("date={d}"))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )),
+    };
+    match (dates_expr, extra) {
+        (Some(d), Some(q)) => Some(format!("{d} and ({q})")),
+        (Some(d), None) => Some(d),
+        (None, Some(q)) => Some(q.to_string()),
+        (None, None) => None,
+    }
+}
+
+/// Create (or replace) a named profile from the tracks matching `source`.
+pub fn create_profile(
+    db: &Database,
+    name: &str,
+    source: &str,
+) -> Result<SoundProfile, ProfileError> {
+    let filter = query::compile(source)?;
+    let track_ids: HashSet<i64> = db.query_filter_ids(&filter, false)?.into_iter().collect();
+
+    let vectors: Vec<Vec<f64>> = db
+        .get_feature_vectors()?
+        .into_iter()
+        .filter(|(id, _)| track_ids.contains(id))
+        .map(|(_, v)| v)
+        .collect();
+
+    if vectors.is_empty() {
+        return Err(ProfileError::NoTracks(format!("\"{source}\"")));
+    }
+
+    let profile = SoundProfile {
+        name: name.to_string(),
+        source: source.to_string(),
+        track_ids: {
+            let mut ids: Vec<i64> = track_ids.into_iter().collect();
+            ids.sort_unstable();
+            ids
+        },
+        centroid: centroid(&vectors),
+        vector_version: feature_vector::VERSION,
+        created_at: String::new(),
+    };
+    db.store_sound_profile(&profile)?;
+    Ok(profile)
+}
+
+/// Rank the library by cosine distance to a profile's centroid (closest first).
+/// Tracks the profile was built from are skipped unless `include_source`.
+pub fn match_profile(
+    db: &Database,
+    name: &str,
+    limit: usize,
+    live_only: bool,
+    include_source: bool,
+) -> Result<Vec<(TrackScore, f64)>, ProfileError> {
+    let profile = db
+        .get_sound_profile(name)?
+        .ok_or_else(|| ProfileError::NotFound(name.to_string()))?;
+
+    let stored = profile.vector_format();
+    if !stored.is_current() {
+        return Err(ProfileError::OutdatedVector {
+            name: profile.name,
+            stored,
+        });
+    }
+
+    let raw = db.get_feature_vectors()?;
+    let (means, stds) = feature_stats(&raw, stored.dim);
+    let target = normalize_vector(&profile.centroid, &means, &stds);
+
+    let candidates = db.get_profile_candidate_ids(live_only)?;
+    let source: HashSet<i64> = profile.track_ids.iter().copied().collect();
+
+    let mut ranked: Vec<(i64, f64)> = raw
+        .iter()
+        .filter(|(id, _)| candidates.contains(id))
+        .filter(|(id, _)| include_source || !source.contains(id))
+        .map(|(id, v)| {
+            let sim = cosine_similarity(&normalize_vector(v, &means, &stds), &target);
+            (*id, 1.0 - sim)
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked.truncate(limit);
+
+    let ids: Vec<i64> = ranked.iter().map(|(id, _)| *id).collect();
+    let distances: HashMap<i64, f64> = ranked.into_iter().collect();
+
+    Ok(db
+        .query_tracks_by_id(&ids, live_only)?
+        .into_iter()
+        .map(|(id, t)| (t, distances[&id]))
+        .collect())
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Save (or overwrite) a sound profile.
+    pub fn store_sound_profile(&self, p: &SoundProfile) -> crate::db::Result<()> {
+        let track_ids_json = serde_json::to_string(&p.track_ids).unwrap_or_default();
+        let centroid_json = serde_json::to_string(&p.centroid).unwrap_or_default();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO sound_profiles
+                 (name, source, track_ids_json, centroid_json, vector_version)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                p.name,
+                p.source,
+                track_ids_json,
+                centroid_json,
+                p.vector_version
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Look up a sound profile by name.
+    pub fn get_sound_profile(&self, name: &str) -> crate::db::Result<Option<SoundProfile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, source, track_ids_json, centroid_json, created_at, vector_version
+             FROM sound_profiles WHERE name = ?1",
+        )?;
+        let mut rows = stmt
+            .query_map([name], map_sound_profile)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows.pop())
+    }
+
+    /// All sound profiles, alphabetically.
+    pub fn list_sound_profiles(&self) -> crate::db::Result<Vec<SoundProfile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, source, track_ids_json, centroid_json, created_at, vector_version
+             FROM sound_profiles ORDER BY name",
+        )?;
+        let rows = stmt
+            .query_map([], map_sound_profile)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Delete a sound profile. Returns true if it existed.
+    pub fn delete_sound_profile(&self, name: &str) -> crate::db::Result<bool> {
+        let n = self
+            .conn
+            .execute("DELETE FROM sound_profiles WHERE name = ?1", [name])?;
+        Ok(n > 0)
+    }
+
+    /// Track IDs eligible to appear in profile matches (non-garbage, optionally live only).
+    fn get_profile_candidate_ids(&self, live_only: bool) -> crate::db::Result<HashSet<i64>> {
+        let live_filter = if live_only {
+            format!("AND {LIVE_ONLY}")
+        } else {
+            String::new()
+        };
+        let sql = format!(
+            "SELECT a.track_id
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {NOT_GARBAGE}
+               {live_filter}"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<HashSet<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+fn map_sound_profile(row: &rusqlite::Row) -> rusqlite::Result<SoundProfile> {
+    let track_ids_json: String = row.get(2)?;
+    let centroid_json: String = row.get(3)?;
+    Ok(SoundProfile {
+        name: row.get(0)?,
+        source: row.get(1)?,
+        track_ids: serde_json::from_str(&track_ids_json).unwrap_or_default(),
+        centroid: serde_json::from_str(&centroid_json).unwrap_or_default(),
+        created_at: row.get(4)?,
+        vector_version: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_expression() {
+        assert_eq!(
+            source_expression(&["1973-06-10".into()], None).as_deref(),
+            Some("date=1973-06-10")
+        );
+        assert_eq!(
+            source_expression(
+                &["1973-06-09".into(), "1973-06-10".into()],
+                Some("song=dark")
+            )
+            .as_deref(),
+            Some("(date=1973-06-09 or date=1973-06-10) and (song=dark)")
+        );
+        assert_eq!(source_expression(&[], None), None);
+    }
+
+    #[test]
+    fn test_centroid() {
+        let c = centroid(&[vec![1.0, 10.0], vec![3.0, 30.0]]);
+        assert_eq!(c, vec![2.0, 20.0]);
+    }
+
+    #[test]
+    fn test_profile_roundtrip() {
+        let db = Database::open_in_memory().unwrap();
+        let p = SoundProfile {
+            name: "wall-of-sound".into(),
+            source: "date=1974".into(),
+            track_ids: vec![3, 7],
+            centroid: vec![0.5, -1.25],
+            vector_version: feature_vector::VERSION,
+            created_at: String::new(),
+        };
+        db.store_sound_profile(&p).unwrap();
+
+        let got = db.get_sound_profile("wall-of-sound").unwrap().unwrap();
+        assert_eq!(got.track_ids, vec![3, 7]);
+        assert_eq!(got.centroid, vec![0.5, -1.25]);
+        // Two dimensions is not the current layout: matching refuses it
+        assert!(!got.vector_format().is_current());
+        assert!(matches!(
+            match_profile(&db, "wall-of-sound", 10, false, false),
+            Err(ProfileError::OutdatedVector { .. })
+        ));
+        assert_eq!(db.list_sound_profiles().unwrap().len(), 1);
+        assert!(db.delete_sound_profile("wall-of-sound").unwrap());
+        assert!(db.get_sound_profile("wall-of-sound").unwrap().is_none());
+    }
+}
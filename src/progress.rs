@@ -0,0 +1,264 @@
+//! Progress reporting for long-running library operations.
+//!
+//! Scanning, analysis, setlist lookup and similarity report through a
+//! [`Progress`] instead of drawing terminal bars themselves, so a library
+//! consumer (or a web UI) can show progress its own way:
+//!
+//! - [`CliProgress`]: an indicatif bar on stderr, as the CLI shows it.
+//! - [`SilentProgress`]: discards everything (tests, scripts).
+//! - [`CallbackProgress`]: hands each update to a closure as a [`ProgressEvent`].
+//!
+//! An operation calls `start` once per phase, then `inc`/`set_message` as work
+//! completes (from any thread), and `finish` at the end.
+//!
+//! `hide_bars` turns every terminal bar off for the rest of the process
+//! (`--no-progress`, `--quiet`), for cron jobs and log collection; `hide_notes`
+//! (`--quiet`) also drops the status lines operations send through `note`.
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+static BARS_HIDDEN: AtomicBool = AtomicBool::new(false);
+static NOTES_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Stop drawing progress bars from here on; lines and results still print.
+pub fn hide_bars() {
+    BARS_HIDDEN.store(true, Ordering::Relaxed);
+}
+
+/// Drop terminal status notes from here on (`note`, `CliProgress::note`).
+pub fn hide_notes() {
+    NOTES_HIDDEN.store(true, Ordering::Relaxed);
+}
+
+/// Whether `hide_notes` was called.
+pub fn notes_hidden() -> bool {
+    NOTES_HIDDEN.load(Ordering::Relaxed)
+}
+
+/// A status note on stderr, for code drawing its own `new_bar`; dropped
+/// after `hide_notes`.
+pub fn note(line: &str) {
+    if !notes_hidden() {
+        eprintln!("{line}");
+    }
+}
+
+/// A terminal progress bar, or a hidden one after `hide_bars`.
+pub fn new_bar(total: u64) -> ProgressBar {
+    if BARS_HIDDEN.load(Ordering::Relaxed) {
+        ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::hidden())
+    } else {
+        ProgressBar::new(total)
+    }
+}
+
+/// Receiver of progress updates. Shared across worker threads.
+pub trait Progress: Send + Sync {
+    /// A phase of `total` steps begins; `unit` names a step ("tracks", "dirs").
+    fn start(&self, total: u64, unit: &str);
+    /// `delta` more steps are done.
+    fn inc(&self, delta: u64);
+    /// Jump to an absolute position (e.g. when resuming).
+    fn set_position(&self, position: u64);
+    /// Short status shown next to the bar: the current item, running counts.
+    fn set_message(&self, message: &str);
+    /// A line of output that shouldn't garble the display.
+    fn println(&self, line: &str);
+    /// A non-essential status line (what's being fetched, how much was found).
+    fn note(&self, line: &str);
+    /// The phase ended; `message` summarizes it (empty to leave no trace).
+    fn finish(&self, message: &str);
+}
+
+/// An indicatif progress bar on stderr.
+#[derive(Default)]
+pub struct CliProgress {
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl CliProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn bar(&self) -> Option<ProgressBar> {
+        self.bar.lock().unwrap().clone()
+    }
+}
+
+impl Progress for CliProgress {
+    fn start(&self, total: u64, unit: &str) {
+        let bar = new_bar(total);
+        bar.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {prefix} ({eta} remaining) {msg}",
+            )
+            .unwrap()
+            .progress_chars("=>-"),
+        );
+        bar.set_prefix(unit.to_string());
+        if let Some(old) = self.bar.lock().unwrap().replace(bar) {
+            old.abandon();
+        }
+    }
+
+    fn inc(&self, delta: u64) {
+        if let Some(bar) = self.bar() {
+            bar.inc(delta);
+        }
+    }
+
+    fn set_position(&self, position: u64) {
+        if let Some(bar) = self.bar() {
+            bar.set_position(position);
+            bar.reset_eta();
+        }
+    }
+
+    fn set_message(&self, message: &str) {
+        if let Some(bar) = self.bar() {
+            bar.set_message(message.to_string());
+        }
+    }
+
+    fn println(&self, line: &str) {
+        match self.bar() {
+            Some(bar) => bar.suspend(|| println!("{line}")),
+            None => println!("{line}"),
+        }
+    }
+
+    /// On stderr, so stdout stays results only; dropped after `hide_notes`.
+    fn note(&self, line: &str) {
+        match self.bar() {
+            Some(bar) => bar.suspend(|| note(line)),
+            None => note(line),
+        }
+    }
+
+    fn finish(&self, message: &str) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            if message.is_empty() {
+                bar.finish_and_clear();
+            } else {
+                bar.finish_with_message(message.to_string());
+            }
+        }
+    }
+}
+
+/// Discards all progress.
+pub struct SilentProgress;
+
+impl Progress for SilentProgress {
+    fn start(&self, _total: u64, _unit: &str) {}
+    fn inc(&self, _delta: u64) {}
+    fn set_position(&self, _position: u64) {}
+    fn set_message(&self, _message: &str) {}
+    fn println(&self, _line: &str) {}
+    fn note(&self, _line: &str) {}
+    fn finish(&self, _message: &str) {}
+}
+
+/// One update delivered to a [`CallbackProgress`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent<'a> {
+    Start {
+        total: u64,
+        unit: &'a str,
+    },
+    /// Steps done so far, out of the phase's total.
+    Position {
+        position: u64,
+        total: u64,
+    },
+    Message(&'a str),
+    Line(&'a str),
+    Note(&'a str),
+    Finish(&'a str),
+}
+
+/// Hands every update to a closure, with positions made absolute.
+pub struct CallbackProgress<F> {
+    callback: F,
+    position: AtomicU64,
+    total: AtomicU64,
+}
+
+impl<F: Fn(ProgressEvent) + Send + Sync> CallbackProgress<F> {
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            position: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+        }
+    }
+
+    fn position_event(&self, position: u64) {
+        (self.callback)(ProgressEvent::Position {
+            position,
+            total: self.total.load(Ordering::Relaxed),
+        });
+    }
+}
+
+impl<F: Fn(ProgressEvent) + Send + Sync> Progress for CallbackProgress<F> {
+    fn start(&self, total: u64, unit: &str) {
+        self.total.store(total, Ordering::Relaxed);
+        self.position.store(0, Ordering::Relaxed);
+        (self.callback)(ProgressEvent::Start { total, unit });
+    }
+
+    fn inc(&self, delta: u64) {
+        let position = self.position.fetch_add(delta, Ordering::Relaxed) + delta;
+        self.position_event(position);
+    }
+
+    fn set_position(&self, position: u64) {
+        self.position.store(position, Ordering::Relaxed);
+        self.position_event(position);
+    }
+
+    fn set_message(&self, message: &str) {
+        (self.callback)(ProgressEvent::Message(message));
+    }
+
+    fn println(&self, line: &str) {
+        (self.callback)(ProgressEvent::Line(line));
+    }
+
+    fn note(&self, line: &str) {
+        (self.callback)(ProgressEvent::Note(line));
+    }
+
+    fn finish(&self, message: &str) {
+        (self.callback)(ProgressEvent::Finish(message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_positions_are_absolute() {
+        let seen = Mutex::new(Vec::new());
+        let progress = CallbackProgress::new(|e: ProgressEvent| {
+            if let ProgressEvent::Position { position, total } = e {
+                seen.lock().unwrap().push((position, total));
+            }
+        });
+        progress.start(10, "tracks");
+        progress.set_position(4);
+        std::thread::scope(|s| {
+            for _ in 0..3 {
+                s.spawn(|| progress.inc(2));
+            }
+        });
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        assert_eq!(seen, vec![(4, 10), (6, 10), (8, 10), (10, 10)]);
+    }
+}
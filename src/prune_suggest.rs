@@ -0,0 +1,535 @@
+//! Pruning suggestions: what to delete when the disk fills up.
+//!
+//! Three kinds of file are worth little. Tracks flagged garbage. A performance's
+//! lesser recordings, when a better source (by `scanner::source::source_quality`,
+//! then completeness) has the same songs. And studio filler: the bottom tenth of
+//! each band's studio tracks by score. Each candidate gets a value (the score
+//! points the library would lose without it) and is ranked by value per byte, so
+//! big, worthless groups come first. `write_script` prints the picks as a shell
+//! script of `rm` lines to review and run; nothing here deletes anything.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+
+use rusqlite::params;
+
+use crate::db::Database;
+use crate::db::columns::{BAND_EXPR, SCORE_COLUMNS};
+
+/// Scores a track's value is the mean of: the first eight `SCORE_COLUMNS`
+/// (valence and arousal are mood, not quality).
+const VALUE_SCORES: usize = 8;
+
+/// Share of a band's studio tracks counted as filler.
+const FILLER_SHARE: f64 = 0.1;
+
+/// Why a candidate could go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// Tracks flagged `data_quality = 'garbage'`.
+    Garbage,
+    /// A recording of a performance that has a better one.
+    InferiorSource,
+    /// A studio track in the bottom tenth of its band's.
+    StudioFiller,
+}
+
+impl Reason {
+    pub fn label(self) -> &'static str {
+        match self {
+            Reason::Garbage => "garbage",
+            Reason::InferiorSource => "inferior source",
+            Reason::StudioFiller => "studio filler",
+        }
+    }
+}
+
+/// A group of files that could be deleted together.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub reason: Reason,
+    /// What the files are ("Grateful Dead 1977-05-08: gd77-05-08.aud").
+    pub label: String,
+    /// Why they're worth little.
+    pub note: String,
+    pub files: Vec<String>,
+    pub bytes: u64,
+    /// Score points lost by deleting: 0 for garbage, for a lesser recording
+    /// what it scores above the kept one plus its songs the kept one lacks.
+    pub value: f64,
+}
+
+impl Candidate {
+    /// Value lost per GB freed; lower goes first.
+    pub fn value_per_gb(&self) -> f64 {
+        self.value / (self.bytes.max(1) as f64 / 1e9)
+    }
+}
+
+/// Candidates picked to reach a target.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub picked: Vec<Candidate>,
+    pub bytes: u64,
+    pub target: Option<u64>,
+    /// Size of every candidate, picked or not.
+    pub available: u64,
+}
+
+impl Plan {
+    pub fn files(&self) -> usize {
+        self.picked.iter().map(|c| c.files.len()).sum()
+    }
+
+    /// Whether the picks free at least the target (always true without one).
+    pub fn reaches_target(&self) -> bool {
+        self.target.is_none_or(|t| self.bytes >= t)
+    }
+}
+
+/// Rank candidates by value per byte (then larger first) and take them until
+/// `target` bytes are freed; all of them without a target.
+pub fn plan(mut candidates: Vec<Candidate>, target: Option<u64>) -> Plan {
+    candidates.sort_by(|a, b| {
+        a.value_per_gb()
+            .total_cmp(&b.value_per_gb())
+            .then(b.bytes.cmp(&a.bytes))
+            .then(a.label.cmp(&b.label))
+    });
+    let mut plan = Plan {
+        target,
+        available: candidates.iter().map(|c| c.bytes).sum(),
+        ..Plan::default()
+    };
+    for c in candidates {
+        if target.is_some_and(|t| plan.bytes >= t) {
+            break;
+        }
+        plan.bytes += c.bytes;
+        plan.picked.push(c);
+    }
+    plan
+}
+
+/// Parse a size like `500GB`, `1.5T` or `800MiB`. Units are
+/// decimal (KB, MB, GB, TB; the B is optional) or binary (KiB, MiB, GiB,
+/// TiB); bare numbers are GB.
+pub fn parse_size(value: &str) -> Option<u64> {
+    let v = value.trim().to_lowercase();
+    let split = v
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(v.len());
+    let (num, unit) = v.split_at(split);
+    let n: f64 = num.parse().ok()?;
+    let mult = match unit.trim() {
+        "" | "g" | "gb" => 1e9,
+        "b" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "t" | "tb" => 1e12,
+        "kib" => 1024f64,
+        "mib" => 1024f64.powi(2),
+        "gib" => 1024f64.powi(3),
+        "tib" => 1024f64.powi(4),
+        _ => return None,
+    };
+    let bytes = n * mult;
+    (bytes.is_finite() && bytes > 0.0).then_some(bytes as u64)
+}
+
+/// Human-readable decimal size ("512.3 GB").
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < units.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", units[unit])
+    }
+}
+
+/// Quote a path for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Write a plan as a shell script: a commented header, then each candidate's
+/// reason and an `rm` line per file, so single lines or whole groups can be
+/// cut before running it.
+pub fn write_script(plan: &Plan, mut out: impl Write) -> io::Result<()> {
+    writeln!(out, "#!/bin/sh")?;
+    writeln!(
+        out,
+        "# setbreak prune-suggest: {} groups, {} files, {}{}",
+        plan.picked.len(),
+        plan.files(),
+        format_size(plan.bytes),
+        match plan.target {
+            Some(t) => format!(" (target {})", format_size(t)),
+            None => String::new(),
+        }
+    )?;
+    writeln!(
+        out,
+        "# Nothing has been deleted. Review and edit, then run with sh."
+    )?;
+    writeln!(
+        out,
+        "# Afterwards `setbreak db prune-details` drops the deleted tracks."
+    )?;
+    for c in &plan.picked {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "# [{}] {} — {} ({}, value {:.1})",
+            c.reason.label(),
+            c.label,
+            c.note,
+            format_size(c.bytes),
+            c.value
+        )?;
+        for f in &c.files {
+            writeln!(out, "rm -f -- {}", shell_quote(f))?;
+        }
+    }
+    Ok(())
+}
+
+/// A track as the suggestions see it.
+struct PruneTrack {
+    file_path: String,
+    file_size: u64,
+    garbage: bool,
+    studio: bool,
+    performance_id: Option<i64>,
+    band: String,
+    date: Option<String>,
+    title: Option<String>,
+    album: Option<String>,
+    source_type: Option<String>,
+    /// Mean of the `VALUE_SCORES` it has; None if unanalyzed.
+    value: Option<f64>,
+}
+
+impl PruneTrack {
+    fn dir(&self) -> &str {
+        crate::paths::source_dir(&self.file_path).unwrap_or("")
+    }
+
+    fn song_key(&self) -> Option<String> {
+        self.title
+            .as_deref()
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, n) = values.fold((0.0, 0usize), |(s, n), v| (s + v, n + 1));
+    (n > 0).then(|| sum / n as f64)
+}
+
+/// Every garbage track, grouped by recording directory.
+fn garbage(tracks: &[PruneTrack]) -> Vec<Candidate> {
+    let mut by_dir: BTreeMap<(&str, &str), Vec<&PruneTrack>> = BTreeMap::new();
+    for t in tracks.iter().filter(|t| t.garbage) {
+        by_dir.entry((&t.band, t.dir())).or_default().push(t);
+    }
+    by_dir
+        .into_iter()
+        .map(|((band, dir), group)| Candidate {
+            reason: Reason::Garbage,
+            label: format!("{band}: {}", crate::paths::file_name(dir)),
+            note: format!("{} tracks flagged garbage", group.len()),
+            files: group.iter().map(|t| t.file_path.clone()).collect(),
+            bytes: group.iter().map(|t| t.file_size).sum(),
+            value: 0.0,
+        })
+        .collect()
+}
+
+/// The lesser recordings of every performance with more than one. The kept
+/// recording is the best source type, then the most tracks, then the best
+/// mean value. A lesser one's value is what its shared songs score above the
+/// kept copies plus the full value of songs only it has.
+fn inferior_sources(tracks: &[PruneTrack]) -> Vec<Candidate> {
+    let mut by_performance: BTreeMap<i64, BTreeMap<&str, Vec<&PruneTrack>>> = BTreeMap::new();
+    for t in tracks.iter().filter(|t| !t.garbage) {
+        if let Some(id) = t.performance_id {
+            by_performance
+                .entry(id)
+                .or_default()
+                .entry(t.dir())
+                .or_default()
+                .push(t);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for recordings in by_performance.values() {
+        if recordings.len() < 2 {
+            continue;
+        }
+        let rank = |group: &[&PruneTrack]| {
+            (
+                crate::scanner::source::source_quality(group[0].source_type.as_deref()),
+                group.len(),
+                mean(group.iter().filter_map(|t| t.value)).unwrap_or(0.0),
+            )
+        };
+        let Some((&kept_dir, kept)) = recordings.iter().max_by(|a, b| {
+            let (ra, rb) = (rank(a.1), rank(b.1));
+            ra.0.cmp(&rb.0)
+                .then(ra.1.cmp(&rb.1))
+                .then(ra.2.total_cmp(&rb.2))
+                .then(b.0.cmp(a.0))
+        }) else {
+            continue;
+        };
+        // Kept value per song
+        let mut kept_songs: HashMap<String, Vec<f64>> = HashMap::new();
+        for t in kept {
+            if let Some(key) = t.song_key() {
+                kept_songs.entry(key).or_default().extend(t.value);
+            }
+        }
+
+        for (&dir, group) in recordings {
+            if dir == kept_dir {
+                continue;
+            }
+            let fallback = mean(group.iter().filter_map(|t| t.value)).unwrap_or(50.0);
+            let mut shared = 0;
+            let mut value = 0.0;
+            for t in group {
+                match t.song_key().and_then(|k| kept_songs.get(&k)) {
+                    Some(kept_values) => {
+                        shared += 1;
+                        if let (Some(own), Some(kept)) =
+                            (t.value, mean(kept_values.iter().copied()))
+                        {
+                            value += (own - kept).max(0.0);
+                        }
+                    }
+                    None => value += t.value.unwrap_or(fallback),
+                }
+            }
+            let kind = |g: &[&PruneTrack]| g[0].source_type.clone().unwrap_or("?".into());
+            candidates.push(Candidate {
+                reason: Reason::InferiorSource,
+                label: format!(
+                    "{} {}: {} ({})",
+                    group[0].band,
+                    group[0].date.as_deref().unwrap_or("?"),
+                    crate::paths::file_name(dir),
+                    kind(group)
+                ),
+                note: format!(
+                    "keeping {} ({}), which has {shared} of its {} tracks",
+                    crate::paths::file_name(kept_dir),
+                    kind(kept),
+                    group.len()
+                ),
+                files: group.iter().map(|t| t.file_path.clone()).collect(),
+                bytes: group.iter().map(|t| t.file_size).sum(),
+                value,
+            });
+        }
+    }
+    candidates
+}
+
+/// The lowest-valued `FILLER_SHARE` of each band's analyzed studio tracks.
+fn studio_filler(tracks: &[PruneTrack]) -> Vec<Candidate> {
+    let mut by_band: BTreeMap<&str, Vec<(&PruneTrack, f64)>> = BTreeMap::new();
+    for t in tracks.iter().filter(|t| t.studio && !t.garbage) {
+        if let Some(v) = t.value {
+            by_band.entry(&t.band).or_default().push((t, v));
+        }
+    }
+    let mut candidates = Vec::new();
+    for (band, mut studio) in by_band {
+        studio.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let n = (studio.len() as f64 * FILLER_SHARE) as usize;
+        for (t, value) in studio.into_iter().take(n) {
+            candidates.push(Candidate {
+                reason: Reason::StudioFiller,
+                label: format!(
+                    "{band}: {} ({})",
+                    t.title.as_deref().unwrap_or("(untitled)"),
+                    t.album.as_deref().unwrap_or("?")
+                ),
+                note: "bottom tenth of the band's studio tracks".to_string(),
+                files: vec![t.file_path.clone()],
+                bytes: t.file_size,
+                value,
+            });
+        }
+    }
+    candidates
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Every pruning candidate, optionally for one band (canonical name). A
+    /// file is only ever in one candidate: garbage first, then lesser sources,
+    /// then studio filler.
+    pub fn prune_candidates(&self, band: Option<&str>) -> crate::db::Result<Vec<Candidate>> {
+        let scores: Vec<String> = SCORE_COLUMNS[..VALUE_SCORES]
+            .iter()
+            .map(|c| format!("a.{c}"))
+            .collect();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT t.file_path, t.file_size, COALESCE(t.data_quality, 'ok') = 'garbage',
+                    COALESCE(t.recording_type, '') = 'studio', t.performance_id, {BAND_EXPR},
+                    COALESCE(t.parsed_date, t.date), COALESCE(t.parsed_title, t.title),
+                    t.album, t.source_type, {}
+             FROM tracks t
+             LEFT JOIN analysis_results a ON a.track_id = t.id
+             WHERE (?1 IS NULL OR {BAND_EXPR} = ?1)",
+            scores.join(", ")
+        ))?;
+        let tracks = stmt
+            .query_map(params![band], |row| {
+                let mut values = Vec::new();
+                for i in 0..VALUE_SCORES {
+                    values.extend(row.get::<_, Option<f64>>(10 + i)?);
+                }
+                Ok(PruneTrack {
+                    file_path: row.get(0)?,
+                    file_size: row.get::<_, i64>(1)?.max(0) as u64,
+                    garbage: row.get(2)?,
+                    studio: row.get(3)?,
+                    performance_id: row.get(4)?,
+                    band: row.get(5)?,
+                    date: row.get(6)?,
+                    title: row.get(7)?,
+                    album: row.get(8)?,
+                    source_type: row.get(9)?,
+                    value: mean(values.into_iter()),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut seen = HashSet::new();
+        let mut candidates = Vec::new();
+        for mut c in garbage(&tracks)
+            .into_iter()
+            .chain(inferior_sources(&tracks))
+            .chain(studio_filler(&tracks))
+        {
+            c.files.retain(|f| seen.insert(f.clone()));
+            if !c.files.is_empty() {
+                candidates.push(c);
+            }
+        }
+        Ok(candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("500GB"), Some(500_000_000_000));
+        assert_eq!(parse_size("1.5t"), Some(1_500_000_000_000));
+        assert_eq!(parse_size("800 MiB"), Some(800 * 1024 * 1024));
+        assert_eq!(parse_size("20"), Some(20_000_000_000));
+        assert_eq!(parse_size("0GB"), None);
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(format_size(512_300_000_000), "512.3 GB");
+        assert_eq!(format_size(999), "999 B");
+    }
+
+    #[test]
+    fn test_prune_candidates_and_plan() {
+        let db = Database::open_in_memory().unwrap();
+        let mut sql = String::from(
+            "INSERT INTO performances (id, band, date) VALUES (1, 'Grateful Dead', '1977-05-08');",
+        );
+        let mut id = 0;
+        let mut track = |path: &str, size: i64, extra: &str, title: &str, score: Option<f64>| {
+            id += 1;
+            let (quality, kind, source, perf) = match extra {
+                "garbage" => ("'garbage'", "'live'", "NULL", "NULL"),
+                "studio" => ("NULL", "'studio'", "NULL", "NULL"),
+                _ => ("NULL", "'live'", extra, "1"),
+            };
+            sql += &format!(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format,
+                                     parsed_band, parsed_date, parsed_title, data_quality,
+                                     recording_type, source_type, performance_id)
+                 VALUES ({id}, '{path}', {size}, '0', 'flac', 'Grateful Dead', '1977-05-08',
+                         '{title}', {quality}, {kind}, {source}, {perf});"
+            );
+            if let Some(s) = score {
+                sql += &format!(
+                    "INSERT INTO analysis_results (track_id, energy_score, groove_score)
+                     VALUES ({id}, {s}, {s});"
+                );
+            }
+        };
+        track("/m/junk/t01.flac", 50, "garbage", "Noise", None);
+        for (i, title) in ["Bertha", "Loser"].iter().enumerate() {
+            let sbd = format!("/m/gd77.sbd/t0{i}.flac");
+            let aud = format!("/m/gd77.aud/t0{i}.flac");
+            track(&sbd, 100, "'sbd'", title, Some(60.0));
+            track(
+                &aud,
+                120,
+                "'aud'",
+                title,
+                Some(if i == 0 { 70.0 } else { 40.0 }),
+            );
+        }
+        for i in 0..10 {
+            track(
+                &format!("/m/studio/t{i:02}.flac"),
+                30,
+                "studio",
+                "Song",
+                Some(10.0 + i as f64),
+            );
+        }
+        db.conn.execute_batch(&sql).unwrap();
+
+        let candidates = db.prune_candidates(None).unwrap();
+        assert_eq!(candidates.len(), 3);
+        let aud = candidates
+            .iter()
+            .find(|c| c.reason == Reason::InferiorSource)
+            .unwrap();
+        assert_eq!(aud.files.len(), 2);
+        assert_eq!(aud.bytes, 240);
+        // Bertha on the AUD scores 10 above the SBD's; Loser nothing
+        assert!((aud.value - 10.0).abs() < 1e-9);
+        let filler = candidates
+            .iter()
+            .find(|c| c.reason == Reason::StudioFiller)
+            .unwrap();
+        assert_eq!(filler.files, vec!["/m/studio/t00.flac"]);
+
+        let p = plan(candidates.clone(), Some(50));
+        assert_eq!(p.picked.len(), 1);
+        assert_eq!(p.picked[0].reason, Reason::Garbage);
+        let p = plan(candidates, Some(1_000));
+        assert_eq!(p.picked.len(), 3);
+        assert_eq!(p.bytes, 320);
+        assert!(!p.reaches_target());
+
+        let mut script = Vec::new();
+        write_script(&p, &mut script).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("rm -f -- '/m/gd77.aud/t00.flac'\n"));
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
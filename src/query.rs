@@ -0,0 +1,827 @@
+//! Filter expression language for `setbreak query`.
+//!
+//! Compiles expressions like `groove>70 and valence>60 and duration>10min and band=gd`
+//! into a parameterized SQL WHERE fragment over
+//! `analysis_results a JOIN tracks t ON t.id = a.track_id`.
+//!
+//! Grammar (keywords are case-insensitive):
+//!
+//! ```text
+//! expr       := and_expr ("or" and_expr)*
+//! and_expr   := unary ("and" unary)*
+//! unary      := "not" unary | "(" expr ")" | comparison
+//! comparison := field op value
+//! op         := >  >=  <  <=  =  ==  !=  <>  ~
+//! ```
+//!
+//! Fields: the 10 jam scores (`groove`, `improv`, `build`, ...), `duration`
+//! (bare numbers are minutes; `s`/`min`/`h` suffixes accepted), `tempo`
+//! (octave-corrected BPM), `year`, `band` (code or name), `date` (full or
+//! partial, e.g. `date=1977-05`), `song`, `key`, `type`, `venue`, `format`,
+//! `source` (sbd/matrix/fm/aud), `taper`, `lineage`, `source_rank` (sbd=3 ..
+//! unknown=0), `plays` (imported scrobbles), any numeric column from
+//! `setbreak schema`, and `ext.<column>` for external analyzer outputs. Values
+//! containing spaces must be quoted: `song="dark star"`.
+
+use crate::db::Database;
+use crate::db::columns::{
+    ANALYSIS_SCHEMA, BAND_EXPR, LIVE_ONLY, NOT_GARBAGE, SCORE_COLUMNS, TRACK_SCORE_SELECT,
+    map_track_score,
+};
+use crate::db::models::{SavedSearch, TrackScore};
+use rusqlite::types::Value;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum QueryError {
+    #[error("Parse error at position {pos}: {message}")]
+    Parse { pos: usize, message: String },
+    #[error(
+        "Unknown field '{0}' (use a score name, duration, tempo, year, band, date, song, key, type, venue, format, source, taper, lineage, source_rank, plays, ext.<column>, or a column from `setbreak schema`)"
+    )]
+    UnknownField(String),
+    #[error("Invalid value '{value}' for {field}: {message}")]
+    InvalidValue {
+        field: String,
+        value: String,
+        message: String,
+    },
+}
+
+/// A compiled filter: a WHERE fragment with `?N` placeholders and its bound values.
+#[derive(Debug, Clone)]
+pub struct CompiledFilter {
+    pub where_clause: String,
+    pub params: Vec<Value>,
+}
+
+/// Compile a filter expression into a parameterized SQL WHERE fragment.
+pub fn compile(expr: &str) -> Result<CompiledFilter, QueryError> {
+    let tokens = tokenize(expr)?;
+    if tokens.is_empty() {
+        return Err(parse_err(0, "empty expression"));
+    }
+
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        end: expr.chars().count(),
+        params: Vec::new(),
+    };
+    let where_clause = parser.parse_or()?;
+
+    if let Some((pos, _)) = parser.peek() {
+        return Err(parse_err(*pos, "unexpected token (missing 'and'/'or'?)"));
+    }
+
+    Ok(CompiledFilter {
+        where_clause,
+        params: parser.params,
+    })
+}
+
+// ── Lexer ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Like,
+}
+
+impl CmpOp {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Like => "LIKE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Op(CmpOp),
+    Word(String),
+    Quoted(String),
+}
+
+fn parse_err(pos: usize, message: &str) -> QueryError {
+    QueryError::Parse {
+        pos,
+        message: message.to_string(),
+    }
+}
+
+/// Split an expression into (char position, token) pairs.
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        match c {
+            '(' => {
+                tokens.push((start, Token::LParen));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((start, Token::RParen));
+                i += 1;
+            }
+            '>' | '<' | '=' | '!' | '~' => {
+                let (op, len) = match (c, chars.get(i + 1).copied()) {
+                    ('>', Some('=')) => (CmpOp::Ge, 2),
+                    ('<', Some('=')) => (CmpOp::Le, 2),
+                    ('<', Some('>')) => (CmpOp::Ne, 2),
+                    ('=', Some('=')) => (CmpOp::Eq, 2),
+                    ('!', Some('=')) => (CmpOp::Ne, 2),
+                    ('>', _) => (CmpOp::Gt, 1),
+                    ('<', _) => (CmpOp::Lt, 1),
+                    ('=', _) => (CmpOp::Eq, 1),
+                    ('~', _) => (CmpOp::Like, 1),
+                    _ => return Err(parse_err(start, "expected '=' after '!'")),
+                };
+                tokens.push((start, Token::Op(op)));
+                i += len;
+            }
+            '"' | '\'' => {
+                i += 1;
+                let mut s = String::new();
+                while i < chars.len() && chars[i] != c {
+                    s.push(chars[i]);
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(parse_err(start, "unterminated string"));
+                }
+                i += 1; // closing quote
+                tokens.push((start, Token::Quoted(s)));
+            }
+            _ => {
+                let mut s = String::new();
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !"()<>=!~\"".contains(chars[i])
+                {
+                    s.push(chars[i]);
+                    i += 1;
+                }
+                tokens.push((start, Token::Word(s)));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+// ── Parser / compiler ───────────────────────────────────────────────────
+
+struct Parser<'a> {
+    tokens: &'a [(usize, Token)],
+    pos: usize,
+    /// Character length of the input (for end-of-input error positions)
+    end: usize,
+    params: Vec<Value>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.pos)
+    }
+
+    fn here(&self) -> usize {
+        self.peek().map(|(p, _)| *p).unwrap_or(self.end)
+    }
+
+    fn advance(&mut self) -> Option<&(usize, Token)> {
+        let t = self.tokens.get(self.pos);
+        self.pos += 1;
+        t
+    }
+
+    /// Consume a bare keyword (case-insensitive) if it is next.
+    fn eat_keyword(&mut self, keywords: &[&str]) -> bool {
+        if let Some((_, Token::Word(w))) = self.peek() {
+            if keywords.iter().any(|k| w.eq_ignore_ascii_case(k)) {
+                self.pos += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn bind(&mut self, value: Value) -> String {
+        self.params.push(value);
+        format!("?{}", self.params.len())
+    }
+
+    fn parse_or(&mut self) -> Result<String, QueryError> {
+        let mut parts = vec![self.parse_and()?];
+        while self.eat_keyword(&["or", "||"]) {
+            parts.push(self.parse_and()?);
+        }
+        Ok(join_parts(parts, " OR "))
+    }
+
+    fn parse_and(&mut self) -> Result<String, QueryError> {
+        let mut parts = vec![self.parse_unary()?];
+        while self.eat_keyword(&["and", "&&"]) {
+            parts.push(self.parse_unary()?);
+        }
+        Ok(join_parts(parts, " AND "))
+    }
+
+    fn parse_unary(&mut self) -> Result<String, QueryError> {
+        if self.eat_keyword(&["not"]) {
+            let inner = self.parse_unary()?;
+            return Ok(format!("NOT ({inner})"));
+        }
+
+        if let Some((_, Token::LParen)) = self.peek() {
+            self.pos += 1;
+            let inner = self.parse_or()?;
+            let here = self.here();
+            match self.advance() {
+                Some((_, Token::RParen)) => return Ok(format!("({inner})")),
+                _ => return Err(parse_err(here, "expected ')'")),
+            }
+        }
+
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<String, QueryError> {
+        let here = self.here();
+        let field = match self.advance() {
+            Some((_, Token::Word(w))) => w.clone(),
+            _ => return Err(parse_err(here, "expected a field name")),
+        };
+
+        let here = self.here();
+        let op = match self.advance() {
+            Some((_, Token::Op(op))) => *op,
+            _ => {
+                return Err(parse_err(
+                    here,
+                    &format!("expected a comparison operator after '{field}'"),
+                ));
+            }
+        };
+
+        let here = self.here();
+        let value = match self.advance() {
+            Some((_, Token::Word(w))) | Some((_, Token::Quoted(w))) => w.clone(),
+            _ => return Err(parse_err(here, &format!("expected a value for '{field}'"))),
+        };
+
+        self.compile_comparison(&field, op, &value)
+    }
+
+    fn compile_comparison(
+        &mut self,
+        field: &str,
+        op: CmpOp,
+        value: &str,
+    ) -> Result<String, QueryError> {
+        let invalid = |message: &str| QueryError::InvalidValue {
+            field: field.to_string(),
+            value: value.to_string(),
+            message: message.to_string(),
+        };
+
+        let resolved = resolve_field(&field.to_lowercase())
+            .ok_or_else(|| QueryError::UnknownField(field.to_string()))?;
+
+        match resolved {
+            Field::Numeric { column, duration } => {
+                if op == CmpOp::Like {
+                    return Err(invalid("'~' only applies to text fields"));
+                }
+                let n = if duration {
+                    parse_duration_secs(value)
+                        .ok_or_else(|| invalid("expected a duration like 10min, 90s, or 1h"))?
+                } else {
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| invalid("expected a number"))?
+                };
+                let p = self.bind(Value::Real(n));
+                Ok(format!("{column} {} {p}", op.sql()))
+            }
+            Field::Text(column) => match op {
+                CmpOp::Like => {
+                    let p = self.bind(Value::Text(format!("%{value}%")));
+                    Ok(format!("{column} LIKE {p}"))
+                }
+                CmpOp::Eq => {
+                    let p = self.bind(Value::Text(value.to_string()));
+                    Ok(format!("LOWER({column}) = LOWER({p})"))
+                }
+                CmpOp::Ne => {
+                    let p = self.bind(Value::Text(value.to_string()));
+                    Ok(format!(
+                        "({column} IS NULL OR LOWER({column}) != LOWER({p}))"
+                    ))
+                }
+                _ => {
+                    let p = self.bind(Value::Text(value.to_string()));
+                    Ok(format!("{column} {} {p}", op.sql()))
+                }
+            },
+            Field::Song => {
+                let p = self.bind(Value::Text(format!("%{value}%")));
+                let cond = format!("(t.parsed_title LIKE {p} OR t.title LIKE {p})");
+                match op {
+                    CmpOp::Eq | CmpOp::Like => Ok(cond),
+                    CmpOp::Ne => Ok(format!("NOT {cond}")),
+                    _ => Err(invalid("song only supports =, != and ~")),
+                }
+            }
+            Field::Band => {
+                let (p, cmp) = match op {
+                    CmpOp::Like => (Value::Text(format!("%{value}%")), "LIKE"),
+                    CmpOp::Eq | CmpOp::Ne => {
+                        let canonical = crate::bands::registry().resolve_canonical_name(value);
+                        (Value::Text(canonical), op.sql())
+                    }
+                    _ => return Err(invalid("band only supports =, != and ~")),
+                };
+                let p = self.bind(p);
+                Ok(format!("{BAND_EXPR} {cmp} {p}"))
+            }
+            Field::Date => {
+                let column = "COALESCE(t.parsed_date, t.date, '')";
+                // Partial dates (1977, 1977-05) match as prefixes
+                let partial = value.len() < 10;
+                match op {
+                    CmpOp::Like => {
+                        let p = self.bind(Value::Text(format!("%{value}%")));
+                        Ok(format!("{column} LIKE {p}"))
+                    }
+                    CmpOp::Eq | CmpOp::Ne if partial => {
+                        let p = self.bind(Value::Text(format!("{value}%")));
+                        let not = if op == CmpOp::Ne { "NOT " } else { "" };
+                        Ok(format!("{column} {not}LIKE {p}"))
+                    }
+                    // `date>1977` means after all of 1977: compare against a key that
+                    // sorts after every date with that prefix ('~' > any digit or '-')
+                    CmpOp::Gt | CmpOp::Le if partial => {
+                        let p = self.bind(Value::Text(format!("{value}~")));
+                        Ok(format!("{column} {} {p}", op.sql()))
+                    }
+                    _ => {
+                        let p = self.bind(Value::Text(value.to_string()));
+                        Ok(format!("{column} {} {p}", op.sql()))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn join_parts(mut parts: Vec<String>, sep: &str) -> String {
+    if parts.len() == 1 {
+        parts.pop().unwrap()
+    } else {
+        format!("({})", parts.join(sep))
+    }
+}
+
+/// What a field name resolves to.
+enum Field {
+    /// Numeric SQL expression; `duration` fields accept time-unit suffixes.
+    Numeric { column: String, duration: bool },
+    /// Case-insensitive text column.
+    Text(&'static str),
+    /// Substring match on parsed or tag title.
+    Song,
+    /// Band code or name, resolved through the band registry.
+    Band,
+    /// Show date (full or prefix).
+    Date,
+}
+
+fn resolve_field(name: &str) -> Option<Field> {
+    let score = match name {
+        "energy" | "intensity" | "groove" | "improvisation" | "tightness" | "build_quality"
+        | "exploratory" | "transcendence" | "valence" | "arousal" => Some(name),
+        "improv" => Some("improvisation"),
+        "build" => Some("build_quality"),
+        _ => None,
+    };
+    if let Some(s) = score {
+        return Some(Field::Numeric {
+            column: format!("a.{s}_score"),
+            duration: false,
+        });
+    }
+
+    let field = match name {
+        "duration" | "length" => Field::Numeric {
+            column: "a.duration".to_string(),
+            duration: true,
+        },
+        "tempo" | "bpm" => Field::Numeric {
+            column: "COALESCE(a.tempo_bpm_corrected, a.tempo_bpm)".to_string(),
+            duration: false,
+        },
+        "year" => Field::Numeric {
+            column: "CAST(substr(COALESCE(t.parsed_date, t.date), 1, 4) AS INTEGER)".to_string(),
+            duration: false,
+        },
+        "band" | "artist" => Field::Band,
+        "date" => Field::Date,
+        "song" | "title" => Field::Song,
+        "key" => Field::Text("a.estimated_key"),
+        "type" => Field::Text("t.recording_type"),
+        "venue" => Field::Text("COALESCE(t.parsed_venue, t.venue)"),
+        "format" => Field::Text("t.format"),
+        "quality" => Field::Text("t.data_quality"),
+        "source" => Field::Text("t.source_type"),
+        "taper" => Field::Text("t.taper"),
+        "lineage" => Field::Text("t.lineage_hint"),
+        "plays" => Field::Numeric {
+            column: "COALESCE((SELECT SUM(p.play_count) FROM track_plays p \
+                     WHERE p.track_id = t.id), 0)"
+                .to_string(),
+            duration: false,
+        },
+        "source_rank" => Field::Numeric {
+            column: "CASE t.source_type WHEN 'sbd' THEN 3 WHEN 'matrix' THEN 2 \
+                     WHEN 'fm' THEN 2 WHEN 'aud' THEN 1 ELSE 0 END"
+                .to_string(),
+            duration: false,
+        },
+        _ if name.starts_with("ext.") => {
+            // External analyzer output; only identifiers are inlined
+            let column = &name["ext.".len()..];
+            if !crate::external::is_identifier(column) {
+                return None;
+            }
+            Field::Numeric {
+                column: format!(
+                    "(SELECT e.{column} FROM external_features e WHERE e.track_id = a.track_id)"
+                ),
+                duration: false,
+            }
+        }
+        _ => {
+            // Any numeric column from the schema inventory (names are static, so safe to inline)
+            return ANALYSIS_SCHEMA
+                .iter()
+                .find(|c| c.name == name && (c.sql_type == "REAL" || c.sql_type == "INT"))
+                .map(|c| Field::Numeric {
+                    column: format!("a.{}", c.name),
+                    duration: false,
+                });
+        }
+    };
+    Some(field)
+}
+
+/// SQL expression for a numeric field (scores, tempo, year, schema columns), for use
+/// outside comparisons — e.g. score recipes. `duration` is in minutes here.
+pub(crate) fn numeric_field_sql(name: &str) -> Option<String> {
+    match resolve_field(&name.to_lowercase())? {
+        Field::Numeric {
+            column,
+            duration: true,
+        } => Some(format!("({column} / 60.0)")),
+        Field::Numeric { column, .. } => Some(column),
+        _ => None,
+    }
+}
+
+/// Parse a duration into seconds. Bare numbers are minutes (matching `--min-duration`).
+pub(crate) fn parse_duration_secs(value: &str) -> Option<f64> {
+    let v = value.trim().to_lowercase();
+    let split = v
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(v.len());
+    let (num, unit) = v.split_at(split);
+    let n: f64 = num.parse().ok()?;
+    let mult = match unit {
+        "" | "m" | "min" | "mins" => 60.0,
+        "s" | "sec" | "secs" => 1.0,
+        "h" | "hr" | "hrs" => 3600.0,
+        _ => return None,
+    };
+    Some(n * mult)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Run a compiled filter and return matching tracks.
+    /// Sorted by `sort_column` (descending) if it is a valid score column, else by date.
+    pub fn query_filter(
+        &self,
+        filter: &CompiledFilter,
+        sort_column: Option<&str>,
+        limit: usize,
+        live_only: bool,
+    ) -> crate::db::Result<Vec<TrackScore>> {
+        let order = match sort_column {
+            Some(col) if SCORE_COLUMNS.contains(&col) => format!("a.{col} DESC"),
+            _ => "COALESCE(t.parsed_date, t.date), t.parsed_disc, t.parsed_track".to_string(),
+        };
+        let live_filter = if live_only {
+            format!("AND {LIVE_ONLY}")
+        } else {
+            String::new()
+        };
+
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {NOT_GARBAGE}
+               {live_filter}
+               AND ({where_clause})
+             ORDER BY {order}
+             LIMIT {limit}",
+            where_clause = filter.where_clause,
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(
+                rusqlite::params_from_iter(filter.params.iter()),
+                map_track_score,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Run a compiled filter and return the matching track IDs (unsorted, unlimited).
+    pub fn query_filter_ids(
+        &self,
+        filter: &CompiledFilter,
+        live_only: bool,
+    ) -> crate::db::Result<Vec<i64>> {
+        let live_filter = if live_only {
+            format!("AND {LIVE_ONLY}")
+        } else {
+            String::new()
+        };
+
+        let sql = format!(
+            "SELECT a.track_id
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {NOT_GARBAGE}
+               {live_filter}
+               AND ({where_clause})",
+            where_clause = filter.where_clause,
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(filter.params.iter()), |row| {
+                row.get(0)
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Save (or overwrite) a named filter expression.
+    pub fn save_search(
+        &self,
+        name: &str,
+        expr: &str,
+        sort_column: Option<&str>,
+        all_types: bool,
+    ) -> crate::db::Result<()> {
+        self.conn.execute(
+            "INSERT INTO saved_searches (name, expr, sort_column, all_types)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name) DO UPDATE SET
+                expr = excluded.expr,
+                sort_column = excluded.sort_column,
+                all_types = excluded.all_types,
+                updated_at = datetime('now')",
+            rusqlite::params![name, expr, sort_column, all_types],
+        )?;
+        Ok(())
+    }
+
+    /// Look up a saved search by name.
+    pub fn get_saved_search(&self, name: &str) -> crate::db::Result<Option<SavedSearch>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, expr, sort_column, all_types, updated_at
+             FROM saved_searches WHERE name = ?1",
+        )?;
+        let mut rows = stmt
+            .query_map([name], map_saved_search)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows.pop())
+    }
+
+    /// All saved searches, alphabetically.
+    pub fn list_saved_searches(&self) -> crate::db::Result<Vec<SavedSearch>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, expr, sort_column, all_types, updated_at
+             FROM saved_searches ORDER BY name",
+        )?;
+        let rows = stmt
+            .query_map([], map_saved_search)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Delete a saved search. Returns true if it existed.
+    pub fn delete_saved_search(&self, name: &str) -> crate::db::Result<bool> {
+        let n = self
+            .conn
+            .execute("DELETE FROM saved_searches WHERE name = ?1", [name])?;
+        Ok(n > 0)
+    }
+}
+
+fn map_saved_search(row: &rusqlite::Row) -> rusqlite::Result<SavedSearch> {
+    Ok(SavedSearch {
+        name: row.get(0)?,
+        expr: row.get(1)?,
+        sort_column: row.get(2)?,
+        all_types: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{NewAnalysis, NewTrack};
+    use crate::db::queries::tests::new_track;
+
+    #[test]
+    fn test_compile_single_comparison() {
+        let f = compile("groove>70").unwrap();
+        assert_eq!(f.where_clause, "a.groove_score > ?1");
+        assert_eq!(f.params, vec![Value::Real(70.0)]);
+
+        let f = compile("ext.mood_happy>0.5").unwrap();
+        assert_eq!(
+            f.where_clause,
+            "(SELECT e.mood_happy FROM external_features e WHERE e.track_id = a.track_id) > ?1"
+        );
+    }
+
+    #[test]
+    fn test_compile_duration_units() {
+        let secs = |expr: &str| match compile(expr).unwrap().params[0] {
+            Value::Real(n) => n,
+            _ => panic!("expected real"),
+        };
+        assert_eq!(secs("duration>10min"), 600.0);
+        assert_eq!(secs("duration>10"), 600.0);
+        assert_eq!(secs("duration<90s"), 90.0);
+        assert_eq!(secs("duration>=1h"), 3600.0);
+        assert!(compile("duration>10parsecs").is_err());
+    }
+
+    #[test]
+    fn test_compile_boolean_structure() {
+        let f = compile("groove > 70 and (valence>=60 or not improv<50)").unwrap();
+        assert_eq!(
+            f.where_clause,
+            "(a.groove_score > ?1 AND ((a.valence_score >= ?2 OR NOT (a.improvisation_score < ?3))))"
+        );
+        assert_eq!(f.params.len(), 3);
+    }
+
+    #[test]
+    fn test_compile_band_and_song() {
+        crate::bands::init_default();
+        let f = compile("band=gd and song=\"dark star\"").unwrap();
+        assert_eq!(
+            f.params,
+            vec![
+                Value::Text("Grateful Dead".into()),
+                Value::Text("%dark star%".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_partial_date() {
+        let f = compile("date=1977-05").unwrap();
+        assert!(f.where_clause.contains("LIKE ?1"));
+        assert_eq!(f.params, vec![Value::Text("1977-05%".into())]);
+
+        let f = compile("date>1977").unwrap();
+        assert_eq!(f.params, vec![Value::Text("1977~".into())]);
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert!(matches!(
+            compile("grooviness>3"),
+            Err(QueryError::UnknownField(_))
+        ));
+        assert!(matches!(
+            compile("ext.x;drop>3"),
+            Err(QueryError::UnknownField(_))
+        ));
+        assert!(matches!(compile("groove>"), Err(QueryError::Parse { .. })));
+        assert!(matches!(
+            compile("song=\"dark star"),
+            Err(QueryError::Parse { .. })
+        ));
+        assert!(matches!(
+            compile("groove>70 valence>3"),
+            Err(QueryError::Parse { .. })
+        ));
+        assert!(matches!(
+            compile("groove~high"),
+            Err(QueryError::InvalidValue { .. })
+        ));
+        assert!(compile("").is_err());
+    }
+
+    #[test]
+    fn test_query_filter_against_db() {
+        let db = Database::open_in_memory().unwrap();
+        for (i, groove) in [(1, 80.0), (2, 40.0)] {
+            let id = db
+                .upsert_track(&NewTrack {
+                    parsed_band: Some("Grateful Dead".into()),
+                    parsed_date: Some("1977-05-08".into()),
+                    parsed_disc: Some(1),
+                    parsed_track: Some(i),
+                    parsed_title: Some(format!("Song {i}")),
+                    recording_type: Some("live".into()),
+                    source_type: Some(if i == 1 { "sbd" } else { "aud" }.into()),
+                    ..new_track(&format!("/music/gd77-05-08d1t0{i}.flac"))
+                })
+                .unwrap();
+            db.store_analysis(&NewAnalysis {
+                track_id: id,
+                duration: Some(900.0),
+                groove_score: Some(groove),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        let f = compile("groove>70 and duration>10min and date=1977").unwrap();
+        let rows = db.query_filter(&f, Some("groove_score"), 10, true).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].title, "Song 1");
+
+        let f = compile("source=aud or source_rank>=3").unwrap();
+        assert_eq!(db.query_filter(&f, None, 10, true).unwrap().len(), 2);
+        let f = compile("source_rank<3").unwrap();
+        let rows = db.query_filter(&f, None, 10, true).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].title, "Song 2");
+
+        let f = compile("plays=0").unwrap();
+        assert_eq!(db.query_filter(&f, None, 10, true).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_saved_search_roundtrip() {
+        let db = Database::open_in_memory().unwrap();
+        db.save_search("mellow", "valence>60 and energy<40", None, false)
+            .unwrap();
+        db.save_search("mellow", "valence>65", Some("groove_score"), true)
+            .unwrap();
+        db.save_search("big-jams", "duration>20", None, false)
+            .unwrap();
+
+        let s = db.get_saved_search("mellow").unwrap().unwrap();
+        assert_eq!(s.expr, "valence>65");
+        assert_eq!(s.sort_column.as_deref(), Some("groove_score"));
+        assert!(s.all_types);
+
+        let names: Vec<_> = db
+            .list_saved_searches()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["big-jams", "mellow"]);
+
+        assert!(db.delete_saved_search("mellow").unwrap());
+        assert!(!db.delete_saved_search("mellow").unwrap());
+        assert!(db.get_saved_search("mellow").unwrap().is_none());
+    }
+}
@@ -0,0 +1,376 @@
+//! Re-listen queue: tracks worth going back to, oldest first.
+//!
+//! `queue add` puts tracks on by hand; after `analyze`, new standouts and
+//! outliers are queued automatically. An outlier is a new performance that
+//! leaves the song's other versions behind — on some ranked score it sits
+//! `OUTLIER_SIGMAS` standard deviations above their mean — so it can be worth
+//! hearing even when it doesn't reach the library's top decile. `queue pop`
+//! takes the oldest entries off; `queue list` shows how long each has waited
+//! and can write the queue as a playlist.
+
+use std::collections::HashMap;
+
+use rusqlite::params;
+
+use crate::db::Database;
+use crate::db::columns::{TRACK_SCORE_SELECT, map_track_score, top_filter_sql};
+use crate::db::models::{TopFilter, TrackScore};
+use crate::scores::{Direction, SCORES, ScoreMeta};
+use crate::standouts::Standout;
+
+/// Fewest other versions of a song before a new one can be an outlier.
+const MIN_VERSIONS: usize = 5;
+
+/// Standard deviations above the other versions' mean that make an outlier.
+const OUTLIER_SIGMAS: f64 = 2.5;
+
+/// Smallest spread (score points) assumed among a song's versions, so a song
+/// played near-identically every night doesn't flag a few points' difference.
+const MIN_SPREAD: f64 = 3.0;
+
+/// How a track got onto the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueSource {
+    Manual,
+    Standout,
+    Outlier,
+}
+
+impl QueueSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueSource::Manual => "manual",
+            QueueSource::Standout => "standout",
+            QueueSource::Outlier => "outlier",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "standout" => QueueSource::Standout,
+            "outlier" => QueueSource::Outlier,
+            _ => QueueSource::Manual,
+        }
+    }
+}
+
+/// A queued track.
+#[derive(Debug, Clone)]
+pub struct QueueEntry {
+    pub track_id: i64,
+    pub track: TrackScore,
+    pub source: QueueSource,
+    /// Why it was queued: the note given to `queue add`, or the scores that
+    /// made it a standout or outlier.
+    pub reason: Option<String>,
+    /// Days since it was queued.
+    pub age_days: f64,
+}
+
+/// A newly analyzed track well above the song's other versions.
+#[derive(Debug, Clone)]
+pub struct Outlier {
+    pub track: TrackScore,
+    pub score: &'static ScoreMeta,
+    /// Standard deviations above the other versions' mean.
+    pub sigmas: f64,
+    /// Other versions compared against.
+    pub versions: usize,
+}
+
+/// Tracks analyzed at or after `since` that stand out from their song's other
+/// versions (other shows, same band), biggest margin first. Versions from the
+/// same date are other tapes of the same performance and aren't compared.
+/// Like standouts, none are looked for when most of the library is new.
+pub fn find_outliers(db: &Database, since: &str) -> crate::db::Result<Vec<Outlier>> {
+    let ranked = db.ranked_with_freshness(since)?;
+    if ranked.iter().filter(|(_, fresh)| *fresh).count() * 2 > ranked.len() {
+        return Ok(Vec::new());
+    }
+    let mut songs: HashMap<(String, String), Vec<usize>> = HashMap::new();
+    for (i, (track, _)) in ranked.iter().enumerate() {
+        songs.entry(song_key(track)).or_default().push(i);
+    }
+
+    let mut outliers = Vec::new();
+    for (track, fresh) in &ranked {
+        if !fresh {
+            continue;
+        }
+        let others: Vec<&TrackScore> = songs[&song_key(track)]
+            .iter()
+            .map(|&i| &ranked[i].0)
+            .filter(|o| o.date != track.date)
+            .collect();
+        if others.len() < MIN_VERSIONS {
+            continue;
+        }
+        let best = SCORES
+            .iter()
+            .filter(|s| s.direction != Direction::Bipolar)
+            .filter_map(|s| {
+                let value = track.score(s.column)?;
+                let values: Vec<f64> = others.iter().filter_map(|o| o.score(s.column)).collect();
+                let sigmas = sigmas_above(value, &values)?;
+                Some((s, sigmas))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((score, sigmas)) = best.filter(|(_, sigmas)| *sigmas >= OUTLIER_SIGMAS) {
+            outliers.push(Outlier {
+                track: track.clone(),
+                score,
+                sigmas,
+                versions: others.len(),
+            });
+        }
+    }
+    outliers.sort_by(|a, b| b.sigmas.total_cmp(&a.sigmas));
+    Ok(outliers)
+}
+
+/// Band and base song name, for grouping a song's versions.
+fn song_key(track: &TrackScore) -> (String, String) {
+    (
+        track.band.clone().unwrap_or_default().to_lowercase(),
+        crate::track_groups::song_part(&track.title)
+            .0
+            .to_lowercase(),
+    )
+}
+
+/// How many standard deviations `value` is above the mean of `others`, with
+/// the deviation floored at `MIN_SPREAD`. `None` without enough others.
+fn sigmas_above(value: f64, others: &[f64]) -> Option<f64> {
+    if others.len() < MIN_VERSIONS {
+        return None;
+    }
+    let n = others.len() as f64;
+    let mean = others.iter().sum::<f64>() / n;
+    let var = others.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    Some((value - mean) / var.sqrt().max(MIN_SPREAD))
+}
+
+/// Queue new standouts and outliers after `analyze`. Tracks already queued
+/// keep their entry. Returns the number newly queued.
+pub fn queue_new_finds(
+    db: &Database,
+    standouts: &[Standout],
+    outliers: &[Outlier],
+) -> crate::db::Result<usize> {
+    let mut added = 0;
+    for s in standouts {
+        let reason = s
+            .scores
+            .iter()
+            .map(|m| format!("{} {:.0}", m.label, s.track.score(m.column).unwrap_or(0.0)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if db.queue_add_path(&s.track.file_path, QueueSource::Standout, Some(&reason))? {
+            added += 1;
+        }
+    }
+    for o in outliers {
+        let reason = format!(
+            "{} {:.0}, +{:.1}σ over {} other versions",
+            o.score.label,
+            o.track.score(o.score.column).unwrap_or(0.0),
+            o.sigmas,
+            o.versions
+        );
+        if db.queue_add_path(&o.track.file_path, QueueSource::Outlier, Some(&reason))? {
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Queue a track. Returns false if it was already queued (the existing
+    /// entry is kept).
+    pub fn queue_add(
+        &self,
+        track_id: i64,
+        source: QueueSource,
+        reason: Option<&str>,
+    ) -> crate::db::Result<bool> {
+        let n = self.conn.execute(
+            "INSERT OR IGNORE INTO listen_queue (track_id, source, reason)
+             SELECT id, ?2, ?3 FROM tracks WHERE id = ?1",
+            params![track_id, source.as_str(), reason],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// Queue a track by file path; false if it's unknown or already queued.
+    fn queue_add_path(
+        &self,
+        file_path: &str,
+        source: QueueSource,
+        reason: Option<&str>,
+    ) -> crate::db::Result<bool> {
+        let n = self.conn.execute(
+            "INSERT OR IGNORE INTO listen_queue (track_id, source, reason)
+             SELECT id, ?2, ?3 FROM tracks WHERE file_path = ?1",
+            params![file_path, source.as_str(), reason],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// The queue, oldest first.
+    pub fn queue_list(&self) -> crate::db::Result<Vec<QueueEntry>> {
+        self.queue_entries(None)
+    }
+
+    /// Take the `count` oldest entries off the queue.
+    pub fn queue_pop(&self, count: usize) -> crate::db::Result<Vec<QueueEntry>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let entries = self.queue_entries(Some(count))?;
+        for e in &entries {
+            tx.execute(
+                "DELETE FROM listen_queue WHERE track_id = ?1",
+                params![e.track_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(entries)
+    }
+
+    fn queue_entries(&self, limit: Option<usize>) -> crate::db::Result<Vec<QueueEntry>> {
+        // Manually queued tracks may not be analyzed yet, hence the LEFT JOIN
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT},
+                q.track_id, q.source, q.reason,
+                julianday('now') - julianday(q.added_at)
+             FROM listen_queue q
+             JOIN tracks t ON t.id = q.track_id
+             LEFT JOIN analysis_results a ON a.track_id = t.id
+             ORDER BY q.id
+             LIMIT ?1"
+        );
+        let limit = limit.map_or(-1, |n| n as i64);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let entries = stmt
+            .query_map(params![limit], |row| {
+                Ok(QueueEntry {
+                    track: map_track_score(row)?,
+                    track_id: row.get(19)?,
+                    source: QueueSource::parse(&row.get::<_, String>(20)?),
+                    reason: row.get(21)?,
+                    age_days: row.get(22)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Every ranked track (as `top` sees them), and whether it was analyzed
+    /// at or after `since`.
+    fn ranked_with_freshness(&self, since: &str) -> crate::db::Result<Vec<(TrackScore, bool)>> {
+        let filter = TopFilter {
+            live_only: true,
+            ..Default::default()
+        };
+        let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(since.to_string())];
+        let conditions = top_filter_sql(&filter, &mut params);
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}, a.analyzed_at >= ?1
+             FROM analysis_results a JOIN tracks t ON t.id = a.track_id
+             WHERE {conditions}"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let tracks = stmt
+            .query_map(
+                rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())),
+                |row| Ok((map_track_score(row)?, row.get(19)?)),
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed(db: &Database) {
+        // Nine Dark Stars from different nights: eight old ones with
+        // transcendence 50..57 and a new one (1979) at 90. One new El Paso.
+        db.conn
+            .execute_batch(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 9)
+                 INSERT INTO tracks (file_path, file_size, file_modified, format,
+                                     parsed_title, parsed_date, parsed_band, recording_type)
+                 SELECT '/music/ds' || i || '.flac', i, '0', 'flac',
+                        CASE WHEN i = 9 THEN 'Dark Star ->' ELSE 'Dark Star' END,
+                        '197' || (i % 10) || '-05-08', 'Grateful Dead', 'live'
+                 FROM n;
+                 INSERT INTO tracks (file_path, file_size, file_modified, format,
+                                     parsed_title, parsed_date, parsed_band, recording_type)
+                 VALUES ('/music/other.flac', 1, '0', 'flac', 'El Paso', '1974-05-08',
+                         'Grateful Dead', 'live');
+                 INSERT INTO analysis_results (track_id, transcendence_score, energy_score,
+                                               analyzed_at)
+                 SELECT id, CASE WHEN file_path = '/music/ds9.flac' THEN 90 ELSE 49 + id END,
+                        50, CASE WHEN file_path IN ('/music/ds9.flac', '/music/other.flac')
+                                 THEN '2030-01-01 00:00:00' ELSE '2020-01-01 00:00:00' END
+                 FROM tracks;",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_find_outliers() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db);
+        let outliers = find_outliers(&db, "2025-01-01").unwrap();
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].track.file_path, "/music/ds9.flac");
+        assert_eq!(outliers[0].score.column, "transcendence_score");
+        assert_eq!(outliers[0].versions, 8);
+        assert!(outliers[0].sigmas > 10.0);
+
+        // Nothing new, nothing to flag
+        assert!(find_outliers(&db, "2031-01-01").unwrap().is_empty());
+        assert_eq!(sigmas_above(60.0, &[50.0; 4]), None);
+        assert_eq!(sigmas_above(59.0, &[50.0; 5]), Some(3.0));
+    }
+
+    #[test]
+    fn test_queue_add_list_pop() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db);
+        let outliers = find_outliers(&db, "2025-01-01").unwrap();
+        assert!(
+            db.queue_add(10, QueueSource::Manual, Some("check the jam"))
+                .unwrap()
+        );
+        assert_eq!(queue_new_finds(&db, &[], &outliers).unwrap(), 1);
+        // Already queued: the first entry stays
+        assert!(!db.queue_add(10, QueueSource::Manual, None).unwrap());
+        assert_eq!(queue_new_finds(&db, &[], &outliers).unwrap(), 0);
+        assert!(!db.queue_add(999, QueueSource::Manual, None).unwrap());
+
+        let queue = db.queue_list().unwrap();
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue[0].track.title, "El Paso");
+        assert_eq!(queue[0].source, QueueSource::Manual);
+        assert_eq!(queue[0].reason.as_deref(), Some("check the jam"));
+        assert!(queue[0].age_days < 1.0);
+        assert_eq!(queue[1].source, QueueSource::Outlier);
+        assert!(
+            queue[1]
+                .reason
+                .as_deref()
+                .unwrap()
+                .starts_with("Transcendence 90")
+        );
+
+        let popped = db.queue_pop(1).unwrap();
+        assert_eq!(popped[0].track_id, 10);
+        assert_eq!(db.queue_list().unwrap().len(), 1);
+        assert_eq!(db.queue_pop(5).unwrap().len(), 1);
+        assert!(db.queue_pop(5).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,305 @@
+//! Community ratings from archive.org reviews.
+//!
+//! archive.org items carry an average star rating (1-5) and a review count.
+//! `discover` picks them up with its listing, and `ratings` looks them up for
+//! the items your local shows were matched to (`setlist --notes`), a batch of
+//! identifiers per search request. Ratings are stored per item in
+//! `archive_ratings`; a show's rating pools its items' reviews, so one
+//! much-reviewed soundboard outweighs a rarely heard audience tape.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{Context, Result};
+use rusqlite::params;
+use serde::Deserialize;
+
+use crate::archive_client::ArchiveClient;
+use crate::db::Database;
+
+/// Identifiers per search request.
+const BATCH: usize = 50;
+
+/// One archive.org item's rating. `avg_rating` is `None` for unreviewed items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemRating {
+    pub identifier: String,
+    pub date: Option<String>,
+    pub avg_rating: Option<f64>,
+    pub num_reviews: u32,
+}
+
+/// A show's rating: the review-weighted mean over its rated items.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShowRating {
+    pub stars: f64,
+    pub reviews: u32,
+    /// Items (tapes) with at least one review.
+    pub items: usize,
+}
+
+impl fmt::Display for ShowRating {
+    /// "4.6/5 (23 reviews)"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = if self.reviews == 1 {
+            "review"
+        } else {
+            "reviews"
+        };
+        write!(f, "{:.1}/5 ({} {noun})", self.stars, self.reviews)
+    }
+}
+
+/// Read archive.org's rating fields, which come back as numbers or strings.
+pub(crate) fn parse_rating(
+    avg: Option<&serde_json::Value>,
+    count: Option<&serde_json::Value>,
+) -> (Option<f64>, u32) {
+    let number = |v: Option<&serde_json::Value>| match v? {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    let reviews = number(count).map_or(0, |n| n.max(0.0) as u32);
+    let avg = number(avg).filter(|a| reviews > 0 && (0.0..=5.0).contains(a));
+    (avg, reviews)
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    response: SearchInner,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchInner {
+    docs: Vec<SearchDoc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchDoc {
+    identifier: String,
+    date: Option<String>,
+    avg_rating: Option<serde_json::Value>,
+    num_reviews: Option<serde_json::Value>,
+}
+
+/// What a `ratings` run did.
+#[derive(Debug, Default)]
+pub struct FetchSummary {
+    /// Matched items looked up.
+    pub looked_up: usize,
+    /// Of those, items with at least one review.
+    pub rated: usize,
+    /// Items left for a later run (request failures or budget).
+    pub skipped: usize,
+}
+
+/// Look up ratings for items matched to local shows that have none stored, or
+/// whose rating is older than `ttl_days` (all of them with `refresh`).
+pub fn fetch_matched(
+    db: &Database,
+    client: &ArchiveClient,
+    refresh: bool,
+    ttl_days: i64,
+) -> Result<FetchSummary> {
+    let identifiers = db
+        .identifiers_to_rate(if refresh { None } else { Some(ttl_days) })
+        .context("Failed to query matched shows")?;
+    let mut summary = FetchSummary::default();
+    for batch in identifiers.chunks(BATCH) {
+        if client.budget_exhausted() {
+            summary.skipped += batch.len();
+            continue;
+        }
+        let url = format!(
+            "https://archive.org/advancedsearch.php?\
+             q=identifier%3A%28{}%29&\
+             fl%5B%5D=identifier&fl%5B%5D=date&fl%5B%5D=avg_rating&fl%5B%5D=num_reviews&\
+             rows={}&output=json",
+            batch.join("+OR+"),
+            batch.len()
+        );
+        let resp: SearchResponse = match client.get_json(&url) {
+            Ok(resp) => resp,
+            Err(e) => {
+                log::warn!("Rating lookup failed for {} items: {e}", batch.len());
+                summary.skipped += batch.len();
+                continue;
+            }
+        };
+        let mut found: HashMap<String, ItemRating> = resp
+            .response
+            .docs
+            .into_iter()
+            .map(|doc| {
+                let (avg_rating, num_reviews) =
+                    parse_rating(doc.avg_rating.as_ref(), doc.num_reviews.as_ref());
+                let item = ItemRating {
+                    identifier: doc.identifier.clone(),
+                    date: doc.date.map(|d| d.chars().take(10).collect()),
+                    avg_rating,
+                    num_reviews,
+                };
+                (doc.identifier, item)
+            })
+            .collect();
+        // Items gone from archive.org are stored unrated, so they aren't asked for again
+        let items: Vec<ItemRating> = batch
+            .iter()
+            .map(|id| {
+                found.remove(id).unwrap_or_else(|| ItemRating {
+                    identifier: id.clone(),
+                    date: None,
+                    avg_rating: None,
+                    num_reviews: 0,
+                })
+            })
+            .collect();
+        summary.looked_up += items.len();
+        summary.rated += items.iter().filter(|i| i.avg_rating.is_some()).count();
+        db.store_ratings(&items)
+            .context("Failed to store ratings")?;
+    }
+    Ok(summary)
+}
+
+/// Pool item ratings into a show rating. `None` if no item has reviews.
+pub fn pool(items: impl IntoIterator<Item = (f64, u32)>) -> Option<ShowRating> {
+    let (mut weighted, mut reviews, mut rated) = (0.0, 0u32, 0usize);
+    for (stars, n) in items.into_iter().filter(|&(_, n)| n > 0) {
+        weighted += stars * n as f64;
+        reviews += n;
+        rated += 1;
+    }
+    (reviews > 0).then(|| ShowRating {
+        stars: weighted / reviews as f64,
+        reviews,
+        items: rated,
+    })
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Store (or replace) item ratings.
+    pub fn store_ratings(&self, items: &[ItemRating]) -> crate::db::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO archive_ratings (identifier, date, avg_rating, num_reviews, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, datetime('now'))
+                 ON CONFLICT(identifier) DO UPDATE SET
+                    date = COALESCE(excluded.date, date),
+                    avg_rating = excluded.avg_rating,
+                    num_reviews = excluded.num_reviews,
+                    fetched_at = excluded.fetched_at",
+            )?;
+            for i in items {
+                stmt.execute(params![i.identifier, i.date, i.avg_rating, i.num_reviews])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Items matched to local shows whose rating is missing or, with
+    /// `ttl_days`, older than that. `None` selects every matched item.
+    fn identifiers_to_rate(&self, ttl_days: Option<i64>) -> crate::db::Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT n.identifier FROM show_notes n
+             LEFT JOIN archive_ratings r ON r.identifier = n.identifier
+             WHERE n.local_dir != ''
+               AND (?1 IS NULL OR r.identifier IS NULL
+                    OR r.fetched_at < datetime('now', '-' || ?1 || ' days'))
+             ORDER BY n.identifier",
+        )?;
+        let ids = stmt
+            .query_map(params![ttl_days], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// The pooled rating of every rated archive.org item dated `date`.
+    pub fn rating_for_date(&self, date: &str) -> crate::db::Result<Option<ShowRating>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT avg_rating, num_reviews FROM archive_ratings
+             WHERE date = ?1 AND avg_rating IS NOT NULL",
+        )?;
+        let items = stmt
+            .query_map(params![date], |row| {
+                Ok((row.get::<_, f64>(0)?, row.get::<_, u32>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(pool(items))
+    }
+
+    /// Pooled ratings per date for a cached `discover` listing.
+    pub fn ratings_by_date(
+        &self,
+        collection: &str,
+    ) -> crate::db::Result<HashMap<String, ShowRating>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.date, r.avg_rating, r.num_reviews
+             FROM archive_shows s JOIN archive_ratings r ON r.identifier = s.identifier
+             WHERE s.collection = ?1 AND r.avg_rating IS NOT NULL",
+        )?;
+        let mut by_date: HashMap<String, Vec<(f64, u32)>> = HashMap::new();
+        let rows = stmt.query_map(params![collection], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, u32>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (date, stars, reviews) = row?;
+            by_date.entry(date).or_default().push((stars, reviews));
+        }
+        Ok(by_date
+            .into_iter()
+            .filter_map(|(date, items)| Some((date, pool(items)?)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_pool() {
+        let json: serde_json::Value =
+            serde_json::from_str(r#"{"a": 4.5, "n": "12", "z": 0, "bad": "n/a"}"#).unwrap();
+        assert_eq!(parse_rating(json.get("a"), json.get("n")), (Some(4.5), 12));
+        // An average without reviews is noise
+        assert_eq!(parse_rating(json.get("a"), json.get("z")), (None, 0));
+        assert_eq!(parse_rating(json.get("bad"), None), (None, 0));
+
+        // The heavily reviewed tape dominates
+        let show = pool([(5.0, 30), (3.0, 10), (1.0, 0)]).unwrap();
+        assert!((show.stars - 4.5).abs() < 1e-9);
+        assert_eq!((show.reviews, show.items), (40, 2));
+        assert_eq!(show.to_string(), "4.5/5 (40 reviews)");
+        assert!(pool([]).is_none());
+
+        let db = Database::open_in_memory().unwrap();
+        db.store_ratings(&[
+            ItemRating {
+                identifier: "gd1977-05-08.sbd".into(),
+                date: Some("1977-05-08".into()),
+                avg_rating: Some(4.8),
+                num_reviews: 20,
+            },
+            ItemRating {
+                identifier: "gd1977-05-08.aud".into(),
+                date: Some("1977-05-08".into()),
+                avg_rating: None,
+                num_reviews: 0,
+            },
+        ])
+        .unwrap();
+        let rating = db.rating_for_date("1977-05-08").unwrap().unwrap();
+        assert_eq!((rating.reviews, rating.items), (20, 1));
+        assert!(db.rating_for_date("1977-05-09").unwrap().is_none());
+    }
+}
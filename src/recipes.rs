@@ -0,0 +1,309 @@
+//! Score recipes: user-defined composite scores.
+//!
+//! Recipes are weighted formulas over the jam scores (or any numeric field the
+//! `query` language knows), defined in `config.toml`:
+//!
+//! ```toml
+//! [recipes]
+//! face_melt = "0.5*transcendence + 0.3*intensity + 0.2*build_quality"
+//! slow_burn = "build - 0.5*tempo/10"
+//! ```
+//!
+//! A recipe compiles to a SQL expression and is computed at query time, so it
+//! always reflects the current (rescored) values — nothing to materialize.
+//! Grammar: `+ - * /`, parentheses, unary minus, numbers, and field names.
+
+use crate::db::Database;
+use crate::db::columns::{
+    ANNOUNCER_TALK, PERFORMANCE_SONG, TRACK_SCORE_SELECT, map_track_score, top_filter_sql,
+};
+use crate::db::models::{TopFilter, TrackScore};
+use crate::query::numeric_field_sql;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RecipeError {
+    #[error("Recipe '{name}': parse error at position {pos}: {message}")]
+    Parse {
+        name: String,
+        pos: usize,
+        message: String,
+    },
+    #[error("Recipe '{name}': unknown or non-numeric field '{field}'")]
+    UnknownField { name: String, field: String },
+}
+
+/// A recipe compiled to a SQL expression over `analysis_results a JOIN tracks t`.
+#[derive(Debug, Clone)]
+pub struct Recipe {
+    pub name: String,
+    pub formula: String,
+    pub sql: String,
+}
+
+/// Compile a named recipe formula.
+pub fn compile(name: &str, formula: &str) -> Result<Recipe, RecipeError> {
+    let mut parser = Parser {
+        name,
+        chars: formula.chars().collect(),
+        pos: 0,
+    };
+    let sql = parser.expr()?;
+    parser.skip_ws();
+    if parser.pos < parser.chars.len() {
+        return Err(parser.err("unexpected character"));
+    }
+    Ok(Recipe {
+        name: name.to_string(),
+        formula: formula.to_string(),
+        sql,
+    })
+}
+
+/// Recursive-descent parser emitting SQL directly.
+struct Parser<'a> {
+    name: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn err(&self, message: &str) -> RecipeError {
+        RecipeError::Parse {
+            name: self.name.to_string(),
+            pos: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.chars.get(self.pos).copied()
+    }
+
+    /// expr := term (("+" | "-") term)*
+    fn expr(&mut self) -> Result<String, RecipeError> {
+        let mut out = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            out = format!("{out} {op} {}", self.term()?);
+        }
+        Ok(out)
+    }
+
+    /// term := factor (("*" | "/") factor)*
+    fn term(&mut self) -> Result<String, RecipeError> {
+        let mut out = self.factor()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.factor()?;
+            // NULLIF: dividing by a zero-valued field yields NULL, not an error
+            out = if op == '/' {
+                format!("{out} / NULLIF({rhs}, 0)")
+            } else {
+                format!("{out} * {rhs}")
+            };
+        }
+        Ok(out)
+    }
+
+    /// factor := number | field | "(" expr ")" | "-" factor
+    fn factor(&mut self) -> Result<String, RecipeError> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                if self.peek() != Some(')') {
+                    return Err(self.err("expected ')'"));
+                }
+                self.pos += 1;
+                Ok(format!("({inner})"))
+            }
+            Some('-') => {
+                self.pos += 1;
+                // Parenthesized: a bare "--" would start an SQL comment
+                Ok(format!("(-{})", self.factor()?))
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                let value: f64 = text.parse().map_err(|_| RecipeError::Parse {
+                    name: self.name.to_string(),
+                    pos: start,
+                    message: format!("invalid number '{text}'"),
+                })?;
+                // Debug formatting keeps a decimal point, so SQLite never does integer division
+                Ok(format!("{value:?}"))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    self.pos += 1;
+                }
+                let field: String = self.chars[start..self.pos].iter().collect();
+                let column =
+                    numeric_field_sql(&field).ok_or_else(|| RecipeError::UnknownField {
+                        name: self.name.to_string(),
+                        field: field.clone(),
+                    })?;
+                Ok(format!("COALESCE({column}, 0)"))
+            }
+            Some(_) => Err(self.err("expected a number, field, or '('")),
+            None => Err(self.err("unexpected end of formula")),
+        }
+    }
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Top tracks by a recipe's value, highest first. Mirrors `query_top`'s filters.
+    pub fn query_top_recipe(
+        &self,
+        recipe: &Recipe,
+        limit: usize,
+        filter: &TopFilter,
+    ) -> crate::db::Result<Vec<(TrackScore, f64)>> {
+        let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = vec![];
+        let version = if filter.per_performance {
+            format!(
+                ", ROW_NUMBER() OVER (
+                    PARTITION BY {PERFORMANCE_SONG}
+                    ORDER BY {ANNOUNCER_TALK}, ({expr}) DESC, t.id
+                 ) AS version",
+                expr = recipe.sql
+            )
+        } else {
+            String::new()
+        };
+        let mut sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}, ({expr}) AS recipe_value{version}
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {conditions} AND recipe_value IS NOT NULL",
+            expr = recipe.sql,
+            conditions = top_filter_sql(filter, &mut params_vec)
+        );
+        if filter.per_performance {
+            sql = format!("SELECT * FROM ({sql}) WHERE version = 1");
+        }
+        sql += &format!(
+            " ORDER BY recipe_value DESC LIMIT {limit} OFFSET {}",
+            filter.offset
+        );
+
+        let params_refs: Vec<&dyn rusqlite::types::ToSql> =
+            params_vec.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_refs.as_slice(), |row| {
+                Ok((map_track_score(row)?, row.get(19)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_weighted_sum() {
+        let r = compile(
+            "face_melt",
+            "0.5*transcendence + 0.3*intensity + 0.2*build_quality",
+        )
+        .unwrap();
+        assert_eq!(
+            r.sql,
+            "0.5 * COALESCE(a.transcendence_score, 0) + 0.3 * COALESCE(a.intensity_score, 0) \
+             + 0.2 * COALESCE(a.build_quality_score, 0)"
+        );
+    }
+
+    #[test]
+    fn test_compile_parens_division_and_aliases() {
+        let r = compile("x", "-(improv + 2) / duration").unwrap();
+        assert_eq!(
+            r.sql,
+            "(-(COALESCE(a.improvisation_score, 0) + 2.0)) / NULLIF(COALESCE((a.duration / 60.0), 0), 0)"
+        );
+    }
+
+    #[test]
+    fn test_compile_double_negation() {
+        let r = compile("x", "groove * - -1").unwrap();
+        assert_eq!(r.sql, "COALESCE(a.groove_score, 0) * (-(-1.0))");
+        let db = Database::open_in_memory().unwrap();
+        let value: f64 = db
+            .conn
+            .query_row(
+                &format!("SELECT {}", r.sql.replace("a.groove_score", "2.0")),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(value, 2.0);
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert!(matches!(
+            compile("x", "0.5*vibes"),
+            Err(RecipeError::UnknownField { .. })
+        ));
+        assert!(matches!(
+            compile("x", "groove +"),
+            Err(RecipeError::Parse { .. })
+        ));
+        assert!(matches!(
+            compile("x", "(groove"),
+            Err(RecipeError::Parse { .. })
+        ));
+        assert!(matches!(
+            compile("x", "groove; drop"),
+            Err(RecipeError::Parse { .. })
+        ));
+        // Text fields can't be weighted
+        assert!(matches!(
+            compile("x", "band * 2"),
+            Err(RecipeError::UnknownField { .. })
+        ));
+    }
+
+    #[test]
+    fn test_query_top_recipe_runs() {
+        let db = Database::open_in_memory().unwrap();
+        let r = compile("face_melt", "0.5*transcendence + 0.5*intensity").unwrap();
+        assert!(
+            db.query_top_recipe(
+                &r,
+                10,
+                &TopFilter {
+                    live_only: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+            .is_empty()
+        );
+    }
+}
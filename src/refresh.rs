@@ -0,0 +1,170 @@
+//! `refresh`: the whole recompute sequence, each step only when it's due.
+//!
+//! scan → analyze → rescore → calibrate → similarity → chains. Scanning is
+//! how changed files are found, so it always runs; analysis runs for whatever
+//! `analyze` would pick up. Stored scores are rescored when the formulas
+//! (`jam_metrics::FORMULA_VERSION` and the `[band_scoring]` config) differ
+//! from the last rescore's, and calibrated when the analyses differ from the
+//! last calibration's. Calibration corrects whatever scores are stored, so
+//! `refresh` always rescores right before it calibrates. Similarity and chains
+//! keep their own freshness state (`similarity_state`, `chain_cache_state`).
+//!
+//! `rescore` and `calibrate` record what they ran on in `refresh_state`, so
+//! running them by hand counts too; a rescore drops the calibration record,
+//! since it replaces calibrated scores with raw ones.
+
+use std::collections::BTreeMap;
+
+use rusqlite::{OptionalExtension, params};
+
+use crate::analyzer::jam_metrics::{BandRanges, FORMULA_VERSION};
+use crate::db::Database;
+
+/// Step names, in the order `refresh` runs them.
+pub const STEPS: [&str; 6] = [
+    "scan",
+    "analyze",
+    "rescore",
+    "calibrate",
+    "similarity",
+    "chains",
+];
+
+/// What the scores are computed with: the formula version and the per-band
+/// score ranges from the config.
+pub fn formula_fingerprint(band_scoring: &BTreeMap<String, BandRanges>) -> String {
+    format!("v{FORMULA_VERSION} {band_scoring:?}")
+}
+
+/// Why the stored scores need a rescore (None if they don't).
+pub fn rescore_reason(db: &Database, formulas: &str) -> crate::db::Result<Option<&'static str>> {
+    Ok(match db.refresh_fingerprint("rescore")? {
+        None => Some("no rescore recorded yet"),
+        Some(f) if f != formulas => Some("scoring formulas changed"),
+        Some(_) => None,
+    })
+}
+
+/// Why the scores need calibrating (None if they don't).
+pub fn calibration_reason(
+    db: &Database,
+    source_offsets: bool,
+) -> crate::db::Result<Option<&'static str>> {
+    let current = db.calibration_fingerprint(source_offsets)?;
+    Ok(match db.refresh_fingerprint("calibrate")? {
+        None => Some("not calibrated since the last rescore"),
+        Some(f) if f != current => Some("analyses changed since the last calibration"),
+        Some(_) => None,
+    })
+}
+
+/// Record a rescore with `formulas`; the scores are raw again, so the
+/// calibration record goes.
+pub fn record_rescore(db: &Database, formulas: &str) -> crate::db::Result<()> {
+    db.record_refresh("rescore", formulas)?;
+    db.conn
+        .execute("DELETE FROM refresh_state WHERE step = 'calibrate'", [])?;
+    Ok(())
+}
+
+/// Record a calibration of the current analyses.
+pub fn record_calibration(db: &Database, source_offsets: bool) -> crate::db::Result<()> {
+    let fingerprint = db.calibration_fingerprint(source_offsets)?;
+    db.record_refresh("calibrate", &fingerprint)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// What a step last ran on, if it's recorded.
+    pub fn refresh_fingerprint(&self, step: &str) -> crate::db::Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT fingerprint FROM refresh_state WHERE step = ?1",
+                [step],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn record_refresh(&self, step: &str, fingerprint: &str) -> crate::db::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO refresh_state (step, fingerprint) VALUES (?1, ?2)",
+            params![step, fingerprint],
+        )?;
+        Ok(())
+    }
+
+    /// Cheap fingerprint of what calibration learns from: which tracks are
+    /// analyzed and when, and whether source offsets are applied.
+    fn calibration_fingerprint(&self, source_offsets: bool) -> crate::db::Result<String> {
+        let fp = self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(track_id), 0), COALESCE(MAX(analyzed_at), '')
+             FROM analysis_results",
+            [],
+            |row| {
+                Ok(format!(
+                    "{}|{}|{}",
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?
+                ))
+            },
+        )?;
+        Ok(if source_offsets {
+            format!("{fp}|offsets")
+        } else {
+            fp
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rescore_and_calibration_state() {
+        let db = Database::open_in_memory().unwrap();
+        let formulas = formula_fingerprint(&BTreeMap::new());
+        assert_eq!(
+            rescore_reason(&db, &formulas).unwrap(),
+            Some("no rescore recorded yet")
+        );
+        record_rescore(&db, &formulas).unwrap();
+        assert_eq!(rescore_reason(&db, &formulas).unwrap(), None);
+        let mut ranges = BTreeMap::new();
+        ranges.insert(
+            "built to spill".to_string(),
+            BandRanges::from([("groove".to_string(), [10.0, 60.0])]),
+        );
+        assert_eq!(
+            rescore_reason(&db, &formula_fingerprint(&ranges)).unwrap(),
+            Some("scoring formulas changed")
+        );
+
+        assert!(calibration_reason(&db, false).unwrap().is_some());
+        record_calibration(&db, false).unwrap();
+        assert_eq!(calibration_reason(&db, false).unwrap(), None);
+        assert!(calibration_reason(&db, true).unwrap().is_some());
+
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format)
+                 VALUES (1, '/m/a.flac', 1, '0', 'flac');
+                 INSERT INTO analysis_results (track_id) VALUES (1);",
+            )
+            .unwrap();
+        assert_eq!(
+            calibration_reason(&db, false).unwrap(),
+            Some("analyses changed since the last calibration")
+        );
+        record_calibration(&db, false).unwrap();
+        record_rescore(&db, &formulas).unwrap();
+        assert_eq!(
+            calibration_reason(&db, false).unwrap(),
+            Some("not calibrated since the last rescore")
+        );
+    }
+}
@@ -0,0 +1,493 @@
+//! Remote music sources: scan and analyze a library served over WebDAV (HTTP).
+//!
+//! A scan root like `https://nas.local/dav/music/` is listed with `PROPFIND`
+//! (one directory level per request) and each audio file becomes a track whose
+//! `file_path` is its URL. Tags aren't read for remote files (that would mean
+//! downloading everything at scan time); titles come from path parsing and
+//! `setlist`.
+//!
+//! At analysis time a file is downloaded into `~/.cache/setbreak/remote/`, decoded
+//! from there, and deleted afterwards. Interrupted downloads are kept as `.part`
+//! files and resumed with an HTTP `Range` request.
+//!
+//! For password-protected shares, put the user in the URL
+//! (`https://me@nas.local/dav/music/`) and store the password with
+//! `setbreak auth set webdav` (or `SETBREAK_WEBDAV_KEY`).
+//!
+//! SFTP isn't supported: it needs an SSH client library. Mount the share (sshfs)
+//! or expose it over WebDAV instead.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use directories::ProjectDirs;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::SUPPORTED_EXTENSIONS;
+
+/// Credential store service holding the WebDAV password.
+pub const CREDENTIAL_SERVICE: &str = "webdav";
+
+/// Directory listings deeper than this are not followed.
+const MAX_DEPTH: usize = 16;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<propfind xmlns="DAV:"><prop><resourcetype/><getcontentlength/><getlastmodified/></prop></propfind>"#;
+
+#[derive(Error, Debug)]
+pub enum RemoteError {
+    #[error("HTTP request failed for {url}: {message}")]
+    Http { url: String, message: String },
+    #[error("HTTP {status} from {url}")]
+    Status { url: String, status: u16 },
+    #[error("Invalid remote URL '{0}'")]
+    InvalidUrl(String),
+    #[error("{0}")]
+    Unsupported(String),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Credential error: {0}")]
+    Credentials(#[from] crate::credentials::CredentialError),
+}
+
+/// True for paths that name a remote source rather than a local file.
+pub fn is_remote(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    ["http://", "https://", "sftp://"]
+        .iter()
+        .any(|s| lower.starts_with(s))
+}
+
+/// An audio file found on a remote share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteEntry {
+    /// Full URL, as stored in `tracks.file_path`.
+    pub url: String,
+    /// Decoded path below the scan root (for ignore patterns and path parsing).
+    pub relative: String,
+    pub size: i64,
+    /// Last-modified as Unix seconds, matching local `file_modified`.
+    pub modified: String,
+}
+
+/// A parsed `scheme://[user@]host[:port]/path` URL.
+#[derive(Debug, Clone, PartialEq)]
+struct RemoteUrl {
+    scheme: String,
+    user: Option<String>,
+    host: String,
+    path: String,
+}
+
+impl RemoteUrl {
+    fn parse(url: &str) -> Result<Self, RemoteError> {
+        static URL_RE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^(?i)(https?|sftp)://(?:([^@/]+)@)?([^/]+)(/.*)?$").unwrap()
+        });
+        let caps = URL_RE
+            .captures(url)
+            .ok_or_else(|| RemoteError::InvalidUrl(url.to_string()))?;
+        let scheme = caps[1].to_ascii_lowercase();
+        if scheme == "sftp" {
+            return Err(RemoteError::Unsupported(
+                "SFTP sources aren't supported; mount the share (e.g. sshfs) or serve it over WebDAV"
+                    .to_string(),
+            ));
+        }
+        Ok(Self {
+            scheme,
+            user: caps.get(2).map(|m| percent_decode(m.as_str())),
+            host: caps[3].to_string(),
+            path: caps.get(4).map_or("/", |m| m.as_str()).to_string(),
+        })
+    }
+
+    /// URL to request: no user info (that goes in the Authorization header).
+    fn request_url(&self, path: &str) -> String {
+        format!("{}://{}{}", self.scheme, self.host, path)
+    }
+
+    /// URL to store: keeps the user so later fetches know whom to authenticate as.
+    fn stored_url(&self, path: &str) -> String {
+        match &self.user {
+            Some(u) => format!("{}://{}@{}{}", self.scheme, u, self.host, path),
+            None => self.request_url(path),
+        }
+    }
+}
+
+/// HTTP client for WebDAV shares.
+struct Client {
+    agent: ureq::Agent,
+    auth: Option<String>,
+}
+
+impl Client {
+    fn new(url: &RemoteUrl) -> Result<Self, RemoteError> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .allow_non_standard_methods(true)
+            .http_status_as_error(false)
+            .timeout_connect(Some(Duration::from_secs(30)))
+            .build()
+            .into();
+        let auth = match &url.user {
+            Some(user) => {
+                let password = crate::credentials::CredentialStore::load()?
+                    .get(CREDENTIAL_SERVICE)?
+                    .unwrap_or_default();
+                Some(format!(
+                    "Basic {}",
+                    base64_encode(format!("{user}:{password}").as_bytes())
+                ))
+            }
+            None => None,
+        };
+        Ok(Self { agent, auth })
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::http::request::Builder {
+        let mut builder = ureq::http::Request::builder().method(method).uri(url);
+        if let Some(auth) = &self.auth {
+            builder = builder.header("Authorization", auth);
+        }
+        builder
+    }
+
+    fn send(
+        &self,
+        request: ureq::http::Request<impl ureq::AsSendBody>,
+        url: &str,
+    ) -> Result<ureq::http::Response<ureq::Body>, RemoteError> {
+        let resp = self.agent.run(request).map_err(|e| RemoteError::Http {
+            url: url.to_string(),
+            message: e.to_string(),
+        })?;
+        let status = resp.status().as_u16();
+        if !(200..300).contains(&status) {
+            return Err(RemoteError::Status {
+                url: url.to_string(),
+                status,
+            });
+        }
+        Ok(resp)
+    }
+
+    /// One-level `PROPFIND` of a collection.
+    fn propfind(&self, url: &str) -> Result<String, RemoteError> {
+        let request = self
+            .request("PROPFIND", url)
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml")
+            .body(PROPFIND_BODY)
+            .map_err(|_| RemoteError::InvalidUrl(url.to_string()))?;
+        let mut resp = self.send(request, url)?;
+        resp.body_mut()
+            .with_config()
+            .limit(64 * 1024 * 1024)
+            .read_to_string()
+            .map_err(|e| RemoteError::Http {
+                url: url.to_string(),
+                message: e.to_string(),
+            })
+    }
+}
+
+/// One `<response>` from a PROPFIND multistatus body.
+#[derive(Debug, Clone, PartialEq)]
+struct DavItem {
+    /// Percent-encoded absolute path.
+    href: String,
+    is_dir: bool,
+    size: i64,
+    modified: Option<i64>,
+}
+
+/// Pull items out of a multistatus body. Namespace prefixes vary between servers
+/// (`D:`, `d:`, `lp1:`, none), so elements are matched by local name.
+fn parse_multistatus(xml: &str) -> Vec<DavItem> {
+    static RESPONSE_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?is)<(?:[\w-]+:)?response\b[^>]*>(.*?)</(?:[\w-]+:)?response>").unwrap()
+    });
+    static HREF_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?is)<(?:[\w-]+:)?href\b[^>]*>\s*(.*?)\s*</(?:[\w-]+:)?href>").unwrap()
+    });
+    static COLLECTION_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?i)<(?:[\w-]+:)?collection\s*/?>").unwrap());
+    static LENGTH_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?is)<(?:[\w-]+:)?getcontentlength\b[^>]*>\s*(\d+)\s*<").unwrap()
+    });
+    static MODIFIED_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?is)<(?:[\w-]+:)?getlastmodified\b[^>]*>\s*(.*?)\s*<").unwrap()
+    });
+
+    RESPONSE_RE
+        .captures_iter(xml)
+        .filter_map(|resp| {
+            let body = &resp[1];
+            let href = HREF_RE.captures(body)?[1].replace("&amp;", "&");
+            // Some servers return absolute URLs instead of paths
+            let href = match href.find("://") {
+                Some(i) => href[i + 3..]
+                    .find('/')
+                    .map_or("/".to_string(), |j| href[i + 3 + j..].to_string()),
+                None => href,
+            };
+            Some(DavItem {
+                href,
+                is_dir: COLLECTION_RE.is_match(body),
+                size: LENGTH_RE
+                    .captures(body)
+                    .and_then(|c| c[1].parse().ok())
+                    .unwrap_or(0),
+                modified: MODIFIED_RE.captures(body).and_then(|c| {
+                    chrono::DateTime::parse_from_rfc2822(&c[1])
+                        .ok()
+                        .map(|d| d.timestamp())
+                }),
+            })
+        })
+        .collect()
+}
+
+/// List supported audio files under a remote root, walking subdirectories.
+pub fn list_audio(root: &str) -> Result<Vec<RemoteEntry>, RemoteError> {
+    let url = RemoteUrl::parse(root)?;
+    let client = Client::new(&url)?;
+    let root_path = with_trailing_slash(&url.path);
+    let root_decoded = percent_decode(&root_path);
+
+    let mut entries = Vec::new();
+    let mut queue = vec![(root_path.clone(), 0usize)];
+    while let Some((dir, depth)) = queue.pop() {
+        let xml = client.propfind(&url.request_url(&dir))?;
+        for item in parse_multistatus(&xml) {
+            // The collection itself is listed first
+            if with_trailing_slash(&item.href) == dir {
+                continue;
+            }
+            if item.is_dir {
+                if depth < MAX_DEPTH {
+                    queue.push((with_trailing_slash(&item.href), depth + 1));
+                }
+                continue;
+            }
+            let decoded = percent_decode(&item.href);
+            let ext = Path::new(&decoded)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            if !SUPPORTED_EXTENSIONS.contains(&ext.as_str()) {
+                continue;
+            }
+            entries.push(RemoteEntry {
+                url: url.stored_url(&item.href),
+                relative: decoded
+                    .strip_prefix(&root_decoded)
+                    .unwrap_or(&decoded)
+                    .to_string(),
+                size: item.size,
+                modified: item.modified.map(|t| t.to_string()).unwrap_or_default(),
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.url.cmp(&b.url));
+    Ok(entries)
+}
+
+/// A downloaded copy of a remote file, deleted when dropped.
+pub struct CachedFile {
+    path: PathBuf,
+}
+
+impl CachedFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for CachedFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Where in-flight downloads live: `~/.cache/setbreak/remote/`.
+pub fn cache_dir() -> PathBuf {
+    ProjectDirs::from("", "", crate::APP_NAME)
+        .map(|dirs| dirs.cache_dir().join("remote"))
+        .unwrap_or_else(|| std::env::temp_dir().join("setbreak-remote"))
+}
+
+/// Download a remote file into the cache, resuming a partial download if one exists.
+pub fn fetch(file_url: &str) -> Result<CachedFile, RemoteError> {
+    let url = RemoteUrl::parse(file_url)?;
+    let client = Client::new(&url)?;
+    let request_url = url.request_url(&url.path);
+
+    let dir = cache_dir();
+    std::fs::create_dir_all(&dir)?;
+    let final_path = dir.join(cache_name(file_url));
+    let part_path = final_path.with_extension(format!(
+        "{}.part",
+        final_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+    ));
+
+    let have = std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+    let mut builder = client.request("GET", &request_url);
+    if have > 0 {
+        builder = builder.header("Range", format!("bytes={have}-"));
+    }
+    let request = builder
+        .body(())
+        .map_err(|_| RemoteError::InvalidUrl(file_url.to_string()))?;
+    let mut resp = client.send(request, &request_url)?;
+
+    // 206 = server honored the range: append. 200 = full body: start over.
+    let resumed = resp.status().as_u16() == 206;
+    if resumed {
+        log::debug!("Resuming {file_url} at byte {have}");
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part_path)?;
+    io::copy(&mut resp.body_mut().as_reader(), &mut file)?;
+    file.flush()?;
+    drop(file);
+
+    std::fs::rename(&part_path, &final_path)?;
+    Ok(CachedFile { path: final_path })
+}
+
+/// Cache filename: a hash of the URL plus the original extension (decoders key off it).
+fn cache_name(url: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let ext = Path::new(url.split(['?', '#']).next().unwrap_or(url))
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("bin")
+        .to_lowercase();
+    format!("{:016x}.{ext}", hasher.finish())
+}
+
+fn with_trailing_slash(path: &str) -> String {
+    if path.ends_with('/') {
+        path.to_string()
+    } else {
+        format!("{path}/")
+    }
+}
+
+/// Decode `%XX` escapes (invalid escapes are kept as-is).
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for (i, shift) in [18, 12, 6, 0].iter().enumerate() {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> shift) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let u = RemoteUrl::parse("https://me%40home@nas.local:8443/dav/Grateful%20Dead").unwrap();
+        assert_eq!(u.user.as_deref(), Some("me@home"));
+        assert_eq!(u.host, "nas.local:8443");
+        assert_eq!(u.path, "/dav/Grateful%20Dead");
+        assert_eq!(u.request_url("/x"), "https://nas.local:8443/x");
+        assert!(matches!(
+            RemoteUrl::parse("sftp://nas/music"),
+            Err(RemoteError::Unsupported(_))
+        ));
+        assert!(is_remote("HTTPS://nas/music"));
+        assert!(!is_remote("/mnt/music"));
+    }
+
+    #[test]
+    fn test_parse_multistatus() {
+        let xml = r#"<?xml version="1.0"?>
+<D:multistatus xmlns:D="DAV:">
+  <D:response><D:href>/dav/music/</D:href>
+    <D:propstat><D:prop><D:resourcetype><D:collection/></D:resourcetype></D:prop></D:propstat>
+  </D:response>
+  <D:response><D:href>/dav/music/gd1977-05-08/</D:href>
+    <D:propstat><D:prop><D:resourcetype><D:collection /></D:resourcetype></D:prop></D:propstat>
+  </D:response>
+  <d:response xmlns:d="DAV:"><d:href>https://nas/dav/music/Scarlet%20%26%20Fire.flac</d:href>
+    <d:propstat><d:prop><d:resourcetype/>
+      <lp1:getcontentlength xmlns:lp1="DAV:">1234</lp1:getcontentlength>
+      <lp1:getlastmodified xmlns:lp1="DAV:">Sun, 08 May 1977 22:00:00 GMT</lp1:getlastmodified>
+    </d:prop></d:propstat>
+  </d:response>
+</D:multistatus>"#;
+        let items = parse_multistatus(xml);
+        assert_eq!(items.len(), 3);
+        assert!(items[0].is_dir && items[1].is_dir);
+        assert_eq!(items[2].href, "/dav/music/Scarlet%20%26%20Fire.flac");
+        assert!(!items[2].is_dir);
+        assert_eq!(items[2].size, 1234);
+        assert_eq!(items[2].modified, Some(231976800));
+        assert_eq!(
+            percent_decode(&items[2].href),
+            "/dav/music/Scarlet & Fire.flac"
+        );
+    }
+
+    #[test]
+    fn test_encoding_helpers() {
+        assert_eq!(base64_encode(b"me:secret"), "bWU6c2VjcmV0");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_encode(b"a"), "YQ==");
+        assert_eq!(percent_decode("100%25%20pure%2"), "100% pure%2");
+        let name = cache_name("https://nas/dav/t01.FLAC");
+        assert!(name.ends_with(".flac"));
+        assert_eq!(name, cache_name("https://nas/dav/t01.FLAC"));
+    }
+}
@@ -0,0 +1,386 @@
+//! Anonymized feature + score datasets for sharing (`export-research`).
+//!
+//! Writes `features.csv` (one row per analyzed track: every numeric analysis
+//! column and jam score) and `manifest.json` (what each column means) into an
+//! output directory. Nothing that describes the library itself leaves: no file
+//! paths, database ids, venues, tapers, or exact dates. Tracks, shows, and songs
+//! are identified by salted hashes, so rows from the same show or song still
+//! group together; band and year stay readable since that's what most
+//! questions are asked about. Reusing a salt gives the same identifiers across
+//! exports; the salt itself is never written out.
+
+use crate::db::Database;
+use crate::db::columns::{ANALYSIS_SCHEMA, BAND_EXPR, ColumnDef, LIVE_ONLY, NOT_GARBAGE};
+use crate::titles::csv_field;
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+/// Columns written ahead of the analysis features.
+const ID_COLUMNS: &[(&str, &str)] = &[
+    ("track_id", "Salted hash of the track's file path"),
+    (
+        "show_id",
+        "Salted hash of band and show date; shared by one show's tracks",
+    ),
+    (
+        "song_id",
+        "Salted hash of the normalized song title; shared by every version",
+    ),
+    ("band", "Canonical band name"),
+    ("year", "Year of the show"),
+    (
+        "recording_type",
+        "live, studio, live_album, soundcheck, interview, or unknown",
+    ),
+];
+
+#[derive(Error, Debug)]
+pub enum ResearchError {
+    #[error("Failed to write {path}: {source}")]
+    Write {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+}
+
+/// What goes into the dataset.
+#[derive(Debug, Clone, Default)]
+pub struct ResearchOptions {
+    /// Secret mixed into every identifier hash.
+    pub salt: String,
+    /// Add the song title next to `song_id`.
+    pub titles: bool,
+    /// Only tracks classified as live recordings.
+    pub live_only: bool,
+}
+
+/// Outcome of an export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResearchSummary {
+    pub tracks: usize,
+    pub shows: usize,
+    pub features: usize,
+}
+
+/// One track as it leaves the library.
+struct ResearchRow {
+    track_id: String,
+    show_id: String,
+    song_id: String,
+    song: String,
+    band: String,
+    year: String,
+    recording_type: String,
+    values: Vec<Option<f64>>,
+}
+
+/// Numeric analysis columns, in schema order.
+fn feature_columns() -> Vec<&'static ColumnDef> {
+    ANALYSIS_SCHEMA
+        .iter()
+        .filter(|c| c.sql_type == "REAL" || c.sql_type == "INT")
+        .collect()
+}
+
+/// 16-hex-digit salted FNV-1a hash; fields are NUL-separated so
+/// ("ab", "c") and ("a", "bc") differ.
+fn hash_id(salt: &str, fields: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for field in std::iter::once(salt).chain(fields.iter().copied()) {
+        for b in field.bytes().chain(std::iter::once(0)) {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+/// A salt for one-off exports whose identifiers never need to match another's.
+pub fn random_salt() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    hash_id(&nanos.to_string(), &[&std::process::id().to_string()])
+}
+
+/// Write `features.csv` and `manifest.json` into `dir` (created if missing).
+pub fn export(
+    db: &Database,
+    dir: &Path,
+    options: &ResearchOptions,
+) -> Result<ResearchSummary, ResearchError> {
+    let write_err = |path: &Path| {
+        let path = path.display().to_string();
+        move |source| ResearchError::Write { path, source }
+    };
+    std::fs::create_dir_all(dir).map_err(write_err(dir))?;
+
+    let columns = feature_columns();
+    let rows = db.research_rows(&columns, options)?;
+
+    let csv_path = dir.join("features.csv");
+    let file = std::fs::File::create(&csv_path).map_err(write_err(&csv_path))?;
+    write_csv(
+        &rows,
+        &columns,
+        options.titles,
+        std::io::BufWriter::new(file),
+    )
+    .map_err(write_err(&csv_path))?;
+
+    let mut shows: Vec<&str> = rows.iter().map(|r| r.show_id.as_str()).collect();
+    shows.sort_unstable();
+    shows.dedup();
+    let summary = ResearchSummary {
+        tracks: rows.len(),
+        shows: shows.len(),
+        features: columns.len(),
+    };
+
+    let manifest_path = dir.join("manifest.json");
+    let manifest = manifest(&columns, options, &summary);
+    std::fs::write(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest).unwrap_or_default() + "\n",
+    )
+    .map_err(write_err(&manifest_path))?;
+    Ok(summary)
+}
+
+fn header(columns: &[&ColumnDef], titles: bool) -> Vec<&'static str> {
+    let mut names: Vec<&str> = ID_COLUMNS.iter().map(|(name, _)| *name).collect();
+    if titles {
+        names.insert(3, "song");
+    }
+    names.extend(columns.iter().map(|c| c.name));
+    names
+}
+
+fn write_csv(
+    rows: &[ResearchRow],
+    columns: &[&ColumnDef],
+    titles: bool,
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    writeln!(out, "{}", header(columns, titles).join(","))?;
+    for r in rows {
+        let mut fields = vec![r.track_id.clone(), r.show_id.clone(), r.song_id.clone()];
+        if titles {
+            fields.push(csv_field(&r.song));
+        }
+        fields.push(csv_field(&r.band));
+        fields.push(r.year.clone());
+        fields.push(csv_field(&r.recording_type));
+        fields.extend(
+            r.values
+                .iter()
+                .map(|v| v.map(|v| v.to_string()).unwrap_or_default()),
+        );
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    out.flush()
+}
+
+fn manifest(
+    columns: &[&ColumnDef],
+    options: &ResearchOptions,
+    summary: &ResearchSummary,
+) -> serde_json::Value {
+    let mut entries: Vec<serde_json::Value> = ID_COLUMNS
+        .iter()
+        .map(|(name, description)| {
+            serde_json::json!({
+                "name": name,
+                "type": if *name == "year" { "INT" } else { "TEXT" },
+                "category": "Identity",
+                "description": description,
+            })
+        })
+        .collect();
+    if options.titles {
+        entries.insert(
+            3,
+            serde_json::json!({
+                "name": "song",
+                "type": "TEXT",
+                "category": "Identity",
+                "description": "Song title",
+            }),
+        );
+    }
+    entries.extend(columns.iter().map(|c| {
+        serde_json::json!({
+            "name": c.name,
+            "type": c.sql_type,
+            "category": c.category,
+            "description": c.description,
+        })
+    }));
+    serde_json::json!({
+        "generator": format!("setbreak {}", env!("CARGO_PKG_VERSION")),
+        "created": chrono::Utc::now().format("%Y-%m-%d").to_string(),
+        "tracks": summary.tracks,
+        "shows": summary.shows,
+        "live_only": options.live_only,
+        "identifiers": "track_id, show_id, and song_id are salted 64-bit FNV-1a hashes; \
+                        the salt is not included",
+        "columns": entries,
+        "scores": crate::scores::SCORES
+            .iter()
+            .map(|s| s.column)
+            .collect::<Vec<_>>(),
+    })
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Analyzed, non-garbage tracks with identifiers hashed, ordered by
+    /// `track_id` so row order says nothing about the library's layout.
+    fn research_rows(
+        &self,
+        columns: &[&ColumnDef],
+        options: &ResearchOptions,
+    ) -> crate::db::Result<Vec<ResearchRow>> {
+        // Column names come from ANALYSIS_SCHEMA, never from user input
+        let selected: Vec<String> = columns.iter().map(|c| format!("a.{}", c.name)).collect();
+        let mut filter = NOT_GARBAGE.to_string();
+        if options.live_only {
+            filter += &format!(" AND {LIVE_ONLY}");
+        }
+        let sql = format!(
+            "SELECT t.file_path, {BAND_EXPR}, COALESCE(t.parsed_date, t.date, ''),
+                    COALESCE(t.parsed_title, t.title, ''),
+                    COALESCE(t.recording_type, 'unknown'), {}
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {filter}",
+            selected.join(", ")
+        );
+        let registry = crate::bands::registry();
+        let salt = options.salt.as_str();
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt
+            .query_map([], |row| {
+                let path: String = row.get(0)?;
+                let band: String = row.get(1)?;
+                let date: String = row.get(2)?;
+                let song: String = row.get(3)?;
+                let band = if band.is_empty() {
+                    band
+                } else {
+                    registry.resolve_canonical_name(&band)
+                };
+                let values = (0..columns.len())
+                    .map(|i| row.get(5 + i))
+                    .collect::<rusqlite::Result<Vec<Option<f64>>>>()?;
+                Ok(ResearchRow {
+                    track_id: hash_id(salt, &["track", &path]),
+                    show_id: hash_id(salt, &["show", &band, &date]),
+                    song_id: hash_id(salt, &["song", &crate::scrobbles::normalize_song(&song)]),
+                    year: date
+                        .get(..4)
+                        .filter(|y| y.bytes().all(|b| b.is_ascii_digit()))
+                        .unwrap_or_default()
+                        .to_string(),
+                    song,
+                    band,
+                    recording_type: row.get(4)?,
+                    values,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows.sort_by(|a, b| a.track_id.cmp(&b.track_id));
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{NewAnalysis, NewTrack};
+    use crate::db::queries::tests::new_track;
+
+    fn add_track(db: &Database, path: &str, title: &str) -> i64 {
+        let id = db
+            .upsert_track(&NewTrack {
+                venue: Some("Barton Hall".into()),
+                parsed_band: Some("Grateful Dead".into()),
+                parsed_date: Some("1977-05-08".into()),
+                parsed_title: Some(title.into()),
+                recording_type: Some("live".into()),
+                ..new_track(path)
+            })
+            .unwrap();
+        db.store_analysis(&NewAnalysis {
+            track_id: id,
+            groove_score: Some(72.5),
+            ..NewAnalysis::default()
+        })
+        .unwrap();
+        id
+    }
+
+    #[test]
+    fn test_export_is_anonymized_and_reproducible() {
+        crate::bands::init_default();
+        let db = Database::open_in_memory().unwrap();
+        add_track(
+            &db,
+            "/home/me/music/gd77-05-08/d1t01.flac",
+            "Scarlet Begonias",
+        );
+        add_track(
+            &db,
+            "/home/me/music/gd77-05-08/d1t02.flac",
+            "Fire on the Mountain",
+        );
+        let dir = std::env::temp_dir().join(format!("setbreak-research-{}", std::process::id()));
+        let options = ResearchOptions {
+            salt: "s3cret".into(),
+            ..ResearchOptions::default()
+        };
+
+        let summary = export(&db, &dir, &options).unwrap();
+        assert_eq!((summary.tracks, summary.shows), (2, 1));
+        let csv = std::fs::read_to_string(dir.join("features.csv")).unwrap();
+        for private in [
+            "/home/me",
+            "d1t01",
+            "Barton",
+            "1977-05-08",
+            "Scarlet",
+            "s3cret",
+        ] {
+            assert!(!csv.contains(private), "{private} leaked");
+        }
+        let lines: Vec<&str> = csv.lines().collect();
+        let header: Vec<&str> = lines[0].split(',').collect();
+        let groove = header.iter().position(|c| *c == "groove_score").unwrap();
+        let row: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(row.len(), header.len());
+        assert_eq!(&row[3..5], ["Grateful Dead", "1977"]);
+        assert_eq!(row[groove], "72.5");
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(manifest["columns"].as_array().unwrap().len(), header.len());
+        assert!(!manifest.to_string().contains("s3cret"));
+
+        // Same salt, same identifiers; titles only on request
+        let titled = ResearchOptions {
+            titles: true,
+            ..options
+        };
+        export(&db, &dir, &titled).unwrap();
+        let again = std::fs::read_to_string(dir.join("features.csv")).unwrap();
+        assert!(again.contains("Scarlet Begonias"));
+        assert!(again.contains(row[0]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,608 @@
+//! Retracking: corrected track boundaries for badly tracked shows.
+//!
+//! Some transfers bundle stage banter with the song that follows it, or split
+//! one song across two tracks mid-jam. `retrack <date>` decodes each recording
+//! of the show, reduces its tracks to song-boundary frames
+//! (`analyzer::songsplit`), and searches them as one continuous recording:
+//!
+//! - A song boundary found well inside a track proposes a **split** there.
+//! - A track change with no boundary cue around it — no pause or applause, no
+//!   change of key or texture — proposes a **merge** of the two tracks.
+//!
+//! The corrected boundaries are stored as segments (a start and end point in
+//! the recording's tracks), so nothing on disk changes unless `--apply` cuts
+//! them out with ffmpeg.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rusqlite::params;
+use thiserror::Error;
+
+use crate::analyzer::songsplit::{self, FRAME_SECS, Frame, SplitParams};
+use crate::db::Database;
+use crate::db::models::Track;
+
+/// A track change within this distance of a detected boundary is confirmed by
+/// it; a detected boundary farther from every track change is a split.
+const JOIN_TOLERANCE_SECS: f64 = 8.0;
+
+/// Track changes this close to either end of a recording are kept: the
+/// novelty window doesn't reach there, so a missing cue proves nothing.
+const MERGE_MARGIN_SECS: f64 = 30.0;
+
+/// A piece this short on either side of a split is likely banter or tuning.
+const BANTER_SECS: f64 = 90.0;
+
+#[derive(Error, Debug)]
+pub enum RetrackError {
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ffmpeg not found — required to cut tracks")]
+    FfmpegNotFound,
+    #[error("ffmpeg failed on {path}: {stderr}")]
+    Ffmpeg { path: String, stderr: String },
+    #[error("{0} is on a remote share; only local files can be cut")]
+    Remote(String),
+}
+
+/// Tuning for `propose`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetrackParams {
+    /// Shortest piece a split may leave (seconds).
+    pub min_segment_secs: f64,
+    /// Minimum boundary score (0-1) for a split inside a track.
+    pub threshold: f64,
+    /// Track changes scoring below this, with no break, are merged.
+    pub merge_below: f64,
+}
+
+impl Default for RetrackParams {
+    fn default() -> Self {
+        Self {
+            // Short enough to cut a minute of banter off a song
+            min_segment_secs: 30.0,
+            threshold: 0.6,
+            // A song carrying on across a track change scores near zero; a
+            // change of key between songs scores well above this
+            merge_below: 0.15,
+        }
+    }
+}
+
+/// A track of a recording, in playing order.
+#[derive(Debug, Clone)]
+pub struct RetrackTrack {
+    pub track: Track,
+    pub title: Option<String>,
+    pub duration_secs: f64,
+}
+
+impl RetrackTrack {
+    pub fn display_title(&self) -> &str {
+        self.title.as_deref().unwrap_or("(untitled)")
+    }
+}
+
+/// One recording (source directory) of a show.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub dir: String,
+    pub tracks: Vec<RetrackTrack>,
+}
+
+impl Recording {
+    /// Directory name for display.
+    pub fn name(&self) -> &str {
+        crate::paths::file_name(&self.dir)
+    }
+
+    fn position(&self, track_id: i64) -> usize {
+        self.tracks
+            .iter()
+            .position(|t| t.track.id == track_id)
+            .unwrap_or(0)
+    }
+}
+
+/// A corrected track: from a point in one track to a point in the same or a
+/// later track of the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// 1-based position in the recording.
+    pub segment_index: i32,
+    pub start_track_id: i64,
+    pub start_time: f64,
+    pub end_track_id: i64,
+    pub end_time: f64,
+    /// Strength of the boundary that starts this segment; `None` for the first.
+    pub boundary_score: Option<f64>,
+    /// A pause or applause was heard at the boundary.
+    pub after_break: bool,
+}
+
+/// A change from the original tracking.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// Cut a track in two at `at` seconds.
+    Split {
+        track_id: i64,
+        at: f64,
+        score: Option<f64>,
+        /// One side of the cut is short enough to be banter or tuning.
+        banter: bool,
+    },
+    /// Join two consecutive tracks into one.
+    Merge { first: i64, second: i64 },
+}
+
+/// Where a corrected boundary falls.
+enum Cut {
+    /// At the start of track `n` (an original track change, kept).
+    Join(usize),
+    /// Inside track `n`, at a time in seconds.
+    Within(usize, f64),
+}
+
+/// Propose corrected boundaries for a recording from its tracks' frames
+/// (`frames[i]` belongs to `recording.tracks[i]`).
+pub fn propose(
+    recording: &Recording,
+    frames: Vec<Vec<Frame>>,
+    params: &RetrackParams,
+) -> Vec<Segment> {
+    let tracks = &recording.tracks;
+    if tracks.is_empty() {
+        return Vec::new();
+    }
+    let mut offsets = Vec::with_capacity(frames.len());
+    let mut all: Vec<Frame> = Vec::new();
+    for track_frames in frames {
+        offsets.push(all.len());
+        all.extend(track_frames);
+    }
+    let split = SplitParams {
+        min_song_secs: params.min_segment_secs,
+        threshold: params.threshold,
+    };
+    let boundaries: Vec<(usize, f64, bool)> = songsplit::detect_in_frames(&all, &split)
+        .into_iter()
+        .map(|b| ((b.time / FRAME_SECS).round() as usize, b.score, b.has_break))
+        .collect();
+    let scores = songsplit::boundary_scores(&all);
+    let tolerance = (JOIN_TOLERANCE_SECS / FRAME_SECS) as usize;
+    let margin = (MERGE_MARGIN_SECS / FRAME_SECS) as usize;
+
+    // (frame, cut, score, after break)
+    let mut cuts: Vec<(usize, Cut, Option<f64>, bool)> = Vec::new();
+    for (n, &at) in offsets.iter().enumerate().skip(1) {
+        let near = &scores[at.saturating_sub(tolerance)..(at + tolerance + 1).min(scores.len())];
+        let score = near.iter().map(|&(s, _)| s).fold(0.0, f64::max);
+        let heard_break = near.iter().any(|&(_, b)| b);
+        let confirmed = boundaries
+            .iter()
+            .any(|&(b, _, _)| b.abs_diff(at) <= tolerance);
+        let at_edge = at < margin || at + margin > all.len();
+        if confirmed || heard_break || at_edge || score >= params.merge_below {
+            cuts.push((at, Cut::Join(n), Some(score), heard_break));
+        }
+    }
+    for &(at, score, has_break) in &boundaries {
+        if offsets.iter().skip(1).any(|&o| o.abs_diff(at) <= tolerance) {
+            continue;
+        }
+        let n = offsets.iter().rposition(|&o| o <= at).unwrap_or(0);
+        let time = (at - offsets[n]) as f64 * FRAME_SECS;
+        if time < tracks[n].duration_secs {
+            cuts.push((at, Cut::Within(n, time), Some(score), has_break));
+        }
+    }
+    cuts.sort_by_key(|c| c.0);
+
+    let mut segments = Vec::with_capacity(cuts.len() + 1);
+    let mut start = (0, 0.0, None, false);
+    for (_, cut, score, after_break) in cuts {
+        let (end, next) = match cut {
+            Cut::Join(n) => ((n - 1, tracks[n - 1].duration_secs), (n, 0.0)),
+            Cut::Within(n, time) => ((n, time), (n, time)),
+        };
+        segments.push(segment(tracks, segments.len(), start, end));
+        start = (next.0, next.1, score, after_break);
+    }
+    let last = tracks.len() - 1;
+    segments.push(segment(
+        tracks,
+        segments.len(),
+        start,
+        (last, tracks[last].duration_secs),
+    ));
+    segments
+}
+
+fn segment(
+    tracks: &[RetrackTrack],
+    index: usize,
+    (start, start_time, boundary_score, after_break): (usize, f64, Option<f64>, bool),
+    (end, end_time): (usize, f64),
+) -> Segment {
+    Segment {
+        segment_index: index as i32 + 1,
+        start_track_id: tracks[start].track.id,
+        start_time,
+        end_track_id: tracks[end].track.id,
+        end_time,
+        boundary_score,
+        after_break,
+    }
+}
+
+/// Length of a segment in seconds.
+pub fn segment_duration(recording: &Recording, segment: &Segment) -> f64 {
+    let first = recording.position(segment.start_track_id);
+    let last = recording.position(segment.end_track_id);
+    if first == last {
+        return segment.end_time - segment.start_time;
+    }
+    let middle: f64 = recording.tracks[first + 1..last]
+        .iter()
+        .map(|t| t.duration_secs)
+        .sum();
+    recording.tracks[first].duration_secs - segment.start_time + middle + segment.end_time
+}
+
+/// The splits and merges that turn the original tracks into `segments`.
+pub fn operations(recording: &Recording, segments: &[Segment]) -> Vec<Operation> {
+    let mut ops = Vec::new();
+    for (i, seg) in segments.iter().enumerate() {
+        if i > 0 && seg.start_time > 0.0 {
+            let short = |s: &Segment| segment_duration(recording, s) < BANTER_SECS;
+            ops.push(Operation::Split {
+                track_id: seg.start_track_id,
+                at: seg.start_time,
+                score: seg.boundary_score,
+                banter: short(seg) || short(&segments[i - 1]),
+            });
+        }
+        let first = recording.position(seg.start_track_id);
+        let last = recording.position(seg.end_track_id);
+        for pair in recording.tracks[first..=last].windows(2) {
+            ops.push(Operation::Merge {
+                first: pair[0].track.id,
+                second: pair[1].track.id,
+            });
+        }
+    }
+    ops
+}
+
+/// Cut each segment into `out_dir` as its own file, named `NN - Title.ext`
+/// after the track it starts in. Segments identical to an original track are
+/// copied; the rest are trimmed and joined by ffmpeg (re-encoded, so lossless
+/// formats stay lossless). Returns the written paths.
+pub fn write_segments(
+    recording: &Recording,
+    segments: &[Segment],
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>, RetrackError> {
+    if let Some(t) = recording
+        .tracks
+        .iter()
+        .find(|t| crate::remote::is_remote(&t.track.file_path))
+    {
+        return Err(RetrackError::Remote(t.track.file_path.clone()));
+    }
+    if Command::new("ffmpeg").arg("-version").output().is_err() {
+        return Err(RetrackError::FfmpegNotFound);
+    }
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut written = Vec::with_capacity(segments.len());
+    for seg in segments {
+        let first = recording.position(seg.start_track_id);
+        let last = recording.position(seg.end_track_id);
+        let parts = &recording.tracks[first..=last];
+        let head = &parts[0];
+        let out = out_dir.join(segment_filename(seg, head, &head.track.format));
+
+        let whole = first == last
+            && seg.start_time <= 0.0
+            && seg.end_time >= head.duration_secs - FRAME_SECS;
+        if whole {
+            std::fs::copy(crate::paths::to_fs(&head.track.file_path), &out)?;
+            written.push(out);
+            continue;
+        }
+
+        let mut cmd = Command::new("ffmpeg");
+        cmd.args(["-hide_banner", "-loglevel", "error"]);
+        let mut filter = String::new();
+        for (k, part) in parts.iter().enumerate() {
+            cmd.arg("-i")
+                .arg(crate::paths::to_fs(&part.track.file_path));
+            let mut trim = Vec::new();
+            if k == 0 && seg.start_time > 0.0 {
+                trim.push(format!("start={:.3}", seg.start_time));
+            }
+            if k == parts.len() - 1 {
+                trim.push(format!("end={:.3}", seg.end_time));
+            }
+            let trim = if trim.is_empty() {
+                String::new()
+            } else {
+                format!("atrim={},asetpts=PTS-STARTPTS,", trim.join(":"))
+            };
+            filter += &format!("[{k}:a]{trim}anull[a{k}];");
+        }
+        for k in 0..parts.len() {
+            filter += &format!("[a{k}]");
+        }
+        filter += &format!("concat=n={}:v=0:a=1[out]", parts.len());
+        let output = cmd
+            .args(["-filter_complex", &filter, "-map", "[out]", "-vn", "-y"])
+            .arg(&out)
+            .output()?;
+        if !output.status.success() {
+            return Err(RetrackError::Ffmpeg {
+                path: head.track.file_path.clone(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        written.push(out);
+    }
+    Ok(written)
+}
+
+/// Output filename for a segment: `03 - Title.flac`.
+fn segment_filename(seg: &Segment, head: &RetrackTrack, extension: &str) -> String {
+    let title: String = head
+        .display_title()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            _ => c,
+        })
+        .collect();
+    format!("{:02} - {}.{}", seg.segment_index, title.trim(), extension)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// A show's recordings (one per source directory), tracks in playing order.
+    pub fn retrack_recordings(&self, date: &str) -> crate::db::Result<Vec<Recording>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.file_path, t.format, t.artist, t.parsed_band, t.parsed_date,
+                    COALESCE(t.parsed_title, t.title), COALESCE(t.duration_secs, 0.0)
+             FROM tracks t
+             WHERE COALESCE(t.parsed_date, t.date) = ?1
+             ORDER BY COALESCE(t.parsed_disc, t.disc_number, CAST(t.parsed_set AS INTEGER), 1),
+                      COALESCE(t.parsed_track, t.track_number, 999),
+                      t.file_path",
+        )?;
+        let rows = stmt
+            .query_map(params![date], |row| {
+                Ok(RetrackTrack {
+                    track: Track {
+                        id: row.get(0)?,
+                        file_path: row.get(1)?,
+                        format: row.get(2)?,
+                        artist: row.get(3)?,
+                        parsed_band: row.get(4)?,
+                        parsed_date: row.get(5)?,
+                    },
+                    title: row.get(6)?,
+                    duration_secs: row.get(7)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut recordings: Vec<Recording> = Vec::new();
+        for track in rows {
+            let dir = crate::paths::source_dir(&track.track.file_path)
+                .unwrap_or("")
+                .to_string();
+            match recordings.iter_mut().find(|r| r.dir == dir) {
+                Some(r) => r.tracks.push(track),
+                None => recordings.push(Recording {
+                    dir,
+                    tracks: vec![track],
+                }),
+            }
+        }
+        recordings.sort_by(|a, b| a.dir.cmp(&b.dir));
+        Ok(recordings)
+    }
+
+    /// Stored corrected boundaries for a recording, in order.
+    pub fn retrack_segments(&self, source_dir: &str) -> crate::db::Result<Vec<Segment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT segment_index, start_track_id, start_time, end_track_id, end_time,
+                    boundary_score, after_break
+             FROM retrack_segments WHERE source_dir = ?1
+             ORDER BY segment_index",
+        )?;
+        let rows = stmt
+            .query_map([source_dir], |row| {
+                Ok(Segment {
+                    segment_index: row.get(0)?,
+                    start_track_id: row.get(1)?,
+                    start_time: row.get(2)?,
+                    end_track_id: row.get(3)?,
+                    end_time: row.get(4)?,
+                    boundary_score: row.get(5)?,
+                    after_break: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Replace a recording's corrected boundaries.
+    pub fn replace_retrack_segments(
+        &self,
+        source_dir: &str,
+        segments: &[Segment],
+    ) -> crate::db::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM retrack_segments WHERE source_dir = ?1",
+            [source_dir],
+        )?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO retrack_segments
+                    (source_dir, segment_index, start_track_id, start_time,
+                     end_track_id, end_time, boundary_score, after_break)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for s in segments {
+                stmt.execute(params![
+                    source_dir,
+                    s.segment_index,
+                    s.start_track_id,
+                    s.start_time,
+                    s.end_track_id,
+                    s.end_time,
+                    s.boundary_score,
+                    s.after_break,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: f32 = 4000.0;
+
+    /// A major triad at MIDI `root`, `secs` long.
+    fn chord(root: u32, secs: f64) -> Vec<f32> {
+        let notes = [root, root + 4, root + 7];
+        (0..(secs * SR as f64) as usize)
+            .map(|i| {
+                let t = i as f64 / SR as f64;
+                notes
+                    .iter()
+                    .map(|&n| {
+                        let f = 440.0 * 2f64.powf((n as f64 - 69.0) / 12.0);
+                        (std::f64::consts::TAU * f * t).sin() * 0.08
+                    })
+                    .sum::<f64>() as f32
+            })
+            .collect()
+    }
+
+    fn hiss(secs: f64) -> Vec<f32> {
+        (0..(secs * SR as f64) as usize)
+            .map(|i| ((i * 7919 % 101) as f32 / 101.0 - 0.5) * 0.002)
+            .collect()
+    }
+
+    fn track(id: i64, secs: f64) -> RetrackTrack {
+        RetrackTrack {
+            track: Track {
+                id,
+                file_path: format!("/aud/t{id:02}.flac"),
+                format: "flac".into(),
+                artist: None,
+                parsed_band: None,
+                parsed_date: None,
+            },
+            title: None,
+            duration_secs: secs,
+        }
+    }
+
+    #[test]
+    fn test_split_banter_and_merge_split_song() {
+        // t01: a minute of banter, then a song; t02/t03: one song cut in two
+        let mut t1 = hiss(40.0);
+        t1.extend(chord(60, 200.0));
+        let t2 = chord(62, 150.0);
+        let t3 = chord(62, 150.0);
+        let recording = Recording {
+            dir: "/aud".into(),
+            tracks: vec![track(1, 240.0), track(2, 150.0), track(3, 150.0)],
+        };
+        let frames = [t1, t2, t3]
+            .iter()
+            .map(|a| songsplit::frames(a, SR))
+            .collect();
+
+        let segments = propose(&recording, frames, &RetrackParams::default());
+        assert_eq!(segments.len(), 3, "{segments:?}");
+        assert!(
+            (25.0..=45.0).contains(&segments[1].start_time),
+            "{segments:?}"
+        );
+        assert_eq!(
+            (segments[2].start_track_id, segments[2].end_track_id),
+            (2, 3)
+        );
+        assert_eq!(segments[2].end_time, 150.0);
+
+        let ops = operations(&recording, &segments);
+        assert_eq!(ops.len(), 2, "{ops:?}");
+        assert!(matches!(
+            ops[0],
+            Operation::Split {
+                track_id: 1,
+                banter: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            ops[1],
+            Operation::Merge {
+                first: 2,
+                second: 3
+            }
+        );
+        assert!((segment_duration(&recording, &segments[2]) - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_store_segments() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format, parsed_date,
+                                     parsed_track, parsed_title, duration_secs)
+                 VALUES (1, '/m/gd77/t02.flac', 1, '0', 'flac', '1977-05-08', 2, 'Dark Star', 600),
+                        (2, '/m/gd77/t01.flac', 1, '0', 'flac', '1977-05-08', 1, 'Bertha', 400),
+                        (3, '/m/other/t01.flac', 1, '0', 'flac', '1977-05-08', 1, 'Bertha', 380);",
+            )
+            .unwrap();
+        let recordings = db.retrack_recordings("1977-05-08").unwrap();
+        assert_eq!(recordings.len(), 2);
+        let gd77 = &recordings[0];
+        assert_eq!(gd77.name(), "gd77");
+        let ids: Vec<i64> = gd77.tracks.iter().map(|t| t.track.id).collect();
+        assert_eq!(ids, vec![2, 1]);
+
+        let segments = vec![Segment {
+            segment_index: 1,
+            start_track_id: 2,
+            start_time: 0.0,
+            end_track_id: 1,
+            end_time: 600.0,
+            boundary_score: None,
+            after_break: false,
+        }];
+        db.replace_retrack_segments(&gd77.dir, &segments).unwrap();
+        assert_eq!(db.retrack_segments(&gd77.dir).unwrap(), segments);
+        assert_eq!(
+            operations(gd77, &segments),
+            vec![Operation::Merge {
+                first: 2,
+                second: 1
+            }]
+        );
+    }
+}
@@ -1,3 +1,113 @@
+use crate::db::Database;
+
+/// Title phrases marking an interview or spoken item (matched on whole words).
+const INTERVIEW_PHRASES: &[&str] = &[
+    "interview",
+    "radio spot",
+    "radio promo",
+    "radio ad",
+    "stage announcement",
+    "stage announcements",
+    "spoken word",
+    "dialogue",
+];
+
+/// Title phrases marking a soundcheck (matched on whole words).
+const SOUNDCHECK_PHRASES: &[&str] = &["soundcheck", "sound check", "warm up", "warmup"];
+
+/// Whole titles that are filler rather than songs, and the type each gets.
+/// Non-speech filler (tuning, crowd, breaks) is filed with soundchecks.
+const FILLER_TITLES: &[(&str, &str)] = &[
+    ("banter", "interview"),
+    ("stage banter", "interview"),
+    ("talk", "interview"),
+    ("announcements", "interview"),
+    ("tuning", "soundcheck"),
+    ("tuning crowd", "soundcheck"),
+    ("crowd tuning", "soundcheck"),
+    ("crowd", "soundcheck"),
+    ("crowd noise", "soundcheck"),
+    ("applause", "soundcheck"),
+    ("set break", "soundcheck"),
+    ("setbreak", "soundcheck"),
+    ("encore break", "soundcheck"),
+    ("intro", "soundcheck"),
+    ("intro tape", "soundcheck"),
+    ("outro", "soundcheck"),
+    ("dead air", "soundcheck"),
+    ("silence", "soundcheck"),
+];
+
+/// Speech score (0-1) at which an item counts as spoken, if it also beats music.
+const SPEECH_DOMINANT: f64 = 0.6;
+
+/// Music score (0-1) below which a short item counts as filler.
+const LOW_MUSIC: f64 = 0.2;
+
+/// Low music alone only flags items shorter than this; a long quiet track is
+/// more likely a space jam than tuning.
+const LOW_MUSIC_MAX_SECS: f64 = 240.0;
+
+/// Audio evidence for `classify_filler`, from a track's analysis.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FillerAudio {
+    pub music_score: Option<f64>,
+    pub speech_score: Option<f64>,
+    pub duration_secs: Option<f64>,
+}
+
+/// Tracks marked by a filler pass, by type.
+#[derive(Debug, Default, PartialEq)]
+pub struct FillerCounts {
+    pub soundcheck: usize,
+    pub interview: usize,
+}
+
+/// Classify soundchecks, interviews and filler from the title, then the audio.
+///
+/// Returns "soundcheck" (also tuning, crowd noise, set breaks), "interview"
+/// (also banter and announcements), or `None` for music. Both types fail the
+/// live-only filter, so `top` and `compare` skip them by default.
+pub fn classify_filler(title: Option<&str>, audio: &FillerAudio) -> Option<&'static str> {
+    if let Some(title) = title {
+        let words = normalize_title(title);
+        if let Some(&(_, kind)) = FILLER_TITLES.iter().find(|(t, _)| *t == words) {
+            return Some(kind);
+        }
+        let padded = format!(" {words} ");
+        let has = |phrases: &[&str]| phrases.iter().any(|p| padded.contains(&format!(" {p} ")));
+        if has(INTERVIEW_PHRASES) {
+            return Some("interview");
+        }
+        if has(SOUNDCHECK_PHRASES) {
+            return Some("soundcheck");
+        }
+    }
+
+    let music = audio.music_score?;
+    if audio
+        .speech_score
+        .is_some_and(|speech| speech >= SPEECH_DOMINANT && speech > music)
+    {
+        return Some("interview");
+    }
+    if music < LOW_MUSIC && audio.duration_secs.is_some_and(|d| d < LOW_MUSIC_MAX_SECS) {
+        return Some("soundcheck");
+    }
+    None
+}
+
+/// Lowercase words separated by single spaces, punctuation dropped
+/// ("Tuning/Crowd" → "tuning crowd").
+fn normalize_title(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// Classify a track's recording type based on metadata heuristics.
 ///
 /// Returns one of: "live", "studio", "live_album", "unknown".
@@ -58,9 +168,69 @@ pub fn classify_recording_type(
     "unknown"
 }
 
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Mark soundchecks, interviews and filler across the library (see
+    /// `classify_filler`). Only adds marks: `classify` recomputes every track's
+    /// base type first, which is what clears a mark that no longer applies.
+    /// Returns the library's totals of each type afterwards.
+    pub fn classify_filler(&self) -> crate::db::Result<FillerCounts> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "SELECT t.id, COALESCE(t.parsed_title, t.title), t.recording_type,
+                        a.classification_music_score, a.classification_speech_score,
+                        COALESCE(a.duration, t.duration_secs)
+                 FROM tracks t
+                 LEFT JOIN analysis_results a ON a.track_id = t.id",
+            )?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        FillerAudio {
+                            music_score: row.get(3)?,
+                            speech_score: row.get(4)?,
+                            duration_secs: row.get(5)?,
+                        },
+                    ))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            let mut update = tx.prepare("UPDATE tracks SET recording_type = ?1 WHERE id = ?2")?;
+            for (id, title, current, audio) in rows {
+                let Some(kind) = classify_filler(title.as_deref(), &audio) else {
+                    continue;
+                };
+                if current.as_deref() != Some(kind) {
+                    update.execute(rusqlite::params![kind, id])?;
+                }
+            }
+        }
+        tx.commit()?;
+
+        let count = |kind: &str| -> crate::db::Result<usize> {
+            let n: i64 = self.conn.query_row(
+                "SELECT COUNT(*) FROM tracks WHERE recording_type = ?1",
+                [kind],
+                |row| row.get(0),
+            )?;
+            Ok(n as usize)
+        };
+        Ok(FillerCounts {
+            soundcheck: count("soundcheck")?,
+            interview: count("interview")?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::queries::tests::new_track;
 
     #[test]
     fn tier1_parsed_date_is_live() {
@@ -170,6 +340,105 @@ mod tests {
         );
     }
 
+    #[test]
+    fn filler_from_title() {
+        let none = FillerAudio::default();
+        assert_eq!(
+            classify_filler(Some("Tuning/Crowd"), &none),
+            Some("soundcheck")
+        );
+        assert_eq!(
+            classify_filler(Some("Soundcheck: Jam"), &none),
+            Some("soundcheck")
+        );
+        assert_eq!(
+            classify_filler(Some("Bill Graham Interview"), &none),
+            Some("interview")
+        );
+        assert_eq!(
+            classify_filler(Some("Stage Banter"), &none),
+            Some("interview")
+        );
+        // Whole-title filler words inside a song title don't count
+        assert_eq!(classify_filler(Some("Crowd Pleaser"), &none), None);
+        assert_eq!(classify_filler(Some("Intro > Dark Star"), &none), None);
+        assert_eq!(classify_filler(Some("Dark Star"), &none), None);
+        assert_eq!(classify_filler(None, &none), None);
+    }
+
+    #[test]
+    fn filler_from_audio() {
+        let audio = |music, speech, secs| FillerAudio {
+            music_score: Some(music),
+            speech_score: Some(speech),
+            duration_secs: Some(secs),
+        };
+        assert_eq!(
+            classify_filler(Some("Track 07"), &audio(0.2, 0.8, 600.0)),
+            Some("interview")
+        );
+        assert_eq!(
+            classify_filler(None, &audio(0.1, 0.1, 90.0)),
+            Some("soundcheck")
+        );
+        // A long, quiet space jam is left alone
+        assert_eq!(
+            classify_filler(Some("Space"), &audio(0.1, 0.1, 900.0)),
+            None
+        );
+        assert_eq!(
+            classify_filler(Some("Sugaree"), &audio(0.9, 0.3, 600.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn filler_marks_survive_rescan() {
+        let db = Database::open_in_memory().unwrap();
+        let track = |path: &str, title: &str| crate::db::models::NewTrack {
+            title: Some(title.to_string()),
+            parsed_date: Some("1977-05-08".to_string()),
+            duration_secs: Some(300.0),
+            recording_type: Some("live".to_string()),
+            ..new_track(path)
+        };
+        db.upsert_track(&track("/m/d1t01.flac", "Tuning")).unwrap();
+        let talk = db
+            .upsert_track(&track("/m/d1t02.flac", "Track 02"))
+            .unwrap();
+        db.upsert_track(&track("/m/d1t03.flac", "Bertha")).unwrap();
+        db.conn
+            .execute(
+                "INSERT INTO analysis_results
+                    (track_id, classification_music_score, classification_speech_score)
+                 VALUES (?1, 0.3, 0.9)",
+                [talk],
+            )
+            .unwrap();
+
+        let counts = db.classify_filler().unwrap();
+        assert_eq!(
+            counts,
+            FillerCounts {
+                soundcheck: 1,
+                interview: 1
+            }
+        );
+
+        // A rescan re-derives the type from metadata but keeps the audio mark
+        db.upsert_track(&track("/m/d1t02.flac", "Track 02"))
+            .unwrap();
+        let rtype: String = db
+            .conn
+            .query_row(
+                "SELECT recording_type FROM tracks WHERE id = ?1",
+                [talk],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(rtype, "interview");
+    }
+
     #[test]
     fn tier1_beats_tier5() {
         // Even with a studio album tag, a parsed date wins
@@ -1,3 +1,4 @@
+use crate::dates::{self, DateOrder};
 use regex::Regex;
 use std::path::Path;
 use std::sync::LazyLock;
@@ -21,34 +22,6 @@ fn expand_band_code(code: &str) -> Option<String> {
         .map(|s| s.to_string())
 }
 
-/// Expand a 2-digit year to 4 digits (30-99 → 19xx, 00-29 → 20xx).
-fn expand_year(year: &str) -> String {
-    if year.len() == 2 {
-        let y: u32 = year.parse().unwrap_or(0);
-        if y >= 30 {
-            format!("19{year}")
-        } else {
-            format!("20{year}")
-        }
-    } else {
-        year.to_string()
-    }
-}
-
-/// Basic date validation: month 1-12, day 1-31.
-fn is_valid_date(month: &str, day: &str) -> bool {
-    let m: u32 = month.parse().unwrap_or(0);
-    let d: u32 = day.parse().unwrap_or(0);
-    (1..=12).contains(&m) && (1..=31).contains(&d)
-}
-
-/// Build a normalized YYYY-MM-DD string from components.
-fn build_date(year: &str, month: &str, day: &str) -> String {
-    let m: u32 = month.parse().unwrap_or(0);
-    let d: u32 = day.parse().unwrap_or(0);
-    format!("{}-{m:02}-{d:02}", expand_year(year))
-}
-
 // Pattern 1: Band code + date at start of filename (supports 2-4 digit years)
 // e.g., gd1977-05-08d1t01.shn, gd71-04-18d1t04.mp3
 static BAND_DATE_RE: LazyLock<Regex> = LazyLock::new(|| {
@@ -105,11 +78,6 @@ static PATH_DISC_TRACK_RE: LazyLock<Regex> = LazyLock::new(|| {
 static SET_DIR_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"(?i)^Set\s+(?P<set>I{1,3}|[1-3]|Encore)$").unwrap());
 
-// Pattern 4: Generic date extraction (supports 2-4 digit years)
-static GENERIC_DATE_RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?P<year>\d{2,4})[\.\-/](?P<month>\d{1,2})[\.\-/](?P<day>\d{1,2})").unwrap()
-});
-
 // Generic track number + title: "01 - Title", "01. Title", "23 Good Times"
 // Allows space-only separator when title starts with a letter
 static GENERIC_TRACK_RE: LazyLock<Regex> = LazyLock::new(|| {
@@ -117,7 +85,8 @@ static GENERIC_TRACK_RE: LazyLock<Regex> = LazyLock::new(|| {
 });
 
 /// Parse a file path to extract jam band metadata using a cascade of patterns.
-pub fn parse_path(path: &Path) -> ParsedPath {
+/// `order` reads dates written with the year last.
+pub fn parse_path(path: &Path, order: DateOrder) -> ParsedPath {
     let mut parsed = ParsedPath::default();
     let file_stem = path
         .file_stem()
@@ -131,9 +100,9 @@ pub fn parse_path(path: &Path) -> ParsedPath {
         let month = caps.name("month").unwrap().as_str();
         let day = caps.name("day").unwrap().as_str();
 
-        if is_valid_date(month, day) {
+        if let Some(date) = dates::ymd(year, month, day) {
             parsed.band = expand_band_code(code);
-            parsed.date = Some(build_date(year, month, day));
+            parsed.date = Some(date);
 
             // Extract disc/track/set from remainder of filename
             let remainder = &file_stem[caps.get(0).unwrap().end()..];
@@ -201,7 +170,10 @@ pub fn parse_path(path: &Path) -> ParsedPath {
             let year = caps.name("year").unwrap().as_str();
             let month = caps.name("month").unwrap().as_str();
             let day = caps.name("day").unwrap().as_str();
-            parsed.date = Some(format!("{year}-{month}-{day}"));
+            let Some(date) = dates::ymd(year, month, day) else {
+                continue;
+            };
+            parsed.date = Some(date);
             parsed.venue = Some(caps.name("venue").unwrap().as_str().to_string());
             break;
         }
@@ -224,18 +196,9 @@ pub fn parse_path(path: &Path) -> ParsedPath {
     }
 
     // Pattern 4: Generic fallback
-    // Extract date from any path component (supports 2-4 digit years, validates)
+    // Extract date from any path component (year first or last, validated)
     if parsed.date.is_none() {
-        let full_path = path.to_string_lossy();
-        for caps in GENERIC_DATE_RE.captures_iter(&full_path) {
-            let year = caps.name("year").unwrap().as_str();
-            let month = caps.name("month").unwrap().as_str();
-            let day = caps.name("day").unwrap().as_str();
-            if is_valid_date(month, day) {
-                parsed.date = Some(build_date(year, month, day));
-                break;
-            }
-        }
+        parsed.date = dates::find_date(&path.to_string_lossy(), order);
     }
 
     // Extract track number + title from filename
@@ -267,7 +230,7 @@ mod tests {
     fn test_compact_gd_4digit_year() {
         setup();
         let p = PathBuf::from("gd1977-05-08d1t01.shn");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1977-05-08"));
         assert_eq!(r.disc, Some(1));
@@ -278,7 +241,7 @@ mod tests {
     fn test_compact_gd_2digit_year_70s() {
         setup();
         let p = PathBuf::from("gd71-04-18d1t04.shn");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1971-04-18"));
         assert_eq!(r.disc, Some(1));
@@ -289,7 +252,7 @@ mod tests {
     fn test_compact_gd_2digit_year_80s() {
         setup();
         let p = PathBuf::from("gd80-01-13d2t05.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1980-01-13"));
         assert_eq!(r.disc, Some(2));
@@ -300,7 +263,7 @@ mod tests {
     fn test_compact_gd_2digit_year_90s() {
         setup();
         let p = PathBuf::from("gd93-04-01d1t02.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1993-04-01"));
         assert_eq!(r.disc, Some(1));
@@ -312,7 +275,7 @@ mod tests {
         // gd74-06-23sbd_t26.mp3 — "sbd" source tag, underscore before track
         setup();
         let p = PathBuf::from("gd74-06-23sbd_t26.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1974-06-23"));
         assert_eq!(r.disc, None);
@@ -324,7 +287,7 @@ mod tests {
         // ph2013-12-31.mk5-s2t09.flac — set 2, track 9
         setup();
         let p = PathBuf::from("ph2013-12-31.mk5-s2t09.flac");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Phish"));
         assert_eq!(r.date.as_deref(), Some("2013-12-31"));
         assert_eq!(r.set.as_deref(), Some("2"));
@@ -335,7 +298,7 @@ mod tests {
     fn test_compact_motb_format() {
         setup();
         let p = PathBuf::from("gd1979-10-31.motb.0039.s2t10.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1979-10-31"));
         assert_eq!(r.set.as_deref(), Some("2"));
@@ -347,7 +310,7 @@ mod tests {
         // gd89-10-09Set2T05.shn — "Set" prefix for set, "T" prefix for track
         setup();
         let p = PathBuf::from("gd89-10-09Set2T05.shn");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1989-10-09"));
         assert_eq!(r.set.as_deref(), Some("2"));
@@ -359,7 +322,7 @@ mod tests {
         // d206 = disc 2, track 06 (no t separator)
         setup();
         let p = PathBuf::from("ph1997-11-14d206.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Phish"));
         assert_eq!(r.date.as_deref(), Some("1997-11-14"));
         assert_eq!(r.disc, Some(2));
@@ -370,7 +333,7 @@ mod tests {
     fn test_compact_track_word_spelled_out() {
         setup();
         let p = PathBuf::from("gd71-12-31d2track06.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1971-12-31"));
         assert_eq!(r.disc, Some(2));
@@ -381,7 +344,7 @@ mod tests {
     fn test_compact_tr_prefix() {
         setup();
         let p = PathBuf::from("gd1993-09-24-d1-tr03.wav");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1993-09-24"));
         assert_eq!(r.disc, Some(1));
@@ -392,7 +355,7 @@ mod tests {
     fn test_compact_single_digit_month() {
         setup();
         let p = PathBuf::from("gd71-4-22d1t05.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.date.as_deref(), Some("1971-04-22"));
         assert_eq!(r.disc, Some(1));
         assert_eq!(r.track, Some(5));
@@ -402,7 +365,7 @@ mod tests {
     fn test_compact_phish() {
         setup();
         let p = PathBuf::from("ph1997-11-22t04.flac");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Phish"));
         assert_eq!(r.date.as_deref(), Some("1997-11-22"));
         assert_eq!(r.disc, None);
@@ -413,7 +376,7 @@ mod tests {
     fn test_compact_no_disc_or_track() {
         setup();
         let p = PathBuf::from("gd1972-08-27.shn");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1972-08-27"));
         assert_eq!(r.disc, None);
@@ -424,7 +387,7 @@ mod tests {
     fn test_compact_uppercase() {
         setup();
         let p = PathBuf::from("GD70-02-06d1t01.shn");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1970-02-06"));
         assert_eq!(r.disc, Some(1));
@@ -436,8 +399,8 @@ mod tests {
         // gd08-06-71 — day 71 is invalid, should not match as a date
         setup();
         let p = PathBuf::from("gd08-06-71d2t06_vbr.mp3");
-        let r = parse_path(&p);
-        // BAND_DATE_RE matches but is_valid_date rejects day=71
+        let r = parse_path(&p, DateOrder::Mdy);
+        // BAND_DATE_RE matches but dates::ymd rejects day=71
         // Falls through, no valid date in the filename
         assert!(r.date.is_none());
     }
@@ -449,7 +412,7 @@ mod tests {
         setup();
         let p =
             PathBuf::from("Grateful Dead/1977/1977-05-08 Barton Hall/d1t01 - Scarlet Begonias.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1977-05-08"));
         assert_eq!(r.venue.as_deref(), Some("Barton Hall"));
@@ -462,7 +425,7 @@ mod tests {
     fn test_set_based_phish() {
         setup();
         let p = PathBuf::from("Phish/1997.11.22/Set II/04 - Tweezer.flac");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Phish"));
         assert_eq!(r.date.as_deref(), Some("1997-11-22"));
         assert_eq!(r.set.as_deref(), Some("II"));
@@ -474,7 +437,7 @@ mod tests {
     fn test_set_encore() {
         setup();
         let p = PathBuf::from("Phish/2023.07.14/Set Encore/01 - Tweezer Reprise.flac");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.set.as_deref(), Some("Encore"));
     }
 
@@ -483,7 +446,7 @@ mod tests {
         // grateful_dead directory should match "grateful dead"
         setup();
         let p = PathBuf::from("grateful_dead/some_show/01 - Dark Star.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.track, Some(1));
         assert_eq!(r.title.as_deref(), Some("Dark Star"));
@@ -495,7 +458,7 @@ mod tests {
     fn test_generic_fallback_date() {
         setup();
         let p = PathBuf::from("music/2023.12.31/03 - Midnight Jam.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.date.as_deref(), Some("2023-12-31"));
         assert_eq!(r.track, Some(3));
         assert_eq!(r.title.as_deref(), Some("Midnight Jam"));
@@ -506,7 +469,7 @@ mod tests {
         // Date extracted from directory name with 2-digit year
         setup();
         let p = PathBuf::from("grateful_dead/gd85-11-10/disc207-truckin.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Grateful Dead"));
         assert_eq!(r.date.as_deref(), Some("1985-11-10"));
     }
@@ -515,7 +478,7 @@ mod tests {
     fn test_generic_track_with_dash() {
         setup();
         let p = PathBuf::from("01 - Dark Star.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.track, Some(1));
         assert_eq!(r.title.as_deref(), Some("Dark Star"));
     }
@@ -525,7 +488,7 @@ mod tests {
         // Baker's Dozen style: "23 Good Times Bad Times.flac"
         setup();
         let p = PathBuf::from("23 Good Times Bad Times.flac");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.track, Some(23));
         assert_eq!(r.title.as_deref(), Some("Good Times Bad Times"));
     }
@@ -535,7 +498,7 @@ mod tests {
         // "2_01.mp3" should NOT match as track 2 title "01"
         setup();
         let p = PathBuf::from("2_01.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         // title should be None (no alpha start) or track should handle differently
         assert!(r.title.is_none());
     }
@@ -544,7 +507,7 @@ mod tests {
     fn test_unknown_band() {
         setup();
         let p = PathBuf::from("Random Band/2020-01-15/01 - Song.mp3");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band, None);
         assert_eq!(r.date.as_deref(), Some("2020-01-15"));
         assert_eq!(r.track, Some(1));
@@ -554,7 +517,7 @@ mod tests {
     fn test_band_code_goose() {
         setup();
         let p = PathBuf::from("goose/goose2023-06-10d1t05.flac");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Goose"));
     }
 
@@ -562,7 +525,7 @@ mod tests {
     fn test_compact_goose_full() {
         setup();
         let p = PathBuf::from("goose2023-06-10d1t05.flac");
-        let r = parse_path(&p);
+        let r = parse_path(&p, DateOrder::Mdy);
         assert_eq!(r.band.as_deref(), Some("Goose"));
         assert_eq!(r.date.as_deref(), Some("2023-06-10"));
         assert_eq!(r.disc, Some(1));
@@ -573,25 +536,25 @@ mod tests {
 
     #[test]
     fn test_expand_year() {
-        assert_eq!(expand_year("71"), "1971");
-        assert_eq!(expand_year("99"), "1999");
-        assert_eq!(expand_year("68"), "1968");
-        assert_eq!(expand_year("30"), "1930");
-        assert_eq!(expand_year("00"), "2000");
-        assert_eq!(expand_year("25"), "2025");
-        assert_eq!(expand_year("29"), "2029");
-        assert_eq!(expand_year("1977"), "1977");
-        assert_eq!(expand_year("2023"), "2023");
+        assert_eq!(dates::expand_year(71), 1971);
+        assert_eq!(dates::expand_year(99), 1999);
+        assert_eq!(dates::expand_year(68), 1968);
+        assert_eq!(dates::expand_year(30), 1930);
+        assert_eq!(dates::expand_year(0), 2000);
+        assert_eq!(dates::expand_year(25), 2025);
+        assert_eq!(dates::ymd("1977", "5", "8").as_deref(), Some("1977-05-08"));
+        assert_eq!(dates::ymd("2023", "5", "8").as_deref(), Some("2023-05-08"));
     }
 
     #[test]
     fn test_date_validation() {
-        assert!(is_valid_date("01", "01"));
-        assert!(is_valid_date("12", "31"));
-        assert!(is_valid_date("6", "8"));
-        assert!(!is_valid_date("13", "01"));
-        assert!(!is_valid_date("00", "15"));
-        assert!(!is_valid_date("06", "32"));
-        assert!(!is_valid_date("06", "71"));
+        assert!(dates::ymd("77", "01", "01").is_some());
+        assert!(dates::ymd("77", "12", "31").is_some());
+        assert!(dates::ymd("77", "6", "8").is_some());
+        assert!(dates::ymd("77", "13", "01").is_none());
+        assert!(dates::ymd("77", "00", "15").is_none());
+        assert!(dates::ymd("77", "06", "32").is_none());
+        assert!(dates::ymd("77", "06", "71").is_none());
+        assert!(dates::ymd("77", "02", "30").is_none());
     }
 }
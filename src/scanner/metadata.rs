@@ -3,6 +3,7 @@ use lofty::prelude::*;
 use std::path::Path;
 
 /// Tags extracted from audio file metadata.
+#[derive(Default)]
 pub struct TagInfo {
     pub title: Option<String>,
     pub artist: Option<String>,
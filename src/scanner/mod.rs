@@ -1,12 +1,17 @@
 pub mod classify;
 pub mod filename;
 pub mod metadata;
+pub mod reparse;
+pub mod source;
 
 use crate::SUPPORTED_EXTENSIONS;
+use crate::config::{AppConfig, CollectionConfig, MetadataPrecedence};
+use crate::dates::DateOrder;
 use crate::db::Database;
 use crate::db::models::NewTrack;
-use indicatif::{ProgressBar, ProgressStyle};
-use std::path::Path;
+use crate::progress::Progress;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 use walkdir::WalkDir;
 
@@ -18,6 +23,8 @@ pub enum ScanError {
     Db(#[from] crate::db::DbError),
     #[error("Metadata error for {path}: {message}")]
     Metadata { path: String, message: String },
+    #[error("Remote source error: {0}")]
+    Remote(#[from] crate::remote::RemoteError),
 }
 
 pub struct ScanResult {
@@ -29,45 +36,29 @@ pub struct ScanResult {
 }
 
 /// Scan directories for audio files and insert/update tracks in the database.
+///
+/// Each path is a collection root: its `.setbreak.toml` (layered over the global
+/// `[collection]` settings) can skip files and set band / recording type defaults.
+/// Roots given as `http(s)://` URLs are listed over WebDAV (see [`crate::remote`]).
 pub fn scan(
     db: &Database,
     paths: &[String],
     force: bool,
+    config: &AppConfig,
+    progress: &dyn Progress,
 ) -> std::result::Result<ScanResult, ScanError> {
-    // First pass: collect all audio file paths
-    let mut audio_files: Vec<walkdir::DirEntry> = Vec::new();
+    let collections: Vec<CollectionConfig> = paths
+        .iter()
+        .map(|p| config.collection_for(&crate::paths::to_fs(p)))
+        .collect();
 
-    for path in paths {
-        for entry in WalkDir::new(path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if !entry.file_type().is_file() {
-                continue;
-            }
-            let ext = entry
-                .path()
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-            if SUPPORTED_EXTENSIONS.contains(&ext.as_str()) {
-                audio_files.push(entry);
-            }
-        }
-    }
+    // First pass: collect all audio file paths
+    let audio_files = collect_audio_files(paths, &collections);
+    let remote_files = collect_remote_files(paths, &collections)?;
 
-    let total = audio_files.len() as u64;
-    let pb = ProgressBar::new(total);
-    pb.set_style(
-        ProgressStyle::with_template(
-            "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({per_sec}) ({eta}) {msg}",
-        )
-        .unwrap()
-        .progress_chars("#>-"),
-    );
-    pb.set_message("Scanning...");
+    let total = (audio_files.len() + remote_files.len()) as u64;
+    progress.start(total, "files");
+    progress.set_message("Scanning...");
 
     let mut result = ScanResult {
         scanned: 0,
@@ -83,11 +74,11 @@ pub fn scan(
         .unchecked_transaction()
         .map_err(crate::db::DbError::from)?;
 
-    for entry in &audio_files {
+    for (entry, idx) in &audio_files {
         let path = entry.path();
         result.scanned += 1;
 
-        match process_file(&tx, path, force) {
+        match process_file(&tx, path, force, &collections[*idx]) {
             Ok(FileAction::New) => result.new += 1,
             Ok(FileAction::Updated) => result.updated += 1,
             Ok(FileAction::Skipped) => result.skipped += 1,
@@ -97,41 +88,234 @@ pub fn scan(
             }
         }
 
-        pb.inc(1);
+        progress.inc(1);
+    }
+
+    for (entry, idx) in &remote_files {
+        result.scanned += 1;
+
+        match process_remote(&tx, entry, force, &collections[*idx]) {
+            Ok(FileAction::New) => result.new += 1,
+            Ok(FileAction::Updated) => result.updated += 1,
+            Ok(FileAction::Skipped) => result.skipped += 1,
+            Err(e) => {
+                log::warn!("Error scanning {}: {}", entry.url, e);
+                result.errors += 1;
+            }
+        }
+
+        progress.inc(1);
     }
 
     tx.commit().map_err(crate::db::DbError::from)?;
 
-    pb.finish_with_message(format!(
+    progress.finish(&format!(
         "Done: {} new, {} updated, {} skipped, {} errors",
         result.new, result.updated, result.skipped, result.errors
     ));
 
+    if let Err(e) = db.record_snapshot("scan", result.new as i64) {
+        log::warn!("Failed to record library snapshot: {e}");
+    }
+    // Rescanned files come back with their tag spelling
+    if result.new + result.updated > 0 {
+        if let Err(e) = db.apply_title_aliases() {
+            log::warn!("Failed to apply title aliases: {e}");
+        }
+    }
+
     Ok(result)
 }
 
+/// Add/update/skip counts for one group of files in a scan preview.
+#[derive(Debug, Default, Clone)]
+pub struct ActionCounts {
+    pub new: u64,
+    pub updated: u64,
+    pub skipped: u64,
+}
+
+impl ActionCounts {
+    pub fn total(&self) -> u64 {
+        self.new + self.updated + self.skipped
+    }
+
+    fn record(&mut self, action: &FileAction) {
+        match action {
+            FileAction::New => self.new += 1,
+            FileAction::Updated => self.updated += 1,
+            FileAction::Skipped => self.skipped += 1,
+        }
+    }
+}
+
+/// What a scan would do, without touching the database.
+#[derive(Debug, Default)]
+pub struct ScanPreview {
+    pub totals: ActionCounts,
+    pub errors: u64,
+    /// Counts per parsed band ("(unknown)" when the path parser found none).
+    pub by_band: BTreeMap<String, ActionCounts>,
+    /// Counts per file extension.
+    pub by_format: BTreeMap<String, ActionCounts>,
+    /// Files whose path yielded no show date.
+    pub undated: u64,
+    /// A few undated paths, to check the parser against.
+    pub undated_examples: Vec<String>,
+}
+
+/// Undated example paths kept in a preview.
+const PREVIEW_EXAMPLES: usize = 10;
+
+/// Walk the directories and report what `scan` would add, update, or skip,
+/// broken down by band and format. Read-only: tags aren't read and nothing is written.
+pub fn preview(
+    db: &Database,
+    paths: &[String],
+    force: bool,
+    config: &AppConfig,
+) -> std::result::Result<ScanPreview, ScanError> {
+    let collections: Vec<CollectionConfig> = paths
+        .iter()
+        .map(|p| config.collection_for(&crate::paths::to_fs(p)))
+        .collect();
+    let audio_files = collect_audio_files(paths, &collections);
+    let remote_files = collect_remote_files(paths, &collections)?;
+
+    let local = audio_files.iter().map(|(entry, idx)| {
+        let path = entry.path();
+        let action = local_stat(path)
+            .map_err(ScanError::from)
+            .and_then(|(size, mtime)| {
+                check_existing(&db.conn, &crate::paths::to_db(path), size, &mtime, force)
+            });
+        (path.to_path_buf(), path.display().to_string(), action, *idx)
+    });
+    let remote = remote_files.iter().map(|(entry, idx)| {
+        let action = check_existing(&db.conn, &entry.url, entry.size, &entry.modified, force);
+        (
+            PathBuf::from(&entry.relative),
+            entry.url.clone(),
+            action,
+            *idx,
+        )
+    });
+
+    let mut preview = ScanPreview::default();
+    for (path, display, action, idx) in local.chain(remote) {
+        let path = path.as_path();
+        let action = match action {
+            Ok(action) => action,
+            Err(e) => {
+                log::warn!("Error checking {}: {}", display, e);
+                preview.errors += 1;
+                continue;
+            }
+        };
+
+        let parsed = parse_with_defaults(path, &metadata::TagInfo::default(), &collections[idx]);
+        let band = parsed.band.unwrap_or_else(|| "(unknown)".to_string());
+        let format = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        preview.totals.record(&action);
+        preview.by_band.entry(band).or_default().record(&action);
+        preview.by_format.entry(format).or_default().record(&action);
+        if parsed.date.is_none() {
+            preview.undated += 1;
+            if preview.undated_examples.len() < PREVIEW_EXAMPLES {
+                preview.undated_examples.push(display);
+            }
+        }
+    }
+    Ok(preview)
+}
+
+/// Collect supported audio files under each root, minus the collection's ignore
+/// patterns. Returns each file with the index of its root.
+fn collect_audio_files(
+    paths: &[String],
+    collections: &[CollectionConfig],
+) -> Vec<(walkdir::DirEntry, usize)> {
+    let mut audio_files = Vec::new();
+
+    for (idx, path) in paths.iter().enumerate() {
+        if crate::remote::is_remote(path) {
+            continue;
+        }
+        for entry in WalkDir::new(crate::paths::to_fs(path))
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let ext = entry
+                .path()
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            if !SUPPORTED_EXTENSIONS.contains(&ext.as_str()) {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+            if collections[idx].is_ignored(relative) {
+                log::debug!("Ignored by collection config: {}", entry.path().display());
+                continue;
+            }
+            audio_files.push((entry, idx));
+        }
+    }
+
+    audio_files
+}
+
+/// List audio files under each remote root, minus the collection's ignore patterns.
+fn collect_remote_files(
+    paths: &[String],
+    collections: &[CollectionConfig],
+) -> std::result::Result<Vec<(crate::remote::RemoteEntry, usize)>, ScanError> {
+    let mut files = Vec::new();
+    for (idx, path) in paths.iter().enumerate() {
+        if !crate::remote::is_remote(path) {
+            continue;
+        }
+        for entry in crate::remote::list_audio(path)? {
+            if collections[idx].is_ignored(Path::new(&entry.relative)) {
+                log::debug!("Ignored by collection config: {}", entry.url);
+                continue;
+            }
+            files.push((entry, idx));
+        }
+    }
+    Ok(files)
+}
+
 enum FileAction {
     New,
     Updated,
     Skipped,
 }
 
-fn process_file(
+/// A local file's size and mtime, as stored in the tracks table.
+fn local_stat(path: &Path) -> std::io::Result<(i64, String)> {
+    let meta = std::fs::metadata(path)?;
+    Ok((meta.len() as i64, format_mtime(&meta)))
+}
+
+/// Decide what a scan should do with a file, given its current size and mtime.
+fn check_existing(
     conn: &rusqlite::Connection,
-    path: &Path,
+    file_path: &str,
+    file_size: i64,
+    file_modified: &str,
     force: bool,
 ) -> std::result::Result<FileAction, ScanError> {
-    let meta = std::fs::metadata(path)?;
-    let file_size = meta.len() as i64;
-    let file_modified = format_mtime(&meta);
-    let file_path = path.to_string_lossy().to_string();
-
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-
     // Single query: check if track exists AND if it's unchanged
     let existing: Option<(i64, String)> = conn
         .query_row(
@@ -141,30 +325,147 @@ fn process_file(
         )
         .ok();
 
-    let is_new = existing.is_none();
-
     // Skip if unchanged and not forced
-    if !force {
-        if let Some((size, mtime)) = &existing {
-            if *size == file_size && *mtime == file_modified {
-                return Ok(FileAction::Skipped);
-            }
+    let action = match &existing {
+        None => FileAction::New,
+        Some((size, mtime)) if !force && *size == file_size && *mtime == file_modified => {
+            FileAction::Skipped
         }
+        Some(_) => FileAction::Updated,
+    };
+    Ok(action)
+}
+
+/// Parse filename/path for jam band metadata, letting tags win where the
+/// collection prefers them, then falling back to the collection's band.
+pub(crate) fn parse_with_defaults(
+    path: &Path,
+    tags: &metadata::TagInfo,
+    collection: &CollectionConfig,
+) -> filename::ParsedPath {
+    let order = collection.date_order.unwrap_or_default();
+    let mut parsed = filename::parse_path(path, order);
+    if collection.prefer == Some(MetadataPrecedence::Tags) {
+        parsed = prefer_tags(parsed, tags, order);
+    }
+    if parsed.band.is_none() {
+        parsed.band = collection
+            .band
+            .as_deref()
+            .map(|b| crate::bands::registry().resolve_canonical_name(b));
+    }
+    parsed.title = parsed
+        .title
+        .map(|t| crate::title_normalize::normalize(&t))
+        .filter(|t| !t.is_empty());
+    parsed
+}
+
+/// Tag values over path-parsed ones. A tag date only counts if it's a full
+/// date; a bare year would be worse than the path's.
+fn prefer_tags(
+    parsed: filename::ParsedPath,
+    tags: &metadata::TagInfo,
+    order: DateOrder,
+) -> filename::ParsedPath {
+    fn tag(value: &Option<String>) -> Option<&str> {
+        value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+    }
+    filename::ParsedPath {
+        band: tag(&tags.artist)
+            .map(|a| crate::bands::registry().resolve_canonical_name(a))
+            .or(parsed.band),
+        date: tag(&tags.date)
+            .and_then(|d| crate::dates::find_date(d, order))
+            .or(parsed.date),
+        venue: tag(&tags.venue).map(str::to_string).or(parsed.venue),
+        disc: tags.disc_number.or(parsed.disc),
+        track: tags.track_number.or(parsed.track),
+        set: parsed.set,
+        title: tag(&tags.title).map(str::to_string).or(parsed.title),
+    }
+}
+
+fn process_file(
+    conn: &rusqlite::Connection,
+    path: &Path,
+    force: bool,
+    collection: &CollectionConfig,
+) -> std::result::Result<FileAction, ScanError> {
+    let file_path = crate::paths::to_db(path);
+    let (file_size, file_modified) = local_stat(path)?;
+    let action = check_existing(conn, &file_path, file_size, &file_modified, force)?;
+    if let FileAction::Skipped = action {
+        return Ok(action);
     }
 
     // Read tags
     let tags = metadata::read_tags(path);
 
-    // Parse filename/path for jam band metadata
-    let parsed = filename::parse_path(path);
+    let track = build_track(path, file_path, file_size, file_modified, tags, collection);
+    upsert_track(conn, &track)?;
+    Ok(action)
+}
 
-    let recording_type = classify::classify_recording_type(
-        &file_path,
-        parsed.date.as_deref(),
-        tags.album.as_deref(),
+/// Add or update a track for a file on a remote share. Tags aren't read (that
+/// would mean downloading the file); metadata comes from the path alone.
+fn process_remote(
+    conn: &rusqlite::Connection,
+    entry: &crate::remote::RemoteEntry,
+    force: bool,
+    collection: &CollectionConfig,
+) -> std::result::Result<FileAction, ScanError> {
+    let action = check_existing(conn, &entry.url, entry.size, &entry.modified, force)?;
+    if let FileAction::Skipped = action {
+        return Ok(action);
+    }
+    let track = build_track(
+        Path::new(&entry.relative),
+        entry.url.clone(),
+        entry.size,
+        entry.modified.clone(),
+        metadata::TagInfo::default(),
+        collection,
     );
+    upsert_track(conn, &track)?;
+    Ok(action)
+}
+
+/// Combine tags and path parsing into a track row. `path` is what gets parsed
+/// (the local path, or a remote file's path below its root).
+fn build_track(
+    path: &Path,
+    file_path: String,
+    file_size: i64,
+    file_modified: String,
+    tags: metadata::TagInfo,
+    collection: &CollectionConfig,
+) -> NewTrack {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    // Parse filename/path for jam band metadata
+    let parsed = parse_with_defaults(path, &tags, collection);
+
+    let title = parsed.title.as_deref().or(tags.title.as_deref());
+    let recording_type = match &collection.recording_type {
+        Some(rt) => rt.clone(),
+        None => classify::classify_filler(title, &classify::FillerAudio::default())
+            .unwrap_or_else(|| {
+                classify::classify_recording_type(
+                    &path.to_string_lossy(),
+                    parsed.date.as_deref(),
+                    tags.album.as_deref(),
+                )
+            })
+            .to_string(),
+    };
+    let source = source::parse_source(path);
 
-    let new_track = NewTrack {
+    NewTrack {
         file_path,
         file_size,
         file_modified,
@@ -186,9 +487,17 @@ fn process_file(
         parsed_set: parsed.set,
         parsed_title: parsed.title,
         duration_secs: tags.duration_secs,
-        recording_type: Some(recording_type.to_string()),
-    };
+        recording_type: Some(recording_type),
+        source_type: source.source_type.map(str::to_string),
+        taper: source.taper,
+        lineage_hint: source.lineage_hint,
+    }
+}
 
+fn upsert_track(
+    conn: &rusqlite::Connection,
+    new_track: &NewTrack,
+) -> std::result::Result<(), ScanError> {
     // Use the transaction connection directly
     conn.execute(
         "INSERT INTO tracks (
@@ -197,14 +506,14 @@ fn process_file(
             set_name, venue, comment,
             parsed_band, parsed_date, parsed_venue, parsed_disc,
             parsed_track, parsed_set, parsed_title, duration_secs,
-            recording_type, updated_at
+            recording_type, source_type, taper, lineage_hint, updated_at
         ) VALUES (
             ?1, ?2, ?3, ?4,
             ?5, ?6, ?7, ?8, ?9, ?10,
             ?11, ?12, ?13,
             ?14, ?15, ?16, ?17,
             ?18, ?19, ?20, ?21,
-            ?22, datetime('now')
+            ?22, ?23, ?24, ?25, datetime('now')
         )
         ON CONFLICT(file_path) DO UPDATE SET
             file_size = excluded.file_size,
@@ -225,9 +534,16 @@ fn process_file(
             parsed_disc = excluded.parsed_disc,
             parsed_track = excluded.parsed_track,
             parsed_set = excluded.parsed_set,
-            parsed_title = excluded.parsed_title,
+            -- Titles fixed by hand (`titles import`) or from beets survive rescans
+            parsed_title = CASE WHEN tracks.title_match_method IN ('manual', 'beets')
+                THEN tracks.parsed_title ELSE excluded.parsed_title END,
             duration_secs = excluded.duration_secs,
-            recording_type = excluded.recording_type,
+            -- Soundcheck/interview marks from audio outlive a rescan
+            recording_type = CASE WHEN tracks.recording_type IN ('soundcheck', 'interview')
+                THEN tracks.recording_type ELSE excluded.recording_type END,
+            source_type = excluded.source_type,
+            taper = excluded.taper,
+            lineage_hint = excluded.lineage_hint,
             updated_at = datetime('now')
         ",
         rusqlite::params![
@@ -253,15 +569,14 @@ fn process_file(
             new_track.parsed_title,
             new_track.duration_secs,
             new_track.recording_type,
+            new_track.source_type,
+            new_track.taper,
+            new_track.lineage_hint,
         ],
     )
     .map_err(crate::db::DbError::from)?;
 
-    if is_new {
-        Ok(FileAction::New)
-    } else {
-        Ok(FileAction::Updated)
-    }
+    Ok(())
 }
 
 fn format_mtime(meta: &std::fs::Metadata) -> String {
@@ -271,3 +586,72 @@ fn format_mtime(meta: &std::fs::Metadata) -> String {
         .map(|d| d.as_secs().to_string())
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+    fn setup() {
+        INIT.call_once(crate::bands::init_default);
+    }
+
+    #[test]
+    fn test_preview_counts_without_writing() {
+        setup();
+        let dir = std::env::temp_dir().join(format!("setbreak-preview-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("skip.dts")).unwrap();
+        for name in ["gd1977-05-08d1t01.flac", "random.mp3", "skip.dts/t01.flac"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let config = AppConfig {
+            collection: CollectionConfig {
+                ignore: vec!["*.dts".into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let db = Database::open_in_memory().unwrap();
+        let paths = vec![dir.to_string_lossy().to_string()];
+        let preview = preview(&db, &paths, false, &config).unwrap();
+
+        assert_eq!(preview.totals.new, 2);
+        assert_eq!(preview.by_band["Grateful Dead"].new, 1);
+        assert_eq!(preview.by_format["mp3"].new, 1);
+        assert_eq!(preview.undated, 1);
+        assert_eq!(db.stats().unwrap().total_tracks, 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scans_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+        setup();
+        let dir = std::env::temp_dir().join(format!("setbreak-latin1-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(std::ffi::OsStr::from_bytes(b"gd1977-05-08 Jos\xe9.flac"));
+        std::fs::write(&file, b"").unwrap();
+
+        let db = Database::open_in_memory().unwrap();
+        let paths = vec![crate::paths::to_db(&dir)];
+        let progress = crate::progress::SilentProgress;
+        let result = scan(&db, &paths, false, &AppConfig::default(), &progress).unwrap();
+        assert_eq!((result.new, result.errors), (1, 0));
+
+        let stored: String = db
+            .conn
+            .query_row("SELECT file_path FROM tracks", [], |row| row.get(0))
+            .unwrap();
+        assert!(stored.ends_with("Jos\u{FFFD}E9.flac"), "{stored}");
+        assert_eq!(crate::paths::to_fs(&stored), file);
+        // A rescan finds the same row
+        let again = scan(&db, &paths, false, &AppConfig::default(), &progress).unwrap();
+        assert_eq!(again.skipped, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
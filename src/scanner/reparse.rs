@@ -0,0 +1,263 @@
+//! Re-derive `parsed_*` columns for tracks already in the database.
+//!
+//! `reparse` runs the same path parsing and tag precedence as a scan, but from
+//! the stored tag columns, so it needs neither the files nor a rescan. Use it
+//! after changing a collection's `prefer` setting (or with `--prefer` to try
+//! one out). Titles that came from a setlist lookup or `titles import` are
+//! left alone: they were chosen deliberately over both tags and filename.
+
+use super::metadata::TagInfo;
+use super::{ScanError, parse_with_defaults};
+use crate::config::{AppConfig, CollectionConfig, MetadataPrecedence};
+use crate::db::Database;
+use std::path::Path;
+
+/// Stored track fields that parsing reads or writes.
+struct StoredTrack {
+    id: i64,
+    file_path: String,
+    tags: TagInfo,
+    /// parsed band, date, venue, set, title
+    text: [Option<String>; 5],
+    disc: Option<i32>,
+    track: Option<i32>,
+    title_locked: bool,
+}
+
+/// Outcome of a reparse.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReparseSummary {
+    pub tracks: usize,
+    /// Tracks with at least one parsed value changed.
+    pub changed: usize,
+    /// Changed values per column, in column order.
+    pub fields: Vec<(&'static str, usize)>,
+}
+
+const FIELDS: [&str; 7] = [
+    "parsed_band",
+    "parsed_date",
+    "parsed_venue",
+    "parsed_set",
+    "parsed_title",
+    "parsed_disc",
+    "parsed_track",
+];
+
+/// Re-derive parsed metadata for every track. Each track uses the collection
+/// settings of the longest root in `roots` containing it (global settings
+/// otherwise); `prefer` overrides their precedence for this run.
+pub fn reparse(
+    db: &Database,
+    roots: &[String],
+    config: &AppConfig,
+    prefer: Option<MetadataPrecedence>,
+    dry_run: bool,
+) -> Result<ReparseSummary, ScanError> {
+    let mut collections: Vec<(&str, CollectionConfig)> = roots
+        .iter()
+        .map(|r| (r.trim_end_matches('/'), config.collection_for(Path::new(r))))
+        .collect();
+    collections.sort_by_key(|(root, _)| std::cmp::Reverse(root.len()));
+    let mut fallback = config.collection.clone();
+    if prefer.is_some() {
+        fallback.prefer = prefer;
+        for (_, c) in &mut collections {
+            c.prefer = prefer;
+        }
+    }
+
+    let tracks = db.stored_tracks_for_reparse()?;
+    let mut summary = ReparseSummary {
+        tracks: tracks.len(),
+        changed: 0,
+        fields: FIELDS.iter().map(|f| (*f, 0)).collect(),
+    };
+    let tx = db
+        .conn
+        .unchecked_transaction()
+        .map_err(crate::db::DbError::from)?;
+    for t in &tracks {
+        let (root, collection) = collections
+            .iter()
+            .find(|(root, _)| {
+                t.file_path
+                    .strip_prefix(root)
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .map(|(root, c)| (*root, c))
+            .unwrap_or(("", &fallback));
+        let parse_path = if crate::remote::is_remote(&t.file_path) {
+            // Remote tracks were parsed from their decoded path below the root
+            let below = t.file_path.strip_prefix(root).unwrap_or(&t.file_path);
+            crate::remote::percent_decode(below)
+        } else {
+            t.file_path.clone()
+        };
+        let mut parsed = parse_with_defaults(Path::new(&parse_path), &t.tags, collection);
+        if t.title_locked {
+            parsed.title = t.text[4].clone();
+        }
+
+        let text = [
+            parsed.band,
+            parsed.date,
+            parsed.venue,
+            parsed.set,
+            parsed.title,
+        ];
+        let mut differs: Vec<bool> = text.iter().zip(&t.text).map(|(a, b)| a != b).collect();
+        differs.push(parsed.disc != t.disc);
+        differs.push(parsed.track != t.track);
+        if !differs.contains(&true) {
+            continue;
+        }
+        summary.changed += 1;
+        for (count, changed) in summary.fields.iter_mut().zip(&differs) {
+            count.1 += usize::from(*changed);
+        }
+        if !dry_run {
+            let [band, date, venue, set, title] = &text;
+            tx.execute(
+                "UPDATE tracks SET parsed_band = ?1, parsed_date = ?2, parsed_venue = ?3,
+                        parsed_set = ?4, parsed_title = ?5, parsed_disc = ?6,
+                        parsed_track = ?7, updated_at = datetime('now')
+                 WHERE id = ?8",
+                rusqlite::params![
+                    band,
+                    date,
+                    venue,
+                    set,
+                    title,
+                    parsed.disc,
+                    parsed.track,
+                    t.id
+                ],
+            )
+            .map_err(crate::db::DbError::from)?;
+        }
+    }
+    if dry_run {
+        return Ok(summary);
+    }
+    tx.commit().map_err(crate::db::DbError::from)?;
+    if summary.changed > 0 {
+        if let Err(e) = db.apply_title_aliases() {
+            log::warn!("Failed to apply title aliases: {e}");
+        }
+    }
+    Ok(summary)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    fn stored_tracks_for_reparse(&self) -> crate::db::Result<Vec<StoredTrack>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path, title, artist, album, date, track_number, disc_number,
+                    venue, parsed_band, parsed_date, parsed_venue, parsed_set, parsed_title,
+                    parsed_disc, parsed_track, title_match_method IS NOT NULL
+             FROM tracks ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(StoredTrack {
+                    id: row.get(0)?,
+                    file_path: row.get(1)?,
+                    tags: TagInfo {
+                        title: row.get(2)?,
+                        artist: row.get(3)?,
+                        album: row.get(4)?,
+                        date: row.get(5)?,
+                        track_number: row.get(6)?,
+                        disc_number: row.get(7)?,
+                        venue: row.get(8)?,
+                        ..TagInfo::default()
+                    },
+                    text: [
+                        row.get(9)?,
+                        row.get(10)?,
+                        row.get(11)?,
+                        row.get(12)?,
+                        row.get(13)?,
+                    ],
+                    disc: row.get(14)?,
+                    track: row.get(15)?,
+                    title_locked: row.get(16)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewTrack;
+    use crate::db::queries::tests::new_track;
+
+    fn track(path: &str, artist: &str, date: &str, title: &str) -> NewTrack {
+        NewTrack {
+            title: Some(title.into()),
+            artist: Some(artist.into()),
+            date: Some(date.into()),
+            track_number: Some(4),
+            ..new_track(path)
+        }
+    }
+
+    fn parsed(db: &Database, id: i64) -> (Option<String>, Option<String>) {
+        db.conn
+            .query_row(
+                "SELECT parsed_date, parsed_title FROM tracks WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_reparse_prefers_tags() {
+        crate::bands::init_default();
+        let db = Database::open_in_memory().unwrap();
+        // A badly named file with good tags, and one whose tag date is only a year
+        let id = db
+            .upsert_track(&track(
+                "/music/misc/gd77-05-08 track 01.flac",
+                "Grateful Dead",
+                "1977-05-08",
+                "Scarlet Begonias",
+            ))
+            .unwrap();
+        let year_only = db
+            .upsert_track(&track(
+                "/music/misc/ph1997-11-22d2t03.flac",
+                "Phish",
+                "1997",
+                "Tweezer",
+            ))
+            .unwrap();
+        let config = AppConfig::default();
+        let roots = vec!["/music".to_string()];
+
+        let dry = reparse(&db, &roots, &config, Some(MetadataPrecedence::Tags), true).unwrap();
+        assert_eq!((dry.tracks, dry.changed), (2, 2));
+        assert_eq!(parsed(&db, id).1, None);
+
+        reparse(&db, &roots, &config, Some(MetadataPrecedence::Tags), false).unwrap();
+        assert_eq!(
+            parsed(&db, id),
+            (Some("1977-05-08".into()), Some("Scarlet Begonias".into()))
+        );
+        // The year-only tag date loses to the filename's full date
+        assert_eq!(
+            parsed(&db, year_only),
+            (Some("1997-11-22".into()), Some("Tweezer".into()))
+        );
+
+        // Nothing left to change on a second run
+        let again = reparse(&db, &roots, &config, Some(MetadataPrecedence::Tags), false).unwrap();
+        assert_eq!(again.changed, 0);
+    }
+}
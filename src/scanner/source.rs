@@ -0,0 +1,224 @@
+//! Source lineage parsed from directory names: how a recording was made
+//! (soundboard, audience, matrix, FM), who taped or transferred it, and
+//! transfer-chain hints.
+//!
+//! Archive.org-style names pack this into dot-separated tokens
+//! (`gd1977-05-08.sbd.miller.12345.sbeok.flac16`); hand-named folders use words
+//! (`1995-07-09 Soldier Field (AUD Bershaw)`). Only directory names are read —
+//! file names are mostly song titles, where taper names would false-match.
+
+use std::path::Path;
+
+/// Parsed source information. Every field is optional: most folders say nothing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceInfo {
+    /// "sbd", "matrix", "fm", or "aud".
+    pub source_type: Option<&'static str>,
+    /// Taper or transferrer, lowercase ("miller", "bershaw").
+    pub taper: Option<String>,
+    /// Comma-separated transfer markers ("reel,24bit").
+    pub lineage_hint: Option<String>,
+}
+
+/// Well-known tapers and transferrers: (token, canonical name).
+const TAPERS: &[(&str, &str)] = &[
+    ("miller", "miller"),
+    ("charlie", "miller"),
+    ("charliemiller", "miller"),
+    ("cmiller", "miller"),
+    ("bershaw", "bershaw"),
+    ("seamons", "seamons"),
+    ("hanno", "hanno"),
+    ("vernon", "vernon"),
+    ("menke", "menke"),
+    ("cotsman", "cotsman"),
+    ("hollister", "hollister"),
+    ("tobin", "tobin"),
+    ("clugston", "clugston"),
+    ("dusborne", "dusborne"),
+    ("yerys", "yerys"),
+    ("ladner", "ladner"),
+    ("eaton", "eaton"),
+    ("wise", "wise"),
+];
+
+/// Transfer-chain markers: (token, hint).
+const LINEAGE_MARKERS: &[(&str, &str)] = &[
+    ("master", "master"),
+    ("masters", "master"),
+    ("reel", "reel"),
+    ("reels", "reel"),
+    ("dat", "dat"),
+    ("cass", "cassette"),
+    ("cassette", "cassette"),
+    ("dsbd", "dsbd"),
+    ("sbeok", "sbeok"),
+    ("remaster", "remaster"),
+    ("remastered", "remaster"),
+    ("24bit", "24bit"),
+    ("flac24", "24bit"),
+    ("flac2496", "24bit"),
+    ("flac2448", "24bit"),
+];
+
+/// Tokens that never name a taper, even right after a source token.
+const NOT_TAPERS: &[&str] = &[
+    "flac", "flac16", "shn", "shnf", "mp3", "wav", "aud", "sbd", "fm", "matrix", "mtx", "vbr",
+    "set", "disc", "cd", "the", "and", "live",
+];
+
+/// Source type for one lowercase token.
+fn source_token(token: &str) -> Option<&'static str> {
+    match token {
+        t if t.contains("matrix") || t == "mtx" => Some("matrix"),
+        "sbd" | "dsbd" | "soundboard" | "soundboards" | "board" => Some("sbd"),
+        "fm" | "prefm" => Some("fm"),
+        "aud" | "audience" => Some("aud"),
+        _ => None,
+    }
+}
+
+/// Parse source info from a path's directory names, nearest directory first.
+pub fn parse_source(path: &Path) -> SourceInfo {
+    let mut info = SourceInfo::default();
+    let dirs: Vec<String> = path
+        .parent()
+        .map(|p| {
+            p.components()
+                .filter_map(|c| c.as_os_str().to_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    for dir in dirs.iter().rev() {
+        let found = parse_name(dir);
+        info.source_type = info.source_type.or(found.source_type);
+        info.taper = info.taper.or(found.taper);
+        info.lineage_hint = info.lineage_hint.or(found.lineage_hint);
+    }
+    info
+}
+
+/// Parse source info from a single name (a directory or archive.org identifier).
+pub fn parse_name(name: &str) -> SourceInfo {
+    let lower = name.to_lowercase();
+    let tokens: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let types: Vec<&'static str> = tokens.iter().filter_map(|t| source_token(t)).collect();
+    // "sbd+aud" without the word matrix is still a matrix
+    let source_type =
+        if types.contains(&"matrix") || (types.contains(&"sbd") && types.contains(&"aud")) {
+            Some("matrix")
+        } else {
+            types.first().copied()
+        };
+
+    let taper = tokens
+        .iter()
+        .find_map(|t| {
+            TAPERS
+                .iter()
+                .find(|(k, _)| k == t)
+                .map(|(_, v)| v.to_string())
+        })
+        .or_else(|| identifier_taper(&lower));
+
+    let mut hints: Vec<&str> = Vec::new();
+    for t in &tokens {
+        if let Some((_, hint)) = LINEAGE_MARKERS.iter().find(|(k, _)| k == t) {
+            if !hints.contains(hint) {
+                hints.push(hint);
+            }
+        }
+    }
+    let lineage_hint = (!hints.is_empty()).then(|| hints.join(","));
+
+    SourceInfo {
+        source_type,
+        taper,
+        lineage_hint,
+    }
+}
+
+/// In archive.org identifiers the taper follows the source: `<date>.sbd.<taper>.<shnid>`.
+fn identifier_taper(lower: &str) -> Option<String> {
+    let parts: Vec<&str> = lower.split('.').collect();
+    let i = parts.iter().position(|p| source_token(p).is_some())?;
+    let next = parts.get(i + 1)?;
+    let is_name = next.len() >= 3
+        && next.chars().all(|c| c.is_ascii_alphabetic())
+        && !NOT_TAPERS.contains(next)
+        && !LINEAGE_MARKERS.iter().any(|(k, _)| k == next);
+    is_name.then(|| next.to_string())
+}
+
+/// Default source ranking: sbd=3, matrix/fm=2, aud=1, unknown=0.
+pub fn source_quality(source_type: Option<&str>) -> i32 {
+    match source_type {
+        Some("sbd") => 3,
+        Some("matrix") | Some("fm") => 2,
+        Some("aud") => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_identifier() {
+        let info = parse_source(Path::new(
+            "/music/gd/gd1977-05-08.sbd.miller.12345.sbeok.flac16/gd77-05-08d1t01.flac",
+        ));
+        assert_eq!(info.source_type, Some("sbd"));
+        assert_eq!(info.taper.as_deref(), Some("miller"));
+        assert_eq!(info.lineage_hint.as_deref(), Some("sbeok"));
+
+        // Unknown taper name taken from its position after the source
+        let info = parse_name("gd1982-08-08.aud.kowalski.7690.shnf");
+        assert_eq!(info.source_type, Some("aud"));
+        assert_eq!(info.taper.as_deref(), Some("kowalski"));
+    }
+
+    #[test]
+    fn test_folder_words() {
+        let info = parse_source(Path::new(
+            "/music/Phish/1995-07-09 Soldier Field (AUD Bershaw, DAT master)/01 Chalk Dust.flac",
+        ));
+        assert_eq!(info.source_type, Some("aud"));
+        assert_eq!(info.taper.as_deref(), Some("bershaw"));
+        assert_eq!(info.lineage_hint.as_deref(), Some("dat,master"));
+
+        assert_eq!(
+            parse_name("gd1972-08-27.sbd-aud.1234").source_type,
+            Some("matrix")
+        );
+        assert_eq!(
+            parse_name("gd90.ultramatrix.flac24").source_type,
+            Some("matrix")
+        );
+        assert_eq!(
+            parse_name("jrad2019-03-02.dsbd.flac")
+                .lineage_hint
+                .as_deref(),
+            Some("dsbd")
+        );
+        // File names aren't parsed: song titles would false-match tapers
+        assert_eq!(
+            parse_source(Path::new("/music/misc/Charlie Miller Blues.flac")),
+            SourceInfo::default()
+        );
+    }
+
+    #[test]
+    fn test_source_quality() {
+        assert_eq!(source_quality(Some("sbd")), 3);
+        assert_eq!(source_quality(Some("matrix")), 2);
+        assert_eq!(source_quality(Some("aud")), 1);
+        assert_eq!(source_quality(None), 0);
+    }
+}
@@ -0,0 +1,230 @@
+//! Before/after score reports for `rescore --report` and `calibrate --report`.
+//!
+//! The stored jam scores are snapshotted before the run and again after it;
+//! comparing the two gives, per score, how many tracks moved, the average shift,
+//! and the tracks that moved most — enough to sanity-check an adjustment
+//! without diffing database dumps.
+
+use crate::db::Database;
+use crate::scores::{self, ScoreMeta};
+use std::collections::HashMap;
+
+/// Changes smaller than this are rounding noise, not movement.
+const MIN_DELTA: f64 = 0.05;
+
+/// Stored scores for every analyzed track, in `scores::SCORES` order.
+#[derive(Debug, Clone, Default)]
+pub struct ScoreSnapshot {
+    tracks: HashMap<i64, SnapshotRow>,
+}
+
+#[derive(Debug, Clone)]
+struct SnapshotRow {
+    title: String,
+    date: String,
+    values: Vec<Option<f64>>,
+}
+
+/// One track's change in one score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreMove {
+    pub track_id: i64,
+    pub title: String,
+    pub date: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+impl ScoreMove {
+    pub fn delta(&self) -> f64 {
+        self.new - self.old
+    }
+}
+
+/// How one score changed across the run.
+#[derive(Debug, Clone)]
+pub struct ScoreDeltas {
+    pub score: &'static ScoreMeta,
+    /// Tracks with the score both before and after.
+    pub tracks: usize,
+    /// Tracks whose score moved.
+    pub changed: usize,
+    /// Mean signed change over `tracks`.
+    pub mean_shift: f64,
+    /// Mean absolute change over `tracks`.
+    pub mean_abs_shift: f64,
+    /// Biggest absolute changes first.
+    pub movers: Vec<ScoreMove>,
+}
+
+impl ScoreSnapshot {
+    /// Compare with a later snapshot, keeping the `movers` biggest changes per score.
+    pub fn compare(&self, after: &ScoreSnapshot, movers: usize) -> Vec<ScoreDeltas> {
+        scores::SCORES
+            .iter()
+            .enumerate()
+            .map(|(i, score)| {
+                let mut moves: Vec<ScoreMove> = Vec::new();
+                let mut tracks = 0;
+                let (mut sum, mut abs_sum) = (0.0, 0.0);
+                for (&track_id, before) in &self.tracks {
+                    let Some(now) = after.tracks.get(&track_id) else {
+                        continue;
+                    };
+                    let (Some(old), Some(new)) = (before.values[i], now.values[i]) else {
+                        continue;
+                    };
+                    tracks += 1;
+                    sum += new - old;
+                    abs_sum += (new - old).abs();
+                    if (new - old).abs() >= MIN_DELTA {
+                        moves.push(ScoreMove {
+                            track_id,
+                            title: now.title.clone(),
+                            date: now.date.clone(),
+                            old,
+                            new,
+                        });
+                    }
+                }
+                let changed = moves.len();
+                moves.sort_by(|a, b| {
+                    b.delta()
+                        .abs()
+                        .total_cmp(&a.delta().abs())
+                        .then(a.track_id.cmp(&b.track_id))
+                });
+                moves.truncate(movers);
+                let n = tracks.max(1) as f64;
+                ScoreDeltas {
+                    score,
+                    tracks,
+                    changed,
+                    mean_shift: sum / n,
+                    mean_abs_shift: abs_sum / n,
+                    movers: moves,
+                }
+            })
+            .collect()
+    }
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Snapshot the stored jam scores of all analyzed tracks.
+    pub fn score_snapshot(&self) -> crate::db::Result<ScoreSnapshot> {
+        // Column names come from the score registry, never from user input
+        let columns: Vec<String> = scores::SCORES
+            .iter()
+            .map(|s| format!("a.{}", s.column))
+            .collect();
+        let sql = format!(
+            "SELECT t.id, COALESCE(t.parsed_title, t.title, '(untitled)'),
+                    COALESCE(t.parsed_date, t.date, '?'), {}
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id",
+            columns.join(", ")
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let tracks = stmt
+            .query_map([], |row| {
+                let values = (0..scores::SCORES.len())
+                    .map(|i| row.get(3 + i))
+                    .collect::<rusqlite::Result<Vec<Option<f64>>>>()?;
+                Ok((
+                    row.get(0)?,
+                    SnapshotRow {
+                        title: row.get(1)?,
+                        date: row.get(2)?,
+                        values,
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(ScoreSnapshot { tracks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{NewAnalysis, NewTrack};
+    use crate::db::queries::tests::new_track;
+
+    fn snapshot(rows: &[(i64, Option<f64>)]) -> ScoreSnapshot {
+        let tracks = rows
+            .iter()
+            .map(|&(id, groove)| {
+                let mut values = vec![Some(50.0); scores::SCORES.len()];
+                values[2] = groove;
+                (
+                    id,
+                    SnapshotRow {
+                        title: format!("Song {id}"),
+                        date: "1977-05-08".into(),
+                        values,
+                    },
+                )
+            })
+            .collect();
+        ScoreSnapshot { tracks }
+    }
+
+    #[test]
+    fn test_biggest_movers() {
+        let before = snapshot(&[
+            (1, Some(40.0)),
+            (2, Some(60.0)),
+            (3, Some(70.0)),
+            (4, None),
+            (5, Some(10.0)),
+        ]);
+        let after = snapshot(&[
+            (1, Some(52.0)),
+            (2, Some(45.0)),
+            (3, Some(70.01)),
+            (4, Some(80.0)),
+        ]);
+        let deltas = before.compare(&after, 1);
+        let groove = &deltas[2];
+        assert_eq!(groove.score.name, "groove");
+        // Track 4 gained a score and track 5 lost its row: neither is comparable
+        assert_eq!((groove.tracks, groove.changed), (3, 2));
+        assert!((groove.mean_shift - (12.0 - 15.0 + 0.01) / 3.0).abs() < 1e-9);
+        assert_eq!(groove.movers.len(), 1);
+        assert_eq!(groove.movers[0].track_id, 2);
+        assert_eq!(groove.movers[0].delta(), -15.0);
+
+        // Untouched scores report no movement
+        assert_eq!(deltas[0].changed, 0);
+        assert!(deltas[0].movers.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_around_update() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db
+            .upsert_track(&NewTrack {
+                parsed_date: Some("1977-05-08".into()),
+                parsed_title: Some("Scarlet Begonias".into()),
+                ..new_track("/m/d1t01.flac")
+            })
+            .unwrap();
+        let mut analysis = NewAnalysis {
+            track_id: id,
+            groove_score: Some(40.0),
+            ..NewAnalysis::default()
+        };
+        db.store_analysis(&analysis).unwrap();
+        let before = db.score_snapshot().unwrap();
+
+        analysis.groove_score = Some(55.0);
+        db.update_jam_scores(&analysis).unwrap();
+        let deltas = before.compare(&db.score_snapshot().unwrap(), 10);
+        let mover = &deltas[2].movers[0];
+        assert_eq!(mover.title, "Scarlet Begonias");
+        assert_eq!((mover.old, mover.new), (40.0, 55.0));
+        assert_eq!(deltas[0].tracks, 0);
+    }
+}
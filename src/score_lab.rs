@@ -14,6 +14,7 @@ use std::collections::HashMap;
 
 /// A track with all its numeric features loaded as a name→value map.
 pub(crate) struct FeatureRow {
+    pub track_id: i64,
     pub title: String,
     pub date: String,
     pub duration_min: f64,
@@ -49,6 +50,50 @@ pub fn evaluate_formula(
     let tree = build_operator_tree::<DefaultNumericTypes>(formula)
         .map_err(|e| format!("Parse error: {e}"))?;
 
+    let rows = load_feature_rows(db, min_duration_secs, live_only)?;
+
+    // Evaluate expression for each row
+    let mut results: Vec<LabResult> = Vec::with_capacity(rows.len());
+
+    for row in &rows {
+        let context = feature_context(row)?;
+
+        if let Ok(val) = tree.eval_with_context(&context) {
+            // Try to extract a numeric value
+            let score = val
+                .as_float()
+                .ok()
+                .or_else(|| val.as_int().ok().map(|i: i64| i as f64));
+            if let Some(s) = score {
+                if s.is_finite() {
+                    results.push(LabResult {
+                        title: row.title.clone(),
+                        date: row.date.clone(),
+                        duration_min: row.duration_min,
+                        computed_score: s,
+                    });
+                }
+            }
+        }
+    }
+
+    // Sort descending by computed score
+    results.sort_by(|a, b| {
+        b.computed_score
+            .partial_cmp(&a.computed_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    results.truncate(limit);
+
+    Ok(results)
+}
+
+/// Load every numeric feature for the analyzed (non-garbage) tracks.
+pub(crate) fn load_feature_rows(
+    db: &Database,
+    min_duration_secs: Option<f64>,
+    live_only: bool,
+) -> Result<Vec<FeatureRow>, String> {
     // Build the SQL query dynamically from ANALYSIS_SCHEMA
     let numeric_cols: Vec<&str> = ANALYSIS_SCHEMA
         .iter()
@@ -86,57 +131,28 @@ pub fn evaluate_formula(
          WHERE {where_clause}"
     );
 
-    // Load all rows
-    let rows = db
-        .query_raw_lab(&sql, &numeric_cols)
-        .map_err(|e| format!("Query error: {e}"))?;
-
-    // Evaluate expression for each row
-    let mut results: Vec<LabResult> = Vec::with_capacity(rows.len());
-
-    for row in &rows {
-        let mut context = HashMapContext::new();
-        for (name, &val) in &row.features {
-            context
-                .set_value(name.clone(), Value::Float(val))
-                .map_err(|e| format!("Variable bind error for {name}: {e}"))?;
-        }
-
-        // Also bind some convenience aliases
-        if let Some(&dur) = row.features.get("duration") {
-            context
-                .set_value("duration_min".to_string(), Value::Float(dur / 60.0))
-                .ok();
-        }
+    db.query_raw_lab(&sql, &numeric_cols)
+        .map_err(|e| format!("Query error: {e}"))
+}
 
-        if let Ok(val) = tree.eval_with_context(&context) {
-            // Try to extract a numeric value
-            let score = val
-                .as_float()
-                .ok()
-                .or_else(|| val.as_int().ok().map(|i: i64| i as f64));
-            if let Some(s) = score {
-                if s.is_finite() {
-                    results.push(LabResult {
-                        title: row.title.clone(),
-                        date: row.date.clone(),
-                        duration_min: row.duration_min,
-                        computed_score: s,
-                    });
-                }
-            }
-        }
+/// Bind a row's features (plus convenience aliases) as expression variables.
+pub(crate) fn feature_context(
+    row: &FeatureRow,
+) -> Result<HashMapContext<DefaultNumericTypes>, String> {
+    let mut context = HashMapContext::new();
+    for (name, &val) in &row.features {
+        context
+            .set_value(name.clone(), Value::Float(val))
+            .map_err(|e| format!("Variable bind error for {name}: {e}"))?;
     }
 
-    // Sort descending by computed score
-    results.sort_by(|a, b| {
-        b.computed_score
-            .partial_cmp(&a.computed_score)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
-    results.truncate(limit);
-
-    Ok(results)
+    // Also bind some convenience aliases
+    if let Some(&dur) = row.features.get("duration") {
+        context
+            .set_value("duration_min".to_string(), Value::Float(dur / 60.0))
+            .ok();
+    }
+    Ok(context)
 }
 
 // ── Database query support ──────────────────────────────────────────────
@@ -154,7 +170,7 @@ impl Database {
 
         let mut rows = stmt.query([])?;
         while let Some(row) = rows.next()? {
-            let _track_id: i64 = row.get(0)?;
+            let track_id: i64 = row.get(0)?;
             let title: String = row.get(1)?;
             let date: String = row.get(2)?;
             let _file_path: String = row.get(3)?;
@@ -167,6 +183,7 @@ impl Database {
             }
 
             rows_out.push(FeatureRow {
+                track_id,
                 title,
                 date,
                 duration_min,
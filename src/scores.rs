@@ -0,0 +1,434 @@
+//! Display metadata for the jam scores.
+//!
+//! One registry of names, column labels, descriptions, inputs, caveats, and
+//! expected ranges so every frontend (CLI tables, TUI, web, reports, the
+//! `scores describe` glossary) renders the same text instead of carrying its own
+//! copies of the strings. Inputs are checked against the schema in tests, so a
+//! formula change that drops or renames a feature has to update its entry here.
+
+use crate::db::Database;
+use crate::db::columns::NOT_GARBAGE;
+
+/// How to read a score's scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// More is more of a good thing (groove, transcendence, ...).
+    HigherIsBetter,
+    /// Descriptive intensity: neither end is better (energy, intensity).
+    Neutral,
+    /// An axis between two moods (valence: dark ↔ happy, arousal: calm ↔ energetic).
+    Bipolar,
+}
+
+/// Display metadata for one jam score.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct ScoreMeta {
+    /// CLI name, as accepted by `--score` (e.g. `build_quality`).
+    pub name: &'static str,
+    /// `analysis_results` column.
+    pub column: &'static str,
+    /// Three-letter column header used in tables.
+    pub short: &'static str,
+    /// Human-readable label for legends and headings.
+    pub label: &'static str,
+    /// One-line explanation.
+    pub description: &'static str,
+    pub direction: Direction,
+    /// Where roughly 90% of a well-calibrated library lands (p5..p95, 0-100 scale).
+    /// A distribution squeezed far inside this has lost resolution.
+    pub healthy_range: (f64, f64),
+    /// Labels for the low and high ends of a `Bipolar` score.
+    pub poles: Option<(&'static str, &'static str)>,
+    /// `analysis_results` features the formula reads (see `analyzer::jam_metrics`).
+    pub inputs: &'static [&'static str],
+    /// Known blind spots worth reading before trusting a ranking.
+    pub caveats: &'static [&'static str],
+}
+
+/// All jam scores, in `db::columns::SCORE_COLUMNS` order.
+pub const SCORES: &[ScoreMeta] = &[
+    ScoreMeta {
+        name: "energy",
+        column: "energy_score",
+        short: "Eng",
+        label: "Energy",
+        description: "How present and powerful the music feels (level, loudness, bass)",
+        direction: Direction::Neutral,
+        healthy_range: (10.0, 85.0),
+        poles: None,
+        inputs: &[
+            "rms_level",
+            "lufs_integrated",
+            "sub_band_bass_mean",
+            "spectral_centroid_mean",
+        ],
+        caveats: &[
+            "Calibrated to live tape levels; mastered studio releases score high across the board",
+            "Tracks loudness, so it also varies with the recording (see `calibrate`)",
+        ],
+    },
+    ScoreMeta {
+        name: "intensity",
+        column: "intensity_score",
+        short: "Int",
+        label: "Intensity",
+        description: "How much the energy varies: flux, dynamics, and loudness contour",
+        direction: Direction::Neutral,
+        healthy_range: (10.0, 85.0),
+        poles: None,
+        inputs: &[
+            "spectral_flux_std",
+            "dynamic_range",
+            "loudness_range",
+            "dynamics_entropy",
+        ],
+        caveats: &[
+            "Measures variation, not loudness: a steady wall of sound scores low",
+            "Crowd noise and tape dropouts read as dynamics",
+        ],
+    },
+    ScoreMeta {
+        name: "groove",
+        column: "groove_score",
+        short: "Grv",
+        label: "Groove",
+        description: "How steady and compelling the rhythm is",
+        direction: Direction::HigherIsBetter,
+        healthy_range: (15.0, 85.0),
+        poles: None,
+        inputs: &[
+            "spectral_flux_mean",
+            "spectral_flux_std",
+            "sub_band_bass_mean",
+            "sub_band_bass_std",
+            "repetition_similarity",
+            "onset_count",
+            "tempo_stability",
+            "duration",
+        ],
+        caveats: &[
+            "Zero when no onsets were detected (silence, drones, some Space)",
+            "Rewards steadiness, so a deliberately loose jam can groove hard and still score low",
+        ],
+    },
+    ScoreMeta {
+        name: "improvisation",
+        column: "improvisation_score",
+        short: "Imp",
+        label: "Improvisation",
+        description: "Sustained development over time: building, peaking, leaving the form",
+        direction: Direction::HigherIsBetter,
+        healthy_range: (5.0, 80.0),
+        poles: None,
+        inputs: &[
+            "duration",
+            "energy_peak_count",
+            "dynamics_peak_count",
+            "dynamics_entropy",
+            "key_change_count",
+            "tempo_stability",
+        ],
+        caveats: &[
+            "Zero below 3 minutes and ramped up to 6: length is part of the definition",
+            "Temporal development only; harmonic breadth is exploratory's job",
+        ],
+    },
+    ScoreMeta {
+        name: "tightness",
+        column: "tightness_score",
+        short: "Tgt",
+        label: "Tightness",
+        description: "How locked in the band is: rhythmic precision and steady delivery",
+        direction: Direction::HigherIsBetter,
+        healthy_range: (15.0, 85.0),
+        poles: None,
+        inputs: &[
+            "spectral_flux_mean",
+            "spectral_flux_std",
+            "spectral_flatness_std",
+            "zcr_mean",
+            "zcr_std",
+            "onset_count",
+            "tempo_stability",
+            "duration",
+        ],
+        caveats: &[
+            "Free-form passages (Drums, Space) score low by design",
+            "Tempo stability depends on the beat tracker locking on; very quiet tracks are unreliable",
+        ],
+    },
+    ScoreMeta {
+        name: "build_quality",
+        column: "build_quality_score",
+        short: "Bld",
+        label: "Build Quality",
+        description: "How well the music builds to peaks: dynamic arcs and tension",
+        direction: Direction::HigherIsBetter,
+        healthy_range: (5.0, 80.0),
+        poles: None,
+        inputs: &[
+            "duration",
+            "crest_factor",
+            "loudness_range",
+            "dynamics_peak_count",
+            "transition_count",
+        ],
+        caveats: &[
+            "Tracks of 90s or more are scored from the segment energy contour; these inputs are the fallback for shorter tracks",
+            "Experiments and batch rescoring without segment data always use the fallback",
+        ],
+    },
+    ScoreMeta {
+        name: "exploratory",
+        column: "exploratory_score",
+        short: "Exp",
+        label: "Exploratory",
+        description: "How much musical territory is covered: harmonic and timbral breadth",
+        direction: Direction::HigherIsBetter,
+        healthy_range: (10.0, 80.0),
+        poles: None,
+        inputs: &[
+            "duration",
+            "chord_count",
+            "chromagram_entropy",
+            "onset_interval_entropy",
+            "chroma_self_similarity_bandwidth",
+            "key_alternatives_count",
+            "section_diversity_score",
+            "pitch_range_low_corrected",
+            "pitch_range_high_corrected",
+        ],
+        caveats: &[
+            "Zero below 2 minutes and ramped up to 5",
+            "Tracks analyzed before pitch octave correction get a neutral melodic range until re-analyzed",
+            "Chord and key detection struggle with heavy distortion and feedback, which can inflate breadth",
+        ],
+    },
+    ScoreMeta {
+        name: "transcendence",
+        column: "transcendence_score",
+        short: "Trn",
+        label: "Transcendence",
+        description: "Peak experience: sustained builds where groove and energy click",
+        direction: Direction::HigherIsBetter,
+        healthy_range: (5.0, 75.0),
+        poles: None,
+        inputs: &[
+            "duration",
+            "energy_level",
+            "spectral_flux_mean",
+            "harmonic_percussive_ratio",
+            "dynamics_entropy",
+            "dynamics_peak_count",
+        ],
+        caveats: &[
+            "Built on build quality plus groove and energy, so it inherits their caveats",
+            "Zero below 2 minutes and ramped up to 6",
+        ],
+    },
+    ScoreMeta {
+        name: "valence",
+        column: "valence_score",
+        short: "Val",
+        label: "Valence",
+        description: "Mood axis from dark/sad to bright/happy",
+        direction: Direction::Bipolar,
+        healthy_range: (15.0, 80.0),
+        poles: Some(("dark", "happy")),
+        inputs: &[
+            "spectral_centroid_mean",
+            "major_chord_ratio",
+            "roughness_mean",
+            "onset_count",
+            "duration",
+        ],
+        caveats: &[
+            "Brightness is the strongest input, so bright-sounding tapes read happier",
+            "Major/minor is estimated from detected chords and is unreliable for modal jams",
+        ],
+    },
+    ScoreMeta {
+        name: "arousal",
+        column: "arousal_score",
+        short: "Aro",
+        label: "Arousal",
+        description: "Mood axis from calm to energetic",
+        direction: Direction::Bipolar,
+        healthy_range: (15.0, 85.0),
+        poles: Some(("calm", "energetic")),
+        inputs: &[
+            "energy_level",
+            "lufs_integrated",
+            "roughness_mean",
+            "spectral_flux_mean",
+            "tempo_bpm_corrected",
+        ],
+        caveats: &[
+            "Uses the octave-corrected tempo, falling back to raw `tempo_bpm` on rows not yet rescored",
+            "Loudness is an input, so quiet recordings read calmer",
+        ],
+    },
+];
+
+/// Look up a score by CLI name, column, short header, or label (case-insensitive).
+pub fn get(key: &str) -> Option<&'static ScoreMeta> {
+    let key = key.trim();
+    SCORES.iter().find(|s| {
+        [s.name, s.column, s.short, s.label]
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(key))
+            || s.name.replace('_', " ").eq_ignore_ascii_case(key)
+    })
+}
+
+/// `Grv=Groove  Imp=Improvisation ...` for the given scores, wrapped every `per_line`.
+pub fn legend(scores: &[&ScoreMeta], per_line: usize) -> String {
+    scores
+        .chunks(per_line.max(1))
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|s| format!("{}={}", s.short, s.label))
+                .collect::<Vec<_>>()
+                .join("  ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Metadata for a list of keys, skipping unknown ones.
+pub fn select(keys: &[&str]) -> Vec<&'static ScoreMeta> {
+    keys.iter().filter_map(|k| get(k)).collect()
+}
+
+/// Where a score's values actually fall in this library.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct Distribution {
+    pub tracks: usize,
+    pub min: f64,
+    pub p5: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    /// Summary of unsorted values, or `None` if there are none.
+    pub fn from_values(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let at = |p: f64| values[((values.len() - 1) as f64 * p).round() as usize];
+        Some(Self {
+            tracks: values.len(),
+            min: values[0],
+            p5: at(0.05),
+            median: at(0.5),
+            p95: at(0.95),
+            max: values[values.len() - 1],
+        })
+    }
+}
+
+/// Sanity problems with a score's live distribution, compared with its
+/// `healthy_range`. Empty when the distribution looks usable.
+pub fn sanity_warnings(meta: &ScoreMeta, d: &Distribution) -> Vec<String> {
+    let (lo, hi) = meta.healthy_range;
+    let mut warnings = Vec::new();
+    if d.p95 - d.p5 < (hi - lo) / 2.0 {
+        warnings.push(format!(
+            "compressed: 90% of tracks span {:.0}-{:.0}, expected about {lo:.0}-{hi:.0}",
+            d.p5, d.p95
+        ));
+    }
+    if d.median < lo || d.median > hi {
+        warnings.push(format!(
+            "median {:.0} is outside the expected {lo:.0}-{hi:.0}",
+            d.median
+        ));
+    }
+    warnings
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Live distribution of a score across non-garbage tracks, optionally for
+    /// one band (canonical `parsed_band`).
+    pub fn score_distribution(
+        &self,
+        meta: &ScoreMeta,
+        band: Option<&str>,
+    ) -> crate::db::Result<Option<Distribution>> {
+        // The column name comes from the registry, never from user input
+        let sql = format!(
+            "SELECT a.{col} FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE a.{col} IS NOT NULL AND {NOT_GARBAGE}
+               AND (?1 IS NULL OR t.parsed_band = ?1 COLLATE NOCASE)",
+            col = meta.column
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let values = stmt
+            .query_map([band], |row| row.get::<_, f64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Distribution::from_values(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::columns::{ANALYSIS_SCHEMA, SCORE_COLUMNS};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_registry_matches_score_columns() {
+        let columns: Vec<_> = SCORES.iter().map(|s| s.column).collect();
+        assert_eq!(columns, SCORE_COLUMNS);
+        let shorts: HashSet<_> = SCORES.iter().map(|s| s.short).collect();
+        assert_eq!(shorts.len(), SCORES.len());
+        for s in SCORES {
+            assert_eq!(s.column, format!("{}_score", s.name));
+            assert!(s.healthy_range.0 < s.healthy_range.1);
+            assert_eq!(s.poles.is_some(), s.direction == Direction::Bipolar);
+            assert!(!s.caveats.is_empty(), "{} has no caveats", s.name);
+            for input in s.inputs {
+                assert!(
+                    ANALYSIS_SCHEMA.iter().any(|c| c.name == *input),
+                    "{} input {input} is not an analysis_results column",
+                    s.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_distribution_and_sanity() {
+        assert!(Distribution::from_values(vec![]).is_none());
+        let d = Distribution::from_values((0..=100).rev().map(f64::from).collect()).unwrap();
+        assert_eq!((d.tracks, d.min, d.median, d.max), (101, 0.0, 50.0, 100.0));
+        assert_eq!((d.p5, d.p95), (5.0, 95.0));
+        let groove = get("groove").unwrap();
+        assert!(sanity_warnings(groove, &d).is_empty());
+
+        // The old valence: two thirds of the library stuck at 20-30
+        let squeezed = Distribution::from_values(vec![20.0, 22.0, 25.0, 28.0, 30.0]).unwrap();
+        assert_eq!(sanity_warnings(groove, &squeezed).len(), 1);
+    }
+
+    #[test]
+    fn test_get_and_legend() {
+        let b = get("build quality").unwrap();
+        assert_eq!(b.column, "build_quality_score");
+        assert_eq!(get("TRN").unwrap().name, "transcendence");
+        assert_eq!(get("groove_score").unwrap().short, "Grv");
+        assert!(get("vibes").is_none());
+        assert_eq!(
+            legend(&select(&["groove", "improvisation", "energy"]), 2),
+            "Grv=Groove  Imp=Improvisation\nEng=Energy"
+        );
+    }
+}
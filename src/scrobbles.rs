@@ -0,0 +1,447 @@
+//! Listening history from scrobble exports.
+//!
+//! Last.fm CSV exports (`artist,album,title,date`, with or without a header row)
+//! and ListenBrainz exports (a JSON array or JSON Lines of listens) are matched to
+//! tracks by title and *show* date: the date in the album/release name
+//! ("1977-05-08 Barton Hall"), else one in the track title. Scrobbles without a
+//! show date can't be told apart from other performances of the song and are
+//! skipped. Play counts land in `track_plays`, one row per track and source;
+//! re-importing an export replaces that source's counts.
+
+use crate::dates::{DateOrder, find_date};
+use crate::db::Database;
+use crate::db::columns::{NOT_GARBAGE, TRACK_SCORE_SELECT, map_track_score};
+use crate::db::models::TrackScore;
+use rusqlite::params;
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ScrobbleError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Invalid ListenBrainz export: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+}
+
+/// Which service an export came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrobbleSource {
+    LastFm,
+    ListenBrainz,
+}
+
+impl ScrobbleSource {
+    /// Key stored in `track_plays.source`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScrobbleSource::LastFm => "lastfm",
+            ScrobbleSource::ListenBrainz => "listenbrainz",
+        }
+    }
+}
+
+/// One listen.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scrobble {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    /// When it was played, as `YYYY-MM-DD HH:MM:SS` (UTC for ListenBrainz).
+    pub listened_at: Option<String>,
+}
+
+impl Scrobble {
+    /// The show the listen was of: a date in the album name, else in the title.
+    pub fn show_date(&self) -> Option<String> {
+        self.album
+            .as_deref()
+            .and_then(|a| find_date(a, DateOrder::default()))
+            .or_else(|| find_date(&self.title, DateOrder::default()))
+    }
+}
+
+/// Outcome of an import.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub scrobbles: usize,
+    pub matched: usize,
+    /// No show date in the album or title.
+    pub undated: usize,
+    /// Dated, but no analyzed track of that song on that date.
+    pub unmatched: usize,
+    /// Distinct tracks with plays.
+    pub tracks: usize,
+}
+
+/// Read an export, telling the format from its content (JSON means ListenBrainz).
+pub fn load(path: &Path) -> Result<(ScrobbleSource, Vec<Scrobble>), ScrobbleError> {
+    let text = std::fs::read_to_string(path).map_err(|source| ScrobbleError::Io {
+        path: path.display().to_string(),
+        source,
+    })?;
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        Ok((ScrobbleSource::ListenBrainz, parse_listenbrainz(&text)?))
+    } else {
+        Ok((ScrobbleSource::LastFm, parse_lastfm_csv(&text)))
+    }
+}
+
+/// Parse a Last.fm CSV export. With a header row, columns are found by name
+/// (`artist`, `album`, `track`/`title`/`name`, `date`/`time`/`uts`); without
+/// one they're taken as artist, album, title, date.
+pub fn parse_lastfm_csv(text: &str) -> Vec<Scrobble> {
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty()).peekable();
+    let mut cols = (0, Some(1), 2, Some(3));
+    if let Some(first) = lines.peek() {
+        let header: Vec<String> = split_csv_line(first)
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect();
+        let find = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+        if let (Some(artist), Some(title)) = (
+            find(&["artist", "artist_name"]),
+            find(&["track", "title", "name", "track_name"]),
+        ) {
+            cols = (
+                artist,
+                find(&["album", "album_name", "release"]),
+                title,
+                find(&["date", "time", "uts", "listened_at"]),
+            );
+            lines.next();
+        }
+    }
+
+    let (artist, album, title, date) = cols;
+    lines
+        .filter_map(|line| {
+            let fields = split_csv_line(line);
+            let get = |i: usize| fields.get(i).map(|f| f.trim()).filter(|f| !f.is_empty());
+            Some(Scrobble {
+                artist: get(artist).unwrap_or_default().to_string(),
+                title: get(title)?.to_string(),
+                album: album.and_then(get).map(str::to_string),
+                listened_at: date.and_then(get).and_then(parse_lastfm_time),
+            })
+        })
+        .collect()
+}
+
+/// Last.fm export timestamps: "31 Jan 2023 21:04" or unix seconds.
+fn parse_lastfm_time(value: &str) -> Option<String> {
+    if let Ok(secs) = value.parse::<i64>() {
+        return unix_time(secs);
+    }
+    chrono::NaiveDateTime::parse_from_str(value, "%d %b %Y %H:%M")
+        .ok()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn unix_time(secs: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(secs, 0).map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+/// Split one CSV line, honoring double-quoted fields with `""` escapes.
+pub(crate) fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Parse a ListenBrainz export: a JSON array of listens, or one listen per line.
+pub fn parse_listenbrainz(text: &str) -> Result<Vec<Scrobble>, ScrobbleError> {
+    let listens: Vec<serde_json::Value> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text)?
+    } else {
+        text.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+
+    Ok(listens
+        .iter()
+        .filter_map(|listen| {
+            let meta = listen.get("track_metadata")?;
+            let text = |key: &str| {
+                meta.get(key)
+                    .and_then(|v| v.as_str())
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+            };
+            Some(Scrobble {
+                artist: text("artist_name").unwrap_or_default(),
+                title: text("track_name")?,
+                album: text("release_name"),
+                listened_at: listen
+                    .get("listened_at")
+                    .and_then(|v| v.as_i64())
+                    .and_then(unix_time),
+            })
+        })
+        .collect())
+}
+
+/// Song title reduced for matching: lowercase alphanumerics, segue arrows and
+/// anything after them dropped ("Scarlet Begonias ->" → "scarletbegonias").
+pub fn normalize_song(title: &str) -> String {
+    let title = title.split("->").next().unwrap_or(title);
+    let title = title.split('>').next().unwrap_or(title);
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Shortest normalized title allowed to match by prefix ("Drums" vs "Drums/Space").
+const MIN_PREFIX_MATCH: usize = 4;
+
+/// Pick the track a scrobble refers to among a show's tracks (id, title, band):
+/// an exact normalized title first, then one title being a prefix of the other.
+/// Tracks of a different band than the scrobble's artist are never picked.
+fn match_track(scrobble: &Scrobble, candidates: &[(i64, String, Option<String>)]) -> Option<i64> {
+    let wanted = normalize_song(&scrobble.title);
+    if wanted.is_empty() {
+        return None;
+    }
+    let artist = (!scrobble.artist.is_empty())
+        .then(|| crate::bands::registry().resolve_canonical_name(&scrobble.artist));
+    let same_band = |band: &Option<String>| match (band, &artist) {
+        (Some(b), Some(a)) => b.eq_ignore_ascii_case(a),
+        _ => true,
+    };
+    let songs: Vec<(i64, String)> = candidates
+        .iter()
+        .filter(|(_, _, band)| same_band(band))
+        .map(|(id, title, _)| (*id, normalize_song(title)))
+        .collect();
+
+    songs
+        .iter()
+        .find(|(_, song)| *song == wanted)
+        .or_else(|| {
+            songs.iter().find(|(_, song)| {
+                song.len().min(wanted.len()) >= MIN_PREFIX_MATCH
+                    && (song.starts_with(&wanted) || wanted.starts_with(song.as_str()))
+            })
+        })
+        .map(|(id, _)| *id)
+}
+
+/// Match scrobbles to tracks and replace the source's play counts.
+pub fn import(
+    db: &Database,
+    source: ScrobbleSource,
+    scrobbles: &[Scrobble],
+) -> Result<ImportSummary, ScrobbleError> {
+    let mut summary = ImportSummary {
+        scrobbles: scrobbles.len(),
+        ..Default::default()
+    };
+    let mut shows: HashMap<String, Vec<(i64, String, Option<String>)>> = HashMap::new();
+    let mut plays: HashMap<i64, (i64, Option<String>)> = HashMap::new();
+
+    for s in scrobbles {
+        let Some(date) = s.show_date() else {
+            summary.undated += 1;
+            continue;
+        };
+        if !shows.contains_key(&date) {
+            let tracks = db.tracks_for_scrobbles(&date)?;
+            shows.insert(date.clone(), tracks);
+        }
+        match match_track(s, &shows[&date]) {
+            Some(id) => {
+                summary.matched += 1;
+                let entry = plays.entry(id).or_insert((0, None));
+                entry.0 += 1;
+                if s.listened_at > entry.1 {
+                    entry.1 = s.listened_at.clone();
+                }
+            }
+            None => summary.unmatched += 1,
+        }
+    }
+
+    summary.tracks = plays.len();
+    db.replace_plays(source.as_str(), &plays)?;
+    Ok(summary)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// A show's analyzed tracks as (id, title, band), for scrobble matching.
+    fn tracks_for_scrobbles(
+        &self,
+        date: &str,
+    ) -> crate::db::Result<Vec<(i64, String, Option<String>)>> {
+        let sql = format!(
+            "SELECT t.id, COALESCE(t.parsed_title, t.title, ''), t.parsed_band
+             FROM tracks t
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE (t.parsed_date = ?1 OR t.date = ?1)
+               AND {NOT_GARBAGE}
+             ORDER BY t.id"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params![date], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Replace a source's play counts: track id → (plays, last played).
+    pub fn replace_plays(
+        &self,
+        source: &str,
+        plays: &HashMap<i64, (i64, Option<String>)>,
+    ) -> crate::db::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM track_plays WHERE source = ?1", params![source])?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO track_plays (track_id, source, play_count, last_played)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (id, (count, last)) in plays {
+                stmt.execute(params![id, source, count, last])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Play counts (all sources) by file path, the key `TrackScore` carries.
+    pub fn play_counts(&self) -> crate::db::Result<HashMap<String, i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.file_path, SUM(p.play_count)
+             FROM track_plays p
+             JOIN tracks t ON t.id = p.track_id
+             GROUP BY t.id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Most played tracks with their scores and play counts, most played first.
+    pub fn most_played(&self, limit: usize) -> crate::db::Result<Vec<(TrackScore, i64)>> {
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}, SUM(p.play_count) AS plays
+             FROM track_plays p
+             JOIN tracks t ON t.id = p.track_id
+             JOIN analysis_results a ON a.track_id = t.id
+             GROUP BY t.id
+             ORDER BY plays DESC, t.parsed_date
+             LIMIT {limit}"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map([], |row| Ok((map_track_score(row)?, row.get(19)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lastfm_csv() {
+        let headerless =
+            "Grateful Dead,1977-05-08 Barton Hall,\"Scarlet Begonias ->\",31 Jan 2023 21:04\n";
+        let s = &parse_lastfm_csv(headerless)[0];
+        assert_eq!(s.title, "Scarlet Begonias ->");
+        assert_eq!(s.listened_at.as_deref(), Some("2023-01-31 21:04:00"));
+        assert_eq!(s.show_date().as_deref(), Some("1977-05-08"));
+
+        let with_header = "uts,artist,album,track\n1675199040,Phish,\"Live, 12/31/95\",Tweezer\n";
+        let s = &parse_lastfm_csv(with_header)[0];
+        assert_eq!(s.artist, "Phish");
+        assert_eq!(s.album.as_deref(), Some("Live, 12/31/95"));
+        assert_eq!(s.title, "Tweezer");
+    }
+
+    #[test]
+    fn test_parse_listenbrainz() {
+        let json = r#"[{"listened_at": 1675199040, "track_metadata":
+            {"artist_name": "Grateful Dead", "track_name": "Dark Star",
+             "release_name": "gd1972-08-27.sbd.miller"}}]"#;
+        let s = &parse_listenbrainz(json).unwrap()[0];
+        assert_eq!(s.title, "Dark Star");
+        assert_eq!(s.show_date().as_deref(), Some("1972-08-27"));
+        assert_eq!(s.listened_at.as_deref(), Some("2023-01-31 21:04:00"));
+
+        let jsonl =
+            "{\"track_metadata\": {\"artist_name\": \"Phish\", \"track_name\": \"Ghost\"}}\n";
+        assert_eq!(parse_listenbrainz(jsonl).unwrap()[0].show_date(), None);
+    }
+
+    #[test]
+    fn test_match_track() {
+        let show = vec![
+            (
+                1,
+                "Scarlet Begonias ->".to_string(),
+                Some("Grateful Dead".to_string()),
+            ),
+            (
+                2,
+                "Fire on the Mountain".to_string(),
+                Some("Grateful Dead".to_string()),
+            ),
+            (3, "Drums/Space".to_string(), None),
+        ];
+        let scrobble = |artist: &str, title: &str| Scrobble {
+            artist: artist.into(),
+            title: title.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            match_track(&scrobble("Grateful Dead", "Scarlet Begonias"), &show),
+            Some(1)
+        );
+        assert_eq!(
+            match_track(&scrobble("", "fire on the mountain"), &show),
+            Some(2)
+        );
+        assert_eq!(
+            match_track(&scrobble("Grateful Dead", "Drums"), &show),
+            Some(3)
+        );
+        // Another band's cover isn't matched
+        assert_eq!(
+            match_track(&scrobble("Phish", "Fire on the Mountain"), &show),
+            None
+        );
+    }
+}
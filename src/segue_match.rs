@@ -0,0 +1,350 @@
+//! Segue matching: which songs could follow (or lead into) a track.
+//!
+//! Feature similarity compares whole tracks; a segue only cares about the
+//! seam. Each analyzed track keeps a summary of its first and last minute
+//! (`analyzer::edges`), and a candidate is scored by how well the seed's tail
+//! runs into its head (or its tail into the seed's head, for `--before`):
+//!
+//! - harmonic: cosine distance between the two pitch-class profiles,
+//! - tempo: the gap between the two tempos, half and double time allowed,
+//! - level and brightness: how big a jump in loudness and timbre the seam is.
+//!
+//! An edge without its own tempo (backfilled by `extract-boundaries`, which
+//! has no onsets) uses the track's overall tempo.
+
+use crate::analyzer::edges::{Edge, Edges};
+use crate::chroma::{chroma_cosine_distance, dominant_pitch_class};
+use crate::db::Database;
+use crate::db::columns::{MISSING_TITLE, NOT_GARBAGE, TRACK_SCORE_SELECT, map_track_score};
+use crate::db::models::TrackScore;
+use rusqlite::{OptionalExtension, params};
+use std::collections::HashSet;
+
+/// Share of the distance from the pitch-class profiles.
+const HARMONIC_WEIGHT: f64 = 0.5;
+/// Share of the distance from the tempo gap.
+const TEMPO_WEIGHT: f64 = 0.3;
+/// Share of the distance from the level jump.
+const LEVEL_WEIGHT: f64 = 0.1;
+/// Share of the distance from the brightness jump.
+const BRIGHTNESS_WEIGHT: f64 = 0.1;
+
+/// Chroma cosine distance at which two edges count as harmonically unrelated.
+const HARMONIC_SCALE: f64 = 0.3;
+/// Tempo gap, in octaves, at which two edges count as unrelated (~10%).
+const TEMPO_SCALE_OCTAVES: f64 = 0.14;
+/// Level jump at which two edges count as unrelated, dB.
+const LEVEL_SCALE_DB: f64 = 12.0;
+/// Tempo term when either side's tempo is unknown.
+const UNKNOWN_TEMPO: f64 = 0.5;
+
+/// How well one edge runs into another. Each term is 0 (seamless) to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegueFit {
+    pub harmonic: f64,
+    pub tempo: f64,
+    pub level: f64,
+    pub brightness: f64,
+    /// Weighted sum of the terms, 0-1.
+    pub distance: f64,
+}
+
+impl SegueFit {
+    /// 0-100, higher is a smoother seam.
+    pub fn score(&self) -> f64 {
+        100.0 * (1.0 - self.distance)
+    }
+}
+
+/// Score the seam from `from` (an ending) into `to` (an opening).
+pub fn fit(from: &Edge, to: &Edge) -> SegueFit {
+    let harmonic = (chroma_cosine_distance(&from.chroma, &to.chroma) / HARMONIC_SCALE).min(1.0);
+    let tempo = match (from.tempo, to.tempo) {
+        (Some(a), Some(b)) if a > 0.0 && b > 0.0 => {
+            // Half or double time is as easy a move as the same tempo
+            let octaves = (b / a).log2().abs();
+            let off = (octaves - octaves.round()).abs();
+            (off / TEMPO_SCALE_OCTAVES).min(1.0)
+        }
+        _ => UNKNOWN_TEMPO,
+    };
+    let level = ((from.level_db - to.level_db).abs() / LEVEL_SCALE_DB).min(1.0);
+    let brightness = if from.brightness + to.brightness > 0.0 {
+        (2.0 * (from.brightness - to.brightness).abs() / (from.brightness + to.brightness)).min(1.0)
+    } else {
+        0.0
+    };
+    SegueFit {
+        harmonic,
+        tempo,
+        level,
+        brightness,
+        distance: HARMONIC_WEIGHT * harmonic
+            + TEMPO_WEIGHT * tempo
+            + LEVEL_WEIGHT * level
+            + BRIGHTNESS_WEIGHT * brightness,
+    }
+}
+
+/// Which way to look from the seed, and how widely.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SuggestOptions {
+    /// Songs that lead into the seed, rather than follow it.
+    pub before: bool,
+    /// Candidates from every band, not just the seed's.
+    pub all_bands: bool,
+    /// Every performance of a song, not just its best fit.
+    pub all_versions: bool,
+}
+
+/// A candidate and how well it joins the seed.
+#[derive(Debug, Clone)]
+pub struct SegueSuggestion {
+    pub track_id: i64,
+    pub track: TrackScore,
+    pub fit: SegueFit,
+    /// Dominant pitch class and tempo on each side of the seam, in playing
+    /// order: (earlier track's tail, later track's head).
+    pub keys: (&'static str, &'static str),
+    pub tempos: (Option<f64>, Option<f64>),
+}
+
+/// Songs that could follow the seed (or precede it, with `before`), best fit
+/// first. Other performances of the seed's own song are never suggested.
+/// None if the seed has no stored edges.
+pub fn suggest(
+    db: &Database,
+    seed_id: i64,
+    limit: usize,
+    opts: SuggestOptions,
+) -> crate::db::Result<Option<Vec<SegueSuggestion>>> {
+    let Some(seed) = db.segue_candidate(seed_id)? else {
+        return Ok(None);
+    };
+    let band = (!opts.all_bands).then(|| seed.track.band.clone()).flatten();
+    let seed_song = song_key(&seed.track.title);
+
+    let mut ranked: Vec<SegueSuggestion> = db
+        .segue_candidates(band.as_deref())?
+        .into_iter()
+        .filter(|c| c.track_id != seed_id && song_key(&c.track.title) != seed_song)
+        .map(|c| {
+            let (from, to) = if opts.before {
+                (&c.edges.tail, &seed.edges.head)
+            } else {
+                (&seed.edges.tail, &c.edges.head)
+            };
+            SegueSuggestion {
+                track_id: c.track_id,
+                fit: fit(from, to),
+                keys: (
+                    dominant_pitch_class(&from.chroma),
+                    dominant_pitch_class(&to.chroma),
+                ),
+                tempos: (from.tempo, to.tempo),
+                track: c.track,
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.fit.distance.total_cmp(&b.fit.distance));
+    if !opts.all_versions {
+        let mut seen = HashSet::new();
+        ranked.retain(|s| seen.insert(song_key(&s.track.title)));
+    }
+    ranked.truncate(limit);
+    Ok(Some(ranked))
+}
+
+/// Key for "the same song": the lowercased title without part or segue markers.
+fn song_key(title: &str) -> String {
+    crate::track_groups::song_part(title).0.to_lowercase()
+}
+
+/// A titled, non-garbage track with stored edges.
+struct Candidate {
+    track_id: i64,
+    track: TrackScore,
+    edges: Edges,
+}
+
+fn map_candidate(row: &rusqlite::Row) -> rusqlite::Result<Candidate> {
+    let chroma = |i: usize| -> rusqlite::Result<[f64; 12]> {
+        let json: String = row.get(i)?;
+        serde_json::from_str(&json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, Box::new(e))
+        })
+    };
+    let edge = |i: usize| -> rusqlite::Result<Edge> {
+        Ok(Edge {
+            chroma: chroma(i)?,
+            level_db: row.get(i + 1)?,
+            brightness: row.get(i + 2)?,
+            tempo: row.get(i + 3)?,
+        })
+    };
+    Ok(Candidate {
+        track: map_track_score(row)?,
+        track_id: row.get(19)?,
+        edges: Edges {
+            head: edge(20)?,
+            tail: edge(24)?,
+        },
+    })
+}
+
+/// Columns after `TRACK_SCORE_SELECT` read by `map_candidate`. Edges without
+/// their own tempo fall back to the track's.
+const CANDIDATE_COLUMNS: &str = "t.id,
+     e.head_chroma, e.head_level_db, e.head_brightness,
+     COALESCE(e.head_tempo, a.tempo_bpm_corrected, a.tempo_bpm),
+     e.tail_chroma, e.tail_level_db, e.tail_brightness,
+     COALESCE(e.tail_tempo, a.tempo_bpm_corrected, a.tempo_bpm)";
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Store (or replace) a track's edge summaries.
+    pub fn store_segue_edges(&self, track_id: i64, edges: &Edges) -> crate::db::Result<()> {
+        let chroma = |e: &Edge| serde_json::to_string(&e.chroma).unwrap_or_default();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO segue_edges (
+                track_id, head_chroma, head_level_db, head_brightness, head_tempo,
+                tail_chroma, tail_level_db, tail_brightness, tail_tempo
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                track_id,
+                chroma(&edges.head),
+                edges.head.level_db,
+                edges.head.brightness,
+                edges.head.tempo,
+                chroma(&edges.tail),
+                edges.tail.level_db,
+                edges.tail.brightness,
+                edges.tail.tempo,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn segue_candidate(&self, track_id: i64) -> crate::db::Result<Option<Candidate>> {
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}, {CANDIDATE_COLUMNS}
+             FROM segue_edges e
+             JOIN tracks t ON t.id = e.track_id
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE t.id = ?1"
+        );
+        Ok(self
+            .conn
+            .query_row(&sql, [track_id], map_candidate)
+            .optional()?)
+    }
+
+    fn segue_candidates(&self, band: Option<&str>) -> crate::db::Result<Vec<Candidate>> {
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}, {CANDIDATE_COLUMNS}
+             FROM segue_edges e
+             JOIN tracks t ON t.id = e.track_id
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE {NOT_GARBAGE} AND NOT ({MISSING_TITLE})
+               AND (?1 IS NULL OR COALESCE(t.parsed_band, t.artist) = ?1)"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map([band], map_candidate)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(pitch: usize, tempo: Option<f64>, level_db: f64) -> Edge {
+        let mut chroma = [0.02; 12];
+        chroma[pitch] = 0.78;
+        Edge {
+            chroma,
+            level_db,
+            brightness: 0.1,
+            tempo,
+        }
+    }
+
+    #[test]
+    fn test_fit_terms() {
+        let a = edge(9, Some(120.0), -20.0);
+        assert!(fit(&a, &a).distance < 1e-9);
+        // Double time is a seamless tempo move
+        assert!(fit(&a, &edge(9, Some(240.0), -20.0)).tempo < 1e-9);
+        assert_eq!(fit(&a, &edge(9, Some(135.0), -20.0)).tempo, 1.0);
+        assert_eq!(fit(&a, &edge(9, None, -20.0)).tempo, UNKNOWN_TEMPO);
+        assert_eq!(fit(&a, &edge(2, Some(120.0), -20.0)).harmonic, 1.0);
+        assert!((fit(&a, &edge(9, Some(120.0), -26.0)).level - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format,
+                                     parsed_title, parsed_date, parsed_band)
+                 VALUES
+                    (1, '/m/1.flac', 1, '0', 'flac', 'China Cat Sunflower', '1974-06-18', 'Grateful Dead'),
+                    (2, '/m/2.flac', 1, '0', 'flac', 'I Know You Rider', '1974-06-18', 'Grateful Dead'),
+                    (3, '/m/3.flac', 1, '0', 'flac', 'I Know You Rider', '1977-05-08', 'Grateful Dead'),
+                    (4, '/m/4.flac', 1, '0', 'flac', 'Stella Blue', '1974-06-18', 'Grateful Dead'),
+                    (5, '/m/5.flac', 1, '0', 'flac', 'China Cat Sunflower', '1977-05-08', 'Grateful Dead'),
+                    (6, '/m/6.flac', 1, '0', 'flac', 'Tweezer', '1997-11-17', 'Phish');
+                 INSERT INTO analysis_results (track_id, tempo_bpm)
+                 VALUES (1, 120), (2, 118), (3, 121), (4, 70), (5, 120), (6, 120);",
+            )
+            .unwrap();
+        let store = |id: i64, head: Edge, tail: Edge| {
+            db.store_segue_edges(id, &Edges { head, tail }).unwrap()
+        };
+        // China Cat ends in D around 120; Rider opens there
+        store(1, edge(9, None, -20.0), edge(2, Some(120.0), -18.0));
+        store(2, edge(2, Some(119.0), -18.0), edge(9, None, -20.0));
+        store(3, edge(2, None, -22.0), edge(9, None, -20.0));
+        store(4, edge(7, Some(70.0), -30.0), edge(7, None, -30.0));
+        store(5, edge(2, Some(120.0), -18.0), edge(2, Some(120.0), -18.0));
+        store(6, edge(2, Some(120.0), -18.0), edge(2, Some(120.0), -18.0));
+
+        let after = suggest(&db, 1, 10, SuggestOptions::default())
+            .unwrap()
+            .unwrap();
+        let picks: Vec<i64> = after.iter().map(|s| s.track_id).collect();
+        // Best Rider only, no other China Cat, no Phish
+        assert_eq!(picks, vec![2, 4]);
+        assert_eq!(after[0].keys, ("D", "D"));
+        // Track 3's head has no tempo of its own: the track's 121 stands in
+        let all = SuggestOptions {
+            all_versions: true,
+            ..Default::default()
+        };
+        let picks: Vec<i64> = suggest(&db, 1, 10, all)
+            .unwrap()
+            .unwrap()
+            .iter()
+            .map(|s| s.track_id)
+            .collect();
+        assert_eq!(picks, vec![2, 3, 4]);
+
+        let before = SuggestOptions {
+            before: true,
+            ..Default::default()
+        };
+        let leads_in = suggest(&db, 2, 1, before).unwrap().unwrap();
+        assert_eq!(leads_in[0].track.title, "China Cat Sunflower");
+
+        let everyone = SuggestOptions {
+            all_bands: true,
+            ..Default::default()
+        };
+        assert_eq!(suggest(&db, 1, 10, everyone).unwrap().unwrap().len(), 3);
+        assert!(suggest(&db, 99, 10, everyone).unwrap().is_none());
+    }
+}
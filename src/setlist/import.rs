@@ -151,8 +151,9 @@ pub fn import_setlists(
     let mut songs = 0usize;
 
     for e in entries {
+        let song = crate::title_normalize::normalize(&e.song);
         stmt.execute(rusqlite::params![
-            e.date, e.set_num, e.position, e.song, e.segued, e.venue, e.city, e.state, e.source,
+            e.date, e.set_num, e.position, song, e.segued, e.venue, e.city, e.state, e.source,
         ])
         .with_context(|| {
             format!(
@@ -192,10 +193,10 @@ pub fn import_setlists_append(db: &Database, entries: &[SetlistEntry]) -> Result
     let mut songs = 0usize;
 
     for e in entries {
+        let song = crate::title_normalize::normalize(&e.song);
         let inserted = stmt
             .execute(rusqlite::params![
-                e.date, e.set_num, e.position, e.song, e.segued, e.venue, e.city, e.state,
-                e.source,
+                e.date, e.set_num, e.position, song, e.segued, e.venue, e.city, e.state, e.source,
             ])
             .with_context(|| {
                 format!(
@@ -1,22 +1,69 @@
 pub mod import;
 pub mod phishin;
+pub mod setlistfm;
+pub mod validate;
 
 use std::collections::HashMap;
 use std::path::Path;
-use std::thread;
-use std::time::Duration;
+use std::sync::LazyLock;
 
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
 use regex::Regex;
+use rusqlite::OptionalExtension;
 use serde::Deserialize;
 
+use crate::archive_client::{ArchiveClient, ArchiveError};
+use crate::dates::DateOrder;
 use crate::db::Database;
+use crate::db::models::ShowNotes;
+use crate::paths;
+use crate::progress::Progress;
 
-/// Archive.org metadata API response (partial — we only need `files`).
+/// Archive.org metadata API response (partial — we only need `files` and item `metadata`).
 #[derive(Debug, Deserialize)]
 struct ArchiveMetadata {
     files: Option<Vec<ArchiveFile>>,
+    metadata: Option<ArchiveItemMetadata>,
+}
+
+/// Item-level metadata: show info and source provenance.
+/// Archive.org returns each field as either a string or an array of strings.
+/// Search results carry the same fields (see `discovery`).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct ArchiveItemMetadata {
+    date: Option<serde_json::Value>,
+    venue: Option<serde_json::Value>,
+    source: Option<serde_json::Value>,
+    lineage: Option<serde_json::Value>,
+    taper: Option<serde_json::Value>,
+    transferer: Option<serde_json::Value>,
+    description: Option<serde_json::Value>,
+    notes: Option<serde_json::Value>,
+}
+
+impl ArchiveItemMetadata {
+    pub(crate) fn into_show_notes(self, identifier: &str, local_dir: &str) -> ShowNotes {
+        ShowNotes {
+            identifier: identifier.to_string(),
+            local_dir: local_dir.to_string(),
+            // Item dates are sometimes full timestamps; keep the YYYY-MM-DD part
+            date: metadata_text(self.date).map(|d| d.chars().take(10).collect()),
+            venue: metadata_text(self.venue),
+            source: metadata_text(self.source),
+            lineage: metadata_text(self.lineage),
+            taper: metadata_text(self.taper),
+            transferer: metadata_text(self.transferer),
+            description: metadata_text(self.description),
+            notes: metadata_text(self.notes),
+        }
+    }
+}
+
+/// A fetched archive.org item: audio filename → title map plus item metadata.
+struct ArchiveItem {
+    identifier: String,
+    titles: HashMap<String, String>,
+    metadata: Option<ArchiveItemMetadata>,
 }
 
 /// A single file entry in archive.org metadata.
@@ -50,10 +97,44 @@ struct ArchiveSearchDoc {
 pub struct SetlistResult {
     pub directories_fetched: usize,
     pub titles_updated: usize,
+    /// Matches below the confidence threshold, queued for `setlist --review`.
+    pub queued_for_review: usize,
+    pub notes_stored: usize,
     pub fetch_errors: usize,
     pub tracks_already_titled: usize,
 }
 
+/// A local file matched to an archive.org title.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleMatch {
+    pub title: String,
+    /// Strategy that matched: exact, stem, fuzzy, position, track-only, trailing-digits.
+    pub method: &'static str,
+    /// How likely the match is right, 0..1.
+    pub confidence: f64,
+}
+
+/// Matches at or above this confidence are applied without review by default.
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.7;
+
+/// Confidence lost by position matches when the folder has more local files
+/// than the archive item has positions — the numbering likely differs.
+const COUNT_MISMATCH_PENALTY: f64 = 0.25;
+
+/// Minimum stem similarity for a fuzzy filename match.
+const FUZZY_MIN_SIMILARITY: f64 = 0.8;
+
+/// A low-confidence match waiting for manual confirmation.
+#[derive(Debug, Clone)]
+pub struct PendingTitleMatch {
+    pub track_id: i64,
+    pub file_path: String,
+    pub title: String,
+    pub method: String,
+    pub confidence: f64,
+    pub identifier: Option<String>,
+}
+
 /// Run setlist lookups against archive.org to populate song titles.
 ///
 /// Groups tracks by parent directory, uses directory name as archive.org identifier,
@@ -64,39 +145,51 @@ pub struct SetlistResult {
 /// - Case differences in BTS dirs (bts → BTS)
 /// - Prefix differences in Phish dirs (ph → phish)
 /// - Filename differences via disc/track position matching
-pub fn lookup_setlists(db: &Database, dry_run: bool, rate_limit_ms: u64) -> Result<SetlistResult> {
+///
+/// Each match gets a confidence; below `min_confidence` it's queued for
+/// `setlist --review` instead of applied, since position guesses (especially
+/// trailing digits like "Boise11") can silently pick the wrong song.
+///
+/// Item descriptions and provenance (source, lineage, taper) are stored as show notes
+/// for every directory fetched. With `fetch_notes`, directories whose tracks are already
+/// titled are also fetched if they have no notes yet.
+pub fn lookup_setlists(
+    db: &Database,
+    dry_run: bool,
+    fetch_notes: bool,
+    min_confidence: f64,
+    client: &ArchiveClient,
+    progress: &dyn Progress,
+) -> Result<SetlistResult> {
     // Get all tracks missing titles (no parsed_title AND no tag title)
     let tracks = db
         .get_tracks_missing_titles()
         .context("Failed to query tracks missing titles")?;
 
-    if tracks.is_empty() {
+    if tracks.is_empty() && !fetch_notes {
         log::info!("All tracks already have titles");
         return Ok(SetlistResult {
             directories_fetched: 0,
             titles_updated: 0,
+            queued_for_review: 0,
+            notes_stored: 0,
             fetch_errors: 0,
             tracks_already_titled: 0,
         });
     }
 
-    // Group tracks by parent directory name (= archive.org identifier)
+    // Group tracks by parent directory name (= archive.org identifier). Stored paths
+    // are split on either separator so a library scanned on Windows groups the same.
     let mut by_dir: HashMap<String, Vec<(i64, String)>> = HashMap::new();
     let mut no_dir_count = 0;
 
     for (track_id, file_path) in &tracks {
-        let path = Path::new(file_path);
-        if let Some(parent) = path.parent() {
-            if let Some(dir_name) = parent.file_name() {
-                let dir = dir_name.to_string_lossy().to_string();
-                let filename = path
-                    .file_name()
-                    .map(|f| f.to_string_lossy().to_string())
-                    .unwrap_or_default();
-                by_dir.entry(dir).or_default().push((*track_id, filename));
-            } else {
-                no_dir_count += 1;
-            }
+        if let Some(dir) = paths::parent_name(file_path) {
+            let filename = paths::file_name(file_path).to_string();
+            by_dir
+                .entry(dir.to_string())
+                .or_default()
+                .push((*track_id, filename));
         } else {
             no_dir_count += 1;
         }
@@ -106,25 +199,37 @@ pub fn lookup_setlists(db: &Database, dry_run: bool, rate_limit_ms: u64) -> Resu
         log::warn!("{no_dir_count} tracks have no parent directory, skipping");
     }
 
-    println!(
+    progress.note(&format!(
         "Found {} tracks missing titles across {} directories",
         tracks.len(),
         by_dir.len()
-    );
+    ));
+
+    if fetch_notes {
+        let have_notes = db
+            .get_show_notes_dirs()
+            .context("Failed to query show notes")?;
+        let mut notes_only = 0;
+        for track in db.get_all_tracks().context("Failed to query tracks")? {
+            if let Some(dir) = paths::parent_name(&track.file_path) {
+                if !have_notes.contains(dir) && !by_dir.contains_key(dir) {
+                    by_dir.insert(dir.to_string(), Vec::new());
+                    notes_only += 1;
+                }
+            }
+        }
+        progress.note(&format!(
+            "Fetching show notes for {notes_only} more directories"
+        ));
+    }
 
-    let pb = ProgressBar::new(by_dir.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template(
-                "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} dirs ({eta} remaining) {msg}",
-            )
-            .unwrap()
-            .progress_chars("=>-"),
-    );
+    progress.start(by_dir.len() as u64, "dirs");
 
     let mut result = SetlistResult {
         directories_fetched: 0,
         titles_updated: 0,
+        queued_for_review: 0,
+        notes_stored: 0,
         fetch_errors: 0,
         tracks_already_titled: 0,
     };
@@ -134,60 +239,105 @@ pub fn lookup_setlists(db: &Database, dry_run: bool, rate_limit_ms: u64) -> Resu
     dirs.sort_by(|a, b| a.0.cmp(&b.0));
 
     for (dir_name, dir_tracks) in &dirs {
-        pb.set_message(dir_name.clone());
+        if client.budget_exhausted() {
+            progress.println("Request budget reached; stopping early (rerun to continue)");
+            break;
+        }
+        progress.set_message(dir_name);
+
+        match fetch_metadata_with_fallbacks(client, dir_name) {
+            Ok(item) => {
+                if let Some(meta) = item.metadata {
+                    if !dry_run {
+                        db.store_show_notes(&meta.into_show_notes(&item.identifier, dir_name))
+                            .with_context(|| {
+                                format!("Failed to store show notes for {dir_name}")
+                            })?;
+                        result.notes_stored += 1;
+                    }
+                }
 
-        match fetch_metadata_with_fallbacks(dir_name) {
-            Ok(file_map) if !file_map.is_empty() => {
+                let file_map = item.titles;
+                if file_map.is_empty() {
+                    // Identifier not found on archive.org, or no titled audio files
+                    if !dir_tracks.is_empty() {
+                        log::debug!("No audio files found for {dir_name}");
+                    }
+                    progress.inc(1);
+                    continue;
+                }
                 result.directories_fetched += 1;
 
                 // Build a position-based lookup as a fallback
                 let position_map = build_position_map(&file_map);
+                let counts_agree = dir_tracks.len() <= position_map.len();
 
                 for (track_id, filename) in dir_tracks {
-                    let title = match_title(filename, &file_map, &position_map);
-                    if let Some((title, method)) = title {
-                        if !dry_run {
-                            db.update_parsed_title(*track_id, &title).with_context(|| {
-                                format!("Failed to update title for track {track_id}")
-                            })?;
+                    let Some(m) = match_title(filename, &file_map, &position_map, counts_agree)
+                    else {
+                        log::debug!("  {filename}: no match in archive.org metadata");
+                        continue;
+                    };
+                    let applied = m.confidence >= min_confidence;
+                    if !dry_run {
+                        if applied {
+                            db.apply_title_match(*track_id, &m)
+                        } else {
+                            db.queue_title_match(*track_id, &m, &item.identifier)
                         }
+                        .with_context(|| format!("Failed to store title for track {track_id}"))?;
+                    }
+                    if applied {
                         result.titles_updated += 1;
-                        log::info!("  {filename} => {title} ({method})");
                     } else {
-                        log::debug!("  {filename}: no match in archive.org metadata");
+                        result.queued_for_review += 1;
                     }
+                    log::info!(
+                        "  {filename} => {} ({}, {:.2}{})",
+                        m.title,
+                        m.method,
+                        m.confidence,
+                        if applied { "" } else { ", needs review" }
+                    );
                 }
             }
-            Ok(_) => {
-                // Empty response — identifier not found on archive.org
-                log::debug!("No audio files found for {dir_name}");
-            }
             Err(e) => {
                 result.fetch_errors += 1;
                 log::warn!("Failed to fetch metadata for {dir_name}: {e}");
             }
         }
 
-        pb.inc(1);
-
-        // Rate limit between requests to be polite
-        thread::sleep(Duration::from_millis(rate_limit_ms));
+        progress.inc(1);
     }
 
-    pb.finish_with_message("done");
+    if !dry_run {
+        db.apply_title_aliases()
+            .context("Failed to apply title aliases")?;
+    }
+    progress.finish("done");
     Ok(result)
 }
 
-/// Try to match a local filename to an archive.org title using multiple strategies.
-/// Returns (title, match_method) or None.
-fn match_title<'a>(
+/// Try to match a local filename to an archive.org title using multiple strategies,
+/// most reliable first. `counts_agree` is false when the folder has more local files
+/// than the item has positions, which makes position matches suspect.
+fn match_title(
     filename: &str,
-    file_map: &'a HashMap<String, String>,
-    position_map: &'a HashMap<(u32, u32), String>,
-) -> Option<(String, &'static str)> {
+    file_map: &HashMap<String, String>,
+    position_map: &HashMap<(u32, u32), String>,
+    counts_agree: bool,
+) -> Option<TitleMatch> {
+    let matched = |title: &String, method, confidence: f64| {
+        Some(TitleMatch {
+            title: title.clone(),
+            method,
+            confidence,
+        })
+    };
+
     // Strategy 1: Exact filename match
     if let Some(title) = file_map.get(filename) {
-        return Some((title.clone(), "exact"));
+        return matched(title, "exact", 1.0);
     }
 
     // Strategy 2: Stem match (strip extension)
@@ -203,22 +353,149 @@ fn match_title<'a>(
                 .unwrap_or(false)
         });
         if let Some((_, title)) = found {
-            return Some((title.clone(), "stem"));
+            return matched(title, "stem", 0.95);
         }
     }
 
-    // Strategy 3: Position match (disc/track numbers)
-    if let Some(pos) = extract_disc_track(filename) {
-        if let Some(title) = position_map.get(&pos) {
-            return Some((title.clone(), "position"));
-        }
-        // If disc extraction failed (disc=0), try matching track-only
-        if pos.0 == 0 {
-            // Find any entry with matching track number
-            let found = position_map.iter().find(|((_, t), _)| *t == pos.1);
-            if let Some((_, title)) = found {
-                return Some((title.clone(), "track-only"));
+    // Strategies 3 and 4: fuzzy stem and position. Either can be the better guess
+    // (a renamed file with intact numbering vs. a taper-renumbered one), so keep
+    // whichever is more confident.
+    let fuzzy = stem.as_deref().and_then(|stem| match_fuzzy(stem, file_map));
+    let position = match_position(filename, position_map, counts_agree);
+    match (fuzzy, position) {
+        (Some(f), Some(p)) => Some(if f.confidence >= p.confidence { f } else { p }),
+        (f, p) => f.or(p),
+    }
+}
+
+/// Fuzzy stem match, for files the taper renamed slightly (bitrate suffix, added
+/// venue). Candidates whose disc/track numbers contradict the local file are
+/// skipped, since numbered stems otherwise differ by a single character.
+fn match_fuzzy(stem: &str, file_map: &HashMap<String, String>) -> Option<TitleMatch> {
+    let local_pos = extract_position(stem).map(|(pos, _)| pos);
+    let mut scored: Vec<(f64, &String)> = file_map
+        .iter()
+        .filter_map(|(name, title)| {
+            let candidate = Path::new(name).file_stem()?.to_string_lossy();
+            let candidate_pos = extract_position(&candidate).map(|(pos, _)| pos);
+            if let (Some(a), Some(b)) = (local_pos, candidate_pos) {
+                if a != b {
+                    return None;
+                }
             }
+            let similarity = stem_similarity(stem, &candidate);
+            (similarity >= FUZZY_MIN_SIMILARITY).then_some((similarity, title))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let (best, title) = *scored.first()?;
+    // Scale the similarity band onto 0.6..0.9 confidence
+    let mut confidence = 0.6 + 0.3 * (best - FUZZY_MIN_SIMILARITY) / (1.0 - FUZZY_MIN_SIMILARITY);
+    // A near-tie with a different title means we can't tell them apart
+    let contested = scored
+        .iter()
+        .skip(1)
+        .any(|(s, t)| *t != title && best - s < 0.05);
+    if contested {
+        confidence -= 0.25;
+    }
+    Some(TitleMatch {
+        title: title.clone(),
+        method: "fuzzy",
+        confidence,
+    })
+}
+
+/// Similarity of two filename stems, 0..1: the better of normalized Levenshtein
+/// and a token-set ratio (shared tokens over the smaller token set), so both
+/// small typos and added/removed words score high.
+fn stem_similarity(a: &str, b: &str) -> f64 {
+    let a = a.to_lowercase();
+    let b = b.to_lowercase();
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 0.0;
+    }
+    let edit = 1.0 - levenshtein(&a, &b) as f64 / max_len as f64;
+
+    let tokens = |s: &str| -> std::collections::HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let (ta, tb) = (tokens(&a), tokens(&b));
+    let smaller = ta.len().min(tb.len());
+    // A single shared token ("01") says nothing
+    let token_set = if smaller >= 2 {
+        ta.intersection(&tb).count() as f64 / smaller as f64
+    } else {
+        0.0
+    };
+    edit.max(token_set)
+}
+
+/// Character-level edit distance.
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = substitute.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// Position match (disc/track numbers). A bare trailing number ("Boise11") is the
+/// weakest evidence: it may be a venue or a date fragment.
+fn match_position(
+    filename: &str,
+    position_map: &HashMap<(u32, u32), String>,
+    counts_agree: bool,
+) -> Option<TitleMatch> {
+    let matched = |title: &String, method, confidence: f64| {
+        Some(TitleMatch {
+            title: title.clone(),
+            method,
+            confidence,
+        })
+    };
+    let (pos, pattern) = extract_position(filename)?;
+    let trailing = pattern == PositionPattern::Trailing;
+    let penalty = if counts_agree {
+        0.0
+    } else {
+        COUNT_MISMATCH_PENALTY
+    };
+    if let Some(title) = position_map.get(&pos) {
+        let (method, base) = if trailing {
+            ("trailing-digits", 0.5)
+        } else if pos.0 == 0 {
+            ("position", 0.7)
+        } else {
+            ("position", 0.85)
+        };
+        return matched(title, method, base - penalty);
+    }
+    // If disc extraction failed (disc=0), try matching track-only
+    if pos.0 == 0 {
+        let mut found = position_map.iter().filter(|((_, t), _)| *t == pos.1);
+        if let Some((_, title)) = found.next() {
+            // The same track number on several discs is a coin flip
+            let ambiguous = found.next().is_some();
+            let (method, base) = if trailing {
+                ("trailing-digits", 0.4)
+            } else {
+                ("track-only", 0.65)
+            };
+            let base = if ambiguous { base - 0.3 } else { base };
+            return matched(title, method, (base - penalty).max(0.05));
         }
     }
 
@@ -248,6 +525,20 @@ fn build_position_map(file_map: &HashMap<String, String>) -> HashMap<(u32, u32),
 /// - `disc one/08 - reggae jam.mp3` → (1, 8) (archive.org naming)
 /// - `disc two/03 - title.flac` → (2, 3) (archive.org naming)
 fn extract_disc_track(filename: &str) -> Option<(u32, u32)> {
+    extract_position(filename).map(|(pos, _)| pos)
+}
+
+/// Which filename pattern yielded a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PositionPattern {
+    /// Explicit disc/set and track markers (`d1t01`, `disc one/08`, `_t40`).
+    Marked,
+    /// Bare digits at the end of the name (`Boise11`).
+    Trailing,
+}
+
+/// [`extract_disc_track`], plus which pattern matched.
+fn extract_position(filename: &str) -> Option<((u32, u32), PositionPattern)> {
     let name = Path::new(filename)
         .file_stem()
         .map(|s| s.to_string_lossy().to_string())
@@ -266,7 +557,7 @@ fn extract_disc_track(filename: &str) -> Option<(u32, u32)> {
         };
         let track: u32 = caps[2].parse().unwrap_or(0);
         if disc > 0 && track > 0 {
-            return Some((disc, track));
+            return Some(((disc, track), PositionPattern::Marked));
         }
     }
 
@@ -276,7 +567,7 @@ fn extract_disc_track(filename: &str) -> Option<(u32, u32)> {
         let disc: u32 = caps[1].parse().unwrap_or(0);
         let track: u32 = caps[2].parse().unwrap_or(0);
         if track > 0 {
-            return Some((disc, track));
+            return Some(((disc, track), PositionPattern::Marked));
         }
     }
 
@@ -285,7 +576,7 @@ fn extract_disc_track(filename: &str) -> Option<(u32, u32)> {
     if let Some(caps) = re_t.captures(&name) {
         let track: u32 = caps[1].parse().unwrap_or(0);
         if track > 0 {
-            return Some((0, track));
+            return Some(((0, track), PositionPattern::Marked));
         }
     }
 
@@ -294,7 +585,7 @@ fn extract_disc_track(filename: &str) -> Option<(u32, u32)> {
     if let Some(caps) = re_trailing.captures(&name) {
         let track: u32 = caps[1].parse().unwrap_or(0);
         if track > 0 && track <= 50 {
-            return Some((0, track));
+            return Some(((0, track), PositionPattern::Trailing));
         }
     }
 
@@ -302,7 +593,9 @@ fn extract_disc_track(filename: &str) -> Option<(u32, u32)> {
 }
 
 /// Fetch metadata with identifier normalization and search fallback.
-fn fetch_metadata_with_fallbacks(dir_name: &str) -> Result<HashMap<String, String>> {
+/// If no candidate has titled audio files, the normalized item is returned as-is
+/// (it may still carry item metadata for show notes).
+fn fetch_metadata_with_fallbacks(client: &ArchiveClient, dir_name: &str) -> Result<ArchiveItem> {
     // Step 1: Try the normalized identifier
     let normalized = crate::bands::registry().normalize_identifier(dir_name);
 
@@ -310,43 +603,32 @@ fn fetch_metadata_with_fallbacks(dir_name: &str) -> Result<HashMap<String, Strin
         log::debug!("Normalized identifier: {dir_name} → {normalized}");
     }
 
-    let map = fetch_archive_metadata(&normalized)?;
-    if !map.is_empty() {
-        return Ok(map);
+    let item = fetch_archive_metadata(client, &normalized)?;
+    if !item.titles.is_empty() {
+        return Ok(item);
     }
 
     // Step 2: If normalization changed it, also try the original
     if normalized != dir_name {
-        let map = fetch_archive_metadata(dir_name)?;
-        if !map.is_empty() {
-            return Ok(map);
+        let original = fetch_archive_metadata(client, dir_name)?;
+        if !original.titles.is_empty() {
+            return Ok(original);
         }
     }
 
     // Step 3: Search fallback — extract date and band, search archive.org
-    if let Some(map) = try_search_fallback(dir_name)? {
-        return Ok(map);
+    if let Some(found) = try_search_fallback(client, dir_name)? {
+        return Ok(found);
     }
 
-    Ok(HashMap::new())
+    Ok(item)
 }
 
 /// Try to find an archive.org identifier by searching for the show date.
-fn try_search_fallback(dir_name: &str) -> Result<Option<HashMap<String, String>>> {
+fn try_search_fallback(client: &ArchiveClient, dir_name: &str) -> Result<Option<ArchiveItem>> {
     // Extract a date from the directory name
-    let re_date = Regex::new(r"(\d{4})-(\d{2})-(\d{2})").unwrap();
-    let date = if let Some(caps) = re_date.captures(dir_name) {
-        format!("{}-{}-{}", &caps[1], &caps[2], &caps[3])
-    } else {
-        // Try 2-digit year
-        let re_date2 = Regex::new(r"(\d{2})-(\d{2})-(\d{2})").unwrap();
-        if let Some(caps) = re_date2.captures(dir_name) {
-            let yy: u32 = caps[1].parse().unwrap_or(0);
-            let century = if yy <= 25 { "20" } else { "19" };
-            format!("{century}{}-{}-{}", &caps[1], &caps[2], &caps[3])
-        } else {
-            return Ok(None);
-        }
+    let Some(date) = crate::dates::find_date(dir_name, DateOrder::default()) else {
+        return Ok(None);
     };
 
     // Determine the band/creator for the search
@@ -361,11 +643,11 @@ fn try_search_fallback(dir_name: &str) -> Result<Option<HashMap<String, String>>
         "https://archive.org/advancedsearch.php?q=collection%3A{creator}+date%3A{date}&fl%5B%5D=identifier&rows=5&output=json"
     );
 
-    let response: ArchiveSearchResponse = match ureq::get(&url).call() {
-        Ok(mut resp) => resp
-            .body_mut()
-            .read_json()
-            .with_context(|| format!("Failed to parse search JSON for {dir_name}"))?,
+    let response: ArchiveSearchResponse = match client.get_json(&url) {
+        Ok(resp) => resp,
+        Err(e @ ArchiveError::Json { .. }) => {
+            return Err(e).with_context(|| format!("Failed to parse search JSON for {dir_name}"));
+        }
         Err(e) => {
             log::debug!("Search request failed for {dir_name}: {e}");
             return Ok(None);
@@ -382,16 +664,13 @@ fn try_search_fallback(dir_name: &str) -> Result<Option<HashMap<String, String>>
     // Try each search result until we find one with titled audio files
     for doc in &docs {
         if let Some(identifier) = &doc.identifier {
-            // Rate limit between attempts (reuse same rate limit)
-            thread::sleep(Duration::from_millis(500));
-
-            let map = fetch_archive_metadata(identifier)?;
-            if !map.is_empty() {
+            let item = fetch_archive_metadata(client, identifier)?;
+            if !item.titles.is_empty() {
                 log::info!(
                     "Search fallback found: {dir_name} → {identifier} ({} files)",
-                    map.len()
+                    item.titles.len()
                 );
-                return Ok(Some(map));
+                return Ok(Some(item));
             }
         }
     }
@@ -400,7 +679,7 @@ fn try_search_fallback(dir_name: &str) -> Result<Option<HashMap<String, String>>
 }
 
 /// Percent-encode characters that break archive.org URLs (spaces, parens, etc.)
-fn encode_identifier(id: &str) -> String {
+pub(crate) fn encode_identifier(id: &str) -> String {
     let mut out = String::with_capacity(id.len());
     for c in id.chars() {
         match c {
@@ -415,18 +694,14 @@ fn encode_identifier(id: &str) -> String {
     out
 }
 
-/// Fetch archive.org metadata for an identifier and return a filename -> title map.
-fn fetch_archive_metadata(identifier: &str) -> Result<HashMap<String, String>> {
+/// Fetch archive.org metadata for an identifier: filename -> title map plus item metadata.
+fn fetch_archive_metadata(client: &ArchiveClient, identifier: &str) -> Result<ArchiveItem> {
     let encoded = encode_identifier(identifier);
     let url = format!("https://archive.org/metadata/{encoded}");
-    log::debug!("Fetching {url}");
 
-    let response: ArchiveMetadata = ureq::get(&url)
-        .call()
-        .with_context(|| format!("HTTP request failed for {identifier}"))?
-        .body_mut()
-        .read_json()
-        .with_context(|| format!("Failed to parse JSON for {identifier}"))?;
+    let response: ArchiveMetadata = client
+        .get_json(&url)
+        .with_context(|| format!("Failed to fetch metadata for {identifier}"))?;
 
     let mut map = HashMap::new();
     if let Some(files) = response.files {
@@ -446,12 +721,154 @@ fn fetch_archive_metadata(identifier: &str) -> Result<HashMap<String, String>> {
     }
 
     log::debug!("  Got {} titled audio files for {identifier}", map.len());
-    Ok(map)
+    Ok(ArchiveItem {
+        identifier: identifier.to_string(),
+        titles: map,
+        metadata: response.metadata,
+    })
+}
+
+/// Flatten an archive.org metadata field (string or array of strings) to plain text.
+fn metadata_text(value: Option<serde_json::Value>) -> Option<String> {
+    let text = match value? {
+        serde_json::Value::String(s) => s,
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => other.to_string(),
+    };
+    let text = strip_html(&text);
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+/// Reduce archive.org description HTML to plain text: line breaks kept, tags dropped,
+/// common entities decoded.
+fn strip_html(html: &str) -> String {
+    static RE_BREAK: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?i)<br\s*/?>|</p>|</div>|</li>").unwrap());
+    static RE_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+    let text = RE_BREAK.replace_all(html, "\n");
+    let text = RE_TAG.replace_all(&text, "");
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Set a track's title from a setlist match, recording how it was matched.
+    /// Clears any pending review for the track.
+    pub fn apply_title_match(&self, track_id: i64, m: &TitleMatch) -> crate::db::Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET parsed_title = ?1, title_match_method = ?2,
+                    title_match_confidence = ?3, updated_at = datetime('now')
+             WHERE id = ?4",
+            rusqlite::params![
+                crate::title_normalize::normalize(&m.title),
+                m.method,
+                m.confidence,
+                track_id
+            ],
+        )?;
+        self.conn.execute(
+            "DELETE FROM title_match_review WHERE track_id = ?1",
+            [track_id],
+        )?;
+        Ok(())
+    }
+
+    /// Queue a low-confidence match for review. Matches already rejected stay rejected.
+    pub fn queue_title_match(
+        &self,
+        track_id: i64,
+        m: &TitleMatch,
+        identifier: &str,
+    ) -> crate::db::Result<()> {
+        self.conn.execute(
+            "INSERT INTO title_match_review (track_id, title, method, confidence, identifier)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(track_id) DO UPDATE SET
+                title = excluded.title, method = excluded.method,
+                confidence = excluded.confidence, identifier = excluded.identifier,
+                queued_at = datetime('now')
+             WHERE rejected = 0",
+            rusqlite::params![track_id, m.title, m.method, m.confidence, identifier],
+        )?;
+        Ok(())
+    }
+
+    /// Matches waiting for review, least confident first.
+    pub fn pending_title_matches(&self) -> crate::db::Result<Vec<PendingTitleMatch>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.track_id, t.file_path, r.title, r.method, r.confidence, r.identifier
+             FROM title_match_review r
+             JOIN tracks t ON t.id = r.track_id
+             WHERE r.rejected = 0
+             ORDER BY r.confidence, t.file_path",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(PendingTitleMatch {
+                track_id: row.get(0)?,
+                file_path: row.get(1)?,
+                title: row.get(2)?,
+                method: row.get(3)?,
+                confidence: row.get(4)?,
+                identifier: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Accept a queued match. Returns false if the track has no pending match.
+    pub fn confirm_title_match(&self, track_id: i64) -> crate::db::Result<bool> {
+        let pending = self
+            .conn
+            .query_row(
+                "SELECT title FROM title_match_review WHERE track_id = ?1 AND rejected = 0",
+                [track_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        let Some(title) = pending else {
+            return Ok(false);
+        };
+        // A human confirmed it, so it's as good as an exact match
+        let m = TitleMatch {
+            title,
+            method: "confirmed",
+            confidence: 1.0,
+        };
+        self.apply_title_match(track_id, &m)?;
+        Ok(true)
+    }
+
+    /// Reject a queued match so later lookups don't queue it again.
+    /// Returns false if the track has no pending match.
+    pub fn reject_title_match(&self, track_id: i64) -> crate::db::Result<bool> {
+        let n = self.conn.execute(
+            "UPDATE title_match_review SET rejected = 1 WHERE track_id = ?1 AND rejected = 0",
+            [track_id],
+        )?;
+        Ok(n > 0)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::queries::tests::new_track;
 
     #[test]
     fn test_archive_file_deserialize() {
@@ -534,8 +951,10 @@ mod tests {
         file_map.insert("gd69-04-22d1t01.mp3".to_string(), "Dark Star".to_string());
         let pos_map = build_position_map(&file_map);
 
-        let result = match_title("gd69-04-22d1t01.mp3", &file_map, &pos_map);
-        assert_eq!(result, Some(("Dark Star".to_string(), "exact")));
+        let result = match_title("gd69-04-22d1t01.mp3", &file_map, &pos_map, true).unwrap();
+        assert_eq!(result.title, "Dark Star");
+        assert_eq!(result.method, "exact");
+        assert_eq!(result.confidence, 1.0);
     }
 
     #[test]
@@ -544,8 +963,9 @@ mod tests {
         file_map.insert("gd69-04-22d1t01.flac".to_string(), "Dark Star".to_string());
         let pos_map = build_position_map(&file_map);
 
-        let result = match_title("gd69-04-22d1t01.mp3", &file_map, &pos_map);
-        assert_eq!(result, Some(("Dark Star".to_string(), "stem")));
+        let result = match_title("gd69-04-22d1t01.mp3", &file_map, &pos_map, true).unwrap();
+        assert_eq!(result.title, "Dark Star");
+        assert_eq!(result.method, "stem");
     }
 
     #[test]
@@ -559,8 +979,126 @@ mod tests {
         let pos_map = build_position_map(&file_map);
 
         // Local has ph-prefixed filenames — different stem but same position
-        let result = match_title("ph97-11-16d1t02.mp3", &file_map, &pos_map);
-        assert_eq!(result, Some(("Tweezer".to_string(), "position")));
+        let result = match_title("ph97-11-16d1t02.mp3", &file_map, &pos_map, true).unwrap();
+        assert_eq!(result.title, "Tweezer");
+        assert_eq!(result.method, "position");
+        assert!(result.confidence >= DEFAULT_MIN_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_match_title_trailing_digits_needs_review() {
+        let mut file_map = HashMap::new();
+        file_map.insert("bts1999-03-08t11.flac".to_string(), "Car".to_string());
+        let pos_map = build_position_map(&file_map);
+
+        let result = match_title(
+            "Built To Spill 1999-03-08 Boise11.mp3",
+            &file_map,
+            &pos_map,
+            true,
+        )
+        .unwrap();
+        assert_eq!(result.title, "Car");
+        assert_eq!(result.method, "trailing-digits");
+        assert!(result.confidence < DEFAULT_MIN_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_match_title_count_mismatch_lowers_confidence() {
+        let mut file_map = HashMap::new();
+        file_map.insert(
+            "phish1997-11-16d1t02.flac".to_string(),
+            "Tweezer".to_string(),
+        );
+        let pos_map = build_position_map(&file_map);
+
+        let agree = match_title("ph97-11-16d1t02.mp3", &file_map, &pos_map, true).unwrap();
+        let differ = match_title("ph97-11-16d1t02.mp3", &file_map, &pos_map, false).unwrap();
+        assert!(differ.confidence < agree.confidence);
+        assert!(differ.confidence < DEFAULT_MIN_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_match_title_ambiguous_track_only() {
+        let mut file_map = HashMap::new();
+        file_map.insert("gd74-06-23d1t05.flac".to_string(), "Sugaree".to_string());
+        file_map.insert("gd74-06-23d2t05.flac".to_string(), "Eyes".to_string());
+        let pos_map = build_position_map(&file_map);
+
+        let result = match_title("gd74-06-23sbd_t05.mp3", &file_map, &pos_map, true).unwrap();
+        assert_eq!(result.method, "track-only");
+        assert!(result.confidence < DEFAULT_MIN_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[test]
+    fn test_match_title_fuzzy_renamed() {
+        let mut file_map = HashMap::new();
+        file_map.insert(
+            "gd77-05-08d1t03_vbr.mp3".to_string(),
+            "Scarlet Begonias".to_string(),
+        );
+        file_map.insert(
+            "gd77-05-08d1t04_vbr.mp3".to_string(),
+            "Fire on the Mountain".to_string(),
+        );
+        let pos_map = build_position_map(&file_map);
+
+        let result = match_title(
+            "gd77-05-08 Barton Hall d1t03.flac",
+            &file_map,
+            &pos_map,
+            true,
+        )
+        .unwrap();
+        assert_eq!(result.title, "Scarlet Begonias");
+
+        let bitrate = match_title("gd77-05-08d1t04.flac", &file_map, &pos_map, true).unwrap();
+        assert_eq!(bitrate.title, "Fire on the Mountain");
+        assert_eq!(bitrate.method, "fuzzy");
+        assert!(bitrate.confidence >= DEFAULT_MIN_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_match_fuzzy_respects_track_numbers() {
+        let mut file_map = HashMap::new();
+        file_map.insert(
+            "gd77-05-08d1t03.flac".to_string(),
+            "Scarlet Begonias".to_string(),
+        );
+        // One character away, but a different track
+        assert!(match_fuzzy("gd77-05-08d1t08", &file_map).is_none());
+    }
+
+    #[test]
+    fn test_title_match_review_queue() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db
+            .upsert_track(&crate::db::models::NewTrack {
+                parsed_track: Some(11),
+                ..new_track("/music/bts1999-03-08/Boise11.mp3")
+            })
+            .unwrap();
+        let m = TitleMatch {
+            title: "Car".to_string(),
+            method: "trailing-digits",
+            confidence: 0.5,
+        };
+        db.queue_title_match(id, &m, "bts1999-03-08").unwrap();
+        let pending = db.pending_title_matches().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].title, "Car");
+
+        assert!(db.reject_title_match(id).unwrap());
+        db.queue_title_match(id, &m, "bts1999-03-08").unwrap();
+        assert!(db.pending_title_matches().unwrap().is_empty());
+        assert!(!db.confirm_title_match(id).unwrap());
     }
 
     #[test]
@@ -573,4 +1111,33 @@ mod tests {
             Some("gd1969-04-22.sbd.miller.88466.sbeok.flac16")
         );
     }
+
+    #[test]
+    fn test_item_metadata_to_show_notes() {
+        let json = r#"{
+            "files": [],
+            "metadata": {
+                "identifier": "gd1977-05-08.sbd.hicks.4982.sbeok.shnf",
+                "date": "1977-05-08",
+                "venue": "Barton Hall, Cornell University",
+                "source": ["SBD > Reel", "Reel > DAT"],
+                "lineage": "DAT > CD > EAC > SHN",
+                "taper": "Betty Cantor-Jackson",
+                "description": "Set 1<br />Minglewood Blues &amp; more<br>"
+            }
+        }"#;
+        let m: ArchiveMetadata = serde_json::from_str(json).unwrap();
+        let notes = m
+            .metadata
+            .unwrap()
+            .into_show_notes("gd1977-05-08.sbd.hicks.4982.sbeok.shnf", "gd77-05-08");
+        assert_eq!(notes.date.as_deref(), Some("1977-05-08"));
+        assert_eq!(notes.source.as_deref(), Some("SBD > Reel\nReel > DAT"));
+        assert_eq!(notes.taper.as_deref(), Some("Betty Cantor-Jackson"));
+        assert_eq!(
+            notes.description.as_deref(),
+            Some("Set 1\nMinglewood Blues & more")
+        );
+        assert!(notes.transferer.is_none());
+    }
 }
@@ -11,11 +11,11 @@ use std::thread;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
 use serde::Deserialize;
 
 use super::import::{ImportResult, SetlistEntry};
 use crate::db::Database;
+use crate::progress::Progress;
 
 // ---------------------------------------------------------------------------
 // API response types (only the fields we need)
@@ -69,12 +69,13 @@ pub fn fetch_phish_setlists(
     db: &Database,
     rate_limit_ms: u64,
     dry_run: bool,
+    progress: &dyn Progress,
 ) -> Result<ImportResult> {
     // Step 1: Get all show dates
-    println!("Fetching Phish show dates from phish.in...");
-    let dates =
-        fetch_all_show_dates(rate_limit_ms).context("Failed to fetch show dates from phish.in")?;
-    println!("Found {} shows", dates.len());
+    progress.note("Fetching Phish show dates from phish.in...");
+    let dates = fetch_all_show_dates(rate_limit_ms, progress)
+        .context("Failed to fetch show dates from phish.in")?;
+    progress.note(&format!("Found {} shows", dates.len()));
 
     // Step 2: Check which dates we already have
     let existing = db
@@ -90,7 +91,7 @@ pub fn fetch_phish_setlists(
         .collect();
 
     if to_fetch.is_empty() {
-        println!("All {} shows already imported", dates.len());
+        progress.note(&format!("All {} shows already imported", dates.len()));
         return Ok(ImportResult {
             shows_imported: 0,
             songs_imported: 0,
@@ -98,28 +99,20 @@ pub fn fetch_phish_setlists(
         });
     }
 
-    println!(
+    progress.note(&format!(
         "Fetching setlists for {} new shows ({} already cached)...",
         to_fetch.len(),
         existing_set.len()
-    );
+    ));
 
     // Step 3: Fetch each show's tracks
-    let pb = ProgressBar::new(to_fetch.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template(
-                "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} shows ({eta} remaining) {msg}",
-            )
-            .unwrap()
-            .progress_chars("=>-"),
-    );
+    progress.start(to_fetch.len() as u64, "shows");
 
     let mut all_entries = Vec::new();
     let mut fetch_errors = 0usize;
 
     for date in &to_fetch {
-        pb.set_message(date.to_string());
+        progress.set_message(date);
 
         match fetch_show_detail(date) {
             Ok(entries) => {
@@ -131,14 +124,14 @@ pub fn fetch_phish_setlists(
             }
         }
 
-        pb.inc(1);
+        progress.inc(1);
         thread::sleep(Duration::from_millis(rate_limit_ms));
     }
 
-    pb.finish_with_message("done");
+    progress.finish("done");
 
     if fetch_errors > 0 {
-        println!("{fetch_errors} shows failed to fetch");
+        log::warn!("{fetch_errors} shows failed to fetch");
     }
 
     if dry_run {
@@ -147,11 +140,11 @@ pub fn fetch_phish_setlists(
             .map(|e| &e.date)
             .collect::<std::collections::HashSet<_>>()
             .len();
-        println!(
+        progress.note(&format!(
             "Would import {} songs across {} shows",
             all_entries.len(),
             show_count
-        );
+        ));
         return Ok(ImportResult {
             shows_imported: show_count,
             songs_imported: all_entries.len(),
@@ -175,7 +168,7 @@ pub fn fetch_phish_setlists(
 // ---------------------------------------------------------------------------
 
 /// Fetch all show dates from the paginated shows endpoint.
-fn fetch_all_show_dates(rate_limit_ms: u64) -> Result<Vec<String>> {
+fn fetch_all_show_dates(rate_limit_ms: u64, progress: &dyn Progress) -> Result<Vec<String>> {
     let mut dates = Vec::new();
     let mut page = 1u32;
 
@@ -189,14 +182,8 @@ fn fetch_all_show_dates(rate_limit_ms: u64) -> Result<Vec<String>> {
         dates.push(show.date.clone());
     }
 
-    let pb = ProgressBar::new(total_pages as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} pages")
-            .unwrap()
-            .progress_chars("=>-"),
-    );
-    pb.inc(1);
+    progress.start(total_pages as u64, "pages");
+    progress.inc(1);
 
     page = 2;
     while page <= total_pages {
@@ -208,12 +195,12 @@ fn fetch_all_show_dates(rate_limit_ms: u64) -> Result<Vec<String>> {
             dates.push(show.date.clone());
         }
 
-        pb.inc(1);
+        progress.inc(1);
         page += 1;
         thread::sleep(Duration::from_millis(rate_limit_ms));
     }
 
-    pb.finish_and_clear();
+    progress.finish("");
     Ok(dates)
 }
 
@@ -0,0 +1,278 @@
+// Setlist fetching from setlist.fm (https://api.setlist.fm, API key required).
+//
+// setlist.fm covers far more bands than gdshowsdb or phish.in, so it fills in
+// reference setlists for `setlist --validate` on shows that have none. Lookups
+// are by band name and date; the band comes from the tracks' tags.
+//
+// Note: setlist.fm has no structured segue data, and songs played from tape
+// (intro music) are skipped. `segued` is always false for these imports.
+
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::params;
+use serde::Deserialize;
+
+use super::import::{ImportResult, SetlistEntry};
+use crate::db::Database;
+use crate::progress::Progress;
+
+/// Credential service name (`setbreak auth set setlistfm`, or `SETBREAK_SETLISTFM_KEY`).
+pub const CREDENTIAL: &str = "setlistfm";
+
+// ---------------------------------------------------------------------------
+// API response types (only the fields we need)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    setlist: Vec<Setlist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Setlist {
+    venue: Option<Venue>,
+    sets: Option<Sets>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Venue {
+    name: Option<String>,
+    city: Option<City>,
+}
+
+#[derive(Debug, Deserialize)]
+struct City {
+    name: Option<String>,
+    #[serde(rename = "stateCode")]
+    state_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sets {
+    #[serde(default)]
+    set: Vec<Set>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Set {
+    encore: Option<i32>,
+    #[serde(default)]
+    song: Vec<Song>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Song {
+    name: String,
+    #[serde(default)]
+    tape: bool,
+}
+
+// ---------------------------------------------------------------------------
+// Public API
+// ---------------------------------------------------------------------------
+
+/// Fetch setlist.fm setlists for dates with titled local tracks but no stored
+/// setlist. Imports into the setlists table with source = "setlistfm".
+pub fn fetch_missing_setlists(
+    db: &Database,
+    api_key: &str,
+    rate_limit_ms: u64,
+    progress: &dyn Progress,
+) -> Result<ImportResult> {
+    let shows = db
+        .shows_without_setlist()
+        .context("Failed to query shows without a setlist")?;
+    if shows.is_empty() {
+        return Ok(ImportResult {
+            shows_imported: 0,
+            songs_imported: 0,
+            files_processed: 0,
+        });
+    }
+
+    progress.note(&format!(
+        "Fetching {} setlists from setlist.fm...",
+        shows.len()
+    ));
+    progress.start(shows.len() as u64, "shows");
+
+    let mut all_entries = Vec::new();
+    let mut fetch_errors = 0usize;
+    for (date, band) in &shows {
+        progress.set_message(date);
+        match fetch_setlist(api_key, band, date) {
+            Ok(entries) => all_entries.extend(entries),
+            Err(e) => {
+                fetch_errors += 1;
+                log::warn!("Failed to fetch {band} {date}: {e}");
+            }
+        }
+        progress.inc(1);
+        thread::sleep(Duration::from_millis(rate_limit_ms));
+    }
+    progress.finish("done");
+
+    if fetch_errors > 0 {
+        log::warn!("{fetch_errors} shows failed to fetch");
+    }
+
+    let result = super::import::import_setlists_append(db, &all_entries)
+        .context("Failed to import setlist.fm setlists")?;
+    Ok(ImportResult {
+        shows_imported: result.shows_imported,
+        songs_imported: result.songs_imported,
+        files_processed: shows.len(),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Internal helpers
+// ---------------------------------------------------------------------------
+
+/// Fetch one band's setlist for a date. No entries when setlist.fm has none.
+fn fetch_setlist(api_key: &str, band: &str, date: &str) -> Result<Vec<SetlistEntry>> {
+    // setlist.fm wants dd-MM-yyyy
+    let Some((y, rest)) = date.split_once('-') else {
+        return Ok(Vec::new());
+    };
+    let Some((m, d)) = rest.split_once('-') else {
+        return Ok(Vec::new());
+    };
+    let url = "https://api.setlist.fm/rest/1.0/search/setlists";
+    log::debug!("GET {url} ({band} {date})");
+    let mut resp = match ureq::get(url)
+        .query("artistName", band)
+        .query("date", format!("{d}-{m}-{y}"))
+        .header("Accept", "application/json")
+        .header("x-api-key", api_key)
+        .call()
+    {
+        Ok(resp) => resp,
+        Err(ureq::Error::StatusCode(404)) => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("HTTP request failed: {url}")),
+    };
+    let search: SearchResponse = resp
+        .body_mut()
+        .read_json()
+        .with_context(|| format!("Failed to parse JSON from {url}"))?;
+
+    // The first setlist with songs (early and late shows come back separately)
+    let Some(setlist) = search
+        .setlist
+        .into_iter()
+        .find(|s| s.sets.as_ref().is_some_and(|sets| !sets.set.is_empty()))
+    else {
+        return Ok(Vec::new());
+    };
+    Ok(setlist_entries(date, setlist))
+}
+
+/// Flatten a setlist.fm setlist into setlist rows. Sets are numbered from 1,
+/// encores from 10 (matching the phish.in import).
+fn setlist_entries(date: &str, setlist: Setlist) -> Vec<SetlistEntry> {
+    let venue = setlist.venue.as_ref().and_then(|v| v.name.clone());
+    let city = setlist.venue.as_ref().and_then(|v| v.city.as_ref());
+    let (city, state) = (
+        city.and_then(|c| c.name.clone()),
+        city.and_then(|c| c.state_code.clone()),
+    );
+
+    let mut entries = Vec::new();
+    let mut set_num = 0;
+    for set in setlist.sets.map(|s| s.set).unwrap_or_default() {
+        let num = match set.encore {
+            Some(n) => 9 + n.max(1),
+            None => {
+                set_num += 1;
+                set_num
+            }
+        };
+        let songs = set
+            .song
+            .into_iter()
+            .filter(|s| !s.tape && !s.name.is_empty());
+        for (i, song) in songs.enumerate() {
+            entries.push(SetlistEntry {
+                date: date.to_string(),
+                set_num: num,
+                position: i as i32 + 1,
+                song: song.name,
+                segued: false, // setlist.fm doesn't provide segue data
+                venue: venue.clone(),
+                city: city.clone(),
+                state: state.clone(),
+                source: "setlistfm".to_string(),
+            });
+        }
+    }
+    entries
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// (date, band) for dates with titled tracks and a known band but no
+    /// stored setlist. The band is the one most of the date's tracks carry.
+    fn shows_without_setlist(&self) -> crate::db::Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT d, band FROM (
+                 SELECT COALESCE(parsed_date, date) AS d,
+                        COALESCE(parsed_band, artist) AS band,
+                        ROW_NUMBER() OVER (PARTITION BY COALESCE(parsed_date, date)
+                                           ORDER BY COUNT(*) DESC) AS rn
+                 FROM tracks
+                 WHERE COALESCE(parsed_date, date) IS NOT NULL
+                   AND COALESCE(parsed_band, artist) IS NOT NULL
+                   AND parsed_title IS NOT NULL
+                   AND COALESCE(parsed_date, date) NOT IN (SELECT date FROM setlists)
+                 GROUP BY d, band
+             )
+             WHERE rn = 1
+             ORDER BY d",
+        )?;
+        let rows = stmt
+            .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setlist_entries() {
+        let search: SearchResponse = serde_json::from_str(
+            r#"{"setlist":[{
+                "venue":{"name":"Barton Hall","city":{"name":"Ithaca","stateCode":"NY"}},
+                "sets":{"set":[
+                    {"song":[{"name":"Intro","tape":true},{"name":"Minglewood Blues"},{"name":"Loser"}]},
+                    {"song":[{"name":"Scarlet Begonias"}]},
+                    {"encore":1,"song":[{"name":"One More Saturday Night"}]}
+                ]}
+            }]}"#,
+        )
+        .unwrap();
+        let setlist = search.setlist.into_iter().next().unwrap();
+        let entries = setlist_entries("1977-05-08", setlist);
+        let rows: Vec<(i32, i32, &str)> = entries
+            .iter()
+            .map(|e| (e.set_num, e.position, e.song.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (1, 1, "Minglewood Blues"),
+                (1, 2, "Loser"),
+                (2, 1, "Scarlet Begonias"),
+                (10, 1, "One More Saturday Night"),
+            ]
+        );
+        assert_eq!(entries[0].state.as_deref(), Some("NY"));
+        assert_eq!(entries[0].source, "setlistfm");
+    }
+}
@@ -0,0 +1,445 @@
+// Setlist validation: titled tracks checked against a reference setlist.
+//
+// After `setlist` assigns titles, each recording source of a show, in disc and
+// track order, is a reconstructed setlist. `setlist --validate` compares it with
+// the show's reference in the `setlists` table (gdshowsdb and phish.in imports,
+// setlist.fm lookups) and flags what a wrong title assignment looks like:
+// reference songs no track carries, titles the reference doesn't have, songs
+// out of the reference's order, and one song on several tracks. Consecutive
+// parts of a split jam ("Dark Star (1)", "Dark Star (2)") count as one song.
+//
+// Tapes often start late or cut out early, so reference songs before the first
+// or after the last matched song are counted as not on the tape rather than
+// reported missing.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rusqlite::params;
+
+use crate::db::Database;
+use crate::db::columns::MISSING_TITLE;
+
+/// One thing wrong with a reconstructed setlist.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    /// A reference song between the first and last matched ones that no track carries.
+    Missing { song: String },
+    /// A track title the reference doesn't have (`track` is 1-based, in playing order).
+    Extra { title: String, track: usize },
+    /// A matched song whose position disagrees with the reference order.
+    OutOfOrder {
+        title: String,
+        track: usize,
+        /// 1-based position in the reference.
+        expected: usize,
+    },
+    /// A title on more tracks than the reference plays it.
+    Duplicate { title: String, tracks: Vec<usize> },
+}
+
+impl Anomaly {
+    /// Short label for reports.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Missing { .. } => "missing",
+            Self::Extra { .. } => "extra",
+            Self::OutOfOrder { .. } => "out of order",
+            Self::Duplicate { .. } => "duplicate",
+        }
+    }
+}
+
+/// Validation of one recording source of a show.
+#[derive(Debug, Clone)]
+pub struct SourceValidation {
+    pub date: String,
+    /// Recording directory (`paths::source_dir`).
+    pub dir: String,
+    /// Reference the tracks were checked against ("gdshowsdb", "setlistfm").
+    pub reference: String,
+    pub reference_songs: usize,
+    /// Songs (split-jam parts merged) matched to the reference.
+    pub matched: usize,
+    /// Reference songs before the tape starts or after it cuts.
+    pub not_on_tape: usize,
+    /// Tracks without a usable title, left out of the comparison.
+    pub untitled: usize,
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl SourceValidation {
+    /// Directory name for display.
+    pub fn name(&self) -> &str {
+        crate::paths::file_name(&self.dir)
+    }
+}
+
+/// Result of `setlist --validate`.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Sources with at least one anomaly, most anomalies first.
+    pub flagged: Vec<SourceValidation>,
+    /// Sources checked.
+    pub checked: usize,
+    /// Sources with no song in common with their reference (another band that
+    /// night, or not titled yet).
+    pub no_overlap: usize,
+}
+
+/// Compare a source's track titles, in playing order, with a reference
+/// setlist. `None` titles are untitled tracks (they keep their place in the
+/// numbering but aren't compared).
+pub fn compare(titles: &[Option<String>], reference: &[String]) -> (Vec<Anomaly>, Matching) {
+    // Songs in playing order: (key, display title, tracks), split parts merged
+    let mut songs: Vec<(String, String, Vec<usize>)> = Vec::new();
+    for (i, title) in titles.iter().enumerate() {
+        let Some(title) = title else { continue };
+        let (base, is_part) = crate::track_groups::song_part(title);
+        let key = base.to_lowercase();
+        match songs.last_mut() {
+            Some(last) if is_part && last.0 == key && last.2.last() == Some(&i) => {
+                last.2.push(i + 1)
+            }
+            _ => songs.push((key, base, vec![i + 1])),
+        }
+    }
+    let reference_keys: Vec<String> = reference
+        .iter()
+        .map(|s| crate::track_groups::song_part(s).0.to_lowercase())
+        .collect();
+
+    // Match each song to an unused reference occurrence: exact, then relaxed
+    let mut used = vec![false; reference.len()];
+    let mut matched: Vec<(usize, usize)> = Vec::new(); // (song index, reference index)
+    let mut unmatched: Vec<usize> = Vec::new();
+    for (i, (key, _, _)) in songs.iter().enumerate() {
+        let found = (0..reference.len())
+            .find(|&r| !used[r] && reference_keys[r] == *key)
+            .or_else(|| {
+                (key.len() >= 4)
+                    .then(|| {
+                        (0..reference.len()).find(|&r| {
+                            !used[r]
+                                && reference_keys[r].len() >= 4
+                                && (key.contains(&reference_keys[r])
+                                    || reference_keys[r].contains(key.as_str()))
+                        })
+                    })
+                    .flatten()
+            });
+        match found {
+            Some(r) => {
+                used[r] = true;
+                matched.push((i, r));
+            }
+            None => unmatched.push(i),
+        }
+    }
+
+    let mut anomalies = Vec::new();
+
+    // A leftover title the reference has (all of its plays already taken) is a duplicate
+    let mut duplicates: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for &i in &unmatched {
+        let (key, title, tracks) = &songs[i];
+        if reference_keys.contains(key) {
+            let first = matched
+                .iter()
+                .filter(|(s, _)| songs[*s].0 == *key)
+                .flat_map(|(s, _)| songs[*s].2.first().copied());
+            let entry = duplicates.entry(title.as_str()).or_default();
+            if entry.is_empty() {
+                entry.extend(first);
+            }
+            entry.push(tracks[0]);
+        } else {
+            anomalies.push(Anomaly::Extra {
+                title: title.clone(),
+                track: tracks[0],
+            });
+        }
+    }
+    for (title, mut tracks) in duplicates {
+        tracks.sort_unstable();
+        anomalies.push(Anomaly::Duplicate {
+            title: title.to_string(),
+            tracks,
+        });
+    }
+
+    // Songs off the longest run that follows the reference order are out of order
+    let order: Vec<usize> = matched.iter().map(|&(_, r)| r).collect();
+    let in_order = longest_increasing(&order);
+    for (k, &(i, r)) in matched.iter().enumerate() {
+        if !in_order.contains(&k) {
+            anomalies.push(Anomaly::OutOfOrder {
+                title: songs[i].1.clone(),
+                track: songs[i].2[0],
+                expected: r + 1,
+            });
+        }
+    }
+
+    let first = order.iter().min().copied();
+    let last = order.iter().max().copied();
+    let mut not_on_tape = 0;
+    for (r, song) in reference.iter().enumerate() {
+        if used[r] {
+            continue;
+        }
+        match (first, last) {
+            (Some(first), Some(last)) if r > first && r < last => {
+                anomalies.push(Anomaly::Missing { song: song.clone() })
+            }
+            _ => not_on_tape += 1,
+        }
+    }
+
+    let matching = Matching {
+        matched: matched.len(),
+        not_on_tape,
+    };
+    (anomalies, matching)
+}
+
+/// Counts from `compare`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matching {
+    pub matched: usize,
+    pub not_on_tape: usize,
+}
+
+/// Indices (into `seq`) of one longest strictly increasing subsequence.
+fn longest_increasing(seq: &[usize]) -> Vec<usize> {
+    let n = seq.len();
+    let mut len = vec![1usize; n];
+    let mut prev = vec![None; n];
+    for i in 0..n {
+        for j in 0..i {
+            if seq[j] < seq[i] && len[j] + 1 > len[i] {
+                len[i] = len[j] + 1;
+                prev[i] = Some(j);
+            }
+        }
+    }
+    let Some(mut at) = (0..n).max_by_key(|&i| (len[i], std::cmp::Reverse(i))) else {
+        return Vec::new();
+    };
+    let mut out = vec![at];
+    while let Some(p) = prev[at] {
+        out.push(p);
+        at = p;
+    }
+    out.reverse();
+    out
+}
+
+/// Validate every recording source of every show that has a reference setlist.
+/// Where a date has references from several sources, the longest is used.
+pub fn validate(db: &Database, date: Option<&str>) -> crate::db::Result<ValidationReport> {
+    let references = db.reference_setlists(date)?;
+    let mut sources: BTreeMap<(String, String), Vec<Option<String>>> = BTreeMap::new();
+    for (date, file_path, title) in db.titled_show_tracks(date)? {
+        let dir = crate::paths::source_dir(&file_path)
+            .unwrap_or("")
+            .to_string();
+        sources.entry((date, dir)).or_default().push(title);
+    }
+
+    let mut report = ValidationReport::default();
+    for ((date, dir), titles) in sources {
+        let Some((reference, songs)) = references
+            .get(&date)
+            .and_then(|by_source| by_source.iter().max_by_key(|(_, songs)| songs.len()))
+        else {
+            continue;
+        };
+        report.checked += 1;
+        let (anomalies, matching) = compare(&titles, songs);
+        if matching.matched == 0 {
+            report.no_overlap += 1;
+            continue;
+        }
+        if anomalies.is_empty() {
+            continue;
+        }
+        report.flagged.push(SourceValidation {
+            date,
+            dir,
+            reference: reference.clone(),
+            reference_songs: songs.len(),
+            matched: matching.matched,
+            not_on_tape: matching.not_on_tape,
+            untitled: titles.iter().filter(|t| t.is_none()).count(),
+            anomalies,
+        });
+    }
+    report.flagged.sort_by(|a, b| {
+        b.anomalies
+            .len()
+            .cmp(&a.anomalies.len())
+            .then(a.date.cmp(&b.date))
+    });
+    Ok(report)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Reference setlists by date, then by source, songs in set and position order.
+    fn reference_setlists(
+        &self,
+        date: Option<&str>,
+    ) -> crate::db::Result<HashMap<String, BTreeMap<String, Vec<String>>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date, source, song FROM setlists
+             WHERE ?1 IS NULL OR date = ?1
+             ORDER BY date, source, set_num, position",
+        )?;
+        let mut out: HashMap<String, BTreeMap<String, Vec<String>>> = HashMap::new();
+        let rows = stmt.query_map(params![date], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (date, source, song) = row?;
+            out.entry(date)
+                .or_default()
+                .entry(source)
+                .or_default()
+                .push(song);
+        }
+        Ok(out)
+    }
+
+    /// (date, file path, title) for every track on a date with a reference
+    /// setlist, in playing order; the title is `None` when it isn't usable.
+    fn titled_show_tracks(
+        &self,
+        date: Option<&str>,
+    ) -> crate::db::Result<Vec<(String, String, Option<String>)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT COALESCE(t.parsed_date, t.date) AS d, t.file_path,
+                    CASE WHEN {MISSING_TITLE} THEN NULL
+                         ELSE COALESCE(t.parsed_title, t.title) END
+             FROM tracks t
+             WHERE COALESCE(t.parsed_date, t.date) IN (SELECT date FROM setlists)
+               AND (?1 IS NULL OR COALESCE(t.parsed_date, t.date) = ?1)
+             ORDER BY d,
+                      COALESCE(t.parsed_disc, t.disc_number, CAST(t.parsed_set AS INTEGER), 1),
+                      COALESCE(t.parsed_track, t.track_number, 999),
+                      t.file_path"
+        ))?;
+        let rows = stmt
+            .query_map(params![date], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(list: &[&str]) -> Vec<Option<String>> {
+        list.iter()
+            .map(|t| (!t.is_empty()).then(|| t.to_string()))
+            .collect()
+    }
+
+    fn songs(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_compare_flags_anomalies() {
+        let reference = songs(&[
+            "Bertha",
+            "Good Lovin'",
+            "Scarlet Begonias",
+            "Fire on the Mountain",
+            "Estimated Prophet",
+            "Dark Star",
+            "Morning Dew",
+        ]);
+
+        // The tape starts at Good Lovin'; Dark Star is split in two
+        let clean = titles(&[
+            "Good Lovin'",
+            "Scarlet Begonias ->",
+            "Fire On The Mountain",
+            "Estimated Prophet",
+            "Dark Star (1)",
+            "Dark Star (2)",
+            "Morning Dew",
+        ]);
+        let (anomalies, matching) = compare(&clean, &reference);
+        assert!(anomalies.is_empty(), "{anomalies:?}");
+        assert_eq!(
+            matching,
+            Matching {
+                matched: 6,
+                not_on_tape: 1
+            }
+        );
+
+        let wrong = titles(&[
+            "Good Lovin'",
+            "Fire on the Mountain",
+            "Scarlet Begonias",
+            "",
+            "Scarlet Begonias",
+            "Drums",
+            "Morning Dew",
+        ]);
+        let (anomalies, _) = compare(&wrong, &reference);
+        let kinds: Vec<&str> = anomalies.iter().map(Anomaly::kind).collect();
+        assert_eq!(
+            kinds,
+            vec!["extra", "duplicate", "out of order", "missing", "missing"]
+        );
+        assert!(anomalies.contains(&Anomaly::Duplicate {
+            title: "Scarlet Begonias".into(),
+            tracks: vec![3, 5],
+        }));
+        assert!(anomalies.contains(&Anomaly::Extra {
+            title: "Drums".into(),
+            track: 6,
+        }));
+        assert!(anomalies.contains(&Anomaly::Missing {
+            song: "Estimated Prophet".into(),
+        }));
+    }
+
+    #[test]
+    fn test_validate_against_stored_setlist() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO setlists (date, set_num, position, song, source) VALUES
+                    ('1977-05-08', 1, 1, 'Bertha', 'gdshowsdb'),
+                    ('1977-05-08', 1, 2, 'Loser', 'gdshowsdb'),
+                    ('1977-05-08', 1, 3, 'Deal', 'gdshowsdb');
+                 INSERT INTO tracks (file_path, file_size, file_modified, format, parsed_date,
+                                     parsed_track, parsed_title)
+                 VALUES ('/m/sbd/t01.flac', 1, '0', 'flac', '1977-05-08', 1, 'Bertha'),
+                        ('/m/sbd/t02.flac', 1, '0', 'flac', '1977-05-08', 2, 'Deal'),
+                        ('/m/sbd/t03.flac', 1, '0', 'flac', '1977-05-08', 3, 'Loser'),
+                        ('/m/aud/t01.flac', 1, '0', 'flac', '1977-05-08', 1, 'Bertha'),
+                        ('/m/aud/t02.flac', 1, '0', 'flac', '1977-05-08', 2, 'Loser');",
+            )
+            .unwrap();
+        let report = validate(&db, None).unwrap();
+        assert_eq!((report.checked, report.no_overlap), (2, 0));
+        assert_eq!(report.flagged.len(), 1);
+        let sbd = &report.flagged[0];
+        assert_eq!((sbd.name(), sbd.reference.as_str()), ("sbd", "gdshowsdb"));
+        assert_eq!(sbd.anomalies.len(), 1);
+        assert_eq!(sbd.anomalies[0].kind(), "out of order");
+    }
+}
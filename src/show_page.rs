@@ -0,0 +1,220 @@
+//! Finding a show's archive.org page (`open`).
+//!
+//! Identifiers for a date come from, in order: show notes stored by `setlist`
+//! (confirmed against archive.org), the names of local show directories
+//! (normalized the way `setlist` does), and the `discover` cache (best source
+//! first). Nothing here touches the network; with no candidates at all the
+//! caller can fall back to an archive.org search for the date.
+
+use crate::db::Database;
+use crate::setlist::encode_identifier;
+use std::collections::HashSet;
+
+/// Which archive.org page to open for an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    /// The item's details page: poster, description, player.
+    Details,
+    /// The item's file listing.
+    Download,
+}
+
+/// An archive.org item that may be the show, and where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShowSource {
+    pub identifier: String,
+    /// `notes`, `local`, or `discover`.
+    pub origin: &'static str,
+}
+
+/// Candidate archive.org items for a show date, most trustworthy first and
+/// without duplicates. `band` (code or name) narrows local directories to
+/// that band's tracks and the discover cache to its collection.
+pub fn resolve(
+    db: &Database,
+    date: &str,
+    band: Option<&str>,
+) -> crate::db::Result<Vec<ShowSource>> {
+    let registry = crate::bands::registry();
+    let canonical = band.map(|b| registry.resolve_canonical_name(b));
+    let local_dirs = db.local_show_dirs(date, canonical.as_deref())?;
+
+    let mut seen = HashSet::new();
+    let mut sources = Vec::new();
+    let mut push = |identifier: String, origin| {
+        if seen.insert(identifier.clone()) {
+            sources.push(ShowSource { identifier, origin });
+        }
+    };
+    for n in db.get_show_notes(date)? {
+        if band.is_none() || local_dirs.contains(&n.local_dir) {
+            push(n.identifier, "notes");
+        }
+    }
+    for dir in &local_dirs {
+        push(registry.normalize_identifier(dir), "local");
+    }
+    let collection = band
+        .and_then(|b| registry.resolve_archive_query(b))
+        .map(|strategy| match strategy {
+            crate::bands::ArchiveStrategy::Collection(c)
+            | crate::bands::ArchiveStrategy::Creator(c) => c.as_str(),
+        });
+    if band.is_none() || collection.is_some() {
+        for identifier in db.archive_identifiers_on(date, collection)? {
+            push(identifier, "discover");
+        }
+    }
+    Ok(sources)
+}
+
+/// URL of an item's details or download page.
+pub fn page_url(identifier: &str, kind: PageKind) -> String {
+    let page = match kind {
+        PageKind::Details => "details",
+        PageKind::Download => "download",
+    };
+    format!(
+        "https://archive.org/{page}/{}",
+        encode_identifier(identifier)
+    )
+}
+
+/// archive.org search for a date's recordings, for when nothing is known locally.
+pub fn search_url(date: &str, band: Option<&str>) -> String {
+    let mut query = format!("date%3A{date}");
+    if let Some(strategy) = band.and_then(|b| crate::bands::registry().resolve_archive_query(b)) {
+        query = format!(
+            "{}%20AND%20{query}",
+            crate::discovery::query_clause(strategy)
+        );
+    }
+    format!("https://archive.org/search?query={query}")
+}
+
+/// Open a URL with the desktop's default handler.
+pub fn open_url(url: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut c = std::process::Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    let status = command.arg(url).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "browser opener exited with {status}"
+        )))
+    }
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Names of the directories holding a date's tracks, most tracks first.
+    fn local_show_dirs(&self, date: &str, band: Option<&str>) -> crate::db::Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT file_path FROM tracks
+             WHERE parsed_date = ?1 AND (?2 IS NULL OR parsed_band = ?2)",
+        )?;
+        let paths = stmt
+            .query_map(rusqlite::params![date, band], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for path in &paths {
+            let Some(dir) = crate::paths::parent_name(path) else {
+                continue;
+            };
+            let dir = crate::remote::percent_decode(dir);
+            match counts.iter_mut().find(|(d, _)| *d == dir) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((dir, 1)),
+            }
+        }
+        counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+        Ok(counts.into_iter().map(|(d, _)| d).collect())
+    }
+
+    /// Cached archive.org identifiers for a date, best source first.
+    fn archive_identifiers_on(
+        &self,
+        date: &str,
+        collection: Option<&str>,
+    ) -> crate::db::Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT identifier FROM archive_shows
+             WHERE date = ?1 AND (?2 IS NULL OR collection = ?2)
+             ORDER BY source_quality * 10 + format_quality DESC, identifier",
+        )?;
+        let ids = stmt
+            .query_map(rusqlite::params![date, collection], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{NewTrack, ShowNotes};
+    use crate::db::queries::tests::new_track;
+
+    fn track(path: &str, band: &str) -> NewTrack {
+        NewTrack {
+            parsed_band: Some(band.into()),
+            parsed_date: Some("1977-05-08".into()),
+            ..new_track(path)
+        }
+    }
+
+    #[test]
+    fn test_resolve_show_sources() {
+        crate::bands::init_default();
+        let db = Database::open_in_memory().unwrap();
+        for t in ["d1t01.flac", "d1t02.flac"] {
+            db.upsert_track(&track(
+                &format!("/music/gd77-05-08.sbd.hicks.4982/{t}"),
+                "Grateful Dead",
+            ))
+            .unwrap();
+        }
+        db.upsert_track(&track("/music/other-band-1977-05-08/t01.flac", "Other"))
+            .unwrap();
+        db.store_show_notes(&ShowNotes {
+            identifier: "gd1977-05-08.sbd.hicks.4982".into(),
+            local_dir: "gd77-05-08.sbd.hicks.4982".into(),
+            date: Some("1977-05-08".into()),
+            venue: None,
+            source: None,
+            lineage: None,
+            taper: None,
+            transferer: None,
+            description: None,
+            notes: None,
+        })
+        .unwrap();
+
+        let sources = resolve(&db, "1977-05-08", Some("gd")).unwrap();
+        assert_eq!(
+            sources,
+            vec![ShowSource {
+                identifier: "gd1977-05-08.sbd.hicks.4982".into(),
+                origin: "notes",
+            }]
+        );
+        let all = resolve(&db, "1977-05-08", None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1].identifier, "other-band-1977-05-08");
+        assert!(resolve(&db, "1977-05-09", None).unwrap().is_empty());
+
+        assert_eq!(
+            page_url("gd77 (1)", PageKind::Download),
+            "https://archive.org/download/gd77%20%281%29"
+        );
+    }
+}
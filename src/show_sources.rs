@@ -0,0 +1,95 @@
+//! Shows with more than one source under the same date.
+//!
+//! A collector often has two tapes of a night — an SBD and an AUD, or two
+//! transfers — and sorting the date's tracks by disc and track number
+//! interleaves them: "Bertha, Bertha, Good Lovin', Good Lovin'". Each source
+//! lives in its own recording directory (`paths::source_dir`), so the tracks are
+//! split on that. `show` lists the sources separately and `--source N` picks
+//! one; chain detection runs within a source so a segue never links a track
+//! on one tape to the next song on another.
+
+use crate::db::models::TrackScore;
+
+/// One source's tracks for a date, in playing order.
+#[derive(Debug, Clone)]
+pub struct SourceTracks {
+    /// Recording directory as stored (empty if the path had none).
+    pub dir: String,
+    pub tracks: Vec<TrackScore>,
+}
+
+impl SourceTracks {
+    /// Directory name for display.
+    pub fn name(&self) -> &str {
+        crate::paths::file_name(&self.dir)
+    }
+}
+
+/// Split a date's tracks by recording directory, keeping each source's order.
+/// Sources come most tracks first, then by directory, so `--source 1` is the
+/// most complete tape.
+pub fn split_by_source(tracks: Vec<TrackScore>) -> Vec<SourceTracks> {
+    let mut sources: Vec<SourceTracks> = Vec::new();
+    for track in tracks {
+        let dir = crate::paths::source_dir(&track.file_path).unwrap_or("");
+        match sources.iter_mut().find(|s| s.dir == dir) {
+            Some(source) => source.tracks.push(track),
+            None => sources.push(SourceTracks {
+                dir: dir.to_string(),
+                tracks: vec![track],
+            }),
+        }
+    }
+    sources.sort_by(|a, b| b.tracks.len().cmp(&a.tracks.len()).then(a.dir.cmp(&b.dir)));
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(path: &str, title: &str) -> TrackScore {
+        TrackScore {
+            title: title.into(),
+            date: "1977-05-08".into(),
+            file_path: path.into(),
+            band: None,
+            set: None,
+            album: None,
+            duration_min: 5.0,
+            key: None,
+            tempo: None,
+            energy: 0.0,
+            intensity: 0.0,
+            groove: 0.0,
+            improvisation: 0.0,
+            tightness: 0.0,
+            build_quality: 0.0,
+            exploratory: 0.0,
+            transcendence: 0.0,
+            valence: 0.0,
+            arousal: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_split_by_source() {
+        // As sorted by disc/track: the two tapes interleave
+        let tracks = vec![
+            track("/music/gd77-05-08.aud/t01.flac", "New Minglewood Blues"),
+            track("/music/gd77-05-08.sbd/cd1/t01.flac", "New Minglewood Blues"),
+            track("/music/gd77-05-08.sbd/cd1/t02.flac", "Loser"),
+            track("/music/gd77-05-08.aud/t02.flac", "Loser"),
+            track("/music/gd77-05-08.sbd/cd2/t01.flac", "Scarlet Begonias"),
+        ];
+        let sources = split_by_source(tracks);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].name(), "gd77-05-08.sbd");
+        let titles: Vec<&str> = sources[0].tracks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec!["New Minglewood Blues", "Loser", "Scarlet Begonias"]
+        );
+        assert_eq!(sources[1].tracks.len(), 2);
+    }
+}
@@ -0,0 +1,179 @@
+//! ASCII timeline of a whole show (`show --viz`).
+//!
+//! A strip across the top gives the whole night at a glance: each track takes
+//! width in proportion to its duration, shaded by one score, with `>` where a
+//! track segues into the next and `|` at a stop. Below it, one row per track
+//! with a bar scaled to the longest track and a `↓` joining chained tracks, so
+//! where the show peaked (marked `◀ peak`) is visible next to what led into it.
+
+use crate::db::models::TrackScore;
+use crate::scores::ScoreMeta;
+use crate::table::truncate;
+use std::collections::HashSet;
+
+/// Shading from lowest to highest quarter of the 0-100 scale.
+const SHADES: [char; 4] = ['░', '▒', '▓', '█'];
+
+/// Width used when no limit is given.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Title column width in the per-track rows.
+const TITLE_WIDTH: usize = 28;
+
+/// Shade for a 0-100 score.
+fn shade(value: f64) -> char {
+    SHADES[((value / 25.0).floor().max(0.0) as usize).min(SHADES.len() - 1)]
+}
+
+/// Render a show's tracks (in setlist order). `segues` holds the file paths of
+/// tracks that segue into the following one; `width` caps the line length.
+pub fn render(
+    tracks: &[TrackScore],
+    segues: &HashSet<&str>,
+    score: &ScoreMeta,
+    width: Option<usize>,
+) -> String {
+    if tracks.is_empty() {
+        return String::new();
+    }
+    let width = width.unwrap_or(DEFAULT_WIDTH).max(40);
+    let value = |t: &TrackScore| t.score(score.column).unwrap_or(0.0);
+    let segues_on = |t: &TrackScore| segues.contains(t.file_path.as_str());
+    let peak = (0..tracks.len())
+        .max_by(|&a, &b| value(&tracks[a]).total_cmp(&value(&tracks[b])))
+        .unwrap_or(0);
+
+    let mut out = String::new();
+
+    // Whole-show strip: separators take one cell each, tracks share the rest
+    let total: f64 = tracks.iter().map(|t| t.duration_min.max(0.0)).sum();
+    let cells = width.saturating_sub(tracks.len() - 1).max(tracks.len());
+    let mut strip = String::new();
+    let mut peak_at = 0;
+    let mut used = 0.0;
+    let mut drawn = 0;
+    for (i, t) in tracks.iter().enumerate() {
+        if i > 0 {
+            strip.push(if segues_on(&tracks[i - 1]) { '>' } else { '|' });
+            drawn += 1;
+        }
+        // Cumulative rounding keeps the strip's total length exact
+        used += t.duration_min.max(0.0);
+        let end = if total > 0.0 {
+            (used / total * cells as f64).round() as usize
+        } else {
+            cells * (i + 1) / tracks.len()
+        };
+        let n = end.saturating_sub(drawn - i).max(1);
+        if i == peak {
+            peak_at = drawn + n / 2;
+        }
+        strip.extend(std::iter::repeat_n(shade(value(t)), n));
+        drawn += n;
+    }
+    out.push_str(&strip);
+    out.push('\n');
+    let peak_label = format!(
+        "^ peak: {} ({:.0})",
+        tracks[peak].title,
+        value(&tracks[peak])
+    );
+    let peak_label = truncate(&peak_label, width);
+    // Keep the label on screen when the peak is near the right edge
+    let indent = peak_at.min(width.saturating_sub(peak_label.chars().count()));
+    out.push_str(&" ".repeat(indent));
+    out.push_str(&peak_label);
+    out.push_str("\n\n");
+
+    // Per-track rows: "NN  Title  MM.Mm  bar  score"
+    let bar_width = width.saturating_sub(TITLE_WIDTH + 28).max(10);
+    let longest = tracks
+        .iter()
+        .map(|t| t.duration_min)
+        .fold(0.0_f64, f64::max)
+        .max(f64::EPSILON);
+    for (i, t) in tracks.iter().enumerate() {
+        let n = ((t.duration_min / longest * bar_width as f64).round() as usize).max(1);
+        let bar: String = std::iter::repeat_n(shade(value(t)), n).collect();
+        let line = format!(
+            "{:>3}  {:<title$}  {:>5.1}m  {:<bar_width$}  {:>3.0}{}",
+            i + 1,
+            truncate(&t.title, TITLE_WIDTH),
+            t.duration_min,
+            bar,
+            value(t),
+            if i == peak { "  ◀ peak" } else { "" },
+            title = TITLE_WIDTH,
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+        if segues_on(t) && i + 1 < tracks.len() {
+            out.push_str("     ↓\n");
+        }
+    }
+
+    out.push('\n');
+    out.push_str(&format!(
+        "{}: {} <25  {} 25-50  {} 50-75  {} 75+   > segue  | stop\n",
+        score.label, SHADES[0], SHADES[1], SHADES[2], SHADES[3]
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str, minutes: f64, energy: f64) -> TrackScore {
+        TrackScore {
+            title: title.into(),
+            date: "1977-05-08".into(),
+            file_path: format!("/music/{title}.flac"),
+            band: None,
+            set: None,
+            album: None,
+            duration_min: minutes,
+            key: None,
+            tempo: None,
+            energy,
+            intensity: 0.0,
+            groove: 0.0,
+            improvisation: 0.0,
+            tightness: 0.0,
+            build_quality: 0.0,
+            exploratory: 0.0,
+            transcendence: 0.0,
+            valence: 0.0,
+            arousal: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_render_timeline() {
+        let tracks = vec![
+            track("Bertha", 6.0, 20.0),
+            track("Scarlet Begonias", 10.0, 60.0),
+            track("Fire on the Mountain", 14.0, 90.0),
+        ];
+        let segues: HashSet<&str> = ["/music/Scarlet Begonias.flac"].into_iter().collect();
+        let energy = crate::scores::get("energy").unwrap();
+        let out = render(&tracks, &segues, energy, Some(62));
+        let lines: Vec<&str> = out.lines().collect();
+
+        // The strip fills the width exactly: 60 track cells + 2 separators
+        assert_eq!(lines[0].chars().count(), 62);
+        assert_eq!(lines[0].chars().filter(|&c| c == '░').count(), 12);
+        assert!(lines[0].contains("▓>█"));
+        assert!(lines[0].contains("░|▓"));
+        assert!(
+            lines[1]
+                .trim_start()
+                .starts_with("^ peak: Fire on the Mountain (90)")
+        );
+
+        assert!(lines[3].starts_with("  1  Bertha"));
+        assert_eq!(lines[5], "     ↓");
+        assert!(lines[6].ends_with("█   90  ◀ peak"));
+        assert!(lines.last().unwrap().starts_with("Energy: ░ <25"));
+    }
+}
@@ -0,0 +1,291 @@
+//! Show-level metrics, led by jam minutes.
+//!
+//! Heads rank shows by how much of the night was spent jamming, not by any one
+//! track's score. A show's jam minutes are the minutes of music in tracks whose
+//! improvisation score reaches `JAM_IMPROVISATION`, each track's length scaled
+//! by its music share (classifier music score) so crowd noise, tuning, and
+//! stage talk don't count. Shows are one date and band; rows live in the `shows`
+//! table and are rebuilt when analysis, titles, or quality flags change.
+
+use crate::db::Database;
+use crate::db::columns::{BAND_EXPR, LIVE_ONLY, NOT_GARBAGE};
+use rusqlite::params;
+use std::collections::BTreeMap;
+
+/// Improvisation score from which a track counts as jamming.
+pub const JAM_IMPROVISATION: f64 = 50.0;
+
+/// One analyzed track as it feeds the show metrics.
+#[derive(Debug, Clone)]
+pub struct ShowTrack {
+    pub date: String,
+    pub band: String,
+    pub title: String,
+    pub minutes: f64,
+    /// Classifier music score (0-1); 1 when unknown.
+    pub music_share: f64,
+    pub improvisation: f64,
+}
+
+/// A show's metrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShowStats {
+    pub date: String,
+    pub band: String,
+    pub tracks: usize,
+    pub minutes: f64,
+    /// Music minutes in tracks at or above `JAM_IMPROVISATION`.
+    pub jam_minutes: f64,
+    pub jam_tracks: usize,
+    /// Title and music minutes of the show's longest jam.
+    pub longest_jam: Option<(String, f64)>,
+}
+
+impl ShowStats {
+    /// Share of the show's minutes spent jamming (0-1).
+    pub fn jam_share(&self) -> f64 {
+        if self.minutes > 0.0 {
+            self.jam_minutes / self.minutes
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What `shows --top` ranks by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShowMetric {
+    JamMinutes,
+    /// Jam minutes as a share of the show.
+    JamShare,
+    Minutes,
+}
+
+impl ShowMetric {
+    fn order_by(self) -> &'static str {
+        match self {
+            Self::JamMinutes => "jam_minutes DESC",
+            Self::JamShare => "jam_minutes / MAX(minutes, 1) DESC",
+            Self::Minutes => "minutes DESC",
+        }
+    }
+}
+
+/// Fold tracks into per-show metrics, ordered by date then band.
+pub fn compute(tracks: &[ShowTrack]) -> Vec<ShowStats> {
+    let mut shows: BTreeMap<(&str, &str), ShowStats> = BTreeMap::new();
+    for t in tracks {
+        let show = shows
+            .entry((t.date.as_str(), t.band.as_str()))
+            .or_insert_with(|| ShowStats {
+                date: t.date.clone(),
+                band: t.band.clone(),
+                tracks: 0,
+                minutes: 0.0,
+                jam_minutes: 0.0,
+                jam_tracks: 0,
+                longest_jam: None,
+            });
+        show.tracks += 1;
+        show.minutes += t.minutes;
+        if t.improvisation < JAM_IMPROVISATION {
+            continue;
+        }
+        let jam = t.minutes * t.music_share.clamp(0.0, 1.0);
+        show.jam_minutes += jam;
+        show.jam_tracks += 1;
+        if show.longest_jam.as_ref().is_none_or(|(_, m)| jam > *m) {
+            show.longest_jam = Some((t.title.clone(), jam));
+        }
+    }
+    shows.into_values().collect()
+}
+
+/// Recompute every show's metrics and replace the `shows` table. Returns the
+/// number of shows stored.
+pub fn rebuild_shows(db: &Database) -> crate::db::Result<usize> {
+    let fingerprint = db.chain_input_fingerprint()?;
+    let shows = compute(&db.show_tracks()?);
+    db.store_shows(&shows, &fingerprint)?;
+    Ok(shows.len())
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Analyzed live tracks with a date, for the show metrics.
+    fn show_tracks(&self) -> crate::db::Result<Vec<ShowTrack>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT COALESCE(t.parsed_date, t.date), {BAND_EXPR},
+                    COALESCE(t.parsed_title, t.title, '(untitled)'),
+                    COALESCE(a.duration, 0) / 60.0,
+                    COALESCE(a.classification_music_score, 1.0),
+                    COALESCE(a.improvisation_score, 0)
+             FROM analysis_results a JOIN tracks t ON t.id = a.track_id
+             WHERE COALESCE(t.parsed_date, t.date) IS NOT NULL
+               AND {LIVE_ONLY} AND {NOT_GARBAGE}"
+        ))?;
+        let tracks = stmt
+            .query_map([], |row| {
+                Ok(ShowTrack {
+                    date: row.get(0)?,
+                    band: row.get(1)?,
+                    title: row.get(2)?,
+                    minutes: row.get(3)?,
+                    music_share: row.get(4)?,
+                    improvisation: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// Replace the stored show metrics in one transaction.
+    fn store_shows(&self, shows: &[ShowStats], fingerprint: &str) -> crate::db::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM shows", [])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO shows (date, band, tracks, minutes, jam_minutes, jam_tracks,
+                                    longest_jam, longest_jam_minutes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for s in shows {
+                let (longest, longest_minutes) = match &s.longest_jam {
+                    Some((title, minutes)) => (Some(title.as_str()), Some(*minutes)),
+                    None => (None, None),
+                };
+                stmt.execute(params![
+                    s.date,
+                    s.band,
+                    s.tracks as i64,
+                    s.minutes,
+                    s.jam_minutes,
+                    s.jam_tracks as i64,
+                    longest,
+                    longest_minutes,
+                ])?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO show_state (id, fingerprint, built_at)
+             VALUES (1, ?1, datetime('now'))",
+            params![fingerprint],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// True if the show metrics were built from the current analysis and titles.
+    pub fn shows_are_fresh(&self) -> crate::db::Result<bool> {
+        let stored: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT fingerprint FROM show_state WHERE id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        let current = self.chain_input_fingerprint()?;
+        Ok(stored.as_deref() == Some(current.as_str()))
+    }
+
+    /// Stored shows ranked by `metric`, optionally for one band (canonical name).
+    pub fn top_shows(
+        &self,
+        metric: ShowMetric,
+        band: Option<&str>,
+        limit: usize,
+    ) -> crate::db::Result<Vec<ShowStats>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT date, band, tracks, minutes, jam_minutes, jam_tracks,
+                    longest_jam, longest_jam_minutes
+             FROM shows
+             WHERE ?1 IS NULL OR band = ?1
+             ORDER BY {}, date
+             LIMIT ?2",
+            metric.order_by()
+        ))?;
+        let shows = stmt
+            .query_map(params![band, limit as i64], |row| {
+                let longest: Option<String> = row.get(6)?;
+                let longest_minutes: Option<f64> = row.get(7)?;
+                Ok(ShowStats {
+                    date: row.get(0)?,
+                    band: row.get(1)?,
+                    tracks: row.get::<_, i64>(2)? as usize,
+                    minutes: row.get(3)?,
+                    jam_minutes: row.get(4)?,
+                    jam_tracks: row.get::<_, i64>(5)? as usize,
+                    longest_jam: longest.zip(longest_minutes),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(shows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(date: &str, title: &str, minutes: f64, music: f64, impro: f64) -> ShowTrack {
+        ShowTrack {
+            date: date.into(),
+            band: "Grateful Dead".into(),
+            title: title.into(),
+            minutes,
+            music_share: music,
+            improvisation: impro,
+        }
+    }
+
+    #[test]
+    fn test_jam_minutes() {
+        let tracks = vec![
+            track("1972-08-27", "Dark Star", 30.0, 0.9, 80.0),
+            track("1972-08-27", "Sugar Magnolia", 7.0, 1.0, 30.0),
+            track("1972-08-27", "Playin' in the Band", 20.0, 1.0, 60.0),
+            track("1977-05-08", "Tuning", 2.0, 0.1, 55.0),
+            track("1977-05-08", "Minglewood Blues", 5.0, 1.0, 20.0),
+        ];
+        let shows = compute(&tracks);
+        assert_eq!(shows.len(), 2);
+
+        let veneta = &shows[0];
+        assert_eq!(veneta.tracks, 3);
+        assert_eq!(veneta.jam_tracks, 2);
+        assert!((veneta.jam_minutes - 47.0).abs() < 1e-9);
+        assert_eq!(veneta.longest_jam.as_ref().unwrap().0, "Dark Star");
+        assert!((veneta.jam_share() - 47.0 / 57.0).abs() < 1e-9);
+
+        // Mostly non-music audio barely counts even when "improvised"
+        assert!((shows[1].jam_minutes - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_store_and_rank() {
+        let db = Database::open_in_memory().unwrap();
+        assert!(!db.shows_are_fresh().unwrap());
+        db.store_shows(
+            &compute(&[
+                track("1972-08-27", "Dark Star", 30.0, 1.0, 80.0),
+                track("1977-05-08", "Morning Dew", 12.0, 1.0, 60.0),
+                track("1977-05-08", "Minglewood Blues", 60.0, 1.0, 10.0),
+            ]),
+            &db.chain_input_fingerprint().unwrap(),
+        )
+        .unwrap();
+        assert!(db.shows_are_fresh().unwrap());
+
+        let by_jam = db.top_shows(ShowMetric::JamMinutes, None, 10).unwrap();
+        assert_eq!(by_jam[0].date, "1972-08-27");
+        let by_length = db.top_shows(ShowMetric::Minutes, None, 10).unwrap();
+        assert_eq!(by_length[0].date, "1977-05-08");
+        assert!(
+            db.top_shows(ShowMetric::JamShare, Some("Phish"), 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
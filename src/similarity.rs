@@ -1,10 +1,19 @@
 use crate::db::Database;
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::db::columns::{BAND_EXPR, NOT_GARBAGE};
+use crate::db::models::TrackScore;
+use crate::feature_vector::{FEATURES, VectorFormat};
+use crate::progress::Progress;
 use rayon::prelude::*;
+use rusqlite::types::Value;
+use std::collections::{HashMap, HashSet};
 
 /// Number of nearest neighbors to store per track.
 const TOP_K: usize = 20;
 
+/// How hard `--not-like` pushes a candidate down: its positive cosine
+/// similarity to the negative centroid, scaled by this, is added to its distance.
+const NOT_LIKE_WEIGHT: f64 = 0.5;
+
 pub struct SimilarityResult {
     pub tracks_processed: usize,
     pub pairs_stored: usize,
@@ -14,6 +23,7 @@ pub struct SimilarityResult {
 pub fn compute_similarity(
     db: &Database,
     jobs: usize,
+    progress: &dyn Progress,
 ) -> Result<SimilarityResult, crate::db::DbError> {
     // Load all feature vectors
     let raw = db.get_feature_vectors()?;
@@ -32,18 +42,12 @@ pub fn compute_similarity(
     // Z-score normalize each dimension across all tracks
     let vectors = normalize_features(&raw, dim);
 
-    println!(
+    progress.note(&format!(
         "Computing similarity for {} tracks ({}-dim vectors)...",
         n, dim
-    );
+    ));
 
-    let pb = ProgressBar::new(n as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} tracks ({eta} remaining)")
-            .unwrap()
-            .progress_chars("=>-"),
-    );
+    progress.start(n as u64, "tracks");
 
     // Build rayon pool
     let pool = rayon::ThreadPoolBuilder::new()
@@ -51,48 +55,22 @@ pub fn compute_similarity(
         .build()
         .unwrap();
 
-    // For each track, find top-K most similar tracks by cosine similarity.
-    // Cosine similarity → distance = 1.0 - similarity (0 = identical, 2 = opposite).
     let all_neighbors: Vec<Vec<(usize, f64)>> = pool.install(|| {
         (0..n)
             .into_par_iter()
             .map(|i| {
-                let mut distances: Vec<(usize, f64)> = (0..n)
-                    .filter(|&j| j != i)
-                    .map(|j| {
-                        let sim = cosine_similarity(&vectors[i], &vectors[j]);
-                        let dist = 1.0 - sim;
-                        (j, dist)
-                    })
-                    .collect();
-
-                // Partial sort: only need top-K smallest distances
-                let k = TOP_K.min(distances.len()) - 1;
-                distances.select_nth_unstable_by(k, |a, b| {
-                    a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)
-                });
-                distances.truncate(TOP_K);
-                distances
-                    .sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-
-                pb.inc(1);
-                distances
+                let neighbors = nearest(i, &vectors);
+                progress.inc(1);
+                neighbors
             })
             .collect()
     });
 
-    pb.finish_with_message("done");
-
-    // Flatten into (track_id, similar_track_id, distance, rank) tuples
-    let mut pairs: Vec<(i64, i64, f64, i32)> = Vec::with_capacity(n * TOP_K);
-    for (i, neighbors) in all_neighbors.iter().enumerate() {
-        for (rank, &(j, dist)) in neighbors.iter().enumerate() {
-            pairs.push((track_ids[i], track_ids[j], dist, rank as i32 + 1));
-        }
-    }
+    progress.finish("done");
 
+    let pairs = neighbor_pairs(&track_ids, (0..n).zip(&all_neighbors));
     let pairs_count = pairs.len();
-    println!("Storing {} similarity pairs...", pairs_count);
+    progress.note(&format!("Storing {} similarity pairs...", pairs_count));
     db.store_similarities(&pairs)?;
 
     Ok(SimilarityResult {
@@ -101,12 +79,293 @@ pub fn compute_similarity(
     })
 }
 
+/// Refresh only the neighbor lists that re-analysis (or new tracks) could have
+/// changed since the last run: the changed tracks themselves, tracks listing a
+/// changed track as a neighbor, and tracks a changed track now sits closer to
+/// than their current furthest neighbor. Z-score statistics are recomputed over
+/// the whole library, so untouched lists drift slightly from a full rebuild;
+/// run plain `similarity` now and then to re-baseline.
+///
+/// Neighbor lists computed with an older feature vector layout (or none yet)
+/// can't be patched with new vectors, so that case does a full rebuild.
+pub fn refresh_changed_similarity(
+    db: &Database,
+    jobs: usize,
+    progress: &dyn Progress,
+) -> Result<SimilarityResult, crate::db::DbError> {
+    match db.similarity_format()? {
+        Some(format) if format.is_current() => {}
+        Some(format) => {
+            progress.note(&format!(
+                "Stored neighbors use feature vector {format}, now {}: rebuilding all of them",
+                VectorFormat::CURRENT
+            ));
+            return compute_similarity(db, jobs, progress);
+        }
+        None => return compute_similarity(db, jobs, progress),
+    }
+    let changed: HashSet<i64> = db.similarity_changed_ids()?.into_iter().collect();
+    if changed.is_empty() {
+        return Ok(SimilarityResult {
+            tracks_processed: 0,
+            pairs_stored: 0,
+        });
+    }
+    let raw = db.get_feature_vectors()?;
+    let n = raw.len();
+    if n < 2 {
+        return Ok(SimilarityResult {
+            tracks_processed: 0,
+            pairs_stored: 0,
+        });
+    }
+    let track_ids: Vec<i64> = raw.iter().map(|(id, _)| *id).collect();
+    let vectors = normalize_features(&raw, raw[0].1.len());
+    let stored = db.stored_neighbors()?;
+    let changed_idx: Vec<usize> = (0..n)
+        .filter(|&i| changed.contains(&track_ids[i]))
+        .collect();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .unwrap();
+    let full_list = TOP_K.min(n - 1);
+    let affected: Vec<usize> = pool.install(|| {
+        (0..n)
+            .into_par_iter()
+            .filter(|&i| {
+                if changed.contains(&track_ids[i]) {
+                    return true;
+                }
+                let Some(list) = stored.get(&track_ids[i]) else {
+                    return true;
+                };
+                if list.len() < full_list || list.iter().any(|(id, _)| changed.contains(id)) {
+                    return true;
+                }
+                let furthest = list.last().map_or(f64::INFINITY, |&(_, d)| d);
+                changed_idx
+                    .iter()
+                    .any(|&c| 1.0 - cosine_similarity(&vectors[i], &vectors[c]) < furthest)
+            })
+            .collect()
+    });
+
+    progress.note(&format!(
+        "Refreshing similarity for {} tracks ({} changed since the last run)...",
+        affected.len(),
+        changed.len()
+    ));
+    progress.start(affected.len() as u64, "tracks");
+    let neighbors: Vec<Vec<(usize, f64)>> = pool.install(|| {
+        affected
+            .par_iter()
+            .map(|&i| {
+                let neighbors = nearest(i, &vectors);
+                progress.inc(1);
+                neighbors
+            })
+            .collect()
+    });
+    progress.finish("done");
+
+    let pairs = neighbor_pairs(&track_ids, affected.iter().copied().zip(&neighbors));
+    let ids: Vec<i64> = affected.iter().map(|&i| track_ids[i]).collect();
+    db.replace_similarities(&ids, &pairs)?;
+
+    Ok(SimilarityResult {
+        tracks_processed: affected.len(),
+        pairs_stored: pairs.len(),
+    })
+}
+
+/// The `TOP_K` nearest tracks to `i` by cosine distance, nearest first.
+/// Distance = 1.0 - cosine similarity (0 = identical, 2 = opposite).
+fn nearest(i: usize, vectors: &[Vec<f64>]) -> Vec<(usize, f64)> {
+    let mut distances: Vec<(usize, f64)> = (0..vectors.len())
+        .filter(|&j| j != i)
+        .map(|j| (j, 1.0 - cosine_similarity(&vectors[i], &vectors[j])))
+        .collect();
+    if distances.is_empty() {
+        return distances;
+    }
+
+    // Partial sort: only need top-K smallest distances
+    let k = TOP_K.min(distances.len()) - 1;
+    distances.select_nth_unstable_by(k, |a, b| {
+        a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    distances.truncate(TOP_K);
+    distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    distances
+}
+
+/// Flatten neighbor lists into (track_id, similar_track_id, distance, rank) tuples.
+fn neighbor_pairs<'a>(
+    track_ids: &[i64],
+    lists: impl Iterator<Item = (usize, &'a Vec<(usize, f64)>)>,
+) -> Vec<(i64, i64, f64, i32)> {
+    let mut pairs = Vec::new();
+    for (i, neighbors) in lists {
+        for (rank, &(j, dist)) in neighbors.iter().enumerate() {
+            pairs.push((track_ids[i], track_ids[j], dist, rank as i32 + 1));
+        }
+    }
+    pairs
+}
+
+/// Rank tracks by cosine distance to the centroid of several seed tracks
+/// ("more like these"), closest first, as (track, distance, track_id).
+/// Seeds are excluded; `same_band` keeps results to the seeds' bands, and
+/// `filter` applies metadata constraints (see [`SimilarFilter`]).
+///
+/// `avoid` are negative seeds ("not like these"): candidates resembling their
+/// centroid are pushed down by [`not_like_distance`], and the reported
+/// distance includes that penalty. Negative seeds never appear in results.
+///
+/// Z-scoring is affine, so the centroid of the normalized seed vectors is the
+/// normalized centroid of the raw ones — the same target a sound profile uses.
+pub fn similar_to_seeds(
+    db: &Database,
+    seeds: &[i64],
+    avoid: &[i64],
+    limit: usize,
+    same_band: bool,
+    filter: &SimilarFilter,
+) -> Result<Vec<(TrackScore, f64, i64)>, crate::db::DbError> {
+    let raw = db.get_feature_vectors()?;
+    let (means, stds) = feature_stats(&raw, FEATURES.len());
+    let seed_set: HashSet<i64> = seeds.iter().copied().collect();
+    let avoid_set: HashSet<i64> = avoid.iter().copied().collect();
+    let normalized_of = |ids: &HashSet<i64>| -> Vec<Vec<f64>> {
+        raw.iter()
+            .filter(|(id, _)| ids.contains(id))
+            .map(|(_, v)| normalize_vector(v, &means, &stds))
+            .collect()
+    };
+    let seed_vectors = normalized_of(&seed_set);
+    if seed_vectors.is_empty() {
+        return Ok(Vec::new());
+    }
+    let target = centroid(&seed_vectors);
+    let avoid_vectors = normalized_of(&avoid_set);
+    let negative = (!avoid_vectors.is_empty()).then(|| centroid(&avoid_vectors));
+
+    let candidates = db.seed_candidate_ids(seeds, same_band, filter)?;
+    let mut ranked: Vec<(i64, f64)> = raw
+        .iter()
+        .filter(|(id, _)| {
+            candidates.contains(id) && !seed_set.contains(id) && !avoid_set.contains(id)
+        })
+        .map(|(id, v)| {
+            let v = normalize_vector(v, &means, &stds);
+            (*id, not_like_distance(&v, &target, negative.as_deref()))
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+    ranked.truncate(limit);
+
+    let ids: Vec<i64> = ranked.iter().map(|(id, _)| *id).collect();
+    let distances: HashMap<i64, f64> = ranked.into_iter().collect();
+    Ok(db
+        .query_tracks_by_id(&ids, false)?
+        .into_iter()
+        .map(|(id, t)| (t, distances[&id], id))
+        .collect())
+}
+
+/// Cosine distance from `v` to `target`, plus a penalty for resembling
+/// `negative`. Only positive similarity is penalized: tracks unlike the
+/// negative seeds keep their plain distance rather than getting a bonus.
+pub fn not_like_distance(v: &[f64], target: &[f64], negative: Option<&[f64]>) -> f64 {
+    let distance = 1.0 - cosine_similarity(v, target);
+    let penalty = negative.map_or(0.0, |n| cosine_similarity(v, n).max(0.0));
+    distance + NOT_LIKE_WEIGHT * penalty
+}
+
+/// Split a `SONG@DATE` seed; the date part is optional (`"Dark Star"` alone works).
+pub fn parse_seed(seed: &str) -> (String, Option<String>) {
+    match seed.rsplit_once('@') {
+        Some((song, date))
+            if !song.trim().is_empty() && date.trim().starts_with(|c: char| c.is_ascii_digit()) =>
+        {
+            (song.trim().to_string(), Some(date.trim().to_string()))
+        }
+        _ => (seed.trim().to_string(), None),
+    }
+}
+
+/// Metadata constraints on `similar` results, so discovery isn't five versions
+/// of the seed. Applied when ranking, on top of the feature distance.
+#[derive(Debug, Clone, Default)]
+pub struct SimilarFilter {
+    /// Skip tracks from a seed's show (same band and date).
+    pub exclude_same_show: bool,
+    /// Skip every performance of a seed's song (part and segue markers ignored).
+    pub exclude_same_song: bool,
+    /// Only this band's tracks; replaces the seeds' bands as the band filter.
+    pub band: Option<String>,
+    /// Only tracks from shows in these years, inclusive. Undated tracks are skipped.
+    pub years: Option<(i32, i32)>,
+}
+
+impl SimilarFilter {
+    /// Whether any constraint is set.
+    pub fn is_active(&self) -> bool {
+        self.exclude_same_show
+            || self.exclude_same_song
+            || self.band.is_some()
+            || self.years.is_some()
+    }
+}
+
+/// Parse a year range: `1972-1974`, `1972..1974`, or a single `1977`.
+pub fn parse_year_range(s: &str) -> Option<(i32, i32)> {
+    let year = |y: &str| {
+        y.trim()
+            .parse::<i32>()
+            .ok()
+            .filter(|y| (1000..=9999).contains(y))
+    };
+    let (from, to) = match s.split_once("..").or_else(|| s.split_once('-')) {
+        Some((from, to)) => (year(from)?, year(to)?),
+        None => (year(s)?, year(s)?),
+    };
+    (from <= to).then_some((from, to))
+}
+
+/// Key for "the same song": the lowercased title without part or segue markers.
+fn song_key(title: &str) -> String {
+    crate::track_groups::song_part(title).0.to_lowercase()
+}
+
+/// Element-wise mean of equal-length vectors.
+pub(crate) fn centroid(vectors: &[Vec<f64>]) -> Vec<f64> {
+    let dim = vectors[0].len();
+    let mut sum = vec![0.0_f64; dim];
+    for v in vectors {
+        for (d, &val) in v.iter().enumerate() {
+            sum[d] += val;
+        }
+    }
+    sum.iter().map(|s| s / vectors.len() as f64).collect()
+}
+
 /// Z-score normalize each dimension: subtract mean, divide by std.
 /// Returns a Vec of normalized vectors (same shape as input).
 fn normalize_features(raw: &[(i64, Vec<f64>)], dim: usize) -> Vec<Vec<f64>> {
+    let (means, stds) = feature_stats(raw, dim);
+    raw.iter()
+        .map(|(_, vec)| normalize_vector(vec, &means, &stds))
+        .collect()
+}
+
+/// Per-dimension mean and standard deviation across all vectors.
+/// Standard deviations are floored at 1e-10 so constant dimensions don't divide by zero.
+pub(crate) fn feature_stats(raw: &[(i64, Vec<f64>)], dim: usize) -> (Vec<f64>, Vec<f64>) {
     let n = raw.len();
 
-    // Compute mean and std for each dimension
     let mut means = vec![0.0_f64; dim];
     let mut vars = vec![0.0_f64; dim];
 
@@ -130,19 +389,179 @@ fn normalize_features(raw: &[(i64, Vec<f64>)], dim: usize) -> Vec<Vec<f64>> {
         .map(|v| (v / n as f64).sqrt().max(1e-10))
         .collect();
 
-    // Normalize
-    raw.iter()
-        .map(|(_, vec)| {
-            vec.iter()
-                .enumerate()
-                .map(|(d, &val)| (val - means[d]) / stds[d])
-                .collect()
-        })
+    (means, stds)
+}
+
+/// Z-score a single vector against precomputed per-dimension stats.
+pub(crate) fn normalize_vector(vec: &[f64], means: &[f64], stds: &[f64]) -> Vec<f64> {
+    vec.iter()
+        .enumerate()
+        .map(|(d, &val)| (val - means[d]) / stds[d])
+        .collect()
+}
+
+/// Feature families that a similarity distance is broken down into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureGroup {
+    /// MFCCs: the tone color of the instruments and recording.
+    Timbre,
+    /// Spectral flux and tempo: how the music pulses.
+    Rhythm,
+    /// Sub-band energy: bass vs mids vs highs.
+    Balance,
+    /// Centroid, bandwidth, rolloff, flatness, ZCR: brightness and noisiness.
+    Spectral,
+}
+
+impl FeatureGroup {
+    pub const ALL: [FeatureGroup; 4] = [
+        FeatureGroup::Timbre,
+        FeatureGroup::Rhythm,
+        FeatureGroup::Balance,
+        FeatureGroup::Spectral,
+    ];
+
+    /// Column header for tables.
+    pub fn short(self) -> &'static str {
+        match self {
+            FeatureGroup::Timbre => "Timbre",
+            FeatureGroup::Rhythm => "Rhythm",
+            FeatureGroup::Balance => "Balance",
+            FeatureGroup::Spectral => "Bright",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FeatureGroup::Timbre => "timbre (MFCC)",
+            FeatureGroup::Rhythm => "rhythm",
+            FeatureGroup::Balance => "spectral balance",
+            FeatureGroup::Spectral => "brightness/texture",
+        }
+    }
+
+    /// Group of a `FEATURES` column.
+    pub fn of(column: &str) -> FeatureGroup {
+        if column.starts_with("mfcc_") {
+            FeatureGroup::Timbre
+        } else if column.starts_with("spectral_flux") || column == "tempo_bpm" {
+            FeatureGroup::Rhythm
+        } else if column.starts_with("sub_band_") {
+            FeatureGroup::Balance
+        } else {
+            FeatureGroup::Spectral
+        }
+    }
+}
+
+/// Why two tracks are the distance apart they are.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub distance: f64,
+    /// Each group's part of the distance, largest first. Parts sum to `distance`.
+    pub groups: Vec<(FeatureGroup, f64)>,
+    /// The single features contributing most, largest first.
+    pub top_features: Vec<(&'static str, f64)>,
+}
+
+impl Explanation {
+    /// Share of the distance from `group`, 0-1.
+    pub fn share(&self, group: FeatureGroup) -> f64 {
+        if self.distance <= 0.0 {
+            return 0.0;
+        }
+        self.groups
+            .iter()
+            .find(|(g, _)| *g == group)
+            .map_or(0.0, |(_, d)| d / self.distance)
+    }
+}
+
+/// Per-dimension contributions to the cosine distance `1 - cos(a, b)`.
+///
+/// With unit vectors `â = a/|a|`, `b̂ = b/|b|`, the distance equals
+/// `½ Σ (â_d - b̂_d)²`, so each dimension's term is its exact share.
+pub fn distance_contributions(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let (na, nb) = (norm(a), norm(b));
+    if na < 1e-10 || nb < 1e-10 {
+        return vec![0.0; a.len()];
+    }
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| 0.5 * (x / na - y / nb).powi(2))
         .collect()
 }
 
+/// Explains stored similarity distances, using the same normalization as
+/// `compute_similarity`. Loads every feature vector once; reuse for many pairs.
+pub struct Explainer {
+    vectors: HashMap<i64, Vec<f64>>,
+}
+
+impl Explainer {
+    pub fn load(db: &Database) -> Result<Self, crate::db::DbError> {
+        let raw = db.get_feature_vectors()?;
+        let (means, stds) = feature_stats(&raw, FEATURES.len());
+        let vectors = raw
+            .iter()
+            .map(|(id, v)| (*id, normalize_vector(v, &means, &stds)))
+            .collect();
+        Ok(Self { vectors })
+    }
+
+    /// Break down the distance between two tracks; `None` if either isn't analyzed.
+    pub fn explain(&self, a: i64, b: i64, top_features: usize) -> Option<Explanation> {
+        let parts = distance_contributions(self.vectors.get(&a)?, self.vectors.get(&b)?);
+        Some(explain_parts(&parts, top_features))
+    }
+
+    /// Break down the distance from the centroid of `seeds` to track `b`.
+    pub fn explain_seeds(&self, seeds: &[i64], b: i64, top_features: usize) -> Option<Explanation> {
+        let seed_vectors: Vec<Vec<f64>> = seeds
+            .iter()
+            .filter_map(|id| self.vectors.get(id).cloned())
+            .collect();
+        if seed_vectors.is_empty() {
+            return None;
+        }
+        let parts = distance_contributions(&centroid(&seed_vectors), self.vectors.get(&b)?);
+        Some(explain_parts(&parts, top_features))
+    }
+}
+
+fn explain_parts(parts: &[f64], top_features: usize) -> Explanation {
+    let mut groups: Vec<(FeatureGroup, f64)> = FeatureGroup::ALL
+        .iter()
+        .map(|&g| {
+            let sum = FEATURES
+                .iter()
+                .zip(parts)
+                .filter(|(c, _)| FeatureGroup::of(c) == g)
+                .map(|(_, p)| p)
+                .sum();
+            (g, sum)
+        })
+        .collect();
+    groups.sort_by(|x, y| y.1.total_cmp(&x.1));
+
+    let mut features: Vec<(&'static str, f64)> = FEATURES
+        .iter()
+        .copied()
+        .zip(parts.iter().copied())
+        .collect();
+    features.sort_by(|x, y| y.1.total_cmp(&x.1));
+    features.truncate(top_features);
+
+    Explanation {
+        distance: parts.iter().sum(),
+        groups,
+        top_features: features,
+    }
+}
+
 /// Cosine similarity between two vectors.
-fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+pub(crate) fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
     let mut dot = 0.0_f64;
     let mut norm_a = 0.0_f64;
     let mut norm_b = 0.0_f64;
@@ -157,9 +576,118 @@ fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
     if denom < 1e-10 { 0.0 } else { dot / denom }
 }
 
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Analyzed, non-garbage tracks eligible as seeded-similarity results.
+    fn seed_candidate_ids(
+        &self,
+        seeds: &[i64],
+        same_band: bool,
+        filter: &SimilarFilter,
+    ) -> crate::db::Result<HashSet<i64>> {
+        let placeholders = vec!["?"; seeds.len()].join(", ");
+        let seed_params = || seeds.iter().map(|&id| Value::Integer(id));
+        let mut conditions = String::new();
+        let mut params: Vec<Value> = Vec::new();
+        match &filter.band {
+            Some(band) => {
+                conditions.push_str(&format!("AND {BAND_EXPR} = ?\n"));
+                params.push(Value::Text(band.clone()));
+            }
+            None if same_band && !seeds.is_empty() => {
+                conditions.push_str(&format!(
+                    "AND {BAND_EXPR} IN (SELECT COALESCE(parsed_band, artist, '')
+                                         FROM tracks WHERE id IN ({placeholders}))\n"
+                ));
+                params.extend(seed_params());
+            }
+            None => {}
+        }
+        if let Some((from, to)) = filter.years {
+            conditions.push_str(
+                "AND CAST(substr(COALESCE(t.parsed_date, t.date), 1, 4) AS INTEGER)
+                     BETWEEN ? AND ?\n",
+            );
+            params.extend([Value::Integer(from.into()), Value::Integer(to.into())]);
+        }
+        if filter.exclude_same_show && !seeds.is_empty() {
+            conditions.push_str(&format!(
+                "AND NOT EXISTS (SELECT 1 FROM tracks s
+                                 WHERE s.id IN ({placeholders})
+                                   AND COALESCE(s.parsed_date, s.date) = COALESCE(t.parsed_date, t.date)
+                                   AND COALESCE(s.parsed_band, s.artist, '') = {BAND_EXPR})\n"
+            ));
+            params.extend(seed_params());
+        }
+        let sql = format!(
+            "SELECT a.track_id, COALESCE(t.parsed_title, t.title, '')
+             FROM analysis_results a
+             JOIN tracks t ON t.id = a.track_id
+             WHERE {NOT_GARBAGE}
+               {conditions}"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        if !filter.exclude_same_song || seeds.is_empty() {
+            return Ok(rows.into_iter().map(|(id, _)| id).collect());
+        }
+        let seed_songs: HashSet<String> = self
+            .conn
+            .prepare(&format!(
+                "SELECT COALESCE(parsed_title, title, '') FROM tracks WHERE id IN ({placeholders})"
+            ))?
+            .query_map(rusqlite::params_from_iter(seeds), |row| {
+                row.get::<_, String>(0)
+            })?
+            .map(|title| title.map(|t| song_key(&t)))
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(rows
+            .into_iter()
+            .filter(|(_, title)| {
+                let key = song_key(title);
+                key.is_empty() || !seed_songs.contains(&key)
+            })
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    /// Every analyzed performance of a song (substring match), optionally on one
+    /// date or by one band — the negative seeds for `similar --not-like`.
+    pub fn matching_track_ids(
+        &self,
+        song: &str,
+        date: Option<&str>,
+        band: Option<&str>,
+    ) -> crate::db::Result<Vec<i64>> {
+        let pattern = format!("%{song}%");
+        let sql = format!(
+            "SELECT t.id
+             FROM tracks t
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE (t.parsed_title LIKE ?1 OR t.title LIKE ?1)
+               AND (?2 IS NULL OR t.parsed_date = ?2 OR t.date = ?2)
+               AND (?3 IS NULL OR {BAND_EXPR} = ?3)
+               AND {NOT_GARBAGE}"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params![pattern, date, band], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::queries::tests::new_track;
+    use crate::progress::SilentProgress;
 
     #[test]
     fn test_cosine_identical() {
@@ -184,6 +712,131 @@ mod tests {
         assert!((sim + 1.0).abs() < 1e-10);
     }
 
+    #[test]
+    fn test_distance_contributions_sum_to_distance() {
+        let a: Vec<f64> = (0..FEATURES.len()).map(|i| (i as f64).sin()).collect();
+        let mut b = a.clone();
+        b[0] += 3.0; // mfcc_0_mean: timbre
+        b[46] -= 1.0; // tempo_bpm: rhythm
+
+        let parts = distance_contributions(&a, &b);
+        let dist = 1.0 - cosine_similarity(&a, &b);
+        assert!((parts.iter().sum::<f64>() - dist).abs() < 1e-9);
+
+        let e = explain_parts(&parts, 2);
+        assert_eq!(e.groups[0].0, FeatureGroup::Timbre);
+        assert_eq!(e.top_features[0].0, "mfcc_0_mean");
+        let shares: f64 = FeatureGroup::ALL.iter().map(|&g| e.share(g)).sum();
+        assert!((shares - 1.0).abs() < 1e-9);
+        assert_eq!(FeatureGroup::of("spectral_flux_std"), FeatureGroup::Rhythm);
+        assert_eq!(FeatureGroup::of("zcr_mean"), FeatureGroup::Spectral);
+        for col in FEATURES {
+            assert!(
+                crate::db::columns::ANALYSIS_SCHEMA
+                    .iter()
+                    .any(|c| c.name == *col),
+                "{col} missing from ANALYSIS_SCHEMA"
+            );
+        }
+    }
+
+    #[test]
+    fn test_not_like_distance() {
+        let target = [1.0, 0.0];
+        let negative = [0.0, 1.0];
+        // Equally far from the target; the one leaning toward the negative ranks lower
+        let toward = not_like_distance(&[1.0, 1.0], &target, Some(&negative));
+        let away = not_like_distance(&[1.0, -1.0], &target, Some(&negative));
+        assert!(toward > away);
+        // Dissimilarity to the negative earns no bonus
+        assert!((away - not_like_distance(&[1.0, -1.0], &target, None)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_seed() {
+        assert_eq!(
+            parse_seed("Dark Star@1972-08-27"),
+            ("Dark Star".to_string(), Some("1972-08-27".to_string()))
+        );
+        assert_eq!(
+            parse_seed("Eyes of the World"),
+            ("Eyes of the World".to_string(), None)
+        );
+        // An @ that isn't followed by a date is part of the title
+        assert_eq!(
+            parse_seed("Jam @ the Fillmore"),
+            ("Jam @ the Fillmore".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_parse_year_range() {
+        assert_eq!(parse_year_range("1972-1974"), Some((1972, 1974)));
+        assert_eq!(parse_year_range("1972..1974"), Some((1972, 1974)));
+        assert_eq!(parse_year_range("1977"), Some((1977, 1977)));
+        assert_eq!(parse_year_range("1974-1972"), None);
+        assert_eq!(parse_year_range("72-74"), None);
+    }
+
+    #[test]
+    fn test_similar_filter() {
+        let db = Database::open_in_memory().unwrap();
+        let ids: Vec<i64> = (0..6)
+            .map(|n| analyzed_track(&db, n, n as f64 * 10.0))
+            .collect();
+        // (band, date, title): the seed, its show-mate, another Dark Star,
+        // a Phish track, and two other Dead shows
+        let meta = [
+            ("Grateful Dead", "1972-08-27", "Dark Star ->"),
+            ("Grateful Dead", "1972-08-27", "El Paso"),
+            ("Grateful Dead", "1973-11-11", "Dark Star (Part 2)"),
+            ("Phish", "1997-11-17", "Tweezer"),
+            ("Grateful Dead", "1974-06-18", "Eyes of the World"),
+            ("Grateful Dead", "1977-05-08", "Morning Dew"),
+        ];
+        for (id, (band, date, title)) in ids.iter().zip(meta) {
+            db.conn
+                .execute(
+                    "UPDATE tracks SET parsed_band = ?2, parsed_date = ?3, parsed_title = ?4
+                     WHERE id = ?1",
+                    rusqlite::params![id, band, date, title],
+                )
+                .unwrap();
+        }
+        let results = |same_band: bool, filter: SimilarFilter| -> Vec<i64> {
+            let mut found: Vec<i64> = similar_to_seeds(&db, &ids[..1], &[], 10, same_band, &filter)
+                .unwrap()
+                .into_iter()
+                .map(|(_, _, id)| id)
+                .collect();
+            found.sort();
+            found
+        };
+
+        assert_eq!(
+            results(true, SimilarFilter::default()),
+            vec![ids[1], ids[2], ids[4], ids[5]]
+        );
+        let filter = SimilarFilter {
+            exclude_same_show: true,
+            exclude_same_song: true,
+            ..Default::default()
+        };
+        assert!(filter.is_active());
+        assert_eq!(results(true, filter), vec![ids[4], ids[5]]);
+        let filter = SimilarFilter {
+            years: Some((1973, 1974)),
+            ..Default::default()
+        };
+        assert_eq!(results(false, filter), vec![ids[2], ids[4]]);
+        // An explicit band replaces the seed's
+        let filter = SimilarFilter {
+            band: Some("Phish".into()),
+            ..Default::default()
+        };
+        assert_eq!(results(true, filter), vec![ids[3]]);
+    }
+
     #[test]
     fn test_normalize_features() {
         let raw = vec![
@@ -202,4 +855,77 @@ mod tests {
         // Both dimensions should have same normalized values despite different scales
         assert!((normed[0][0] - normed[0][1]).abs() < 1e-10);
     }
+
+    fn analyzed_track(db: &Database, n: usize, mfcc: f64) -> i64 {
+        let id = db
+            .upsert_track(&crate::db::models::NewTrack {
+                title: Some(format!("Song {n}")),
+                ..new_track(&format!("/m/t{n:02}.flac"))
+            })
+            .unwrap();
+        db.store_analysis(&crate::db::models::NewAnalysis {
+            track_id: id,
+            mfcc_0_mean: Some(mfcc),
+            mfcc_1_mean: Some(n as f64),
+            zcr_mean: Some((n % 3) as f64),
+            ..Default::default()
+        })
+        .unwrap();
+        id
+    }
+
+    #[test]
+    fn test_changed_only_refresh() {
+        let db = Database::open_in_memory().unwrap();
+        let ids: Vec<i64> = (0..6)
+            .map(|n| analyzed_track(&db, n, n as f64 * 10.0))
+            .collect();
+        compute_similarity(&db, 1, &SilentProgress).unwrap();
+        assert!(db.similarity_changed_ids().unwrap().is_empty());
+
+        // Re-analysis bumps analyzed_at; a new track has no state at all
+        db.conn
+            .execute(
+                "UPDATE analysis_results SET mfcc_0_mean = 500, analyzed_at = '2099-01-01 00:00:00'
+                 WHERE track_id = ?1",
+                [ids[2]],
+            )
+            .unwrap();
+        let added = analyzed_track(&db, 6, 15.0);
+        assert_eq!(db.similarity_changed_ids().unwrap(), vec![ids[2], added]);
+
+        let result = refresh_changed_similarity(&db, 1, &SilentProgress).unwrap();
+        assert!(result.tracks_processed >= 2);
+        assert!(db.similarity_changed_ids().unwrap().is_empty());
+        let stored = db.stored_neighbors().unwrap();
+        assert_eq!(stored[&added].len(), 6);
+        assert!(stored.values().all(|l| l.len() == 6));
+
+        // Nothing changed: nothing to do
+        assert_eq!(
+            refresh_changed_similarity(&db, 1, &SilentProgress)
+                .unwrap()
+                .tracks_processed,
+            0
+        );
+    }
+
+    #[test]
+    fn test_outdated_vector_format_rebuilds() {
+        let db = Database::open_in_memory().unwrap();
+        for n in 0..4 {
+            analyzed_track(&db, n, n as f64 * 10.0);
+        }
+        assert_eq!(db.similarity_format().unwrap(), None);
+        compute_similarity(&db, 1, &SilentProgress).unwrap();
+        assert_eq!(db.similarity_format().unwrap(), Some(VectorFormat::CURRENT));
+
+        // Neighbors from an older layout are rebuilt even with nothing re-analyzed
+        db.conn
+            .execute("UPDATE similarity_format SET version = 0, dim = 40", [])
+            .unwrap();
+        let result = refresh_changed_similarity(&db, 1, &SilentProgress).unwrap();
+        assert_eq!(result.tracks_processed, 4);
+        assert!(db.similarity_format().unwrap().unwrap().is_current());
+    }
 }
@@ -0,0 +1,149 @@
+//! Library growth history.
+//!
+//! Every completed scan and analyze run records a snapshot of the library's size
+//! (tracks, shows, hours, bytes) in `library_snapshots`. `stats --history` lists
+//! them, and a jump in tracks without a matching jump in shows — the signature of
+//! the same tapes imported twice under a different path — is flagged.
+
+use crate::db::Database;
+
+/// Library size at the end of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibrarySnapshot {
+    pub taken_at: String,
+    /// "scan" or "analyze".
+    pub trigger: String,
+    /// Tracks the run added (scan) or analyzed (analyze).
+    pub run_count: i64,
+    pub tracks: i64,
+    pub analyzed: i64,
+    /// Distinct band + date pairs.
+    pub shows: i64,
+    pub bands: i64,
+    pub hours: f64,
+    pub bytes: i64,
+}
+
+/// Smallest track jump worth checking for duplicates.
+const MIN_SUSPICIOUS_ADD: i64 = 50;
+
+/// Tracks-per-new-show, relative to the library's usual ratio, that looks like a
+/// re-import rather than new shows.
+const SUSPICIOUS_RATIO: f64 = 2.5;
+
+/// Explain why the step from `prev` to `cur` looks like a duplicate import, if it does.
+pub fn duplicate_warning(prev: &LibrarySnapshot, cur: &LibrarySnapshot) -> Option<String> {
+    let added = cur.tracks - prev.tracks;
+    if added < MIN_SUSPICIOUS_ADD || prev.shows == 0 {
+        return None;
+    }
+    let new_shows = cur.shows - prev.shows;
+    if new_shows <= 0 {
+        return Some(format!(
+            "{added} tracks added but no new shows — duplicate import?"
+        ));
+    }
+    let usual = prev.tracks as f64 / prev.shows as f64;
+    let per_show = added as f64 / new_shows as f64;
+    (per_show > usual * SUSPICIOUS_RATIO).then(|| {
+        format!(
+            "{added} tracks for {new_shows} new shows ({per_show:.0}/show vs {usual:.0} usual) — duplicate import?"
+        )
+    })
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Record the library's current size after a scan or analyze run.
+    pub fn record_snapshot(&self, trigger: &str, run_count: i64) -> crate::db::Result<()> {
+        self.conn.execute(
+            "INSERT INTO library_snapshots
+                (trigger, run_count, tracks, analyzed, shows, bands, hours, bytes)
+             SELECT ?1, ?2,
+                    COUNT(*),
+                    COUNT(a.track_id),
+                    COUNT(DISTINCT CASE WHEN t.parsed_date IS NOT NULL
+                          THEN COALESCE(t.parsed_band, t.artist, '') || '|' || t.parsed_date END),
+                    COUNT(DISTINCT COALESCE(t.parsed_band, t.artist)),
+                    COALESCE(SUM(COALESCE(a.duration, t.duration_secs)), 0.0) / 3600.0,
+                    COALESCE(SUM(t.file_size), 0)
+             FROM tracks t
+             LEFT JOIN analysis_results a ON a.track_id = t.id",
+            rusqlite::params![trigger, run_count],
+        )?;
+        Ok(())
+    }
+
+    /// The most recent `limit` snapshots, oldest first.
+    pub fn library_snapshots(&self, limit: usize) -> crate::db::Result<Vec<LibrarySnapshot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT taken_at, trigger, run_count, tracks, analyzed, shows, bands, hours, bytes
+             FROM (SELECT * FROM library_snapshots ORDER BY id DESC LIMIT ?1)
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map([limit as i64], |row| {
+                Ok(LibrarySnapshot {
+                    taken_at: row.get(0)?,
+                    trigger: row.get(1)?,
+                    run_count: row.get(2)?,
+                    tracks: row.get(3)?,
+                    analyzed: row.get(4)?,
+                    shows: row.get(5)?,
+                    bands: row.get(6)?,
+                    hours: row.get(7)?,
+                    bytes: row.get(8)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(tracks: i64, shows: i64) -> LibrarySnapshot {
+        LibrarySnapshot {
+            taken_at: "2026-01-01 00:00:00".into(),
+            trigger: "scan".into(),
+            run_count: 0,
+            tracks,
+            analyzed: 0,
+            shows,
+            bands: 1,
+            hours: 0.0,
+            bytes: 0,
+        }
+    }
+
+    #[test]
+    fn test_duplicate_warning() {
+        let base = snap(2000, 100); // 20 tracks per show
+        assert_eq!(duplicate_warning(&base, &snap(2200, 110)), None);
+        assert_eq!(duplicate_warning(&base, &snap(2030, 100)), None);
+        assert!(
+            duplicate_warning(&base, &snap(2400, 100))
+                .unwrap()
+                .contains("no new shows")
+        );
+        assert!(
+            duplicate_warning(&base, &snap(2600, 105))
+                .unwrap()
+                .contains("120/show vs 20 usual")
+        );
+    }
+
+    #[test]
+    fn test_record_and_list_snapshots() {
+        let db = Database::open_in_memory().unwrap();
+        db.record_snapshot("scan", 0).unwrap();
+        db.record_snapshot("analyze", 0).unwrap();
+        let snaps = db.library_snapshots(1).unwrap();
+        assert_eq!(snaps.len(), 1);
+        assert_eq!(snaps[0].trigger, "analyze");
+        assert_eq!(snaps[0].tracks, 0);
+    }
+}
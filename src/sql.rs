@@ -0,0 +1,276 @@
+//! `sql`: one-off SQL against the library, read-only unless asked otherwise.
+//!
+//! A statement SQLite doesn't report as read-only is refused before it runs,
+//! and the connection is put in `query_only` mode while it does, so a stray
+//! `DELETE` can't change anything. `ATTACH` and `DETACH` pass both checks (and
+//! `ATTACH` creates a missing database file), so they're refused by name.
+//! `--write` lifts all three.
+//!
+//! The `v_*` views are the stable layer for these queries. They spell out the
+//! join conventions the commands use (tag vs. filename metadata, tempo
+//! correction, band names) under plain column names, and unlike the tables
+//! behind them their columns are never renamed or dropped: later versions only
+//! append columns, so saved queries keep working across upgrades.
+
+use rusqlite::types::ValueRef;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::db::Database;
+
+/// One view of the stable layer.
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// The stable views, created by migration v59.
+pub const VIEWS: [View; 4] = [
+    View {
+        name: "v_tracks",
+        description: "Every scanned track: band, date, title, set, length, source and quality",
+    },
+    View {
+        name: "v_scores",
+        description: "Analyzed tracks with the ten scores, tempo and key",
+    },
+    View {
+        name: "v_features",
+        description: "Analyzed tracks with every stored feature (grows with the schema)",
+    },
+    View {
+        name: "v_shows",
+        description: "Show metrics as of the last `shows` run, with guests",
+    },
+];
+
+#[derive(Error, Debug)]
+pub enum SqlError {
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+    #[error("Not a read-only query; use --write to modify the database")]
+    NotReadOnly,
+}
+
+impl From<rusqlite::Error> for SqlError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Db(e.into())
+    }
+}
+
+/// Rows returned by a query, with their column names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryOutput {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl QueryOutput {
+    /// Rows as an array of objects keyed by column name.
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.rows
+                .iter()
+                .map(|row| {
+                    Value::Object(
+                        self.columns
+                            .iter()
+                            .cloned()
+                            .zip(row.iter().cloned())
+                            .collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A value as a table cell: NULL spelled out, whole reals with one decimal.
+pub fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::String(s) => s.clone(),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if !n.is_i64() && f.fract() == 0.0 && f.abs() < 1e15 => format!("{f:.1}"),
+            Some(f) if !n.is_i64() => format!("{f:.4}"),
+            _ => n.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+/// Run one statement and collect its rows. Without `write`, the statement
+/// must be read-only and not an `ATTACH` or `DETACH`.
+pub fn run(db: &Database, query: &str, write: bool) -> Result<QueryOutput, SqlError> {
+    let mut stmt = db.conn.prepare(query)?;
+    if !write && (!stmt.readonly() || attaches(query)) {
+        return Err(SqlError::NotReadOnly);
+    }
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+    if !write {
+        db.conn.pragma_update(None, "query_only", true)?;
+    }
+    let rows = collect_rows(&mut stmt, columns.len());
+    if !write {
+        db.conn.pragma_update(None, "query_only", false)?;
+    }
+    Ok(QueryOutput {
+        columns,
+        rows: rows?,
+    })
+}
+
+/// Whether the statement is an `ATTACH` or `DETACH`, which SQLite reports
+/// as read-only. Leading whitespace and comments are skipped.
+fn attaches(query: &str) -> bool {
+    let mut rest = query;
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, after)| after);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, after)| after);
+        } else {
+            break;
+        }
+    }
+    let keyword: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    keyword.eq_ignore_ascii_case("attach") || keyword.eq_ignore_ascii_case("detach")
+}
+
+fn collect_rows(
+    stmt: &mut rusqlite::Statement,
+    column_count: usize,
+) -> rusqlite::Result<Vec<Vec<Value>>> {
+    let mut rows = stmt.query([])?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next()? {
+        let values = (0..column_count)
+            .map(|i| {
+                Ok(match row.get_ref(i)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(n) => n.into(),
+                    ValueRef::Real(f) => {
+                        serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number)
+                    }
+                    ValueRef::Text(s) => String::from_utf8_lossy(s).into_owned().into(),
+                    ValueRef::Blob(b) => format!("<blob {}B>", b.len()).into(),
+                })
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        out.push(values);
+    }
+    Ok(out)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Column names of a table or view, in order.
+    pub fn view_columns(&self, name: &str) -> crate::db::Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?;
+        let columns = stmt
+            .query_map([name], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_queries_and_views() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (file_path, file_size, file_modified, format, parsed_date,
+                                     parsed_band, parsed_title, duration_secs)
+                 VALUES ('/m/gd77-05-08/t01.flac', 1, '0', 'flac', '1977-05-08',
+                         'Grateful Dead', 'Scarlet Begonias', 600.0);
+                 INSERT INTO analysis_results (track_id, groove_score, tempo_bpm)
+                 VALUES (1, 72.5, 120.0);",
+            )
+            .unwrap();
+
+        let out = run(
+            &db,
+            "SELECT band, title, minutes, groove, tempo_bpm, key FROM v_scores",
+            false,
+        )
+        .unwrap();
+        assert_eq!(out.columns[0], "band");
+        assert_eq!(
+            out.rows,
+            vec![vec![
+                "Grateful Dead".into(),
+                "Scarlet Begonias".into(),
+                10.0.into(),
+                72.5.into(),
+                120.0.into(),
+                Value::Null,
+            ]]
+        );
+        assert_eq!(out.to_json()[0]["groove"], 72.5);
+        assert_eq!(cell_text(&out.rows[0][4]), "120.0");
+        assert_eq!(cell_text(&out.rows[0][5]), "NULL");
+
+        assert!(matches!(
+            run(&db, "DELETE FROM tracks", false),
+            Err(SqlError::NotReadOnly)
+        ));
+        // Read-only mode is lifted again afterwards
+        run(&db, "UPDATE tracks SET title = 'x'", true).unwrap();
+
+        for view in VIEWS {
+            assert!(
+                !db.view_columns(view.name).unwrap().is_empty(),
+                "{}",
+                view.name
+            );
+        }
+        assert_eq!(
+            run(&db, "SELECT * FROM v_features", false)
+                .unwrap()
+                .rows
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_attach_needs_write() {
+        let db = Database::open_in_memory().unwrap();
+        let dir = std::env::temp_dir().join(format!("setbreak-sql-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("other.db");
+        let attach = format!("ATTACH '{}' AS other", path.display());
+
+        for query in [
+            attach.as_str(),
+            "  -- comment\n /* block */ attach ':memory:' AS m",
+            "DETACH other",
+        ] {
+            assert!(
+                matches!(run(&db, query, false), Err(SqlError::NotReadOnly)),
+                "{query}"
+            );
+        }
+        assert!(!path.exists());
+
+        run(&db, &attach, true).unwrap();
+        assert!(path.exists());
+        run(&db, "DETACH other", true).unwrap();
+        assert!(!attaches("SELECT 'attach' AS attached"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+//! New standouts: freshly analyzed tracks in the library's top decile.
+//!
+//! `analyze` notes the database clock before it starts and afterwards ranks
+//! what it analyzed against the rest of the library, as `top` sees it (live,
+//! no tape cuts, no garbage). A track is a standout if any ranked score —
+//! every score but the bipolar valence and arousal axes — reaches that score's
+//! 90th percentile. The check is skipped while the library is too small for a
+//! decile to mean much, or when the run analyzed most of it (a first run or
+//! `--force`), where `top` is the better view.
+//!
+//! An optional `[notify]` command is run with the standouts, so new
+//! acquisitions worth hearing can reach a desktop notification or a chat hook.
+
+use crate::db::Database;
+use crate::db::columns::{TRACK_SCORE_SELECT, map_track_score, top_filter_sql};
+use crate::db::models::{TopFilter, TrackScore};
+use crate::scores::{Direction, SCORES, ScoreMeta};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Fewest ranked tracks (new ones included) before standouts are looked for.
+const MIN_LIBRARY: usize = 100;
+
+/// Share of the library a score's standouts come from.
+const TOP_SHARE: f64 = 0.10;
+
+/// A newly analyzed track and the scores it stands out on.
+#[derive(Debug, Clone)]
+pub struct Standout {
+    pub track: TrackScore,
+    /// Scores at or above the library's top-decile line, best margin first.
+    pub scores: Vec<&'static ScoreMeta>,
+}
+
+/// The database clock, for `find`'s `since`. Read it before analysis starts.
+pub fn clock(db: &Database) -> crate::db::Result<String> {
+    Ok(db
+        .conn
+        .query_row("SELECT datetime('now')", [], |row| row.get(0))?)
+}
+
+/// Tracks analyzed at or after `since` that reach the top decile on any
+/// ranked score, most standout scores first. `None` when the library is too
+/// small or mostly new, so a decile isn't a useful bar.
+pub fn find(db: &Database, since: &str) -> crate::db::Result<Option<Vec<Standout>>> {
+    let filter = TopFilter {
+        live_only: true,
+        include_untitled: true,
+        ..Default::default()
+    };
+    let (library, fresh) = db.ranked_counts(&filter, since)?;
+    if library < MIN_LIBRARY || fresh * 2 > library {
+        return Ok(None);
+    }
+
+    let mut lines = Vec::new();
+    for score in SCORES.iter().filter(|s| s.direction != Direction::Bipolar) {
+        if let Some(line) = db.top_decile_line(&filter, score.column)? {
+            lines.push((score, line));
+        }
+    }
+
+    let mut standouts: Vec<Standout> = db
+        .analyzed_since(&filter, since)?
+        .into_iter()
+        .filter_map(|track| {
+            let mut hits: Vec<_> = lines
+                .iter()
+                .filter(|(s, line)| track.score(s.column).is_some_and(|v| v >= *line))
+                .collect();
+            if hits.is_empty() {
+                return None;
+            }
+            let margin = |s: &ScoreMeta, line: f64| track.score(s.column).unwrap_or(0.0) - line;
+            hits.sort_by(|(a, la), (b, lb)| margin(b, *lb).total_cmp(&margin(a, *la)));
+            let scores = hits.into_iter().map(|(s, _)| *s).collect();
+            Some(Standout { track, scores })
+        })
+        .collect();
+    standouts.sort_by_key(|s| std::cmp::Reverse(s.scores.len()));
+    Ok(Some(standouts))
+}
+
+/// Run the notify command. `{count}` in its arguments becomes the number of
+/// standouts and `{summary}` a one-line description; the standouts are
+/// written to stdin as a JSON array.
+pub fn notify(command: &[String], standouts: &[Standout]) -> std::io::Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        return Ok(());
+    };
+    let count = standouts.len().to_string();
+    let summary = summary(standouts);
+    let mut child = Command::new(program)
+        .args(
+            args.iter()
+                .map(|a| a.replace("{count}", &count).replace("{summary}", &summary)),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    let payload = serde_json::Value::Array(standouts.iter().map(to_json).collect());
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores stdin may exit before reading it
+        let _ = writeln!(stdin, "{payload}");
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "{program} exited with {status}"
+        )))
+    }
+}
+
+/// "3 new standouts: Dark Star 1972-08-27 (Transcendence), ..."
+fn summary(standouts: &[Standout]) -> String {
+    let named: Vec<String> = standouts
+        .iter()
+        .take(3)
+        .map(|s| format!("{} {} ({})", s.track.title, s.track.date, s.scores[0].label))
+        .collect();
+    let more = standouts.len().saturating_sub(named.len());
+    let noun = if standouts.len() == 1 {
+        "standout"
+    } else {
+        "standouts"
+    };
+    let mut text = format!("{} new {noun}: {}", standouts.len(), named.join(", "));
+    if more > 0 {
+        text += &format!(" and {more} more");
+    }
+    text
+}
+
+fn to_json(s: &Standout) -> serde_json::Value {
+    let scores: serde_json::Map<String, serde_json::Value> = s
+        .scores
+        .iter()
+        .map(|m| (m.name.to_string(), s.track.score(m.column).into()))
+        .collect();
+    serde_json::json!({
+        "title": s.track.title,
+        "date": s.track.date,
+        "band": s.track.band,
+        "file_path": s.track.file_path,
+        "scores": scores,
+    })
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Ranked tracks in the library, and how many of them were analyzed since `since`.
+    fn ranked_counts(&self, filter: &TopFilter, since: &str) -> crate::db::Result<(usize, usize)> {
+        let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(since.to_string())];
+        let conditions = top_filter_sql(filter, &mut params);
+        let sql = format!(
+            "SELECT COUNT(*), COALESCE(SUM(a.analyzed_at >= ?1), 0)
+             FROM analysis_results a JOIN tracks t ON t.id = a.track_id
+             WHERE {conditions}"
+        );
+        let (library, fresh): (i64, i64) = self.conn.query_row(
+            &sql,
+            rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((library as usize, fresh as usize))
+    }
+
+    /// The lowest value of `column` still in the top decile of ranked tracks.
+    /// Ties can't push the decile past its share: if too many tracks share the
+    /// value at the cut, only higher values count.
+    fn top_decile_line(&self, filter: &TopFilter, column: &str) -> crate::db::Result<Option<f64>> {
+        let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+        let conditions = top_filter_sql(filter, &mut params);
+        let from = format!(
+            "FROM analysis_results a JOIN tracks t ON t.id = a.track_id
+             WHERE {conditions} AND a.{column} IS NOT NULL"
+        );
+        let params = || rusqlite::params_from_iter(params.iter().map(|p| p.as_ref()));
+        let count: i64 =
+            self.conn
+                .query_row(&format!("SELECT COUNT(*) {from}"), params(), |row| {
+                    row.get(0)
+                })?;
+        if count == 0 {
+            return Ok(None);
+        }
+        let share = ((count as f64 * TOP_SHARE).ceil() as i64).max(1);
+        let line: f64 = self.conn.query_row(
+            &format!(
+                "SELECT a.{column} {from} ORDER BY a.{column} DESC LIMIT 1 OFFSET {}",
+                share - 1
+            ),
+            params(),
+            |row| row.get(0),
+        )?;
+        let at_or_above: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) {from} AND a.{column} >= {line}"),
+            params(),
+            |row| row.get(0),
+        )?;
+        if at_or_above <= share {
+            return Ok(Some(line));
+        }
+        Ok(self.conn.query_row(
+            &format!("SELECT MIN(a.{column}) {from} AND a.{column} > {line}"),
+            params(),
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Ranked tracks analyzed at or after `since`.
+    fn analyzed_since(
+        &self,
+        filter: &TopFilter,
+        since: &str,
+    ) -> crate::db::Result<Vec<TrackScore>> {
+        let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(since.to_string())];
+        let conditions = top_filter_sql(filter, &mut params);
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}
+             FROM analysis_results a JOIN tracks t ON t.id = a.track_id
+             WHERE {conditions} AND a.analyzed_at >= ?1
+             ORDER BY t.parsed_date, t.file_path"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let tracks = stmt
+            .query_map(
+                rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())),
+                map_track_score,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_standouts() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200)
+                 INSERT INTO tracks (file_path, file_size, file_modified, format,
+                                     parsed_title, parsed_date, recording_type)
+                 SELECT '/music/' || i || '.flac', i, '0', 'flac',
+                        'Song ' || i, '1977-05-08', 'live'
+                 FROM n;
+                 INSERT INTO analysis_results (track_id, energy_score, groove_score,
+                                               transcendence_score, valence_score, analyzed_at)
+                 SELECT id, 50, id % 100, 50, 99, '2020-01-01 00:00:00' FROM tracks;",
+            )
+            .unwrap();
+
+        // Only tracks analyzed since the cutoff count; valence (bipolar) never does
+        let since = "2024-01-01 00:00:00";
+        db.conn
+            .execute_batch(
+                "UPDATE analysis_results SET analyzed_at = '2024-06-01 00:00:00'
+                 WHERE track_id IN (5, 99, 199);
+                 UPDATE analysis_results SET transcendence_score = 80 WHERE track_id = 5;",
+            )
+            .unwrap();
+        let standouts = find(&db, since).unwrap().unwrap();
+        let titles: Vec<&str> = standouts.iter().map(|s| s.track.title.as_str()).collect();
+        assert_eq!(titles, vec!["Song 199", "Song 5", "Song 99"]);
+        assert_eq!(standouts[0].scores[0].name, "groove");
+        // Everyone ties at 50 energy, so nobody stands out on it
+        assert_eq!(standouts[1].scores.len(), 1);
+        assert_eq!(standouts[1].scores[0].name, "transcendence");
+        assert!(summary(&standouts).starts_with("3 new standouts: Song 199 1977-05-08 (Groove)"));
+
+        // A run that analyzed most of the library is left to `top`
+        db.conn
+            .execute("UPDATE analysis_results SET analyzed_at = '2024-06-01'", [])
+            .unwrap();
+        assert!(find(&db, since).unwrap().is_none());
+    }
+}
@@ -0,0 +1,286 @@
+//! Plain-text table rendering shared by the list commands.
+//!
+//! A table is a set of columns plus string cells. Widths come from the content;
+//! when the result would overflow the terminal, the flexible column (usually the
+//! title) shrinks and truncates with `...`. `--wide` turns the limit off.
+//! Score columns are chosen by short name from the `scores` registry.
+
+use crate::scores::{self, ScoreMeta};
+
+/// Fallback width when stdout isn't a terminal.
+pub const DEFAULT_WIDTH: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub header: String,
+    pub align: Align,
+    /// Narrowest a flexible column may shrink to; `None` for fixed columns.
+    pub flex_min: Option<usize>,
+    /// Extra blank columns before this one (to set score blocks apart).
+    pub gap: usize,
+}
+
+impl Column {
+    pub fn left(header: impl Into<String>) -> Self {
+        Self {
+            header: header.into(),
+            align: Align::Left,
+            flex_min: None,
+            gap: 0,
+        }
+    }
+
+    pub fn right(header: impl Into<String>) -> Self {
+        Self {
+            align: Align::Right,
+            ..Self::left(header)
+        }
+    }
+
+    /// Let this column shrink (truncating cells) down to `min` to fit the width.
+    pub fn flex(mut self, min: usize) -> Self {
+        self.flex_min = Some(min);
+        self
+    }
+
+    /// Add blank columns before this one.
+    pub fn gap(mut self, n: usize) -> Self {
+        self.gap = n;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row; missing trailing cells render blank.
+    pub fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// Render header, rule, and rows, fitting within `max_width` if given.
+    pub fn render(&self, max_width: Option<usize>) -> String {
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, col)| {
+                self.rows
+                    .iter()
+                    .filter_map(|r| r.get(i))
+                    .map(|c| c.chars().count())
+                    .chain(std::iter::once(col.header.chars().count()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        if let Some(max) = max_width {
+            let mut total = self.total_width(&widths);
+            for (i, col) in self.columns.iter().enumerate() {
+                if total <= max {
+                    break;
+                }
+                if let Some(min) = col.flex_min {
+                    let shrink = (total - max).min(widths[i].saturating_sub(min));
+                    widths[i] -= shrink;
+                    total -= shrink;
+                }
+            }
+        }
+
+        let mut out = String::new();
+        let headers: Vec<&str> = self.columns.iter().map(|c| c.header.as_str()).collect();
+        out.push_str(&self.line(&headers, &widths));
+        out.push_str(&"-".repeat(self.total_width(&widths)));
+        out.push('\n');
+        for row in &self.rows {
+            let cells: Vec<&str> = (0..self.columns.len())
+                .map(|i| row.get(i).map(String::as_str).unwrap_or(""))
+                .collect();
+            out.push_str(&self.line(&cells, &widths));
+        }
+        out
+    }
+
+    fn total_width(&self, widths: &[usize]) -> usize {
+        let gaps: usize = self.columns.iter().map(|c| c.gap).sum();
+        widths.iter().sum::<usize>() + widths.len().saturating_sub(1) + gaps
+    }
+
+    fn line(&self, cells: &[&str], widths: &[usize]) -> String {
+        let mut line = String::new();
+        for (i, (col, cell)) in self.columns.iter().zip(cells).enumerate() {
+            if i > 0 {
+                line.push(' ');
+            }
+            line.push_str(&" ".repeat(col.gap));
+            let cell = truncate(cell, widths[i]);
+            match col.align {
+                Align::Left => line.push_str(&format!("{:<w$}", cell, w = widths[i])),
+                Align::Right => line.push_str(&format!("{:>w$}", cell, w = widths[i])),
+            }
+        }
+        let mut line = line.trim_end().to_string();
+        line.push('\n');
+        line
+    }
+}
+
+/// Cut `s` to `width` characters, ending in `...` when shortened.
+pub fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.to_string();
+    }
+    if width <= 3 {
+        return s.chars().take(width).collect();
+    }
+    let mut out: String = s.chars().take(width - 3).collect();
+    out.push_str("...");
+    out
+}
+
+/// Width of the terminal on stdout, if it is one.
+pub fn terminal_width() -> Option<usize> {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, cols)| cols as usize)
+}
+
+/// Table options shared across commands (`--columns`, `--wide`).
+#[derive(Debug, Clone, Default)]
+pub struct TableOptions {
+    /// Score columns to show; `None` keeps each table's default set.
+    pub scores: Option<Vec<&'static ScoreMeta>>,
+    /// Width limit; `None` never truncates.
+    pub max_width: Option<usize>,
+    /// Number rows from this rank in a leading `#` column (`top` paging);
+    /// `None` leaves them unnumbered.
+    pub first_rank: Option<usize>,
+}
+
+impl TableOptions {
+    /// Build from CLI values: score short names (or any name `scores::get` accepts)
+    /// and whether to disable the width limit.
+    pub fn new(columns: &[String], wide: bool) -> Result<Self, String> {
+        let scores = if columns.is_empty() {
+            None
+        } else {
+            Some(parse_score_columns(columns)?)
+        };
+        let max_width = if wide {
+            None
+        } else {
+            Some(terminal_width().unwrap_or(DEFAULT_WIDTH))
+        };
+        Ok(Self {
+            scores,
+            max_width,
+            first_rank: None,
+        })
+    }
+
+    /// The selected score columns, or `defaults` (registry names) if none were chosen.
+    pub fn score_columns(&self, defaults: &[&str]) -> Vec<&'static ScoreMeta> {
+        match &self.scores {
+            Some(s) => s.clone(),
+            None => scores::select(defaults),
+        }
+    }
+
+    /// The `#` column, if rows are numbered.
+    pub fn rank_column(&self) -> Option<Column> {
+        self.first_rank.map(|_| Column::right("#"))
+    }
+
+    /// The `#` cell for the `i`th row shown, if rows are numbered.
+    pub fn rank_cell(&self, i: usize) -> Option<String> {
+        self.first_rank.map(|r| (r + i).to_string())
+    }
+}
+
+/// Resolve `grv,imp,val`-style names against the score registry.
+pub fn parse_score_columns(names: &[String]) -> Result<Vec<&'static ScoreMeta>, String> {
+    names
+        .iter()
+        .flat_map(|n| n.split(','))
+        .filter(|n| !n.trim().is_empty())
+        .map(|n| {
+            scores::get(n).ok_or_else(|| {
+                let known: Vec<String> = scores::SCORES
+                    .iter()
+                    .map(|s| s.short.to_lowercase())
+                    .collect();
+                format!("unknown score column '{n}' (known: {})", known.join(","))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Table {
+        let mut t = Table::new(vec![
+            Column::left("Song").flex(8),
+            Column::right("Date"),
+            Column::right("Grv").gap(1),
+        ]);
+        t.push(vec![
+            "Dark Star -> St. Stephen".into(),
+            "1969-02-27".into(),
+            "82".into(),
+        ]);
+        t.push(vec!["Bertha".into(), "1977-05-08".into(), "7".into()]);
+        t
+    }
+
+    #[test]
+    fn test_render_wide() {
+        assert_eq!(
+            sample().render(None),
+            "Song                           Date  Grv\n\
+             ----------------------------------------\n\
+             Dark Star -> St. Stephen 1969-02-27   82\n\
+             Bertha                   1977-05-08    7\n"
+        );
+    }
+
+    #[test]
+    fn test_render_shrinks_flex_column() {
+        let out = sample().render(Some(30));
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[2], "Dark Star -... 1969-02-27   82");
+        assert!(lines.iter().all(|l| l.chars().count() <= 30));
+        // Never below the flex minimum
+        let out = sample().render(Some(10));
+        assert!(out.lines().nth(2).unwrap().starts_with("Dark ... "));
+    }
+
+    #[test]
+    fn test_parse_score_columns() {
+        let cols = parse_score_columns(&["grv,imp".into(), "val".into()]).unwrap();
+        let names: Vec<_> = cols.iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["groove", "improvisation", "valence"]);
+        assert!(parse_score_columns(&["vibes".into()]).is_err());
+        assert_eq!(truncate("abcdef", 5), "ab...");
+    }
+}
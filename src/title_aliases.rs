@@ -0,0 +1,343 @@
+//! Song title aliases: merge misspelled and variant titles into one.
+//!
+//! `titles dedupe` clusters near-identical titles — the same letters after
+//! lowercasing and dropping punctuation, or a few edits apart ("Playin' in the
+//! Band" / "Playing In The Band" / "Playin in the Bnad") — and the accepted
+//! merges are stored as alias → canonical mappings. Aliases are applied to
+//! `parsed_title` right away and again after every scan and setlist lookup, so
+//! every query sees the canonical title from then on. Segue markers are kept:
+//! "Playin in the Bnad ->" becomes "Playin' in the Band ->".
+
+use crate::db::Database;
+use crate::db::columns::MISSING_TITLE;
+use crate::scrobbles::normalize_song;
+use crate::setlist::levenshtein;
+use std::collections::HashMap;
+
+/// Shortest normalized title considered for edit-distance merges; shorter ones
+/// ("Jam", "Drums") only merge on an exact normalized match.
+const MIN_FUZZY_LEN: usize = 6;
+
+/// Normalized characters per allowed edit, so short titles need closer matches.
+const CHARS_PER_EDIT: usize = 4;
+
+/// One spelling of a title and how many tracks use it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleVariant {
+    pub title: String,
+    pub tracks: usize,
+}
+
+/// Spellings that look like the same song, the most used first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleCluster {
+    pub variants: Vec<TitleVariant>,
+}
+
+impl TitleCluster {
+    /// The suggested title: the most common spelling.
+    pub fn canonical(&self) -> &str {
+        &self.variants[0].title
+    }
+
+    pub fn tracks(&self) -> usize {
+        self.variants.iter().map(|v| v.tracks).sum()
+    }
+
+    /// Alias mappings merging every other variant into `canonical`.
+    pub fn merges_into(&self, canonical: &str) -> Vec<(String, String)> {
+        self.variants
+            .iter()
+            .filter(|v| v.title != canonical)
+            .map(|v| (v.title.clone(), canonical.to_string()))
+            .collect()
+    }
+}
+
+/// A stored alias.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleAlias {
+    pub alias: String,
+    pub canonical: String,
+}
+
+/// Split a title into the song and its trailing segue marker ("Estimated
+/// Prophet ->" → ("Estimated Prophet", " ->")).
+pub fn split_segue(title: &str) -> (&str, &str) {
+    let trimmed = title.trim_end();
+    for marker in ["-->", "->", ">"] {
+        if let Some(song) = trimmed.strip_suffix(marker) {
+            let song = song.trim_end();
+            return (song, &title[song.len()..]);
+        }
+    }
+    (trimmed, &title[trimmed.len()..])
+}
+
+/// Group title variants that are probably the same song. Two titles cluster if
+/// they normalize to the same letters, or are at most `max_edits` edits apart
+/// (fewer for short titles) without differing in their numbers ("Drums 1" and
+/// "Drums 2" stay apart). Clusters with a single spelling are dropped; the
+/// rest come back with the most tracks first.
+pub fn cluster_titles(variants: Vec<TitleVariant>, max_edits: usize) -> Vec<TitleCluster> {
+    let mut by_key: HashMap<String, Vec<TitleVariant>> = HashMap::new();
+    for v in variants {
+        let key = normalize_song(&v.title);
+        if !key.is_empty() {
+            by_key.entry(key).or_default().push(v);
+        }
+    }
+    let mut keys: Vec<String> = by_key.keys().cloned().collect();
+    keys.sort();
+
+    // Union-find over normalized keys
+    let mut parent: Vec<usize> = (0..keys.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let digits = |s: &str| -> String { s.chars().filter(char::is_ascii_digit).collect() };
+    let lens: Vec<usize> = keys.iter().map(|k| k.chars().count()).collect();
+    for i in 0..keys.len() {
+        for j in i + 1..keys.len() {
+            let shorter = lens[i].min(lens[j]);
+            let allowed = max_edits.min(shorter / CHARS_PER_EDIT);
+            if shorter < MIN_FUZZY_LEN
+                || lens[i].abs_diff(lens[j]) > allowed
+                || digits(&keys[i]) != digits(&keys[j])
+            {
+                continue;
+            }
+            if levenshtein(&keys[i], &keys[j]) <= allowed {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<TitleVariant>> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        let r = root(&mut parent, i);
+        groups
+            .entry(r)
+            .or_default()
+            .extend(by_key.remove(key).unwrap_or_default());
+    }
+    let mut clusters: Vec<TitleCluster> = groups
+        .into_values()
+        .filter(|v| v.len() > 1)
+        .map(|mut variants| {
+            variants.sort_by(|a, b| b.tracks.cmp(&a.tracks).then(a.title.cmp(&b.title)));
+            TitleCluster { variants }
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.tracks()
+            .cmp(&a.tracks())
+            .then_with(|| a.canonical().cmp(b.canonical()))
+    });
+    clusters
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Every title spelling in use (segue markers dropped) with its track
+    /// count. Tracks without a usable title are left out.
+    pub fn title_variants(&self) -> crate::db::Result<Vec<TitleVariant>> {
+        let sql = format!(
+            "SELECT COALESCE(t.parsed_title, t.title)
+             FROM tracks t
+             WHERE NOT ({MISSING_TITLE})"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let title: String = row.get(0)?;
+            let (song, _) = split_segue(&title);
+            if !song.is_empty() {
+                *counts.entry(song.to_string()).or_default() += 1;
+            }
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(title, tracks)| TitleVariant { title, tracks })
+            .collect())
+    }
+
+    /// Stored aliases, grouped by canonical title.
+    pub fn title_aliases(&self) -> crate::db::Result<Vec<TitleAlias>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT alias, canonical FROM title_aliases ORDER BY canonical, alias")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(TitleAlias {
+                    alias: row.get(0)?,
+                    canonical: row.get(1)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Store (alias, canonical) mappings. Aliases that pointed at a title now
+    /// merged away are re-pointed, and a title chosen as canonical stops being
+    /// an alias, so mappings never chain.
+    pub fn add_title_aliases(&self, merges: &[(String, String)]) -> crate::db::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (alias, canonical) in merges {
+            tx.execute(
+                "INSERT OR REPLACE INTO title_aliases (alias, canonical) VALUES (?1, ?2)",
+                rusqlite::params![alias, canonical],
+            )?;
+            tx.execute(
+                "UPDATE title_aliases SET canonical = ?1 WHERE canonical = ?2",
+                rusqlite::params![canonical, alias],
+            )?;
+            tx.execute("DELETE FROM title_aliases WHERE alias = ?1", [canonical])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Rewrite the titles of tracks using an alias to the canonical spelling,
+    /// keeping segue markers. Returns the number of tracks retitled.
+    pub fn apply_title_aliases(&self) -> crate::db::Result<usize> {
+        let aliases: HashMap<String, String> = self
+            .title_aliases()?
+            .into_iter()
+            .map(|a| (a.alias, a.canonical))
+            .collect();
+        if aliases.is_empty() {
+            return Ok(0);
+        }
+        let updates: Vec<(i64, String)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, COALESCE(parsed_title, title) FROM tracks
+                 WHERE COALESCE(parsed_title, title) IS NOT NULL",
+            )?;
+            let mut rows = stmt.query([])?;
+            let mut updates = Vec::new();
+            while let Some(row) = rows.next()? {
+                let title: String = row.get(1)?;
+                let (song, segue) = split_segue(&title);
+                if let Some(canonical) = aliases.get(song) {
+                    updates.push((row.get(0)?, format!("{canonical}{segue}")));
+                }
+            }
+            updates
+        };
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE tracks SET parsed_title = ?1, updated_at = datetime('now') WHERE id = ?2",
+            )?;
+            for (id, title) in &updates {
+                stmt.execute(rusqlite::params![title, id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(updates.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewTrack;
+    use crate::db::queries::tests::new_track;
+
+    fn variant(title: &str, tracks: usize) -> TitleVariant {
+        TitleVariant {
+            title: title.into(),
+            tracks,
+        }
+    }
+
+    #[test]
+    fn test_split_segue() {
+        assert_eq!(
+            split_segue("Estimated Prophet ->"),
+            ("Estimated Prophet", " ->")
+        );
+        assert_eq!(split_segue("China Cat>"), ("China Cat", ">"));
+        assert_eq!(split_segue("Morning Dew "), ("Morning Dew", " "));
+    }
+
+    #[test]
+    fn test_cluster_titles() {
+        let clusters = cluster_titles(
+            vec![
+                variant("Playin' in the Band", 40),
+                variant("Playing In The Band", 3),
+                variant("Playin in the Bnad", 1),
+                variant("Drums 1", 5),
+                variant("Drums 2", 5),
+                variant("Jam", 9),
+                variant("Ja", 1),
+                variant("Sugaree", 12),
+                variant("sugaree", 2),
+                variant("Bertha", 20),
+            ],
+            2,
+        );
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].canonical(), "Playin' in the Band");
+        assert_eq!(clusters[0].tracks(), 44);
+        assert_eq!(
+            clusters[0].merges_into("Playin' in the Band"),
+            vec![
+                (
+                    "Playing In The Band".to_string(),
+                    "Playin' in the Band".to_string()
+                ),
+                (
+                    "Playin in the Bnad".to_string(),
+                    "Playin' in the Band".to_string()
+                ),
+            ]
+        );
+        assert_eq!(clusters[1].canonical(), "Sugaree");
+    }
+
+    fn track(path: &str, title: &str) -> NewTrack {
+        NewTrack {
+            title: Some(title.to_string()),
+            ..new_track(path)
+        }
+    }
+
+    #[test]
+    fn test_aliases_apply_and_survive_rescan() {
+        let db = Database::open_in_memory().unwrap();
+        db.upsert_track(&track("/m/a.flac", "Sugaree")).unwrap();
+        db.upsert_track(&track("/m/b.flac", "Sugarree ->")).unwrap();
+        db.upsert_track(&track("/m/c.flac", "Sugeree")).unwrap();
+
+        db.add_title_aliases(&[("Sugarree".into(), "Sugeree".into())])
+            .unwrap();
+        // Changing our mind re-points the old alias instead of chaining
+        db.add_title_aliases(&[("Sugeree".into(), "Sugaree".into())])
+            .unwrap();
+        let aliases = db.title_aliases().unwrap();
+        assert_eq!(aliases.len(), 2);
+        assert!(aliases.iter().all(|a| a.canonical == "Sugaree"));
+
+        assert_eq!(db.apply_title_aliases().unwrap(), 2);
+        let mut variants = db.title_variants().unwrap();
+        variants.sort_by(|a, b| a.title.cmp(&b.title));
+        assert_eq!(variants, vec![variant("Sugaree", 3)]);
+        let rows = db.title_rows(false).unwrap();
+        assert_eq!(rows[1].title, "Sugaree ->");
+
+        // A rescan brings back the tag spelling until aliases are re-applied
+        db.upsert_track(&track("/m/c.flac", "Sugeree")).unwrap();
+        assert_eq!(db.apply_title_aliases().unwrap(), 1);
+        assert_eq!(db.apply_title_aliases().unwrap(), 0);
+    }
+}
@@ -0,0 +1,268 @@
+//! Song title text cleanup: one spelling of the same characters.
+//!
+//! Titles arrive from tags, directory names, beets, and archive.org
+//! descriptions, and the same song can come through as "Althea", "Althea "
+//! with a no-break space, "Uncle John&#39;s Band", or "Uncle John’s Band" —
+//! different strings that group as different songs. `normalize` decodes HTML
+//! entities, folds curly quotes to straight ones, composes accented Latin
+//! letters written as letter + combining mark (the part of NFC that shows up in
+//! titles; other scripts pass through unchanged), drops zero-width characters,
+//! and collapses whitespace. Every title write goes through it; `titles
+//! normalize` backfills rows stored before.
+
+use crate::db::Database;
+
+/// Composed Latin letters by combining mark: `base[i]` + mark → `composed[i]`.
+const COMPOSITIONS: &[(char, &str, &str)] = &[
+    ('\u{300}', "AEIOUaeiou", "ÀÈÌÒÙàèìòù"),
+    (
+        '\u{301}',
+        "AEIOUYaeiouyCcLlNnRrSsZz",
+        "ÁÉÍÓÚÝáéíóúýĆćĹĺŃńŔŕŚśŹź",
+    ),
+    (
+        '\u{302}',
+        "AEIOUaeiouCcGgHhJjSsWwYy",
+        "ÂÊÎÔÛâêîôûĈĉĜĝĤĥĴĵŜŝŴŵŶŷ",
+    ),
+    ('\u{303}', "ANOanoIiUu", "ÃÑÕãñõĨĩŨũ"),
+    ('\u{304}', "AaEeIiOoUu", "ĀāĒēĪīŌōŪū"),
+    ('\u{306}', "AaEeGgIiOoUu", "ĂăĔĕĞğĬĭŎŏŬŭ"),
+    ('\u{307}', "CcEeGgIZz", "ĊċĖėĠġİŻż"),
+    ('\u{308}', "AEIOUaeiouyY", "ÄËÏÖÜäëïöüÿŸ"),
+    ('\u{30a}', "AaUu", "ÅåŮů"),
+    ('\u{30b}', "OoUu", "ŐőŰű"),
+    ('\u{30c}', "CcDdEeLlNnRrSsTtZz", "ČčĎďĚěĽľŇňŘřŠšŤťŽž"),
+    ('\u{327}', "CcGgKkLlNnRrSsTt", "ÇçĢģĶķĻļŅņŖŗŞşŢţ"),
+    ('\u{328}', "AaEeIiUu", "ĄąĘęĮįŲų"),
+];
+
+/// Named entities seen in archive.org descriptions.
+const ENTITIES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", ' '),
+    ("lsquo", '\''),
+    ("rsquo", '\''),
+    ("ldquo", '"'),
+    ("rdquo", '"'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("hellip", '…'),
+    ("aacute", 'á'),
+    ("eacute", 'é'),
+    ("iacute", 'í'),
+    ("oacute", 'ó'),
+    ("uacute", 'ú'),
+    ("ntilde", 'ñ'),
+    ("auml", 'ä'),
+    ("ouml", 'ö'),
+    ("uuml", 'ü'),
+];
+
+/// Longest entity name looked for after `&`.
+const MAX_ENTITY_LEN: usize = 10;
+
+/// Clean up a title's text. Idempotent: normalizing twice changes nothing.
+pub fn normalize(title: &str) -> String {
+    // Until nothing decodes, for double-encoded text like "&amp;#39;"
+    let mut decoded = decode_entities(title);
+    loop {
+        let again = decode_entities(&decoded);
+        if again == decoded {
+            break;
+        }
+        decoded = again;
+    }
+    let mut out = String::with_capacity(decoded.len());
+    let mut pending_space = false;
+    for c in decoded.chars() {
+        let c = match c {
+            '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' | '\u{2032}' => '\'',
+            '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' | '\u{2033}' => '"',
+            '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{feff}' => continue,
+            c if c.is_whitespace() => {
+                pending_space = !out.is_empty();
+                continue;
+            }
+            c => c,
+        };
+        if pending_space {
+            out.push(' ');
+            pending_space = false;
+        } else if let Some(composed) = out.chars().last().and_then(|base| compose(base, c)) {
+            out.pop();
+            out.push(composed);
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The precomposed form of `base` followed by combining `mark`, if there is one.
+fn compose(base: char, mark: char) -> Option<char> {
+    let (_, bases, composed) = COMPOSITIONS.iter().find(|(m, _, _)| *m == mark)?;
+    let i = bases.chars().position(|b| b == base)?;
+    composed.chars().nth(i)
+}
+
+/// Decode `&name;`, `&#NNN;`, and `&#xHH;`; anything unrecognized is kept as is.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .char_indices()
+            .take(MAX_ENTITY_LEN + 1)
+            .find(|(_, c)| *c == ';')
+            .and_then(|(end, _)| Some((entity(&rest[1..1 + end])?, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(num) = name.strip_prefix('#') {
+        let code = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    ENTITIES.iter().find(|(n, _)| *n == name).map(|(_, c)| *c)
+}
+
+/// Rows rewritten by a backfill.
+#[derive(Debug, Default, PartialEq)]
+pub struct NormalizeSummary {
+    /// Tracks whose parsed title changed.
+    pub tracks: usize,
+    /// Setlist songs that changed.
+    pub setlist_songs: usize,
+    /// Title aliases whose canonical title changed.
+    pub aliases: usize,
+}
+
+/// Normalize titles stored before normalization on write, in one transaction.
+/// With `dry_run`, only counts what would change.
+pub fn backfill(db: &Database, dry_run: bool) -> crate::db::Result<NormalizeSummary> {
+    let tx = db.conn.unchecked_transaction()?;
+    let summary = NormalizeSummary {
+        tracks: db.normalize_column("tracks", "id", "parsed_title", dry_run)?,
+        setlist_songs: db.normalize_column("setlists", "id", "song", dry_run)?,
+        aliases: db.normalize_column("title_aliases", "alias", "canonical", dry_run)?,
+    };
+    tx.commit()?;
+    Ok(summary)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Rewrite each non-null `column` of `table` to its normalized form,
+    /// returning how many rows differ.
+    fn normalize_column(
+        &self,
+        table: &str,
+        key: &str,
+        column: &str,
+        dry_run: bool,
+    ) -> crate::db::Result<usize> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {key}, {column} FROM {table} WHERE {column} IS NOT NULL"
+        ))?;
+        let changed: Vec<(rusqlite::types::Value, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?
+            .filter_map(|row| match row {
+                Ok((id, text)) => {
+                    let clean = normalize(&text);
+                    (clean != text).then_some(Ok((id, clean)))
+                }
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        if !dry_run {
+            let touch = if table == "tracks" {
+                ", updated_at = datetime('now')"
+            } else {
+                ""
+            };
+            let mut update = self.conn.prepare(&format!(
+                "UPDATE {table} SET {column} = ?1{touch} WHERE {key} = ?2"
+            ))?;
+            for (id, clean) in &changed {
+                update.execute(rusqlite::params![clean, id])?;
+            }
+        }
+        Ok(changed.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Uncle John&#39;s Band"), "Uncle John's Band");
+        assert_eq!(normalize("Uncle John’s Band"), "Uncle John's Band");
+        assert_eq!(normalize("Franklin&amp;#39;s Tower"), "Franklin's Tower");
+        assert_eq!(normalize("Scarlet &gt; Fire"), "Scarlet > Fire");
+        assert_eq!(normalize("  Althea\u{a0}\u{200b} \t"), "Althea");
+        assert_eq!(normalize("Help  On\nThe Way"), "Help On The Way");
+        assert_eq!(normalize("Cafe\u{301} Jam"), "Café Jam");
+        assert_eq!(normalize("AT&T &foo; &#x41;"), "AT&T &foo; A");
+        assert_eq!(normalize("“Dark Star”"), "\"Dark Star\"");
+        // Marks with no precomposed form stay as they are
+        assert_eq!(normalize("q\u{301}"), "q\u{301}");
+        for t in ["Althea", "Café", "St. Stephen ->", "Drums &"] {
+            assert_eq!(normalize(&normalize(t)), normalize(t));
+        }
+    }
+
+    #[test]
+    fn test_backfill() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (file_path, file_size, file_modified, format, parsed_title)
+                 VALUES ('/a.flac', 1, '0', 'flac', 'Althea'),
+                        ('/b.flac', 1, '0', 'flac', 'Althea '),
+                        ('/c.flac', 1, '0', 'flac', NULL);
+                 INSERT INTO setlists (date, set_num, position, song, source)
+                 VALUES ('1977-05-08', 1, 1, 'Uncle John&#39;s Band', 'archive');",
+            )
+            .unwrap();
+
+        let dry = backfill(&db, true).unwrap();
+        assert_eq!(dry.tracks, 1);
+        assert_eq!(dry.setlist_songs, 1);
+        assert_eq!(backfill(&db, false).unwrap(), dry);
+        assert_eq!(backfill(&db, false).unwrap(), NormalizeSummary::default());
+        let althea: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM tracks WHERE parsed_title = 'Althea'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(althea, 2);
+    }
+}
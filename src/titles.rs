@@ -0,0 +1,353 @@
+//! Manual title corrections through a CSV round-trip.
+//!
+//! `titles export` writes `id,file_path,title` for editing in a spreadsheet;
+//! `titles import` applies the edited titles in one transaction. Rows are keyed
+//! by track id, and the file path is checked against the database so a CSV from
+//! another library (or a shuffled one) fails instead of retitling the wrong
+//! tracks. Imported titles are recorded with match method `manual`, which keeps
+//! them through rescans and takes them out of the setlist review queue.
+
+use crate::db::Database;
+use crate::db::columns::MISSING_TITLE;
+use crate::scrobbles::split_csv_line;
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+/// Header written by `export` and required by `import`.
+const HEADER: [&str; 3] = ["id", "file_path", "title"];
+
+#[derive(Error, Debug)]
+pub enum TitleError {
+    #[error("Failed to read {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Failed to write CSV: {0}")]
+    Write(#[from] std::io::Error),
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error("Line {line}: no track with id {id}")]
+    UnknownTrack { line: usize, id: i64 },
+    #[error("Line {line}: track {id} is {actual}, not {expected}")]
+    PathMismatch {
+        line: usize,
+        id: i64,
+        expected: String,
+        actual: String,
+    },
+    #[error("Database error: {0}")]
+    Db(#[from] crate::db::DbError),
+}
+
+/// One track's title as exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleRow {
+    pub track_id: i64,
+    pub file_path: String,
+    /// Parsed title, else the tag title; empty if neither.
+    pub title: String,
+}
+
+/// One edited row from an import file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleCorrection {
+    /// 1-based line in the CSV, for error messages.
+    pub line: usize,
+    pub track_id: i64,
+    /// Empty when the column was left out of the file.
+    pub file_path: String,
+    pub title: String,
+}
+
+/// Outcome of an import.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub rows: usize,
+    pub updated: usize,
+    /// Title already matched the database.
+    pub unchanged: usize,
+    /// Title left empty; nothing to apply.
+    pub blank: usize,
+}
+
+/// Write rows as CSV with a header.
+pub fn write_csv(rows: &[TitleRow], mut out: impl Write) -> Result<(), TitleError> {
+    writeln!(out, "{}", HEADER.join(","))?;
+    for r in rows {
+        writeln!(
+            out,
+            "{},{},{}",
+            r.track_id,
+            csv_field(&r.file_path),
+            csv_field(&r.title)
+        )?;
+    }
+    Ok(())
+}
+
+/// Read an edited CSV.
+pub fn load(path: &Path) -> Result<Vec<TitleCorrection>, TitleError> {
+    let text = std::fs::read_to_string(path).map_err(|source| TitleError::Read {
+        path: path.display().to_string(),
+        source,
+    })?;
+    parse_csv(&text)
+}
+
+/// Parse an edited CSV. Columns are found by header name, so extra columns and
+/// reordering from a spreadsheet are fine; `id` and `title` are required.
+pub fn parse_csv(text: &str) -> Result<Vec<TitleCorrection>, TitleError> {
+    // Spreadsheets like to save with a byte-order mark
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let header: Vec<String> = split_csv_line(header)
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (Some(id_col), Some(title_col)) = (column("id"), column("title")) else {
+        return Err(TitleError::Parse {
+            line: 1,
+            message: format!(
+                "header must include id and title (expected {})",
+                HEADER.join(",")
+            ),
+        });
+    };
+    let path_col = column("file_path");
+
+    lines
+        .map(|(i, line)| {
+            let line_no = i + 1;
+            let fields = split_csv_line(line);
+            let field = |col: usize| fields.get(col).map_or("", |f| f.trim());
+            let track_id = field(id_col).parse().map_err(|_| TitleError::Parse {
+                line: line_no,
+                message: format!("invalid track id '{}'", field(id_col)),
+            })?;
+            Ok(TitleCorrection {
+                line: line_no,
+                track_id,
+                file_path: path_col.map(field).unwrap_or_default().to_string(),
+                title: field(title_col).to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Apply corrections in one transaction: any unknown id or path mismatch
+/// rolls back the whole file. With `dry_run`, only the summary is computed.
+pub fn import(
+    db: &Database,
+    corrections: &[TitleCorrection],
+    dry_run: bool,
+) -> Result<ImportSummary, TitleError> {
+    let mut summary = ImportSummary {
+        rows: corrections.len(),
+        ..Default::default()
+    };
+    let tx = db
+        .conn
+        .unchecked_transaction()
+        .map_err(crate::db::DbError::from)?;
+    for c in corrections {
+        let Some(current) = db.title_row(c.track_id)? else {
+            return Err(TitleError::UnknownTrack {
+                line: c.line,
+                id: c.track_id,
+            });
+        };
+        if !c.file_path.is_empty() && c.file_path != current.file_path {
+            return Err(TitleError::PathMismatch {
+                line: c.line,
+                id: c.track_id,
+                expected: c.file_path.clone(),
+                actual: current.file_path,
+            });
+        }
+        if c.title.is_empty() {
+            summary.blank += 1;
+        } else if c.title == current.title {
+            summary.unchanged += 1;
+        } else {
+            db.set_manual_title(c.track_id, &c.title)?;
+            summary.updated += 1;
+        }
+    }
+    if !dry_run {
+        tx.commit().map_err(crate::db::DbError::from)?;
+    }
+    Ok(summary)
+}
+
+/// Quote a field if it contains a comma, quote, or line break.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Tracks' current titles in path order; with `missing_only`, just those
+    /// without a usable title.
+    pub fn title_rows(&self, missing_only: bool) -> crate::db::Result<Vec<TitleRow>> {
+        let filter = if missing_only { MISSING_TITLE } else { "1 = 1" };
+        let sql = format!(
+            "SELECT t.id, t.file_path, COALESCE(t.parsed_title, t.title, '')
+             FROM tracks t
+             WHERE {filter}
+             ORDER BY t.file_path"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(TitleRow {
+                    track_id: row.get(0)?,
+                    file_path: row.get(1)?,
+                    title: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    fn title_row(&self, track_id: i64) -> crate::db::Result<Option<TitleRow>> {
+        use rusqlite::OptionalExtension;
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id, file_path, COALESCE(parsed_title, title, '') FROM tracks WHERE id = ?1",
+                [track_id],
+                |row| {
+                    Ok(TitleRow {
+                        track_id: row.get(0)?,
+                        file_path: row.get(1)?,
+                        title: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Set a hand-corrected title. Clears any pending setlist review for the track.
+    fn set_manual_title(&self, track_id: i64, title: &str) -> crate::db::Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET parsed_title = ?1, title_match_method = 'manual',
+                    title_match_confidence = 1.0, updated_at = datetime('now')
+             WHERE id = ?2",
+            rusqlite::params![crate::title_normalize::normalize(title), track_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM title_match_review WHERE track_id = ?1",
+            [track_id],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewTrack;
+    use crate::db::queries::tests::new_track;
+
+    fn track(path: &str, title: Option<&str>) -> NewTrack {
+        NewTrack {
+            title: title.map(str::to_string),
+            ..new_track(path)
+        }
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let rows = vec![TitleRow {
+            track_id: 7,
+            file_path: "/music/gd, 1977/d1t01.flac".into(),
+            title: "He's Gone".into(),
+        }];
+        let mut buf = Vec::new();
+        write_csv(&rows, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            text,
+            "id,file_path,title\n7,\"/music/gd, 1977/d1t01.flac\",He's Gone\n"
+        );
+
+        // Reordered columns, BOM, and an extra column from a spreadsheet
+        let edited = "\u{feff}title,notes,id\n\"Sugaree \"\"jam\"\"\",x,7\n";
+        let parsed = parse_csv(edited).unwrap();
+        assert_eq!(parsed[0].title, "Sugaree \"jam\"");
+        assert_eq!(parsed[0].track_id, 7);
+        assert_eq!(parsed[0].file_path, "");
+        assert!(matches!(
+            parse_csv("id,title\nseven,x\n"),
+            Err(TitleError::Parse { line: 2, .. })
+        ));
+        assert!(parse_csv("path,name\n").is_err());
+    }
+
+    #[test]
+    fn test_import_is_transactional_and_survives_rescan() {
+        let db = Database::open_in_memory().unwrap();
+        let a = db
+            .upsert_track(&track("/m/d1t01.flac", Some("Track 01")))
+            .unwrap();
+        let b = db
+            .upsert_track(&track("/m/d1t02.flac", Some("Bertha")))
+            .unwrap();
+        assert_eq!(db.title_rows(true).unwrap().len(), 1);
+
+        let fix = |id, path: &str, title: &str| TitleCorrection {
+            line: 2,
+            track_id: id,
+            file_path: path.into(),
+            title: title.into(),
+        };
+
+        // A bad row anywhere rolls back the good ones
+        let err = import(
+            &db,
+            &[
+                fix(a, "/m/d1t01.flac", "Jack Straw"),
+                fix(b, "/m/other.flac", "x"),
+            ],
+            false,
+        );
+        assert!(matches!(err, Err(TitleError::PathMismatch { .. })));
+        assert_eq!(db.title_rows(true).unwrap().len(), 1);
+
+        let summary = import(
+            &db,
+            &[
+                fix(a, "/m/d1t01.flac", "Jack Straw"),
+                fix(b, "", "Bertha"),
+                fix(b, "", ""),
+            ],
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            (summary.updated, summary.unchanged, summary.blank),
+            (1, 1, 1)
+        );
+        assert!(db.title_rows(true).unwrap().is_empty());
+
+        // A rescan re-parses the file but keeps the hand-fixed title
+        db.upsert_track(&track("/m/d1t01.flac", Some("Track 01")))
+            .unwrap();
+        let rows = db.title_rows(false).unwrap();
+        assert_eq!(rows[0].title, "Jack Straw");
+    }
+}
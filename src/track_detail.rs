@@ -0,0 +1,172 @@
+//! Everything stored about one track, for drilling into a ranking row.
+//!
+//! Tables show a handful of scores; `top --interactive` prints this for the
+//! row picked, so the file, its source and every analysis feature are a
+//! keypress away instead of a separate `show` or `compare` run.
+
+use crate::db::columns::{ANALYSIS_SCHEMA, ColumnDef};
+use crate::db::{BLOB_COLUMNS, Database};
+use rusqlite::OptionalExtension;
+use rusqlite::types::Value;
+
+/// One track's metadata, recording source and analysis features.
+#[derive(Clone)]
+pub struct TrackDetail {
+    pub track_id: i64,
+    pub file_path: String,
+    pub format: String,
+    pub title: String,
+    pub date: Option<String>,
+    pub band: Option<String>,
+    pub venue: Option<String>,
+    pub set: Option<String>,
+    pub album: Option<String>,
+    pub recording_type: Option<String>,
+    pub data_quality: Option<String>,
+    /// Recording source ("sbd", "aud", "matrix", ...), taper and lineage,
+    /// from the directory name or source notes.
+    pub source_type: Option<String>,
+    pub taper: Option<String>,
+    pub lineage_hint: Option<String>,
+    pub analyzed_at: Option<String>,
+    /// Every non-null analysis column in `ANALYSIS_SCHEMA` order (the JSON
+    /// array columns are left out), formatted for display.
+    pub features: Vec<(&'static ColumnDef, String)>,
+}
+
+/// Display text for a stored value: reals to four decimals with trailing
+/// zeros dropped.
+fn format_value(value: Value) -> Option<String> {
+    match value {
+        Value::Null | Value::Blob(_) => None,
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Real(r) => {
+            let s = format!("{r:.4}");
+            let s = s.trim_end_matches('0').trim_end_matches('.');
+            Some(if s == "-0" {
+                "0".to_string()
+            } else {
+                s.to_string()
+            })
+        }
+        Value::Text(t) => Some(t),
+    }
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Details of the track at `file_path`, or None if there is none.
+    /// Features are empty for a track that hasn't been analyzed.
+    pub fn track_detail(&self, file_path: &str) -> crate::db::Result<Option<TrackDetail>> {
+        let detail = self
+            .conn
+            .query_row(
+                "SELECT t.id, t.file_path, t.format,
+                        COALESCE(t.parsed_title, t.title, '(untitled)'),
+                        COALESCE(t.parsed_date, t.date),
+                        COALESCE(t.parsed_band, t.artist),
+                        COALESCE(t.parsed_venue, t.venue),
+                        COALESCE(t.parsed_set, t.set_name),
+                        t.album, t.recording_type, t.data_quality,
+                        t.source_type, t.taper, t.lineage_hint, a.analyzed_at
+                 FROM tracks t
+                 LEFT JOIN analysis_results a ON a.track_id = t.id
+                 WHERE t.file_path = ?1",
+                [file_path],
+                |row| {
+                    Ok(TrackDetail {
+                        track_id: row.get(0)?,
+                        file_path: row.get(1)?,
+                        format: row.get(2)?,
+                        title: row.get(3)?,
+                        date: row.get(4)?,
+                        band: row.get(5)?,
+                        venue: row.get(6)?,
+                        set: row.get(7)?,
+                        album: row.get(8)?,
+                        recording_type: row.get(9)?,
+                        data_quality: row.get(10)?,
+                        source_type: row.get(11)?,
+                        taper: row.get(12)?,
+                        lineage_hint: row.get(13)?,
+                        analyzed_at: row.get(14)?,
+                        features: Vec::new(),
+                    })
+                },
+            )
+            .optional()?;
+        let Some(mut detail) = detail else {
+            return Ok(None);
+        };
+        if detail.analyzed_at.is_none() {
+            return Ok(Some(detail));
+        }
+
+        let columns: Vec<&'static ColumnDef> = ANALYSIS_SCHEMA
+            .iter()
+            .filter(|c| !BLOB_COLUMNS.contains(&c.name))
+            .collect();
+        let select = columns
+            .iter()
+            .map(|c| c.name)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let values: Vec<Value> = self.conn.query_row(
+            &format!("SELECT {select} FROM analysis_results WHERE track_id = ?1"),
+            [detail.track_id],
+            |row| (0..columns.len()).map(|i| row.get(i)).collect(),
+        )?;
+        detail.features = columns
+            .into_iter()
+            .zip(values)
+            .filter_map(|(c, v)| Some((c, format_value(v)?)))
+            .collect();
+        Ok(Some(detail))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_detail() {
+        let db = Database::open_in_memory().unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, file_path, file_size, file_modified, format, title,
+                                     parsed_date, parsed_band, source_type, taper)
+                 VALUES
+                    (1, '/m/gd77-05-08.sbd/d1t01.flac', 1, '0', 'flac', 'Minglewood',
+                     '1977-05-08', 'Grateful Dead', 'sbd', 'Betty'),
+                    (2, '/m/new.flac', 1, '0', 'flac', NULL, NULL, NULL, NULL, NULL);
+                 INSERT INTO analysis_results (track_id, duration, groove_score, estimated_key)
+                 VALUES (1, 312.5, 71.25, 'E major');",
+            )
+            .unwrap();
+
+        let d = db
+            .track_detail("/m/gd77-05-08.sbd/d1t01.flac")
+            .unwrap()
+            .unwrap();
+        assert_eq!(d.title, "Minglewood");
+        assert_eq!(d.source_type.as_deref(), Some("sbd"));
+        assert_eq!(d.taper.as_deref(), Some("Betty"));
+        let feature = |name: &str| {
+            d.features
+                .iter()
+                .find(|(c, _)| c.name == name)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(feature("duration"), Some("312.5"));
+        assert_eq!(feature("groove_score"), Some("71.25"));
+        assert_eq!(feature("estimated_key"), Some("E major"));
+        assert_eq!(feature("energy_score"), None);
+
+        let unanalyzed = db.track_detail("/m/new.flac").unwrap().unwrap();
+        assert_eq!(unanalyzed.title, "(untitled)");
+        assert!(unanalyzed.features.is_empty());
+        assert!(db.track_detail("/m/missing.flac").unwrap().is_none());
+    }
+}
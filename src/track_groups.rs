@@ -0,0 +1,720 @@
+//! Track groups: jams split across files, scored as one virtual track.
+//!
+//! Tapers often split a long jam at a disc or file boundary — "Dark Star (1)" /
+//! "Dark Star (2)", "Playin' Part 1" / "Playin' Part 2", "Other One" /
+//! "Other One (cont.)". Scored separately, each half looks like a short, unresolved
+//! fragment. Groups are detected from consecutive titles within a recording
+//! directory; the parts' features are merged (durations and counts summed, rates
+//! duration-weighted, segments laid end to end) and the jam scores computed on the
+//! whole. The files themselves are untouched.
+
+use crate::analyzer::jam_metrics;
+use crate::db::Database;
+use crate::db::columns::{
+    LIVE_ONLY, MISSING_TITLE, NO_CUTS, NOT_GARBAGE, SCORE_COLUMNS, SHOW_QUALITY, STUDIO_ALBUM,
+    TRACK_SCORE_SELECT, map_track_score,
+};
+use crate::db::models::{NewAnalysis, TopFilter, TrackGroup, TrackScore};
+use regex::Regex;
+use rusqlite::params;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// An analyzed track considered for grouping, in playing order.
+#[derive(Debug, Clone)]
+pub struct GroupCandidate {
+    pub track_id: i64,
+    pub file_path: String,
+    pub title: String,
+    pub date: String,
+}
+
+/// A detected group: display title and member track IDs in playing order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedGroup {
+    pub title: String,
+    pub track_ids: Vec<i64>,
+}
+
+/// What a title's suffix says about its place in a split jam.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PartMarker {
+    Numbered(u32),
+    Continued,
+}
+
+static PART_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(.+?)\s*(?:[\(\[]\s*(?:part|pt\.?)\s*(\d+|[ivx]+)\s*[\)\]]|[,:-]?\s+(?:part|pt\.?)\s*(\d+|[ivx]+)|[\(\[](\d)[\)\]])$",
+    )
+    .unwrap()
+});
+
+static CONT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(.+?)\s*(?:[\(\[]\s*(?:cont\.?|continued|cont'd)\s*[\)\]]|-?\s+(?:cont\.|continued|cont'd))$",
+    )
+    .unwrap()
+});
+
+/// Split a title into its base song name and part marker, if it has one.
+fn split_part(title: &str) -> (String, Option<PartMarker>) {
+    let title = strip_segue(title);
+    if let Some(caps) = PART_RE.captures(title) {
+        let n = caps
+            .get(2)
+            .or_else(|| caps.get(3))
+            .or_else(|| caps.get(4))
+            .and_then(|m| parse_part_number(m.as_str()));
+        if let Some(n) = n {
+            return (caps[1].trim().to_string(), Some(PartMarker::Numbered(n)));
+        }
+    }
+    if let Some(caps) = CONT_RE.captures(title) {
+        return (caps[1].trim().to_string(), Some(PartMarker::Continued));
+    }
+    (title.trim().to_string(), None)
+}
+
+/// Base song name of a title (segue and part markers stripped), and whether
+/// it carried a part marker.
+pub(crate) fn song_part(title: &str) -> (String, bool) {
+    let (base, marker) = split_part(title);
+    (base, marker.is_some())
+}
+
+fn parse_part_number(s: &str) -> Option<u32> {
+    if let Ok(n) = s.parse() {
+        return Some(n);
+    }
+    let n = match s.to_lowercase().as_str() {
+        "i" => 1,
+        "ii" => 2,
+        "iii" => 3,
+        "iv" => 4,
+        "v" => 5,
+        "vi" => 6,
+        _ => return None,
+    };
+    Some(n)
+}
+
+fn strip_segue(title: &str) -> &str {
+    let t = title.trim_end();
+    for marker in &[" -->", "-->", " ->", "->", " >"] {
+        if let Some(stripped) = t.strip_suffix(marker) {
+            return stripped.trim_end();
+        }
+    }
+    t
+}
+
+/// Find split jams among tracks sorted in playing order.
+///
+/// A group is a run of consecutive tracks in the same directory with the same base
+/// title, starting at part 1 (or an unmarked title) and continuing with the next
+/// part number or a "(cont.)" marker.
+pub fn detect_groups(tracks: &[GroupCandidate]) -> Vec<DetectedGroup> {
+    let parsed: Vec<(String, Option<PartMarker>)> =
+        tracks.iter().map(|t| split_part(&t.title)).collect();
+    let dir = |i: usize| Path::new(&tracks[i].file_path).parent();
+
+    let mut groups = Vec::new();
+    let mut i = 0;
+    while i < tracks.len() {
+        let (base, marker) = &parsed[i];
+        let starts = matches!(marker, None | Some(PartMarker::Numbered(1)));
+        let mut j = i + 1;
+        let mut expected = 2;
+        while starts && j < tracks.len() && dir(j) == dir(i) {
+            let (next_base, next_marker) = &parsed[j];
+            let continues = match next_marker {
+                Some(PartMarker::Numbered(n)) => *n == expected,
+                Some(PartMarker::Continued) => true,
+                None => false,
+            };
+            if !continues || !next_base.eq_ignore_ascii_case(base) {
+                break;
+            }
+            expected += 1;
+            j += 1;
+        }
+        if j - i >= 2 {
+            groups.push(DetectedGroup {
+                title: base.clone(),
+                track_ids: tracks[i..j].iter().map(|t| t.track_id).collect(),
+            });
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+    groups
+}
+
+/// Merge the parts' score inputs into one virtual analysis.
+///
+/// Durations and event counts add up; extremes take the min/max; keys and energy
+/// shape come from the longest part; everything else is duration-weighted.
+pub fn merge_analyses(parts: &[&NewAnalysis]) -> NewAnalysis {
+    let weights: Vec<f64> = parts
+        .iter()
+        .map(|p| p.duration.unwrap_or(0.0).max(0.0))
+        .collect();
+    let wmean = |f: fn(&NewAnalysis) -> Option<f64>| -> Option<f64> {
+        let (sum, weight) = parts
+            .iter()
+            .zip(&weights)
+            .filter_map(|(p, &w)| f(p).map(|v| (v * w, w)))
+            .fold((0.0, 0.0), |(s, tw), (v, w)| (s + v, tw + w));
+        if weight > 0.0 {
+            Some(sum / weight)
+        } else {
+            parts.iter().find_map(|p| f(p))
+        }
+    };
+    let sum = |f: fn(&NewAnalysis) -> Option<i32>| -> Option<i32> {
+        parts
+            .iter()
+            .filter_map(|p| f(p))
+            .fold(None, |acc, v| Some(acc.unwrap_or(0) + v))
+    };
+    let extreme = |f: fn(&NewAnalysis) -> Option<f64>, max: bool| -> Option<f64> {
+        parts
+            .iter()
+            .filter_map(|p| f(p))
+            .reduce(|a, b| if max { a.max(b) } else { a.min(b) })
+    };
+    let longest = parts
+        .iter()
+        .zip(&weights)
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(p, _)| *p);
+
+    NewAnalysis {
+        track_id: parts.first().map(|p| p.track_id).unwrap_or(0),
+        duration: parts
+            .iter()
+            .filter_map(|p| p.duration)
+            .fold(None, |acc, d| Some(acc.unwrap_or(0.0) + d)),
+        rms_level: wmean(|a| a.rms_level),
+        lufs_integrated: wmean(|a| a.lufs_integrated),
+        spectral_centroid_mean: wmean(|a| a.spectral_centroid_mean),
+        spectral_centroid_std: wmean(|a| a.spectral_centroid_std),
+        spectral_flux_mean: wmean(|a| a.spectral_flux_mean),
+        spectral_flux_std: wmean(|a| a.spectral_flux_std),
+        dynamic_range: extreme(|a| a.dynamic_range, true),
+        loudness_range: extreme(|a| a.loudness_range, true),
+        onset_count: sum(|a| a.onset_count),
+        beat_count: sum(|a| a.beat_count),
+        tempo_bpm: wmean(|a| a.tempo_bpm),
+        tempo_bpm_corrected: wmean(|a| a.tempo_bpm_corrected),
+        tempo_stability: wmean(|a| a.tempo_stability),
+        coherence_score: wmean(|a| a.coherence_score),
+        pitch_range_low: extreme(|a| a.pitch_range_low, false),
+        pitch_range_high: extreme(|a| a.pitch_range_high, true),
+        pitch_range_low_corrected: extreme(|a| a.pitch_range_low_corrected, false),
+        pitch_range_high_corrected: extreme(|a| a.pitch_range_high_corrected, true),
+        harmonic_complexity: wmean(|a| a.harmonic_complexity),
+        key_confidence: wmean(|a| a.key_confidence),
+        key_alternatives_count: parts.iter().filter_map(|p| p.key_alternatives_count).max(),
+        chord_count: sum(|a| a.chord_count),
+        tonality: wmean(|a| a.tonality),
+        energy_level: wmean(|a| a.energy_level),
+        estimated_key: longest.and_then(|p| p.estimated_key.clone()),
+        energy_shape: longest.and_then(|p| p.energy_shape.clone()),
+        peak_energy: extreme(|a| a.peak_energy, true),
+        energy_variance: wmean(|a| a.energy_variance),
+        tension_build_count: sum(|a| a.tension_build_count),
+        tension_release_count: sum(|a| a.tension_release_count),
+        repetition_similarity: wmean(|a| a.repetition_similarity),
+        solo_section_count: sum(|a| a.solo_section_count),
+        transition_count: sum(|a| a.transition_count),
+        segment_count: sum(|a| a.segment_count),
+        spectral_flatness_std: wmean(|a| a.spectral_flatness_std),
+        pitch_confidence_mean: wmean(|a| a.pitch_confidence_mean),
+        mode_clarity: wmean(|a| a.mode_clarity),
+        crest_factor: wmean(|a| a.crest_factor),
+        pitch_stability: wmean(|a| a.pitch_stability),
+        sub_band_bass_mean: wmean(|a| a.sub_band_bass_mean),
+        sub_band_bass_std: wmean(|a| a.sub_band_bass_std),
+        zcr_mean: wmean(|a| a.zcr_mean),
+        zcr_std: wmean(|a| a.zcr_std),
+        roughness_mean: wmean(|a| a.roughness_mean),
+        spectral_crest_mean: wmean(|a| a.spectral_crest_mean),
+        onset_strength_mean: wmean(|a| a.onset_strength_mean),
+        harmonic_percussive_ratio: wmean(|a| a.harmonic_percussive_ratio),
+        chromagram_entropy: wmean(|a| a.chromagram_entropy),
+        major_frame_ratio: wmean(|a| a.major_frame_ratio),
+        major_chord_ratio: wmean(|a| a.major_chord_ratio),
+        dynamics_entropy: wmean(|a| a.dynamics_entropy),
+        dynamics_slope: wmean(|a| a.dynamics_slope),
+        dynamics_peak_count: sum(|a| a.dynamics_peak_count),
+        key_change_count: sum(|a| a.key_change_count),
+        rhythmic_periodicity_strength: wmean(|a| a.rhythmic_periodicity_strength),
+        ..Default::default()
+    }
+}
+
+/// Re-detect split jams across the library, score each group, and replace the
+/// `track_groups` table. Returns the number of groups stored.
+pub fn rebuild_track_groups(db: &Database) -> crate::db::Result<usize> {
+    let fingerprint = db.chain_input_fingerprint()?;
+    let detected = detect_groups(&db.get_group_candidates()?);
+
+    let mut analyses: HashMap<i64, NewAnalysis> = db
+        .get_analyses_for_rescore()?
+        .into_iter()
+        .map(|a| (a.track_id, a))
+        .collect();
+    let bands = db.track_bands()?;
+
+    let mut scored = Vec::with_capacity(detected.len());
+    for group in detected {
+        let parts: Vec<&NewAnalysis> = group
+            .track_ids
+            .iter()
+            .filter_map(|id| analyses.get(id))
+            .collect();
+        if parts.len() != group.track_ids.len() {
+            continue;
+        }
+        let mut merged = merge_analyses(&parts);
+
+        // Lay the parts' segments end to end so arcs can span the file boundary
+        let mut segments = Vec::new();
+        let mut offset = 0.0;
+        for part in &parts {
+            for (start, energy) in db.get_segment_energies(part.track_id)? {
+                segments.push((start + offset, energy));
+            }
+            offset += part.duration.unwrap_or(0.0);
+        }
+        let segments = if segments.is_empty() {
+            None
+        } else {
+            Some(segments.as_slice())
+        };
+        jam_metrics::compute_jam_scores_from_scalars(&mut merged, segments);
+        let band = bands.get(&group.track_ids[0]).map(String::as_str);
+        jam_metrics::adjust_for_band(&mut merged, band);
+        scored.push((group, merged));
+    }
+    analyses.clear();
+
+    db.store_track_groups(&scored, &fingerprint)
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Analyzed, non-garbage tracks in playing order (directory, disc, track, path).
+    pub fn get_group_candidates(&self) -> crate::db::Result<Vec<GroupCandidate>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT t.id, t.file_path, COALESCE(t.parsed_title, t.title, ''),
+                    COALESCE(t.parsed_date, t.date, '?'),
+                    t.parsed_disc, t.parsed_track
+             FROM tracks t
+             JOIN analysis_results a ON a.track_id = t.id
+             WHERE {NOT_GARBAGE}"
+        ))?;
+        let mut rows: Vec<(GroupCandidate, Option<i32>, Option<i32>)> = stmt
+            .query_map([], |row| {
+                Ok((
+                    GroupCandidate {
+                        track_id: row.get(0)?,
+                        file_path: row.get(1)?,
+                        title: row.get(2)?,
+                        date: row.get(3)?,
+                    },
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows.sort_by(|a, b| {
+            let dir = |c: &GroupCandidate| Path::new(&c.file_path).parent().map(|p| p.to_owned());
+            dir(&a.0)
+                .cmp(&dir(&b.0))
+                .then(a.1.cmp(&b.1))
+                .then(a.2.cmp(&b.2))
+                .then(a.0.file_path.cmp(&b.0.file_path))
+        });
+        Ok(rows.into_iter().map(|(c, _, _)| c).collect())
+    }
+
+    /// Replace all track groups with freshly scored ones.
+    pub fn store_track_groups(
+        &self,
+        groups: &[(DetectedGroup, NewAnalysis)],
+        fingerprint: &str,
+    ) -> crate::db::Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM track_group_members", [])?;
+        tx.execute("DELETE FROM track_groups", [])?;
+        {
+            let mut group_stmt = tx.prepare_cached(
+                "INSERT INTO track_groups (title, date, part_count, first_track_id, duration,
+                    estimated_key, tempo_bpm, has_cut,
+                    energy_score, intensity_score, groove_score, improvisation_score,
+                    tightness_score, build_quality_score, exploratory_score,
+                    transcendence_score, valence_score, arousal_score)
+                 SELECT ?1, COALESCE(t.parsed_date, t.date, '?'), ?2, ?3, ?4, ?5, ?6,
+                    (SELECT COALESCE(MAX(has_cut), 0) FROM analysis_results
+                     WHERE track_id IN (SELECT value FROM json_each(?7))),
+                    ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17
+                 FROM tracks t WHERE t.id = ?3",
+            )?;
+            let mut member_stmt = tx.prepare_cached(
+                "INSERT INTO track_group_members (group_id, part, track_id) VALUES (?1, ?2, ?3)",
+            )?;
+            for (group, a) in groups {
+                group_stmt.execute(params![
+                    group.title,
+                    group.track_ids.len() as i64,
+                    group.track_ids[0],
+                    a.duration.unwrap_or(0.0),
+                    a.estimated_key,
+                    a.tempo_bpm_corrected.or(a.tempo_bpm),
+                    serde_json::to_string(&group.track_ids).unwrap_or_default(),
+                    a.energy_score,
+                    a.intensity_score,
+                    a.groove_score,
+                    a.improvisation_score,
+                    a.tightness_score,
+                    a.build_quality_score,
+                    a.exploratory_score,
+                    a.transcendence_score,
+                    a.valence_score,
+                    a.arousal_score,
+                ])?;
+                let group_id = tx.last_insert_rowid();
+                for (part, track_id) in group.track_ids.iter().enumerate() {
+                    member_stmt.execute(params![group_id, part as i64 + 1, track_id])?;
+                }
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO track_group_state (id, fingerprint, built_at)
+             VALUES (1, ?1, datetime('now'))",
+            params![fingerprint],
+        )?;
+        tx.commit()?;
+        Ok(groups.len())
+    }
+
+    /// True if the groups were built from the current analysis and titles.
+    pub fn track_groups_are_fresh(&self) -> crate::db::Result<bool> {
+        let stored: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT fingerprint FROM track_group_state WHERE id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        let current = self.chain_input_fingerprint()?;
+        Ok(stored.as_deref() == Some(current.as_str()))
+    }
+
+    /// Stored groups with their parts, optionally filtered by song and/or date.
+    pub fn get_track_groups(
+        &self,
+        song: Option<&str>,
+        date: Option<&str>,
+    ) -> crate::db::Result<Vec<TrackGroup>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT g.id, g.title, g.date, g.duration / 60.0, g.estimated_key, g.tempo_bpm,
+                    COALESCE(g.energy_score, 0), COALESCE(g.intensity_score, 0),
+                    COALESCE(g.groove_score, 0), COALESCE(g.improvisation_score, 0),
+                    COALESCE(g.tightness_score, 0), COALESCE(g.build_quality_score, 0),
+                    COALESCE(g.exploratory_score, 0), COALESCE(g.transcendence_score, 0),
+                    COALESCE(g.valence_score, 0), COALESCE(g.arousal_score, 0),
+                    t.file_path, COALESCE(t.parsed_band, t.artist),
+                    COALESCE(t.parsed_set, t.set_name), {STUDIO_ALBUM}
+             FROM track_groups g
+             JOIN tracks t ON t.id = g.first_track_id
+             WHERE (?1 IS NULL OR g.title LIKE '%' || ?1 || '%')
+               AND (?2 IS NULL OR g.date = ?2)
+             ORDER BY g.date, g.id"
+        ))?;
+        let mut groups: Vec<(i64, TrackGroup)> = stmt
+            .query_map(params![song, date], |row| {
+                Ok((
+                    row.get(0)?,
+                    TrackGroup {
+                        combined: TrackScore {
+                            title: row.get(1)?,
+                            date: row.get(2)?,
+                            duration_min: row.get(3)?,
+                            key: row.get(4)?,
+                            tempo: row.get(5)?,
+                            energy: row.get(6)?,
+                            intensity: row.get(7)?,
+                            groove: row.get(8)?,
+                            improvisation: row.get(9)?,
+                            tightness: row.get(10)?,
+                            build_quality: row.get(11)?,
+                            exploratory: row.get(12)?,
+                            transcendence: row.get(13)?,
+                            valence: row.get(14)?,
+                            arousal: row.get(15)?,
+                            file_path: row.get(16)?,
+                            band: row.get(17)?,
+                            set: row.get(18)?,
+                            album: row.get(19)?,
+                        },
+                        parts: Vec::new(),
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let index: HashMap<i64, usize> = groups
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, i))
+            .collect();
+        let sql = format!(
+            "SELECT {TRACK_SCORE_SELECT}, m.group_id
+             FROM track_group_members m
+             JOIN tracks t ON t.id = m.track_id
+             JOIN analysis_results a ON a.track_id = t.id
+             ORDER BY m.group_id, m.part"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(19)?, map_track_score(row)?))
+        })?;
+        for row in rows {
+            let (group_id, track) = row?;
+            if let Some(&i) = index.get(&group_id) {
+                groups[i].1.parts.push(track);
+            }
+        }
+        Ok(groups.into_iter().map(|(_, g)| g).collect())
+    }
+
+    /// The tracks to lay end to end when displaying `track_id`: every part of its
+    /// group (with durations in seconds), or just the track itself.
+    pub fn get_group_parts(&self, track_id: i64) -> crate::db::Result<Vec<(i64, f64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.track_id, COALESCE(a.duration, 0)
+             FROM track_group_members m
+             JOIN analysis_results a ON a.track_id = m.track_id
+             WHERE m.group_id = (SELECT group_id FROM track_group_members WHERE track_id = ?1)
+             ORDER BY m.part",
+        )?;
+        let parts = stmt
+            .query_map(params![track_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<(i64, f64)>, _>>()?;
+        if parts.is_empty() {
+            let duration: f64 = self.conn.query_row(
+                "SELECT COALESCE(duration, 0) FROM analysis_results WHERE track_id = ?1",
+                params![track_id],
+                |row| row.get(0),
+            )?;
+            return Ok(vec![(track_id, duration)]);
+        }
+        Ok(parts)
+    }
+
+    /// Like `query_top`, but grouped parts are replaced by their group's combined row.
+    pub fn query_top_merged(
+        &self,
+        score_column: &str,
+        limit: usize,
+        filter: &TopFilter,
+    ) -> crate::db::Result<Vec<TrackScore>> {
+        if !SCORE_COLUMNS.contains(&score_column) {
+            return Ok(vec![]);
+        }
+        let mut track_where = format!(
+            "{NOT_GARBAGE} AND a.track_id NOT IN (SELECT track_id FROM track_group_members)"
+        );
+        // Live/quality filters on a group apply to its first part
+        let mut group_where = NOT_GARBAGE.to_string();
+        if filter.live_only {
+            track_where += &format!(" AND {LIVE_ONLY}");
+            group_where += &format!(" AND {LIVE_ONLY}");
+        }
+        if !filter.include_cuts {
+            track_where += &format!(" AND {NO_CUTS}");
+            group_where += " AND g.has_cut = 0";
+        }
+        // Groups are formed from titles, so only single tracks can be untitled
+        if !filter.include_untitled {
+            track_where += &format!(" AND NOT ({MISSING_TITLE})");
+        }
+        if filter.song.is_some() {
+            track_where += " AND (t.parsed_title LIKE ?1 OR t.title LIKE ?1)";
+            group_where += " AND g.title LIKE ?1";
+        }
+        // Always bound (?3), so the parameter count doesn't depend on the filters
+        track_where += &format!(" AND (?3 IS NULL OR {SHOW_QUALITY} >= ?3)");
+        group_where += &format!(" AND (?3 IS NULL OR {SHOW_QUALITY} >= ?3)");
+
+        let sql = format!(
+            "SELECT * FROM (
+                SELECT {TRACK_SCORE_SELECT}, a.{score_column} AS rank_value, a.duration AS secs
+                FROM analysis_results a
+                JOIN tracks t ON t.id = a.track_id
+                WHERE {track_where}
+                UNION ALL
+                SELECT g.title || ' [' || g.part_count || ' parts]', g.date, g.duration / 60.0,
+                    g.estimated_key, g.tempo_bpm,
+                    COALESCE(g.energy_score, 0), COALESCE(g.intensity_score, 0),
+                    COALESCE(g.groove_score, 0), COALESCE(g.improvisation_score, 0),
+                    COALESCE(g.tightness_score, 0), COALESCE(g.build_quality_score, 0),
+                    COALESCE(g.exploratory_score, 0), COALESCE(g.transcendence_score, 0),
+                    COALESCE(g.valence_score, 0), COALESCE(g.arousal_score, 0),
+                    t.file_path, COALESCE(t.parsed_band, t.artist),
+                    COALESCE(t.parsed_set, t.set_name), {STUDIO_ALBUM},
+                    g.{score_column}, g.duration
+                FROM track_groups g
+                JOIN tracks t ON t.id = g.first_track_id
+                WHERE {group_where}
+             )
+             WHERE rank_value IS NOT NULL AND (?2 IS NULL OR secs >= ?2)
+             ORDER BY rank_value DESC
+             LIMIT {limit} OFFSET {offset}",
+            offset = filter.offset
+        );
+        let pattern = filter.song.map(|s| format!("%{s}%"));
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(
+                params![pattern, filter.min_duration_secs, filter.min_show_quality],
+                map_track_score,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(titles: &[(&str, &str)]) -> Vec<GroupCandidate> {
+        titles
+            .iter()
+            .enumerate()
+            .map(|(i, (dir, title))| GroupCandidate {
+                track_id: i as i64 + 1,
+                file_path: format!("/music/{dir}/t{:02}.flac", i + 1),
+                title: title.to_string(),
+                date: "1972-08-27".into(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_part() {
+        let numbered = |n| Some(PartMarker::Numbered(n));
+        assert_eq!(
+            split_part("Dark Star (1)"),
+            ("Dark Star".into(), numbered(1))
+        );
+        assert_eq!(
+            split_part("Playin' in the Band Part 2 ->"),
+            ("Playin' in the Band".into(), numbered(2))
+        );
+        assert_eq!(split_part("Drums [Pt. II]"), ("Drums".into(), numbered(2)));
+        assert_eq!(
+            split_part("The Other One (cont.)"),
+            ("The Other One".into(), Some(PartMarker::Continued))
+        );
+        assert_eq!(split_part("Morning Dew"), ("Morning Dew".into(), None));
+        // A trailing number that isn't a part marker
+        assert_eq!(
+            split_part("Fire on the Mountain 2"),
+            ("Fire on the Mountain 2".into(), None)
+        );
+    }
+
+    #[test]
+    fn test_detect_groups() {
+        let tracks = candidates(&[
+            ("gd72-08-27", "Dark Star (1)"),
+            ("gd72-08-27", "Dark Star (2) ->"),
+            ("gd72-08-27", "El Paso"),
+            ("gd72-08-27", "The Other One"),
+            ("gd72-08-27", "The Other One (cont.)"),
+            ("gd72-08-27", "Sing Me Back Home"),
+            // Part 2 without part 1: nothing to join
+            ("gd72-08-27", "Playin' Part 2"),
+            // Same title across recordings isn't a split
+            ("gd72-08-27", "Morning Dew (1)"),
+            ("gd72-08-27-aud", "Morning Dew (2)"),
+        ]);
+        let groups = detect_groups(&tracks);
+        assert_eq!(
+            groups,
+            vec![
+                DetectedGroup {
+                    title: "Dark Star".into(),
+                    track_ids: vec![1, 2]
+                },
+                DetectedGroup {
+                    title: "The Other One".into(),
+                    track_ids: vec![4, 5]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_analyses() {
+        let a = NewAnalysis {
+            track_id: 1,
+            duration: Some(600.0),
+            rms_level: Some(0.1),
+            onset_count: Some(100),
+            peak_energy: Some(0.5),
+            estimated_key: Some("A".into()),
+            ..Default::default()
+        };
+        let b = NewAnalysis {
+            track_id: 2,
+            duration: Some(1200.0),
+            rms_level: Some(0.4),
+            onset_count: Some(300),
+            peak_energy: Some(0.9),
+            estimated_key: Some("E".into()),
+            ..Default::default()
+        };
+        let m = merge_analyses(&[&a, &b]);
+        assert_eq!(m.track_id, 1);
+        assert_eq!(m.duration, Some(1800.0));
+        assert!((m.rms_level.unwrap() - 0.3).abs() < 1e-9);
+        assert_eq!(m.onset_count, Some(400));
+        assert_eq!(m.peak_energy, Some(0.9));
+        assert_eq!(m.estimated_key.as_deref(), Some("E"));
+        assert_eq!(m.tempo_bpm, None);
+    }
+
+    #[test]
+    fn test_empty_library_queries() {
+        let db = Database::open_in_memory().unwrap();
+        assert_eq!(rebuild_track_groups(&db).unwrap(), 0);
+        assert!(db.track_groups_are_fresh().unwrap());
+        assert!(db.get_track_groups(None, None).unwrap().is_empty());
+        assert!(
+            db.query_top_merged(
+                "groove_score",
+                10,
+                &TopFilter {
+                    song: Some("Dark"),
+                    min_duration_secs: Some(60.0),
+                    live_only: true,
+                    min_show_quality: Some(0.5),
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+            .is_empty()
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Virtual tracks: songs inside a single continuous recording.
+//!
+//! `split-songs` runs song boundary detection (`analyzer::songsplit`) over long
+//! unsplit files — usually audience transfers with one file per set — and
+//! stores each proposed song as a time range of the parent file. Nothing is
+//! re-encoded: the ranges can be written out as a CUE sheet for a player or a
+//! splitter like `shnsplit` to use.
+
+use crate::analyzer::songsplit::{SongBoundary, song_spans};
+use crate::db::Database;
+use crate::db::models::Track;
+use std::io::Write;
+
+/// CD frames per second, the resolution of CUE `INDEX` times.
+const CUE_FRAMES_PER_SEC: f64 = 75.0;
+
+/// One proposed song within a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualTrack {
+    /// 1-based position in the recording.
+    pub song_index: i32,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// Strength of the split that starts this song; `None` for the first.
+    pub boundary_score: Option<f64>,
+    /// A pause or applause was heard at the split (otherwise a segue).
+    pub after_break: bool,
+    pub title: Option<String>,
+}
+
+impl VirtualTrack {
+    pub fn duration(&self) -> f64 {
+        self.end_time - self.start_time
+    }
+
+    /// Stored title, else "Song N".
+    pub fn display_title(&self) -> String {
+        self.title
+            .clone()
+            .unwrap_or_else(|| format!("Song {}", self.song_index))
+    }
+}
+
+/// A long recording to split.
+#[derive(Debug, Clone)]
+pub struct SplitCandidate {
+    pub track: Track,
+    /// Tagged duration, if known.
+    pub duration_secs: Option<f64>,
+    pub venue: Option<String>,
+}
+
+/// Turn split points into songs covering the whole recording.
+pub fn from_boundaries(boundaries: &[SongBoundary], duration: f64) -> Vec<VirtualTrack> {
+    song_spans(boundaries, duration)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start_time, end_time))| {
+            let split = i.checked_sub(1).map(|b| &boundaries[b]);
+            VirtualTrack {
+                song_index: i as i32 + 1,
+                start_time,
+                end_time,
+                boundary_score: split.map(|b| b.score),
+                after_break: split.is_some_and(|b| b.has_break),
+                title: None,
+            }
+        })
+        .collect()
+}
+
+/// Write a CUE sheet for a recording's songs. The `FILE` line names the
+/// recording by file name, so the sheet belongs next to it.
+pub fn write_cue(
+    recording: &SplitCandidate,
+    songs: &[VirtualTrack],
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    let track = &recording.track;
+    if let Some(performer) = track.parsed_band.as_ref().or(track.artist.as_ref()) {
+        writeln!(out, "PERFORMER {}", cue_string(performer))?;
+    }
+    let title: Vec<&str> = [track.parsed_date.as_deref(), recording.venue.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect();
+    if !title.is_empty() {
+        writeln!(out, "TITLE {}", cue_string(&title.join(" ")))?;
+    }
+    let file_type = if track.format.eq_ignore_ascii_case("mp3") {
+        "MP3"
+    } else {
+        "WAVE"
+    };
+    writeln!(
+        out,
+        "FILE {} {file_type}",
+        cue_string(crate::paths::file_name(&track.file_path))
+    )?;
+    for song in songs {
+        writeln!(out, "  TRACK {:02} AUDIO", song.song_index)?;
+        writeln!(out, "    TITLE {}", cue_string(&song.display_title()))?;
+        writeln!(out, "    INDEX 01 {}", cue_time(song.start_time))?;
+    }
+    Ok(())
+}
+
+/// `MM:SS:FF` at 75 frames per second. Minutes run past 99 for long sets,
+/// which CUE readers accept.
+fn cue_time(secs: f64) -> String {
+    let frames = (secs.max(0.0) * CUE_FRAMES_PER_SEC).round() as u64;
+    let per_min = CUE_FRAMES_PER_SEC as u64 * 60;
+    format!(
+        "{:02}:{:02}:{:02}",
+        frames / per_min,
+        frames % per_min / CUE_FRAMES_PER_SEC as u64,
+        frames % CUE_FRAMES_PER_SEC as u64
+    )
+}
+
+/// Double-quoted CUE string. The format has no escape for `"`, so it becomes `'`.
+fn cue_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "'"))
+}
+
+// ── Database query support ──────────────────────────────────────────────
+
+impl Database {
+    /// Recordings at least `min_secs` long. Already-split ones are skipped
+    /// unless `include_split`.
+    pub fn split_candidates(
+        &self,
+        min_secs: f64,
+        include_split: bool,
+    ) -> crate::db::Result<Vec<SplitCandidate>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.file_path, t.format, t.artist, t.parsed_band, t.parsed_date,
+                    t.duration_secs, t.parsed_venue
+             FROM tracks t
+             WHERE t.duration_secs >= ?1
+               AND (?2 OR NOT EXISTS (SELECT 1 FROM virtual_tracks v WHERE v.track_id = t.id))
+             ORDER BY t.file_path",
+        )?;
+        let rows = stmt
+            .query_map(
+                rusqlite::params![min_secs, include_split],
+                split_candidate_row,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// A specific recording to split, whatever its length.
+    pub fn split_candidate(&self, track_id: i64) -> crate::db::Result<Option<SplitCandidate>> {
+        use rusqlite::OptionalExtension;
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT t.id, t.file_path, t.format, t.artist, t.parsed_band, t.parsed_date,
+                        t.duration_secs, t.parsed_venue
+                 FROM tracks t WHERE t.id = ?1",
+                [track_id],
+                split_candidate_row,
+            )
+            .optional()?)
+    }
+
+    /// Stored songs for a recording, in order.
+    pub fn virtual_tracks(&self, track_id: i64) -> crate::db::Result<Vec<VirtualTrack>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT song_index, start_time, end_time, boundary_score, after_break, title
+             FROM virtual_tracks WHERE track_id = ?1
+             ORDER BY song_index",
+        )?;
+        let rows = stmt
+            .query_map([track_id], |row| {
+                Ok(VirtualTrack {
+                    song_index: row.get(0)?,
+                    start_time: row.get(1)?,
+                    end_time: row.get(2)?,
+                    boundary_score: row.get(3)?,
+                    after_break: row.get(4)?,
+                    title: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Replace a recording's songs with a new split.
+    pub fn replace_virtual_tracks(
+        &self,
+        track_id: i64,
+        songs: &[VirtualTrack],
+    ) -> crate::db::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM virtual_tracks WHERE track_id = ?1", [track_id])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO virtual_tracks
+                    (track_id, song_index, start_time, end_time, boundary_score, after_break, title)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for s in songs {
+                stmt.execute(rusqlite::params![
+                    track_id,
+                    s.song_index,
+                    s.start_time,
+                    s.end_time,
+                    s.boundary_score,
+                    s.after_break,
+                    s.title,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn split_candidate_row(row: &rusqlite::Row) -> rusqlite::Result<SplitCandidate> {
+    Ok(SplitCandidate {
+        track: Track {
+            id: row.get(0)?,
+            file_path: row.get(1)?,
+            format: row.get(2)?,
+            artist: row.get(3)?,
+            parsed_band: row.get(4)?,
+            parsed_date: row.get(5)?,
+        },
+        duration_secs: row.get(6)?,
+        venue: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewTrack;
+    use crate::db::queries::tests::new_track;
+
+    fn recording(path: &str, duration_secs: f64) -> NewTrack {
+        NewTrack {
+            parsed_band: Some("Grateful Dead".to_string()),
+            parsed_date: Some("1977-05-08".to_string()),
+            parsed_venue: Some("Barton Hall".to_string()),
+            duration_secs: Some(duration_secs),
+            ..new_track(path)
+        }
+    }
+
+    fn boundary(time: f64, has_break: bool) -> SongBoundary {
+        SongBoundary {
+            time,
+            score: 0.8,
+            has_break,
+        }
+    }
+
+    #[test]
+    fn test_cue_sheet() {
+        let songs = from_boundaries(&[boundary(301.5, true), boundary(6012.04, false)], 6500.0);
+        assert_eq!(songs.len(), 3);
+        assert_eq!(songs[0].boundary_score, None);
+        assert!(songs[1].after_break && !songs[2].after_break);
+
+        let candidate = SplitCandidate {
+            track: Track {
+                id: 1,
+                file_path: "/aud/gd77-05-08 set2.flac".into(),
+                format: "flac".into(),
+                artist: None,
+                parsed_band: Some("Grateful Dead".into()),
+                parsed_date: Some("1977-05-08".into()),
+            },
+            duration_secs: Some(6500.0),
+            venue: Some("Barton \"Hall\"".into()),
+        };
+        let mut buf = Vec::new();
+        write_cue(&candidate, &songs, &mut buf).unwrap();
+        let cue = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            cue,
+            "PERFORMER \"Grateful Dead\"\n\
+             TITLE \"1977-05-08 Barton 'Hall'\"\n\
+             FILE \"gd77-05-08 set2.flac\" WAVE\n\
+             \x20 TRACK 01 AUDIO\n    TITLE \"Song 1\"\n    INDEX 01 00:00:00\n\
+             \x20 TRACK 02 AUDIO\n    TITLE \"Song 2\"\n    INDEX 01 05:01:38\n\
+             \x20 TRACK 03 AUDIO\n    TITLE \"Song 3\"\n    INDEX 01 100:12:03\n"
+        );
+    }
+
+    #[test]
+    fn test_store_and_candidates() {
+        let db = Database::open_in_memory().unwrap();
+        let long = db
+            .upsert_track(&recording("/aud/set1.flac", 5400.0))
+            .unwrap();
+        db.upsert_track(&recording("/aud/d1t01.flac", 420.0))
+            .unwrap();
+
+        let found = db.split_candidates(2400.0, false).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].track.id, long);
+        assert_eq!(found[0].venue.as_deref(), Some("Barton Hall"));
+
+        let songs = from_boundaries(&[boundary(600.0, true)], 5400.0);
+        db.replace_virtual_tracks(long, &songs).unwrap();
+        assert_eq!(db.virtual_tracks(long).unwrap(), songs);
+        // Already split: skipped unless asked
+        assert!(db.split_candidates(2400.0, false).unwrap().is_empty());
+        assert_eq!(db.split_candidates(2400.0, true).unwrap().len(), 1);
+
+        // A re-split replaces the old songs
+        db.replace_virtual_tracks(long, &from_boundaries(&[], 5400.0))
+            .unwrap();
+        assert_eq!(db.virtual_tracks(long).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,381 @@
+//! Golden-score regression test: scan and analyze a handful of synthesized
+//! fixtures with the full pipeline and compare every jam score (plus tempo, key
+//! and data quality) to the values recorded in `tests/golden/scores.json`.
+//!
+//! Scoring refactors and ferrous-waves upgrades shift scores library-wide; this
+//! makes such shifts show up as a failing test instead of silently reordering
+//! every ranking. The engine is slow in debug builds, so the test is ignored by
+//! default:
+//!
+//! ```text
+//! cargo test --release --test golden_scores -- --ignored
+//! ```
+//!
+//! When a change is meant to move scores, re-record the golden values with
+//! `SETBREAK_BLESS=1` and commit the updated JSON alongside the change.
+
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use setbreak::analyzer::pipeline::PipelineConfig;
+use setbreak::analyzer::{AnalyzeMode, analyze_tracks};
+use setbreak::config::AppConfig;
+use setbreak::db::Database;
+use setbreak::progress::SilentProgress;
+
+const SAMPLE_RATE: u32 = 44_100;
+const FIXTURE_SECS: f64 = 45.0;
+
+/// Allowed drift before a value counts as changed.
+const SCORE_TOLERANCE: f64 = 1.0;
+const TEMPO_TOLERANCE: f64 = 1.0;
+const DURATION_TOLERANCE: f64 = 0.05;
+
+/// Everything compared for one fixture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Golden {
+    data_quality: String,
+    duration: f64,
+    key: Option<String>,
+    tempo: Option<f64>,
+    /// Score name (as in `setbreak::scores::SCORES`) → value.
+    scores: BTreeMap<String, f64>,
+}
+
+fn golden_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/scores.json")
+}
+
+// ---------------------------------------------------------------------------
+// Fixtures
+// ---------------------------------------------------------------------------
+
+/// Deterministic white noise in -1..1 (xorshift, so fixtures never depend on a
+/// random seed or crate version).
+struct Noise(u64);
+
+impl Noise {
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+}
+
+fn midi_hz(note: f64) -> f64 {
+    440.0 * 2f64.powf((note - 69.0) / 12.0)
+}
+
+/// A decaying percussive hit `since` seconds after its onset.
+fn hit(since: f64, hz: f64, decay: f64) -> f64 {
+    (TAU * hz * since).sin() * (-since / decay).exp()
+}
+
+/// Sample value at a time in seconds.
+type Generator = Box<dyn FnMut(f64) -> f64>;
+
+fn fixture(
+    name: &'static str,
+    generator: impl FnMut(f64) -> f64 + 'static,
+) -> (&'static str, Generator) {
+    (name, Box::new(generator))
+}
+
+/// The fixtures, each a name and its generator.
+fn fixtures() -> Vec<(&'static str, Generator)> {
+    vec![
+        // Sustained A major triad with a slow swell: calm, tonal, no beat
+        fixture("drone", |t| {
+            let swell = 0.6 + 0.4 * (TAU * t / 30.0).sin();
+            let chord: f64 = [57.0, 61.0, 64.0, 69.0]
+                .iter()
+                .map(|&n| (TAU * midi_hz(n) * t).sin())
+                .sum();
+            0.12 * swell * chord
+        }),
+        // 120 BPM kick on the beat, hi-hat on the off-beat, bass in E
+        fixture("groove", {
+            let mut noise = Noise(0x9E37_79B9_7F4A_7C15);
+            move |t| {
+                let beat = 0.5;
+                let since_kick = t % beat;
+                let since_hat = (t + beat / 2.0) % beat;
+                let bass_notes = [40.0, 40.0, 43.0, 45.0];
+                let bar_beat = (t / beat) as usize % bass_notes.len();
+                let bass = (TAU * midi_hz(bass_notes[bar_beat]) * t).sin();
+                0.5 * hit(since_kick, 55.0, 0.08)
+                    + 0.15 * noise.next() * (-since_hat / 0.02).exp()
+                    + 0.25 * bass
+            }
+        }),
+        // Crescendo: quiet pads to loud, dense pulses accelerating from 90 to 150 BPM
+        fixture("build", {
+            let mut noise = Noise(0xD1B5_4A32_D192_ED03);
+            move |t| {
+                let progress = t / FIXTURE_SECS;
+                let bpm = 90.0 + 60.0 * progress;
+                // Beat phase is the integral of the tempo ramp
+                let beats = (90.0 * t + 30.0 * t * t / FIXTURE_SECS) / 60.0;
+                let since = beats.fract() * 60.0 / bpm;
+                let level = 0.05 + 0.6 * progress * progress;
+                let pad: f64 = [50.0, 57.0, 62.0, 66.0]
+                    .iter()
+                    .map(|&n| (TAU * midi_hz(n) * t).sin())
+                    .sum();
+                level * (0.2 * pad + 0.6 * hit(since, 70.0, 0.1) + 0.2 * noise.next())
+            }
+        }),
+        // 100 BPM arpeggios in C major, modulating to E major halfway through
+        fixture("modulate", |t| {
+            let step = 60.0 / 100.0 / 2.0;
+            let root = if t < FIXTURE_SECS / 2.0 { 60.0 } else { 64.0 };
+            let arp = [0.0, 4.0, 7.0, 12.0, 7.0, 4.0];
+            let n = (t / step) as usize;
+            let note = root + arp[n % arp.len()];
+            let since = t % step;
+            0.4 * hit(since, midi_hz(note), 0.25) + 0.1 * (TAU * midi_hz(root - 24.0) * t).sin()
+        }),
+        // Mostly quiet noise wash with irregular loud bursts: unstructured
+        fixture("chaos", {
+            let mut noise = Noise(0x2545_F491_4F6C_DD1D);
+            move |t| {
+                let burst_at = [3.1, 7.9, 8.4, 15.2, 21.7, 22.1, 29.5, 36.8, 40.3];
+                let burst: f64 = burst_at
+                    .iter()
+                    .filter(|&&b| t >= b)
+                    .map(|&b| (-(t - b) / 0.4).exp())
+                    .sum();
+                let sweep = (TAU * (200.0 + 40.0 * t) * t).sin();
+                noise.next() * (0.05 + 0.5 * burst.min(1.0)) + 0.1 * sweep
+            }
+        }),
+    ]
+}
+
+/// Write a 16-bit stereo WAV of `generator` sampled over the fixture length.
+fn write_wav(path: &Path, generator: &mut dyn FnMut(f64) -> f64) {
+    let frames = (FIXTURE_SECS * SAMPLE_RATE as f64) as u32;
+    let data_len = frames * 4;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&2u16.to_le_bytes()); // channels
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 4).to_le_bytes());
+    bytes.extend_from_slice(&4u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for i in 0..frames {
+        let sample = generator(i as f64 / SAMPLE_RATE as f64).clamp(-1.0, 1.0);
+        let pcm = ((sample * i16::MAX as f64) as i16).to_le_bytes();
+        bytes.extend_from_slice(&pcm);
+        bytes.extend_from_slice(&pcm);
+    }
+    std::fs::write(path, bytes).unwrap();
+}
+
+// ---------------------------------------------------------------------------
+// Pipeline
+// ---------------------------------------------------------------------------
+
+/// Synthesize the fixtures, scan and analyze them, and read back the results.
+fn run_pipeline() -> BTreeMap<String, Golden> {
+    let dir = std::env::temp_dir().join(format!("setbreak-golden-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, mut generator) in fixtures() {
+        write_wav(&dir.join(format!("{name}.wav")), &mut generator);
+    }
+
+    let mut db = Database::open_in_memory().unwrap();
+    let config = AppConfig::default();
+    let scan = setbreak::scanner::scan(
+        &db,
+        &[dir.to_string_lossy().into_owned()],
+        false,
+        &config,
+        &SilentProgress,
+    )
+    .unwrap();
+    assert_eq!(scan.errors, 0, "fixtures failed to scan");
+
+    let pipeline = PipelineConfig {
+        jobs: 2,
+        decode_jobs: 1,
+        prefetch_bytes: 256 << 20,
+        queue_depth: 2,
+    };
+    let result = analyze_tracks(
+        &mut db,
+        AnalyzeMode::Missing,
+        &pipeline,
+        &config.track_tuning(&[]),
+        None,
+        None,
+        &SilentProgress,
+    )
+    .unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+    assert_eq!(result.failed, 0, "fixtures failed to analyze");
+
+    let score_columns: Vec<String> = setbreak::scores::SCORES
+        .iter()
+        .map(|s| format!("a.{}", s.column))
+        .collect();
+    let sql = format!(
+        "SELECT t.file_path, COALESCE(t.data_quality, 'ok'), COALESCE(a.duration, 0.0),
+                a.estimated_key, COALESCE(a.tempo_bpm_corrected, a.tempo_bpm),
+                {}
+         FROM analysis_results a
+         JOIN tracks t ON t.id = a.track_id",
+        score_columns.join(", ")
+    );
+    let mut stmt = db.conn.prepare(&sql).unwrap();
+    stmt.query_map([], |row| {
+        let path: String = row.get(0)?;
+        let name = Path::new(&path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or(path);
+        let mut scores = BTreeMap::new();
+        for (i, meta) in setbreak::scores::SCORES.iter().enumerate() {
+            let value: Option<f64> = row.get(5 + i)?;
+            scores.insert(meta.name.to_string(), round(value.unwrap_or(0.0)));
+        }
+        let golden = Golden {
+            data_quality: row.get(1)?,
+            duration: round(row.get(2)?),
+            key: row.get(3)?,
+            tempo: row.get::<_, Option<f64>>(4)?.map(round),
+            scores,
+        };
+        Ok((name, golden))
+    })
+    .unwrap()
+    .collect::<Result<_, _>>()
+    .unwrap()
+}
+
+/// Golden values are stored to 0.01, well inside every tolerance.
+fn round(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// Every difference beyond tolerance, as readable lines.
+fn differences(
+    expected: &BTreeMap<String, Golden>,
+    actual: &BTreeMap<String, Golden>,
+) -> Vec<String> {
+    let mut diffs = Vec::new();
+    let close = |a: f64, b: f64, tolerance: f64| (a - b).abs() <= tolerance;
+    for (name, want) in expected {
+        let Some(got) = actual.get(name) else {
+            diffs.push(format!("{name}: not analyzed"));
+            continue;
+        };
+        if got.data_quality != want.data_quality {
+            diffs.push(format!(
+                "{name}: data quality {} -> {}",
+                want.data_quality, got.data_quality
+            ));
+        }
+        if !close(got.duration, want.duration, DURATION_TOLERANCE) {
+            diffs.push(format!(
+                "{name}: duration {} -> {}",
+                want.duration, got.duration
+            ));
+        }
+        if got.key != want.key {
+            diffs.push(format!("{name}: key {:?} -> {:?}", want.key, got.key));
+        }
+        match (want.tempo, got.tempo) {
+            (Some(a), Some(b)) if close(a, b, TEMPO_TOLERANCE) => {}
+            (None, None) => {}
+            (a, b) => diffs.push(format!("{name}: tempo {a:?} -> {b:?}")),
+        }
+        for (score, &want_value) in &want.scores {
+            match got.scores.get(score) {
+                Some(&v) if close(v, want_value, SCORE_TOLERANCE) => {}
+                Some(&v) => diffs.push(format!(
+                    "{name}: {score} {want_value:.2} -> {v:.2} ({:+.2})",
+                    v - want_value
+                )),
+                None => diffs.push(format!("{name}: {score} missing")),
+            }
+        }
+        for score in got.scores.keys().filter(|s| !want.scores.contains_key(*s)) {
+            diffs.push(format!("{name}: new score {score} has no golden value"));
+        }
+    }
+    for name in actual.keys().filter(|n| !expected.contains_key(*n)) {
+        diffs.push(format!("{name}: new fixture has no golden values"));
+    }
+    diffs
+}
+
+#[test]
+#[ignore = "runs the full analysis engine; use --release"]
+fn golden_scores() {
+    let actual = run_pipeline();
+    let path = golden_path();
+
+    if std::env::var_os("SETBREAK_BLESS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let json = serde_json::to_string_pretty(&actual).unwrap();
+        std::fs::write(&path, json + "\n").unwrap();
+        eprintln!(
+            "Recorded golden values for {} fixtures in {}",
+            actual.len(),
+            path.display()
+        );
+        return;
+    }
+
+    let expected: BTreeMap<String, Golden> = match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).unwrap(),
+        Err(_) => panic!(
+            "no golden values at {}; record them with SETBREAK_BLESS=1",
+            path.display()
+        ),
+    };
+    let diffs = differences(&expected, &actual);
+    assert!(
+        diffs.is_empty(),
+        "scores drifted from the golden values (re-record with SETBREAK_BLESS=1 if intended):\n  {}",
+        diffs.join("\n  ")
+    );
+}
+
+#[test]
+fn golden_differences_respect_tolerances() {
+    let golden = |energy: f64, tempo: Option<f64>| Golden {
+        data_quality: "ok".into(),
+        duration: 45.0,
+        key: Some("A major".into()),
+        tempo,
+        scores: BTreeMap::from([("energy".to_string(), energy)]),
+    };
+    let expected = BTreeMap::from([("drone".to_string(), golden(40.0, Some(120.0)))]);
+
+    let within = BTreeMap::from([("drone".to_string(), golden(40.8, Some(120.5)))]);
+    assert!(differences(&expected, &within).is_empty());
+
+    let drifted = BTreeMap::from([
+        ("drone".to_string(), golden(42.0, None)),
+        ("extra".to_string(), golden(10.0, None)),
+    ]);
+    assert_eq!(
+        differences(&expected, &drifted),
+        vec![
+            "drone: tempo Some(120.0) -> None",
+            "drone: energy 40.00 -> 42.00 (+2.00)",
+            "extra: new fixture has no golden values",
+        ]
+    );
+}